@@ -0,0 +1,58 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.crypto;
+
+import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
+
+import org.signal.libsignal.internal.Native;
+import org.signal.libsignal.internal.NativeHandleGuard;
+import org.signal.libsignal.protocol.InvalidKeyException;
+
+public class ChaCha20Poly1305Decryption implements NativeHandleGuard.Owner {
+  public static final int TAG_SIZE_IN_BYTES = 16;
+
+  private long unsafeHandle;
+
+  public ChaCha20Poly1305Decryption(byte[] key, byte[] nonce, byte[] associatedData)
+      throws InvalidKeyException {
+    this.unsafeHandle =
+        filterExceptions(
+            InvalidKeyException.class,
+            () -> Native.ChaCha20Poly1305Decryption_New(key, nonce, associatedData));
+  }
+
+  @Override
+  @SuppressWarnings("deprecation")
+  protected void finalize() {
+    Native.ChaCha20Poly1305Decryption_Destroy(this.unsafeHandle);
+  }
+
+  public long unsafeNativeHandleWithoutGuard() {
+    return this.unsafeHandle;
+  }
+
+  public void decrypt(byte[] plaintext) {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      Native.ChaCha20Poly1305Decryption_Update(guard.nativeHandle(), plaintext, 0, plaintext.length);
+    }
+  }
+
+  public void decrypt(byte[] plaintext, int offset, int length) {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      Native.ChaCha20Poly1305Decryption_Update(guard.nativeHandle(), plaintext, offset, length);
+    }
+  }
+
+  public boolean verifyTag(byte[] tag) {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      boolean tagOk =
+          filterExceptions(() -> Native.ChaCha20Poly1305Decryption_VerifyTag(guard.nativeHandle(), tag));
+      Native.ChaCha20Poly1305Decryption_Destroy(guard.nativeHandle());
+      this.unsafeHandle = 0;
+      return tagOk;
+    }
+  }
+}
@@ -11,6 +11,7 @@ import org.signal.libsignal.internal.Native;
 import org.signal.libsignal.internal.NativeHandleGuard;
 import org.signal.libsignal.internal.NativeTesting;
 import org.signal.libsignal.net.internal.BridgeChatListener;
+import org.signal.libsignal.protocol.ecc.ECPrivateKey;
 import org.signal.libsignal.protocol.util.Pair;
 
 /**
@@ -36,21 +37,25 @@ public class AuthenticatedChatConnection extends ChatConnection {
       final String username,
       final String password,
       final boolean receiveStories,
+      final ECPrivateKey noisePrivateKey,
       ChatConnectionListener chatListener) {
-    return tokioAsyncContext.guardedMap(
-        asyncContextHandle ->
-            connectionManager.guardedMap(
-                connectionManagerHandle ->
-                    Native.AuthenticatedChatConnection_connect(
-                            asyncContextHandle,
-                            connectionManagerHandle,
-                            username,
-                            password,
-                            receiveStories)
-                        .thenApply(
-                            nativeHandle ->
-                                new AuthenticatedChatConnection(
-                                    tokioAsyncContext, nativeHandle, chatListener))));
+    try (NativeHandleGuard noisePrivateKeyGuard = new NativeHandleGuard(noisePrivateKey)) {
+      return tokioAsyncContext.guardedMap(
+          asyncContextHandle ->
+              connectionManager.guardedMap(
+                  connectionManagerHandle ->
+                      Native.AuthenticatedChatConnection_connect(
+                              asyncContextHandle,
+                              connectionManagerHandle,
+                              username,
+                              password,
+                              receiveStories,
+                              noisePrivateKeyGuard.nativeHandle())
+                          .thenApply(
+                              nativeHandle ->
+                                  new AuthenticatedChatConnection(
+                                      tokioAsyncContext, nativeHandle, chatListener))));
+    }
   }
 
   private static final class SetChatLaterListenerBridge extends ListenerBridge {
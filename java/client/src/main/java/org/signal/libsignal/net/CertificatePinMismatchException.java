@@ -0,0 +1,18 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net;
+
+/**
+ * Indicates that the server's certificate chain didn't include any of the keys pinned for it.
+ *
+ * <p>This usually means that something between the device and the server is intercepting TLS
+ * connections.
+ */
+public class CertificatePinMismatchException extends ChatServiceException {
+  public CertificatePinMismatchException(String message) {
+    super(message);
+  }
+}
@@ -0,0 +1,124 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net;
+
+import java.util.Optional;
+import org.signal.libsignal.internal.Native;
+
+/** A single attempt to connect over one route, as reported to a {@link ConnectionAttemptListener}. */
+public final class ConnectionAttempt {
+  public enum Result {
+    SUCCEEDED,
+    /** The attempt failed, but other routes might still work. */
+    FAILED,
+    /** The server rejected the connection, so no other routes were tried. */
+    REJECTED,
+    /**
+     * The attempt was still in progress when another route succeeded or the overall connection
+     * attempt timed out.
+     */
+    ABANDONED,
+  }
+
+  /** The stage at which an attempt failed. */
+  public enum Failure {
+    DNS,
+    TCP,
+    TLS,
+    CERTIFICATE_PIN_MISMATCH,
+    PROXY,
+    TIMEOUT,
+    /** See {@link ConnectionAttempt#getHttpStatus}. */
+    HTTP_STATUS,
+    WEB_SOCKET,
+    OTHER,
+  }
+
+  private final String route;
+  private final IpType ipType;
+  private final long startedAtMillis;
+  private final int durationMillis;
+  private final Optional<Integer> tlsHandshakeCompletedAfterMillis;
+  private final Optional<Integer> websocketUpgradeMillis;
+  private final Result result;
+  private final Optional<Failure> failure;
+  private final Optional<Integer> httpStatus;
+
+  /** Reads the attempt from {@code nativeHandle} and then destroys the handle. */
+  static ConnectionAttempt consumeNativeHandle(long nativeHandle) {
+    try {
+      return new ConnectionAttempt(nativeHandle);
+    } finally {
+      Native.ConnectionAttempt_Destroy(nativeHandle);
+    }
+  }
+
+  private ConnectionAttempt(long nativeHandle) {
+    this.route = Native.ConnectionAttempt_route(nativeHandle);
+    this.ipType = IpType.values()[Native.ConnectionAttempt_ip_version(nativeHandle)];
+    this.startedAtMillis = Native.ConnectionAttempt_started_at(nativeHandle);
+    this.durationMillis = Native.ConnectionAttempt_duration_millis(nativeHandle);
+    this.tlsHandshakeCompletedAfterMillis =
+        optionalMillis(Native.ConnectionAttempt_tls_handshake_completed_after_millis(nativeHandle));
+    this.websocketUpgradeMillis =
+        optionalMillis(Native.ConnectionAttempt_websocket_upgrade_millis(nativeHandle));
+    this.result = Result.values()[Native.ConnectionAttempt_result(nativeHandle)];
+    int failure = Native.ConnectionAttempt_error(nativeHandle);
+    this.failure = failure == 0 ? Optional.empty() : Optional.of(Failure.values()[failure - 1]);
+    int httpStatus = Native.ConnectionAttempt_http_status(nativeHandle);
+    this.httpStatus = httpStatus == 0 ? Optional.empty() : Optional.of(httpStatus);
+  }
+
+  private static Optional<Integer> optionalMillis(int millis) {
+    return millis < 0 ? Optional.empty() : Optional.of(millis);
+  }
+
+  /** A log-safe description of the route. */
+  public String getRoute() {
+    return route;
+  }
+
+  /** The IP version of the address connected to directly (for proxied routes, the proxy's). */
+  public IpType getIpType() {
+    return ipType;
+  }
+
+  /** When the attempt started, in milliseconds since the epoch. */
+  public long getStartedAtMillis() {
+    return startedAtMillis;
+  }
+
+  public int getDurationMillis() {
+    return durationMillis;
+  }
+
+  /**
+   * Time from the start of the attempt until the TLS handshake finished, including connecting over
+   * TCP and through any proxy.
+   */
+  public Optional<Integer> getTlsHandshakeCompletedAfterMillis() {
+    return tlsHandshakeCompletedAfterMillis;
+  }
+
+  /** Time taken by the websocket upgrade request, if the server responded to it. */
+  public Optional<Integer> getWebsocketUpgradeMillis() {
+    return websocketUpgradeMillis;
+  }
+
+  public Result getResult() {
+    return result;
+  }
+
+  /** Present if the result is {@link Result#FAILED} or {@link Result#REJECTED}. */
+  public Optional<Failure> getFailure() {
+    return failure;
+  }
+
+  /** Present if the failure is {@link Failure#HTTP_STATUS}. */
+  public Optional<Integer> getHttpStatus() {
+    return httpStatus;
+  }
+}
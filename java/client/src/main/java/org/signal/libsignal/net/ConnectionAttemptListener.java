@@ -0,0 +1,17 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net;
+
+/**
+ * Receives details of every attempt to connect over a single route.
+ *
+ * <p>Calls are serialized but may happen on any thread, and should return promptly.
+ *
+ * @see Network#setConnectionAttemptListener
+ */
+public interface ConnectionAttemptListener {
+  void onConnectionAttempt(ConnectionAttempt attempt);
+}
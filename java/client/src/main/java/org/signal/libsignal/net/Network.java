@@ -13,6 +13,8 @@ import java.util.function.Consumer;
 import org.signal.libsignal.internal.CompletableFuture;
 import org.signal.libsignal.internal.Native;
 import org.signal.libsignal.internal.NativeHandleGuard;
+import org.signal.libsignal.net.internal.BridgeConnectionAttemptListener;
+import org.signal.libsignal.protocol.ecc.ECPrivateKey;
 
 public class Network {
   public enum Environment {
@@ -93,6 +95,30 @@ public class Network {
     this.connectionManager.setProxy(SIGNAL_TLS_PROXY_SCHEME, host, port, username, null);
   }
 
+  /**
+   * Chooses a proxy (or none) per destination for all new connections, from a PAC-like list of
+   * rules, one per line.
+   *
+   * <p>Each line is a host pattern ({@code *}, a domain, {@code *.suffix}, an IP address, or a CIDR
+   * network) followed by {@code DIRECT}, {@code PROXY host:port}, {@code HTTPS host:port}, {@code
+   * SOCKS host:port} (SOCKS4), {@code SOCKS4 host:port}, or {@code SOCKS5 host:port}. The first
+   * matching rule wins; destinations that match no rule are connected to directly. {@code #}
+   * starts a comment.
+   *
+   * <p>This replaces any proxy set with {@link #setProxy}, and is in turn replaced by calling
+   * {@link #setProxy}, {@link #setInvalidProxy}, or {@link #clearProxy}.
+   *
+   * <p>Existing connections and services will continue with the setting they were created with. (In
+   * particular, changing this setting will not affect any existing {@link ChatConnection
+   * ChatConnections}.)
+   *
+   * @throws IOException if the rules can't be parsed. In that case, all new connections will fail
+   *     until {@link #clearProxy} is called.
+   */
+  public void setProxyRules(String rules) throws IOException {
+    this.connectionManager.setProxyRules(rules);
+  }
+
   /**
    * Refuses to make any new connections until a new proxy configuration is set or {@link
    * #clearProxy} is called.
@@ -131,6 +157,60 @@ public class Network {
     this.connectionManager.setCensorshipCircumventionEnabled(enabled);
   }
 
+  /**
+   * Enables or disables compression for all new chat connections (until changed).
+   *
+   * <p>Existing connections will continue with the setting they were created with. When enabled,
+   * each message is compressed independently.
+   *
+   * <p>Compression is off by default.
+   */
+  public void setChatCompressionEnabled(boolean enabled) {
+    this.connectionManager.setChatCompressionEnabled(enabled);
+  }
+
+  /**
+   * Reports every subsequent attempt to connect over a single route to {@code listener}, e.g. to
+   * track which routes work on the current network.
+   *
+   * <p>Passing {@code null} stops reporting attempts.
+   */
+  public void setConnectionAttemptListener(ConnectionAttemptListener listener) {
+    this.connectionManager.setConnectionAttemptListener(listener);
+  }
+
+  /**
+   * Saves DNS results in files in {@code directory}, so that they can be reused after a restart.
+   *
+   * <p>The directory must already exist. Passing {@code null} stops saving results. Results are
+   * only saved and used while a network identity is set with {@link #setNetworkIdentity}.
+   */
+  public void setDnsCacheDirectory(String directory) {
+    this.connectionManager.setDnsCacheDirectory(directory);
+  }
+
+  /**
+   * Saves which connection routes recently failed in files in {@code directory}, so that they are
+   * still tried last after a restart.
+   *
+   * <p>The directory must already exist. Passing {@code null} stops saving route history.
+   */
+  public void setRouteHistoryDirectory(String directory) {
+    this.connectionManager.setRouteHistoryDirectory(directory);
+  }
+
+  /**
+   * Sets an identifier for the current network, which determines which saved DNS results can be
+   * used.
+   *
+   * <p>This should distinguish networks that might resolve names differently, e.g. a hash of the
+   * Wi-Fi network name. Passing {@code null} stops using saved results until an identity is set
+   * again.
+   */
+  public void setNetworkIdentity(String identity) {
+    this.connectionManager.setNetworkIdentity(identity);
+  }
+
   /**
    * Notifies libsignal that the network has changed.
    *
@@ -228,8 +308,30 @@ public class Network {
       final String password,
       final boolean receiveStories,
       ChatConnectionListener listener) {
+    return connectAuthChat(username, password, receiveStories, null, listener);
+  }
+
+  /**
+   * Like {@link #connectAuthChat(String, String, boolean, ChatConnectionListener)}, but identifies
+   * the device with {@code noisePrivateKey} if the connection goes through a Noise tunnel.
+   *
+   * <p>{@code noisePrivateKey} should be the account's identity key. If it is {@code null}, the
+   * tunnel is anonymous.
+   */
+  public CompletableFuture<AuthenticatedChatConnection> connectAuthChat(
+      final String username,
+      final String password,
+      final boolean receiveStories,
+      final ECPrivateKey noisePrivateKey,
+      ChatConnectionListener listener) {
     return AuthenticatedChatConnection.connect(
-        tokioAsyncContext, connectionManager, username, password, receiveStories, listener);
+        tokioAsyncContext,
+        connectionManager,
+        username,
+        password,
+        receiveStories,
+        noisePrivateKey,
+        listener);
   }
 
   static class ConnectionManager extends NativeHandleGuard.SimpleOwner {
@@ -269,6 +371,12 @@ public class Network {
       }
     }
 
+    private void setProxyRules(String rules) throws IOException {
+      filterExceptions(
+          IOException.class,
+          () -> guardedRunChecked(h -> Native.ConnectionManager_set_proxy_rules(h, rules)));
+    }
+
     private void setInvalidProxy() {
       guardedRun(Native::ConnectionManager_set_invalid_proxy);
     }
@@ -285,6 +393,31 @@ public class Network {
       guardedRun(h -> Native.ConnectionManager_set_censorship_circumvention_enabled(h, enabled));
     }
 
+    private void setChatCompressionEnabled(boolean enabled) {
+      guardedRun(h -> Native.ConnectionManager_set_chat_compression_enabled(h, enabled));
+    }
+
+    private void setConnectionAttemptListener(ConnectionAttemptListener listener) {
+      BridgeConnectionAttemptListener bridgeListener =
+          listener == null
+              ? null
+              : attemptHandle ->
+                  listener.onConnectionAttempt(ConnectionAttempt.consumeNativeHandle(attemptHandle));
+      guardedRun(h -> Native.ConnectionManager_set_connection_attempt_listener(h, bridgeListener));
+    }
+
+    private void setDnsCacheDirectory(String directory) {
+      guardedRun(h -> Native.ConnectionManager_set_dns_cache_directory(h, directory));
+    }
+
+    private void setRouteHistoryDirectory(String directory) {
+      guardedRun(h -> Native.ConnectionManager_set_route_history_directory(h, directory));
+    }
+
+    private void setNetworkIdentity(String identity) {
+      guardedRun(h -> Native.ConnectionManager_set_network_identity(h, identity));
+    }
+
     @Override
     protected void release(final long nativeHandle) {
       Native.ConnectionManager_Destroy(nativeHandle);
@@ -0,0 +1,56 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.crypto;
+
+import junit.framework.TestCase;
+import org.signal.libsignal.protocol.util.Hex;
+
+public class ChaCha20Poly1305Tests extends TestCase {
+
+  // RFC 8439 section 2.8.2
+  private static final String KEY =
+      "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f";
+  private static final String NONCE = "070000004041424344454647";
+  private static final String AAD = "50515253c0c1c2c3c4c5c6c7";
+  private static final String PLAINTEXT =
+      "4c616469657320616e642047656e746c656d656e206f662074686520636c617373206f66202739393a204966204920636f756c64206f6666657220796f75206f6e6c79206f6e652074697020666f7220746865206675747572652c2073756e73637265656e20776f756c642062652069742e";
+  private static final String CIPHERTEXT =
+      "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3ff4def08e4b7a9de576d26586cec64b6116";
+  private static final String TAG = "1ae10b594f09e26a7e902ecbd0600691";
+
+  public void testRfc8439() throws Exception {
+    byte[] key = Hex.fromStringCondensed(KEY);
+    byte[] nonce = Hex.fromStringCondensed(NONCE);
+    byte[] ad = Hex.fromStringCondensed(AAD);
+    byte[] plaintext = Hex.fromStringCondensed(PLAINTEXT);
+
+    ChaCha20Poly1305Encryption enc = new ChaCha20Poly1305Encryption(key, nonce, ad);
+    byte[] ciphertext = plaintext.clone();
+    enc.encrypt(ciphertext, 0, 1);
+    enc.encrypt(ciphertext, 1, ciphertext.length - 1);
+    assertEquals(CIPHERTEXT, Hex.toStringCondensed(ciphertext));
+    assertEquals(TAG, Hex.toStringCondensed(enc.computeTag()));
+
+    ChaCha20Poly1305Decryption dec = new ChaCha20Poly1305Decryption(key, nonce, ad);
+    byte[] decrypted = ciphertext.clone();
+    dec.decrypt(decrypted);
+    assertEquals(PLAINTEXT, Hex.toStringCondensed(decrypted));
+    assertTrue(dec.verifyTag(Hex.fromStringCondensed(TAG)));
+  }
+
+  public void testBadTag() throws Exception {
+    byte[] key = Hex.fromStringCondensed(KEY);
+    byte[] nonce = Hex.fromStringCondensed(NONCE);
+    byte[] ad = Hex.fromStringCondensed(AAD);
+
+    ChaCha20Poly1305Decryption dec = new ChaCha20Poly1305Decryption(key, nonce, ad);
+    byte[] decrypted = Hex.fromStringCondensed(CIPHERTEXT);
+    dec.decrypt(decrypted);
+    byte[] badTag = Hex.fromStringCondensed(TAG);
+    badTag[0] ^= 1;
+    assertFalse(dec.verifyTag(badTag));
+  }
+}
@@ -63,6 +63,7 @@ public class ChatServiceTest {
   public void chatServiceErrorConvert() {
     assertChatServiceErrorIs("AppExpired", AppExpiredException.class);
     assertChatServiceErrorIs("DeviceDeregistered", DeviceDeregisteredException.class);
+    assertChatServiceErrorIs("CertificatePinMismatch", CertificatePinMismatchException.class);
     assertChatServiceErrorIs("Disconnected", ChatServiceInactiveException.class);
 
     assertChatServiceErrorIs("WebSocket", ChatServiceException.class);
@@ -62,4 +62,41 @@ public class HKDFTest extends TestCase {
 
     assertTrue(Arrays.equals(outputWithNull, outputWithEmpty));
   }
+
+  public void testVectorsWithDigest() {
+    byte[] salt = Hex.fromStringCondensedAssert("000102030405060708090a0b0c");
+    byte[] info = Hex.fromStringCondensedAssert("f0f1f2f3f4f5f6f7f8f9");
+
+    assertTrue(
+        Arrays.equals(
+            Hex.fromStringCondensedAssert(
+                "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896"),
+            HKDF.deriveSecrets(
+                HKDF.Digest.SHA1,
+                Hex.fromStringCondensedAssert("0b0b0b0b0b0b0b0b0b0b0b"),
+                salt,
+                info,
+                42)));
+
+    byte[] ikm = Hex.fromStringCondensedAssert("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+    assertTrue(
+        Arrays.equals(
+            Hex.fromStringCondensedAssert(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"),
+            HKDF.deriveSecrets(HKDF.Digest.SHA256, ikm, salt, info, 42)));
+    assertTrue(
+        Arrays.equals(
+            Hex.fromStringCondensedAssert(
+                "832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579338da362cb8d9f925d7cb"),
+            HKDF.deriveSecrets(HKDF.Digest.SHA512, ikm, salt, info, 42)));
+  }
+
+  public void testOutputTooLongForDigest() {
+    try {
+      HKDF.deriveSecrets(HKDF.Digest.SHA1, new byte[16], null, null, 255 * 20 + 1);
+      fail("should have thrown");
+    } catch (IllegalArgumentException e) {
+      // expected
+    }
+  }
 }
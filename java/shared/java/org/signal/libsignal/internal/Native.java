@@ -17,6 +17,8 @@ import org.signal.libsignal.protocol.groups.state.SenderKeyStore;
 import org.signal.libsignal.protocol.logging.Log;
 import org.signal.libsignal.protocol.logging.SignalProtocolLogger;
 import org.signal.libsignal.net.internal.BridgeChatListener;
+import org.signal.libsignal.net.internal.BridgeConnectionAttemptListener;
+import org.signal.libsignal.net.internal.BridgeTransferProgressListener;
 
 import java.io.File;
 import java.io.FileOutputStream;
@@ -169,7 +171,7 @@ public final class Native {
   public static native void AuthCredentialWithPni_CheckValidContents(byte[] bytes) throws Exception;
 
   public static native void AuthenticatedChatConnection_Destroy(long handle);
-  public static native CompletableFuture<Long> AuthenticatedChatConnection_connect(long asyncRuntime, long connectionManager, String username, String password, boolean receiveStories);
+  public static native CompletableFuture<Long> AuthenticatedChatConnection_connect(long asyncRuntime, long connectionManager, String username, String password, boolean receiveStories, long noisePrivateKey);
   public static native CompletableFuture AuthenticatedChatConnection_disconnect(long asyncRuntime, long chat);
   public static native void AuthenticatedChatConnection_init_listener(long chat, BridgeChatListener listener);
   public static native CompletableFuture<Object> AuthenticatedChatConnection_send(long asyncRuntime, long chat, long httpRequest, int timeoutMillis);
@@ -221,6 +223,12 @@ public final class Native {
   public static native byte[] CallLinkSecretParams_DeriveFromRootKey(byte[] rootKey);
   public static native byte[] CallLinkSecretParams_GetPublicParams(byte[] paramsBytes);
 
+  public static native CompletableFuture<Long> CdnConnection_Connect(long asyncRuntime, long connectionManager, String hostname);
+  public static native CompletableFuture<Long> CdnConnection_CreateUpload(long asyncRuntime, long connection, long request, long length, int timeoutMillis);
+  public static native void CdnConnection_Destroy(long handle);
+  public static native CompletableFuture CdnConnection_DownloadToFile(long asyncRuntime, long connection, long request, String filePath, BridgeTransferProgressListener progress, int timeoutMillis);
+  public static native CompletableFuture CdnConnection_UploadFile(long asyncRuntime, long connection, long upload, String filePath, BridgeTransferProgressListener progress, int timeoutMillis);
+
   public static native long Cds2ClientState_New(byte[] mrenclave, byte[] attestationMsg, long currentTimestamp) throws Exception;
 
   public static native Map Cds2Metrics_extract(byte[] attestationMsg) throws Exception;
@@ -231,13 +239,40 @@ public final class Native {
   public static native CompletableFuture<Long> CdsiLookup_new_routes(long asyncRuntime, long connectionManager, String username, String password, long request);
   public static native byte[] CdsiLookup_token(long lookup);
 
+  public static native void ChaCha20Poly1305Decryption_Destroy(long handle);
+  public static native long ChaCha20Poly1305Decryption_New(byte[] key, byte[] nonce, byte[] associatedData) throws Exception;
+  public static native void ChaCha20Poly1305Decryption_Update(long cipher, byte[] data, int offset, int length);
+  public static native boolean ChaCha20Poly1305Decryption_VerifyTag(long cipher, byte[] tag) throws Exception;
+
+  public static native byte[] ChaCha20Poly1305Encryption_ComputeTag(long cipher);
+  public static native void ChaCha20Poly1305Encryption_Destroy(long handle);
+  public static native long ChaCha20Poly1305Encryption_New(byte[] key, byte[] nonce, byte[] associatedData) throws Exception;
+  public static native void ChaCha20Poly1305Encryption_Update(long cipher, byte[] data, int offset, int length);
+
+  public static native void ConnectionAttempt_Destroy(long handle);
+  public static native int ConnectionAttempt_duration_millis(long attempt);
+  public static native int ConnectionAttempt_error(long attempt);
+  public static native int ConnectionAttempt_http_status(long attempt);
+  public static native int ConnectionAttempt_ip_version(long attempt);
+  public static native int ConnectionAttempt_result(long attempt);
+  public static native String ConnectionAttempt_route(long attempt);
+  public static native long ConnectionAttempt_started_at(long attempt);
+  public static native int ConnectionAttempt_tls_handshake_completed_after_millis(long attempt);
+  public static native int ConnectionAttempt_websocket_upgrade_millis(long attempt);
+
   public static native void ConnectionManager_Destroy(long handle);
   public static native void ConnectionManager_clear_proxy(long connectionManager);
   public static native long ConnectionManager_new(int environment, String userAgent);
   public static native void ConnectionManager_on_network_change(long connectionManager);
   public static native void ConnectionManager_set_censorship_circumvention_enabled(long connectionManager, boolean enabled);
+  public static native void ConnectionManager_set_chat_compression_enabled(long connectionManager, boolean enabled);
+  public static native void ConnectionManager_set_connection_attempt_listener(long connectionManager, BridgeConnectionAttemptListener listener);
+  public static native void ConnectionManager_set_dns_cache_directory(long connectionManager, String directory);
   public static native void ConnectionManager_set_invalid_proxy(long connectionManager);
+  public static native void ConnectionManager_set_network_identity(long connectionManager, String identity);
   public static native void ConnectionManager_set_proxy(long connectionManager, long proxy);
+  public static native void ConnectionManager_set_proxy_rules(long connectionManager, String rules) throws Exception;
+  public static native void ConnectionManager_set_route_history_directory(long connectionManager, String directory);
 
   public static native void ConnectionProxyConfig_Destroy(long handle);
   public static native long ConnectionProxyConfig_new(String scheme, String host, int port, String username, String password) throws Exception;
@@ -358,6 +393,7 @@ public final class Native {
   public static native void GroupSessionBuilder_ProcessSenderKeyDistributionMessage(long sender, long senderKeyDistributionMessage, SenderKeyStore store) throws Exception;
 
   public static native byte[] HKDF_DeriveSecrets(int outputLength, byte[] ikm, byte[] label, byte[] salt) throws Exception;
+  public static native byte[] HKDF_DeriveWithDigest(int digest, int outputLength, byte[] ikm, byte[] info, byte[] salt) throws Exception;
 
   public static native void HsmEnclaveClient_CompleteHandshake(long cli, byte[] handshakeReceived) throws Exception;
   public static native void HsmEnclaveClient_Destroy(long handle);
@@ -537,6 +573,10 @@ public final class Native {
   public static native long ReceiptCredential_GetReceiptExpirationTime(byte[] receiptCredential);
   public static native long ReceiptCredential_GetReceiptLevel(byte[] receiptCredential);
 
+  public static native void ResumableUpload_Destroy(long handle);
+  public static native String ResumableUpload_GetLocation(long upload);
+  public static native long ResumableUpload_Resume(String location, long request, long length) throws Exception;
+
   public static native void SanitizedMetadata_Destroy(long handle);
   public static native long SanitizedMetadata_GetDataLen(long sanitized);
   public static native long SanitizedMetadata_GetDataOffset(long sanitized);
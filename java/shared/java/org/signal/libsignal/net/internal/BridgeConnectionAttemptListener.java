@@ -0,0 +1,19 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net.internal;
+
+import org.signal.libsignal.internal.CalledFromNative;
+
+/**
+ * A helper interface that represents the callback methods used by the Rust side of the bridge.
+ *
+ * <p>The app-facing listener API is {@link org.signal.libsignal.net.ConnectionAttemptListener}.
+ */
+@CalledFromNative
+public interface BridgeConnectionAttemptListener {
+  // The callee takes ownership of the handle.
+  void onConnectionAttempt(long connectionAttemptHandle);
+}
@@ -0,0 +1,24 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net.internal;
+
+import org.signal.libsignal.internal.CalledFromNative;
+
+/**
+ * A helper interface that represents the callback methods used by the Rust side of the bridge.
+ *
+ * <p>Receives updates on how much of an attachment transfer is done.
+ */
+@CalledFromNative
+public interface BridgeTransferProgressListener {
+  /**
+   * Called as data is transferred.
+   *
+   * <p>{@code transferred} counts from the start of the attachment, including data transferred
+   * before the transfer was resumed. {@code total} is -1 if the length isn't known yet.
+   */
+  void onProgress(long transferred, long total);
+}
@@ -10,6 +10,14 @@ import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
 import org.signal.libsignal.internal.Native;
 
 public abstract class HKDF {
+  /** The hash functions supported by {@link #deriveSecrets(Digest, byte[], byte[], byte[], int)}. */
+  public enum Digest {
+    // These must be kept in sync with DigestAlgorithm in signal-crypto.
+    SHA1,
+    SHA256,
+    SHA512,
+  }
+
   public static byte[] deriveSecrets(byte[] inputKeyMaterial, byte[] info, int outputLength) {
     return filterExceptions(
         () -> Native.HKDF_DeriveSecrets(outputLength, inputKeyMaterial, info, null));
@@ -20,4 +28,22 @@ public abstract class HKDF {
     return filterExceptions(
         () -> Native.HKDF_DeriveSecrets(outputLength, inputKeyMaterial, info, salt));
   }
+
+  /**
+   * Derives {@code outputLength} bytes with HKDF, using {@code digest} as the underlying hash.
+   *
+   * <p>A {@code null} salt is equivalent to an empty one.
+   *
+   * @throws IllegalArgumentException if {@code outputLength} is more than 255 times the digest
+   *     size.
+   */
+  public static byte[] deriveSecrets(
+      Digest digest, byte[] inputKeyMaterial, byte[] salt, byte[] info, int outputLength) {
+    byte[] nonNullSalt = salt != null ? salt : new byte[0];
+    byte[] nonNullInfo = info != null ? info : new byte[0];
+    return filterExceptions(
+        () ->
+            Native.HKDF_DeriveWithDigest(
+                digest.ordinal(), outputLength, inputKeyMaterial, nonNullInfo, nonNullSalt));
+  }
 }
@@ -129,6 +129,14 @@ type ChatListener = {
   ): void;
 };
 
+type ConnectionAttemptListener = {
+  _connection_attempt(attempt: ConnectionAttempt): void;
+};
+
+type TransferProgressListener = {
+  _on_progress(transferred: number, total: number | null): void;
+};
+
 type Wrapper<T> = Readonly<{
   _nativeHandle: T;
 }>;
@@ -163,7 +171,7 @@ export function AuthCredentialPresentation_GetRedemptionTime(presentationBytes:
 export function AuthCredentialPresentation_GetUuidCiphertext(presentationBytes: Buffer): Serialized<UuidCiphertext>;
 export function AuthCredentialWithPniResponse_CheckValidContents(bytes: Buffer): void;
 export function AuthCredentialWithPni_CheckValidContents(bytes: Buffer): void;
-export function AuthenticatedChatConnection_connect(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, username: string, password: string, receiveStories: boolean): CancellablePromise<AuthenticatedChatConnection>;
+export function AuthenticatedChatConnection_connect(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, username: string, password: string, receiveStories: boolean, noisePrivateKey: Wrapper<PrivateKey> | null): CancellablePromise<AuthenticatedChatConnection>;
 export function AuthenticatedChatConnection_disconnect(asyncRuntime: Wrapper<TokioAsyncContext>, chat: Wrapper<AuthenticatedChatConnection>): CancellablePromise<void>;
 export function AuthenticatedChatConnection_info(chat: Wrapper<AuthenticatedChatConnection>): ChatConnectionInfo;
 export function AuthenticatedChatConnection_init_listener(chat: Wrapper<AuthenticatedChatConnection>, listener: ChatListener): void;
@@ -204,6 +212,10 @@ export function CallLinkSecretParams_CheckValidContents(paramsBytes: Buffer): vo
 export function CallLinkSecretParams_DecryptUserId(paramsBytes: Buffer, userId: Serialized<UuidCiphertext>): Buffer;
 export function CallLinkSecretParams_DeriveFromRootKey(rootKey: Buffer): Buffer;
 export function CallLinkSecretParams_GetPublicParams(paramsBytes: Buffer): Buffer;
+export function CdnConnection_Connect(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, hostname: string): CancellablePromise<CdnConnection>;
+export function CdnConnection_CreateUpload(asyncRuntime: Wrapper<TokioAsyncContext>, connection: Wrapper<CdnConnection>, request: Wrapper<HttpRequest>, length: bigint, timeoutMillis: number): CancellablePromise<ResumableUpload>;
+export function CdnConnection_DownloadToFile(asyncRuntime: Wrapper<TokioAsyncContext>, connection: Wrapper<CdnConnection>, request: Wrapper<HttpRequest>, filePath: string, progress: TransferProgressListener | null, timeoutMillis: number): CancellablePromise<void>;
+export function CdnConnection_UploadFile(asyncRuntime: Wrapper<TokioAsyncContext>, connection: Wrapper<CdnConnection>, upload: Wrapper<ResumableUpload>, filePath: string, progress: TransferProgressListener | null, timeoutMillis: number): CancellablePromise<void>;
 export function Cds2ClientState_New(mrenclave: Buffer, attestationMsg: Buffer, currentTimestamp: Timestamp): SgxClientState;
 export function CdsiLookup_complete(asyncRuntime: Wrapper<TokioAsyncContext>, lookup: Wrapper<CdsiLookup>): CancellablePromise<LookupResponse>;
 export function CdsiLookup_new(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, username: string, password: string, request: Wrapper<LookupRequest>): CancellablePromise<CdsiLookup>;
@@ -218,13 +230,28 @@ export function CiphertextMessage_Type(msg: Wrapper<CiphertextMessage>): number;
 export function ComparableBackup_GetComparableString(backup: Wrapper<ComparableBackup>): string;
 export function ComparableBackup_GetUnknownFields(backup: Wrapper<ComparableBackup>): string[];
 export function ComparableBackup_ReadUnencrypted(stream: InputStream, len: bigint, purpose: number): Promise<ComparableBackup>;
+export function ConnectionAttempt_duration_millis(attempt: Wrapper<ConnectionAttempt>): number;
+export function ConnectionAttempt_error(attempt: Wrapper<ConnectionAttempt>): number;
+export function ConnectionAttempt_http_status(attempt: Wrapper<ConnectionAttempt>): number;
+export function ConnectionAttempt_ip_version(attempt: Wrapper<ConnectionAttempt>): number;
+export function ConnectionAttempt_result(attempt: Wrapper<ConnectionAttempt>): number;
+export function ConnectionAttempt_route(attempt: Wrapper<ConnectionAttempt>): string;
+export function ConnectionAttempt_started_at(attempt: Wrapper<ConnectionAttempt>): Timestamp;
+export function ConnectionAttempt_tls_handshake_completed_after_millis(attempt: Wrapper<ConnectionAttempt>): number | null;
+export function ConnectionAttempt_websocket_upgrade_millis(attempt: Wrapper<ConnectionAttempt>): number | null;
 export function ConnectionManager_clear_proxy(connectionManager: Wrapper<ConnectionManager>): void;
 export function ConnectionManager_new(environment: number, userAgent: string): ConnectionManager;
 export function ConnectionManager_on_network_change(connectionManager: Wrapper<ConnectionManager>): void;
 export function ConnectionManager_set_censorship_circumvention_enabled(connectionManager: Wrapper<ConnectionManager>, enabled: boolean): void;
+export function ConnectionManager_set_chat_compression_enabled(connectionManager: Wrapper<ConnectionManager>, enabled: boolean): void;
+export function ConnectionManager_set_connection_attempt_listener(connectionManager: Wrapper<ConnectionManager>, listener: ConnectionAttemptListener | null): void;
+export function ConnectionManager_set_dns_cache_directory(connectionManager: Wrapper<ConnectionManager>, directory: string | null): void;
 export function ConnectionManager_set_invalid_proxy(connectionManager: Wrapper<ConnectionManager>): void;
 export function ConnectionManager_set_ipv6_enabled(connectionManager: Wrapper<ConnectionManager>, ipv6Enabled: boolean): void;
+export function ConnectionManager_set_network_identity(connectionManager: Wrapper<ConnectionManager>, identity: string | null): void;
 export function ConnectionManager_set_proxy(connectionManager: Wrapper<ConnectionManager>, proxy: Wrapper<ConnectionProxyConfig>): void;
+export function ConnectionManager_set_proxy_rules(connectionManager: Wrapper<ConnectionManager>, rules: string): void;
+export function ConnectionManager_set_route_history_directory(connectionManager: Wrapper<ConnectionManager>, directory: string | null): void;
 export function ConnectionProxyConfig_new(scheme: string, host: string, port: number, username: string | null, password: string | null): ConnectionProxyConfig;
 export function CreateCallLinkCredentialPresentation_CheckValidContents(presentationBytes: Buffer): void;
 export function CreateCallLinkCredentialPresentation_Verify(presentationBytes: Buffer, roomId: Buffer, now: Timestamp, serverParamsBytes: Buffer, callLinkParamsBytes: Buffer): void;
@@ -289,6 +316,7 @@ export function GroupSendFullToken_Verify(token: Buffer, userIds: Buffer, now: T
 export function GroupSendToken_CheckValidContents(bytes: Buffer): void;
 export function GroupSendToken_ToFullToken(token: Buffer, expiration: Timestamp): Buffer;
 export function HKDF_DeriveSecrets(outputLength: number, ikm: Buffer, label: Buffer | null, salt: Buffer | null): Buffer;
+export function HKDF_DeriveWithDigest(digest: number, outputLength: number, ikm: Buffer, info: Buffer, salt: Buffer): Buffer;
 export function HsmEnclaveClient_CompleteHandshake(cli: Wrapper<HsmEnclaveClient>, handshakeReceived: Buffer): void;
 export function HsmEnclaveClient_EstablishedRecv(cli: Wrapper<HsmEnclaveClient>, receivedCiphertext: Buffer): Buffer;
 export function HsmEnclaveClient_EstablishedSend(cli: Wrapper<HsmEnclaveClient>, plaintextToSend: Buffer): Buffer;
@@ -404,6 +432,8 @@ export function ReceiptCredentialResponse_CheckValidContents(buffer: Buffer): vo
 export function ReceiptCredential_CheckValidContents(buffer: Buffer): void;
 export function ReceiptCredential_GetReceiptExpirationTime(receiptCredential: Serialized<ReceiptCredential>): Timestamp;
 export function ReceiptCredential_GetReceiptLevel(receiptCredential: Serialized<ReceiptCredential>): bigint;
+export function ResumableUpload_GetLocation(upload: Wrapper<ResumableUpload>): string;
+export function ResumableUpload_Resume(location: string, request: Wrapper<HttpRequest>, length: bigint): ResumableUpload;
 export function SanitizedMetadata_GetDataLen(sanitized: Wrapper<SanitizedMetadata>): bigint;
 export function SanitizedMetadata_GetDataOffset(sanitized: Wrapper<SanitizedMetadata>): bigint;
 export function SanitizedMetadata_GetMetadata(sanitized: Wrapper<SanitizedMetadata>): Buffer;
@@ -600,11 +630,13 @@ export function initLogger(maxLevel: LogLevel, callback: (level: LogLevel, targe
 export function test_only_fn_returns_123(): number;
 interface Aes256GcmSiv { readonly __type: unique symbol; }
 interface AuthenticatedChatConnection { readonly __type: unique symbol; }
+interface CdnConnection { readonly __type: unique symbol; }
 interface CdsiLookup { readonly __type: unique symbol; }
 interface ChatConnectionInfo { readonly __type: unique symbol; }
 interface CiphertextMessage { readonly __type: unique symbol; }
 interface ComparableBackup { readonly __type: unique symbol; }
 interface ComparableBackup { readonly __type: unique symbol; }
+interface ConnectionAttempt { readonly __type: unique symbol; }
 interface ConnectionManager { readonly __type: unique symbol; }
 interface ConnectionProxyConfig { readonly __type: unique symbol; }
 interface DecryptionErrorMessage { readonly __type: unique symbol; }
@@ -646,6 +678,7 @@ interface ReceiptCredentialPresentation { readonly __type: unique symbol; }
 interface ReceiptCredentialRequest { readonly __type: unique symbol; }
 interface ReceiptCredentialRequestContext { readonly __type: unique symbol; }
 interface ReceiptCredentialResponse { readonly __type: unique symbol; }
+interface ResumableUpload { readonly __type: unique symbol; }
 interface SanitizedMetadata { readonly __type: unique symbol; }
 interface SealedSenderDecryptionResult { readonly __type: unique symbol; }
 interface SenderCertificate { readonly __type: unique symbol; }
@@ -50,6 +50,7 @@ export enum ErrorCode {
   ChatServiceInactive,
   AppExpired,
   DeviceDelinked,
+  CertificatePinMismatch,
 
   BackupValidation,
 
@@ -252,6 +253,10 @@ export type DeviceDelinkedError = LibSignalErrorBase & {
   code: ErrorCode.DeviceDelinked;
 };
 
+export type CertificatePinMismatchError = LibSignalErrorBase & {
+  code: ErrorCode.CertificatePinMismatch;
+};
+
 export type SvrDataMissingError = LibSignalErrorBase & {
   code: ErrorCode.SvrDataMissing;
 };
@@ -309,6 +314,7 @@ export type LibSignalError =
   | ChatServiceInactive
   | AppExpiredError
   | DeviceDelinkedError
+  | CertificatePinMismatchError
   | RateLimitedError
   | BackupValidationError
   | CancellationError;
@@ -79,6 +79,34 @@ export function hkdf(
   return Native.HKDF_DeriveSecrets(outputLength, keyMaterial, label, salt);
 }
 
+// This enum must be kept in sync with DigestAlgorithm in signal-crypto.
+export enum HkdfDigest {
+  Sha1 = 0,
+  Sha256 = 1,
+  Sha512 = 2,
+}
+
+/**
+ * Derives `outputLength` bytes with HKDF, using `digest` as the underlying hash.
+ *
+ * Throws if `outputLength` is more than 255 times the digest size.
+ */
+export function hkdfWithDigest(
+  digest: HkdfDigest,
+  outputLength: number,
+  keyMaterial: Buffer,
+  info: Buffer,
+  salt: Buffer | null
+): Buffer {
+  return Native.HKDF_DeriveWithDigest(
+    digest,
+    outputLength,
+    keyMaterial,
+    info,
+    salt ?? Buffer.alloc(0)
+  );
+}
+
 export class ScannableFingerprint {
   private readonly scannable: Buffer;
 
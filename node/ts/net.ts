@@ -6,6 +6,7 @@
 import type { ReadonlyDeep } from 'type-fest';
 import * as Native from '../Native';
 import { Aci } from './Address';
+import { PrivateKey } from './EcKeys';
 import { LibSignalError } from './Errors';
 import { ServerMessageAck, Wrapper } from '../Native';
 import { Buffer } from 'node:buffer';
@@ -195,6 +196,87 @@ class ConnectionInfoImpl
   }
 }
 
+/**
+ * A single attempt to connect over one route, as reported to
+ * {@link Net#setConnectionAttemptListener}.
+ */
+export type ConnectionAttempt = {
+  /** A log-safe description of the route. */
+  route: string;
+  /** The IP version of the address connected to directly (for proxied routes, the proxy's). */
+  ipVersion: 'IPv4' | 'IPv6';
+  startedAt: Date;
+  durationMillis: number;
+  /**
+   * Time from the start of the attempt until the TLS handshake finished, including connecting over
+   * TCP and through any proxy.
+   */
+  tlsHandshakeCompletedAfterMillis: number | null;
+  /** Time taken by the websocket upgrade request, if the server responded to it. */
+  websocketUpgradeMillis: number | null;
+  /**
+   * - `failed`: other routes might still work.
+   * - `rejected`: the server rejected the connection, so no other routes were tried.
+   * - `abandoned`: another route succeeded, or the overall connection attempt timed out, first.
+   */
+  result: 'succeeded' | 'failed' | 'rejected' | 'abandoned';
+  /** The stage at which the attempt failed, if it did. */
+  failure:
+    | 'dns'
+    | 'tcp'
+    | 'tls'
+    | 'certificate-pin-mismatch'
+    | 'proxy'
+    | 'timeout'
+    | 'http-status'
+    | 'websocket'
+    | 'other'
+    | null;
+  /** Set if `failure` is `http-status`. */
+  httpStatus: number | null;
+};
+
+const CONNECTION_ATTEMPT_RESULTS = [
+  'succeeded',
+  'failed',
+  'rejected',
+  'abandoned',
+] as const;
+
+const CONNECTION_ATTEMPT_FAILURES = [
+  null,
+  'dns',
+  'tcp',
+  'tls',
+  'certificate-pin-mismatch',
+  'proxy',
+  'timeout',
+  'http-status',
+  'websocket',
+  'other',
+] as const;
+
+function readConnectionAttempt(
+  nativeHandle: Native.ConnectionAttempt
+): ConnectionAttempt {
+  const attempt = { _nativeHandle: nativeHandle };
+  const httpStatus = Native.ConnectionAttempt_http_status(attempt);
+  return {
+    route: Native.ConnectionAttempt_route(attempt),
+    ipVersion:
+      Native.ConnectionAttempt_ip_version(attempt) == 2 ? 'IPv6' : 'IPv4',
+    startedAt: new Date(Native.ConnectionAttempt_started_at(attempt)),
+    durationMillis: Native.ConnectionAttempt_duration_millis(attempt),
+    tlsHandshakeCompletedAfterMillis:
+      Native.ConnectionAttempt_tls_handshake_completed_after_millis(attempt),
+    websocketUpgradeMillis:
+      Native.ConnectionAttempt_websocket_upgrade_millis(attempt),
+    result: CONNECTION_ATTEMPT_RESULTS[Native.ConnectionAttempt_result(attempt)],
+    failure: CONNECTION_ATTEMPT_FAILURES[Native.ConnectionAttempt_error(attempt)],
+    httpStatus: httpStatus != 0 ? httpStatus : null,
+  };
+}
+
 export class UnauthenticatedChatConnection implements ChatConnection {
   static async connect(
     asyncContext: TokioAsyncContext,
@@ -270,7 +352,7 @@ export class AuthenticatedChatConnection implements ChatConnection {
     password: string,
     receiveStories: boolean,
     listener: ChatServiceListener,
-    options?: { abortSignal?: AbortSignal }
+    options?: { abortSignal?: AbortSignal; noisePrivateKey?: PrivateKey }
   ): Promise<AuthenticatedChatConnection> {
     const nativeChatListener = makeNativeChatListener(asyncContext, listener);
     const connect = Native.AuthenticatedChatConnection_connect(
@@ -278,7 +360,8 @@ export class AuthenticatedChatConnection implements ChatConnection {
       connectionManager,
       username,
       password,
-      receiveStories
+      receiveStories,
+      options?.noisePrivateKey ?? null
     );
     const chat = await asyncContext.makeCancellable(
       options?.abortSignal,
@@ -540,13 +623,16 @@ export class Net {
 
   /**
    * Creates a new instance of {@link AuthenticatedChatConnection}.
+   *
+   * If the connection goes through a Noise tunnel, `options.noisePrivateKey` (the account's
+   * identity key) identifies the device to the server. Without it, the tunnel is anonymous.
    */
   public connectAuthenticatedChat(
     username: string,
     password: string,
     receiveStories: boolean,
     listener: ChatServiceListener,
-    options?: { abortSignal?: AbortSignal }
+    options?: { abortSignal?: AbortSignal; noisePrivateKey?: PrivateKey }
   ): Promise<AuthenticatedChatConnection> {
     return AuthenticatedChatConnection.connect(
       this.asyncContext,
@@ -588,6 +674,21 @@ export class Net {
     );
   }
 
+  /**
+   * Enables or disables compression for all new chat connections (until changed).
+   *
+   * Existing connections will continue with the setting they were created with. When enabled, each
+   * message is compressed independently.
+   *
+   * Compression is off by default.
+   */
+  public setChatCompressionEnabled(enabled: boolean): void {
+    Native.ConnectionManager_set_chat_compression_enabled(
+      this._connectionManager,
+      enabled
+    );
+  }
+
   /**
    * Sets the proxy host to be used for all new connections (until overridden).
    *
@@ -711,6 +812,25 @@ export class Net {
     return { scheme, username, password, host, port };
   }
 
+  /**
+   * Chooses a proxy (or none) per destination for all new connections, from a PAC-like list of
+   * rules, one per line.
+   *
+   * Each line is a host pattern (`*`, a domain, `*.suffix`, an IP address, or a CIDR network)
+   * followed by `DIRECT`, `PROXY host:port`, `HTTPS host:port`, `SOCKS host:port` (SOCKS4),
+   * `SOCKS4 host:port`, or `SOCKS5 host:port`. The first matching rule wins; destinations that
+   * match no rule are connected to directly. `#` starts a comment.
+   *
+   * This replaces any proxy set with {@link #setProxy}, and is in turn replaced by calling
+   * {@link #setProxy}, {@link #setInvalidProxy}, or {@link #clearProxy}.
+   *
+   * @throws if the rules can't be parsed. In that case, all new connections will fail until
+   * {@link #clearProxy} is called, consistent with {@link #setProxy}.
+   */
+  setProxyRules(rules: string): void {
+    Native.ConnectionManager_set_proxy_rules(this._connectionManager, rules);
+  }
+
   /**
    * Refuses to make any new connections until a new proxy configuration is set or
    * {@link #clearProxy} is called.
@@ -731,6 +851,67 @@ export class Net {
     Native.ConnectionManager_clear_proxy(this._connectionManager);
   }
 
+  /**
+   * Calls `listener` with details of every subsequent attempt to connect over a single route, e.g.
+   * to track which routes work on the current network.
+   *
+   * Passing `null` stops reporting attempts.
+   */
+  setConnectionAttemptListener(
+    listener: ((attempt: ConnectionAttempt) => void) | null
+  ): void {
+    Native.ConnectionManager_set_connection_attempt_listener(
+      this._connectionManager,
+      listener
+        ? {
+            _connection_attempt(attempt: Native.ConnectionAttempt): void {
+              listener(readConnectionAttempt(attempt));
+            },
+          }
+        : null
+    );
+  }
+
+  /**
+   * Saves DNS results in files in `directory`, so that they can be reused after a restart.
+   *
+   * The directory must already exist. Passing `null` stops saving results. Results are only saved
+   * and used while a network identity is set with {@link #setNetworkIdentity}.
+   */
+  setDnsCacheDirectory(directory: string | null): void {
+    Native.ConnectionManager_set_dns_cache_directory(
+      this._connectionManager,
+      directory
+    );
+  }
+
+  /**
+   * Saves which connection routes recently failed in files in `directory`, so that they are still
+   * tried last after a restart.
+   *
+   * The directory must already exist. Passing `null` stops saving route history.
+   */
+  setRouteHistoryDirectory(directory: string | null): void {
+    Native.ConnectionManager_set_route_history_directory(
+      this._connectionManager,
+      directory
+    );
+  }
+
+  /**
+   * Sets an identifier for the current network, which determines which saved DNS results can be
+   * used.
+   *
+   * This should distinguish networks that might resolve names differently, e.g. a hash of the
+   * Wi-Fi network name. Passing `null` stops using saved results until an identity is set again.
+   */
+  setNetworkIdentity(identity: string | null): void {
+    Native.ConnectionManager_set_network_identity(
+      this._connectionManager,
+      identity
+    );
+  }
+
   /**
    * Notifies libsignal that the network has changed.
    *
@@ -53,6 +53,7 @@ describe('chat service api', () => {
     const cases: Array<[string, ErrorCode | object]> = [
       ['AppExpired', ErrorCode.AppExpired],
       ['DeviceDeregistered', ErrorCode.DeviceDelinked],
+      ['CertificatePinMismatch', ErrorCode.CertificatePinMismatch],
       ['Disconnected', ErrorCode.ChatServiceInactive],
 
       ['WebSocket', ErrorCode.IoError],
@@ -336,6 +336,57 @@ describe('SignalClient', () => {
       '3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865'
     );
   });
+  it('HKDF test vectors with explicit digests', () => {
+    // From RFC 5869, test cases 1 and 4, plus test case 1's inputs with SHA-512.
+    const salt = Buffer.from('000102030405060708090A0B0C', 'hex');
+    const info = Buffer.from('F0F1F2F3F4F5F6F7F8F9', 'hex');
+
+    assert.deepEqual(
+      SignalClient.hkdfWithDigest(
+        SignalClient.HkdfDigest.Sha1,
+        42,
+        Buffer.from('0B0B0B0B0B0B0B0B0B0B0B', 'hex'),
+        info,
+        salt
+      ).toString('hex'),
+      '085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896'
+    );
+
+    const secret = Buffer.from(
+      '0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B0B',
+      'hex'
+    );
+    assert.deepEqual(
+      SignalClient.hkdfWithDigest(
+        SignalClient.HkdfDigest.Sha256,
+        42,
+        secret,
+        info,
+        salt
+      ).toString('hex'),
+      '3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865'
+    );
+    assert.deepEqual(
+      SignalClient.hkdfWithDigest(
+        SignalClient.HkdfDigest.Sha512,
+        42,
+        secret,
+        info,
+        salt
+      ).toString('hex'),
+      '832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579338da362cb8d9f925d7cb'
+    );
+
+    assert.throws(() =>
+      SignalClient.hkdfWithDigest(
+        SignalClient.HkdfDigest.Sha1,
+        255 * 20 + 1,
+        secret,
+        info,
+        null
+      )
+    );
+  });
   describe('ServiceId', () => {
     const testingUuid = '8c78cd2a-16ff-427d-83dc-1a5e36ce713d';
 
@@ -4,11 +4,14 @@
 //
 
 use ::signal_crypto;
-use aes_gcm_siv::aead::generic_array::typenum::Unsigned;
-use aes_gcm_siv::{AeadCore, AeadInPlace, KeyInit};
 use libsignal_bridge_macros::*;
-use libsignal_bridge_types::crypto::{Aes256GcmDecryption, Aes256GcmEncryption, Aes256GcmSiv};
-use signal_crypto::{Aes256Ctr32, CryptographicHash, CryptographicMac, Error, Result};
+use libsignal_bridge_types::crypto::{
+    Aes256GcmDecryption, Aes256GcmEncryption, Aes256GcmSiv, ChaCha20Poly1305Decryption,
+    ChaCha20Poly1305Encryption,
+};
+use signal_crypto::{
+    Aes256Ctr32, CryptographicHash, CryptographicMac, DigestAlgorithm, Error, Result,
+};
 
 use crate::support::*;
 use crate::*;
@@ -17,8 +20,22 @@ bridge_handle_fns!(CryptographicHash, ffi = false, node = false);
 bridge_handle_fns!(CryptographicMac, ffi = false, node = false);
 bridge_handle_fns!(Aes256GcmSiv, clone = false);
 bridge_handle_fns!(Aes256Ctr32, clone = false, node = false);
+// Node's built-in crypto module already provides streaming AES-GCM and ChaCha20-Poly1305, so the
+// streaming ciphers are only bridged to the apps.
 bridge_handle_fns!(Aes256GcmEncryption, clone = false, node = false);
 bridge_handle_fns!(Aes256GcmDecryption, clone = false, node = false);
+bridge_handle_fns!(
+    ChaCha20Poly1305Encryption,
+    clone = false,
+    ffi = chacha20_poly1305_encryption,
+    node = false
+);
+bridge_handle_fns!(
+    ChaCha20Poly1305Decryption,
+    clone = false,
+    ffi = chacha20_poly1305_decryption,
+    node = false
+);
 
 #[bridge_fn(node = false)]
 fn Aes256Ctr32_New(key: &[u8], nonce: &[u8], initial_ctr: u32) -> Result<Aes256Ctr32> {
@@ -84,11 +101,64 @@ fn Aes256GcmDecryption_VerifyTag(gcm: &mut Aes256GcmDecryption, tag: &[u8]) -> R
     gcm.verify_tag(tag)
 }
 
+#[bridge_fn(ffi = "chacha20_poly1305_encryption_new", node = false)]
+fn ChaCha20Poly1305Encryption_New(
+    key: &[u8],
+    nonce: &[u8],
+    associated_data: &[u8],
+) -> Result<ChaCha20Poly1305Encryption> {
+    ChaCha20Poly1305Encryption::new(key, nonce, associated_data)
+}
+
+#[bridge_fn(ffi = "chacha20_poly1305_encryption_update", node = false)]
+fn ChaCha20Poly1305Encryption_Update(
+    cipher: &mut ChaCha20Poly1305Encryption,
+    data: &mut [u8],
+    offset: u32,
+    length: u32,
+) {
+    let offset = offset as usize;
+    let length = length as usize;
+    cipher.encrypt(&mut data[offset..offset + length]);
+}
+
+#[bridge_fn(ffi = "chacha20_poly1305_encryption_compute_tag", node = false)]
+fn ChaCha20Poly1305Encryption_ComputeTag(cipher: &mut ChaCha20Poly1305Encryption) -> Vec<u8> {
+    cipher.compute_tag()
+}
+
+#[bridge_fn(ffi = "chacha20_poly1305_decryption_new", node = false)]
+fn ChaCha20Poly1305Decryption_New(
+    key: &[u8],
+    nonce: &[u8],
+    associated_data: &[u8],
+) -> Result<ChaCha20Poly1305Decryption> {
+    ChaCha20Poly1305Decryption::new(key, nonce, associated_data)
+}
+
+#[bridge_fn(ffi = "chacha20_poly1305_decryption_update", node = false)]
+fn ChaCha20Poly1305Decryption_Update(
+    cipher: &mut ChaCha20Poly1305Decryption,
+    data: &mut [u8],
+    offset: u32,
+    length: u32,
+) {
+    let offset = offset as usize;
+    let length = length as usize;
+    cipher.decrypt(&mut data[offset..offset + length]);
+}
+
+#[bridge_fn(ffi = "chacha20_poly1305_decryption_verify_tag", node = false)]
+fn ChaCha20Poly1305Decryption_VerifyTag(
+    cipher: &mut ChaCha20Poly1305Decryption,
+    tag: &[u8],
+) -> Result<bool> {
+    cipher.verify_tag(tag)
+}
+
 #[bridge_fn]
 fn Aes256GcmSiv_New(key: &[u8]) -> Result<Aes256GcmSiv> {
-    Ok(Aes256GcmSiv(
-        aes_gcm_siv::Aes256GcmSiv::new_from_slice(key).map_err(|_| Error::InvalidKeySize)?,
-    ))
+    Ok(Aes256GcmSiv(signal_crypto::Aes256GcmSiv::new(key)?))
 }
 
 #[bridge_fn]
@@ -98,19 +168,13 @@ fn Aes256GcmSiv_Encrypt(
     nonce: &[u8],
     associated_data: &[u8],
 ) -> Result<Vec<u8>> {
-    if nonce.len() != <aes_gcm_siv::Aes256GcmSiv as AeadCore>::NonceSize::USIZE {
-        return Err(Error::InvalidNonceSize);
-    }
-    let nonce: &aes_gcm_siv::Nonce = nonce.into();
-
-    let mut buf =
-        Vec::with_capacity(ptext.len() + <aes_gcm_siv::Aes256GcmSiv as AeadCore>::TagSize::USIZE);
+    let mut buf = Vec::with_capacity(ptext.len() + signal_crypto::Aes256GcmSiv::TAG_SIZE);
     buf.extend_from_slice(ptext);
 
-    aes_gcm_siv_obj
+    let tag = aes_gcm_siv_obj
         .0
-        .encrypt_in_place(nonce, associated_data, &mut buf)
-        .expect("cannot run out of capacity in a Vec");
+        .encrypt(&mut buf, nonce, associated_data)?;
+    buf.extend_from_slice(&tag);
 
     Ok(buf)
 }
@@ -122,19 +186,33 @@ fn Aes256GcmSiv_Decrypt(
     nonce: &[u8],
     associated_data: &[u8],
 ) -> Result<Vec<u8>> {
-    if nonce.len() != <aes_gcm_siv::Aes256GcmSiv as AeadCore>::NonceSize::USIZE {
-        return Err(Error::InvalidNonceSize);
-    }
-    let nonce: &aes_gcm_siv::Nonce = nonce.into();
+    let ctext_len = ctext
+        .len()
+        .checked_sub(signal_crypto::Aes256GcmSiv::TAG_SIZE)
+        .ok_or(Error::InvalidTag)?;
+    let (ctext, tag) = ctext.split_at(ctext_len);
 
     let mut buf = ctext.to_vec();
     aes_gcm_siv
         .0
-        .decrypt_in_place(nonce, associated_data, &mut buf)
-        .map_err(|_| Error::InvalidTag)?;
+        .decrypt(&mut buf, nonce, associated_data, tag)?;
     Ok(buf)
 }
 
+#[bridge_fn]
+fn HKDF_DeriveWithDigest(
+    digest: AsType<DigestAlgorithm, u8>,
+    output_length: u32,
+    ikm: &[u8],
+    info: &[u8],
+    salt: &[u8],
+) -> Result<Vec<u8>> {
+    let mut buffer = vec![0; output_length as usize];
+    // An empty salt is equivalent to an absent one, so there's no need for an Option here.
+    signal_crypto::hkdf_derive(digest.into_inner(), ikm, Some(salt), info, &mut buffer)?;
+    Ok(buffer)
+}
+
 #[bridge_fn(ffi = false, node = false)]
 fn CryptographicHash_New(algo: String) -> Result<CryptographicHash> {
     CryptographicHash::new(&algo)
@@ -188,3 +266,74 @@ fn CryptographicMac_UpdateWithOffset(
 fn CryptographicMac_Finalize(mac: &mut CryptographicMac) -> Vec<u8> {
     mac.finalize()
 }
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+    use test_case::test_case;
+
+    use super::*;
+
+    // From RFC 5869, test cases 1 and 4, plus test case 1's inputs with SHA-512.
+    #[test_case(
+        DigestAlgorithm::Sha256,
+        &hex!("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+        &hex!("000102030405060708090a0b0c"),
+        &hex!("f0f1f2f3f4f5f6f7f8f9"),
+        &hex!("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+        "SHA-256"
+    )]
+    #[test_case(
+        DigestAlgorithm::Sha1,
+        &hex!("0b0b0b0b0b0b0b0b0b0b0b"),
+        &hex!("000102030405060708090a0b0c"),
+        &hex!("f0f1f2f3f4f5f6f7f8f9"),
+        &hex!("085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896");
+        "SHA-1"
+    )]
+    #[test_case(
+        DigestAlgorithm::Sha512,
+        &hex!("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+        &hex!("000102030405060708090a0b0c"),
+        &hex!("f0f1f2f3f4f5f6f7f8f9"),
+        &hex!("832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579338da362cb8d9f925d7cb");
+        "SHA-512"
+    )]
+    fn hkdf_with_digest(
+        digest: DigestAlgorithm,
+        ikm: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        expected: &[u8],
+    ) {
+        let output = HKDF_DeriveWithDigest(
+            digest.into(),
+            expected.len().try_into().unwrap(),
+            ikm,
+            info,
+            salt,
+        )
+        .expect("valid length");
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn hkdf_with_digest_rejects_long_output() {
+        assert_matches::assert_matches!(
+            HKDF_DeriveWithDigest(DigestAlgorithm::Sha1.into(), 255 * 20 + 1, b"ikm", b"", b""),
+            Err(Error::InvalidOutputSize)
+        );
+    }
+
+    #[test]
+    fn digest_discriminants_are_stable() {
+        for (value, digest) in [
+            (0, DigestAlgorithm::Sha1),
+            (1, DigestAlgorithm::Sha256),
+            (2, DigestAlgorithm::Sha512),
+        ] {
+            assert_eq!(DigestAlgorithm::try_from(value), Ok(digest));
+        }
+        assert!(DigestAlgorithm::try_from(3).is_err());
+    }
+}
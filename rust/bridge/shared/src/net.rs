@@ -7,18 +7,23 @@ use std::num::NonZeroU16;
 
 use base64::prelude::{Engine, BASE64_STANDARD};
 use libsignal_bridge_macros::bridge_fn;
+use libsignal_bridge_types::net::telemetry::ConnectionAttemptListener;
 pub use libsignal_bridge_types::net::{ConnectionManager, Environment, TokioAsyncContext};
 use libsignal_net::auth::Auth;
 use libsignal_net::chat::ConnectionInfo;
 use libsignal_net::infra::errors::LogSafeDisplay;
-use libsignal_net::infra::route::ConnectionProxyConfig;
+use libsignal_net::infra::route::{
+    ConnectionProxyConfig, ProxyFromPartsError, ProxySelector, ProxySelectorError,
+};
 
 use crate::support::*;
 use crate::*;
 
+mod attachments;
 pub(crate) mod cdsi;
 pub(crate) mod chat;
 mod keytrans;
+mod telemetry;
 mod tokio;
 
 bridge_handle_fns!(ConnectionInfo, clone = false, jni = false);
@@ -71,7 +76,6 @@ fn ConnectionProxyConfig_new(
     scheme.make_ascii_lowercase();
 
     ConnectionProxyConfig::from_parts(&scheme, &host, port, auth).map_err(|e| {
-        use libsignal_net::infra::route::ProxyFromPartsError;
         static_assertions::assert_impl_all!(ProxyFromPartsError: LogSafeDisplay);
         match e {
             ProxyFromPartsError::UnsupportedScheme(_) => {
@@ -114,6 +118,27 @@ fn ConnectionManager_clear_proxy(connection_manager: &ConnectionManager) {
     connection_manager.clear_proxy();
 }
 
+#[bridge_fn]
+fn ConnectionManager_set_proxy_rules(
+    connection_manager: &ConnectionManager,
+    rules: String,
+) -> Result<(), std::io::Error> {
+    let selector = ProxySelector::from_rules(&rules).map_err(|e| {
+        // Don't fall back to connecting directly if the rules can't be used.
+        connection_manager.set_invalid_proxy();
+        static_assertions::assert_impl_all!(ProxySelectorError: LogSafeDisplay);
+        let kind = match e {
+            ProxySelectorError::FromParts(ProxyFromPartsError::UnsupportedScheme(_)) => {
+                std::io::ErrorKind::Unsupported
+            }
+            _ => std::io::ErrorKind::InvalidInput,
+        };
+        std::io::Error::new(kind, e.to_string())
+    })?;
+    connection_manager.set_proxy_selector(selector);
+    Ok(())
+}
+
 #[bridge_fn(jni = false, ffi = false)]
 fn ConnectionManager_set_ipv6_enabled(connection_manager: &ConnectionManager, ipv6_enabled: bool) {
     connection_manager.set_ipv6_enabled(ipv6_enabled)
@@ -127,6 +152,46 @@ fn ConnectionManager_set_censorship_circumvention_enabled(
     connection_manager.set_censorship_circumvention_enabled(enabled)
 }
 
+#[bridge_fn]
+fn ConnectionManager_set_chat_compression_enabled(
+    connection_manager: &ConnectionManager,
+    enabled: bool,
+) {
+    connection_manager.set_chat_compression_enabled(enabled)
+}
+
+#[bridge_fn]
+fn ConnectionManager_set_dns_cache_directory(
+    connection_manager: &ConnectionManager,
+    directory: Option<String>,
+) {
+    connection_manager.set_dns_cache_directory(directory.map(Into::into))
+}
+
+#[bridge_fn]
+fn ConnectionManager_set_route_history_directory(
+    connection_manager: &ConnectionManager,
+    directory: Option<String>,
+) {
+    connection_manager.set_route_history_directory(directory.map(Into::into))
+}
+
+#[bridge_fn]
+fn ConnectionManager_set_connection_attempt_listener(
+    connection_manager: &ConnectionManager,
+    listener: Option<Box<dyn ConnectionAttemptListener>>,
+) {
+    connection_manager.set_connection_attempt_listener(listener)
+}
+
+#[bridge_fn]
+fn ConnectionManager_set_network_identity(
+    connection_manager: &ConnectionManager,
+    identity: Option<String>,
+) {
+    connection_manager.set_network_identity(identity)
+}
+
 #[bridge_fn]
 fn ConnectionManager_on_network_change(connection_manager: &ConnectionManager) {
     connection_manager.on_network_change(std::time::Instant::now())
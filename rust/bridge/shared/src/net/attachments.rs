@@ -0,0 +1,262 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::time::Duration;
+
+use http::uri::InvalidUri;
+use libsignal_bridge_macros::{bridge_fn, bridge_io};
+use libsignal_bridge_types::net::attachments::{CdnConnection, TransferProgressListener};
+use libsignal_bridge_types::net::chat::HttpRequest;
+use libsignal_bridge_types::net::{ConnectionManager, TokioAsyncContext};
+use libsignal_net::attachments::ResumableUpload;
+
+use crate::support::*;
+use crate::*;
+
+bridge_handle_fns!(CdnConnection, clone = false);
+bridge_handle_fns!(ResumableUpload, clone = false);
+
+#[bridge_io(TokioAsyncContext)]
+async fn CdnConnection_Connect(
+    connection_manager: &ConnectionManager,
+    hostname: String,
+) -> Result<CdnConnection, std::io::Error> {
+    Ok(CdnConnection::connect(connection_manager, &hostname).await?)
+}
+
+#[bridge_io(TokioAsyncContext)]
+async fn CdnConnection_CreateUpload(
+    connection: &CdnConnection,
+    request: &HttpRequest,
+    length: u64,
+    timeout_millis: u32,
+) -> Result<ResumableUpload, std::io::Error> {
+    Ok(connection
+        .create_upload(
+            request,
+            length,
+            Duration::from_millis(timeout_millis.into()),
+        )
+        .await?)
+}
+
+#[bridge_io(TokioAsyncContext)]
+async fn CdnConnection_UploadFile(
+    connection: &CdnConnection,
+    upload: &ResumableUpload,
+    file_path: String,
+    progress: Option<Box<dyn TransferProgressListener>>,
+    timeout_millis: u32,
+) -> Result<(), std::io::Error> {
+    Ok(connection
+        .upload_file(
+            upload,
+            &file_path,
+            progress,
+            Duration::from_millis(timeout_millis.into()),
+        )
+        .await?)
+}
+
+#[bridge_io(TokioAsyncContext)]
+async fn CdnConnection_DownloadToFile(
+    connection: &CdnConnection,
+    request: &HttpRequest,
+    file_path: String,
+    progress: Option<Box<dyn TransferProgressListener>>,
+    timeout_millis: u32,
+) -> Result<(), std::io::Error> {
+    Ok(connection
+        .download_to_file(
+            request,
+            &file_path,
+            progress,
+            Duration::from_millis(timeout_millis.into()),
+        )
+        .await?)
+}
+
+#[bridge_fn]
+fn ResumableUpload_Resume(
+    location: String,
+    request: &HttpRequest,
+    length: u64,
+) -> Result<ResumableUpload, InvalidUri> {
+    let headers = request.headers.lock().expect("not poisoned").clone();
+    Ok(ResumableUpload::resume(
+        location.try_into()?,
+        headers,
+        length,
+    ))
+}
+
+#[bridge_fn]
+fn ResumableUpload_GetLocation(upload: &ResumableUpload) -> String {
+    upload.location().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use libsignal_bridge_types::net::chat::HttpMethod;
+    use libsignal_bridge_types::support::{AsyncRuntime as _, ResultReporter};
+    use libsignal_net::attachments::fake_cdn::FakeCdn;
+    use libsignal_net::connect_state::{ConnectState, SUGGESTED_CONNECT_CONFIG};
+    use libsignal_net::env::UserAgent;
+    use libsignal_net::https_client::HttpsClient;
+    use libsignal_net::infra::Alpn;
+
+    use super::*;
+    use crate::net::tokio::TokioAsyncContext_cancel;
+
+    const TIMEOUT_MILLIS: u32 = 10_000;
+
+    /// Sends the result of a future over a channel.
+    struct SendingReporter<T>(T);
+
+    impl<T> ResultReporter for SendingReporter<T> {
+        type Receiver = std::sync::mpsc::Sender<T>;
+
+        fn report_to(self, receiver: Self::Receiver) {
+            receiver.send(self.0).expect("test is waiting");
+        }
+    }
+
+    /// Records every progress update.
+    #[derive(Clone, Default)]
+    struct RecordingProgress(Arc<std::sync::Mutex<Vec<(u64, Option<u64>)>>>);
+
+    impl TransferProgressListener for RecordingProgress {
+        fn on_progress(&mut self, transferred: u64, total: Option<u64>) {
+            self.0
+                .lock()
+                .expect("not poisoned")
+                .push((transferred, total));
+        }
+    }
+
+    /// Starts a download the same way a bridged call does, cancelling it if
+    /// the returned ID is passed to `TokioAsyncContext_cancel`.
+    fn start_download(
+        async_context: &TokioAsyncContext,
+        connection: &Arc<CdnConnection>,
+        path: &str,
+        file_path: &std::path::Path,
+        progress: Option<Box<dyn TransferProgressListener>>,
+    ) -> (u64, std::sync::mpsc::Receiver<Option<std::io::Result<()>>>) {
+        let request = HttpRequest::new(
+            HttpMethod::try_from("GET".to_owned()).expect("valid"),
+            path.to_owned(),
+            None,
+        )
+        .expect("valid");
+        let connection = Arc::clone(connection);
+        let file_path = file_path.to_str().expect("UTF-8").to_owned();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let id = async_context.run_future(
+            |cancel| async move {
+                SendingReporter(::tokio::select! {
+                    result = CdnConnection_DownloadToFile(
+                        &connection,
+                        &request,
+                        file_path,
+                        progress,
+                        TIMEOUT_MILLIS,
+                    ) => Some(result),
+                    () = cancel => None,
+                })
+            },
+            tx,
+        );
+        (id.into(), rx)
+    }
+
+    #[test]
+    fn cancelled_download_can_be_resumed() {
+        let async_context = TokioAsyncContext::new();
+
+        let cdn = async_context.handle().block_on(async { FakeCdn::start() });
+        let contents: Vec<u8> = (0..50_000_usize)
+            .map(|i| u8::try_from(i % 251).unwrap())
+            .collect();
+        cdn.put_object("/attachments/abc", contents.clone());
+        const RECEIVED_BEFORE_STALL: usize = 20_000;
+        cdn.stall_next_download_after(RECEIVED_BEFORE_STALL);
+
+        let connect = || {
+            let client = async_context
+                .handle()
+                .block_on(HttpsClient::connect(
+                    &ConnectState::new(SUGGESTED_CONNECT_CONFIG),
+                    &cdn.dns_resolver(),
+                    vec![cdn.route(Alpn::Http2)],
+                    None,
+                    &UserAgent::with_libsignal_version("test"),
+                    "test",
+                ))
+                .expect("can connect");
+            Arc::new(CdnConnection::from(client))
+        };
+
+        let file_path = std::env::temp_dir().join(format!(
+            "libsignal-cancelled-download-{}",
+            std::process::id()
+        ));
+        let _cleanup = scopeguard::guard((), |()| {
+            let _ = std::fs::remove_file(&file_path);
+        });
+
+        let (id, result) = start_download(
+            &async_context,
+            &connect(),
+            "/attachments/abc",
+            &file_path,
+            None,
+        );
+        // Wait for the download to stall, then cancel it.
+        while std::fs::metadata(&file_path).map_or(0, |m| m.len())
+            < u64::try_from(RECEIVED_BEFORE_STALL).unwrap()
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        TokioAsyncContext_cancel(&async_context, id);
+        assert!(result.recv().expect("reported").is_none(), "cancelled");
+        assert_eq!(
+            std::fs::read(&file_path).expect("can read"),
+            &contents[..RECEIVED_BEFORE_STALL]
+        );
+
+        // The stalled response may still be occupying the old connection.
+        let progress = RecordingProgress::default();
+        let (_id, result) = start_download(
+            &async_context,
+            &connect(),
+            "/attachments/abc",
+            &file_path,
+            Some(Box::new(progress.clone())),
+        );
+        result
+            .recv()
+            .expect("reported")
+            .expect("not cancelled")
+            .expect("can finish");
+        assert_eq!(std::fs::read(&file_path).expect("can read"), contents);
+
+        // Progress counts from the start of the attachment, including what was
+        // received before the download was resumed.
+        let total = u64::try_from(contents.len()).unwrap();
+        let updates = progress.0.lock().expect("not poisoned").clone();
+        assert_eq!(
+            updates.first(),
+            Some(&(u64::try_from(RECEIVED_BEFORE_STALL).unwrap(), Some(total)))
+        );
+        assert_eq!(updates.last(), Some(&(total, Some(total))));
+        assert!(
+            updates.windows(2).all(|pair| pair[0].0 <= pair[1].0),
+            "{updates:?}"
+        );
+    }
+}
@@ -13,6 +13,7 @@ use libsignal_bridge_types::net::{ConnectionManager, TokioAsyncContext};
 use libsignal_bridge_types::support::AsType;
 use libsignal_net::auth::Auth;
 use libsignal_net::chat::{self, ChatServiceError, Response as ChatResponse};
+use libsignal_protocol::PrivateKey;
 
 use crate::support::*;
 use crate::*;
@@ -119,11 +120,18 @@ async fn AuthenticatedChatConnection_connect(
     username: String,
     password: String,
     receive_stories: bool,
+    noise_private_key: Option<&PrivateKey>,
 ) -> Result<AuthenticatedChatConnection, ChatServiceError> {
+    let noise_private_key = noise_private_key.map(|key| {
+        key.serialize()
+            .try_into()
+            .expect("Curve25519 private keys are 32 bytes")
+    });
     AuthenticatedChatConnection::connect(
         connection_manager,
         Auth { username, password },
         receive_stories,
+        noise_private_key,
     )
     .await
 }
@@ -0,0 +1,71 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::time::{Duration, SystemTime};
+
+use libsignal_bridge_macros::bridge_fn;
+use libsignal_bridge_types::net::telemetry::ConnectionAttempt;
+use libsignal_protocol::Timestamp;
+
+use crate::support::*;
+use crate::*;
+
+bridge_handle_fns!(ConnectionAttempt, clone = false);
+
+fn millis(duration: Duration) -> u32 {
+    duration.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_route(attempt: &ConnectionAttempt) -> String {
+    attempt.0.route.to_string()
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_ip_version(attempt: &ConnectionAttempt) -> u8 {
+    attempt.0.ip_type as u8
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_started_at(attempt: &ConnectionAttempt) -> Timestamp {
+    let since_epoch = attempt
+        .0
+        .started_at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    Timestamp::from_epoch_millis(since_epoch.as_millis().try_into().unwrap_or(u64::MAX))
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_duration_millis(attempt: &ConnectionAttempt) -> u32 {
+    millis(attempt.0.duration)
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_tls_handshake_completed_after_millis(
+    attempt: &ConnectionAttempt,
+) -> Option<u32> {
+    attempt.0.tls_handshake_completed_after.map(millis)
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_websocket_upgrade_millis(attempt: &ConnectionAttempt) -> Option<u32> {
+    attempt.0.websocket_upgrade_duration.map(millis)
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_result(attempt: &ConnectionAttempt) -> u8 {
+    attempt.result_code()
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_error(attempt: &ConnectionAttempt) -> u8 {
+    attempt.error_code()
+}
+
+#[bridge_fn]
+fn ConnectionAttempt_http_status(attempt: &ConnectionAttempt) -> u16 {
+    attempt.http_status()
+}
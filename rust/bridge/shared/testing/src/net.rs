@@ -144,6 +144,7 @@ make_error_testing_enum! {
         WebSocket => WebSocket,
         AppExpired => AppExpired,
         DeviceDeregistered => DeviceDeregistered,
+        CertificatePinMismatch => CertificatePinMismatch,
         UnexpectedFrameReceived => UnexpectedFrameReceived,
         ServerRequestMissingId => ServerRequestMissingId,
         IncomingDataInvalid => IncomingDataInvalid,
@@ -168,6 +169,7 @@ fn TESTING_ChatServiceErrorConvert(
         ),
         TestingChatServiceError::AppExpired => ChatServiceError::AppExpired,
         TestingChatServiceError::DeviceDeregistered => ChatServiceError::DeviceDeregistered,
+        TestingChatServiceError::CertificatePinMismatch => ChatServiceError::CertificatePinMismatch,
         TestingChatServiceError::UnexpectedFrameReceived => {
             ChatServiceError::UnexpectedFrameReceived
         }
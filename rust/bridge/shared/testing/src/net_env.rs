@@ -29,8 +29,10 @@ fn localhost_test_domain_config_with_port_and_cert(
             hostname: "localhost",
             port,
             cert: RootCertificates::FromDer(std::borrow::Cow::Owned(root_certificate_der.to_vec())),
+            pin_set: None,
             confirmation_header_name: None,
             proxy: None,
+            noise: None,
         },
     }
 }
@@ -118,5 +120,6 @@ pub(crate) fn localhost_test_env_with_ports(
             },
         ),
         keytrans_config: None,
+        service_binding_lookup: false,
     }
 }
@@ -52,8 +52,62 @@ impl Aes256GcmDecryption {
     }
 }
 
+pub struct ChaCha20Poly1305Encryption {
+    cipher: Option<signal_crypto::ChaCha20Poly1305Encryption>,
+}
+
+impl ChaCha20Poly1305Encryption {
+    pub fn new(key: &[u8], nonce: &[u8], associated_data: &[u8]) -> Result<Self> {
+        let cipher = signal_crypto::ChaCha20Poly1305Encryption::new(key, nonce, associated_data)?;
+        Ok(Self {
+            cipher: Some(cipher),
+        })
+    }
+
+    pub fn encrypt(&mut self, buf: &mut [u8]) {
+        self.cipher
+            .as_mut()
+            .expect("not yet finalized")
+            .encrypt(buf);
+    }
+
+    pub fn compute_tag(&mut self) -> Vec<u8> {
+        let cipher = self.cipher.take().expect("not yet finalized");
+        cipher.compute_tag().to_vec()
+    }
+}
+
+pub struct ChaCha20Poly1305Decryption {
+    cipher: Option<signal_crypto::ChaCha20Poly1305Decryption>,
+}
+
+impl ChaCha20Poly1305Decryption {
+    pub fn new(key: &[u8], nonce: &[u8], associated_data: &[u8]) -> Result<Self> {
+        let cipher = signal_crypto::ChaCha20Poly1305Decryption::new(key, nonce, associated_data)?;
+        Ok(Self {
+            cipher: Some(cipher),
+        })
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) {
+        self.cipher
+            .as_mut()
+            .expect("not yet finalized")
+            .decrypt(buf);
+    }
+
+    pub fn verify_tag(&mut self, tag: &[u8]) -> Result<bool> {
+        let cipher = self.cipher.take().expect("not yet finalized");
+        match cipher.verify_tag(tag) {
+            Ok(()) => Ok(true),
+            Err(Error::InvalidTag) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
 // Explicit wrapper for cbindgen purposes.
-pub struct Aes256GcmSiv(pub aes_gcm_siv::Aes256GcmSiv);
+pub struct Aes256GcmSiv(pub signal_crypto::Aes256GcmSiv);
 
 bridge_as_handle!(CryptographicHash, mut = true, ffi = false, node = false);
 bridge_as_handle!(CryptographicMac, mut = true, ffi = false, node = false);
@@ -61,3 +115,5 @@ bridge_as_handle!(Aes256GcmSiv);
 bridge_as_handle!(Aes256Ctr32, mut = true, node = false);
 bridge_as_handle!(Aes256GcmEncryption, mut = true, node = false);
 bridge_as_handle!(Aes256GcmDecryption, mut = true, node = false);
+bridge_as_handle!(ChaCha20Poly1305Encryption, mut = true, node = false);
+bridge_as_handle!(ChaCha20Poly1305Decryption, mut = true, node = false);
@@ -0,0 +1,75 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::ffi::c_void;
+
+use crate::net::attachments::TransferProgressListener;
+
+/// `total` is `u64::MAX` if the length isn't known yet.
+type TransferProgressed = extern "C" fn(ctx: *mut c_void, transferred: u64, total: u64);
+type DestroyTransferProgressListener = extern "C" fn(ctx: *mut c_void);
+
+/// Callbacks for [`TransferProgressListener`].
+///
+/// Callbacks will be serialized (i.e. two calls will not come in at the same time), but may not
+/// always happen on the same thread. Calls should be responded to promptly to avoid holding up
+/// the transfer.
+///
+/// # Safety
+///
+/// This type contains raw pointers. Code that constructs an instance of this type must ensure
+/// memory safety assuming that
+/// - the callback function pointer fields are called with `ctx` as an argument;
+/// - the `destroy` function pointer field is called with `ctx` as an argument;
+/// - no function pointer fields are called after `destroy` is called.
+#[repr(C)]
+pub struct FfiTransferProgressListenerStruct {
+    ctx: *mut c_void,
+    on_progress: TransferProgressed,
+    destroy: DestroyTransferProgressListener,
+}
+
+impl FfiTransferProgressListenerStruct {
+    /// Turns `self` into a type-erased [`TransferProgressListener`].
+    ///
+    /// Takes ownership of the memory behind [`FfiTransferProgressListenerStruct::ctx`].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that this method is called at most once on an
+    /// `FfiTransferProgressListenerStruct`.
+    pub(crate) unsafe fn make_listener(&self) -> Box<dyn TransferProgressListener> {
+        let FfiTransferProgressListenerStruct {
+            ctx,
+            on_progress,
+            destroy,
+        } = *self;
+        Box::new(TransferProgressListenerStruct(
+            FfiTransferProgressListenerStruct {
+                ctx,
+                on_progress,
+                destroy,
+            },
+        ))
+    }
+}
+
+// SAFETY: Transfers run on the async runtime, which may move between threads. It's up to the
+// creator of the C struct to make sure `ctx` is appropriate for this.
+unsafe impl Send for FfiTransferProgressListenerStruct {}
+
+struct TransferProgressListenerStruct(FfiTransferProgressListenerStruct);
+
+impl Drop for TransferProgressListenerStruct {
+    fn drop(&mut self) {
+        (self.0.destroy)(self.0.ctx);
+    }
+}
+
+impl TransferProgressListener for TransferProgressListenerStruct {
+    fn on_progress(&mut self, transferred: u64, total: Option<u64>) {
+        (self.0.on_progress)(self.0.ctx, transferred, total.unwrap_or(u64::MAX))
+    }
+}
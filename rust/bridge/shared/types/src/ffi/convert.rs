@@ -15,7 +15,9 @@ use uuid::Uuid;
 
 use super::*;
 use crate::io::{InputStream, SyncInputStream};
+use crate::net::attachments::TransferProgressListener;
 use crate::net::chat::ChatListener;
+use crate::net::telemetry::ConnectionAttemptListener;
 use crate::support::{extend_lifetime, AsType, FixedLengthBincodeSerializable, Serialized};
 
 /// Converts arguments from their FFI form to their Rust form.
@@ -433,6 +435,30 @@ impl<'a> ArgTypeInfo<'a> for Option<Box<dyn ChatListener>> {
     }
 }
 
+impl<'a> ArgTypeInfo<'a> for Option<Box<dyn ConnectionAttemptListener>> {
+    type ArgType = crate::ffi::ConstPointer<FfiConnectionAttemptListenerStruct>;
+    type StoredType = Option<Box<dyn ConnectionAttemptListener>>;
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn borrow(foreign: Self::ArgType) -> SignalFfiResult<Self::StoredType> {
+        Ok(unsafe { foreign.into_inner().as_ref().map(|f| f.make_listener()) })
+    }
+    fn load_from(stored: &'a mut Self::StoredType) -> Self {
+        stored.take()
+    }
+}
+
+impl<'a> ArgTypeInfo<'a> for Option<Box<dyn TransferProgressListener>> {
+    type ArgType = crate::ffi::ConstPointer<FfiTransferProgressListenerStruct>;
+    type StoredType = Option<Box<dyn TransferProgressListener>>;
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn borrow(foreign: Self::ArgType) -> SignalFfiResult<Self::StoredType> {
+        Ok(unsafe { foreign.into_inner().as_ref().map(|f| f.make_listener()) })
+    }
+    fn load_from(stored: &'a mut Self::StoredType) -> Self {
+        stored.take()
+    }
+}
+
 impl<T: ResultTypeInfo, E> ResultTypeInfo for Result<T, E>
 where
     E: FfiError,
@@ -96,6 +96,7 @@ pub enum SignalErrorCode {
     ConnectionFailed = 148,
     ChatServiceInactive = 149,
     RequestTimedOut = 150,
+    CertificatePinMismatch = 151,
 
     SvrDataMissing = 160,
     SvrRestoreFailed = 161,
@@ -334,7 +335,8 @@ impl FfiError for SignalCryptoError {
             Self::UnknownAlgorithm(_, _)
             | Self::InvalidKeySize
             | Self::InvalidNonceSize
-            | Self::InvalidInputSize => SignalErrorCode::InvalidArgument,
+            | Self::InvalidInputSize
+            | Self::InvalidOutputSize => SignalErrorCode::InvalidArgument,
             Self::InvalidTag => SignalErrorCode::InvalidMessage,
         }
     }
@@ -565,6 +567,7 @@ impl FfiError for ChatServiceError {
             Self::Disconnected => "Chat service disconnected".to_owned(),
             Self::AppExpired => "App expired".to_owned(),
             Self::DeviceDeregistered => "Device deregistered or delinked".to_owned(),
+            Self::CertificatePinMismatch => "Certificate pin mismatch".to_owned(),
             Self::RetryLater {
                 retry_after_seconds,
             } => format!("Rate limited; try again after {retry_after_seconds}s"),
@@ -586,6 +589,7 @@ impl FfiError for ChatServiceError {
             Self::Disconnected => SignalErrorCode::ChatServiceInactive,
             Self::AppExpired => SignalErrorCode::AppExpired,
             Self::DeviceDeregistered => SignalErrorCode::DeviceDeregistered,
+            Self::CertificatePinMismatch => SignalErrorCode::CertificatePinMismatch,
             Self::RetryLater { .. } => SignalErrorCode::RateLimited,
         }
     }
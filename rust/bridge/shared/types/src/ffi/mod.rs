@@ -12,6 +12,9 @@ use libsignal_protocol::*;
 mod convert;
 pub use convert::*;
 
+mod attachments;
+pub use attachments::*;
+
 mod chat;
 pub use chat::*;
 
@@ -27,6 +30,9 @@ pub use io::*;
 mod storage;
 pub use storage::*;
 
+mod telemetry;
+pub use telemetry::*;
+
 use crate::support::describe_panic;
 
 #[derive(Debug)]
@@ -0,0 +1,81 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::ffi::c_void;
+
+use super::*;
+use crate::net::telemetry::{ConnectionAttempt, ConnectionAttemptListener};
+
+type ConnectionAttempted = extern "C" fn(ctx: *mut c_void, attempt: *mut ConnectionAttempt);
+type DestroyConnectionAttemptListener = extern "C" fn(ctx: *mut c_void);
+
+/// Callbacks for [`ConnectionAttemptListener`].
+///
+/// Callbacks will be serialized (i.e. two calls will not come in at the same time), but may not
+/// always happen on the same thread. Calls should be responded to promptly to avoid holding up
+/// connection attempts.
+///
+/// # Safety
+///
+/// This type contains raw pointers. Code that constructs an instance of this type must ensure
+/// memory safety assuming that
+/// - the callback function pointer fields are called with `ctx` as an argument;
+/// - the `destroy` function pointer field is called with `ctx` as an argument;
+/// - no function pointer fields are called after `destroy` is called.
+#[repr(C)]
+pub struct FfiConnectionAttemptListenerStruct {
+    ctx: *mut c_void,
+    connection_attempted: ConnectionAttempted,
+    destroy: DestroyConnectionAttemptListener,
+}
+
+impl FfiConnectionAttemptListenerStruct {
+    /// Turns `self` into a type-erased [`ConnectionAttemptListener`].
+    ///
+    /// Takes ownership of the memory behind [`FfiConnectionAttemptListenerStruct::ctx`].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that this method is called at most once on an
+    /// `FfiConnectionAttemptListenerStruct`.
+    pub(crate) unsafe fn make_listener(&self) -> Box<dyn ConnectionAttemptListener> {
+        let FfiConnectionAttemptListenerStruct {
+            ctx,
+            connection_attempted,
+            destroy,
+        } = *self;
+        Box::new(ConnectionAttemptListenerStruct(
+            FfiConnectionAttemptListenerStruct {
+                ctx,
+                connection_attempted,
+                destroy,
+            },
+        ))
+    }
+}
+
+// SAFETY: Connection attempts are reported from multiple threads. It's up to the creator of the C
+// struct to make sure `ctx` is appropriate for this.
+unsafe impl Send for FfiConnectionAttemptListenerStruct {}
+
+struct ConnectionAttemptListenerStruct(FfiConnectionAttemptListenerStruct);
+
+impl Drop for ConnectionAttemptListenerStruct {
+    fn drop(&mut self) {
+        (self.0.destroy)(self.0.ctx);
+    }
+}
+
+impl ConnectionAttemptListener for ConnectionAttemptListenerStruct {
+    fn connection_attempted(&mut self, attempt: ConnectionAttempt) {
+        (self.0.connection_attempted)(
+            self.0.ctx,
+            attempt
+                .convert_into()
+                .expect("bridge_as_handle conversion is infallible")
+                .into_inner(),
+        )
+    }
+}
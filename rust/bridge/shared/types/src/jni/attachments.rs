@@ -0,0 +1,50 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use super::*;
+use crate::net::attachments::TransferProgressListener;
+
+pub type JavaBridgeTransferProgressListener<'a> = JObject<'a>;
+
+pub struct JniTransferProgressListener {
+    vm: JavaVM,
+    listener: GlobalRef,
+}
+
+impl JniTransferProgressListener {
+    pub fn new(env: &mut JNIEnv<'_>, listener: &JObject) -> Result<Self, BridgeLayerError> {
+        check_jobject_type(
+            env,
+            listener,
+            ClassName("org.signal.libsignal.net.internal.BridgeTransferProgressListener"),
+        )?;
+        Ok(Self {
+            vm: env.get_java_vm().expect("can get VM"),
+            listener: env.new_global_ref(listener).expect("can get env"),
+        })
+    }
+}
+
+impl TransferProgressListener for JniTransferProgressListener {
+    fn on_progress(&mut self, transferred: u64, total: Option<u64>) {
+        let Self { vm, listener } = self;
+        // Java has no unsigned longs; no attachment is big enough for that to matter.
+        let transferred = transferred.try_into().unwrap_or(jlong::MAX);
+        let total: jlong = total.map_or(-1, |total| total.try_into().unwrap_or(jlong::MAX));
+        let attach_and_run = || -> Result<(), BridgeLayerError> {
+            let mut guard = vm.attach_current_thread().expect("can attach thread");
+            let env: &mut JNIEnv<'_> = &mut guard;
+            call_method_checked(
+                env,
+                &*listener,
+                "onProgress",
+                jni_args!((transferred => long, total => long) -> void),
+            )
+        };
+        if let Err(e) = attach_and_run() {
+            log::error!("failed to report transfer progress: {e}")
+        }
+    }
+}
@@ -17,7 +17,9 @@ use paste::paste;
 use super::*;
 use crate::io::{InputStream, SyncInputStream};
 use crate::message_backup::MessageBackupValidationOutcome;
+use crate::net::attachments::TransferProgressListener;
 use crate::net::chat::ChatListener;
+use crate::net::telemetry::ConnectionAttemptListener;
 use crate::support::{Array, AsType, FixedLengthBincodeSerializable, Serialized};
 
 /// Converts arguments from their JNI form to their Rust form.
@@ -541,6 +543,46 @@ impl<'storage, 'param: 'storage, 'context: 'param> ArgTypeInfo<'storage, 'param,
     }
 }
 
+impl<'storage, 'param: 'storage, 'context: 'param> ArgTypeInfo<'storage, 'param, 'context>
+    for Option<Box<dyn ConnectionAttemptListener>>
+{
+    type ArgType = JObject<'context>;
+    type StoredType = Option<JniConnectionAttemptListener>;
+    fn borrow(
+        env: &mut JNIEnv<'context>,
+        store: &'param Self::ArgType,
+    ) -> Result<Self::StoredType, BridgeLayerError> {
+        if store.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(JniConnectionAttemptListener::new(env, store)?))
+        }
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.take().map(|j| Box::new(j) as _)
+    }
+}
+
+impl<'storage, 'param: 'storage, 'context: 'param> ArgTypeInfo<'storage, 'param, 'context>
+    for Option<Box<dyn TransferProgressListener>>
+{
+    type ArgType = JObject<'context>;
+    type StoredType = Option<JniTransferProgressListener>;
+    fn borrow(
+        env: &mut JNIEnv<'context>,
+        store: &'param Self::ArgType,
+    ) -> Result<Self::StoredType, BridgeLayerError> {
+        if store.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(JniTransferProgressListener::new(env, store)?))
+        }
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.take().map(|j| Box::new(j) as _)
+    }
+}
+
 impl<'storage, 'param: 'storage, 'context: 'param> ArgTypeInfo<'storage, 'param, 'context>
     for Box<dyn ChatListener>
 {
@@ -1443,6 +1485,12 @@ macro_rules! jni_arg_type {
     (Box<dyn ChatListener >) =>{
         jni::JavaBridgeChatListener<'local>
     };
+    (Option<Box<dyn ConnectionAttemptListener> >) =>{
+        jni::JavaBridgeConnectionAttemptListener<'local>
+    };
+    (Option<Box<dyn TransferProgressListener> >) =>{
+        jni::JavaBridgeTransferProgressListener<'local>
+    };
     (&mut [u8]) => {
         ::jni::objects::JByteArray<'local>
     };
@@ -30,6 +30,9 @@ use crate::net::cdsi::CdsiError;
 mod args;
 pub use args::*;
 
+mod attachments;
+pub use attachments::*;
+
 mod chat;
 pub use chat::*;
 
@@ -53,6 +56,9 @@ use libsignal_net::chat::ChatServiceError;
 mod storage;
 pub use storage::*;
 
+mod telemetry;
+pub use telemetry::*;
+
 /// The type of boxed Rust values, as surfaced in JavaScript.
 pub type ObjectHandle = jlong;
 
@@ -366,6 +372,7 @@ impl<'env> ConsumableException<'env> {
             SignalJniError::Protocol(SignalProtocolError::InvalidArgument(_))
             | SignalJniError::SignalCrypto(SignalCryptoError::UnknownAlgorithm(_, _))
             | SignalJniError::SignalCrypto(SignalCryptoError::InvalidInputSize)
+            | SignalJniError::SignalCrypto(SignalCryptoError::InvalidOutputSize)
             | SignalJniError::SignalCrypto(SignalCryptoError::InvalidNonceSize)
             | SignalJniError::Bridge(BridgeLayerError::BadArgument(_))
             | SignalJniError::Bridge(BridgeLayerError::IntegerOverflow(_))
@@ -662,6 +669,9 @@ impl<'env> ConsumableException<'env> {
                     ChatServiceError::DeviceDeregistered => {
                         ClassName("org.signal.libsignal.net.DeviceDeregisteredException")
                     }
+                    ChatServiceError::CertificatePinMismatch => {
+                        ClassName("org.signal.libsignal.net.CertificatePinMismatchException")
+                    }
                     ChatServiceError::WebSocket(_)
                     | ChatServiceError::UnexpectedFrameReceived
                     | ChatServiceError::ServerRequestMissingId
@@ -0,0 +1,48 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use super::*;
+use crate::net::telemetry::{ConnectionAttempt, ConnectionAttemptListener};
+
+pub type JavaBridgeConnectionAttemptListener<'a> = JObject<'a>;
+
+pub struct JniConnectionAttemptListener {
+    vm: JavaVM,
+    listener: GlobalRef,
+}
+
+impl JniConnectionAttemptListener {
+    pub fn new(env: &mut JNIEnv<'_>, listener: &JObject) -> Result<Self, BridgeLayerError> {
+        check_jobject_type(
+            env,
+            listener,
+            ClassName("org.signal.libsignal.net.internal.BridgeConnectionAttemptListener"),
+        )?;
+        Ok(Self {
+            vm: env.get_java_vm().expect("can get VM"),
+            listener: env.new_global_ref(listener).expect("can get env"),
+        })
+    }
+}
+
+impl ConnectionAttemptListener for JniConnectionAttemptListener {
+    fn connection_attempted(&mut self, attempt: ConnectionAttempt) {
+        let Self { vm, listener } = self;
+        let attach_and_run = || -> Result<(), BridgeLayerError> {
+            let mut guard = vm.attach_current_thread().expect("can attach thread");
+            let env: &mut JNIEnv<'_> = &mut guard;
+            let attempt_handle = attempt.convert_into(env)?;
+            call_method_checked(
+                env,
+                &*listener,
+                "onConnectionAttempt",
+                jni_args!((attempt_handle => long) -> void),
+            )
+        };
+        if let Err(e) = attach_and_run() {
+            log::error!("failed to report connection attempt: {e}")
+        }
+    }
+}
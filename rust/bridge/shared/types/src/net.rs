@@ -14,6 +14,7 @@ use async_trait::async_trait;
 use futures_util::future::join3;
 use http::HeaderName;
 use libsignal_net::auth::Auth;
+use libsignal_net::connect_state::route_history::FileRouteHistoryStore;
 use libsignal_net::connect_state::{ConnectState, SUGGESTED_CONNECT_CONFIG};
 use libsignal_net::enclave::{
     Cdsi, EnclaveEndpoint, EnclaveEndpointConnection, EnclaveKind, NewHandshake, Nitro, PpssSetup,
@@ -21,13 +22,14 @@ use libsignal_net::enclave::{
 };
 use libsignal_net::env::{add_user_agent_header, Env, Svr3Env, UserAgent};
 use libsignal_net::infra::connection_manager::MultiRouteConnectionManager;
+use libsignal_net::infra::dns::persistent_cache::{FileDnsCacheStore, NetworkIdentity};
 use libsignal_net::infra::dns::DnsResolver;
-use libsignal_net::infra::route::{
-    ConnectionProxyConfig, DirectOrProxyProvider, RouteProviderExt as _,
-};
+use libsignal_net::infra::host::Host;
+use libsignal_net::infra::route::{ConnectionProxyConfig, ProxySelector, RouteProviderExt as _};
 use libsignal_net::infra::tcp_ssl::{InvalidProxyConfig, TcpSslConnector};
 use libsignal_net::infra::timeouts::ONE_ROUTE_CONNECTION_TIMEOUT;
 use libsignal_net::infra::utils::ObservableEvent;
+use libsignal_net::infra::ws::DeflateConfig;
 use libsignal_net::infra::{AsHttpHeader as _, EnableDomainFronting, EndpointConnection};
 use libsignal_net::svr::SvrConnection;
 use libsignal_net::svr3::traits::*;
@@ -37,8 +39,10 @@ use libsignal_svr3::EvaluationResult;
 
 use crate::*;
 
+pub mod attachments;
 pub mod cdsi;
 pub mod chat;
+pub mod telemetry;
 pub mod tokio;
 
 pub use tokio::TokioAsyncContext;
@@ -157,6 +161,9 @@ pub struct ConnectionManager {
     // but we don't hold it for very long anyway (just enough to clone the Arc).
     endpoints: std::sync::Mutex<Arc<EndpointConnections>>,
     transport_connector: std::sync::Mutex<TcpSslConnector>,
+    /// Overrides the proxy set on `transport_connector`, if present.
+    proxy_selector: std::sync::Mutex<Option<ProxySelector>>,
+    chat_compression: std::sync::Mutex<Option<DeflateConfig>>,
     most_recent_network_change: std::sync::Mutex<Instant>,
     network_change_event: ObservableEvent,
 }
@@ -178,6 +185,7 @@ impl ConnectionManager {
 
         let dns_resolver =
             DnsResolver::new_with_static_fallback(env.static_fallback(), &network_change_event);
+        dns_resolver.set_service_binding_lookup_enabled(env.service_binding_lookup);
         let transport_connector =
             std::sync::Mutex::new(TcpSslConnector::new_direct(dns_resolver.clone()));
         let endpoints = std::sync::Mutex::new(
@@ -190,6 +198,8 @@ impl ConnectionManager {
             connect: ConnectState::new(SUGGESTED_CONNECT_CONFIG),
             dns_resolver,
             transport_connector,
+            proxy_selector: Default::default(),
+            chat_compression: Default::default(),
             most_recent_network_change: Instant::now().into(),
             network_change_event,
         }
@@ -198,23 +208,49 @@ impl ConnectionManager {
     pub fn set_proxy(&self, proxy: ConnectionProxyConfig) {
         let mut guard = self.transport_connector.lock().expect("not poisoned");
         guard.set_proxy(proxy);
+        *self.proxy_selector.lock().expect("not poisoned") = None;
     }
 
     pub fn set_invalid_proxy(&self) {
         let mut guard = self.transport_connector.lock().expect("not poisoned");
         guard.set_invalid();
+        *self.proxy_selector.lock().expect("not poisoned") = None;
     }
 
     pub fn clear_proxy(&self) {
         let mut guard = self.transport_connector.lock().expect("not poisoned");
         guard.clear_proxy();
+        *self.proxy_selector.lock().expect("not poisoned") = None;
+    }
+
+    /// Chooses a proxy per destination host for new route-based connections,
+    /// replacing any proxy set with [`Self::set_proxy`].
+    ///
+    /// Calling `set_proxy`, `set_invalid_proxy`, or `clear_proxy` removes the
+    /// selector again.
+    pub fn set_proxy_selector(&self, selector: ProxySelector) {
+        *self.proxy_selector.lock().expect("not poisoned") = Some(selector);
     }
 
     pub fn is_using_proxy(&self) -> Result<bool, InvalidProxyConfig> {
+        if let Some(selector) = &*self.proxy_selector.lock().expect("not poisoned") {
+            return Ok(selector.uses_proxy());
+        }
         let guard = self.transport_connector.lock().expect("not poisoned");
         guard.proxy().map(|proxy| proxy.is_some())
     }
 
+    /// The selector to use for new route-based connections: the one set with
+    /// [`Self::set_proxy_selector`], or else one that applies the single
+    /// configured proxy (if any) to every destination.
+    pub(crate) fn proxy_selector(&self) -> Result<ProxySelector, InvalidProxyConfig> {
+        if let Some(selector) = &*self.proxy_selector.lock().expect("not poisoned") {
+            return Ok(selector.clone());
+        }
+        let proxy = (&*self.transport_connector.lock().expect("not poisoned")).try_into()?;
+        Ok(ProxySelector::uniform(proxy))
+    }
+
     pub fn set_ipv6_enabled(&self, ipv6_enabled: bool) {
         let mut guard = self.transport_connector.lock().expect("not poisoned");
         guard.set_ipv6_enabled(ipv6_enabled);
@@ -235,6 +271,71 @@ impl ConnectionManager {
         *self.endpoints.lock().expect("not poisoned") = Arc::new(new_endpoints);
     }
 
+    /// Offers permessage-deflate compression on new chat connections, or stops
+    /// offering it.
+    ///
+    /// Compression is off by default. When enabled, each message is compressed
+    /// independently, so one message's contents can't affect the compressed
+    /// size of another's.
+    pub fn set_chat_compression_enabled(&self, enabled: bool) {
+        *self.chat_compression.lock().expect("not poisoned") = enabled.then_some(DeflateConfig {
+            server_no_context_takeover: true,
+            client_no_context_takeover: true,
+            ..Default::default()
+        });
+    }
+
+    pub(crate) fn chat_compression(&self) -> Option<DeflateConfig> {
+        *self.chat_compression.lock().expect("not poisoned")
+    }
+
+    /// Saves DNS results in files in `directory`, so that they can be reused
+    /// after a restart, or stops saving them if `None`.
+    ///
+    /// The directory must already exist.
+    pub fn set_dns_cache_directory(&self, directory: Option<std::path::PathBuf>) {
+        self.dns_resolver.set_persistent_cache_store(
+            directory.map(|directory| Arc::new(FileDnsCacheStore::new(directory)) as _),
+        );
+    }
+
+    /// Saves route connection outcomes in files in `directory`, so that routes
+    /// that recently failed are still deprioritized after a restart, or stops
+    /// saving them if `None`.
+    ///
+    /// The directory must already exist.
+    pub fn set_route_history_directory(&self, directory: Option<std::path::PathBuf>) {
+        let store = |name: &str| {
+            directory
+                .as_ref()
+                .map(|directory| Arc::new(FileRouteHistoryStore::new(directory.join(name))) as _)
+        };
+        self.connect.blocking_write().set_route_history_stores(
+            store("route-history.json"),
+            store("tunneled-route-history.json"),
+        );
+    }
+
+    /// Reports every subsequent connection attempt to `listener`, or stops
+    /// reporting them if `None`.
+    pub fn set_connection_attempt_listener(
+        &self,
+        listener: Option<Box<dyn telemetry::ConnectionAttemptListener>>,
+    ) {
+        self.connect
+            .blocking_write()
+            .set_telemetry(listener.map(|listener| listener.into_telemetry_callback()));
+    }
+
+    /// Sets an identifier for the current network, which determines which
+    /// saved DNS results can be used.
+    ///
+    /// See [`NetworkIdentity`].
+    pub fn set_network_identity(&self, identity: Option<String>) {
+        self.dns_resolver
+            .set_network_identity(identity.map(NetworkIdentity));
+    }
+
     const NETWORK_CHANGE_DEBOUNCE: Duration = Duration::from_secs(1);
 
     pub fn on_network_change(&self, now: Instant) {
@@ -305,25 +406,23 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
     async fn connect(&self) -> <Self::Env as PpssSetup>::ConnectionResults {
         let ConnectionManager {
             endpoints,
-            transport_connector,
             connect,
             dns_resolver,
             env,
             user_agent,
             ..
         } = &self.connection_manager;
-        let proxy_config: Option<libsignal_net::infra::route::ConnectionProxyConfig> =
-            match (&*transport_connector.lock().expect("not poisoned")).try_into() {
-                Ok(proxy_config) => proxy_config,
-                Err(_) => {
-                    let err = || {
-                        libsignal_net::svr::Error::WebSocketConnect(
-                            WebSocketServiceConnectError::invalid_proxy_configuration(),
-                        )
-                    };
-                    return (Err(err()), Err(err()), Err(err()));
-                }
-            };
+        let proxy_selector = match self.connection_manager.proxy_selector() {
+            Ok(proxy_selector) => proxy_selector,
+            Err(InvalidProxyConfig) => {
+                let err = || {
+                    libsignal_net::svr::Error::WebSocketConnect(
+                        WebSocketServiceConnectError::invalid_proxy_configuration(),
+                    )
+                };
+                return (Err(err()), Err(err()), Err(err()));
+            }
+        };
 
         let (enable_domain_fronting, sgx_ws, nitro_ws, tpm2snp_ws) = {
             let guard = endpoints.lock().expect("not poisoned");
@@ -343,7 +442,7 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
             enable_domain_fronting: EnableDomainFronting,
             endpoint: &EnclaveEndpoint<'static, Enclave>,
             ws_config: libsignal_net::infra::ws2::Config,
-            proxy_config: Option<&libsignal_net::infra::route::ConnectionProxyConfig>,
+            proxy_selector: &ProxySelector,
             auth: &Auth,
         ) -> Result<SvrConnection<Enclave>, libsignal_net::enclave::Error>
         where
@@ -352,14 +451,14 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
             SvrConnection::connect(
                 connect_state,
                 dns_resolver,
-                DirectOrProxyProvider::maybe_proxied(
+                proxy_selector.route_provider_for(
+                    &Host::Domain(endpoint.domain_config.connect.hostname),
                     endpoint
                         .route_provider(enable_domain_fronting)
                         .map_routes(|mut route| {
                             route.fragment.headers.extend([user_agent.as_header()]);
                             route
                         }),
-                    proxy_config.cloned(),
                 ),
                 endpoint
                     .domain_config
@@ -381,7 +480,7 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
                 enable_domain_fronting,
                 sgx,
                 sgx_ws,
-                proxy_config.as_ref(),
+                &proxy_selector,
                 &self.auth,
             ),
             connect_one(
@@ -391,7 +490,7 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
                 enable_domain_fronting,
                 nitro,
                 nitro_ws,
-                proxy_config.as_ref(),
+                &proxy_selector,
                 &self.auth,
             ),
             connect_one(
@@ -401,7 +500,7 @@ impl Svr3Connect for Svr3Client<'_, CurrentVersion> {
                 enable_domain_fronting,
                 tpm2snp,
                 tpm2snp_ws,
-                proxy_config.as_ref(),
+                &proxy_selector,
                 &self.auth,
             ),
         )
@@ -529,6 +628,50 @@ mod test {
         assert_matches!(err, ChatServiceError::InvalidConnectionConfiguration);
     }
 
+    #[test]
+    fn proxy_selector_replaces_single_proxy() {
+        let cm = ConnectionManager::new(Environment::Staging, "test-user-agent");
+        cm.set_invalid_proxy();
+        assert_matches!(cm.proxy_selector(), Err(InvalidProxyConfig));
+
+        let selector =
+            ProxySelector::from_rules("chat.staging.signal.org PROXY proxy.example:8080")
+                .expect("valid");
+        cm.set_proxy_selector(selector);
+        assert_matches!(cm.is_using_proxy(), Ok(true));
+        let selector = cm.proxy_selector().expect("valid");
+        assert_matches!(
+            selector.select(&Host::Domain("chat.staging.signal.org")),
+            Some(_)
+        );
+        assert_matches!(
+            selector.select(&Host::Domain("cdsi.staging.signal.org")),
+            None
+        );
+
+        cm.clear_proxy();
+        assert_matches!(cm.is_using_proxy(), Ok(false));
+    }
+
+    #[test]
+    fn chat_compression_is_opt_in() {
+        let cm = ConnectionManager::new(Environment::Staging, "test-user-agent");
+        assert_eq!(cm.chat_compression(), None);
+
+        cm.set_chat_compression_enabled(true);
+        assert_matches!(
+            cm.chat_compression(),
+            Some(DeflateConfig {
+                server_no_context_takeover: true,
+                client_no_context_takeover: true,
+                ..
+            })
+        );
+
+        cm.set_chat_compression_enabled(false);
+        assert_eq!(cm.chat_compression(), None);
+    }
+
     #[test]
     fn network_change_event_debounced() {
         let cm = ConnectionManager::new(Environment::Staging, "test-user-agent");
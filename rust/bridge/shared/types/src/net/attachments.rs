@@ -0,0 +1,177 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::num::NonZeroU16;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::Arc;
+use std::time::Duration;
+
+use libsignal_net::attachments::{
+    AttachmentTransferError, ResumableDownload, ResumableUpload, TransferProgress,
+};
+use libsignal_net::env::cdn_pin_set;
+use libsignal_net::https_client::{HttpsClient, HttpsClientError};
+use libsignal_net::infra::certs::RootCertificates;
+use libsignal_net::infra::host::Host;
+use libsignal_net::infra::route::{
+    DirectTcpRouteProvider, DomainFrontRouteProvider, HttpVersion, HttpsProvider, TlsRouteProvider,
+};
+use libsignal_net::infra::tcp_ssl::InvalidProxyConfig;
+use tokio::fs::OpenOptions;
+
+use crate::net::chat::HttpRequest;
+use crate::net::ConnectionManager;
+use crate::*;
+
+/// Receives updates on how much of a bridged attachment transfer is done.
+///
+/// See [`TransferProgress::on_progress`] for the meaning of the arguments.
+/// Calls are serialized, but may come from any thread.
+pub trait TransferProgressListener: Send {
+    fn on_progress(&mut self, transferred: u64, total: Option<u64>);
+}
+
+/// Adapts an optional [`TransferProgressListener`] to [`TransferProgress`].
+struct ListenerProgress(Option<std::sync::Mutex<Box<dyn TransferProgressListener>>>);
+
+impl ListenerProgress {
+    fn new(listener: Option<Box<dyn TransferProgressListener>>) -> Self {
+        Self(listener.map(std::sync::Mutex::new))
+    }
+}
+
+impl TransferProgress for ListenerProgress {
+    fn on_progress(&self, transferred: u64, total: Option<u64>) {
+        if let Some(listener) = &self.0 {
+            listener
+                .lock()
+                .expect("not poisoned")
+                .on_progress(transferred, total)
+        }
+    }
+}
+
+/// A connection to a CDN, for transferring attachments.
+#[derive(derive_more::From)]
+pub struct CdnConnection(HttpsClient);
+
+bridge_as_handle!(CdnConnection);
+// The connection's locks are only held while a request is being sent, and
+// the connection doesn't break if one of those panics.
+impl UnwindSafe for CdnConnection {}
+impl RefUnwindSafe for CdnConnection {}
+bridge_as_handle!(ResumableUpload);
+
+impl CdnConnection {
+    /// Connects to `hostname` directly over TLS, or through the proxy selected
+    /// for it, if any.
+    ///
+    /// Signal's own CDNs are pinned (see [`cdn_pin_set`]).
+    pub async fn connect(
+        connection_manager: &ConnectionManager,
+        hostname: &str,
+    ) -> Result<Self, AttachmentTransferError> {
+        let ConnectionManager {
+            dns_resolver,
+            connect,
+            user_agent,
+            ..
+        } = connection_manager;
+
+        let proxy_selector = connection_manager
+            .proxy_selector()
+            .map_err(|InvalidProxyConfig| HttpsClientError::InvalidConnectionConfiguration)?;
+
+        let pin_set = cdn_pin_set(hostname);
+        let destination = Host::<&str>::parse_as_ip_or_domain(hostname);
+        let hostname = Arc::<str>::from(hostname);
+        let route_provider = HttpsProvider::new(
+            Arc::clone(&hostname),
+            HttpVersion::Http1_1,
+            DomainFrontRouteProvider::new(HttpVersion::Http1_1, vec![]),
+            TlsRouteProvider::new(
+                RootCertificates::Native,
+                Host::Domain(Arc::clone(&hostname)),
+                DirectTcpRouteProvider::new(hostname, NonZeroU16::new(443).expect("non-zero")),
+            )
+            .with_pin_set(pin_set),
+        );
+
+        let client = HttpsClient::connect(
+            connect,
+            dns_resolver,
+            proxy_selector.route_provider_for(&destination, route_provider),
+            None,
+            user_agent,
+            "cdn",
+        )
+        .await?;
+        Ok(Self(client))
+    }
+
+    /// Creates an upload of `length` bytes at the path of `request`, with its
+    /// headers.
+    pub async fn create_upload(
+        &self,
+        request: &HttpRequest,
+        length: u64,
+        timeout: Duration,
+    ) -> Result<ResumableUpload, AttachmentTransferError> {
+        let headers = request.headers.lock().expect("not poisoned").clone();
+        ResumableUpload::create(&self.0, request.path.clone(), headers, length, timeout).await
+    }
+
+    /// Uploads the contents of the file at `path`, resuming from wherever the
+    /// CDN reports the upload has reached.
+    pub async fn upload_file(
+        &self,
+        upload: &ResumableUpload,
+        path: &str,
+        progress: Option<Box<dyn TransferProgressListener>>,
+        timeout: Duration,
+    ) -> Result<(), AttachmentTransferError> {
+        let mut file = tokio::fs::File::open(path).await?;
+        if file.metadata().await?.len() != upload.length() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "file length doesn't match the upload",
+            )
+            .into());
+        }
+        let progress = ListenerProgress::new(progress);
+        upload
+            .upload_from(&self.0, &mut file, &progress, timeout)
+            .await
+    }
+
+    /// Downloads the attachment at the path of `request` into the file at
+    /// `path`.
+    ///
+    /// If the file already exists, the download resumes after its contents.
+    /// Only the file is kept between calls, not the attachment's validator, so
+    /// the existing contents can't be checked against the attachment on the
+    /// CDN. Callers must delete a partial file before downloading to it again
+    /// if what's at the path may have changed in the meantime.
+    pub async fn download_to_file(
+        &self,
+        request: &HttpRequest,
+        path: &str,
+        progress: Option<Box<dyn TransferProgressListener>>,
+        timeout: Duration,
+    ) -> Result<(), AttachmentTransferError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let offset = file.metadata().await?.len();
+        let headers = request.headers.lock().expect("not poisoned").clone();
+        let progress = ListenerProgress::new(progress);
+        ResumableDownload::new(request.path.clone(), headers)
+            .with_offset(offset)
+            .download_into(&self.0, &mut file, &progress, timeout)
+            .await
+    }
+}
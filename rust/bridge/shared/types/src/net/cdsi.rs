@@ -6,7 +6,8 @@
 use http::HeaderName;
 use libsignal_net::auth::Auth;
 use libsignal_net::cdsi::{self, CdsiConnection, ClientResponseCollector, Token};
-use libsignal_net::infra::route::{DirectOrProxyProvider, RouteProviderExt};
+use libsignal_net::infra::host::Host;
+use libsignal_net::infra::route::RouteProviderExt;
 use libsignal_net::infra::tcp_ssl::InvalidProxyConfig;
 use libsignal_net::infra::AsHttpHeader as _;
 
@@ -86,19 +87,17 @@ impl CdsiLookup {
             dns_resolver,
             connect,
             user_agent,
-            transport_connector,
             endpoints,
             ..
         } = connection_manager;
 
-        let proxy_config: Option<libsignal_net::infra::route::ConnectionProxyConfig> =
-            (&*transport_connector.lock().expect("not poisoned"))
-                .try_into()
-                .map_err(|InvalidProxyConfig| {
-                    cdsi::LookupError::ConnectTransport(
-                        libsignal_net::infra::errors::TransportConnectError::InvalidConfiguration,
-                    )
-                })?;
+        let proxy_selector = connection_manager
+            .proxy_selector()
+            .map_err(|InvalidProxyConfig| {
+                cdsi::LookupError::ConnectTransport(
+                    libsignal_net::infra::errors::TransportConnectError::InvalidConfiguration,
+                )
+            })?;
 
         let (ws_config, enable_domain_fronting) = {
             let guard = endpoints.lock().expect("not poisoned");
@@ -121,7 +120,10 @@ impl CdsiLookup {
         let connected = CdsiConnection::connect_with(
             connect,
             dns_resolver,
-            DirectOrProxyProvider::maybe_proxied(route_provider, proxy_config),
+            proxy_selector.route_provider_for(
+                &Host::Domain(env_cdsi.domain_config.connect.hostname),
+                route_provider,
+            ),
             confirmation_header_name,
             ws_config,
             &env.cdsi.params,
@@ -20,9 +20,9 @@ use libsignal_net::chat::{
     self, ChatConnection, ChatServiceError, ConnectionInfo, DebugInfo as ChatServiceDebugInfo,
     Request, Response as ChatResponse,
 };
-use libsignal_net::infra::route::{ConnectionProxyConfig, DirectOrProxyProvider};
+use libsignal_net::infra::host::Host;
 use libsignal_net::infra::tcp_ssl::InvalidProxyConfig;
-use libsignal_protocol::Timestamp;
+use libsignal_protocol::{Aci, Timestamp};
 use static_assertions::assert_impl_all;
 
 use crate::net::ConnectionManager;
@@ -70,7 +70,8 @@ assert_impl_all!(MaybeChatConnection: Send, Sync);
 
 impl UnauthenticatedChatConnection {
     pub async fn connect(connection_manager: &ConnectionManager) -> Result<Self, ChatServiceError> {
-        let inner = establish_chat_connection("unauthenticated", connection_manager, None).await?;
+        let inner =
+            establish_chat_connection("unauthenticated", connection_manager, None, None).await?;
         log::info!("connected unauthenticated chat");
         Ok(Self {
             inner: MaybeChatConnection::WaitingForListener(
@@ -82,10 +83,16 @@ impl UnauthenticatedChatConnection {
     }
 }
 impl AuthenticatedChatConnection {
+    /// Connects chat as the account and device identified by `auth`.
+    ///
+    /// If the environment supports Noise tunnels, `noise_private_key` is used
+    /// to identify the client inside them; without it the tunnel is
+    /// anonymous, like for an unauthenticated connection.
     pub async fn connect(
         connection_manager: &ConnectionManager,
         auth: Auth,
         receive_stories: bool,
+        noise_private_key: Option<[u8; 32]>,
     ) -> Result<Self, ChatServiceError> {
         let inner = establish_chat_connection(
             "authenticated",
@@ -94,6 +101,7 @@ impl AuthenticatedChatConnection {
                 auth,
                 receive_stories: receive_stories.into(),
             }),
+            noise_private_key,
         )
         .await?;
         Ok(Self {
@@ -216,21 +224,20 @@ async fn establish_chat_connection(
     auth_type: &'static str,
     connection_manager: &ConnectionManager,
     auth: Option<chat::AuthenticatedChatHeaders>,
+    noise_private_key: Option<[u8; 32]>,
 ) -> Result<chat::PendingChatConnection, ChatServiceError> {
     let ConnectionManager {
         env,
         dns_resolver,
         connect,
         user_agent,
-        transport_connector,
         endpoints,
         ..
     } = connection_manager;
 
-    let proxy_config: Option<ConnectionProxyConfig> =
-        (&*transport_connector.lock().expect("not poisoned"))
-            .try_into()
-            .map_err(|InvalidProxyConfig| ChatServiceError::InvalidConnectionConfiguration)?;
+    let proxy_selector = connection_manager
+        .proxy_selector()
+        .map_err(|InvalidProxyConfig| ChatServiceError::InvalidConnectionConfiguration)?;
 
     let (ws_config, enable_domain_fronting) = {
         let endpoints_guard = endpoints.lock().expect("not poisoned");
@@ -249,12 +256,30 @@ async fn establish_chat_connection(
     let chat_connect = &env.chat_domain_config.connect;
     log::info!("connecting {auth_type} chat");
 
+    let noise_route = chat_connect.noise.as_ref().map(|noise| {
+        let credentials = auth.as_ref().zip(noise_private_key).and_then(
+            |(chat::AuthenticatedChatHeaders { auth, .. }, private_key)| {
+                let Some((aci, device_id)) = parse_chat_username(&auth.username) else {
+                    log::warn!("[{auth_type}] can't identify the client inside a Noise tunnel");
+                    return None;
+                };
+                Some((aci, device_id, private_key))
+            },
+        );
+        match credentials {
+            Some((aci, device_id, private_key)) => {
+                noise.authenticated_route(aci, device_id, private_key)
+            }
+            None => noise.anonymous_route(),
+        }
+    });
+
     ChatConnection::start_connect_with(
         connect,
         dns_resolver,
-        DirectOrProxyProvider::maybe_proxied(
+        proxy_selector.route_provider_for(
+            &Host::Domain(chat_connect.hostname),
             chat_connect.route_provider(enable_domain_fronting),
-            proxy_config,
         ),
         chat_connect
             .confirmation_header_name
@@ -264,8 +289,10 @@ async fn establish_chat_connection(
             local_idle_timeout,
             remote_idle_timeout: remote_idle_disconnect_timeout,
             initial_request_id: 0,
+            compression: connection_manager.chat_compression(),
         },
         auth,
+        noise_route,
         auth_type,
     )
     .inspect(|r| match r {
@@ -275,6 +302,15 @@ async fn establish_chat_connection(
     .await
 }
 
+/// Parses a chat username of the form `<ACI>` or `<ACI>.<device ID>`.
+fn parse_chat_username(username: &str) -> Option<(Aci, u8)> {
+    let (aci, device_id) = match username.split_once('.') {
+        Some((aci, device_id)) => (aci, device_id.parse().ok()?),
+        None => (username, 1),
+    };
+    Some((Aci::parse_from_service_id_string(aci)?, device_id))
+}
+
 pub struct HttpRequest {
     pub method: http::Method,
     pub path: PathAndQuery,
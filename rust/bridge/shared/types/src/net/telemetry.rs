@@ -0,0 +1,86 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use libsignal_net::connect_state::telemetry::{
+    AttemptError, AttemptResult, ConnectionAttemptRecord, TelemetryCallback,
+};
+
+use crate::*;
+
+/// Receives a [`ConnectionAttempt`] for every connection attempt made by a
+/// [`ConnectionManager`](super::ConnectionManager).
+///
+/// Calls are serialized, but may come from any thread.
+pub trait ConnectionAttemptListener: Send {
+    fn connection_attempted(&mut self, attempt: ConnectionAttempt);
+}
+
+impl dyn ConnectionAttemptListener {
+    pub(super) fn into_telemetry_callback(self: Box<Self>) -> TelemetryCallback {
+        let listener = std::sync::Mutex::new(self);
+        std::sync::Arc::new(move |record| {
+            listener
+                .lock()
+                .expect("not poisoned")
+                .connection_attempted(ConnectionAttempt(record))
+        })
+    }
+}
+
+/// Named wrapper around [`ConnectionAttemptRecord`] for the bridges.
+pub struct ConnectionAttempt(pub ConnectionAttemptRecord);
+
+bridge_as_handle!(ConnectionAttempt);
+
+impl ConnectionAttempt {
+    /// How the attempt ended, as bridged.
+    ///
+    /// 0 means it succeeded, 1 that it failed, 2 that the server rejected it,
+    /// and 3 that it was abandoned.
+    pub fn result_code(&self) -> u8 {
+        match self.0.result {
+            AttemptResult::Succeeded => 0,
+            AttemptResult::Failed { .. } => 1,
+            AttemptResult::Rejected { .. } => 2,
+            AttemptResult::Abandoned => 3,
+        }
+    }
+
+    /// The stage at which the attempt failed, as bridged.
+    ///
+    /// 0 means the attempt didn't fail; otherwise the values follow the order
+    /// of [`AttemptError`]'s variants, starting from 1.
+    pub fn error_code(&self) -> u8 {
+        let (AttemptResult::Failed { error } | AttemptResult::Rejected { error }) = self.0.result
+        else {
+            return 0;
+        };
+        match error {
+            AttemptError::Dns => 1,
+            AttemptError::Tcp => 2,
+            AttemptError::Tls => 3,
+            AttemptError::CertificatePinMismatch => 4,
+            AttemptError::Proxy => 5,
+            AttemptError::Timeout => 6,
+            AttemptError::HttpStatus(_) => 7,
+            AttemptError::WebSocket => 8,
+            AttemptError::Other => 9,
+        }
+    }
+
+    /// The HTTP status the websocket upgrade got, or 0 if it didn't fail with
+    /// one.
+    pub fn http_status(&self) -> u16 {
+        match self.0.result {
+            AttemptResult::Failed {
+                error: AttemptError::HttpStatus(status),
+            }
+            | AttemptResult::Rejected {
+                error: AttemptError::HttpStatus(status),
+            } => status,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::sync::Arc;
+
+use neon::context::FunctionContext;
+use neon::event::Channel;
+use neon::handle::{Handle, Root};
+use neon::prelude::{Context, Finalize, JsObject, Object};
+use neon::result::NeonResult;
+use neon::types::JsValue;
+use signal_neon_futures::call_method;
+
+use crate::net::attachments::TransferProgressListener;
+
+#[derive(Clone)]
+pub struct NodeTransferProgressListener {
+    js_channel: Channel,
+    callback_object: Arc<Root<JsObject>>,
+}
+
+impl TransferProgressListener for NodeTransferProgressListener {
+    fn on_progress(&mut self, transferred: u64, total: Option<u64>) {
+        let callback_object_shared = self.callback_object.clone();
+        self.js_channel.send(move |mut cx| {
+            let callback = callback_object_shared.to_inner(&mut cx);
+            // Attachments are far smaller than 2^53 bytes, so numbers are exact.
+            let transferred = cx.number(transferred as f64).upcast::<JsValue>();
+            let total = match total {
+                Some(total) => cx.number(total as f64).upcast(),
+                None => cx.null().upcast(),
+            };
+            let _result = call_method(&mut cx, callback, "_on_progress", [transferred, total])?;
+            callback_object_shared.finalize(&mut cx);
+            Ok(())
+        });
+    }
+}
+
+impl NodeTransferProgressListener {
+    pub(crate) fn new(cx: &mut FunctionContext, callbacks: Handle<JsObject>) -> NeonResult<Self> {
+        let mut channel = cx.channel();
+        channel.unref(cx);
+
+        Ok(Self {
+            js_channel: channel,
+            callback_object: Arc::new(callbacks.root(cx)),
+        })
+    }
+
+    pub(crate) fn make_listener(&self) -> Box<dyn TransferProgressListener> {
+        Box::new(self.clone())
+    }
+}
+
+impl Finalize for NodeTransferProgressListener {
+    fn finalize<'a, C: neon::prelude::Context<'a>>(self, cx: &mut C) {
+        self.callback_object.finalize(cx);
+    }
+}
@@ -19,8 +19,12 @@ use paste::paste;
 use super::*;
 use crate::io::{InputStream, SyncInputStream};
 use crate::message_backup::MessageBackupValidationOutcome;
+use crate::net::attachments::TransferProgressListener;
 use crate::net::chat::ChatListener;
+use crate::net::telemetry::ConnectionAttemptListener;
+use crate::node::attachments::NodeTransferProgressListener;
 use crate::node::chat::NodeChatListener;
+use crate::node::telemetry::NodeConnectionAttemptListener;
 use crate::support::{extend_lifetime, Array, AsType, FixedLengthBincodeSerializable, Serialized};
 
 /// Converts arguments from their JavaScript form to their Rust form.
@@ -690,6 +694,58 @@ impl<'a> AsyncArgTypeInfo<'a> for Box<dyn ChatListener> {
     }
 }
 
+impl<'storage, 'context: 'storage> ArgTypeInfo<'storage, 'context>
+    for Box<dyn ConnectionAttemptListener>
+{
+    type ArgType = JsObject;
+    type StoredType = NodeConnectionAttemptListener;
+
+    fn borrow(
+        cx: &mut FunctionContext<'context>,
+        foreign: Handle<'context, Self::ArgType>,
+    ) -> NeonResult<Self::StoredType> {
+        NodeConnectionAttemptListener::new(cx, foreign)
+    }
+
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.make_listener()
+    }
+}
+
+impl<'storage, 'context: 'storage> ArgTypeInfo<'storage, 'context>
+    for Box<dyn TransferProgressListener>
+{
+    type ArgType = JsObject;
+    type StoredType = NodeTransferProgressListener;
+
+    fn borrow(
+        cx: &mut FunctionContext<'context>,
+        foreign: Handle<'context, Self::ArgType>,
+    ) -> NeonResult<Self::StoredType> {
+        NodeTransferProgressListener::new(cx, foreign)
+    }
+
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.make_listener()
+    }
+}
+
+impl<'a> AsyncArgTypeInfo<'a> for Box<dyn TransferProgressListener> {
+    type ArgType = JsObject;
+    type StoredType = NodeTransferProgressListener;
+
+    fn save_async_arg(
+        cx: &mut FunctionContext,
+        foreign: Handle<Self::ArgType>,
+    ) -> NeonResult<Self::StoredType> {
+        NodeTransferProgressListener::new(cx, foreign)
+    }
+
+    fn load_async_arg(stored: &'a mut Self::StoredType) -> Self {
+        stored.make_listener()
+    }
+}
+
 impl<'storage, 'context: 'storage> ArgTypeInfo<'storage, 'context>
     for &'storage mut dyn SyncInputStream
 {
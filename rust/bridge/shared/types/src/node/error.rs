@@ -415,6 +415,7 @@ impl SignalNodeError for libsignal_net::chat::ChatServiceError {
             ChatServiceError::Disconnected => (Some("ChatServiceInactive"), None),
             ChatServiceError::AppExpired => (Some("AppExpired"), None),
             ChatServiceError::DeviceDeregistered => (Some("DeviceDelinked"), None),
+            ChatServiceError::CertificatePinMismatch => (Some("CertificatePinMismatch"), None),
             ChatServiceError::RetryLater {
                 retry_after_seconds,
             } => rate_limited_error(retry_after_seconds),
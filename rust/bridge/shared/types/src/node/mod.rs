@@ -39,8 +39,10 @@ pub use futures::*;
 mod io;
 pub use io::*;
 
+mod attachments;
 mod chat;
 mod storage;
+mod telemetry;
 
 pub use storage::*;
 
@@ -0,0 +1,57 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::sync::Arc;
+
+use neon::context::FunctionContext;
+use neon::event::Channel;
+use neon::handle::{Handle, Root};
+use neon::prelude::{Context, Finalize, JsObject, Object};
+use neon::result::NeonResult;
+use signal_neon_futures::call_method;
+
+use crate::net::telemetry::{ConnectionAttempt, ConnectionAttemptListener};
+use crate::node::ResultTypeInfo;
+
+#[derive(Clone)]
+pub struct NodeConnectionAttemptListener {
+    js_channel: Channel,
+    callback_object: Arc<Root<JsObject>>,
+}
+
+impl ConnectionAttemptListener for NodeConnectionAttemptListener {
+    fn connection_attempted(&mut self, attempt: ConnectionAttempt) {
+        let callback_object_shared = self.callback_object.clone();
+        self.js_channel.send(move |mut cx| {
+            let callback = callback_object_shared.to_inner(&mut cx);
+            let attempt = attempt.convert_into(&mut cx)?;
+            let _result = call_method(&mut cx, callback, "_connection_attempt", [attempt])?;
+            callback_object_shared.finalize(&mut cx);
+            Ok(())
+        });
+    }
+}
+
+impl NodeConnectionAttemptListener {
+    pub(crate) fn new(cx: &mut FunctionContext, callbacks: Handle<JsObject>) -> NeonResult<Self> {
+        let mut channel = cx.channel();
+        channel.unref(cx);
+
+        Ok(Self {
+            js_channel: channel,
+            callback_object: Arc::new(callbacks.root(cx)),
+        })
+    }
+
+    pub(crate) fn make_listener(&self) -> Box<dyn ConnectionAttemptListener> {
+        Box::new(self.clone())
+    }
+}
+
+impl Finalize for NodeConnectionAttemptListener {
+    fn finalize<'a, C: neon::prelude::Context<'a>>(self, cx: &mut C) {
+        self.callback_object.finalize(cx);
+    }
+}
@@ -4,7 +4,7 @@
 //
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use libsignal_core::curve::KeyPair;
+use libsignal_core::curve::{KeyPair, PublicKey};
 use rand::{thread_rng, Rng};
 
 pub fn generation(c: &mut Criterion) {
@@ -37,6 +37,22 @@ pub fn signatures(c: &mut Criterion) {
     c.bench_function("verify signature", |b| {
         b.iter(|| alice_key.public_key.verify_signature(&some_data, &sig))
     });
+
+    let batch: Vec<_> = (0..64)
+        .map(|_| {
+            let key = KeyPair::generate(rng);
+            let sig = key.calculate_signature(&some_data, rng).unwrap();
+            (key.public_key, sig)
+        })
+        .collect();
+    let items: Vec<_> = batch
+        .iter()
+        .map(|(key, sig)| (key, &some_data[..], &sig[..]))
+        .collect();
+
+    c.bench_function("verify 64 signatures in a batch", |b| {
+        b.iter(|| PublicKey::verify_signatures_batch(&items, rng))
+    });
 }
 
 criterion_group!(benches, generation, key_agreement, signatures);
@@ -136,6 +136,26 @@ impl PublicKey {
         }
     }
 
+    /// Verifies many signatures at once, returning `true` only if all of them are valid.
+    ///
+    /// Each item is a public key, the message it signed, and the signature. This is faster than
+    /// calling [`Self::verify_signature`] on each item, but does not identify which signature was
+    /// bad if the batch fails; callers that need that can fall back to checking individually.
+    pub fn verify_signatures_batch<R: CryptoRng + Rng>(
+        items: &[(&PublicKey, &[u8], &[u8])],
+        csprng: &mut R,
+    ) -> bool {
+        let mut djb_items = Vec::with_capacity(items.len());
+        for (public_key, message, signature) in items {
+            let PublicKeyData::DjbPublicKey(pub_key) = &public_key.key;
+            let Ok(signature) = (*signature).try_into() else {
+                return false;
+            };
+            djb_items.push((pub_key, std::slice::from_ref(message), signature));
+        }
+        curve25519::PrivateKey::verify_signatures_batch(&djb_items, csprng)
+    }
+
     fn key_data(&self) -> &[u8] {
         match &self.key {
             PublicKeyData::DjbPublicKey(ref k) => k.as_ref(),
@@ -271,6 +291,31 @@ impl PrivateKey {
         }
     }
 
+    /// Calculates a signature that depends only on the key and the message.
+    ///
+    /// This is meant for reproducible test vectors; prefer [`Self::calculate_signature`]
+    /// otherwise.
+    pub fn calculate_deterministic_signature(
+        &self,
+        message: &[u8],
+    ) -> Result<Box<[u8]>, CurveError> {
+        self.calculate_deterministic_signature_for_multipart_message(&[message])
+    }
+
+    pub fn calculate_deterministic_signature_for_multipart_message(
+        &self,
+        message: &[&[u8]],
+    ) -> Result<Box<[u8]>, CurveError> {
+        match self.key {
+            PrivateKeyData::DjbPrivateKey(k) => {
+                let private_key = curve25519::PrivateKey::from(k);
+                Ok(Box::new(
+                    private_key.calculate_deterministic_signature(message),
+                ))
+            }
+        }
+    }
+
     pub fn calculate_agreement(&self, their_key: &PublicKey) -> Result<Box<[u8]>, CurveError> {
         match (self.key, their_key.key) {
             (PrivateKeyData::DjbPrivateKey(priv_key), PublicKeyData::DjbPublicKey(pub_key)) => {
@@ -390,6 +435,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_batch_signatures() -> Result<(), CurveError> {
+        let mut csprng = OsRng;
+        let key_pairs: Vec<_> = (0..5).map(|_| KeyPair::generate(&mut csprng)).collect();
+        let message = b"batch";
+        let signatures = key_pairs
+            .iter()
+            .map(|pair| pair.private_key.calculate_deterministic_signature(message))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut items: Vec<_> = key_pairs
+            .iter()
+            .zip(&signatures)
+            .map(|(pair, signature)| (&pair.public_key, &message[..], &signature[..]))
+            .collect();
+        assert!(PublicKey::verify_signatures_batch(&items, &mut csprng));
+
+        items.swap(0, 1);
+        assert!(PublicKey::verify_signatures_batch(&items, &mut csprng));
+
+        items[0].0 = items[1].0;
+        assert!(!PublicKey::verify_signatures_batch(&items, &mut csprng));
+        items.swap(0, 1);
+        items[0].0 = &key_pairs[0].public_key;
+
+        items[2].2 = &signatures[2][..63];
+        assert!(!PublicKey::verify_signatures_batch(&items, &mut csprng));
+
+        items[2].2 = &signatures[3];
+        assert!(!PublicKey::verify_signatures_batch(&items, &mut csprng));
+
+        Ok(())
+    }
+
     #[test]
     fn test_decode_size() -> Result<(), CurveError> {
         let mut csprng = OsRng;
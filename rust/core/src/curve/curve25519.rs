@@ -3,11 +3,12 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
-use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::constants::{ED25519_BASEPOINT_POINT, ED25519_BASEPOINT_TABLE};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
 use curve25519_dalek::montgomery::MontgomeryPoint;
 use curve25519_dalek::scalar;
 use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
 use rand::{CryptoRng, Rng};
 use sha2::{Digest, Sha512};
 use subtle::ConstantTimeEq;
@@ -18,6 +19,13 @@ pub const PRIVATE_KEY_LENGTH: usize = 32;
 pub const PUBLIC_KEY_LENGTH: usize = 32;
 pub const SIGNATURE_LENGTH: usize = 64;
 
+/// A public key, the (multipart) message it signed, and the signature.
+pub type SignatureBatchItem<'a> = (
+    &'a [u8; PUBLIC_KEY_LENGTH],
+    &'a [&'a [u8]],
+    &'a [u8; SIGNATURE_LENGTH],
+);
+
 #[derive(Clone)]
 pub struct PrivateKey {
     secret: StaticSecret,
@@ -65,7 +73,26 @@ impl PrivateKey {
     {
         let mut random_bytes = [0u8; 64];
         csprng.fill_bytes(&mut random_bytes);
+        self.calculate_signature_with_nonce_input(&random_bytes, message)
+    }
+
+    /// Calculates an XEdDSA signature without any randomness, so that the same key and message
+    /// always produce the same signature.
+    ///
+    /// This is equivalent to [`Self::calculate_signature`] with the 64 random bytes ("Z" in the
+    /// XEdDSA specification) fixed to zero, which makes the nonce a pure function of the private
+    /// key and the message, as in Ed25519. That's still secure, but gives up the specification's
+    /// hedging against fault attacks, so prefer the randomized form outside of test vectors and
+    /// other cases where reproducibility is required.
+    pub fn calculate_deterministic_signature(&self, message: &[&[u8]]) -> [u8; SIGNATURE_LENGTH] {
+        self.calculate_signature_with_nonce_input(&[0; 64], message)
+    }
 
+    fn calculate_signature_with_nonce_input(
+        &self,
+        random_bytes: &[u8; 64],
+        message: &[&[u8]],
+    ) -> [u8; SIGNATURE_LENGTH] {
         let key_data = self.secret.to_bytes();
         let a = Scalar::from_bytes_mod_order(key_data);
         let ed_public_key_point = &a * ED25519_BASEPOINT_TABLE;
@@ -112,12 +139,100 @@ impl PrivateKey {
         message: &[&[u8]],
         signature: &[u8; SIGNATURE_LENGTH],
     ) -> bool {
+        SignatureComponents::new(their_public_key, message, signature)
+            .is_some_and(|components| components.verify())
+    }
+
+    /// Verifies many XEdDSA signatures at once, returning `true` only if all of them are valid.
+    ///
+    /// This checks a random linear combination of the individual verification equations, which
+    /// is considerably faster than verifying each signature separately. If the batch fails, it
+    /// does not say which signature was bad; callers that need to know should fall back to
+    /// [`Self::verify_signature`].
+    ///
+    /// The combined check is done in the prime-order subgroup (the "cofactored" equation), while
+    /// single verification is cofactorless. The two only agree when neither the key nor R has a
+    /// small-order component, so any item that does is verified on its own instead. The result
+    /// is therefore always the same as calling [`Self::verify_signature`] on every item.
+    pub fn verify_signatures_batch<R>(items: &[SignatureBatchItem<'_>], csprng: &mut R) -> bool
+    where
+        R: CryptoRng + Rng,
+    {
+        // We're checking that sum(z_i * (s_i * B - h_i * A_i - R_i)) is the identity, for random
+        // 128-bit z_i. Collect the scalars and points for a single multiscalar multiplication.
+        let mut basepoint_scalar = Scalar::ZERO;
+        let mut scalars = Vec::with_capacity(2 * items.len());
+        let mut points = Vec::with_capacity(2 * items.len());
+
+        for (their_public_key, message, signature) in items {
+            let Some(components) = SignatureComponents::new(their_public_key, message, signature)
+            else {
+                return false;
+            };
+            // Single verification compares encodings, so reject non-canonical encodings of R.
+            let Some(cap_r_point) = CompressedEdwardsY(components.cap_r)
+                .decompress()
+                .filter(|point| point.compress().as_bytes() == &components.cap_r)
+            else {
+                return false;
+            };
+            // Multiplying by the cofactor below would hide a small-order component, so such
+            // items can't be batched without changing the result.
+            if !components.ed_pub_key_point.is_torsion_free() || !cap_r_point.is_torsion_free() {
+                if !components.verify() {
+                    return false;
+                }
+                continue;
+            }
+
+            let SignatureComponents {
+                ed_pub_key_point,
+                cap_r: _,
+                s,
+                h,
+            } = components;
+            let z = Scalar::from(csprng.gen::<u128>());
+            basepoint_scalar += z * s;
+            scalars.push(-(z * h));
+            points.push(ed_pub_key_point);
+            scalars.push(-z);
+            points.push(cap_r_point);
+        }
+
+        scalars.push(basepoint_scalar);
+        points.push(ED25519_BASEPOINT_POINT);
+
+        EdwardsPoint::vartime_multiscalar_mul(scalars, points)
+            .mul_by_cofactor()
+            .is_identity()
+    }
+
+    pub fn derive_public_key_bytes(&self) -> [u8; PUBLIC_KEY_LENGTH] {
+        *PublicKey::from(&self.secret).as_bytes()
+    }
+
+    pub fn private_key_bytes(&self) -> [u8; PRIVATE_KEY_LENGTH] {
+        self.secret.to_bytes()
+    }
+}
+
+/// The parts of an XEdDSA signature needed for verification, extracted and range-checked.
+struct SignatureComponents {
+    ed_pub_key_point: EdwardsPoint,
+    cap_r: [u8; 32],
+    s: Scalar,
+    h: Scalar,
+}
+
+impl SignatureComponents {
+    fn new(
+        their_public_key: &[u8; PUBLIC_KEY_LENGTH],
+        message: &[&[u8]],
+        signature: &[u8; SIGNATURE_LENGTH],
+    ) -> Option<Self> {
         let mont_point = MontgomeryPoint(*their_public_key);
         let ed_pub_key_point =
-            match mont_point.to_edwards((signature[SIGNATURE_LENGTH - 1] & 0b1000_0000_u8) >> 7) {
-                Some(x) => x,
-                None => return false,
-            };
+            mont_point.to_edwards((signature[SIGNATURE_LENGTH - 1] & 0b1000_0000_u8) >> 7)?;
         let cap_a = ed_pub_key_point.compress();
         let mut cap_r = [0u8; 32];
         cap_r.copy_from_slice(&signature[..32]);
@@ -125,9 +240,8 @@ impl PrivateKey {
         s.copy_from_slice(&signature[32..]);
         s[31] &= 0b0111_1111_u8;
         if (s[31] & 0b1110_0000_u8) != 0 {
-            return false;
+            return None;
         }
-        let minus_cap_a = -ed_pub_key_point;
 
         let mut hash = Sha512::new();
         // Explicitly pass a slice to avoid generating multiple versions of update().
@@ -138,22 +252,23 @@ impl PrivateKey {
         }
         let h = Scalar::from_hash(hash);
 
-        let cap_r_check_point = EdwardsPoint::vartime_double_scalar_mul_basepoint(
-            &h,
-            &minus_cap_a,
-            &Scalar::from_bytes_mod_order(s),
-        );
-        let cap_r_check = cap_r_check_point.compress();
-
-        bool::from(cap_r_check.as_bytes().ct_eq(&cap_r))
+        Some(Self {
+            ed_pub_key_point,
+            cap_r,
+            s: Scalar::from_bytes_mod_order(s),
+            h,
+        })
     }
 
-    pub fn derive_public_key_bytes(&self) -> [u8; PUBLIC_KEY_LENGTH] {
-        *PublicKey::from(&self.secret).as_bytes()
-    }
+    /// Checks the (cofactorless) verification equation R = s * B - h * A.
+    fn verify(&self) -> bool {
+        let minus_cap_a = -self.ed_pub_key_point;
 
-    pub fn private_key_bytes(&self) -> [u8; PRIVATE_KEY_LENGTH] {
-        self.secret.to_bytes()
+        let cap_r_check_point =
+            EdwardsPoint::vartime_double_scalar_mul_basepoint(&self.h, &minus_cap_a, &self.s);
+        let cap_r_check = cap_r_check_point.compress();
+
+        bool::from(cap_r_check.as_bytes().ct_eq(&self.cap_r))
     }
 }
 
@@ -303,4 +418,138 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_deterministic_signatures() {
+        let mut csprng = OsRng;
+        let key = PrivateKey::new(&mut csprng);
+        let message = b"reproducible";
+
+        let signature = key.calculate_deterministic_signature(&[message]);
+        assert_eq!(
+            signature,
+            key.calculate_deterministic_signature(&[&message[..4], &message[4..]])
+        );
+        assert_ne!(signature, key.calculate_signature(&mut csprng, &[message]));
+        assert!(
+            PrivateKey::verify_signature(&key.derive_public_key_bytes(), &[message], &signature),
+            "signature check failed"
+        );
+    }
+
+    #[test]
+    fn test_batch_signatures() {
+        let mut csprng = OsRng;
+        let entries: Vec<_> = (0..20)
+            .map(|i| {
+                let mut message = vec![0u8; i];
+                csprng.fill_bytes(&mut message);
+                let key = PrivateKey::new(&mut csprng);
+                let signature = key.calculate_signature(&mut csprng, &[&message]);
+                (key.derive_public_key_bytes(), message, signature)
+            })
+            .collect();
+
+        let check = |entries: &[([u8; 32], Vec<u8>, [u8; 64])]| {
+            let messages: Vec<[&[u8]; 1]> = entries.iter().map(|(_, m, _)| [&m[..]]).collect();
+            let items: Vec<_> = entries
+                .iter()
+                .zip(&messages)
+                .map(|((public, _, signature), message)| (public, &message[..], signature))
+                .collect();
+            PrivateKey::verify_signatures_batch(&items, &mut OsRng)
+        };
+
+        assert!(check(&[]), "empty batch should pass");
+        assert!(check(&entries), "batch check failed");
+
+        for i in [0, 7, 19] {
+            let mut bad_entries = entries.clone();
+            bad_entries[i].2[3] ^= 0x01;
+            assert!(!check(&bad_entries), "bad signature passed");
+
+            let mut bad_entries = entries.clone();
+            bad_entries[i].2[40] ^= 0x01;
+            assert!(!check(&bad_entries), "bad signature passed");
+
+            let mut bad_entries = entries.clone();
+            bad_entries[i].1.push(0);
+            assert!(!check(&bad_entries), "bad message passed");
+
+            let mut bad_entries = entries.clone();
+            bad_entries[i].0 = entries[(i + 1) % entries.len()].0;
+            assert!(!check(&bad_entries), "wrong key passed");
+        }
+    }
+
+    /// Signs `message` with `a * B + key_torsion` as the public key and `r * B + nonce_torsion`
+    /// as R, picking `r` so that the key's torsion component cancels out.
+    fn sign_with_torsion(
+        key_torsion: EdwardsPoint,
+        nonce_torsion: EdwardsPoint,
+        message: &[u8],
+    ) -> ([u8; PUBLIC_KEY_LENGTH], [u8; SIGNATURE_LENGTH]) {
+        let random_scalar = || {
+            let mut bytes = [0u8; 64];
+            OsRng.fill_bytes(&mut bytes);
+            Scalar::from_bytes_mod_order_wide(&bytes)
+        };
+        let a = random_scalar();
+        let cap_a = ED25519_BASEPOINT_TABLE * &a + key_torsion;
+        let cap_a_bytes = cap_a.compress();
+        loop {
+            let r = random_scalar();
+            let cap_r = (ED25519_BASEPOINT_TABLE * &r + nonce_torsion).compress();
+            let mut hash = Sha512::new();
+            hash.update(cap_r.as_bytes());
+            hash.update(cap_a_bytes.as_bytes());
+            hash.update(message);
+            let h = Scalar::from_hash(hash);
+            if !(h * key_torsion).is_identity() {
+                continue;
+            }
+
+            let mut signature = [0u8; SIGNATURE_LENGTH];
+            signature[..32].copy_from_slice(cap_r.as_bytes());
+            signature[32..].copy_from_slice((r + h * a).as_bytes());
+            signature[SIGNATURE_LENGTH - 1] |= cap_a_bytes.as_bytes()[31] & 0b1000_0000_u8;
+            return (cap_a.to_montgomery().to_bytes(), signature);
+        }
+    }
+
+    #[test]
+    fn test_batch_signatures_with_small_order_components() {
+        use curve25519_dalek::constants::EIGHT_TORSION;
+        use curve25519_dalek::traits::Identity;
+
+        let message = b"torsion";
+        let key = PrivateKey::new(&mut OsRng);
+        let good = (
+            key.derive_public_key_bytes(),
+            key.calculate_signature(&mut OsRng, &[message]),
+        );
+        // Only valid under the cofactored equation: R is off by a point of order 8.
+        let bad_nonce = sign_with_torsion(EdwardsPoint::identity(), EIGHT_TORSION[1], message);
+        // Valid under both equations even though the key has a small-order component.
+        let odd_key = sign_with_torsion(EIGHT_TORSION[1], EdwardsPoint::identity(), message);
+
+        for (public_key, signature, expected) in [
+            (bad_nonce.0, bad_nonce.1, false),
+            (odd_key.0, odd_key.1, true),
+        ] {
+            assert_eq!(
+                PrivateKey::verify_signature(&public_key, &[message], &signature),
+                expected
+            );
+            let items = [
+                (&good.0, &[&message[..]][..], &good.1),
+                (&public_key, &[&message[..]][..], &signature),
+            ];
+            assert_eq!(
+                PrivateKey::verify_signatures_batch(&items, &mut OsRng),
+                expected,
+                "batch disagrees with single verification"
+            );
+        }
+    }
 }
@@ -3,10 +3,36 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+mod metadata;
+
 use std::fmt::Display;
 use std::num::{NonZeroU64, ParseIntError};
 use std::str::FromStr;
 
+/// The maximum number of digits in an E.164 number, including the country calling code.
+const MAX_DIGITS: usize = 15;
+
+/// Error returned by [`E164::parse_with_region`].
+///
+/// Deliberately does not include any part of the input, which is user data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, displaydoc::Display)]
+pub enum E164ParseError {
+    /// phone number contained no digits
+    Empty,
+    /// phone number contained an invalid character
+    InvalidCharacter,
+    /// unknown default region
+    UnknownRegion,
+    /// unknown country calling code
+    UnknownCallingCode,
+    /// phone number is too short for its country calling code
+    TooShort,
+    /// phone number is too long for its country calling code
+    TooLong,
+}
+
+impl std::error::Error for E164ParseError {}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, derive_more::Into)]
 pub struct E164(NonZeroU64);
 
@@ -22,6 +48,76 @@ impl E164 {
     pub fn from_be_bytes(bytes: [u8; std::mem::size_of::<u64>()]) -> Option<Self> {
         NonZeroU64::new(u64::from_be_bytes(bytes)).map(Self)
     }
+
+    /// Parses and normalizes a phone number as a user might enter it.
+    ///
+    /// Numbers starting with `+`, or with `default_region`'s international dialing prefix, are
+    /// treated as international. Anything else is treated as a national number in
+    /// `default_region` (an ISO 3166-1 alpha-2 code such as "US"), with the region's trunk
+    /// prefix removed if present. Spaces and the punctuation commonly used to format phone
+    /// numbers (`-`, `.`, `/`, and parentheses) are ignored.
+    ///
+    /// The country calling code and the length of the remaining number are checked against
+    /// embedded numbering-plan metadata; this does not guarantee the number is actually assigned.
+    pub fn parse_with_region(input: &str, default_region: &str) -> Result<Self, E164ParseError> {
+        let region = metadata::for_region(default_region).ok_or(E164ParseError::UnknownRegion)?;
+
+        let input = input.trim();
+        let (is_international, input) = match input.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+        let mut digits = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '0'..='9' => digits.push(c),
+                ' ' | '\u{a0}' | '-' | '.' | '/' | '(' | ')' => {}
+                _ => return Err(E164ParseError::InvalidCharacter),
+            }
+        }
+        if digits.is_empty() {
+            return Err(E164ParseError::Empty);
+        }
+
+        let international_digits = if is_international {
+            digits
+        } else if let Some(rest) = digits.strip_prefix(region.international_prefix) {
+            rest.to_owned()
+        } else {
+            let national_number = region
+                .national_prefix
+                .and_then(|prefix| digits.strip_prefix(prefix))
+                // Only treat the leading digits as a trunk prefix if what's left is plausible.
+                // Otherwise they're presumably part of the number itself.
+                .filter(|rest| region.national_number_lengths.contains(&rest.len()))
+                .unwrap_or(&digits);
+            format!("{}{}", region.calling_code, national_number)
+        };
+
+        Self::validate_international(&international_digits)
+    }
+
+    fn validate_international(digits: &str) -> Result<Self, E164ParseError> {
+        let (calling_code, regions) =
+            metadata::split_calling_code(digits).ok_or(E164ParseError::UnknownCallingCode)?;
+        let national_len = digits.len() - calling_code.to_string().len();
+
+        let (min, max) = regions.fold((usize::MAX, 0), |(min, max), region| {
+            let lengths = &region.national_number_lengths;
+            (min.min(*lengths.start()), max.max(*lengths.end()))
+        });
+        if national_len < min {
+            return Err(E164ParseError::TooShort);
+        }
+        if national_len > max || digits.len() > MAX_DIGITS {
+            return Err(E164ParseError::TooLong);
+        }
+
+        let number = digits
+            .parse()
+            .expect("at most 15 digits, starting with a non-zero calling code");
+        Ok(Self(number))
+    }
 }
 
 impl FromStr for E164 {
@@ -44,8 +140,9 @@ mod test {
 
     use assert_matches::assert_matches;
     use proptest::{prop_compose, proptest};
+    use test_case::test_case;
 
-    use super::E164;
+    use super::{E164ParseError, E164};
 
     prop_compose! {
         fn gen_e164()(num in 18005550101_u64..=18995550199) -> E164 {
@@ -70,4 +167,47 @@ mod test {
             assert_matches!(E164::from_str(&repr), Ok(actual) => assert_eq!(actual, e164));
         });
     }
+
+    #[test_case("+1 (415) 555-0100", "US" => "+14155550100"; "international")]
+    #[test_case("+14155550100", "DE" => "+14155550100"; "international ignores region")]
+    #[test_case("(415) 555-0100", "US" => "+14155550100"; "national")]
+    #[test_case("1-415-555-0100", "us" => "+14155550100"; "national with trunk prefix")]
+    #[test_case("011 44 20 7946 0018", "US" => "+442079460018"; "international prefix")]
+    #[test_case("020 7946 0018", "GB" => "+442079460018"; "GB trunk prefix")]
+    #[test_case("06 12 34 56 78", "FR" => "+33612345678"; "FR mobile")]
+    #[test_case("06 12 345 678", "IT" => "+390612345678"; "IT keeps leading zero")]
+    #[test_case("8 (912) 345-67-89", "RU" => "+79123456789"; "RU trunk prefix")]
+    #[test_case("0011 1 415 555 0100", "AU" => "+14155550100"; "AU international prefix")]
+    #[test_case("71 234 567", "TN" => "+21671234567"; "TN no trunk prefix")]
+    #[test_case("22 123456", "CY" => "+35722123456"; "CY")]
+    #[test_case("024 123 4567", "GH" => "+233241234567"; "GH trunk prefix")]
+    #[test_case("810 376 312 345", "BY" => "+376312345"; "BY international prefix")]
+    #[test_case("+800 1234 5678", "US" => "+80012345678"; "non-geographic")]
+    fn parse_with_region(input: &str, region: &str) -> String {
+        E164::parse_with_region(input, region)
+            .expect("valid")
+            .to_string()
+    }
+
+    #[test_case("", "US" => E164ParseError::Empty; "empty")]
+    #[test_case("+", "US" => E164ParseError::Empty; "only plus")]
+    #[test_case("415-555-O1OO", "US" => E164ParseError::InvalidCharacter; "letters")]
+    #[test_case("++14155550100", "US" => E164ParseError::InvalidCharacter; "double plus")]
+    #[test_case("4155550100", "XX" => E164ParseError::UnknownRegion; "unknown region")]
+    #[test_case("+999 1234567", "US" => E164ParseError::UnknownCallingCode; "unknown calling code")]
+    #[test_case("+0 1 415 555 0100", "US" => E164ParseError::UnknownCallingCode; "leading zero")]
+    #[test_case("4155550100", "001" => E164ParseError::UnknownRegion; "non-geographic region")]
+    #[test_case("555-0100", "US" => E164ParseError::TooShort; "too short")]
+    #[test_case("+1 415 555 01000", "US" => E164ParseError::TooLong; "too long")]
+    #[test_case("+49 1234 5678 9012 345", "DE" => E164ParseError::TooLong; "longer than E.164 allows")]
+    fn parse_with_region_invalid(input: &str, region: &str) -> E164ParseError {
+        E164::parse_with_region(input, region).expect_err("invalid")
+    }
+
+    #[test]
+    fn parse_with_region_accepts_formatted_output() {
+        proptest!(|(e164 in gen_e164(), region in "US|CA|GB|DE|JP")| {
+            assert_matches!(E164::parse_with_region(&e164.to_string(), &region), Ok(actual) => assert_eq!(actual, e164));
+        });
+    }
 }
@@ -0,0 +1,396 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Embedded numbering-plan metadata used by [`E164::parse_with_region`](super::E164).
+//!
+//! Every assigned country calling code is covered, but only with the few details of each region's
+//! numbering plan needed to reject obviously malformed input and to turn national-format numbers
+//! into international ones. It is not meant to decide whether a number is in service.
+
+use std::ops::RangeInclusive;
+
+/// Numbering-plan information for a single region.
+#[derive(Debug)]
+pub(super) struct RegionMetadata {
+    /// ISO 3166-1 alpha-2 code, upper-case.
+    pub region: &'static str,
+    /// Country calling code, as dialed after `+`.
+    pub calling_code: u16,
+    /// Trunk prefix dialed before national numbers within the region, if any.
+    pub national_prefix: Option<&'static str>,
+    /// Prefix dialed within the region to reach an international number.
+    pub international_prefix: &'static str,
+    /// Valid lengths for the national significant number (i.e. excluding the calling code).
+    pub national_number_lengths: RangeInclusive<usize>,
+}
+
+macro_rules! regions {
+    ($($region:literal => $cc:literal, $national:expr, $intl:literal, $lengths:expr;)*) => {
+        &[$(RegionMetadata {
+            region: $region,
+            calling_code: $cc,
+            national_prefix: $national,
+            international_prefix: $intl,
+            national_number_lengths: $lengths,
+        }),*]
+    };
+}
+
+/// Every region with an ITU-T E.164 country calling code.
+///
+/// Sorted by region code so that [`for_region`] can use a binary search.
+static REGIONS: &[RegionMetadata] = regions! {
+    "AC" => 247, None, "00", 5..=6;
+    "AD" => 376, None, "00", 6..=9;
+    "AE" => 971, Some("0"), "00", 8..=9;
+    "AF" => 93, Some("0"), "00", 9..=9;
+    "AG" => 1, Some("1"), "011", 10..=10;
+    "AI" => 1, Some("1"), "011", 10..=10;
+    "AL" => 355, Some("0"), "00", 6..=9;
+    "AM" => 374, Some("0"), "00", 8..=8;
+    "AO" => 244, None, "00", 9..=9;
+    "AR" => 54, Some("0"), "00", 10..=11;
+    "AS" => 1, Some("1"), "011", 10..=10;
+    "AT" => 43, Some("0"), "00", 4..=13;
+    "AU" => 61, Some("0"), "0011", 9..=9;
+    "AW" => 297, None, "00", 7..=7;
+    "AX" => 358, Some("0"), "00", 5..=12;
+    "AZ" => 994, Some("0"), "00", 9..=9;
+    "BA" => 387, Some("0"), "00", 8..=9;
+    "BB" => 1, Some("1"), "011", 10..=10;
+    "BD" => 880, Some("0"), "00", 8..=10;
+    "BE" => 32, Some("0"), "00", 8..=9;
+    "BF" => 226, None, "00", 8..=8;
+    "BG" => 359, Some("0"), "00", 6..=9;
+    "BH" => 973, None, "00", 8..=8;
+    "BI" => 257, None, "00", 8..=8;
+    "BJ" => 229, None, "00", 8..=10;
+    "BL" => 590, Some("0"), "00", 9..=9;
+    "BM" => 1, Some("1"), "011", 10..=10;
+    "BN" => 673, None, "00", 7..=7;
+    "BO" => 591, Some("0"), "00", 8..=9;
+    "BQ" => 599, None, "00", 7..=7;
+    "BR" => 55, Some("0"), "00", 10..=11;
+    "BS" => 1, Some("1"), "011", 10..=10;
+    "BT" => 975, None, "00", 7..=8;
+    "BW" => 267, None, "00", 7..=8;
+    "BY" => 375, Some("8"), "810", 9..=10;
+    "BZ" => 501, None, "00", 7..=7;
+    "CA" => 1, Some("1"), "011", 10..=10;
+    "CC" => 61, Some("0"), "0011", 9..=9;
+    "CD" => 243, Some("0"), "00", 7..=9;
+    "CF" => 236, None, "00", 8..=8;
+    "CG" => 242, None, "00", 9..=9;
+    "CH" => 41, Some("0"), "00", 9..=12;
+    "CI" => 225, None, "00", 10..=10;
+    "CK" => 682, None, "00", 5..=5;
+    "CL" => 56, None, "00", 9..=9;
+    "CM" => 237, None, "00", 9..=9;
+    "CN" => 86, Some("0"), "00", 7..=12;
+    "CO" => 57, Some("0"), "00", 8..=10;
+    "CR" => 506, None, "00", 8..=10;
+    "CU" => 53, Some("0"), "119", 6..=8;
+    "CV" => 238, None, "0", 7..=7;
+    "CW" => 599, None, "00", 7..=8;
+    "CX" => 61, Some("0"), "0011", 9..=9;
+    "CY" => 357, None, "00", 8..=8;
+    "CZ" => 420, None, "00", 9..=9;
+    "DE" => 49, Some("0"), "00", 4..=13;
+    "DJ" => 253, None, "00", 8..=8;
+    "DK" => 45, None, "00", 8..=8;
+    "DM" => 1, Some("1"), "011", 10..=10;
+    "DO" => 1, Some("1"), "011", 10..=10;
+    "DZ" => 213, Some("0"), "00", 8..=9;
+    "EC" => 593, Some("0"), "00", 8..=9;
+    "EE" => 372, None, "00", 7..=8;
+    "EG" => 20, Some("0"), "00", 8..=10;
+    "EH" => 212, Some("0"), "00", 9..=9;
+    "ER" => 291, Some("0"), "00", 7..=7;
+    "ES" => 34, None, "00", 9..=9;
+    "ET" => 251, Some("0"), "00", 9..=9;
+    "FI" => 358, Some("0"), "00", 5..=12;
+    "FJ" => 679, None, "00", 7..=7;
+    "FK" => 500, None, "00", 5..=5;
+    "FM" => 691, None, "00", 7..=7;
+    "FO" => 298, None, "00", 6..=6;
+    "FR" => 33, Some("0"), "00", 9..=9;
+    "GA" => 241, None, "00", 7..=8;
+    "GB" => 44, Some("0"), "00", 7..=10;
+    "GD" => 1, Some("1"), "011", 10..=10;
+    "GE" => 995, Some("0"), "00", 9..=9;
+    "GF" => 594, Some("0"), "00", 9..=9;
+    "GG" => 44, Some("0"), "00", 7..=10;
+    "GH" => 233, Some("0"), "00", 9..=9;
+    "GI" => 350, None, "00", 8..=8;
+    "GL" => 299, None, "00", 6..=6;
+    "GM" => 220, None, "00", 7..=7;
+    "GN" => 224, None, "00", 8..=9;
+    "GP" => 590, Some("0"), "00", 9..=9;
+    "GQ" => 240, None, "00", 9..=9;
+    "GR" => 30, None, "00", 10..=10;
+    "GT" => 502, None, "00", 8..=8;
+    "GU" => 1, Some("1"), "011", 10..=10;
+    "GW" => 245, None, "00", 7..=9;
+    "GY" => 592, None, "001", 7..=7;
+    "HK" => 852, None, "001", 8..=8;
+    "HN" => 504, None, "00", 8..=8;
+    "HR" => 385, Some("0"), "00", 6..=9;
+    "HT" => 509, None, "00", 8..=8;
+    "HU" => 36, Some("06"), "00", 8..=9;
+    "ID" => 62, Some("0"), "001", 7..=12;
+    "IE" => 353, Some("0"), "00", 7..=10;
+    "IL" => 972, Some("0"), "00", 8..=9;
+    "IM" => 44, Some("0"), "00", 10..=10;
+    "IN" => 91, Some("0"), "00", 10..=10;
+    "IO" => 246, None, "00", 7..=7;
+    "IQ" => 964, Some("0"), "00", 8..=10;
+    "IR" => 98, Some("0"), "00", 10..=10;
+    "IS" => 354, None, "00", 7..=9;
+    "IT" => 39, None, "00", 6..=12;
+    "JE" => 44, Some("0"), "00", 10..=10;
+    "JM" => 1, Some("1"), "011", 10..=10;
+    "JO" => 962, Some("0"), "00", 8..=9;
+    "JP" => 81, Some("0"), "010", 9..=10;
+    "KE" => 254, Some("0"), "000", 9..=10;
+    "KG" => 996, Some("0"), "00", 9..=9;
+    "KH" => 855, Some("0"), "001", 8..=9;
+    "KI" => 686, Some("0"), "00", 5..=8;
+    "KM" => 269, None, "00", 7..=7;
+    "KN" => 1, Some("1"), "011", 10..=10;
+    "KP" => 850, Some("0"), "00", 8..=10;
+    "KR" => 82, Some("0"), "001", 8..=10;
+    "KW" => 965, None, "00", 7..=8;
+    "KY" => 1, Some("1"), "011", 10..=10;
+    "KZ" => 7, Some("8"), "810", 10..=10;
+    "LA" => 856, Some("0"), "00", 8..=10;
+    "LB" => 961, Some("0"), "00", 7..=8;
+    "LC" => 1, Some("1"), "011", 10..=10;
+    "LI" => 423, Some("0"), "00", 7..=9;
+    "LK" => 94, Some("0"), "00", 9..=9;
+    "LR" => 231, Some("0"), "00", 7..=9;
+    "LS" => 266, None, "00", 8..=8;
+    "LT" => 370, Some("8"), "00", 8..=8;
+    "LU" => 352, None, "00", 4..=11;
+    "LV" => 371, None, "00", 8..=8;
+    "LY" => 218, Some("0"), "00", 9..=9;
+    "MA" => 212, Some("0"), "00", 9..=9;
+    "MC" => 377, Some("0"), "00", 8..=9;
+    "MD" => 373, Some("0"), "00", 8..=8;
+    "ME" => 382, Some("0"), "00", 8..=8;
+    "MF" => 590, Some("0"), "00", 9..=9;
+    "MG" => 261, Some("0"), "00", 9..=9;
+    "MH" => 692, Some("1"), "011", 7..=7;
+    "MK" => 389, Some("0"), "00", 8..=8;
+    "ML" => 223, None, "00", 8..=8;
+    "MM" => 95, Some("0"), "00", 6..=10;
+    "MN" => 976, Some("0"), "001", 8..=10;
+    "MO" => 853, None, "00", 8..=8;
+    "MP" => 1, Some("1"), "011", 10..=10;
+    "MQ" => 596, Some("0"), "00", 9..=9;
+    "MR" => 222, None, "00", 8..=8;
+    "MS" => 1, Some("1"), "011", 10..=10;
+    "MT" => 356, None, "00", 8..=8;
+    "MU" => 230, None, "020", 7..=8;
+    "MV" => 960, None, "00", 7..=7;
+    "MW" => 265, Some("0"), "00", 7..=9;
+    "MX" => 52, None, "00", 10..=10;
+    "MY" => 60, Some("0"), "00", 8..=10;
+    "MZ" => 258, None, "00", 8..=9;
+    "NA" => 264, Some("0"), "00", 8..=9;
+    "NC" => 687, None, "00", 6..=6;
+    "NE" => 227, None, "00", 8..=8;
+    "NF" => 672, None, "00", 6..=6;
+    "NG" => 234, Some("0"), "009", 8..=10;
+    "NI" => 505, None, "00", 8..=8;
+    "NL" => 31, Some("0"), "00", 9..=9;
+    "NO" => 47, None, "00", 5..=8;
+    "NP" => 977, Some("0"), "00", 8..=10;
+    "NR" => 674, None, "00", 7..=7;
+    "NU" => 683, None, "00", 4..=7;
+    "NZ" => 64, Some("0"), "00", 8..=10;
+    "OM" => 968, None, "00", 8..=8;
+    "PA" => 507, None, "00", 7..=8;
+    "PE" => 51, Some("0"), "00", 8..=9;
+    "PF" => 689, None, "00", 8..=8;
+    "PG" => 675, None, "00", 7..=8;
+    "PH" => 63, Some("0"), "00", 8..=10;
+    "PK" => 92, Some("0"), "00", 9..=10;
+    "PL" => 48, None, "00", 9..=9;
+    "PM" => 508, Some("0"), "00", 6..=6;
+    "PR" => 1, Some("1"), "011", 10..=10;
+    "PS" => 970, Some("0"), "00", 8..=9;
+    "PT" => 351, None, "00", 9..=9;
+    "PW" => 680, None, "01", 7..=7;
+    "PY" => 595, Some("0"), "00", 6..=9;
+    "QA" => 974, None, "00", 7..=8;
+    "RE" => 262, Some("0"), "00", 9..=9;
+    "RO" => 40, Some("0"), "00", 9..=9;
+    "RS" => 381, Some("0"), "00", 6..=12;
+    "RU" => 7, Some("8"), "810", 10..=10;
+    "RW" => 250, Some("0"), "00", 9..=9;
+    "SA" => 966, Some("0"), "00", 9..=9;
+    "SB" => 677, None, "00", 5..=7;
+    "SC" => 248, None, "00", 7..=7;
+    "SD" => 249, Some("0"), "00", 9..=9;
+    "SE" => 46, Some("0"), "00", 7..=10;
+    "SG" => 65, None, "000", 8..=8;
+    "SH" => 290, None, "00", 4..=5;
+    "SI" => 386, Some("0"), "00", 8..=8;
+    "SJ" => 47, None, "00", 5..=8;
+    "SK" => 421, Some("0"), "00", 9..=9;
+    "SL" => 232, Some("0"), "00", 8..=8;
+    "SM" => 378, None, "00", 6..=10;
+    "SN" => 221, None, "00", 9..=9;
+    "SO" => 252, Some("0"), "00", 7..=9;
+    "SR" => 597, None, "00", 6..=7;
+    "SS" => 211, Some("0"), "00", 9..=9;
+    "ST" => 239, None, "00", 7..=7;
+    "SV" => 503, None, "00", 7..=8;
+    "SX" => 1, Some("1"), "011", 10..=10;
+    "SY" => 963, Some("0"), "00", 8..=9;
+    "SZ" => 268, None, "00", 8..=8;
+    "TA" => 290, None, "00", 4..=5;
+    "TC" => 1, Some("1"), "011", 10..=10;
+    "TD" => 235, None, "00", 8..=8;
+    "TG" => 228, None, "00", 8..=8;
+    "TH" => 66, Some("0"), "001", 8..=9;
+    "TJ" => 992, None, "810", 9..=9;
+    "TK" => 690, None, "00", 4..=7;
+    "TL" => 670, None, "00", 7..=8;
+    "TM" => 993, Some("8"), "810", 8..=8;
+    "TN" => 216, None, "00", 8..=8;
+    "TO" => 676, None, "00", 5..=7;
+    "TR" => 90, Some("0"), "00", 10..=10;
+    "TT" => 1, Some("1"), "011", 10..=10;
+    "TV" => 688, None, "00", 5..=6;
+    "TW" => 886, Some("0"), "00", 8..=9;
+    "TZ" => 255, Some("0"), "000", 9..=9;
+    "UA" => 380, Some("0"), "00", 9..=9;
+    "UG" => 256, Some("0"), "000", 9..=9;
+    "US" => 1, Some("1"), "011", 10..=10;
+    "UY" => 598, Some("0"), "00", 8..=8;
+    "UZ" => 998, None, "00", 9..=9;
+    "VA" => 39, None, "00", 6..=12;
+    "VC" => 1, Some("1"), "011", 10..=10;
+    "VE" => 58, Some("0"), "00", 10..=10;
+    "VG" => 1, Some("1"), "011", 10..=10;
+    "VI" => 1, Some("1"), "011", 10..=10;
+    "VN" => 84, Some("0"), "00", 9..=10;
+    "VU" => 678, None, "00", 5..=7;
+    "WF" => 681, None, "00", 6..=6;
+    "WS" => 685, None, "0", 5..=7;
+    "XK" => 383, Some("0"), "00", 8..=9;
+    "YE" => 967, Some("0"), "00", 7..=9;
+    "YT" => 262, Some("0"), "00", 9..=9;
+    "ZA" => 27, Some("0"), "00", 9..=9;
+    "ZM" => 260, Some("0"), "00", 9..=9;
+    "ZW" => 263, Some("0"), "00", 5..=10;
+};
+
+/// Calling codes for global services rather than regions, such as international freephone numbers
+/// and satellite networks.
+///
+/// These use libphonenumber's "001" pseudo-region and can't be used as a default region.
+static NON_GEOGRAPHIC: &[RegionMetadata] = regions! {
+    "001" => 800, None, "00", 8..=8;
+    "001" => 808, None, "00", 8..=8;
+    "001" => 870, None, "00", 9..=9;
+    "001" => 878, None, "00", 12..=12;
+    "001" => 881, None, "00", 9..=10;
+    "001" => 882, None, "00", 7..=12;
+    "001" => 883, None, "00", 9..=12;
+    "001" => 888, None, "00", 11..=11;
+    "001" => 979, None, "00", 9..=9;
+};
+
+/// Looks up a region by its ISO 3166-1 alpha-2 code, ignoring case.
+pub(super) fn for_region(region: &str) -> Option<&'static RegionMetadata> {
+    let region = region.to_ascii_uppercase();
+    REGIONS
+        .binary_search_by(|entry| entry.region.cmp(&region))
+        .ok()
+        .map(|index| &REGIONS[index])
+}
+
+/// Splits a known country calling code off the front of `digits`.
+///
+/// Calling codes are prefix-free, so at most one of the one-, two-, or three-digit prefixes can
+/// match. Returns the calling code along with every region that uses it.
+///
+/// No calling code starts with `0`, so `digits` that do are rejected rather than having the zero
+/// ignored.
+pub(super) fn split_calling_code(
+    digits: &str,
+) -> Option<(u16, impl Iterator<Item = &'static RegionMetadata>)> {
+    if digits.starts_with('0') {
+        return None;
+    }
+    (1..=3).filter(|&len| len <= digits.len()).find_map(|len| {
+        let calling_code: u16 = digits[..len].parse().ok()?;
+        let mut regions = REGIONS
+            .iter()
+            .chain(NON_GEOGRAPHIC)
+            .filter(move |entry| entry.calling_code == calling_code)
+            .peekable();
+        regions.peek()?;
+        Some((calling_code, regions))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn regions_are_sorted() {
+        assert!(REGIONS.windows(2).all(|w| w[0].region < w[1].region));
+    }
+
+    #[test]
+    fn calling_codes_are_prefix_free() {
+        for a in REGIONS.iter().chain(NON_GEOGRAPHIC) {
+            for b in REGIONS.iter().chain(NON_GEOGRAPHIC) {
+                if a.calling_code == b.calling_code {
+                    continue;
+                }
+                let (a, b) = (a.calling_code.to_string(), b.calling_code.to_string());
+                assert!(!b.starts_with(&a), "{a} is a prefix of {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_calling_code_is_covered() {
+        // The calling codes in ITU-T E.164 that are assigned to a region or a global service, and
+        // in use. Spare and reserved codes, and ones for trials or shared by groups of countries
+        // (388, 991, 999), are left out.
+        const ASSIGNED: &[u16] = &[
+            1, 7, 20, 27, 30, 31, 32, 33, 34, 36, 39, 40, 41, 43, 44, 45, 46, 47, 48, 49, 51, 52,
+            53, 54, 55, 56, 57, 58, 60, 61, 62, 63, 64, 65, 66, 81, 82, 84, 86, 90, 91, 92, 93, 94,
+            95, 98, 211, 212, 213, 216, 218, 220, 221, 222, 223, 224, 225, 226, 227, 228, 229, 230,
+            231, 232, 233, 234, 235, 236, 237, 238, 239, 240, 241, 242, 243, 244, 245, 246, 247,
+            248, 249, 250, 251, 252, 253, 254, 255, 256, 257, 258, 260, 261, 262, 263, 264, 265,
+            266, 267, 268, 269, 290, 291, 297, 298, 299, 350, 351, 352, 353, 354, 355, 356, 357,
+            358, 359, 370, 371, 372, 373, 374, 375, 376, 377, 378, 380, 381, 382, 383, 385, 386,
+            387, 389, 420, 421, 423, 500, 501, 502, 503, 504, 505, 506, 507, 508, 509, 590, 591,
+            592, 593, 594, 595, 596, 597, 598, 599, 670, 672, 673, 674, 675, 676, 677, 678, 679,
+            680, 681, 682, 683, 685, 686, 687, 688, 689, 690, 691, 692, 800, 808, 850, 852, 853,
+            855, 856, 870, 878, 880, 881, 882, 883, 886, 888, 960, 961, 962, 963, 964, 965, 966,
+            967, 968, 970, 971, 972, 973, 974, 975, 976, 977, 979, 992, 993, 994, 995, 996, 998,
+        ];
+        for &calling_code in ASSIGNED {
+            let digits = format!("{calling_code}123456789");
+            let (split, _regions) =
+                split_calling_code(&digits).unwrap_or_else(|| panic!("+{calling_code} is missing"));
+            assert_eq!(split, calling_code);
+        }
+    }
+
+    #[test]
+    fn calling_codes_never_start_with_zero() {
+        assert!(split_calling_code("014155550100").is_none());
+        assert!(split_calling_code("0044207946001").is_none());
+    }
+}
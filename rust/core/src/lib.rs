@@ -13,5 +13,5 @@ pub use address::{
     Aci, DeviceId, Pni, ProtocolAddress, ServiceId, ServiceIdFixedWidthBinaryBytes, ServiceIdKind,
     WrongKindOfServiceIdError,
 };
-pub use e164::E164;
+pub use e164::{E164ParseError, E164};
 pub use version::VERSION;
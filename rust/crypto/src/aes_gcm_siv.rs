@@ -0,0 +1,72 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use aes_gcm_siv::aead::{AeadInPlace, KeyInit};
+
+use crate::{Error, Result};
+
+pub const KEY_SIZE: usize = 32;
+pub const TAG_SIZE: usize = 16;
+pub const NONCE_SIZE: usize = 12;
+
+/// [RFC 8452][] AES-256-GCM-SIV.
+///
+/// Unlike [`Aes256GcmEncryption`](crate::Aes256GcmEncryption), SIV mode derives its counter from
+/// the tag over the entire plaintext, so it cannot be processed incrementally; each call handles
+/// a complete message.
+///
+/// [RFC 8452]: https://www.rfc-editor.org/rfc/rfc8452
+#[derive(Clone)]
+pub struct Aes256GcmSiv(aes_gcm_siv::Aes256GcmSiv);
+
+impl Aes256GcmSiv {
+    pub const KEY_SIZE: usize = KEY_SIZE;
+    pub const TAG_SIZE: usize = TAG_SIZE;
+    pub const NONCE_SIZE: usize = NONCE_SIZE;
+
+    pub fn new(key: &[u8]) -> Result<Self> {
+        aes_gcm_siv::Aes256GcmSiv::new_from_slice(key)
+            .map(Self)
+            .map_err(|_| Error::InvalidKeySize)
+    }
+
+    /// Encrypts `buf` in place, returning the authentication tag.
+    pub fn encrypt(
+        &self,
+        buf: &mut [u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+    ) -> Result<[u8; TAG_SIZE]> {
+        if nonce.len() != NONCE_SIZE {
+            return Err(Error::InvalidNonceSize);
+        }
+        let tag = self
+            .0
+            .encrypt_in_place_detached(nonce.into(), associated_data, buf)
+            .map_err(|_| Error::InvalidInputSize)?;
+        Ok(tag.into())
+    }
+
+    /// Decrypts `buf` in place, checking it against `tag`.
+    ///
+    /// On failure, the contents of `buf` are unspecified.
+    pub fn decrypt(
+        &self,
+        buf: &mut [u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if nonce.len() != NONCE_SIZE {
+            return Err(Error::InvalidNonceSize);
+        }
+        if tag.len() != TAG_SIZE {
+            return Err(Error::InvalidTag);
+        }
+        self.0
+            .decrypt_in_place_detached(nonce.into(), associated_data, buf, tag.into())
+            .map_err(|_| Error::InvalidTag)
+    }
+}
@@ -0,0 +1,183 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use poly1305::universal_hash::{KeyInit, UniversalHash};
+use poly1305::Poly1305;
+use subtle::ConstantTimeEq;
+
+use crate::{Error, Result};
+
+pub const KEY_SIZE: usize = 32;
+pub const TAG_SIZE: usize = 16;
+pub const NONCE_SIZE: usize = 12;
+
+/// The size of a ChaCha20 keystream block; block 0 is reserved for the Poly1305 key.
+const CHACHA20_BLOCK_SIZE: u64 = 64;
+
+/// Incremental Poly1305 over AEAD data, as laid out in [RFC 8439 section 2.8][].
+///
+/// [RFC 8439 section 2.8]: https://www.rfc-editor.org/rfc/rfc8439#section-2.8
+#[derive(Clone)]
+struct AeadPoly1305 {
+    poly1305: Poly1305,
+    msg_buf: [u8; TAG_SIZE],
+    msg_buf_offset: usize,
+    ad_len: usize,
+    msg_len: usize,
+}
+
+impl AeadPoly1305 {
+    fn new(key: &[u8; KEY_SIZE], associated_data: &[u8]) -> Self {
+        let mut poly1305 = Poly1305::new(key.into());
+        poly1305.update_padded(associated_data);
+
+        Self {
+            poly1305,
+            msg_buf: [0u8; TAG_SIZE],
+            msg_buf_offset: 0,
+            ad_len: associated_data.len(),
+            msg_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut msg: &[u8]) {
+        self.msg_len += msg.len();
+
+        if self.msg_buf_offset > 0 {
+            let taking = std::cmp::min(msg.len(), TAG_SIZE - self.msg_buf_offset);
+            self.msg_buf[self.msg_buf_offset..self.msg_buf_offset + taking]
+                .copy_from_slice(&msg[..taking]);
+            self.msg_buf_offset += taking;
+            msg = &msg[taking..];
+
+            if self.msg_buf_offset < TAG_SIZE {
+                return;
+            }
+            self.poly1305
+                .update(std::slice::from_ref(poly1305::Block::from_slice(
+                    &self.msg_buf,
+                )));
+            self.msg_buf_offset = 0;
+        }
+
+        let mut chunks = msg.chunks_exact(TAG_SIZE);
+        for block in &mut chunks {
+            self.poly1305
+                .update(std::slice::from_ref(poly1305::Block::from_slice(block)));
+        }
+
+        let leftover = chunks.remainder();
+        self.msg_buf[..leftover.len()].copy_from_slice(leftover);
+        self.msg_buf_offset = leftover.len();
+    }
+
+    fn finalize(mut self) -> [u8; TAG_SIZE] {
+        if self.msg_buf_offset > 0 {
+            self.poly1305
+                .update_padded(&self.msg_buf[..self.msg_buf_offset]);
+        }
+
+        let mut final_block = [0u8; TAG_SIZE];
+        final_block[..8].copy_from_slice(&(self.ad_len as u64).to_le_bytes());
+        final_block[8..].copy_from_slice(&(self.msg_len as u64).to_le_bytes());
+        self.poly1305.update(&[final_block.into()]);
+
+        self.poly1305.finalize().into()
+    }
+}
+
+fn setup_chacha20_poly1305(
+    key: &[u8],
+    nonce: &[u8],
+    associated_data: &[u8],
+) -> Result<(ChaCha20, AeadPoly1305)> {
+    if key.len() != KEY_SIZE {
+        return Err(Error::InvalidKeySize);
+    }
+    if nonce.len() != NONCE_SIZE {
+        return Err(Error::InvalidNonceSize);
+    }
+
+    let mut chacha20 = ChaCha20::new(key.into(), nonce.into());
+
+    let mut poly1305_key = [0u8; KEY_SIZE];
+    chacha20.apply_keystream(&mut poly1305_key);
+    chacha20.seek(CHACHA20_BLOCK_SIZE);
+
+    let poly1305 = AeadPoly1305::new(&poly1305_key, associated_data);
+    Ok((chacha20, poly1305))
+}
+
+/// Incremental [RFC 8439][] ChaCha20-Poly1305 encryption.
+///
+/// [RFC 8439]: https://www.rfc-editor.org/rfc/rfc8439
+pub struct ChaCha20Poly1305Encryption {
+    chacha20: ChaCha20,
+    poly1305: AeadPoly1305,
+}
+
+impl ChaCha20Poly1305Encryption {
+    pub const KEY_SIZE: usize = KEY_SIZE;
+    pub const TAG_SIZE: usize = TAG_SIZE;
+    pub const NONCE_SIZE: usize = NONCE_SIZE;
+
+    pub fn new(key: &[u8], nonce: &[u8], associated_data: &[u8]) -> Result<Self> {
+        let (chacha20, poly1305) = setup_chacha20_poly1305(key, nonce, associated_data)?;
+        Ok(Self { chacha20, poly1305 })
+    }
+
+    pub fn encrypt(&mut self, buf: &mut [u8]) {
+        self.chacha20.apply_keystream(buf);
+        self.poly1305.update(buf);
+    }
+
+    pub fn compute_tag(self) -> [u8; TAG_SIZE] {
+        self.poly1305.finalize()
+    }
+}
+
+/// Incremental [RFC 8439][] ChaCha20-Poly1305 decryption.
+///
+/// As with [`Aes256GcmDecryption`](crate::Aes256GcmDecryption), plaintext is produced before the
+/// tag has been checked; callers must not act on it until [`verify_tag`](Self::verify_tag)
+/// succeeds.
+///
+/// [RFC 8439]: https://www.rfc-editor.org/rfc/rfc8439
+pub struct ChaCha20Poly1305Decryption {
+    chacha20: ChaCha20,
+    poly1305: AeadPoly1305,
+}
+
+impl ChaCha20Poly1305Decryption {
+    pub const KEY_SIZE: usize = KEY_SIZE;
+    pub const TAG_SIZE: usize = TAG_SIZE;
+    pub const NONCE_SIZE: usize = NONCE_SIZE;
+
+    pub fn new(key: &[u8], nonce: &[u8], associated_data: &[u8]) -> Result<Self> {
+        let (chacha20, poly1305) = setup_chacha20_poly1305(key, nonce, associated_data)?;
+        Ok(Self { chacha20, poly1305 })
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) {
+        self.poly1305.update(buf);
+        self.chacha20.apply_keystream(buf);
+    }
+
+    pub fn verify_tag(self, tag: &[u8]) -> Result<()> {
+        if tag.len() != TAG_SIZE {
+            return Err(Error::InvalidTag);
+        }
+
+        let computed_tag = self.poly1305.finalize();
+
+        if !bool::from(tag.ct_eq(&computed_tag)) {
+            return Err(Error::InvalidTag);
+        }
+
+        Ok(())
+    }
+}
@@ -13,6 +13,8 @@ pub enum Error {
     InvalidNonceSize,
     /// invalid input size
     InvalidInputSize,
+    /// invalid output size
+    InvalidOutputSize,
     /// invalid authentication tag
     InvalidTag,
 }
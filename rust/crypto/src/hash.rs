@@ -3,28 +3,90 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::str::FromStr;
+
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
 use sha2::{Digest, Sha256, Sha512};
 
 use crate::{Error, Result};
 
+/// The hash functions supported by [`CryptographicHash`] and [`hkdf_derive`](crate::hkdf_derive).
+///
+/// The discriminants are stable, so that the algorithm can be passed across the app bridges.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, num_enum::TryFromPrimitive)]
+pub enum DigestAlgorithm {
+    Sha1 = 0,
+    Sha256 = 1,
+    Sha512 = 2,
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = Error;
+
+    fn from_str(algo: &str) -> Result<Self> {
+        match algo {
+            "SHA-1" | "SHA1" | "Sha1" => Ok(Self::Sha1),
+            "SHA-256" | "SHA256" | "Sha256" => Ok(Self::Sha256),
+            "SHA-512" | "SHA512" | "Sha512" => Ok(Self::Sha512),
+            _ => Err(Error::UnknownAlgorithm("digest", algo.to_string())),
+        }
+    }
+}
+
+/// The MACs supported by [`CryptographicMac`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacAlgorithm {
+    HmacSha1,
+    HmacSha256,
+    HmacSha512,
+}
+
+impl FromStr for MacAlgorithm {
+    type Err = Error;
+
+    fn from_str(algo: &str) -> Result<Self> {
+        match algo {
+            "HMACSha1" | "HmacSha1" => Ok(Self::HmacSha1),
+            "HMACSha256" | "HmacSha256" => Ok(Self::HmacSha256),
+            "HMACSha512" | "HmacSha512" => Ok(Self::HmacSha512),
+            _ => Err(Error::UnknownAlgorithm("MAC", algo.to_string())),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum CryptographicMac {
     HmacSha256(Hmac<Sha256>),
     HmacSha1(Hmac<Sha1>),
+    HmacSha512(Hmac<Sha512>),
 }
 
 impl CryptographicMac {
     pub fn new(algo: &str, key: &[u8]) -> Result<Self> {
+        Ok(Self::with_algorithm(algo.parse()?, key))
+    }
+
+    pub fn with_algorithm(algo: MacAlgorithm, key: &[u8]) -> Self {
         match algo {
-            "HMACSha1" | "HmacSha1" => Ok(Self::HmacSha1(
+            MacAlgorithm::HmacSha1 => Self::HmacSha1(
                 Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length"),
-            )),
-            "HMACSha256" | "HmacSha256" => Ok(Self::HmacSha256(
+            ),
+            MacAlgorithm::HmacSha256 => Self::HmacSha256(
                 Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length"),
-            )),
-            _ => Err(Error::UnknownAlgorithm("MAC", algo.to_string())),
+            ),
+            MacAlgorithm::HmacSha512 => Self::HmacSha512(
+                Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length"),
+            ),
+        }
+    }
+
+    pub fn algorithm(&self) -> MacAlgorithm {
+        match self {
+            Self::HmacSha1(_) => MacAlgorithm::HmacSha1,
+            Self::HmacSha256(_) => MacAlgorithm::HmacSha256,
+            Self::HmacSha512(_) => MacAlgorithm::HmacSha512,
         }
     }
 
@@ -32,6 +94,7 @@ impl CryptographicMac {
         match self {
             Self::HmacSha1(sha1) => sha1.update(input),
             Self::HmacSha256(sha256) => sha256.update(input),
+            Self::HmacSha512(sha512) => sha512.update(input),
         }
     }
 
@@ -44,6 +107,7 @@ impl CryptographicMac {
         match self {
             Self::HmacSha1(sha1) => sha1.finalize_reset().into_bytes().to_vec(),
             Self::HmacSha256(sha256) => sha256.finalize_reset().into_bytes().to_vec(),
+            Self::HmacSha512(sha512) => sha512.finalize_reset().into_bytes().to_vec(),
         }
     }
 }
@@ -57,11 +121,22 @@ pub enum CryptographicHash {
 
 impl CryptographicHash {
     pub fn new(algo: &str) -> Result<Self> {
+        Ok(Self::with_algorithm(algo.parse()?))
+    }
+
+    pub fn with_algorithm(algo: DigestAlgorithm) -> Self {
         match algo {
-            "SHA-1" | "SHA1" | "Sha1" => Ok(Self::Sha1(Sha1::new())),
-            "SHA-256" | "SHA256" | "Sha256" => Ok(Self::Sha256(Sha256::new())),
-            "SHA-512" | "SHA512" | "Sha512" => Ok(Self::Sha512(Sha512::new())),
-            _ => Err(Error::UnknownAlgorithm("digest", algo.to_string())),
+            DigestAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+            DigestAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        match self {
+            Self::Sha1(_) => DigestAlgorithm::Sha1,
+            Self::Sha256(_) => DigestAlgorithm::Sha256,
+            Self::Sha512(_) => DigestAlgorithm::Sha512,
         }
     }
 
@@ -0,0 +1,31 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hkdf::Hkdf;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::{DigestAlgorithm, Error, Result};
+
+/// [RFC 5869][] HKDF, filling `output` with key material derived from `ikm`.
+///
+/// A missing `salt` is equivalent to a salt of all zeros, per the RFC. Fails with
+/// [`Error::InvalidOutputSize`] if `output` is longer than 255 times the digest size.
+///
+/// [RFC 5869]: https://www.rfc-editor.org/rfc/rfc5869
+pub fn hkdf_derive(
+    algo: DigestAlgorithm,
+    ikm: &[u8],
+    salt: Option<&[u8]>,
+    info: &[u8],
+    output: &mut [u8],
+) -> Result<()> {
+    match algo {
+        DigestAlgorithm::Sha1 => Hkdf::<Sha1>::new(salt, ikm).expand(info, output),
+        DigestAlgorithm::Sha256 => Hkdf::<Sha256>::new(salt, ikm).expand(info, output),
+        DigestAlgorithm::Sha512 => Hkdf::<Sha512>::new(salt, ikm).expand(info, output),
+    }
+    .map_err(|_| Error::InvalidOutputSize)
+}
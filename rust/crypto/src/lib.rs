@@ -7,13 +7,19 @@
 
 mod error;
 mod hash;
+mod hkdf;
 
 mod aes_cbc;
 mod aes_ctr;
 mod aes_gcm;
+mod aes_gcm_siv;
+mod chacha20_poly1305;
 
 pub use aes_cbc::{aes_256_cbc_decrypt, aes_256_cbc_encrypt, DecryptionError, EncryptionError};
 pub use aes_ctr::Aes256Ctr32;
 pub use aes_gcm::{Aes256GcmDecryption, Aes256GcmEncryption};
+pub use aes_gcm_siv::Aes256GcmSiv;
+pub use chacha20_poly1305::{ChaCha20Poly1305Decryption, ChaCha20Poly1305Encryption};
 pub use error::{Error, Result};
-pub use hash::{CryptographicHash, CryptographicMac};
+pub use hash::{CryptographicHash, CryptographicMac, DigestAlgorithm, MacAlgorithm};
+pub use hkdf::hkdf_derive;
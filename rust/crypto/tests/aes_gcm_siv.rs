@@ -0,0 +1,48 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hex_literal::hex;
+use signal_crypto::{Aes256GcmSiv, Error};
+
+#[test]
+fn aes_gcm_siv_rfc8452() -> Result<(), Error> {
+    // RFC 8452 appendix C.2
+    let key = hex!("0100000000000000000000000000000000000000000000000000000000000000");
+    let nonce = hex!("030000000000000000000000");
+    let plaintext = hex!("0100000000000000");
+
+    let cipher = Aes256GcmSiv::new(&key)?;
+    let mut buf = plaintext.to_vec();
+    let tag = cipher.encrypt(&mut buf, &nonce, &[])?;
+    assert_eq!(hex::encode(&buf), "c2ef328e5c71c83b");
+    assert_eq!(hex::encode(tag), "843122130f7364b761e0b97427e3df28");
+
+    cipher.decrypt(&mut buf, &nonce, &[], &tag)?;
+    assert_eq!(buf, plaintext);
+
+    let mut bad_tag = tag;
+    bad_tag[15] ^= 1;
+    assert!(matches!(
+        cipher.decrypt(&mut buf, &nonce, &[], &bad_tag),
+        Err(Error::InvalidTag)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn aes_gcm_siv_rejects_bad_parameters() -> Result<(), Error> {
+    assert!(matches!(
+        Aes256GcmSiv::new(&[0; 16]),
+        Err(Error::InvalidKeySize)
+    ));
+
+    let cipher = Aes256GcmSiv::new(&[0; 32])?;
+    assert!(matches!(
+        cipher.encrypt(&mut [], &[0; 8], &[]),
+        Err(Error::InvalidNonceSize)
+    ));
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hex_literal::hex;
+use rand::Rng;
+use signal_crypto::{ChaCha20Poly1305Decryption, ChaCha20Poly1305Encryption, Error};
+
+// RFC 8439 section 2.8.2
+const KEY: [u8; 32] = hex!("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+const NONCE: [u8; 12] = hex!("070000004041424344454647");
+const AAD: [u8; 12] = hex!("50515253c0c1c2c3c4c5c6c7");
+const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+const CIPHERTEXT: [u8; 114] = hex!("d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3ff4def08e4b7a9de576d26586cec64b6116");
+const TAG: [u8; 16] = hex!("1ae10b594f09e26a7e902ecbd0600691");
+
+#[test]
+fn chacha20_poly1305_rfc8439() -> Result<(), Error> {
+    let mut enc = ChaCha20Poly1305Encryption::new(&KEY, &NONCE, &AAD)?;
+    let mut buf = PLAINTEXT.to_vec();
+    enc.encrypt(&mut buf);
+    assert_eq!(hex::encode(&buf), hex::encode(CIPHERTEXT));
+    assert_eq!(hex::encode(enc.compute_tag()), hex::encode(TAG));
+
+    let mut dec = ChaCha20Poly1305Decryption::new(&KEY, &NONCE, &AAD)?;
+    dec.decrypt(&mut buf);
+    dec.verify_tag(&TAG)?;
+    assert_eq!(buf, PLAINTEXT);
+
+    Ok(())
+}
+
+#[test]
+fn chacha20_poly1305_split_inputs() -> Result<(), Error> {
+    let mut rng = rand::rngs::OsRng;
+
+    for _ in 0..32 {
+        let mut enc = ChaCha20Poly1305Encryption::new(&KEY, &NONCE, &AAD)?;
+        let mut dec = ChaCha20Poly1305Decryption::new(&KEY, &NONCE, &AAD)?;
+
+        let mut enc_buf = PLAINTEXT.to_vec();
+        let mut dec_buf = CIPHERTEXT.to_vec();
+
+        let mut processed = 0;
+        while processed != enc_buf.len() {
+            let remaining = enc_buf.len() - processed;
+            let this_time = if remaining > 1 {
+                rng.gen_range(1..remaining)
+            } else {
+                remaining
+            };
+            enc.encrypt(&mut enc_buf[processed..processed + this_time]);
+            dec.decrypt(&mut dec_buf[processed..processed + this_time]);
+            processed += this_time;
+        }
+
+        assert_eq!(hex::encode(enc.compute_tag()), hex::encode(TAG));
+        dec.verify_tag(&TAG)?;
+
+        assert_eq!(hex::encode(enc_buf), hex::encode(CIPHERTEXT));
+        assert_eq!(dec_buf, PLAINTEXT);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn chacha20_poly1305_rejects_bad_tag() -> Result<(), Error> {
+    let mut bad_tag = TAG;
+    bad_tag[0] ^= 1;
+
+    let mut dec = ChaCha20Poly1305Decryption::new(&KEY, &NONCE, &AAD)?;
+    dec.decrypt(&mut CIPHERTEXT.to_vec());
+    assert!(matches!(dec.verify_tag(&bad_tag), Err(Error::InvalidTag)));
+
+    let dec = ChaCha20Poly1305Decryption::new(&KEY, &NONCE, &AAD)?;
+    assert!(matches!(dec.verify_tag(&TAG[..15]), Err(Error::InvalidTag)));
+
+    Ok(())
+}
+
+#[test]
+fn chacha20_poly1305_rejects_bad_parameters() {
+    assert!(matches!(
+        ChaCha20Poly1305Encryption::new(&KEY[..31], &NONCE, &AAD),
+        Err(Error::InvalidKeySize)
+    ));
+    assert!(matches!(
+        ChaCha20Poly1305Encryption::new(&KEY, &NONCE[..8], &AAD),
+        Err(Error::InvalidNonceSize)
+    ));
+}
@@ -0,0 +1,56 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hex_literal::hex;
+use signal_crypto::{hkdf_derive, DigestAlgorithm, Error};
+
+#[test]
+fn rfc5869_sha256() {
+    // Test case 1
+    let ikm = hex!("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+    let salt = hex!("000102030405060708090a0b0c");
+    let info = hex!("f0f1f2f3f4f5f6f7f8f9");
+    let mut okm = [0u8; 42];
+    hkdf_derive(DigestAlgorithm::Sha256, &ikm, Some(&salt), &info, &mut okm).expect("valid length");
+    assert_eq!(
+        okm,
+        hex!(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf"
+            "34007208d5b887185865"
+        )
+    );
+}
+
+#[test]
+fn rfc5869_sha1_without_salt() {
+    // Test case 7
+    let ikm = hex!("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c");
+    let mut okm = [0u8; 42];
+    hkdf_derive(DigestAlgorithm::Sha1, &ikm, None, &[], &mut okm).expect("valid length");
+    assert_eq!(
+        okm,
+        hex!(
+            "2c91117204d745f3500d636a62f64f0ab3bae548aa53d423b0d1f27ebba6f5e5"
+            "673a081d70cce7acfc48"
+        )
+    );
+}
+
+#[test]
+fn output_too_long() {
+    let mut okm = vec![0u8; 255 * 64 + 1];
+    assert!(matches!(
+        hkdf_derive(DigestAlgorithm::Sha512, b"ikm", None, b"info", &mut okm),
+        Err(Error::InvalidOutputSize)
+    ));
+    hkdf_derive(
+        DigestAlgorithm::Sha512,
+        b"ikm",
+        None,
+        b"info",
+        &mut okm[..255 * 64],
+    )
+    .expect("maximum length is allowed");
+}
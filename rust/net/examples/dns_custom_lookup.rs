@@ -11,8 +11,8 @@ use either::{for_both, Either};
 use libsignal_net::infra::certs::RootCertificates;
 use libsignal_net::infra::dns::custom_resolver::CustomDnsResolver;
 use libsignal_net::infra::dns::dns_lookup::{DnsLookup, DnsLookupRequest};
-use libsignal_net::infra::dns::dns_transport_doh::DohTransport;
-use libsignal_net::infra::dns::dns_transport_udp::UdpTransport;
+use libsignal_net::infra::dns::dns_transport_doh::{DohTransport, DohTransportParams};
+use libsignal_net::infra::dns::dns_transport_udp::{UdpTransport, UdpTransportParams};
 use libsignal_net::infra::host::Host;
 use libsignal_net::infra::utils::ObservableEvent;
 use libsignal_net_infra::route::{
@@ -53,7 +53,7 @@ async fn main() {
         Transport::Udp => {
             let ns_address = (HOST_IP, 53);
             Either::Left(CustomDnsResolver::<UdpTransport>::new(
-                ns_address,
+                UdpTransportParams::new(ns_address),
                 &ObservableEvent::default(),
             ))
         }
@@ -70,6 +70,8 @@ async fn main() {
                         root_certs: RootCertificates::Native,
                         sni: Host::Domain(host.clone()),
                         alpn: Some(Alpn::Http2),
+                        ech_config_list: None,
+                        pin_set: None,
                     },
                     inner: TcpRoute {
                         address: HOST_IP,
@@ -78,7 +80,7 @@ async fn main() {
                 },
             };
             Either::Right(CustomDnsResolver::<DohTransport>::new(
-                vec![target],
+                DohTransportParams::new(vec![target]),
                 &ObservableEvent::default(),
             ))
         }
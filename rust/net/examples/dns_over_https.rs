@@ -11,7 +11,7 @@ use futures_util::StreamExt;
 use libsignal_net::infra::certs::RootCertificates;
 use libsignal_net::infra::dns::custom_resolver::DnsTransport;
 use libsignal_net::infra::dns::dns_lookup::DnsLookupRequest;
-use libsignal_net::infra::dns::dns_transport_doh::DohTransport;
+use libsignal_net::infra::dns::dns_transport_doh::{DohTransport, DohTransportParams};
 use libsignal_net::infra::host::Host;
 use libsignal_net_infra::route::{
     HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment,
@@ -32,6 +32,9 @@ struct Args {
     /// port of the name server
     #[arg(long, default_value = "443")]
     ns_port: NonZeroU16,
+    /// validate responses with DNSSEC
+    #[arg(long, default_value = "false")]
+    dnssec: bool,
 }
 
 #[tokio::main]
@@ -55,6 +58,8 @@ async fn main() {
                 root_certs: RootCertificates::Native,
                 sni: Host::Domain(host),
                 alpn: Some(Alpn::Http2),
+                ech_config_list: None,
+                pin_set: None,
             },
             inner: TcpRoute {
                 address,
@@ -63,7 +68,12 @@ async fn main() {
         },
     };
 
-    let doh_transport = DohTransport::connect(vec![route.clone()], !args.no_ipv6)
+    let mut params = DohTransportParams::new(vec![route.clone()]);
+    if args.dnssec {
+        params = params.with_dnssec_validation();
+    }
+
+    let doh_transport = DohTransport::connect(params, !args.no_ipv6)
         .await
         .expect("connected to the DNS server");
     log::info!("successfully connected to the DNS server at {:?}", route);
@@ -10,7 +10,7 @@ use clap::Parser;
 use futures_util::StreamExt;
 use libsignal_net::infra::dns::custom_resolver::DnsTransport;
 use libsignal_net::infra::dns::dns_lookup::DnsLookupRequest;
-use libsignal_net::infra::dns::dns_transport_udp::UdpTransport;
+use libsignal_net::infra::dns::dns_transport_udp::{UdpTransport, UdpTransportParams};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -26,6 +26,9 @@ struct Args {
     /// port of the name server
     #[arg(long, default_value = "53")]
     ns_port: u16,
+    /// validate responses with DNSSEC
+    #[arg(long, default_value = "false")]
+    dnssec: bool,
 }
 
 #[tokio::main]
@@ -41,7 +44,12 @@ async fn main() {
         args.ns_port,
     );
 
-    let udp_transport = UdpTransport::connect(ns_address, !args.no_ipv6)
+    let mut params = UdpTransportParams::new(ns_address);
+    if args.dnssec {
+        params = params.with_dnssec_validation();
+    }
+
+    let udp_transport = UdpTransport::connect(params, !args.no_ipv6)
         .await
         .expect("connected to the DNS server");
     log::info!(
@@ -95,6 +95,8 @@ async fn main() {
                 root_certs,
                 sni: proxy_host.clone(),
                 alpn: Some(Alpn::Http1_1),
+                ech_config_list: None,
+                pin_set: None,
             },
         }),
         scheme => panic!("unsupported protocol {scheme}"),
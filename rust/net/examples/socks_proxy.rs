@@ -106,6 +106,8 @@ async fn main() {
                 root_certs,
                 sni: Host::Domain(host_name),
                 alpn: None,
+                ech_config_list: None,
+                pin_set: None,
             },
             inner: SocksRoute {
                 proxy: TcpRoute {
@@ -170,6 +172,7 @@ async fn main() {
             tcp_host: host,
             port,
             certs: root_certs,
+            pin_set: None,
         };
         let StreamAndInfo(connection, info) = connector
             .connect(&connection_params, Alpn::Http1_1)
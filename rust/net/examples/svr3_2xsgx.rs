@@ -53,11 +53,13 @@ const TEST_SERVER_DOMAIN_CONFIG: DomainConfig = DomainConfig {
         hostname: "backend1.svr3.test.signal.org",
         port: nonzero!(443_u16),
         cert: TEST_SERVER_CERT,
+        pin_set: None,
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-test",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
 };
 const TEST_SERVER_ENDPOINT_PARAMS: EndpointParams<'static, Sgx> = EndpointParams {
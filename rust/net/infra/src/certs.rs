@@ -4,11 +4,17 @@
 //
 
 use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use boring_signal::error::ErrorStack;
+use boring_signal::sha::sha256;
 use boring_signal::ssl::{SslAlert, SslConnectorBuilder, SslVerifyError, SslVerifyMode};
+use boring_signal::stack::StackRef;
 use boring_signal::x509::store::X509StoreBuilder;
-use boring_signal::x509::X509;
+use boring_signal::x509::{X509Ref, X509VerifyResult, X509};
+use itertools::Itertools as _;
 use rustls::client::danger::ServerCertVerifier;
 
 use crate::host::Host;
@@ -34,12 +40,135 @@ pub enum RootCertificates {
     FromDer(Cow<'static, [u8]>),
 }
 
+/// SHA-256 digest of a DER-encoded SubjectPublicKeyInfo.
+pub type SpkiHash = [u8; 32];
+
+/// Public keys that a server's certificate chain must include, on top of
+/// chaining to one of the [`RootCertificates`].
+///
+/// Backup pins are for keys that aren't deployed yet, so that rotating to them
+/// doesn't require a client update.
+///
+/// With [`RootCertificates::Native`], the platform verifier doesn't expose the
+/// chain it built. The pins are checked against the certificates the server
+/// sent instead, following only those that actually issued the certificate
+/// before them, and ending with one of [`Self::anchors`] if that issued the
+/// last one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpkiPinSet {
+    pub primary: &'static [SpkiHash],
+    pub backup: &'static [SpkiHash],
+    /// DER-encoded certificates for pinned keys that servers don't send, such
+    /// as roots.
+    ///
+    /// Only used with [`RootCertificates::Native`]; otherwise the verified
+    /// chain already ends in one of the trusted roots.
+    pub anchors: &'static [&'static [u8]],
+    /// When the pins stop being enforced, as the time since the Unix epoch.
+    ///
+    /// This keeps clients that are never updated from failing every
+    /// connection once the pinned keys have been rotated out.
+    pub expires_after: Duration,
+}
+
+impl SpkiPinSet {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now.duration_since(SystemTime::UNIX_EPOCH)
+            .is_ok_and(|since_epoch| since_epoch > self.expires_after)
+    }
+
+    fn matches(&self, cert: &X509Ref) -> bool {
+        let Ok(hash) = spki_hash(cert) else {
+            return false;
+        };
+        self.primary.contains(&hash) || self.backup.contains(&hash)
+    }
+}
+
+fn spki_hash(cert: &X509Ref) -> Result<SpkiHash, ErrorStack> {
+    Ok(sha256(&cert.public_key()?.public_key_to_der()?))
+}
+
+/// Computes the [`SpkiHash`] of a DER-encoded certificate, as used for pins.
+pub fn spki_hash_of_der(cert_der: &[u8]) -> Result<SpkiHash, Error> {
+    Ok(spki_hash(&X509::from_der(cert_der)?)?)
+}
+
+/// Follows `sent_chain` from its leaf through the certificates that issued
+/// each one, finishing with one of `anchors` if possible.
+///
+/// Servers can send unrelated certificates along with their own, so ones that
+/// didn't issue the previous certificate in the chain are skipped.
+fn issuing_chain<'a>(sent_chain: &'a StackRef<X509>, anchors: &'a [X509]) -> Vec<&'a X509Ref> {
+    let mut sent = sent_chain.iter();
+    let Some(leaf) = sent.next() else {
+        return vec![];
+    };
+    let mut candidates = sent
+        .chain(anchors.iter().map(|anchor| &**anchor))
+        .collect_vec();
+    let mut chain = vec![leaf];
+    let mut current = leaf;
+    // Each candidate is used at most once, so this always finishes.
+    while let Some(position) = candidates
+        .iter()
+        .position(|candidate| was_issued_by(current, candidate))
+    {
+        current = candidates.swap_remove(position);
+        chain.push(current);
+    }
+    chain
+}
+
+fn was_issued_by(cert: &X509Ref, issuer: &X509Ref) -> bool {
+    issuer.issued(cert) == X509VerifyResult::OK
+        && issuer
+            .public_key()
+            .and_then(|key| cert.verify(&key))
+            .unwrap_or(false)
+}
+
+/// Reports whether a handshake was rejected because the server's certificate
+/// chain didn't include any key from an [`SpkiPinSet`].
+#[derive(Clone, Debug, Default)]
+pub struct PinCheck(Arc<AtomicBool>);
+
+impl PinCheck {
+    pub fn mismatched(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn verify<'a>(
+        &self,
+        pin_set: &SpkiPinSet,
+        chain: impl IntoIterator<Item = &'a X509Ref>,
+    ) -> bool {
+        let matched = chain.into_iter().any(|cert| pin_set.matches(cert));
+        if !matched {
+            log::warn!("TLS certificate chain doesn't include any pinned key");
+            self.0.store(true, Ordering::Relaxed);
+        }
+        matched
+    }
+}
+
 impl RootCertificates {
+    /// Configures `connector` to trust these certificates.
+    ///
+    /// If `pin_set` is present and not expired, the verified chain must also
+    /// include one of its keys. With [`RootCertificates::Native`] the platform
+    /// verifier doesn't expose the chain it built, so the chain the server sent
+    /// is checked instead (see [`SpkiPinSet`]).
     pub fn apply_to_connector(
         &self,
         connector: &mut SslConnectorBuilder,
         host: Host<&str>,
-    ) -> Result<(), Error> {
+        pin_set: Option<&SpkiPinSet>,
+    ) -> Result<PinCheck, Error> {
+        let pin_set = pin_set
+            .filter(|pin_set| !pin_set.is_expired(SystemTime::now()))
+            .cloned();
+        let pin_check = PinCheck::default();
         let ders: &[&[u8]] = match self {
             RootCertificates::Native => {
                 let mut verifier = rustls_platform_verifier::Verifier::new();
@@ -52,7 +181,8 @@ impl RootCertificates {
                     // dependency on ring.
                     verifier.set_provider(rustls::crypto::ring::default_provider().into())
                 }
-                return set_up_platform_verifier(connector, host, verifier);
+                set_up_platform_verifier(connector, host, verifier, pin_set, pin_check.clone())?;
+                return Ok(pin_check);
             }
             RootCertificates::FromStaticDers(ders) => ders,
             RootCertificates::FromDer(der) => &[der],
@@ -62,7 +192,25 @@ impl RootCertificates {
             store_builder.add_cert(X509::from_der(der)?)?;
         }
         connector.set_verify_cert_store(store_builder.build())?;
-        Ok(())
+
+        if let Some(pin_set) = pin_set {
+            let pin_check = pin_check.clone();
+            connector.set_verify_callback(SslVerifyMode::PEER, move |preverified, context| {
+                // Pins are checked once, against the full chain, after the
+                // leaf certificate has been verified.
+                if !preverified || context.error_depth() != 0 {
+                    return preverified;
+                }
+                let matched = context
+                    .chain()
+                    .is_some_and(|chain| pin_check.verify(&pin_set, chain));
+                if !matched {
+                    context.set_error(X509VerifyResult::APPLICATION_VERIFICATION);
+                }
+                matched
+            });
+        }
+        Ok(pin_check)
     }
 }
 
@@ -72,6 +220,8 @@ fn set_up_platform_verifier(
     connector: &mut SslConnectorBuilder,
     host: Host<&str>,
     verifier: impl ServerCertVerifier + 'static,
+    pin_set: Option<SpkiPinSet>,
+    pin_check: PinCheck,
 ) -> Result<(), Error> {
     let host_as_server_name = match host {
         Host::Domain(host_name) => rustls::pki_types::ServerName::try_from(host_name)
@@ -79,6 +229,16 @@ fn set_up_platform_verifier(
             .to_owned(),
         Host::Ip(ip) => rustls::pki_types::ServerName::IpAddress(ip.into()),
     };
+    let pins = pin_set
+        .map(|pin_set| {
+            let anchors = pin_set
+                .anchors
+                .iter()
+                .map(|der| X509::from_der(der))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok::<_, Error>((pin_set, anchors))
+        })
+        .transpose()?;
 
     connector.set_custom_verify_callback(SslVerifyMode::PEER, move |ssl| {
         // Get the certificate chain, lazily convert each certificate to DER (as expected by rustls).
@@ -162,6 +322,15 @@ fn set_up_platform_verifier(
                 })
             })?;
 
+        if let Some((pin_set, anchors)) = &pins {
+            let sent_chain = ssl
+                .peer_cert_chain()
+                .ok_or(SslVerifyError::Invalid(SslAlert::NO_CERTIFICATE))?;
+            if !pin_check.verify(pin_set, issuing_chain(sent_chain, anchors)) {
+                return Err(SslVerifyError::Invalid(SslAlert::CERTIFICATE_UNKNOWN));
+            }
+        }
+
         Ok(())
     });
 
@@ -170,7 +339,6 @@ fn set_up_platform_verifier(
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
 
     use assert_matches::assert_matches;
     use boring_signal::ssl::{ErrorCode, SslConnector, SslMethod};
@@ -201,6 +369,8 @@ mod test {
             &mut ssl,
             Host::Domain(SERVER_HOSTNAME),
             Arc::into_inner(verifier).expect("only one referent"),
+            None,
+            PinCheck::default(),
         )
         .expect("valid");
 
@@ -235,6 +405,8 @@ mod test {
             &mut ssl,
             Host::Domain(SERVER_HOSTNAME),
             Arc::into_inner(verifier).expect("only one referent"),
+            None,
+            PinCheck::default(),
         )
         .expect("valid");
 
@@ -249,4 +421,152 @@ mod test {
             Err(e) if e.code() == Some(ErrorCode::SSL)
         );
     }
+
+    fn pin_set_for(cert_der: &[u8]) -> SpkiPinSet {
+        let hash = spki_hash(&X509::from_der(cert_der).expect("valid")).expect("valid");
+        SpkiPinSet {
+            primary: Vec::leak(vec![hash]),
+            backup: &[],
+            anchors: &[],
+            expires_after: Duration::MAX,
+        }
+    }
+
+    async fn connect_with_pins(pin_set: &SpkiPinSet) -> (bool, PinCheck) {
+        let (addr, server) = localhost_http_server();
+        let _server_handle = tokio::spawn(server);
+
+        let mut ssl = SslConnector::builder(SslMethod::tls_client()).expect("valid");
+        let pin_check = RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der()))
+            .apply_to_connector(&mut ssl, Host::Domain(SERVER_HOSTNAME), Some(pin_set))
+            .expect("valid");
+
+        let transport = TcpStream::connect(addr).await.expect("can connect");
+        let result = tokio_boring_signal::connect(
+            ssl.build().configure().expect("valid"),
+            SERVER_HOSTNAME,
+            transport,
+        )
+        .await;
+        (result.is_ok(), pin_check)
+    }
+
+    #[tokio::test]
+    async fn pinned_key_in_chain_is_accepted() {
+        let pin_set = pin_set_for(SERVER_CERTIFICATE.cert.der());
+
+        let (connected, pin_check) = connect_with_pins(&pin_set).await;
+        assert!(connected);
+        assert!(!pin_check.mismatched());
+    }
+
+    #[tokio::test]
+    async fn backup_pin_is_accepted() {
+        let SpkiPinSet { primary, .. } = pin_set_for(SERVER_CERTIFICATE.cert.der());
+        let pin_set = SpkiPinSet {
+            primary: &[[0; 32]],
+            backup: primary,
+            anchors: &[],
+            expires_after: Duration::MAX,
+        };
+
+        let (connected, _pin_check) = connect_with_pins(&pin_set).await;
+        assert!(connected);
+    }
+
+    #[tokio::test]
+    async fn pin_mismatch_is_rejected_and_reported() {
+        let pin_set = pin_set_for(PROXY_CERTIFICATE.cert.der());
+
+        let (connected, pin_check) = connect_with_pins(&pin_set).await;
+        assert!(!connected);
+        assert!(pin_check.mismatched());
+    }
+
+    #[tokio::test]
+    async fn expired_pins_are_not_enforced() {
+        let pin_set = SpkiPinSet {
+            expires_after: Duration::ZERO,
+            ..pin_set_for(PROXY_CERTIFICATE.cert.der())
+        };
+
+        let (connected, pin_check) = connect_with_pins(&pin_set).await;
+        assert!(connected);
+        assert!(!pin_check.mismatched());
+    }
+
+    /// Connects with the platform verifier to a server whose certificate was
+    /// issued by a CA certificate that the server doesn't send, pinning the
+    /// CA's key.
+    async fn connect_to_ca_issued_server(pin_ca_as_anchor: bool) -> (bool, PinCheck) {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+        use warp::Filter as _;
+
+        let ca_key = KeyPair::generate().expect("can generate");
+        let mut ca_params = CertificateParams::new([]).expect("valid");
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_cert = ca_params.self_signed(&ca_key).expect("can sign");
+
+        let leaf_key = KeyPair::generate().expect("can generate");
+        let leaf_cert = CertificateParams::new([SERVER_HOSTNAME.to_string()])
+            .expect("valid")
+            .signed_by(&leaf_key, &ca_cert, &ca_key)
+            .expect("can sign");
+
+        let (addr, server) = warp::serve(warp::any().map(|| "Hello there"))
+            .tls()
+            .cert(leaf_cert.pem())
+            .key(leaf_key.serialize_pem())
+            .bind_ephemeral((std::net::Ipv6Addr::LOCALHOST, 0));
+        let _server_handle = tokio::spawn(server);
+
+        let mut root_cert_store = RootCertStore::empty();
+        root_cert_store.add(ca_cert.der().clone()).expect("valid");
+        let verifier = rustls::client::WebPkiServerVerifier::builder(Arc::new(root_cert_store))
+            .build()
+            .expect("valid");
+
+        let pin_set = SpkiPinSet {
+            anchors: if pin_ca_as_anchor {
+                Vec::leak(vec![&*Vec::leak(ca_cert.der().to_vec())])
+            } else {
+                &[]
+            },
+            ..pin_set_for(ca_cert.der())
+        };
+        let pin_check = PinCheck::default();
+
+        let mut ssl = SslConnector::builder(SslMethod::tls_client()).expect("valid");
+        set_up_platform_verifier(
+            &mut ssl,
+            Host::Domain(SERVER_HOSTNAME),
+            Arc::into_inner(verifier).expect("only one referent"),
+            Some(pin_set),
+            pin_check.clone(),
+        )
+        .expect("valid");
+
+        let transport = TcpStream::connect(addr).await.expect("can connect");
+        let result = tokio_boring_signal::connect(
+            ssl.build().configure().expect("valid"),
+            SERVER_HOSTNAME,
+            transport,
+        )
+        .await;
+        (result.is_ok(), pin_check)
+    }
+
+    #[tokio::test]
+    async fn pinned_anchor_is_accepted_with_platform_verifier() {
+        let (connected, pin_check) = connect_to_ca_issued_server(true).await;
+        assert!(connected);
+        assert!(!pin_check.mismatched());
+    }
+
+    #[tokio::test]
+    async fn unsent_issuer_is_not_pinned_without_anchor() {
+        let (connected, pin_check) = connect_to_ca_issued_server(false).await;
+        assert!(!connected);
+        assert!(pin_check.mismatched());
+    }
 }
@@ -996,6 +996,7 @@ mod test {
                 tcp_host: Host::Domain(Arc::clone(&host)),
                 certs: RootCertificates::Native,
                 port: nonzero!(443u16),
+                pin_set: None,
             },
             http_host: host,
             http_request_decorator: HttpRequestDecoratorSeq::default(),
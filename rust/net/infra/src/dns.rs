@@ -4,31 +4,29 @@
 //
 
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv6Addr};
+use std::net::Ipv6Addr;
 use std::str::FromStr as _;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use futures_util::{FutureExt as _, StreamExt as _};
 use oneshot_broadcast::Sender;
 use tokio::time::Instant;
 
-use crate::certs::RootCertificates;
 use crate::dns::custom_resolver::CustomDnsResolver;
 use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::{DnsLookup, DnsLookupRequest, StaticDnsMap, SystemDnsLookup};
-use crate::dns::dns_transport_doh::{DohTransport, CLOUDFLARE_IPS};
+use crate::dns::dns_transport_doh::{DohTransport, DohTransportParams};
 use crate::dns::dns_types::ResourceType;
 use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::doh_providers::{DohProviderConfig, DohProviderRace};
 use crate::dns::lookup_result::LookupResult;
+use crate::dns::persistent_cache::{DnsCacheStore, NetworkIdentity, PersistentDnsCache};
+use crate::dns::service_binding::ServiceBinding;
 use crate::host::Host;
-use crate::route::{
-    HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment, DEFAULT_HTTPS_PORT,
-};
 use crate::timeouts::{DNS_FALLBACK_LOOKUP_TIMEOUTS, DNS_SYSTEM_LOOKUP_TIMEOUT};
 use crate::utils::oneshot_broadcast::{self, Receiver};
 use crate::utils::{self, ObservableEvent};
-use crate::Alpn;
 
 pub mod custom_resolver;
 mod dns_errors;
@@ -38,7 +36,11 @@ pub mod dns_transport_doh;
 pub mod dns_transport_udp;
 mod dns_types;
 pub(crate) mod dns_utils;
+pub mod dnssec;
+pub mod doh_providers;
 pub mod lookup_result;
+pub mod persistent_cache;
+pub mod service_binding;
 
 pub type DnsError = Error;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -46,6 +48,9 @@ pub type Result<T> = std::result::Result<T, Error>;
 struct DnsResolverState {
     /// Controls if lookup results will contain IPv6 entries.
     ipv6_enabled: bool,
+    /// Controls if HTTPS records are looked up; see
+    /// [`DnsResolver::lookup_service_binding`].
+    service_binding_lookup_enabled: bool,
     in_flight_lookups: HashMap<String, Receiver<Result<LookupResult>>>,
 }
 
@@ -53,6 +58,10 @@ impl std::fmt::Debug for DnsResolverState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DnsResolverState")
             .field("ipv6_enabled", &self.ipv6_enabled)
+            .field(
+                "service_binding_lookup_enabled",
+                &self.service_binding_lookup_enabled,
+            )
             .field("in_flight_lookups", &self.in_flight_lookups.keys())
             .finish()
     }
@@ -62,6 +71,7 @@ impl Default for DnsResolverState {
     fn default() -> Self {
         Self {
             ipv6_enabled: true,
+            service_binding_lookup_enabled: false,
             in_flight_lookups: Default::default(),
         }
     }
@@ -71,6 +81,7 @@ impl Default for DnsResolverState {
 pub struct DnsResolver {
     lookup_options: Arc<[LookupOption]>,
     state: Arc<Mutex<DnsResolverState>>,
+    persistent_cache: Arc<PersistentDnsCache>,
 }
 
 /// A single DNS resolution strategy that can be tried.
@@ -84,29 +95,10 @@ struct LookupOption {
 pub fn build_custom_resolver_cloudflare_doh(
     network_change_event: &ObservableEvent,
 ) -> CustomDnsResolver<DohTransport> {
-    let (v4, v6) = CLOUDFLARE_IPS;
-    let targets = [IpAddr::V6(v6), IpAddr::V4(v4)].map(|ip_addr| {
-        let host = Host::Ip(ip_addr);
-        HttpsTlsRoute {
-            fragment: HttpRouteFragment {
-                path_prefix: "".into(),
-                front_name: None,
-                host_header: Arc::from(host.to_string()),
-            },
-            inner: TlsRoute {
-                fragment: TlsRouteFragment {
-                    sni: host,
-                    root_certs: RootCertificates::Native,
-                    alpn: Some(Alpn::Http2),
-                },
-                inner: TcpRoute {
-                    address: ip_addr,
-                    port: DEFAULT_HTTPS_PORT,
-                },
-            },
-        }
-    });
-    CustomDnsResolver::<DohTransport>::new(targets.into(), network_change_event)
+    CustomDnsResolver::<DohTransport>::new(
+        DohTransportParams::new(DohProviderConfig::cloudflare().routes()),
+        network_change_event,
+    )
 }
 
 impl DnsResolver {
@@ -123,6 +115,7 @@ impl DnsResolver {
         DnsResolver {
             lookup_options,
             state: Default::default(),
+            persistent_cache: Default::default(),
         }
     }
 
@@ -140,6 +133,7 @@ impl DnsResolver {
                 timeout_after: Duration::from_millis(1),
             }]),
             state: Default::default(),
+            persistent_cache: Default::default(),
         }
     }
 
@@ -149,14 +143,34 @@ impl DnsResolver {
         static_map: HashMap<&'static str, LookupResult>,
         network_change_event: &ObservableEvent,
     ) -> Self {
-        let cloudflare_doh = Box::new(build_custom_resolver_cloudflare_doh(network_change_event));
+        Self::new_with_doh_providers(
+            &DohProviderConfig::defaults(),
+            static_map,
+            network_change_event,
+        )
+    }
 
-        let cloudflare_fallback_options =
+    /// Like [`Self::new_with_static_fallback`], but falls back to racing
+    /// lookups across `doh_providers` instead of
+    /// [`DohProviderConfig::defaults`].
+    pub fn new_with_doh_providers(
+        doh_providers: &[DohProviderConfig],
+        static_map: HashMap<&'static str, LookupResult>,
+        network_change_event: &ObservableEvent,
+    ) -> Self {
+        let persistent_cache = Arc::new(PersistentDnsCache::default());
+        let doh = Box::new(DohProviderRace::new(
+            doh_providers,
+            persistent_cache.clone(),
+            network_change_event,
+        ));
+
+        let doh_fallback_options =
             DNS_FALLBACK_LOOKUP_TIMEOUTS
                 .iter()
                 .copied()
                 .map(|timeout_after| LookupOption {
-                    lookup: cloudflare_doh.clone(),
+                    lookup: doh.clone(),
                     timeout_after,
                 });
 
@@ -165,7 +179,7 @@ impl DnsResolver {
             timeout_after: DNS_SYSTEM_LOOKUP_TIMEOUT,
         }]
         .into_iter()
-        .chain(cloudflare_fallback_options)
+        .chain(doh_fallback_options)
         .chain([LookupOption {
             lookup: Box::new(StaticDnsMap(static_map)),
             timeout_after: Duration::from_secs(1),
@@ -174,6 +188,7 @@ impl DnsResolver {
         DnsResolver {
             lookup_options,
             state: Default::default(),
+            persistent_cache,
         }
     }
 
@@ -185,6 +200,38 @@ impl DnsResolver {
         }
     }
 
+    /// Enables looking up HTTPS records alongside addresses.
+    ///
+    /// This is off by default because only some lookup options (DNS-over-HTTPS
+    /// and UDP) can query HTTPS records, so enabling it means those are used
+    /// even when the system resolver would have sufficed. ECH configs are only
+    /// taken from answers that pass DNSSEC validation, which the default
+    /// DNS-over-HTTPS lookups don't perform.
+    pub fn set_service_binding_lookup_enabled(&self, enabled: bool) {
+        self.state
+            .lock()
+            .expect("not poisoned")
+            .service_binding_lookup_enabled = enabled;
+    }
+
+    /// Sets where DNS results are saved so that they can be reused after a
+    /// restart.
+    ///
+    /// Only results from resolvers that report record TTLs are saved, and only
+    /// while a network identity is set.
+    pub fn set_persistent_cache_store(&self, store: Option<Arc<dyn DnsCacheStore>>) {
+        self.persistent_cache.set_store(store)
+    }
+
+    /// Sets the identity of the current network, which determines which saved
+    /// DNS results can be used.
+    ///
+    /// This is independent of network change events; apps should update it
+    /// whenever they learn that the network has changed.
+    pub fn set_network_identity(&self, network: Option<NetworkIdentity>) {
+        self.persistent_cache.set_network(network)
+    }
+
     pub async fn lookup_ip(&self, hostname: &str) -> Result<LookupResult> {
         let parse_as_ip_addr = hostname.parse().ok().or_else(|| {
             let hostname = hostname.strip_prefix('[')?;
@@ -196,11 +243,10 @@ impl DnsResolver {
                 std::net::IpAddr::V4(ip) => (vec![ip], vec![]),
                 std::net::IpAddr::V6(ip) => (vec![], vec![ip]),
             };
-            return Ok(LookupResult {
-                source: super::DnsSource::Static,
-                ipv4,
-                ipv6,
-            });
+            return Ok(LookupResult::new(super::DnsSource::Static, ipv4, ipv6));
+        }
+        if let Some(cached) = self.persistent_cache_get(hostname) {
+            return Ok(cached);
         }
         match self.start_or_join_lookup(hostname).val().await {
             Ok(r) => r,
@@ -211,6 +257,67 @@ impl DnsResolver {
         }
     }
 
+    /// Looks up the [`ServiceBinding`] published for `hostname`, if any.
+    ///
+    /// Tries each lookup option that supports HTTPS records in order. Failures
+    /// are logged and treated as the absence of a record, since connecting
+    /// without one is always possible.
+    pub async fn lookup_service_binding(&self, hostname: &str) -> Option<ServiceBinding> {
+        let ipv6_enabled = {
+            let guard = self.state.lock().expect("not poisoned");
+            if !guard.service_binding_lookup_enabled {
+                return None;
+            }
+            guard.ipv6_enabled
+        };
+        if let Host::Ip(_) = Host::<&str>::parse_as_ip_or_domain(hostname) {
+            return None;
+        }
+        let request = DnsLookupRequest {
+            hostname: Arc::from(hostname),
+            ipv6_enabled,
+        };
+
+        for LookupOption {
+            lookup,
+            timeout_after,
+        } in self.lookup_options.iter()
+        {
+            match utils::timeout(
+                *timeout_after,
+                Error::Timeout,
+                lookup.service_binding_lookup(request.clone()),
+            )
+            .await
+            {
+                Ok(binding) => return binding,
+                // This lookup option doesn't support HTTPS records.
+                Err(Error::NoData) => continue,
+                Err(error) => {
+                    log::info!(
+                        "Failed to look up HTTPS record for [{}]: {}",
+                        log_safe_domain(hostname),
+                        error
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    fn persistent_cache_get(&self, hostname: &str) -> Option<LookupResult> {
+        let ipv6_enabled = self.state.lock().expect("not poisoned").ipv6_enabled;
+        let cached = self.persistent_cache.get(hostname, SystemTime::now())?;
+        match ipv6_enabled {
+            true => Some(cached),
+            false if cached.ipv4.is_empty() => None,
+            false => Some(LookupResult {
+                ipv6: vec![],
+                ..cached
+            }),
+        }
+    }
+
     fn start_or_join_lookup(&self, hostname: &str) -> Receiver<Result<LookupResult>> {
         let mut guard = self.state.lock().expect("not poisoned");
         let ipv6_enabled = guard.ipv6_enabled;
@@ -234,6 +341,7 @@ impl DnsResolver {
         let Self {
             lookup_options,
             state,
+            persistent_cache: _,
         } = self.clone();
         tokio::spawn(async move {
             let request = DnsLookupRequest {
@@ -318,6 +426,7 @@ mod test {
 
     use super::*;
     use crate::dns::dns_lookup::DnsLookupRequest;
+    use crate::dns::persistent_cache::PersistedDnsEntry;
     use crate::dns::{DnsLookup, DnsResolver, Error, LookupResult, StaticDnsMap};
     use crate::utils::sleep_and_catch_up;
     use crate::DnsSource;
@@ -764,4 +873,48 @@ mod test {
         // making sure that the `test_lookup` have only seen one request
         assert_matches!(test_lookup.logged_requests().as_slice(), [_, _]);
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_persistent_cache_consulted_before_lookup() {
+        #[derive(Debug)]
+        struct EmptyStore;
+
+        impl DnsCacheStore for EmptyStore {
+            fn load(&self, _network: &NetworkIdentity) -> Vec<PersistedDnsEntry> {
+                vec![]
+            }
+
+            fn save(&self, _network: &NetworkIdentity, _entries: Vec<PersistedDnsEntry>) {}
+        }
+
+        let test_lookup = TestLookup::standard_responses(Duration::ZERO);
+        let dns_resolver = DnsResolver::new_custom(vec![(test_lookup.clone(), ATTEMPT_TIMEOUT)]);
+        let network = NetworkIdentity("test".to_owned());
+        dns_resolver.set_persistent_cache_store(Some(Arc::new(EmptyStore)));
+        dns_resolver.set_network_identity(Some(network.clone()));
+
+        let now = SystemTime::now();
+        dns_resolver.persistent_cache.insert(
+            &network,
+            PersistedDnsEntry {
+                hostname: CUSTOM_DOMAIN.to_owned(),
+                source: DnsSource::DnsOverHttpsLookup,
+                ipv4: vec![IPV4],
+                ipv6: vec![IPV6],
+                expires_at: now + Duration::from_secs(60),
+            },
+            now,
+        );
+
+        let result = dns_resolver.lookup_ip(CUSTOM_DOMAIN).await.expect("cached");
+        assert_eq!(result.source(), DnsSource::Cache);
+        assert_eq!(result.ipv6, [IPV6]);
+
+        dns_resolver.set_ipv6_enabled(false);
+        let result = dns_resolver.lookup_ip(CUSTOM_DOMAIN).await.expect("cached");
+        assert_eq!(result.ipv4, [IPV4]);
+        assert_empty!(result.ipv6);
+
+        assert_empty!(test_lookup.logged_requests());
+    }
 }
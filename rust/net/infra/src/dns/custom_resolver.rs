@@ -9,7 +9,7 @@ use std::fmt::Debug;
 use std::future::Future;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use either::Either;
 use futures_util::{FutureExt as _, Stream, StreamExt as _};
@@ -21,7 +21,10 @@ use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_types::Expiring;
 use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::dnssec::DnssecStatus;
 use crate::dns::lookup_result::LookupResult;
+use crate::dns::persistent_cache::{PersistedDnsEntry, PersistentDnsCache};
+use crate::dns::service_binding::ServiceBinding;
 use crate::timeouts::{DNS_CALL_BACKGROUND_TIMEOUT, DNS_RESOLUTION_DELAY};
 use crate::utils::future::results_within_interval;
 use crate::utils::{EventSubscription, ObservableEvent};
@@ -29,7 +32,23 @@ use crate::{dns, DnsSource};
 
 pub type DnsIpv4Result = Expiring<Vec<Ipv4Addr>>;
 pub type DnsIpv6Result = Expiring<Vec<Ipv6Addr>>;
-pub type DnsQueryResult = Either<DnsIpv4Result, DnsIpv6Result>;
+
+/// The records returned for a single query.
+#[derive(Clone, Debug)]
+pub struct DnsQueryResult {
+    pub records: Either<DnsIpv4Result, DnsIpv6Result>,
+    pub dnssec: DnssecStatus,
+}
+
+impl DnsQueryResult {
+    /// A result for records that the transport didn't validate.
+    pub fn not_validated(records: Either<DnsIpv4Result, DnsIpv6Result>) -> Self {
+        Self {
+            records,
+            dnssec: DnssecStatus::NotValidated,
+        }
+    }
+}
 
 /// Artificially limit DNS lookup results, so we don't get stuck on stale info with a bad TTL field.
 const MAX_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
@@ -67,6 +86,17 @@ pub trait DnsTransport: Debug + Sized + Send {
     ) -> impl Future<
         Output = dns::Result<impl Stream<Item = dns::Result<DnsQueryResult>> + Send + 'static>,
     > + Send;
+
+    /// Sends a query for the HTTPS records of the requested name.
+    ///
+    /// Transports that can't make such queries keep the default
+    /// implementation, which fails with [`Error::NoData`].
+    fn send_service_binding_query(
+        self,
+        _request: DnsLookupRequest,
+    ) -> impl Future<Output = dns::Result<Expiring<Vec<ServiceBinding>>>> + Send {
+        std::future::ready(Err(Error::NoData))
+    }
 }
 
 #[derive(Debug)]
@@ -98,6 +128,9 @@ impl<K, V> Default for SharedCacheWithGenerations<K, V> {
 pub struct CustomDnsResolver<T: DnsTransport> {
     connection_manager: SingleRouteThrottlingConnectionManager<T::ConnectionParameters>,
     cache: Arc<std::sync::Mutex<SharedCacheWithGenerations<String, Expiring<LookupResult>>>>,
+    service_binding_cache:
+        Arc<std::sync::Mutex<SharedCacheWithGenerations<String, Expiring<Option<ServiceBinding>>>>>,
+    persistent_cache: Option<Arc<PersistentDnsCache>>,
     _network_change_subscription: Arc<EventSubscription>,
 }
 
@@ -107,14 +140,19 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
         network_change_event: &ObservableEvent,
     ) -> Self {
         let cache = Arc::new(std::sync::Mutex::new(SharedCacheWithGenerations::default()));
+        let service_binding_cache =
+            Arc::new(std::sync::Mutex::new(SharedCacheWithGenerations::default()));
         let cache_for_network_change = Arc::downgrade(&cache);
+        let service_binding_cache_for_network_change = Arc::downgrade(&service_binding_cache);
         let network_change_subscription = network_change_event.subscribe(Box::new(move || {
             // We're clearing the cache on network changes because some networks intercept DNS
             // requests and return IPs that only work within that network.
-            let Some(cache) = cache_for_network_change.upgrade() else {
-                return;
-            };
-            cache.lock().expect("not poisoned").clear_and_advance();
+            if let Some(cache) = cache_for_network_change.upgrade() {
+                cache.lock().expect("not poisoned").clear_and_advance();
+            }
+            if let Some(cache) = service_binding_cache_for_network_change.upgrade() {
+                cache.lock().expect("not poisoned").clear_and_advance();
+            }
         }));
         Self {
             connection_manager: SingleRouteThrottlingConnectionManager::new(
@@ -123,10 +161,20 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
                 network_change_event,
             ),
             cache,
+            service_binding_cache,
+            persistent_cache: None,
             _network_change_subscription: Arc::new(network_change_subscription),
         }
     }
 
+    /// Also saves results to `persistent_cache` while it has a current network.
+    pub fn with_persistent_cache(self, persistent_cache: Arc<PersistentDnsCache>) -> Self {
+        Self {
+            persistent_cache: Some(persistent_cache),
+            ..self
+        }
+    }
+
     pub async fn resolve(&self, request: DnsLookupRequest) -> dns::Result<LookupResult> {
         match self.cache_get(&request.hostname) {
             Some(res) => {
@@ -146,6 +194,65 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
         }
     }
 
+    /// Looks up the [`ServiceBinding`] for the requested name.
+    ///
+    /// Returns `Ok(None)` if the name has no applicable HTTPS record.
+    pub async fn resolve_service_binding(
+        &self,
+        request: DnsLookupRequest,
+    ) -> dns::Result<Option<ServiceBinding>> {
+        {
+            let mut guard = self.service_binding_cache.lock().expect("not poisoned");
+            match guard.map.get(&*request.hostname) {
+                Some(expiring) if expiring.expiration < Instant::now() => {
+                    guard.map.remove(&*request.hostname);
+                }
+                Some(expiring) => return Ok(expiring.data.clone()),
+                None => {}
+            }
+        }
+
+        let generation_before_lookup = self
+            .service_binding_cache
+            .lock()
+            .expect("not poisoned")
+            .generation;
+        let started_at = Instant::now();
+        let transport = match self
+            .connection_manager
+            .connect_or_wait(|params| T::connect(params.clone(), request.ipv6_enabled))
+            .await
+        {
+            ConnectionAttemptOutcome::Attempted(result) => result,
+            ConnectionAttemptOutcome::TimedOut => Err(Error::Timeout),
+            ConnectionAttemptOutcome::WaitUntil(_) => Err(Error::Cooldown),
+        }?;
+        let hostname = request.hostname.clone();
+        let expiring = match transport.send_service_binding_query(request).await {
+            Ok(Expiring { data, expiration }) => Expiring {
+                data: ServiceBinding::select(data, &hostname),
+                expiration,
+            },
+            // Remember that there's nothing to find, so that connections don't
+            // keep paying for the query.
+            Err(Error::NoData) => Expiring {
+                data: None,
+                expiration: started_at + MAX_CACHE_TTL,
+            },
+            Err(error) => return Err(error),
+        };
+        let expiring = Expiring {
+            expiration: min(expiring.expiration, started_at + MAX_CACHE_TTL),
+            ..expiring
+        };
+
+        let mut guard = self.service_binding_cache.lock().expect("not poisoned");
+        if guard.generation == generation_before_lookup {
+            guard.map.insert(hostname.to_string(), expiring.clone());
+        }
+        Ok(expiring.data)
+    }
+
     fn cache_get(&self, hostname: &str) -> Option<LookupResult> {
         let mut guard = self.cache.lock().expect("not poisoned");
         match guard.map.get(hostname) {
@@ -175,9 +282,13 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
             DNS_RESOLUTION_DELAY,
         )
         .await;
-        let ipv4s = maybe_ipv4.map_or(vec![], |r| r.data);
-        let ipv6s = maybe_ipv6.map_or(vec![], |r| r.data);
-        match LookupResult::new(T::dns_source(), ipv4s, ipv6s) {
+        let dnssec = combined_dnssec_status(
+            maybe_ipv4.as_ref().map(|(_, status)| *status),
+            maybe_ipv6.as_ref().map(|(_, status)| *status),
+        );
+        let ipv4s = maybe_ipv4.map_or(vec![], |(r, _)| r.data);
+        let ipv6s = maybe_ipv6.map_or(vec![], |(r, _)| r.data);
+        match LookupResult::new(T::dns_source(), ipv4s, ipv6s).with_dnssec_status(dnssec) {
             lookup_result if !lookup_result.is_empty() => Ok(lookup_result),
             _ => Err(Error::LookupFailed),
         }
@@ -194,14 +305,18 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
         transport: T,
         request: DnsLookupRequest,
     ) -> (
-        oneshot::Receiver<DnsIpv4Result>,
-        oneshot::Receiver<DnsIpv6Result>,
+        oneshot::Receiver<(DnsIpv4Result, DnssecStatus)>,
+        oneshot::Receiver<(DnsIpv6Result, DnssecStatus)>,
     ) {
-        let (ipv4_res_tx, ipv4_res_rx) = oneshot::channel::<DnsIpv4Result>();
-        let (ipv6_res_tx, ipv6_res_rx) = oneshot::channel::<DnsIpv6Result>();
+        let (ipv4_res_tx, ipv4_res_rx) = oneshot::channel();
+        let (ipv6_res_tx, ipv6_res_rx) = oneshot::channel();
         let cache = self.cache.clone();
         let generation_before_lookup = cache.lock().expect("not poisoned").generation;
         let hostname = request.hostname.clone();
+        let persistent_cache = self.persistent_cache.as_ref().and_then(|persistent_cache| {
+            let network = persistent_cache.current_network()?;
+            Some((persistent_cache.clone(), network))
+        });
         // We're starting this operation on a separate thread because we want to let it run
         // beyond an individual attempt timeout so that even if a result arrived late
         // we could still cache it for the next time.
@@ -220,8 +335,30 @@ impl<T: DnsTransport + Sync + 'static> CustomDnsResolver<T> {
                 // In the second case caching the result would still be valid, but trying to
                 // distinguish them is tricky. Not caching just means we might do another lookup
                 // sooner than necessary.
-                if guard.generation == generation_before_lookup {
-                    guard.map.insert(hostname.to_string(), expiring_entry);
+                if guard.generation != generation_before_lookup {
+                    return;
+                }
+                let now = SystemTime::now();
+                let persisted = persistent_cache.map(|(persistent_cache, network)| {
+                    let Expiring { data, expiration } = &expiring_entry;
+                    let entry = PersistedDnsEntry {
+                        hostname: hostname.to_string(),
+                        source: T::dns_source(),
+                        ipv4: data.ipv4.clone(),
+                        ipv6: data.ipv6.clone(),
+                        expires_at: now + expiration.saturating_duration_since(Instant::now()),
+                    };
+                    (persistent_cache, network, entry)
+                });
+                guard.map.insert(hostname.to_string(), expiring_entry);
+                drop(guard);
+
+                if let Some((persistent_cache, network, entry)) = persisted {
+                    // Stores may write to disk, so keep them off the runtime's
+                    // worker threads, where they'd hold up other lookups.
+                    tokio::task::spawn_blocking(move || {
+                        persistent_cache.insert(&network, entry, now)
+                    });
                 }
             },
         ));
@@ -235,8 +372,8 @@ async fn do_lookup_task_body<T: DnsTransport>(
     transport: T,
     request: DnsLookupRequest,
     (ipv4_res_tx, ipv6_res_tx): (
-        oneshot::Sender<DnsIpv4Result>,
-        oneshot::Sender<DnsIpv6Result>,
+        oneshot::Sender<(DnsIpv4Result, DnssecStatus)>,
+        oneshot::Sender<(DnsIpv6Result, DnssecStatus)>,
     ),
     try_cache_result: impl FnOnce(Expiring<LookupResult>),
 ) {
@@ -270,12 +407,15 @@ async fn do_lookup_task_body<T: DnsTransport>(
             _ = tokio::time::sleep_until(timeout_at) => None,
             res = stream.next() => res,
         } {
-            Some(Ok(DnsQueryResult::Left(res))) => {
-                maybe_ipv4_res = Some(res.clone());
+            Some(Ok(DnsQueryResult {
+                records: Either::Left(res),
+                dnssec,
+            })) => {
+                maybe_ipv4_res = Some((res.clone(), dnssec));
                 if let Some(p) = ipv4_res_tx_opt.take() {
                     // it is possible that the receiver is dropped,
                     // so we're not treating this as an error
-                    let _ = p.send(res);
+                    let _ = p.send((res, dnssec));
                 }
                 log::info!(
                     "Received result of the IPv4 DNS query for [{}] after {:?}",
@@ -283,12 +423,15 @@ async fn do_lookup_task_body<T: DnsTransport>(
                     started_at.elapsed()
                 );
             }
-            Some(Ok(DnsQueryResult::Right(res))) => {
-                maybe_ipv6_res = Some(res.clone());
+            Some(Ok(DnsQueryResult {
+                records: Either::Right(res),
+                dnssec,
+            })) => {
+                maybe_ipv6_res = Some((res.clone(), dnssec));
                 if let Some(p) = ipv6_res_tx_opt.take() {
                     // it is possible that the receiver is dropped,
                     // so we're not treating this as an error
-                    let _ = p.send(res);
+                    let _ = p.send((res, dnssec));
                 }
                 log::info!(
                     "Received result of the IPv6 DNS query for [{}] after {:?}",
@@ -316,18 +459,22 @@ async fn do_lookup_task_body<T: DnsTransport>(
     }
 
     let Some(expiration) = min(
-        maybe_ipv4_res.as_ref().map(|e| e.expiration),
-        maybe_ipv6_res.as_ref().map(|e| e.expiration),
+        maybe_ipv4_res.as_ref().map(|(e, _)| e.expiration),
+        maybe_ipv6_res.as_ref().map(|(e, _)| e.expiration),
     ) else {
         // Nothing to cache
         return;
     };
 
     // update cache
-    let v4 = maybe_ipv4_res.map_or(vec![], |e| e.data);
-    let v6 = maybe_ipv6_res.map_or(vec![], |e| e.data);
+    let dnssec = combined_dnssec_status(
+        maybe_ipv4_res.as_ref().map(|(_, status)| *status),
+        maybe_ipv6_res.as_ref().map(|(_, status)| *status),
+    );
+    let v4 = maybe_ipv4_res.map_or(vec![], |(e, _)| e.data);
+    let v6 = maybe_ipv6_res.map_or(vec![], |(e, _)| e.data);
     let expiring_entry = Expiring {
-        data: LookupResult::new(DnsSource::Cache, v4, v6),
+        data: LookupResult::new(DnsSource::Cache, v4, v6).with_dnssec_status(dnssec),
         // Clamp cached TTLs.
         expiration: min(expiration, started_at + MAX_CACHE_TTL),
     };
@@ -335,6 +482,11 @@ async fn do_lookup_task_body<T: DnsTransport>(
     try_cache_result(expiring_entry)
 }
 
+/// A combined result is only as trustworthy as the least trustworthy of its parts.
+fn combined_dnssec_status(ipv4: Option<DnssecStatus>, ipv6: Option<DnssecStatus>) -> DnssecStatus {
+    ipv4.into_iter().chain(ipv6).min().unwrap_or_default()
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use std::collections::HashSet;
@@ -348,6 +500,7 @@ pub(crate) mod test {
     use futures_util::stream::FuturesUnordered;
 
     use super::*;
+    use crate::dns::persistent_cache::{DnsCacheStore, NetworkIdentity};
     use crate::timeouts::CONNECTION_ROUTE_MAX_COOLDOWN;
     use crate::utils::{sleep_and_catch_up, sleep_until_and_catch_up};
 
@@ -505,17 +658,17 @@ pub(crate) mod test {
     }
 
     fn ok_query_result_ipv4(ttl: Duration, data: &[Ipv4Addr]) -> dns::Result<DnsQueryResult> {
-        Ok(DnsQueryResult::Left(Expiring {
+        Ok(DnsQueryResult::not_validated(Either::Left(Expiring {
             data: data.to_vec(),
             expiration: Instant::now() + ttl,
-        }))
+        })))
     }
 
     fn ok_query_result_ipv6(ttl: Duration, data: &[Ipv6Addr]) -> dns::Result<DnsQueryResult> {
-        Ok(DnsQueryResult::Right(Expiring {
+        Ok(DnsQueryResult::not_validated(Either::Right(Expiring {
             data: data.to_vec(),
             expiration: Instant::now() + ttl,
-        }))
+        })))
     }
 
     fn respond_after_timeout(
@@ -842,4 +995,59 @@ pub(crate) mod test {
         lookup.await.expect("success");
         assert_matches!(resolver.cache_get(&test_request().hostname), None);
     }
+
+    #[derive(Debug)]
+    struct BlockingStore {
+        saving: tokio::sync::mpsc::UnboundedSender<()>,
+        release: std::sync::Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl DnsCacheStore for BlockingStore {
+        fn load(&self, _network: &NetworkIdentity) -> Vec<PersistedDnsEntry> {
+            vec![]
+        }
+
+        fn save(&self, _network: &NetworkIdentity, _entries: Vec<PersistedDnsEntry>) {
+            _ = self.saving.send(());
+            _ = self.release.lock().expect("not poisoned").recv();
+        }
+    }
+
+    // Not paused: time doesn't auto-advance while a blocking task is running.
+    #[tokio::test]
+    async fn blocked_persistent_store_does_not_hold_up_lookups() {
+        let resolver = TestDnsTransportWithTwoResponses::custom_dns_resolver(|_, _, txs| {
+            let [tx_1, tx_2] = txs;
+            tx_1.send(ok_query_result_ipv4(NORMAL_TTL, IP_V4_LIST_1))
+                .expect("receiver alive");
+            tx_2.send(ok_query_result_ipv6(NORMAL_TTL, IP_V6_LIST_1))
+                .expect("receiver alive");
+        });
+        let (saving_tx, mut saving_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let persistent_cache = Arc::new(PersistentDnsCache::default());
+        persistent_cache.set_store(Some(Arc::new(BlockingStore {
+            saving: saving_tx,
+            release: release_rx.into(),
+        })));
+        persistent_cache.set_network(Some(NetworkIdentity("home".to_owned())));
+        let resolver = resolver.with_persistent_cache(persistent_cache);
+
+        resolver.resolve(test_request()).await.expect("success");
+        saving_rx.recv().await.expect("started saving");
+
+        // This test uses a current-thread runtime, so if the store were
+        // called on it, this lookup would never finish.
+        let other_request = DnsLookupRequest {
+            hostname: Arc::from("cdn.signal.org"),
+            ipv6_enabled: true,
+        };
+        let result = tokio::time::timeout(Duration::from_secs(5), resolver.resolve(other_request))
+            .await
+            .expect("not held up by the store")
+            .expect("success");
+        assert_lookup_result_content_equal(&result, IP_V4_LIST_1, IP_V6_LIST_1);
+
+        drop(release_tx);
+    }
 }
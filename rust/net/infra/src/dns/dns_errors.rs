@@ -5,8 +5,8 @@
 
 use std::io;
 
-use crate::dns::dns_message;
 use crate::dns::dns_message::MAX_DNS_UDP_MESSAGE_LEN;
+use crate::dns::{dns_message, dnssec};
 
 #[derive(displaydoc::Display, Debug, thiserror::Error, Clone)]
 pub enum Error {
@@ -36,6 +36,8 @@ pub enum Error {
     Protocol(dns_message::Error),
     /// DNS request resulted in a non-zero error code: {0}
     RequestFailedWithErrorCode(u8),
+    /// DNSSEC validation failed: {0}
+    Dnssec(dnssec::ValidationError),
 }
 
 impl From<dns_message::Error> for Error {
@@ -47,7 +49,8 @@ impl From<dns_message::Error> for Error {
             | dns_message::Error::ProtocolErrorUnexpectedValue
             | dns_message::Error::ProtocolErrorInvalidNameCharacters
             | dns_message::Error::ProtocolErrorFailedToParseResourceRecord
-            | dns_message::Error::ProtocolErrorInvalidMessage => Error::Protocol(error),
+            | dns_message::Error::ProtocolErrorInvalidMessage
+            | dns_message::Error::ProtocolErrorTruncated => Error::Protocol(error),
             dns_message::Error::NoData => Error::NoData,
             dns_message::Error::RequestFailedWithErrorCode(code) => {
                 Error::RequestFailedWithErrorCode(code)
@@ -56,6 +59,12 @@ impl From<dns_message::Error> for Error {
     }
 }
 
+impl From<dnssec::ValidationError> for Error {
+    fn from(error: dnssec::ValidationError) -> Self {
+        Error::Dnssec(error)
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(a: io::Error) -> Self {
         Error::Io(a.kind())
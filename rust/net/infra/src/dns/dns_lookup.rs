@@ -15,6 +15,7 @@ use itertools::Itertools;
 use crate::dns::custom_resolver::{CustomDnsResolver, DnsTransport};
 use crate::dns::dns_errors::Error;
 use crate::dns::lookup_result::LookupResult;
+use crate::dns::service_binding::ServiceBinding;
 use crate::{dns, DnsSource};
 
 #[derive(Clone, Debug)]
@@ -26,6 +27,18 @@ pub struct DnsLookupRequest {
 #[async_trait]
 pub trait DnsLookup: Debug + Send + Sync {
     async fn dns_lookup(&self, request: DnsLookupRequest) -> dns::Result<LookupResult>;
+
+    /// Looks up the [`ServiceBinding`] for the requested name.
+    ///
+    /// Returns `Ok(None)` if the name has no applicable HTTPS record. Lookups
+    /// that can't query HTTPS records keep the default implementation, which
+    /// fails with [`Error::NoData`] so that the next lookup can be tried.
+    async fn service_binding_lookup(
+        &self,
+        _request: DnsLookupRequest,
+    ) -> dns::Result<Option<ServiceBinding>> {
+        Err(Error::NoData)
+    }
 }
 
 /// Performs DNS lookup using system resolver
@@ -73,4 +86,11 @@ where
     async fn dns_lookup(&self, request: DnsLookupRequest) -> dns::Result<LookupResult> {
         self.resolve(request).await
     }
+
+    async fn service_binding_lookup(
+        &self,
+        request: DnsLookupRequest,
+    ) -> dns::Result<Option<ServiceBinding>> {
+        self.resolve_service_binding(request).await
+    }
 }
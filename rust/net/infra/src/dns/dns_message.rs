@@ -15,7 +15,9 @@ use bitstream_io::{
 use tokio::time::Instant;
 
 use crate::dns::dns_types::Expiring;
+use crate::dns::service_binding::ServiceBinding;
 use crate::dns::ResourceType;
+use crate::route::EchConfigList;
 
 pub(crate) const QCLASS_IN: u16 = 1;
 const POINTER_MASK: u8 = 0xC0;
@@ -25,6 +27,11 @@ const POINTER_MASK: u8 = 0xC0;
 pub(crate) const MAX_DNS_LABEL_LEN: usize = 63;
 pub(crate) const MAX_DNS_NAME_LEN: usize = 255;
 pub(crate) const MAX_DNS_UDP_MESSAGE_LEN: usize = 512;
+/// The UDP payload size advertised in EDNS requests.
+///
+/// This is the value recommended to avoid IP fragmentation; see
+/// <https://www.dnsflagday.net/2020/>.
+pub(crate) const EDNS_UDP_PAYLOAD_SIZE: usize = 1232;
 
 #[derive(displaydoc::Display, Debug, thiserror::Error, Clone)]
 pub enum Error {
@@ -42,6 +49,8 @@ pub enum Error {
     ProtocolErrorInvalidNameCharacters,
     /// Failed to parse resourse record
     ProtocolErrorFailedToParseResourceRecord,
+    /// The response was truncated
+    ProtocolErrorTruncated,
     /// Data for the given name is not available
     NoData,
     /// DNS request resulted in a non-zero error code: {0}
@@ -69,6 +78,15 @@ pub fn create_request_with_id(
     request_id: u16,
     domain: &str,
     resource_type: ResourceType,
+) -> Result<Vec<u8>> {
+    create_request(request_id, domain, resource_type, false)
+}
+
+fn create_request(
+    request_id: u16,
+    domain: &str,
+    resource_type: ResourceType,
+    allow_root: bool,
 ) -> Result<Vec<u8>> {
     // the information hardcoded in this section is that the message is a request
     // and that the request is recursive
@@ -91,7 +109,12 @@ pub fn create_request_with_id(
     // Question section
 
     // name
-    write_name(&mut writer.bytewriter().expect("byte aligned"), domain)?;
+    let mut name_writer = writer.bytewriter().expect("byte aligned");
+    if allow_root && domain.is_empty() {
+        name_writer.write(0u8)?;
+    } else {
+        write_name(&mut name_writer, domain)?;
+    }
     // the rest of the request
     writer.write_from(resource_type as u16)?;
     writer.write_from(QCLASS_IN)?;
@@ -99,6 +122,41 @@ pub fn create_request_with_id(
     Ok(writer.into_writer())
 }
 
+/// Like [`create_request_with_id`], but asks for DNSSEC signatures to be
+/// included in the response.
+///
+/// This adds an EDNS `OPT` record with the `DO` bit set. Unlike plain
+/// requests, an empty `domain` is allowed and refers to the root zone.
+///
+/// [EDNS](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1)
+/// [DO bit](https://datatracker.ietf.org/doc/html/rfc3225#section-3)
+pub fn create_dnssec_request_with_id(
+    request_id: u16,
+    domain: &str,
+    resource_type: ResourceType,
+) -> Result<Vec<u8>> {
+    const ADDITIONAL_RECORD_COUNT_OFFSET: usize = 10;
+    const OPT_RESOURCE_TYPE: u16 = 41;
+    const DNSSEC_OK_FLAG: u16 = 0x8000;
+
+    let mut request = create_request(request_id, domain, resource_type, true)?;
+    request[ADDITIONAL_RECORD_COUNT_OFFSET..][..2].copy_from_slice(&1u16.to_be_bytes());
+
+    let mut writer = ByteWriter::endian(&mut request, BigEndian);
+    // root name
+    writer.write(0u8)?;
+    writer.write(OPT_RESOURCE_TYPE)?;
+    // the class field holds the payload size
+    writer.write(u16::try_from(EDNS_UDP_PAYLOAD_SIZE).expect("fits"))?;
+    // the TTL field holds the extended response code, version, and flags
+    writer.write(0u8)?;
+    writer.write(0u8)?;
+    writer.write(DNSSEC_OK_FLAG)?;
+    // no options
+    writer.write(0u16)?;
+    Ok(request)
+}
+
 pub fn get_id(message: &[u8]) -> Result<u16> {
     match message {
         [a, b, ..] => Ok(((*a as u16) << 8) | *b as u16),
@@ -120,6 +178,84 @@ pub fn parse_aaaa_record(bytes_vec: &[u8]) -> Result<Ipv6Addr> {
     Ok(Ipv6Addr::from(octets))
 }
 
+/// Parses the data of an HTTPS (or SVCB) record.
+///
+/// Only the parameters we use are kept; others are skipped.
+///
+/// [Record format](https://datatracker.ietf.org/doc/html/rfc9460#section-2.2)
+pub fn parse_https_record(bytes_vec: &[u8]) -> Result<ServiceBinding> {
+    const SVC_PARAM_KEY_ALPN: u16 = 1;
+    const SVC_PARAM_KEY_ECH: u16 = 5;
+
+    let invalid = || Error::ProtocolErrorFailedToParseResourceRecord;
+
+    let (priority, rest) = bytes_vec.split_first_chunk::<2>().ok_or_else(invalid)?;
+    let priority = u16::from_be_bytes(*priority);
+
+    // Name compression is not allowed for the target name, so there are no
+    // preceding bytes that it could point into.
+    let mut target = vec![];
+    let mut reader = ByteReader::endian(Cursor::new(rest), BigEndian);
+    read_name_to_vec(&mut reader, &[], &mut target)?;
+    let target =
+        String::from_utf8(target).map_err(|_| Error::ProtocolErrorInvalidNameCharacters)?;
+    let name_len: usize = reader
+        .into_reader()
+        .position()
+        .try_into()
+        .expect("in usize range");
+
+    let mut params = &rest[name_len..];
+    let mut alpn = vec![];
+    let mut ech_config_list = None;
+    let mut previous_key = None;
+    while !params.is_empty() {
+        let (key, rest) = params.split_first_chunk::<2>().ok_or_else(invalid)?;
+        let (len, rest) = rest.split_first_chunk::<2>().ok_or_else(invalid)?;
+        let key = u16::from_be_bytes(*key);
+        let len = u16::from_be_bytes(*len).into();
+        if rest.len() < len || previous_key.is_some_and(|previous| key <= previous) {
+            // Keys must be in strictly increasing order.
+            return Err(invalid());
+        }
+        let (value, rest) = rest.split_at(len);
+        params = rest;
+        previous_key = Some(key);
+
+        match key {
+            SVC_PARAM_KEY_ALPN => alpn = parse_alpn_ids(value)?,
+            SVC_PARAM_KEY_ECH => ech_config_list = Some(EchConfigList::new(value)),
+            _ => {}
+        }
+    }
+
+    Ok(ServiceBinding {
+        priority,
+        target,
+        alpn,
+        ech_config_list,
+    })
+}
+
+/// Parses a list of length-prefixed ALPN protocol IDs.
+///
+/// IDs that aren't valid UTF-8 can't be ones we support, so they are skipped.
+fn parse_alpn_ids(mut value: &[u8]) -> Result<Vec<String>> {
+    let mut ids = vec![];
+    while let Some((len, rest)) = value.split_first() {
+        let len = usize::from(*len);
+        if len == 0 || rest.len() < len {
+            return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+        }
+        let (id, rest) = rest.split_at(len);
+        value = rest;
+        if let Ok(id) = std::str::from_utf8(id) {
+            ids.push(id.to_owned());
+        }
+    }
+    Ok(ids)
+}
+
 pub fn parse_response<T>(
     message: &[u8],
     expected_type: ResourceType,
@@ -127,31 +263,10 @@ pub fn parse_response<T>(
 ) -> Result<Expiring<Vec<T>>> {
     let mut reader = BitReader::endian(Cursor::new(message), BigEndian);
 
-    let _id = reader.read_to::<u16>()?;
-    let _flag_is_response = reader.read_bit()?;
-    let _flag_operation_code = reader.read::<u8>(4)?;
-    let _flag_authoritative_answer = reader.read_bit()?;
-    let _flag_truncated = reader.read_bit()?;
-    let _flag_recursion_requested = reader.read_bit()?;
-    let _flag_recursion_available = reader.read_bit()?;
-    let _flag_zero = reader.read::<u8>(3)?;
-    let response_code = reader.read::<u8>(4)?;
-    let _questions_count = reader.read_to::<u16>()?;
-    let answers_count = reader.read_to::<u16>()?;
-    let _authority_record_count = reader.read_to::<u16>()?;
-    let _additional_record_count = reader.read_to::<u16>()?;
-
-    if response_code != 0 {
-        return Err(Error::RequestFailedWithErrorCode(response_code));
-    }
-    if answers_count == 0 {
-        return Err(Error::NoData);
-    }
-
-    // question section repeats here
-    let _name = read_name(&mut reader, message)?;
-    let _data_type = reader.read_to::<u16>()?;
-    let _data_class = reader.read_to::<u16>()?;
+    let ResponseHeader {
+        truncated: _,
+        answers_count,
+    } = read_response_header(&mut reader, message)?;
 
     let mut results = Vec::with_capacity(answers_count.into());
     let mut min_ttl = u32::MAX;
@@ -185,6 +300,212 @@ pub fn parse_response<T>(
     })
 }
 
+/// A resource record from the answer section of a response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Record {
+    /// The owner name, in lowercase.
+    ///
+    /// This is empty for the root.
+    pub name: String,
+    pub resource_type: u16,
+    pub class: u16,
+    pub ttl: u32,
+    /// The record data, with any compressed names expanded.
+    pub data: Vec<u8>,
+}
+
+/// Parses all records in the answer section of a response, whatever their
+/// type.
+///
+/// Unlike [`parse_response`], this fails for truncated responses, since
+/// callers need the complete set of records.
+pub(crate) fn parse_answer_records(message: &[u8]) -> Result<Vec<Record>> {
+    let mut reader = BitReader::endian(Cursor::new(message), BigEndian);
+
+    let ResponseHeader {
+        truncated,
+        answers_count,
+    } = read_response_header(&mut reader, message)?;
+    if truncated {
+        return Err(Error::ProtocolErrorTruncated);
+    }
+
+    (0..answers_count)
+        .map(|_| read_record(&mut reader, message))
+        .collect()
+}
+
+/// The records in a response that are needed to validate it.
+#[derive(Clone, Debug)]
+pub(crate) struct SignedResponse {
+    /// The name that was queried, in lowercase.
+    pub question: String,
+    /// Whether the server reported that the queried name doesn't exist.
+    pub name_error: bool,
+    pub answers: Vec<Record>,
+    /// Records from the authority section, which for DNSSEC responses include
+    /// the proofs that records don't exist.
+    pub authority: Vec<Record>,
+}
+
+/// Parses the answer and authority sections of a response.
+///
+/// Unlike [`parse_answer_records`], this succeeds for responses without
+/// answers, including those reporting that the queried name doesn't exist,
+/// since those are what prove a record's absence.
+pub(crate) fn parse_signed_response(message: &[u8]) -> Result<SignedResponse> {
+    const RESPONSE_CODE_NAME_ERROR: u8 = 3;
+
+    let mut reader = BitReader::endian(Cursor::new(message), BigEndian);
+    let Header {
+        truncated,
+        response_code,
+        answers_count,
+        authority_count,
+        question,
+    } = read_header(&mut reader, message)?;
+    if truncated {
+        return Err(Error::ProtocolErrorTruncated);
+    }
+    let name_error = match response_code {
+        0 => false,
+        RESPONSE_CODE_NAME_ERROR => true,
+        code => return Err(Error::RequestFailedWithErrorCode(code)),
+    };
+
+    let answers = (0..answers_count)
+        .map(|_| read_record(&mut reader, message))
+        .collect::<Result<_>>()?;
+    let authority = (0..authority_count)
+        .map(|_| read_record(&mut reader, message))
+        .collect::<Result<_>>()?;
+    Ok(SignedResponse {
+        question: question.to_ascii_lowercase(),
+        name_error,
+        answers,
+        authority,
+    })
+}
+
+/// Returns whether the response was truncated, and so should be repeated over
+/// a transport without UDP's size limit.
+pub(crate) fn is_truncated(message: &[u8]) -> Result<bool> {
+    const TRUNCATED_FLAG: u8 = 0x02;
+
+    match message {
+        [_, _, flags, ..] => Ok(flags & TRUNCATED_FLAG != 0),
+        _ => Err(Error::ProtocolErrorInvalidMessage),
+    }
+}
+
+fn read_record<R: io::Read + io::Seek>(
+    reader: &mut BitReader<R, BigEndian>,
+    message: &[u8],
+) -> Result<Record> {
+    const CNAME_RESOURCE_TYPE: u16 = 5;
+
+    let name = read_name(reader, message)?.to_ascii_lowercase();
+    let resource_type = reader.read_to::<u16>()?;
+    let class = reader.read_to::<u16>()?;
+    let ttl = reader.read_to::<u32>()?;
+    let data_length = reader.read_to::<u16>()?;
+
+    let data = if resource_type == CNAME_RESOURCE_TYPE {
+        // The target name may be compressed, which would make the data
+        // meaningless outside of this message.
+        let target = read_name(reader, message)?.to_ascii_lowercase();
+        let mut data = vec![];
+        write_name(&mut ByteWriter::endian(&mut data, BigEndian), &target)?;
+        data
+    } else {
+        reader.read_to_vec(data_length.into())?
+    };
+    Ok(Record {
+        name,
+        resource_type,
+        class,
+        ttl,
+        data,
+    })
+}
+
+/// The header fields that matter when reading a response.
+struct ResponseHeader {
+    truncated: bool,
+    answers_count: u16,
+}
+
+/// Reads the header and question sections, leaving `reader` at the start of
+/// the answer section.
+///
+/// Fails for error responses and for those without any answers.
+fn read_response_header<R: io::Read + io::Seek>(
+    reader: &mut BitReader<R, BigEndian>,
+    message: &[u8],
+) -> Result<ResponseHeader> {
+    let Header {
+        truncated,
+        response_code,
+        answers_count,
+        authority_count: _,
+        question: _,
+    } = read_header(reader, message)?;
+
+    if response_code != 0 {
+        return Err(Error::RequestFailedWithErrorCode(response_code));
+    }
+    if answers_count == 0 {
+        return Err(Error::NoData);
+    }
+
+    Ok(ResponseHeader {
+        truncated,
+        answers_count,
+    })
+}
+
+struct Header {
+    truncated: bool,
+    response_code: u8,
+    answers_count: u16,
+    authority_count: u16,
+    question: String,
+}
+
+/// Like [`read_response_header`], but doesn't check the response code or the
+/// number of answers.
+fn read_header<R: io::Read + io::Seek>(
+    reader: &mut BitReader<R, BigEndian>,
+    message: &[u8],
+) -> Result<Header> {
+    let _id = reader.read_to::<u16>()?;
+    let _flag_is_response = reader.read_bit()?;
+    let _flag_operation_code = reader.read::<u8>(4)?;
+    let _flag_authoritative_answer = reader.read_bit()?;
+    let flag_truncated = reader.read_bit()?;
+    let _flag_recursion_requested = reader.read_bit()?;
+    let _flag_recursion_available = reader.read_bit()?;
+    let _flag_zero = reader.read::<u8>(3)?;
+    let response_code = reader.read::<u8>(4)?;
+    let _questions_count = reader.read_to::<u16>()?;
+    let answers_count = reader.read_to::<u16>()?;
+    let authority_count = reader.read_to::<u16>()?;
+    let _additional_record_count = reader.read_to::<u16>()?;
+
+    // question section repeats here
+    let question = read_name(reader, message)?;
+    let _data_type = reader.read_to::<u16>()?;
+    let _data_class = reader.read_to::<u16>()?;
+
+    Ok(Header {
+        truncated: flag_truncated,
+        response_code,
+        answers_count,
+        authority_count,
+        question,
+    })
+}
+
 fn write_name<W: io::Write>(writer: &mut ByteWriter<W, BigEndian>, name: &str) -> Result<()> {
     let no_trailing_dot = name.strip_suffix('.').unwrap_or(name);
 
@@ -557,6 +878,137 @@ mod test {
         assert_eq!(&[EXPECTED_IP], response.data.as_slice());
     }
 
+    #[test]
+    fn parse_https_record_with_alpn_and_ech() {
+        const RDATA: &[u8] = concat_bytes!(
+            [0x00, 0x01],                   // priority
+            [0x00],                         // target: "."
+            [0x00, 0x01, 0x00, 0x0C, 0x02], // alpn
+            b"h2",
+            [0x08],
+            b"http/1.1",
+            [0x00, 0x03, 0x00, 0x00],                   // port (ignored)
+            [0x00, 0x05, 0x00, 0x03, 0xEC, 0xEC, 0xEC], // ech
+        );
+
+        let binding = parse_https_record(RDATA).expect("valid record");
+        assert_eq!(
+            binding,
+            ServiceBinding {
+                priority: 1,
+                target: "".to_owned(),
+                alpn: vec!["h2".to_owned(), "http/1.1".to_owned()],
+                ech_config_list: Some(EchConfigList::new([0xEC; 3].as_slice())),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_https_record_rejects_unordered_keys() {
+        const RDATA: &[u8] = concat_bytes!(
+            [0x00, 0x01],
+            [0x00],
+            [0x00, 0x05, 0x00, 0x01, 0xEC],
+            [0x00, 0x01, 0x00, 0x03, 0x02],
+            b"h2",
+        );
+
+        assert_matches!(
+            parse_https_record(RDATA),
+            Err(Error::ProtocolErrorFailedToParseResourceRecord)
+        );
+    }
+
+    #[test]
+    fn parse_https_record_rejects_truncated_value() {
+        const RDATA: &[u8] = concat_bytes!([0x00, 0x01], [0x00], [0x00, 0x05, 0x00, 0x04, 0xEC]);
+
+        assert_matches!(
+            parse_https_record(RDATA),
+            Err(Error::ProtocolErrorFailedToParseResourceRecord)
+        );
+    }
+
+    #[test]
+    fn dnssec_request_sets_dnssec_ok() {
+        let query = create_dnssec_request_with_id(REQUEST_ID, VALID_DOMAIN, ResourceType::A)
+            .expect("valid request");
+
+        let message = hickory_proto::op::Message::from_vec(&query).expect("valid message");
+        let edns = message.extensions().as_ref().expect("has EDNS");
+        assert!(edns.dnssec_ok());
+        assert_eq!(usize::from(edns.max_payload()), EDNS_UDP_PAYLOAD_SIZE);
+        assert_eq!(message.queries()[0].name().to_ascii(), "chat.signal.org.");
+    }
+
+    #[test]
+    fn dnssec_request_allows_root() {
+        let query = create_dnssec_request_with_id(REQUEST_ID, "", ResourceType::DNSKEY)
+            .expect("valid request");
+
+        let message = hickory_proto::op::Message::from_vec(&query).expect("valid message");
+        assert!(message.queries()[0].name().is_root());
+    }
+
+    #[test]
+    fn answer_records_are_parsed_with_names_expanded() {
+        let name = Name::from_str("Chat.Signal.org").expect("valid name");
+        let response_message = response_bytes(RecordType::A, |message| {
+            let mut rr = hickory_proto::rr::Record::<RData>::new();
+            rr.set_name(name.clone())
+                .set_record_type(RecordType::CNAME)
+                .set_ttl(100)
+                .set_data(Some(RData::CNAME(CNAME(
+                    Name::from_str("cname.signal.org").unwrap(),
+                ))));
+            message.add_answer(rr);
+        });
+
+        let records = parse_answer_records(&response_message).expect("parsed");
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.name, "chat.signal.org");
+        assert_eq!(record.resource_type, 5);
+        assert_eq!(record.ttl, 100);
+        // The target name is written out in full rather than as a pointer.
+        assert_eq!(
+            record.data,
+            concat_bytes!(5, b"cname", 6, b"signal", 3, b"org", 0).to_vec()
+        );
+    }
+
+    #[test]
+    fn signed_response_includes_authority_records_for_missing_names() {
+        let response_message = response_bytes(RecordType::DS, |message| {
+            message.set_response_code(ResponseCode::NXDomain);
+            let mut rr = hickory_proto::rr::Record::<RData>::new();
+            rr.set_name(Name::from_str("Signal.org").unwrap())
+                .set_record_type(RecordType::A)
+                .set_ttl(100)
+                .set_data(Some(RData::A(A(Ipv4Addr::LOCALHOST))));
+            message.add_name_server(rr);
+        });
+
+        let response = parse_signed_response(&response_message).expect("parsed");
+        assert_eq!(response.question, VALID_DOMAIN);
+        assert!(response.name_error);
+        assert_eq!(response.answers, []);
+        assert_eq!(response.authority.len(), 1);
+        assert_eq!(response.authority[0].name, "signal.org");
+    }
+
+    #[test]
+    fn truncated_flag_is_read() {
+        let mut response_message = response_bytes(RecordType::A, |_| {});
+        assert_matches!(is_truncated(&response_message), Ok(false));
+        response_message[2] |= 0x02;
+        assert_matches!(is_truncated(&response_message), Ok(true));
+        assert_matches!(
+            parse_signed_response(&response_message),
+            Err(Error::ProtocolErrorTruncated)
+        );
+    }
+
     fn response_bytes<F>(record_type: RecordType, builder: F) -> Vec<u8>
     where
         F: FnOnce(&mut hickory_proto::op::message::Message),
@@ -4,9 +4,11 @@
 //
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use bytes::Bytes;
 use const_str::ip_addr;
+use either::Either;
 use futures_util::stream::FuturesUnordered;
 use futures_util::Stream;
 use http::uri::PathAndQuery;
@@ -16,8 +18,10 @@ use crate::dns::custom_resolver::{DnsQueryResult, DnsTransport};
 use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_message;
-use crate::dns::dns_message::{parse_a_record, parse_aaaa_record};
-use crate::dns::dns_types::ResourceType;
+use crate::dns::dns_message::{parse_a_record, parse_aaaa_record, parse_https_record};
+use crate::dns::dns_types::{Expiring, ResourceType};
+use crate::dns::dnssec::{DnssecQuery, DnssecStatus, DnssecValidator};
+use crate::dns::service_binding::ServiceBinding;
 use crate::http_client::{http2_client, AggregatingHttp2Client};
 use crate::route::{HttpsTlsRoute, TcpRoute, TlsRoute};
 use crate::{dns, DnsSource};
@@ -28,14 +32,45 @@ pub(crate) const CLOUDFLARE_IPS: (Ipv4Addr, Ipv6Addr) = (
 );
 const MAX_RESPONSE_SIZE: usize = 10240;
 
+/// Connection parameters for [`DohTransport`]
+#[derive(Clone, Debug)]
+pub struct DohTransportParams {
+    routes: Vec<HttpsTlsRoute<TlsRoute<TcpRoute<IpAddr>>>>,
+    dnssec_validator: Option<Arc<DnssecValidator>>,
+}
+
+impl DohTransportParams {
+    pub fn new(routes: Vec<HttpsTlsRoute<TlsRoute<TcpRoute<IpAddr>>>>) -> Self {
+        Self {
+            routes,
+            dnssec_validator: None,
+        }
+    }
+
+    /// Requests DNSSEC signatures and checks them against the root trust
+    /// anchor, so that the provider doesn't have to be trusted with the
+    /// answers.
+    ///
+    /// See [`UdpTransportParams::with_dnssec_validation`].
+    ///
+    /// [`UdpTransportParams::with_dnssec_validation`]: crate::dns::dns_transport_udp::UdpTransportParams::with_dnssec_validation
+    pub fn with_dnssec_validation(self) -> Self {
+        Self {
+            dnssec_validator: Some(Arc::new(DnssecValidator::with_root_trust_anchors())),
+            ..self
+        }
+    }
+}
+
 /// DNS transport that sends queries over HTTPS
 #[derive(Clone, Debug)]
 pub struct DohTransport {
     http_client: AggregatingHttp2Client,
+    dnssec_validator: Option<Arc<DnssecValidator>>,
 }
 
 impl DnsTransport for DohTransport {
-    type ConnectionParameters = Vec<HttpsTlsRoute<TlsRoute<TcpRoute<IpAddr>>>>;
+    type ConnectionParameters = DohTransportParams;
 
     fn dns_source() -> DnsSource {
         DnsSource::DnsOverHttpsLookup
@@ -45,9 +80,16 @@ impl DnsTransport for DohTransport {
         connection_params: Self::ConnectionParameters,
         _ipv6_enabled: bool,
     ) -> dns::Result<Self> {
+        let DohTransportParams {
+            routes,
+            dnssec_validator,
+        } = connection_params;
         let log_tag = "DNS-over-HTTPS".into();
-        match http2_client(connection_params, MAX_RESPONSE_SIZE, &log_tag).await {
-            Ok(http_client) => Ok(Self { http_client }),
+        match http2_client(routes, MAX_RESPONSE_SIZE, &log_tag).await {
+            Ok(http_client) => Ok(Self {
+                http_client,
+                dnssec_validator,
+            }),
             Err(error) => {
                 log::error!("[{log_tag}] Failed to create HTTP2 client: {error}");
                 Err(Error::TransportFailure)
@@ -70,6 +112,20 @@ impl DnsTransport for DohTransport {
         };
         Ok(FuturesUnordered::from_iter(futures))
     }
+
+    async fn send_service_binding_query(
+        self,
+        request: DnsLookupRequest,
+    ) -> dns::Result<Expiring<Vec<ServiceBinding>>> {
+        let response_body = self
+            .send_query(&request.hostname, ResourceType::HTTPS)
+            .await?;
+        let mut bindings =
+            dns_message::parse_response(&response_body, ResourceType::HTTPS, parse_https_record)?;
+        let dnssec = self.validate(&response_body).await?;
+        ServiceBinding::clear_unauthenticated(&mut bindings.data, dnssec);
+        Ok(bindings)
+    }
 }
 
 impl DohTransport {
@@ -78,11 +134,49 @@ impl DohTransport {
         request: DnsLookupRequest,
         resource_type: ResourceType,
     ) -> dns::Result<DnsQueryResult> {
+        let response_body = self.send_query(&request.hostname, resource_type).await?;
+        let result = match resource_type {
+            ResourceType::A => Either::Left(dns_message::parse_response(
+                &response_body,
+                ResourceType::A,
+                parse_a_record,
+            )?),
+            ResourceType::AAAA => Either::Right(dns_message::parse_response(
+                &response_body,
+                ResourceType::AAAA,
+                parse_aaaa_record,
+            )?),
+            ResourceType::HTTPS
+            | ResourceType::DS
+            | ResourceType::RRSIG
+            | ResourceType::NSEC
+            | ResourceType::DNSKEY
+            | ResourceType::NSEC3 => {
+                unreachable!("not an address query")
+            }
+        };
+        let dnssec = self.validate(&response_body).await?;
+        Ok(DnsQueryResult {
+            records: result,
+            dnssec,
+        })
+    }
+
+    async fn validate(&self, message: &[u8]) -> dns::Result<DnssecStatus> {
+        let Some(validator) = &self.dnssec_validator else {
+            return Ok(DnssecStatus::NotValidated);
+        };
+        validator.validate(message, self, SystemTime::now()).await
+    }
+
+    async fn send_query(&self, hostname: &str, resource_type: ResourceType) -> dns::Result<Bytes> {
         // In DoH, responses are correlated with requests via HTTP,
         // so request ID should always be 0
         // https://datatracker.ietf.org/doc/html/rfc8484#section-4.1
-        let request_message =
-            dns_message::create_request_with_id(0, &request.hostname, resource_type)?;
+        let request_message = match self.dnssec_validator {
+            Some(_) => dns_message::create_dnssec_request_with_id(0, hostname, resource_type)?,
+            None => dns_message::create_request_with_id(0, hostname, resource_type)?,
+        };
 
         let (response_parts, response_body) = self
             .http_client
@@ -104,18 +198,14 @@ impl DohTransport {
         if response_parts.status.as_u16() != 200 {
             return Err(Error::DohRequestBadStatus(response_parts.status.as_u16()));
         }
-        let result = match resource_type {
-            ResourceType::A => DnsQueryResult::Left(dns_message::parse_response(
-                &response_body,
-                ResourceType::A,
-                parse_a_record,
-            )?),
-            ResourceType::AAAA => DnsQueryResult::Right(dns_message::parse_response(
-                &response_body,
-                ResourceType::AAAA,
-                parse_aaaa_record,
-            )?),
-        };
-        Ok(result)
+        Ok(response_body)
+    }
+}
+
+/// Sends the additional queries needed to validate a response over the same
+/// connection.
+impl DnssecQuery for DohTransport {
+    async fn query(&self, name: &str, resource_type: ResourceType) -> dns::Result<Vec<u8>> {
+        Ok(self.send_query(name, resource_type).await?.to_vec())
     }
 }
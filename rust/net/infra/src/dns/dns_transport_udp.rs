@@ -5,29 +5,68 @@
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
+use std::time::SystemTime;
 
+use either::Either;
 use futures_util::{stream, Stream, StreamExt};
-use tokio::net::UdpSocket;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::{TcpStream, UdpSocket};
 
 use crate::dns::custom_resolver::{DnsQueryResult, DnsTransport};
 use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_message;
-use crate::dns::dns_message::{parse_a_record, parse_aaaa_record, MAX_DNS_UDP_MESSAGE_LEN};
-use crate::dns::dns_types::ResourceType;
+use crate::dns::dns_message::{
+    parse_a_record, parse_aaaa_record, parse_https_record, EDNS_UDP_PAYLOAD_SIZE,
+    MAX_DNS_UDP_MESSAGE_LEN,
+};
+use crate::dns::dns_types::{Expiring, ResourceType};
+use crate::dns::dnssec::{DnssecQuery, DnssecStatus, DnssecValidator};
+use crate::dns::service_binding::ServiceBinding;
 use crate::{dns, DnsSource};
 
 const A_REQUEST_ID: u16 = 0;
 const AAAA_REQUEST_ID: u16 = 1;
+const HTTPS_REQUEST_ID: u16 = 2;
+
+/// Connection parameters for [`UdpTransport`]
+#[derive(Clone, Debug)]
+pub struct UdpTransportParams {
+    server: (IpAddr, u16),
+    dnssec_validator: Option<Arc<DnssecValidator>>,
+}
+
+impl UdpTransportParams {
+    pub fn new(server: (IpAddr, u16)) -> Self {
+        Self {
+            server,
+            dnssec_validator: None,
+        }
+    }
+
+    /// Requests DNSSEC signatures and checks them against the root trust
+    /// anchor, failing queries whose signatures don't verify.
+    ///
+    /// Unsigned answers are only accepted for names in zones that are proven to
+    /// be unsigned, and are reported as [`DnssecStatus::Unsigned`].
+    pub fn with_dnssec_validation(self) -> Self {
+        Self {
+            dnssec_validator: Some(Arc::new(DnssecValidator::with_root_trust_anchors())),
+            ..self
+        }
+    }
+}
 
 /// DNS transport that sends queries in plaintext over UDP
 #[derive(Clone, Debug)]
 pub struct UdpTransport {
     socket: Arc<UdpSocket>,
+    server: (IpAddr, u16),
+    dnssec_validator: Option<Arc<DnssecValidator>>,
 }
 
 impl DnsTransport for UdpTransport {
-    type ConnectionParameters = (IpAddr, u16);
+    type ConnectionParameters = UdpTransportParams;
 
     fn dns_source() -> DnsSource {
         DnsSource::UdpLookup
@@ -37,15 +76,15 @@ impl DnsTransport for UdpTransport {
         connection_params: Self::ConnectionParameters,
         ipv6_enabled: bool,
     ) -> dns::Result<UdpTransport> {
-        let local_addr = match connection_params.0 {
-            IpAddr::V4(_) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
-            IpAddr::V6(_) if !ipv6_enabled => return Err(Error::TransportRestricted),
-            IpAddr::V6(_) => (IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
-        };
-        let socket = UdpSocket::bind(local_addr).await?;
-        socket.connect(connection_params).await?;
+        let UdpTransportParams {
+            server,
+            dnssec_validator,
+        } = connection_params;
+        let socket = connect_socket(server, ipv6_enabled).await?;
         Ok(UdpTransport {
             socket: Arc::new(socket),
+            server,
+            dnssec_validator,
         })
     }
 
@@ -60,36 +99,115 @@ impl DnsTransport for UdpTransport {
         if request.ipv6_enabled {
             arc.send_request(&request.hostname, AAAA_REQUEST_ID, ResourceType::AAAA)
                 .await?;
-            futures.push(arc.clone().next_dns_query_result1());
+            futures.push(arc.clone().next_dns_query_result1(request.hostname.clone()));
         }
 
         // always sending A request
         arc.send_request(&request.hostname, A_REQUEST_ID, ResourceType::A)
             .await?;
-        futures.push(arc.clone().next_dns_query_result1());
+        futures.push(arc.clone().next_dns_query_result1(request.hostname.clone()));
         Ok(stream::iter(futures).then(|task| task))
     }
+
+    async fn send_service_binding_query(
+        self,
+        request: DnsLookupRequest,
+    ) -> dns::Result<Expiring<Vec<ServiceBinding>>> {
+        self.send_request(&request.hostname, HTTPS_REQUEST_ID, ResourceType::HTTPS)
+            .await?;
+        let mut buf = [0; EDNS_UDP_PAYLOAD_SIZE];
+        let bytes_received = self.socket.recv(&mut buf).await?;
+        let message = &buf[..bytes_received];
+        if dns_message::get_id(message)? != HTTPS_REQUEST_ID {
+            return Err(Error::UnexpectedMessageId);
+        }
+        let message = self
+            .untruncated(
+                message,
+                &request.hostname,
+                HTTPS_REQUEST_ID,
+                ResourceType::HTTPS,
+            )
+            .await?;
+        let mut bindings =
+            dns_message::parse_response(&message, ResourceType::HTTPS, parse_https_record)?;
+        let dnssec = self.validate(&message).await?;
+        ServiceBinding::clear_unauthenticated(&mut bindings.data, dnssec);
+        Ok(bindings)
+    }
 }
 
 impl UdpTransport {
-    async fn next_dns_query_result1(self: Arc<Self>) -> dns::Result<DnsQueryResult> {
-        let mut buf = [0; MAX_DNS_UDP_MESSAGE_LEN];
+    async fn next_dns_query_result1(
+        self: Arc<Self>,
+        hostname: Arc<str>,
+    ) -> dns::Result<DnsQueryResult> {
+        let mut buf = [0; EDNS_UDP_PAYLOAD_SIZE];
         let bytes_received = self.socket.recv(&mut buf).await?;
         let message = &buf[..bytes_received];
-        let result = match dns_message::get_id(message)? {
-            A_REQUEST_ID => DnsQueryResult::Left(dns_message::parse_response(
-                message,
+        let (request_id, resource_type) = match dns_message::get_id(message)? {
+            A_REQUEST_ID => (A_REQUEST_ID, ResourceType::A),
+            AAAA_REQUEST_ID => (AAAA_REQUEST_ID, ResourceType::AAAA),
+            _ => Err(Error::UnexpectedMessageId)?,
+        };
+        let message = self
+            .untruncated(message, &hostname, request_id, resource_type)
+            .await?;
+        let records = match resource_type {
+            ResourceType::A => Either::Left(dns_message::parse_response(
+                &message,
                 ResourceType::A,
                 parse_a_record,
             )?),
-            AAAA_REQUEST_ID => DnsQueryResult::Right(dns_message::parse_response(
-                message,
+            _ => Either::Right(dns_message::parse_response(
+                &message,
                 ResourceType::AAAA,
                 parse_aaaa_record,
             )?),
-            _ => Err(Error::UnexpectedMessageId)?,
         };
-        Ok(result)
+        let dnssec = self.validate(&message).await?;
+        Ok(DnsQueryResult { records, dnssec })
+    }
+
+    /// Returns `message`, or if it was truncated, the complete response
+    /// fetched over TCP.
+    async fn untruncated(
+        &self,
+        message: &[u8],
+        hostname: &str,
+        request_id: u16,
+        resource_type: ResourceType,
+    ) -> dns::Result<Vec<u8>> {
+        if !dns_message::is_truncated(message)? {
+            return Ok(message.to_vec());
+        }
+        log::debug!("UDP response was truncated, retrying over TCP");
+        let request = self.create_request(hostname, request_id, resource_type)?;
+        query_over_tcp(self.server, &request).await
+    }
+
+    async fn validate(&self, message: &[u8]) -> dns::Result<DnssecStatus> {
+        let Some(validator) = &self.dnssec_validator else {
+            return Ok(DnssecStatus::NotValidated);
+        };
+        let query = UdpDnssecQuery {
+            server: self.server,
+        };
+        validator.validate(message, &query, SystemTime::now()).await
+    }
+
+    fn create_request(
+        &self,
+        hostname: &str,
+        request_id: u16,
+        resource_type: ResourceType,
+    ) -> dns::Result<Vec<u8>> {
+        Ok(match self.dnssec_validator {
+            Some(_) => {
+                dns_message::create_dnssec_request_with_id(request_id, hostname, resource_type)?
+            }
+            None => dns_message::create_request_with_id(request_id, hostname, resource_type)?,
+        })
     }
 
     async fn send_request(
@@ -98,7 +216,7 @@ impl UdpTransport {
         request_id: u16,
         resource_type: ResourceType,
     ) -> dns::Result<()> {
-        let request = dns_message::create_request_with_id(request_id, hostname, resource_type)?;
+        let request = self.create_request(hostname, request_id, resource_type)?;
         let udp_message = match request {
             data if data.len() > MAX_DNS_UDP_MESSAGE_LEN => Err(Error::MessageTooLong),
             data => Ok(data),
@@ -107,3 +225,144 @@ impl UdpTransport {
         Ok(())
     }
 }
+
+/// Sends the additional queries needed to validate a response.
+///
+/// Each query gets its own socket and a random ID so that its response can't
+/// be mistaken for one to the lookup being validated.
+struct UdpDnssecQuery {
+    server: (IpAddr, u16),
+}
+
+impl DnssecQuery for UdpDnssecQuery {
+    async fn query(&self, name: &str, resource_type: ResourceType) -> dns::Result<Vec<u8>> {
+        // The server address was already accepted when the transport connected.
+        let socket = connect_socket(self.server, true).await?;
+        let request_id = rand::random();
+        let request = dns_message::create_dnssec_request_with_id(request_id, name, resource_type)?;
+        let _bytes_sent = socket.send(&request).await?;
+
+        let mut buf = [0; EDNS_UDP_PAYLOAD_SIZE];
+        loop {
+            let bytes_received = socket.recv(&mut buf).await?;
+            let message = &buf[..bytes_received];
+            if dns_message::get_id(message)? != request_id {
+                continue;
+            }
+            // Key sets in particular are often too large for a UDP response.
+            if dns_message::is_truncated(message)? {
+                return query_over_tcp(self.server, &request).await;
+            }
+            return Ok(message.to_vec());
+        }
+    }
+}
+
+/// Sends `request` over TCP, which doesn't limit the size of the response.
+///
+/// See [RFC 1035 section 4.2.2](https://datatracker.ietf.org/doc/html/rfc1035#section-4.2.2)
+/// and [RFC 7766](https://datatracker.ietf.org/doc/html/rfc7766).
+async fn query_over_tcp(server: (IpAddr, u16), request: &[u8]) -> dns::Result<Vec<u8>> {
+    let request_len = u16::try_from(request.len()).map_err(|_| Error::MessageTooLong)?;
+    let mut stream = TcpStream::connect(server).await?;
+    let mut framed = request_len.to_be_bytes().to_vec();
+    framed.extend_from_slice(request);
+    stream.write_all(&framed).await?;
+
+    let response_len = stream.read_u16().await?;
+    let mut response = vec![0; response_len.into()];
+    stream.read_exact(&mut response).await?;
+    if dns_message::get_id(&response)? != dns_message::get_id(request)? {
+        return Err(Error::UnexpectedMessageId);
+    }
+    Ok(response)
+}
+
+async fn connect_socket(server: (IpAddr, u16), ipv6_enabled: bool) -> dns::Result<UdpSocket> {
+    let local_addr = match server.0 {
+        IpAddr::V4(_) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        IpAddr::V6(_) if !ipv6_enabled => return Err(Error::TransportRestricted),
+        IpAddr::V6(_) => (IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    let socket = UdpSocket::bind(local_addr).await?;
+    socket.connect(server).await?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use assert_matches::assert_matches;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Turns `request` into a response with a single A record, or into an
+    /// empty truncated one.
+    fn response(request: &[u8], truncated: bool) -> Vec<u8> {
+        let mut response = request.to_vec();
+        // Response with recursion desired, and maybe truncated.
+        response[2] = 0x81 | if truncated { 0x02 } else { 0 };
+        // Recursion available.
+        response[3] = 0x80;
+        if !truncated {
+            response[7] = 1;
+            // A pointer to the name in the question.
+            response.extend([0xC0, 12]);
+            response.extend((ResourceType::A as u16).to_be_bytes());
+            response.extend(dns_message::QCLASS_IN.to_be_bytes());
+            response.extend(300u32.to_be_bytes());
+            response.extend(4u16.to_be_bytes());
+            response.extend([192, 0, 2, 1]);
+        }
+        response
+    }
+
+    #[tokio::test]
+    async fn truncated_response_is_retried_over_tcp() {
+        let udp_server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let server_addr = udp_server.local_addr().unwrap();
+        let tcp_server = TcpListener::bind(server_addr).await.unwrap();
+
+        let transport = UdpTransport::connect(
+            UdpTransportParams::new((server_addr.ip(), server_addr.port())),
+            false,
+        )
+        .await
+        .unwrap();
+        let results = transport
+            .send_queries(DnsLookupRequest {
+                hostname: "chat.signal.org".into(),
+                ipv6_enabled: false,
+            })
+            .await
+            .unwrap();
+
+        let mut buf = [0; EDNS_UDP_PAYLOAD_SIZE];
+        let (len, client) = udp_server.recv_from(&mut buf).await.unwrap();
+        udp_server
+            .send_to(&response(&buf[..len], true), client)
+            .await
+            .unwrap();
+
+        let tcp_server = async {
+            let (mut stream, _) = tcp_server.accept().await.unwrap();
+            let len = stream.read_u16().await.unwrap();
+            let mut request = vec![0; len.into()];
+            stream.read_exact(&mut request).await.unwrap();
+            let response = response(&request, false);
+            stream
+                .write_u16(response.len().try_into().unwrap())
+                .await
+                .unwrap();
+            stream.write_all(&response).await.unwrap();
+        };
+        let (results, ()) = tokio::join!(results.collect::<Vec<_>>(), tcp_server);
+
+        assert_matches!(&results[..], [Ok(DnsQueryResult { records: Either::Left(ipv4), .. })] => {
+            assert_eq!(ipv4.data, [Ipv4Addr::new(192, 0, 2, 1)]);
+        });
+    }
+}
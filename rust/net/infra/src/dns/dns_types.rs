@@ -18,7 +18,7 @@ pub struct Expiring<T> {
 /// from [RFC1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2)
 /// and [RFC3596](https://datatracker.ietf.org/doc/html/rfc3596#section-2.1)
 #[repr(u16)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ResourceType {
     /// An IPv4 host address type
@@ -29,4 +29,28 @@ pub enum ResourceType {
     ///
     /// https://datatracker.ietf.org/doc/html/rfc3596#section-2.1
     AAAA = 28,
+    /// Delegation signer, linking a zone's keys to its parent
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-5
+    DS = 43,
+    /// A signature over a set of records
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-3
+    RRSIG = 46,
+    /// Proof that no records exist between two names in a zone
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-4
+    NSEC = 47,
+    /// A zone's public key
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-2
+    DNSKEY = 48,
+    /// Like [`Self::NSEC`], but with hashed names
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc5155#section-3
+    NSEC3 = 50,
+    /// A service binding for HTTPS
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc9460#section-9
+    HTTPS = 65,
 }
@@ -0,0 +1,1751 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! DNSSEC validation of responses.
+//!
+//! A response is trusted if every record set in it is signed by a key that has
+//! a chain of signatures leading up to one of the root zone keys embedded here.
+//! The keys and delegation records needed to build that chain are fetched with
+//! additional queries, and cached once validated.
+//!
+//! Record sets without signatures are only accepted once a signed parent zone
+//! has proven, with NSEC or NSEC3 records, that the zone containing them isn't
+//! signed. Otherwise an on-path attacker could strip the signatures to skip
+//! validation.
+//!
+//! See [RFC 4033](https://datatracker.ietf.org/doc/html/rfc4033),
+//! [RFC 4034](https://datatracker.ietf.org/doc/html/rfc4034),
+//! [RFC 4035](https://datatracker.ietf.org/doc/html/rfc4035), and
+//! [RFC 5155](https://datatracker.ietf.org/doc/html/rfc5155).
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use boring_signal::bn::BigNum;
+use boring_signal::ec::{EcGroup, EcKey};
+use boring_signal::ecdsa::EcdsaSig;
+use boring_signal::error::ErrorStack;
+use boring_signal::hash::MessageDigest;
+use boring_signal::nid::Nid;
+use boring_signal::pkey::PKey;
+use boring_signal::rsa::Rsa;
+use boring_signal::sha::{sha1, sha256};
+use boring_signal::sign::Verifier;
+use hex_literal::hex;
+use indexmap::IndexMap;
+use itertools::Itertools as _;
+
+use crate::dns;
+use crate::dns::dns_message::{self, Record};
+use crate::dns::dns_types::ResourceType;
+use crate::dns::dns_utils::log_safe_domain;
+
+const ALGORITHM_RSA_SHA256: u8 = 8;
+const ALGORITHM_ECDSA_P256_SHA256: u8 = 13;
+const DIGEST_SHA256: u8 = 2;
+const DNSKEY_PROTOCOL: u8 = 3;
+const DNSKEY_FLAG_ZONE_KEY: u16 = 0x0100;
+const NSEC3_HASH_SHA1: u8 = 1;
+const NSEC3_FLAG_OPT_OUT: u8 = 0x01;
+const TYPE_NS: u16 = 2;
+
+/// NSEC3 records with more hash iterations than this are ignored.
+///
+/// See [RFC 9276 section 3.2](https://datatracker.ietf.org/doc/html/rfc9276#section-3.2).
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+/// How long validated keys are reused, at most, before being fetched again.
+const MAX_KEY_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// DS records for the root zone's key-signing keys (KSK-2017 and KSK-2024).
+///
+/// See <https://data.iana.org/root-anchors/root-anchors.xml>.
+const ROOT_TRUST_ANCHORS: [(u16, u8, u8, [u8; 32]); 2] = [
+    (
+        20326,
+        ALGORITHM_RSA_SHA256,
+        DIGEST_SHA256,
+        hex!("E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D"),
+    ),
+    (
+        38696,
+        ALGORITHM_RSA_SHA256,
+        DIGEST_SHA256,
+        hex!("683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16"),
+    ),
+];
+
+/// How the records in a lookup result were authenticated.
+///
+/// Variants are ordered from least to most assurance.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DnssecStatus {
+    /// The records weren't checked.
+    #[default]
+    NotValidated,
+    /// The records are in a zone that was proven to be unsigned, or that is
+    /// signed only with unsupported algorithms.
+    ///
+    /// The proof is authenticated, so signatures can't have been stripped, but
+    /// nothing vouches for the records themselves.
+    Unsigned,
+    /// The records were signed with a chain of trust to the root zone.
+    Secure,
+}
+
+#[derive(Clone, Debug, displaydoc::Display, thiserror::Error)]
+pub enum ValidationError {
+    /// a record set has no signature
+    MissingSignature,
+    /// a record set was signed by a zone that doesn't contain it
+    SignerOutOfZone,
+    /// no trusted key for the signing zone
+    NoTrustedKey,
+    /// no signature could be verified
+    InvalidSignature,
+    /// a DNSSEC record is malformed
+    MalformedRecord,
+    /// no authenticated proof that a record doesn't exist
+    MissingDenial,
+}
+
+/// Sends the queries needed to build a chain of trust.
+pub(crate) trait DnssecQuery: Sync {
+    /// Returns the response to a query for `name`, with DNSSEC signatures
+    /// requested.
+    fn query(
+        &self,
+        name: &str,
+        resource_type: ResourceType,
+    ) -> impl Future<Output = dns::Result<Vec<u8>>> + Send;
+}
+
+/// Validates responses, caching the zone keys it validates along the way.
+#[derive(Debug)]
+pub(crate) struct DnssecValidator {
+    trust_anchors: Vec<Ds>,
+    zone_keys: Mutex<HashMap<String, CachedZoneKeys>>,
+}
+
+#[derive(Debug)]
+struct CachedZoneKeys {
+    keys: ZoneKeys,
+    expires_at: SystemTime,
+}
+
+#[derive(Clone, Debug)]
+enum ZoneKeys {
+    Trusted(Vec<DnsKey>),
+    /// The zone is only signed with algorithms that aren't supported, so its
+    /// records can't be validated.
+    Unsupported,
+}
+
+impl DnssecValidator {
+    pub(crate) fn with_root_trust_anchors() -> Self {
+        Self::new(
+            ROOT_TRUST_ANCHORS
+                .iter()
+                .map(|&(key_tag, algorithm, digest_type, digest)| Ds {
+                    key_tag,
+                    algorithm,
+                    digest_type,
+                    digest: digest.to_vec(),
+                })
+                .collect(),
+        )
+    }
+
+    fn new(trust_anchors: Vec<Ds>) -> Self {
+        Self {
+            trust_anchors,
+            zone_keys: Default::default(),
+        }
+    }
+
+    /// Checks the signatures on every record in the answer section of
+    /// `message`.
+    ///
+    /// Fails if any record set has only invalid signatures, or has none and
+    /// isn't in a zone that can be proven to be unsigned. Records synthesized
+    /// from a wildcard also need proof that the queried name doesn't exist.
+    pub(crate) async fn validate(
+        &self,
+        message: &[u8],
+        query: &impl DnssecQuery,
+        now: SystemTime,
+    ) -> dns::Result<DnssecStatus> {
+        let response = dns_message::parse_signed_response(message)?;
+        if response.answers.is_empty() {
+            return Err(dns_message::Error::NoData.into());
+        }
+        let rrsets = group_rrsets(&response.answers)?;
+        let authority = group_rrsets(&response.authority)?;
+        let now_serial = serial_time(now);
+
+        let mut status = DnssecStatus::Secure;
+        for rrset in &rrsets {
+            let Some(signature) = rrset.signatures.first() else {
+                self.prove_unsigned(rrset.name, query, now).await?;
+                status = DnssecStatus::Unsigned;
+                continue;
+            };
+            let signer = &signature.signer;
+            if !is_subdomain(rrset.name, signer) {
+                return Err(ValidationError::SignerOutOfZone.into());
+            }
+            let keys = match self.zone_keys(signer, query, now).await? {
+                ZoneKeys::Trusted(keys) => keys,
+                ZoneKeys::Unsupported => {
+                    status = DnssecStatus::Unsigned;
+                    continue;
+                }
+            };
+            let signature = verify_rrset(rrset, signer, &keys, now_serial)?;
+            if let Some(next_closer) = wildcard_next_closer(rrset.name, signature) {
+                prove_no_closer_match(
+                    rrset.name,
+                    &next_closer,
+                    signer,
+                    &keys,
+                    &authority,
+                    now_serial,
+                )?;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Succeeds if `name` is in a zone that is provably unsigned.
+    ///
+    /// Starting from the root, each ancestor of `name` is checked for a
+    /// delegation. Signed delegations are followed down, and the proof is
+    /// complete at the first delegation that the signed parent shows has no DS
+    /// records.
+    async fn prove_unsigned(
+        &self,
+        name: &str,
+        query: &impl DnssecQuery,
+        now: SystemTime,
+    ) -> dns::Result<()> {
+        let now_serial = serial_time(now);
+        let mut zone = String::new();
+        let mut keys = match self.zone_keys(&zone, query, now).await? {
+            ZoneKeys::Trusted(keys) => keys,
+            ZoneKeys::Unsupported => return Ok(()),
+        };
+
+        let labels = name_labels(name).collect_vec();
+        for start in (0..labels.len()).rev() {
+            let child = labels[start..].join(".").to_ascii_lowercase();
+            let child_keys = match self.cached_zone_keys(&child, now) {
+                Some(cached) => Some(cached),
+                None => {
+                    log::debug!("checking for a delegation at [{}]", log_safe_domain(&child));
+                    let message = query.query(&child, ResourceType::DS).await?;
+                    let response = dns_message::parse_signed_response(&message)?;
+                    let has_ds = group_rrsets(&response.answers)?
+                        .iter()
+                        .any(|rrset| rrset.is(&child, ResourceType::DS));
+                    if has_ds {
+                        Some(self.zone_keys(&child, query, now).await?)
+                    } else {
+                        let authority = group_rrsets(&response.authority)?;
+                        match ds_denial(&child, &zone, &keys, &authority, now_serial)? {
+                            Delegation::None => None,
+                            Delegation::Unsigned { ttl } => {
+                                self.cache_zone_keys(
+                                    child,
+                                    ZoneKeys::Unsupported,
+                                    Duration::from_secs(ttl.into()),
+                                    now,
+                                );
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+            };
+            match child_keys {
+                Some(ZoneKeys::Trusted(child_keys)) => {
+                    zone = child;
+                    keys = child_keys;
+                }
+                Some(ZoneKeys::Unsupported) => return Ok(()),
+                None => {}
+            }
+        }
+
+        // The records are in a signed zone, so they should have been signed.
+        Err(ValidationError::MissingSignature.into())
+    }
+
+    /// Returns the validated keys for `zone`, fetching whatever isn't cached.
+    async fn zone_keys(
+        &self,
+        zone: &str,
+        query: &impl DnssecQuery,
+        now: SystemTime,
+    ) -> dns::Result<ZoneKeys> {
+        /// A zone whose keys haven't been validated yet, along with the records
+        /// needed to do so.
+        struct PendingZone {
+            zone: String,
+            dnskeys: Vec<Record>,
+            /// The zone's DS records and the parent zone that signed them, or
+            /// `None` for the root.
+            delegation: Option<(Vec<Record>, String)>,
+        }
+
+        // Walk up towards the root until reaching a zone whose keys are already
+        // known, then validate back down.
+        let mut pending = vec![];
+        let mut zone = zone.to_owned();
+        let mut parent_keys = loop {
+            if let Some(keys) = self.cached_zone_keys(&zone, now) {
+                break Some(keys);
+            }
+            log::debug!("fetching DNSSEC keys for [{}]", log_safe_domain(&zone));
+            let dnskeys = fetch(query, &zone, ResourceType::DNSKEY).await?;
+            if zone.is_empty() {
+                pending.push(PendingZone {
+                    zone,
+                    dnskeys,
+                    delegation: None,
+                });
+                break None;
+            }
+            let ds = fetch(query, &zone, ResourceType::DS).await?;
+            let parent = group_rrsets(&ds)?
+                .iter()
+                .find(|rrset| rrset.is(&zone, ResourceType::DS))
+                .and_then(|rrset| rrset.signatures.first())
+                .ok_or(ValidationError::MissingSignature)?
+                .signer
+                .clone();
+            if parent == zone || !is_subdomain(&zone, &parent) {
+                return Err(ValidationError::SignerOutOfZone.into());
+            }
+            pending.push(PendingZone {
+                zone,
+                dnskeys,
+                delegation: Some((ds, parent.clone())),
+            });
+            zone = parent;
+        };
+
+        let now_serial = serial_time(now);
+        for PendingZone {
+            zone,
+            dnskeys,
+            delegation,
+        } in pending.into_iter().rev()
+        {
+            let ds_set = match (delegation, &parent_keys) {
+                (None, _) => self.trust_anchors.clone(),
+                (Some((ds, parent)), Some(ZoneKeys::Trusted(keys))) => {
+                    let rrsets = group_rrsets(&ds)?;
+                    let rrset = find_rrset(&rrsets, &zone, ResourceType::DS)?;
+                    verify_rrset(rrset, &parent, keys, now_serial)?;
+                    rrset
+                        .records
+                        .iter()
+                        .map(|record| Ds::parse(&record.data))
+                        .try_collect()?
+                }
+                (Some(_), Some(ZoneKeys::Unsupported)) => {
+                    // If the parent can't be validated, neither can its children.
+                    self.cache_zone_keys(zone, ZoneKeys::Unsupported, MAX_KEY_CACHE_TTL, now);
+                    continue;
+                }
+                (Some(_), None) => {
+                    // The root is always validated first, so this shouldn't
+                    // happen.
+                    return Err(ValidationError::NoTrustedKey.into());
+                }
+            };
+
+            let rrsets = group_rrsets(&dnskeys)?;
+            let rrset = find_rrset(&rrsets, &zone, ResourceType::DNSKEY)?;
+            let keys = trusted_zone_keys(&zone, rrset, &ds_set, now_serial)?;
+            let ttl = rrset.min_ttl();
+            self.cache_zone_keys(zone, keys.clone(), Duration::from_secs(ttl.into()), now);
+            parent_keys = Some(keys);
+        }
+
+        parent_keys.ok_or_else(|| ValidationError::NoTrustedKey.into())
+    }
+
+    fn cached_zone_keys(&self, zone: &str, now: SystemTime) -> Option<ZoneKeys> {
+        let mut guard = self.zone_keys.lock().expect("not poisoned");
+        match guard.get(zone) {
+            Some(cached) if cached.expires_at <= now => {
+                guard.remove(zone);
+                None
+            }
+            Some(cached) => Some(cached.keys.clone()),
+            None => None,
+        }
+    }
+
+    fn cache_zone_keys(&self, zone: String, keys: ZoneKeys, ttl: Duration, now: SystemTime) {
+        let expires_at = now + ttl.min(MAX_KEY_CACHE_TTL);
+        self.zone_keys
+            .lock()
+            .expect("not poisoned")
+            .insert(zone, CachedZoneKeys { keys, expires_at });
+    }
+}
+
+/// Fetches the records of `resource_type` for `name`, along with their
+/// signatures.
+async fn fetch(
+    query: &impl DnssecQuery,
+    name: &str,
+    resource_type: ResourceType,
+) -> dns::Result<Vec<Record>> {
+    let message = query.query(name, resource_type).await?;
+    match dns_message::parse_answer_records(&message) {
+        Ok(records) => Ok(records),
+        // Without these records there's no chain of trust.
+        Err(dns_message::Error::NoData) => Err(ValidationError::NoTrustedKey.into()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Returns the keys in `rrset` if it is signed by a key matching one of the
+/// `ds_set` records.
+fn trusted_zone_keys(
+    zone: &str,
+    rrset: &SignedRrset<'_>,
+    ds_set: &[Ds],
+    now: u32,
+) -> Result<ZoneKeys, ValidationError> {
+    let ds_set = ds_set.iter().filter(|ds| ds.is_supported()).collect_vec();
+    if ds_set.is_empty() {
+        return Ok(ZoneKeys::Unsupported);
+    }
+
+    let keys: Vec<DnsKey> = rrset
+        .records
+        .iter()
+        .map(|record| DnsKey::parse(&record.data))
+        .try_collect()?;
+    let entry_keys = keys
+        .iter()
+        .filter(|key| ds_set.iter().any(|ds| ds.matches(zone, key)))
+        .cloned()
+        .collect_vec();
+    if entry_keys.is_empty() {
+        return Err(ValidationError::NoTrustedKey);
+    }
+    verify_rrset(rrset, zone, &entry_keys, now)?;
+
+    Ok(ZoneKeys::Trusted(
+        keys.into_iter().filter(DnsKey::is_zone_key).collect(),
+    ))
+}
+
+/// Records with the same owner name and type, and the signatures over them.
+struct SignedRrset<'a> {
+    name: &'a str,
+    resource_type: u16,
+    records: Vec<&'a Record>,
+    signatures: Vec<Rrsig>,
+}
+
+impl SignedRrset<'_> {
+    fn is(&self, name: &str, resource_type: ResourceType) -> bool {
+        self.resource_type == resource_type as u16 && names_equal(self.name, name)
+    }
+
+    fn min_ttl(&self) -> u32 {
+        self.records
+            .iter()
+            .map(|record| record.ttl)
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+fn group_rrsets(records: &[Record]) -> Result<Vec<SignedRrset<'_>>, ValidationError> {
+    let mut rrsets = IndexMap::<(&str, u16), SignedRrset<'_>>::new();
+    let mut signatures = vec![];
+    for record in records {
+        if record.resource_type == ResourceType::RRSIG as u16 {
+            signatures.push((record.name.as_str(), Rrsig::parse(&record.data)?));
+            continue;
+        }
+        rrsets
+            .entry((&record.name, record.resource_type))
+            .or_insert_with(|| SignedRrset {
+                name: &record.name,
+                resource_type: record.resource_type,
+                records: vec![],
+                signatures: vec![],
+            })
+            .records
+            .push(record);
+    }
+    for (name, signature) in signatures {
+        // Signatures over records that weren't included can't be checked, and
+        // aren't needed.
+        if let Some(rrset) = rrsets.get_mut(&(name, signature.type_covered)) {
+            rrset.signatures.push(signature);
+        }
+    }
+    Ok(rrsets.into_values().collect())
+}
+
+fn find_rrset<'r, 'a>(
+    rrsets: &'r [SignedRrset<'a>],
+    name: &str,
+    resource_type: ResourceType,
+) -> Result<&'r SignedRrset<'a>, ValidationError> {
+    rrsets
+        .iter()
+        .find(|rrset| rrset.is(name, resource_type))
+        .ok_or(ValidationError::NoTrustedKey)
+}
+
+/// Returns one of the signatures on `rrset` made by `signer` that is currently
+/// valid and verifies with one of `keys`.
+fn verify_rrset<'r>(
+    rrset: &'r SignedRrset<'_>,
+    signer: &str,
+    keys: &[DnsKey],
+    now: u32,
+) -> Result<&'r Rrsig, ValidationError> {
+    if rrset.signatures.is_empty() {
+        return Err(ValidationError::MissingSignature);
+    }
+
+    rrset
+        .signatures
+        .iter()
+        .filter(|signature| names_equal(&signature.signer, signer) && signature.is_current(now))
+        .find(|signature| {
+            let Some(data) = signed_data(signature, rrset) else {
+                return false;
+            };
+            keys.iter()
+                .filter(|key| {
+                    key.is_zone_key()
+                        && key.algorithm == signature.algorithm
+                        && key.key_tag() == signature.key_tag
+                })
+                .any(|key| {
+                    verify_signature(key.algorithm, &key.public_key, &data, &signature.signature)
+                })
+        })
+        .ok_or(ValidationError::InvalidSignature)
+}
+
+/// Whether a delegation to a child zone exists, as shown by its parent.
+#[derive(Debug, PartialEq)]
+enum Delegation {
+    /// The name isn't a zone cut, so it's part of the parent zone.
+    None,
+    /// The name is a zone cut without DS records, so the child zone isn't
+    /// signed.
+    Unsigned { ttl: u32 },
+}
+
+/// Uses the NSEC or NSEC3 records in `authority` that `zone` signed to show
+/// whether `child` is an unsigned delegation.
+///
+/// `authority` should be from a DS query for `child` that had no answers.
+fn ds_denial(
+    child: &str,
+    zone: &str,
+    keys: &[DnsKey],
+    authority: &[SignedRrset<'_>],
+    now: u32,
+) -> Result<Delegation, ValidationError> {
+    for rrset in authenticated_denials(authority, zone, keys, now) {
+        let ttl = rrset.min_ttl();
+        for record in &rrset.records {
+            if rrset.resource_type == ResourceType::NSEC as u16 {
+                let nsec = Nsec::parse(&record.data)?;
+                if names_equal(rrset.name, child) {
+                    return delegation_from_types(&nsec.types, ttl);
+                }
+                if nsec.covers(rrset.name, child) {
+                    // The name doesn't exist, so it can't be a delegation.
+                    return Ok(Delegation::None);
+                }
+            } else {
+                let nsec3 = Nsec3::parse(&record.data)?;
+                let (Some(owner_hash), Some(hash)) =
+                    (nsec3_owner_hash(rrset.name, zone), nsec3.hash(child))
+                else {
+                    continue;
+                };
+                if owner_hash == hash {
+                    return delegation_from_types(&nsec3.types, ttl);
+                }
+                if nsec3.covers(&owner_hash, &hash) {
+                    // Opt-out spans may contain unsigned delegations that
+                    // don't have records of their own.
+                    return Ok(if nsec3.flags & NSEC3_FLAG_OPT_OUT != 0 {
+                        Delegation::Unsigned { ttl }
+                    } else {
+                        Delegation::None
+                    });
+                }
+            }
+        }
+    }
+    Err(ValidationError::MissingDenial)
+}
+
+fn delegation_from_types(types: &[u8], ttl: u32) -> Result<Delegation, ValidationError> {
+    if type_bitmap_contains(types, ResourceType::DS as u16) {
+        // There should have been DS records in the answer.
+        Err(ValidationError::MissingDenial)
+    } else if type_bitmap_contains(types, TYPE_NS) {
+        Ok(Delegation::Unsigned { ttl })
+    } else {
+        Ok(Delegation::None)
+    }
+}
+
+/// Succeeds if `authority` proves that no records closer to `name` than the
+/// wildcard they were synthesized from exist.
+///
+/// See [RFC 4035 section 5.3.4](https://datatracker.ietf.org/doc/html/rfc4035#section-5.3.4)
+/// and [RFC 5155 section 8.8](https://datatracker.ietf.org/doc/html/rfc5155#section-8.8).
+fn prove_no_closer_match(
+    name: &str,
+    next_closer: &str,
+    zone: &str,
+    keys: &[DnsKey],
+    authority: &[SignedRrset<'_>],
+    now: u32,
+) -> Result<(), ValidationError> {
+    for rrset in authenticated_denials(authority, zone, keys, now) {
+        for record in &rrset.records {
+            let covered = if rrset.resource_type == ResourceType::NSEC as u16 {
+                Nsec::parse(&record.data)?.covers(rrset.name, name)
+            } else {
+                let nsec3 = Nsec3::parse(&record.data)?;
+                match (nsec3_owner_hash(rrset.name, zone), nsec3.hash(next_closer)) {
+                    (Some(owner_hash), Some(hash)) => nsec3.covers(&owner_hash, &hash),
+                    _ => false,
+                }
+            };
+            if covered {
+                return Ok(());
+            }
+        }
+    }
+    Err(ValidationError::MissingDenial)
+}
+
+/// Returns the NSEC and NSEC3 record sets in `authority` with a valid signature
+/// from `zone`.
+fn authenticated_denials<'r, 'a>(
+    authority: &'r [SignedRrset<'a>],
+    zone: &'r str,
+    keys: &'r [DnsKey],
+    now: u32,
+) -> impl Iterator<Item = &'r SignedRrset<'a>> {
+    authority.iter().filter(move |rrset| {
+        (rrset.resource_type == ResourceType::NSEC as u16
+            || rrset.resource_type == ResourceType::NSEC3 as u16)
+            && is_subdomain(rrset.name, zone)
+            && verify_rrset(rrset, zone, keys, now).is_ok()
+    })
+}
+
+/// If the records were synthesized from a wildcard, returns the name one label
+/// closer to `name` than the wildcard's parent, which must not exist.
+fn wildcard_next_closer(name: &str, signature: &Rrsig) -> Option<String> {
+    let labels = name_labels(name).collect_vec();
+    let signed_label_count = usize::from(signature.labels);
+    let label_count = labels.len() - usize::from(labels.first() == Some(&"*"));
+    (label_count > signed_label_count)
+        .then(|| labels[labels.len() - signed_label_count - 1..].join("."))
+}
+
+/// Builds the data covered by `signature`.
+///
+/// See [RFC 4034 section 3.1.8.1](https://datatracker.ietf.org/doc/html/rfc4034#section-3.1.8.1).
+fn signed_data(signature: &Rrsig, rrset: &SignedRrset<'_>) -> Option<Vec<u8>> {
+    let labels = name_labels(rrset.name).collect_vec();
+    let signed_label_count = usize::from(signature.labels);
+    // A leading wildcard label isn't counted.
+    let label_count = labels.len() - usize::from(labels.first() == Some(&"*"));
+    let owner = match label_count.cmp(&signed_label_count) {
+        std::cmp::Ordering::Less => return None,
+        std::cmp::Ordering::Equal => canonical_name(rrset.name),
+        std::cmp::Ordering::Greater => {
+            // The records were synthesized from a wildcard.
+            let suffix = &labels[labels.len() - signed_label_count..];
+            canonical_name_from_labels(std::iter::once("*").chain(suffix.iter().copied()))
+        }
+    };
+    let class = rrset.records.first()?.class;
+
+    let mut record_data = rrset
+        .records
+        .iter()
+        .map(|record| &record.data[..])
+        .collect_vec();
+    record_data.sort_unstable();
+    record_data.dedup();
+
+    let mut data = signature.signed_fields.clone();
+    for record_data in record_data {
+        data.extend_from_slice(&owner);
+        data.extend_from_slice(&rrset.resource_type.to_be_bytes());
+        data.extend_from_slice(&class.to_be_bytes());
+        data.extend_from_slice(&signature.original_ttl.to_be_bytes());
+        data.extend_from_slice(&u16::try_from(record_data.len()).ok()?.to_be_bytes());
+        data.extend_from_slice(record_data);
+    }
+    Some(data)
+}
+
+fn verify_signature(algorithm: u8, public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+    let result = match algorithm {
+        ALGORITHM_RSA_SHA256 => verify_rsa_sha256(public_key, data, signature),
+        ALGORITHM_ECDSA_P256_SHA256 => verify_ecdsa_p256_sha256(public_key, data, signature),
+        _ => return false,
+    };
+    result.unwrap_or(false)
+}
+
+/// See [RFC 5702](https://datatracker.ietf.org/doc/html/rfc5702) and, for the
+/// key format, [RFC 3110](https://datatracker.ietf.org/doc/html/rfc3110#section-2).
+fn verify_rsa_sha256(public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<bool, ErrorStack> {
+    let (exponent_len, rest) = match public_key.split_first() {
+        Some((0, rest)) => match rest.split_first_chunk::<2>() {
+            Some((len, rest)) => (usize::from(u16::from_be_bytes(*len)), rest),
+            None => return Ok(false),
+        },
+        Some((len, rest)) => (usize::from(*len), rest),
+        None => return Ok(false),
+    };
+    if rest.len() <= exponent_len {
+        return Ok(false);
+    }
+    let (exponent, modulus) = rest.split_at(exponent_len);
+
+    let key = PKey::from_rsa(Rsa::from_public_components(
+        BigNum::from_slice(modulus)?,
+        BigNum::from_slice(exponent)?,
+    )?)?;
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &key)?;
+    verifier.update(data)?;
+    verifier.verify(signature)
+}
+
+/// See [RFC 6605](https://datatracker.ietf.org/doc/html/rfc6605#section-4).
+fn verify_ecdsa_p256_sha256(
+    public_key: &[u8],
+    data: &[u8],
+    signature: &[u8],
+) -> Result<bool, ErrorStack> {
+    const COORDINATE_LEN: usize = 32;
+    if public_key.len() != 2 * COORDINATE_LEN || signature.len() != 2 * COORDINATE_LEN {
+        return Ok(false);
+    }
+    let (x, y) = public_key.split_at(COORDINATE_LEN);
+    let (r, s) = signature.split_at(COORDINATE_LEN);
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = EcKey::from_public_key_affine_coordinates(
+        &group,
+        &BigNum::from_slice(x)?,
+        &BigNum::from_slice(y)?,
+    )?;
+    let signature =
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
+    signature.verify(&sha256(data), &key)
+}
+
+/// A signature over a record set.
+///
+/// See [RFC 4034 section 3.1](https://datatracker.ietf.org/doc/html/rfc4034#section-3.1).
+#[derive(Clone, Debug)]
+struct Rrsig {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer: String,
+    /// The record data preceding the signature, in canonical form.
+    signed_fields: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Rrsig {
+    fn parse(data: &[u8]) -> Result<Self, ValidationError> {
+        let (fields, rest) = data
+            .split_first_chunk::<18>()
+            .ok_or(ValidationError::MalformedRecord)?;
+        let (signer, signature) = read_uncompressed_name(rest)?;
+
+        let u16_at = |i: usize| u16::from_be_bytes(fields[i..][..2].try_into().expect("2 bytes"));
+        let u32_at = |i: usize| u32::from_be_bytes(fields[i..][..4].try_into().expect("4 bytes"));
+
+        let mut signed_fields = fields.to_vec();
+        signed_fields.extend(canonical_name(&signer));
+        Ok(Self {
+            type_covered: u16_at(0),
+            algorithm: fields[2],
+            labels: fields[3],
+            original_ttl: u32_at(4),
+            expiration: u32_at(8),
+            inception: u32_at(12),
+            key_tag: u16_at(16),
+            signer,
+            signed_fields,
+            signature: signature.to_vec(),
+        })
+    }
+
+    fn is_current(&self, now: u32) -> bool {
+        serial_le(self.inception, now) && serial_le(now, self.expiration)
+    }
+}
+
+/// A zone's public key.
+///
+/// See [RFC 4034 section 2.1](https://datatracker.ietf.org/doc/html/rfc4034#section-2.1).
+#[derive(Clone, Debug)]
+struct DnsKey {
+    flags: u16,
+    protocol: u8,
+    algorithm: u8,
+    public_key: Vec<u8>,
+    /// The full record data, used when computing key tags and digests.
+    data: Vec<u8>,
+}
+
+impl DnsKey {
+    fn parse(data: &[u8]) -> Result<Self, ValidationError> {
+        let ([flags_0, flags_1, protocol, algorithm], public_key) =
+            data.split_first_chunk::<4>()
+                .ok_or(ValidationError::MalformedRecord)?;
+        Ok(Self {
+            flags: u16::from_be_bytes([*flags_0, *flags_1]),
+            protocol: *protocol,
+            algorithm: *algorithm,
+            public_key: public_key.to_vec(),
+            data: data.to_vec(),
+        })
+    }
+
+    fn is_zone_key(&self) -> bool {
+        self.flags & DNSKEY_FLAG_ZONE_KEY != 0 && self.protocol == DNSKEY_PROTOCOL
+    }
+
+    /// See [RFC 4034 appendix B](https://datatracker.ietf.org/doc/html/rfc4034#appendix-B).
+    fn key_tag(&self) -> u16 {
+        let mut sum = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| {
+                if i % 2 == 0 {
+                    u32::from(*byte) << 8
+                } else {
+                    u32::from(*byte)
+                }
+            })
+            .sum::<u32>();
+        sum += (sum >> 16) & 0xFFFF;
+        u16::try_from(sum & 0xFFFF).expect("masked")
+    }
+}
+
+/// A delegation signer record, identifying a child zone's key by its digest.
+///
+/// See [RFC 4034 section 5.1](https://datatracker.ietf.org/doc/html/rfc4034#section-5.1).
+#[derive(Clone, Debug)]
+struct Ds {
+    key_tag: u16,
+    algorithm: u8,
+    digest_type: u8,
+    digest: Vec<u8>,
+}
+
+impl Ds {
+    fn parse(data: &[u8]) -> Result<Self, ValidationError> {
+        let ([tag_0, tag_1, algorithm, digest_type], digest) = data
+            .split_first_chunk::<4>()
+            .ok_or(ValidationError::MalformedRecord)?;
+        Ok(Self {
+            key_tag: u16::from_be_bytes([*tag_0, *tag_1]),
+            algorithm: *algorithm,
+            digest_type: *digest_type,
+            digest: digest.to_vec(),
+        })
+    }
+
+    fn is_supported(&self) -> bool {
+        self.digest_type == DIGEST_SHA256
+            && matches!(
+                self.algorithm,
+                ALGORITHM_RSA_SHA256 | ALGORITHM_ECDSA_P256_SHA256
+            )
+    }
+
+    fn matches(&self, zone: &str, key: &DnsKey) -> bool {
+        if self.digest_type != DIGEST_SHA256
+            || self.algorithm != key.algorithm
+            || self.key_tag != key.key_tag()
+        {
+            return false;
+        }
+        let mut owner_and_key = canonical_name(zone);
+        owner_and_key.extend_from_slice(&key.data);
+        sha256(&owner_and_key)[..] == self.digest[..]
+    }
+}
+
+/// Proof that no names exist between the owner and the next name in a zone.
+///
+/// See [RFC 4034 section 4.1](https://datatracker.ietf.org/doc/html/rfc4034#section-4.1).
+#[derive(Clone, Debug)]
+struct Nsec {
+    next: String,
+    types: Vec<u8>,
+}
+
+impl Nsec {
+    fn parse(data: &[u8]) -> Result<Self, ValidationError> {
+        let (next, types) = read_uncompressed_name(data)?;
+        Ok(Self {
+            next,
+            types: types.to_vec(),
+        })
+    }
+
+    /// Whether `name` is strictly between `owner` and the next name.
+    fn covers(&self, owner: &str, name: &str) -> bool {
+        let after_owner = canonical_cmp(owner, name) == Ordering::Less;
+        // The last record in a zone wraps around to the zone's apex.
+        let is_last = canonical_cmp(&self.next, owner) != Ordering::Greater;
+        after_owner && (is_last || canonical_cmp(name, &self.next) == Ordering::Less)
+    }
+}
+
+/// Like [`Nsec`], but for hashed names.
+///
+/// See [RFC 5155 section 3.2](https://datatracker.ietf.org/doc/html/rfc5155#section-3.2).
+#[derive(Clone, Debug)]
+struct Nsec3 {
+    hash_algorithm: u8,
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+    next_hash: Vec<u8>,
+    types: Vec<u8>,
+}
+
+impl Nsec3 {
+    fn parse(data: &[u8]) -> Result<Self, ValidationError> {
+        let ([hash_algorithm, flags, iterations_0, iterations_1, salt_len], rest) = data
+            .split_first_chunk::<5>()
+            .ok_or(ValidationError::MalformedRecord)?;
+        let salt_len = usize::from(*salt_len);
+        if rest.len() < salt_len {
+            return Err(ValidationError::MalformedRecord);
+        }
+        let (salt, rest) = rest.split_at(salt_len);
+        let (hash_len, rest) = rest.split_first().ok_or(ValidationError::MalformedRecord)?;
+        let hash_len = usize::from(*hash_len);
+        if rest.len() < hash_len {
+            return Err(ValidationError::MalformedRecord);
+        }
+        let (next_hash, types) = rest.split_at(hash_len);
+        Ok(Self {
+            hash_algorithm: *hash_algorithm,
+            flags: *flags,
+            iterations: u16::from_be_bytes([*iterations_0, *iterations_1]),
+            salt: salt.to_vec(),
+            next_hash: next_hash.to_vec(),
+            types: types.to_vec(),
+        })
+    }
+
+    /// Hashes `name` with this record's parameters, if they're supported.
+    ///
+    /// See [RFC 5155 section 5](https://datatracker.ietf.org/doc/html/rfc5155#section-5).
+    fn hash(&self, name: &str) -> Option<Vec<u8>> {
+        if self.hash_algorithm != NSEC3_HASH_SHA1 || self.iterations > MAX_NSEC3_ITERATIONS {
+            return None;
+        }
+        let mut input = canonical_name(name);
+        input.extend_from_slice(&self.salt);
+        let mut digest = sha1(&input);
+        for _ in 0..self.iterations {
+            let mut input = digest.to_vec();
+            input.extend_from_slice(&self.salt);
+            digest = sha1(&input);
+        }
+        Some(digest.to_vec())
+    }
+
+    /// Whether `hash` is strictly between `owner_hash` and the next hash.
+    fn covers(&self, owner_hash: &[u8], hash: &[u8]) -> bool {
+        let next_hash = &self.next_hash[..];
+        if owner_hash < next_hash {
+            owner_hash < hash && hash < next_hash
+        } else {
+            // The last record in a zone wraps around to the first.
+            owner_hash < hash || hash < next_hash
+        }
+    }
+}
+
+/// Returns the hash in an NSEC3 record's owner name, if the record belongs to
+/// `zone`.
+fn nsec3_owner_hash(owner: &str, zone: &str) -> Option<Vec<u8>> {
+    let (label, parent) = owner.split_once('.').unwrap_or((owner, ""));
+    if !names_equal(parent, zone) {
+        return None;
+    }
+    base32hex_decode(label)
+}
+
+/// Decodes unpadded base32 with the extended hex alphabet, as used in NSEC3
+/// owner names.
+///
+/// See [RFC 4648 section 7](https://datatracker.ietf.org/doc/html/rfc4648#section-7).
+fn base32hex_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = vec![];
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'A'..=b'V' => c - b'A' + 10,
+            _ => return None,
+        };
+        buffer = ((buffer << 5) | u16::from(value)) & 0xFFF;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push(u8::try_from((buffer >> bits) & 0xFF).expect("masked"));
+        }
+    }
+    Some(decoded)
+}
+
+/// Checks whether `resource_type` is listed in the type bitmap of an NSEC or
+/// NSEC3 record.
+///
+/// See [RFC 4034 section 4.1.2](https://datatracker.ietf.org/doc/html/rfc4034#section-4.1.2).
+fn type_bitmap_contains(mut types: &[u8], resource_type: u16) -> bool {
+    let [window, bit] = resource_type.to_be_bytes();
+    while let [block, len, rest @ ..] = types {
+        let len = usize::from(*len);
+        if rest.len() < len {
+            return false;
+        }
+        let (bitmap, rest) = rest.split_at(len);
+        if *block == window {
+            return bitmap
+                .get(usize::from(bit / 8))
+                .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0);
+        }
+        types = rest;
+    }
+    false
+}
+
+/// Reads a name that isn't compressed, as in RRSIG records, returning it in
+/// lowercase along with the remaining bytes.
+fn read_uncompressed_name(mut data: &[u8]) -> Result<(String, &[u8]), ValidationError> {
+    let mut labels = vec![];
+    loop {
+        let (len, rest) = data.split_first().ok_or(ValidationError::MalformedRecord)?;
+        let len = usize::from(*len);
+        if len == 0 {
+            return Ok((labels.join("."), rest));
+        }
+        if len > dns_message::MAX_DNS_LABEL_LEN || rest.len() < len {
+            return Err(ValidationError::MalformedRecord);
+        }
+        let (label, rest) = rest.split_at(len);
+        let label = std::str::from_utf8(label).map_err(|_| ValidationError::MalformedRecord)?;
+        labels.push(label.to_ascii_lowercase());
+        data = rest;
+    }
+}
+
+fn name_labels(name: &str) -> impl Iterator<Item = &str> {
+    name.split('.').filter(|label| !label.is_empty())
+}
+
+/// Encodes `name` in the canonical wire format, which is uncompressed and
+/// lowercase.
+fn canonical_name(name: &str) -> Vec<u8> {
+    canonical_name_from_labels(name_labels(name))
+}
+
+fn canonical_name_from_labels<'a>(labels: impl IntoIterator<Item = &'a str>) -> Vec<u8> {
+    let mut encoded = vec![];
+    for label in labels {
+        encoded.push(u8::try_from(label.len()).expect("labels are read from the wire format"));
+        encoded.extend(label.bytes().map(|b| b.to_ascii_lowercase()));
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// Compares names as DNS does, ignoring ASCII case.
+fn names_equal(a: &str, b: &str) -> bool {
+    name_labels(a)
+        .map(str::to_ascii_lowercase)
+        .eq(name_labels(b).map(str::to_ascii_lowercase))
+}
+
+fn is_subdomain(name: &str, zone: &str) -> bool {
+    let mut labels = name_labels(name).rev();
+    name_labels(zone).rev().all(|zone_label| {
+        labels
+            .next()
+            .is_some_and(|label| label.eq_ignore_ascii_case(zone_label))
+    })
+}
+
+/// Orders names as in a zone's chain of NSEC records.
+///
+/// See [RFC 4034 section 6.1](https://datatracker.ietf.org/doc/html/rfc4034#section-6.1).
+fn canonical_cmp(a: &str, b: &str) -> Ordering {
+    fn canonical_labels(name: &str) -> impl Iterator<Item = String> + '_ {
+        name_labels(name).rev().map(str::to_ascii_lowercase)
+    }
+    canonical_labels(a).cmp(canonical_labels(b))
+}
+
+/// Signature times are seconds since the epoch, modulo 2<sup>32</sup>.
+fn serial_time(now: SystemTime) -> u32 {
+    let seconds = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    u32::try_from(seconds & u64::from(u32::MAX)).expect("masked")
+}
+
+/// Compares serial numbers, which wrap around.
+///
+/// See [RFC 1982](https://datatracker.ietf.org/doc/html/rfc1982#section-3.2).
+fn serial_le(a: u32, b: u32) -> bool {
+    b.wrapping_sub(a) < 1 << 31
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use assert_matches::assert_matches;
+    use boring_signal::bn::{BigNumContext, BigNumRef};
+    use boring_signal::ec::PointConversionForm;
+    use boring_signal::pkey::Private;
+
+    use super::*;
+    use crate::dns::dns_message::QCLASS_IN;
+
+    const TTL: u32 = 300;
+    const SIGNATURE_VALIDITY: u32 = 60 * 60;
+
+    fn record(name: &str, resource_type: ResourceType, data: Vec<u8>) -> Record {
+        Record {
+            name: name.to_owned(),
+            resource_type: resource_type as u16,
+            class: QCLASS_IN,
+            ttl: TTL,
+            data,
+        }
+    }
+
+    fn message(name: &str, resource_type: ResourceType, records: &[Record]) -> Vec<u8> {
+        message_with_authority(name, resource_type, records, &[])
+    }
+
+    fn message_with_authority(
+        name: &str,
+        resource_type: ResourceType,
+        answers: &[Record],
+        authority: &[Record],
+    ) -> Vec<u8> {
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 1];
+        message.extend(u16::try_from(answers.len()).unwrap().to_be_bytes());
+        message.extend(u16::try_from(authority.len()).unwrap().to_be_bytes());
+        message.extend([0, 0]);
+        message.extend(canonical_name(name));
+        message.extend((resource_type as u16).to_be_bytes());
+        message.extend(QCLASS_IN.to_be_bytes());
+        for record in answers.iter().chain(authority) {
+            message.extend(canonical_name(&record.name));
+            message.extend(record.resource_type.to_be_bytes());
+            message.extend(record.class.to_be_bytes());
+            message.extend(record.ttl.to_be_bytes());
+            message.extend(u16::try_from(record.data.len()).unwrap().to_be_bytes());
+            message.extend(&record.data);
+        }
+        message
+    }
+
+    struct TestZone {
+        name: &'static str,
+        key: EcKey<Private>,
+        dnskey: DnsKey,
+    }
+
+    impl TestZone {
+        fn new(name: &'static str) -> Self {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+            let key = EcKey::generate(&group).unwrap();
+            let point = key
+                .public_key()
+                .to_bytes(
+                    &group,
+                    PointConversionForm::UNCOMPRESSED,
+                    &mut BigNumContext::new().unwrap(),
+                )
+                .unwrap();
+            // Zone key and secure entry point flags.
+            let mut data = vec![0x01, 0x01, DNSKEY_PROTOCOL, ALGORITHM_ECDSA_P256_SHA256];
+            // Skip the uncompressed point prefix.
+            data.extend(&point[1..]);
+            Self {
+                name,
+                key,
+                dnskey: DnsKey::parse(&data).unwrap(),
+            }
+        }
+
+        fn ds(&self) -> Ds {
+            let mut owner_and_key = canonical_name(self.name);
+            owner_and_key.extend(&self.dnskey.data);
+            Ds {
+                key_tag: self.dnskey.key_tag(),
+                algorithm: ALGORITHM_ECDSA_P256_SHA256,
+                digest_type: DIGEST_SHA256,
+                digest: sha256(&owner_and_key).to_vec(),
+            }
+        }
+
+        fn dnskey_records(&self, now: SystemTime) -> Vec<Record> {
+            let dnskey = record(self.name, ResourceType::DNSKEY, self.dnskey.data.clone());
+            let signature = self.sign(std::slice::from_ref(&dnskey), now);
+            vec![dnskey, signature]
+        }
+
+        /// Returns the DS records for `child`, signed by this zone.
+        fn ds_records(&self, child: &TestZone, now: SystemTime) -> Vec<Record> {
+            let ds = child.ds();
+            let mut data = ds.key_tag.to_be_bytes().to_vec();
+            data.extend([ds.algorithm, ds.digest_type]);
+            data.extend(ds.digest);
+            let ds = record(child.name, ResourceType::DS, data);
+            let signature = self.sign(std::slice::from_ref(&ds), now);
+            vec![ds, signature]
+        }
+
+        /// Returns an NSEC record showing that nothing exists between `owner`
+        /// and `next`, signed by this zone.
+        fn nsec_records(
+            &self,
+            owner: &str,
+            next: &str,
+            types: &[u16],
+            now: SystemTime,
+        ) -> Vec<Record> {
+            let mut data = canonical_name(next);
+            data.extend(type_bitmap(types));
+            let nsec = record(owner, ResourceType::NSEC, data);
+            let signature = self.sign(std::slice::from_ref(&nsec), now);
+            vec![nsec, signature]
+        }
+
+        /// Like [`Self::nsec_records`], but for hashed names, with no salt and
+        /// no extra iterations.
+        fn nsec3_records(
+            &self,
+            owner_hash: &[u8],
+            next_hash: &[u8],
+            flags: u8,
+            types: &[u16],
+            now: SystemTime,
+        ) -> Vec<Record> {
+            let mut owner = base32hex_encode(owner_hash);
+            if !self.name.is_empty() {
+                owner = format!("{owner}.{}", self.name);
+            }
+            let mut data = vec![NSEC3_HASH_SHA1, flags, 0, 0, 0];
+            data.push(u8::try_from(next_hash.len()).unwrap());
+            data.extend(next_hash);
+            data.extend(type_bitmap(types));
+            let nsec3 = record(&owner, ResourceType::NSEC3, data);
+            let signature = self.sign(std::slice::from_ref(&nsec3), now);
+            vec![nsec3, signature]
+        }
+
+        fn sign(&self, records: &[Record], now: SystemTime) -> Record {
+            let first = &records[0];
+            let now = serial_time(now);
+            // A wildcard label isn't counted.
+            let labels = name_labels(&first.name)
+                .skip_while(|label| *label == "*")
+                .count();
+
+            let mut data = first.resource_type.to_be_bytes().to_vec();
+            data.push(ALGORITHM_ECDSA_P256_SHA256);
+            data.push(u8::try_from(labels).unwrap());
+            data.extend(TTL.to_be_bytes());
+            data.extend((now + SIGNATURE_VALIDITY).to_be_bytes());
+            data.extend((now - SIGNATURE_VALIDITY).to_be_bytes());
+            data.extend(self.dnskey.key_tag().to_be_bytes());
+            data.extend(canonical_name(self.name));
+
+            let unsigned = Rrsig::parse(&data).unwrap();
+            let rrset = SignedRrset {
+                name: &first.name,
+                resource_type: first.resource_type,
+                records: records.iter().collect(),
+                signatures: vec![],
+            };
+            let signed_data = signed_data(&unsigned, &rrset).unwrap();
+            let signature = EcdsaSig::sign(&sha256(&signed_data), &self.key).unwrap();
+            data.extend(fixed_width(signature.r()));
+            data.extend(fixed_width(signature.s()));
+            record(&first.name, ResourceType::RRSIG, data)
+        }
+    }
+
+    fn type_bitmap(types: &[u16]) -> Vec<u8> {
+        let mut bitmap = vec![0u8; 32];
+        for resource_type in types {
+            let bit = usize::from(u8::try_from(*resource_type).expect("in the first window"));
+            bitmap[bit / 8] |= 0x80 >> (bit % 8);
+        }
+        while bitmap.last() == Some(&0) {
+            bitmap.pop();
+        }
+        let mut data = vec![0, u8::try_from(bitmap.len()).unwrap()];
+        data.extend(bitmap);
+        data
+    }
+
+    fn base32hex_encode(data: &[u8]) -> String {
+        const ALPHABET: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+        let mut encoded = String::new();
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        for byte in data {
+            buffer = ((buffer << 8) | u16::from(*byte)) & 0xFFF;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(char::from(ALPHABET[usize::from((buffer >> bits) & 0x1F)]));
+            }
+        }
+        if bits > 0 {
+            encoded.push(char::from(
+                ALPHABET[usize::from((buffer << (5 - bits)) & 0x1F)],
+            ));
+        }
+        encoded
+    }
+
+    fn fixed_width(number: &BigNumRef) -> Vec<u8> {
+        let bytes = number.to_vec();
+        let mut padded = vec![0; 32 - bytes.len()];
+        padded.extend(bytes);
+        padded
+    }
+
+    #[derive(Default)]
+    struct TestQuery {
+        responses: HashMap<(&'static str, ResourceType), Vec<u8>>,
+        query_count: AtomicUsize,
+    }
+
+    impl TestQuery {
+        fn with_chain(root: &TestZone, zone: &TestZone, now: SystemTime) -> Self {
+            let responses = [
+                (root.name, ResourceType::DNSKEY, root.dnskey_records(now)),
+                (zone.name, ResourceType::DNSKEY, zone.dnskey_records(now)),
+                (zone.name, ResourceType::DS, root.ds_records(zone, now)),
+            ];
+            Self {
+                responses: responses
+                    .into_iter()
+                    .map(|(name, resource_type, records)| {
+                        (
+                            (name, resource_type),
+                            message(name, resource_type, &records),
+                        )
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+
+        fn add_response(
+            &mut self,
+            name: &'static str,
+            resource_type: ResourceType,
+            answers: &[Record],
+            authority: &[Record],
+        ) {
+            self.responses.insert(
+                (name, resource_type),
+                message_with_authority(name, resource_type, answers, authority),
+            );
+        }
+
+        fn query_count(&self) -> usize {
+            self.query_count.load(Ordering::Relaxed)
+        }
+    }
+
+    impl DnssecQuery for TestQuery {
+        async fn query(&self, name: &str, resource_type: ResourceType) -> dns::Result<Vec<u8>> {
+            self.query_count.fetch_add(1, Ordering::Relaxed);
+            Ok(self
+                .responses
+                .iter()
+                .find(|((n, t), _)| *n == name && *t == resource_type)
+                .map(|(_, message)| message.clone())
+                .unwrap_or_else(|| message(name, resource_type, &[])))
+        }
+    }
+
+    struct TestChain {
+        zone: TestZone,
+        query: TestQuery,
+        validator: DnssecValidator,
+        now: SystemTime,
+    }
+
+    impl TestChain {
+        fn new() -> Self {
+            let now = SystemTime::now();
+            let root = TestZone::new("");
+            let zone = TestZone::new("example");
+            Self {
+                query: TestQuery::with_chain(&root, &zone, now),
+                validator: DnssecValidator::new(vec![root.ds()]),
+                zone,
+                now,
+            }
+        }
+
+        fn signed_answer(&self, name: &str) -> Vec<Record> {
+            let answer = record(name, ResourceType::A, vec![192, 0, 2, 1]);
+            let signature = self.zone.sign(std::slice::from_ref(&answer), self.now);
+            vec![answer, signature]
+        }
+
+        /// Returns records synthesized for `name` from a wildcard in the zone.
+        fn wildcard_answer(&self, name: &str) -> Vec<Record> {
+            let wildcard = record("*.example", ResourceType::A, vec![192, 0, 2, 1]);
+            let signature = self.zone.sign(std::slice::from_ref(&wildcard), self.now);
+            [wildcard, signature]
+                .into_iter()
+                .map(|record| Record {
+                    name: name.to_owned(),
+                    ..record
+                })
+                .collect()
+        }
+
+        async fn validate(&self, answer: &[Record], now: SystemTime) -> dns::Result<DnssecStatus> {
+            self.validate_with_authority(answer, &[], now).await
+        }
+
+        async fn validate_with_authority(
+            &self,
+            answer: &[Record],
+            authority: &[Record],
+            now: SystemTime,
+        ) -> dns::Result<DnssecStatus> {
+            let message =
+                message_with_authority(&answer[0].name, ResourceType::A, answer, authority);
+            self.validator.validate(&message, &self.query, now).await
+        }
+    }
+
+    #[tokio::test]
+    async fn signed_answer_is_secure() {
+        let chain = TestChain::new();
+        let answer = chain.signed_answer("www.example");
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Ok(DnssecStatus::Secure)
+        );
+        assert_eq!(chain.query.query_count(), 3);
+
+        // The validated keys are reused.
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Ok(DnssecStatus::Secure)
+        );
+        assert_eq!(chain.query.query_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn stripped_signatures_are_rejected() {
+        let mut chain = TestChain::new();
+        // The zone shows that the name isn't a delegation to another zone.
+        let nsec = chain.zone.nsec_records(
+            "www.example",
+            "zzz.example",
+            &[
+                ResourceType::A as u16,
+                ResourceType::RRSIG as u16,
+                ResourceType::NSEC as u16,
+            ],
+            chain.now,
+        );
+        chain
+            .query
+            .add_response("www.example", ResourceType::DS, &[], &nsec);
+        let answer = [record("www.example", ResourceType::A, vec![192, 0, 2, 1])];
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::MissingSignature))
+        );
+    }
+
+    #[tokio::test]
+    async fn unsigned_answer_without_denial_is_rejected() {
+        let chain = TestChain::new();
+        let answer = [record("www.example", ResourceType::A, vec![192, 0, 2, 1])];
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::MissingDenial))
+        );
+    }
+
+    /// Returns a validator and queries for a signed root with an unsigned
+    /// delegation to "example", proven by `authority`.
+    fn unsigned_delegation(
+        root: &TestZone,
+        authority: impl FnOnce(&TestZone) -> Vec<Record>,
+        now: SystemTime,
+    ) -> (DnssecValidator, TestQuery) {
+        let mut query = TestQuery::default();
+        query.add_response("", ResourceType::DNSKEY, &root.dnskey_records(now), &[]);
+        query.add_response("example", ResourceType::DS, &[], &authority(root));
+        (DnssecValidator::new(vec![root.ds()]), query)
+    }
+
+    #[tokio::test]
+    async fn unsigned_answer_in_unsigned_zone_is_accepted() {
+        let now = SystemTime::now();
+        let root = TestZone::new("");
+        let (validator, query) = unsigned_delegation(
+            &root,
+            |root| {
+                root.nsec_records(
+                    "example",
+                    "org",
+                    &[
+                        TYPE_NS,
+                        ResourceType::RRSIG as u16,
+                        ResourceType::NSEC as u16,
+                    ],
+                    now,
+                )
+            },
+            now,
+        );
+        let answer = [record("www.Example", ResourceType::A, vec![192, 0, 2, 1])];
+        let message = message("www.example", ResourceType::A, &answer);
+
+        assert_matches!(
+            validator.validate(&message, &query, now).await,
+            Ok(DnssecStatus::Unsigned)
+        );
+        assert_eq!(query.query_count(), 2);
+
+        // The proof is reused.
+        assert_matches!(
+            validator.validate(&message, &query, now).await,
+            Ok(DnssecStatus::Unsigned)
+        );
+        assert_eq!(query.query_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn nsec3_opt_out_proves_unsigned_zone() {
+        let now = SystemTime::now();
+        let root = TestZone::new("");
+        let covering = |flags| {
+            move |root: &TestZone| root.nsec3_records(&[0; 20], &[0xFF; 20], flags, &[TYPE_NS], now)
+        };
+        let answer = [record("www.example", ResourceType::A, vec![192, 0, 2, 1])];
+        let message = message("www.example", ResourceType::A, &answer);
+
+        let (validator, query) = unsigned_delegation(&root, covering(NSEC3_FLAG_OPT_OUT), now);
+        assert_matches!(
+            validator.validate(&message, &query, now).await,
+            Ok(DnssecStatus::Unsigned)
+        );
+
+        // Without opt-out, the record only shows that the name doesn't exist.
+        let (validator, query) = unsigned_delegation(&root, covering(0), now);
+        assert_matches!(
+            validator.validate(&message, &query, now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::MissingDenial))
+        );
+    }
+
+    #[tokio::test]
+    async fn denial_signed_by_another_key_is_rejected() {
+        let now = SystemTime::now();
+        let root = TestZone::new("");
+        let (validator, query) = unsigned_delegation(
+            &root,
+            |_| TestZone::new("").nsec_records("example", "org", &[TYPE_NS], now),
+            now,
+        );
+        let answer = [record("www.example", ResourceType::A, vec![192, 0, 2, 1])];
+
+        assert_matches!(
+            validator
+                .validate(
+                    &message("www.example", ResourceType::A, &answer),
+                    &query,
+                    now
+                )
+                .await,
+            Err(dns::DnsError::Dnssec(ValidationError::MissingDenial))
+        );
+    }
+
+    #[tokio::test]
+    async fn wildcard_answer_needs_proof_that_name_does_not_exist() {
+        let chain = TestChain::new();
+        let answer = chain.wildcard_answer("www.example");
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::MissingDenial))
+        );
+
+        let nsec = chain.zone.nsec_records(
+            "example",
+            "zzz.example",
+            &[TYPE_NS, ResourceType::NSEC as u16],
+            chain.now,
+        );
+        assert_matches!(
+            chain
+                .validate_with_authority(&answer, &nsec, chain.now)
+                .await,
+            Ok(DnssecStatus::Secure)
+        );
+    }
+
+    #[tokio::test]
+    async fn tampered_answer_is_rejected() {
+        let chain = TestChain::new();
+        let mut answer = chain.signed_answer("www.example");
+        answer[0].data = vec![198, 51, 100, 1];
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::InvalidSignature))
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_signature_is_rejected() {
+        let chain = TestChain::new();
+        let answer = chain.signed_answer("www.example");
+        let later = chain.now + Duration::from_secs(2 * u64::from(SIGNATURE_VALIDITY));
+
+        assert_matches!(
+            chain.validate(&answer, later).await,
+            Err(dns::DnsError::Dnssec(ValidationError::InvalidSignature))
+        );
+    }
+
+    #[tokio::test]
+    async fn untrusted_root_is_rejected() {
+        let mut chain = TestChain::new();
+        chain.validator = DnssecValidator::new(vec![TestZone::new("").ds()]);
+        let answer = chain.signed_answer("www.example");
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::NoTrustedKey))
+        );
+    }
+
+    #[tokio::test]
+    async fn signer_must_contain_answer() {
+        let chain = TestChain::new();
+        let answer = chain.signed_answer("www.example.org");
+
+        assert_matches!(
+            chain.validate(&answer, chain.now).await,
+            Err(dns::DnsError::Dnssec(ValidationError::SignerOutOfZone))
+        );
+    }
+
+    #[test]
+    fn names_are_compared_ignoring_case() {
+        assert!(names_equal("Example.", "example"));
+        assert!(is_subdomain("WWW.Example", "example."));
+        assert!(is_subdomain("www.example", ""));
+        assert!(!is_subdomain("www.example", "ample"));
+        assert_eq!(canonical_cmp("Example", "a.example"), Ordering::Less);
+        assert_eq!(canonical_cmp("z.example", "A.B.example"), Ordering::Greater);
+    }
+
+    #[test]
+    fn base32hex_round_trips() {
+        let hash = [0x12, 0x34, 0x56, 0x78, 0x9A];
+        assert_eq!(
+            base32hex_decode(&base32hex_encode(&hash)),
+            Some(hash.to_vec())
+        );
+        assert_eq!(base32hex_decode("W"), None);
+    }
+
+    #[test]
+    fn wildcard_signatures_cover_the_wildcard_name() {
+        let zone = TestZone::new("example");
+        let answer = record("www.example", ResourceType::A, vec![192, 0, 2, 1]);
+        let signature = Rrsig::parse(
+            &zone
+                .sign(std::slice::from_ref(&answer), SystemTime::now())
+                .data,
+        )
+        .unwrap();
+        let synthesized = record("a.b.example", ResourceType::A, vec![192, 0, 2, 1]);
+        let rrset = SignedRrset {
+            name: &synthesized.name,
+            resource_type: synthesized.resource_type,
+            records: vec![&synthesized],
+            signatures: vec![],
+        };
+
+        let data = signed_data(&signature, &rrset).unwrap();
+        let owner = canonical_name("*.b.example");
+        assert_eq!(&data[signature.signed_fields.len()..][..owner.len()], owner);
+    }
+}
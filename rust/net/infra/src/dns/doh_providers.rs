@@ -0,0 +1,601 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Lookups raced across several DNS-over-HTTPS providers.
+//!
+//! A single blocked provider shouldn't push every lookup onto the system
+//! resolver, which is the one most likely to be tampered with. A
+//! [`DohProviderRace`] starts lookups against its providers one after another
+//! and uses the first answer, while keeping track of which providers have been
+//! failing so that those are tried later.
+
+use std::cmp::max;
+use std::fmt::Debug;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use const_str::ip_addr;
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt as _;
+use itertools::Itertools as _;
+use tokio::time::Instant;
+
+use crate::certs::RootCertificates;
+use crate::dns::custom_resolver::CustomDnsResolver;
+use crate::dns::dns_errors::Error;
+use crate::dns::dns_lookup::{DnsLookup, DnsLookupRequest};
+use crate::dns::dns_transport_doh::{DohTransport, DohTransportParams, CLOUDFLARE_IPS};
+use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::lookup_result::LookupResult;
+use crate::dns::persistent_cache::PersistentDnsCache;
+use crate::dns::service_binding::ServiceBinding;
+use crate::host::Host;
+use crate::route::{
+    AttemptOutcome, ConnectionOutcomeParams, ConnectionOutcomes, DomainFrontConfig,
+    HttpRouteFragment, HttpsTlsRoute, RouteDelayPolicy as _, TcpRoute, TlsRoute, TlsRouteFragment,
+    UnsuccessfulOutcome, DEFAULT_HTTPS_PORT,
+};
+use crate::timeouts::{DOH_PROVIDER_ATTEMPT_DELAY, DOH_PROVIDER_ATTEMPT_TIMEOUT};
+use crate::utils::{EventSubscription, ObservableEvent};
+use crate::{dns, Alpn};
+
+/// How long failing providers are pushed back.
+///
+/// These are the same values used for connection routes, except that failures
+/// are forgotten sooner, since a provider's lookups are much cheaper to retry
+/// than a chat connection.
+const PROVIDER_OUTCOME_PARAMS: ConnectionOutcomeParams = ConnectionOutcomeParams {
+    age_cutoff: Duration::from_secs(2 * 60),
+    cooldown_growth_factor: 10.0,
+    count_growth_factor: 10.0,
+    max_count: 5,
+    max_delay: Duration::from_secs(30),
+};
+
+const GOOGLE_IPS: (Ipv4Addr, Ipv6Addr) = (
+    ip_addr!(v4, "8.8.8.8"),
+    ip_addr!(v6, "2001:4860:4860::8888"),
+);
+
+/// A DNS-over-HTTPS server that lookups can be sent to.
+#[derive(Clone, Debug)]
+pub struct DohProviderConfig {
+    /// A loggable name for the provider.
+    pub name: &'static str,
+    /// Addresses to connect to.
+    ///
+    /// These are IP literals so that reaching the provider doesn't itself
+    /// require a DNS lookup. When connecting through a domain front, these
+    /// should be addresses of the front.
+    pub ips: Vec<IpAddr>,
+    /// If set, requests are sent through this front instead of directly to the
+    /// provider.
+    pub domain_front: Option<DomainFrontConfig>,
+}
+
+impl DohProviderConfig {
+    pub fn cloudflare() -> Self {
+        let (v4, v6) = CLOUDFLARE_IPS;
+        Self {
+            name: "cloudflare",
+            ips: vec![IpAddr::V6(v6), IpAddr::V4(v4)],
+            domain_front: None,
+        }
+    }
+
+    pub fn google() -> Self {
+        let (v4, v6) = GOOGLE_IPS;
+        Self {
+            name: "google",
+            ips: vec![IpAddr::V6(v6), IpAddr::V4(v4)],
+            domain_front: None,
+        }
+    }
+
+    /// The providers used when none are configured explicitly.
+    pub fn defaults() -> Vec<Self> {
+        vec![Self::cloudflare(), Self::google()]
+    }
+
+    /// The routes to the provider, in the order they should be attempted.
+    pub fn routes(&self) -> Vec<HttpsTlsRoute<TlsRoute<TcpRoute<IpAddr>>>> {
+        let Self {
+            name: _,
+            ips,
+            domain_front,
+        } = self;
+
+        let Some(DomainFrontConfig {
+            http_host,
+            sni_list,
+            root_certs,
+            path_prefix,
+            front_name,
+            return_routes_with_all_snis,
+        }) = domain_front
+        else {
+            return ips
+                .iter()
+                .map(|&ip_addr| {
+                    let host = Host::Ip(ip_addr);
+                    HttpsTlsRoute {
+                        fragment: HttpRouteFragment {
+                            path_prefix: "".into(),
+                            front_name: None,
+                            host_header: Arc::from(host.to_string()),
+                        },
+                        inner: TlsRoute {
+                            fragment: TlsRouteFragment {
+                                sni: host,
+                                root_certs: RootCertificates::Native,
+                                alpn: Some(Alpn::Http2),
+                                ech_config_list: None,
+                                pin_set: None,
+                            },
+                            inner: TcpRoute {
+                                address: ip_addr,
+                                port: DEFAULT_HTTPS_PORT,
+                            },
+                        },
+                    }
+                })
+                .collect();
+        };
+
+        let sni_list = if *return_routes_with_all_snis {
+            &sni_list[..]
+        } else {
+            &sni_list[..sni_list.len().min(1)]
+        };
+        sni_list
+            .iter()
+            .cartesian_product(ips)
+            .map(|(sni, &ip_addr)| HttpsTlsRoute {
+                fragment: HttpRouteFragment {
+                    path_prefix: Arc::clone(path_prefix),
+                    front_name: Some(*front_name),
+                    host_header: Arc::clone(http_host),
+                },
+                inner: TlsRoute {
+                    fragment: TlsRouteFragment {
+                        sni: Host::Domain(Arc::clone(sni)),
+                        root_certs: root_certs.clone(),
+                        alpn: Some(Alpn::Http2),
+                        ech_config_list: None,
+                        pin_set: None,
+                    },
+                    inner: TcpRoute {
+                        address: ip_addr,
+                        port: DEFAULT_HTTPS_PORT,
+                    },
+                },
+            })
+            .collect()
+    }
+}
+
+/// Races lookups across several providers, preferring those that have been
+/// working.
+///
+/// The provider with the best recent record starts right away, and each of the
+/// others starts [`DOH_PROVIDER_ATTEMPT_DELAY`] after the previous one (or
+/// later, if it has been failing). The first successful answer is used.
+#[derive(Clone)]
+pub struct DohProviderRace {
+    providers: Arc<[DohProvider]>,
+    outcomes: Arc<Mutex<ConnectionOutcomes<usize>>>,
+    _network_change_subscription: Arc<EventSubscription>,
+}
+
+#[derive(Debug)]
+struct DohProvider {
+    name: &'static str,
+    lookup: Box<dyn DnsLookup>,
+}
+
+type BoxedLookupFuture<'a, T> = Pin<Box<dyn Future<Output = dns::Result<T>> + Send + 'a>>;
+
+impl DohProviderRace {
+    pub fn new(
+        providers: &[DohProviderConfig],
+        persistent_cache: Arc<PersistentDnsCache>,
+        network_change_event: &ObservableEvent,
+    ) -> Self {
+        let providers = providers
+            .iter()
+            .map(|config| {
+                let params = DohTransportParams::new(config.routes());
+                let resolver = CustomDnsResolver::<DohTransport>::new(params, network_change_event)
+                    .with_persistent_cache(persistent_cache.clone());
+                (config.name, Box::new(resolver) as Box<dyn DnsLookup>)
+            })
+            .collect();
+        Self::from_lookups(providers, network_change_event)
+    }
+
+    pub(crate) fn from_lookups(
+        providers: Vec<(&'static str, Box<dyn DnsLookup>)>,
+        network_change_event: &ObservableEvent,
+    ) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|(name, lookup)| DohProvider { name, lookup })
+            .collect();
+        let outcomes = Arc::new(Mutex::new(ConnectionOutcomes::new(PROVIDER_OUTCOME_PARAMS)));
+
+        let outcomes_for_network_change = Arc::downgrade(&outcomes);
+        let network_change_subscription = network_change_event.subscribe(Box::new(move || {
+            // Which providers are reachable depends on the network, so
+            // failures seen on the previous one are no longer relevant.
+            if let Some(outcomes) = outcomes_for_network_change.upgrade() {
+                outcomes.lock().expect("not poisoned").reset(Instant::now());
+            }
+        }));
+
+        Self {
+            providers,
+            outcomes,
+            _network_change_subscription: Arc::new(network_change_subscription),
+        }
+    }
+
+    async fn race<'s, T>(
+        &'s self,
+        hostname: &str,
+        attempt: impl Fn(&'s dyn DnsLookup) -> BoxedLookupFuture<'s, T>,
+    ) -> dns::Result<T> {
+        let started = Instant::now();
+        let order = {
+            let outcomes = self.outcomes.lock().expect("not poisoned");
+            (0..self.providers.len())
+                .map(|index| (outcomes.compute_delay(&index, started), index))
+                .sorted()
+                .collect_vec()
+        };
+
+        let mut attempts = FuturesUnordered::from_iter(order.into_iter().enumerate().map(
+            |(position, (delay, index))| {
+                let position = u32::try_from(position).unwrap_or(u32::MAX);
+                let start_at =
+                    started + max(delay, DOH_PROVIDER_ATTEMPT_DELAY.saturating_mul(position));
+                let lookup = attempt(&*self.providers[index].lookup);
+                let outcomes = &*self.outcomes;
+                async move {
+                    tokio::time::sleep_until(start_at).await;
+                    let attempt_started = Instant::now();
+                    let result = tokio::time::timeout(DOH_PROVIDER_ATTEMPT_TIMEOUT, lookup)
+                        .await
+                        .unwrap_or(Err(Error::Timeout));
+                    // Only finished attempts are recorded. One that's cut off
+                    // by a faster provider or by the caller giving up says
+                    // nothing about whether the provider works, so it never
+                    // gets here.
+                    let result_for_outcome = match &result {
+                        // A name without records doesn't mean the provider is
+                        // failing.
+                        Ok(_) | Err(Error::NoData) => Ok(()),
+                        Err(_) => Err(UnsuccessfulOutcome),
+                    };
+                    outcomes
+                        .lock()
+                        .expect("not poisoned")
+                        .apply_outcome_updates(
+                            [(
+                                index,
+                                AttemptOutcome {
+                                    started: attempt_started,
+                                    result: result_for_outcome,
+                                },
+                            )],
+                            Instant::now(),
+                        );
+                    (index, result)
+                }
+            },
+        ));
+
+        let mut last_error = Error::LookupFailed;
+        loop {
+            let Some((index, result)) = attempts.next().await else {
+                return Err(last_error);
+            };
+            match result {
+                Ok(value) => return Ok(value),
+                Err(error) => {
+                    log::info!(
+                        "DoH provider {} failed to look up [{}]: {}",
+                        self.providers[index].name,
+                        log_safe_domain(hostname),
+                        error
+                    );
+                    last_error = error;
+                }
+            }
+        }
+    }
+}
+
+impl Debug for DohProviderRace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DohProviderRace")
+            .field(
+                "providers",
+                &self.providers.iter().map(|p| p.name).collect_vec(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl DnsLookup for DohProviderRace {
+    async fn dns_lookup(&self, request: DnsLookupRequest) -> dns::Result<LookupResult> {
+        let hostname = request.hostname.clone();
+        self.race(&hostname, |lookup| lookup.dns_lookup(request.clone()))
+            .await
+    }
+
+    async fn service_binding_lookup(
+        &self,
+        request: DnsLookupRequest,
+    ) -> dns::Result<Option<ServiceBinding>> {
+        let hostname = request.hostname.clone();
+        self.race(&hostname, |lookup| {
+            lookup.service_binding_lookup(request.clone())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use assert_matches::assert_matches;
+    use const_str::ip_addr;
+
+    use super::*;
+    use crate::DnsSource;
+
+    const HOSTNAME: &str = "chat.signal.org";
+    const LOOKUP_TIME: Duration = Duration::from_secs(1);
+
+    /// A provider that answers after [`LOOKUP_TIME`], or fails if `ip` is
+    /// `None`.
+    #[derive(Debug)]
+    struct TestProvider {
+        ip: Option<IpAddr>,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl DnsLookup for TestProvider {
+        async fn dns_lookup(&self, _request: DnsLookupRequest) -> dns::Result<LookupResult> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(LOOKUP_TIME).await;
+            match self.ip {
+                Some(IpAddr::V4(ip)) => Ok(LookupResult::new(DnsSource::Test, vec![ip], vec![])),
+                Some(IpAddr::V6(ip)) => Ok(LookupResult::new(DnsSource::Test, vec![], vec![ip])),
+                None => Err(Error::TransportFailure),
+            }
+        }
+    }
+
+    fn race(
+        providers: &[(&'static str, Option<IpAddr>)],
+    ) -> (DohProviderRace, Vec<Arc<AtomicUsize>>) {
+        let counters = providers
+            .iter()
+            .map(|_| Arc::new(AtomicUsize::new(0)))
+            .collect_vec();
+        let lookups = providers
+            .iter()
+            .zip(&counters)
+            .map(|((name, ip), lookups)| {
+                (
+                    *name,
+                    Box::new(TestProvider {
+                        ip: *ip,
+                        lookups: lookups.clone(),
+                    }) as Box<dyn DnsLookup>,
+                )
+            })
+            .collect();
+        (
+            DohProviderRace::from_lookups(lookups, &ObservableEvent::new()),
+            counters,
+        )
+    }
+
+    fn request() -> DnsLookupRequest {
+        DnsLookupRequest {
+            hostname: HOSTNAME.into(),
+            ipv6_enabled: true,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn first_answer_wins() {
+        let (race, counters) = race(&[
+            ("first", Some(ip_addr!("192.0.2.1"))),
+            ("second", Some(ip_addr!("192.0.2.2"))),
+        ]);
+        assert!(LOOKUP_TIME > DOH_PROVIDER_ATTEMPT_DELAY);
+
+        let result = race.dns_lookup(request()).await.expect("success");
+        assert_eq!(result.ipv4, [ip_addr!(v4, "192.0.2.1")]);
+        // The second provider started, but its answer wasn't waited for.
+        assert_eq!(counters[1].load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn falls_back_to_working_provider() {
+        let (race, _counters) = race(&[("blocked", None), ("working", Some(ip_addr!("::1")))]);
+
+        let result = race.dns_lookup(request()).await.expect("success");
+        assert_eq!(result.ipv6, [ip_addr!(v6, "::1")]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failing_provider_is_tried_later() {
+        let (race, counters) = race(&[("blocked", None), ("working", Some(ip_addr!("::1")))]);
+
+        let started = Instant::now();
+        race.dns_lookup(request()).await.expect("success");
+        assert_eq!(started.elapsed(), DOH_PROVIDER_ATTEMPT_DELAY + LOOKUP_TIME);
+
+        // Now the working provider goes first.
+        let started = Instant::now();
+        race.dns_lookup(request()).await.expect("success");
+        assert_eq!(started.elapsed(), LOOKUP_TIME);
+        assert_eq!(counters[1].load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_when_all_providers_fail() {
+        let (race, _counters) = race(&[("blocked-1", None), ("blocked-2", None)]);
+
+        assert_matches!(
+            race.dns_lookup(request()).await,
+            Err(Error::TransportFailure)
+        );
+    }
+
+    /// A provider that never answers.
+    #[derive(Debug)]
+    struct HangingProvider;
+
+    #[async_trait]
+    impl DnsLookup for HangingProvider {
+        async fn dns_lookup(&self, _request: DnsLookupRequest) -> dns::Result<LookupResult> {
+            std::future::pending().await
+        }
+    }
+
+    fn hanging_then_working() -> DohProviderRace {
+        DohProviderRace::from_lookups(
+            vec![
+                ("hanging", Box::new(HangingProvider) as Box<dyn DnsLookup>),
+                (
+                    "working",
+                    Box::new(TestProvider {
+                        ip: Some(ip_addr!("::1")),
+                        lookups: Default::default(),
+                    }),
+                ),
+            ],
+            &ObservableEvent::new(),
+        )
+    }
+
+    fn delay_for(race: &DohProviderRace, index: usize) -> Duration {
+        race.outcomes
+            .lock()
+            .expect("not poisoned")
+            .compute_delay(&index, Instant::now())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn losing_provider_keeps_its_place() {
+        let race = hanging_then_working();
+
+        let started = Instant::now();
+        race.dns_lookup(request()).await.expect("success");
+        assert_eq!(started.elapsed(), DOH_PROVIDER_ATTEMPT_DELAY + LOOKUP_TIME);
+
+        // The hanging provider was cut off before it could answer or time
+        // out, which doesn't count against it.
+        assert_eq!(delay_for(&race, 0), Duration::ZERO);
+        let started = Instant::now();
+        race.dns_lookup(request()).await.expect("success");
+        assert_eq!(started.elapsed(), DOH_PROVIDER_ATTEMPT_DELAY + LOOKUP_TIME);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_provider_keeps_its_place() {
+        let race = hanging_then_working();
+
+        // Give up before the second provider even starts.
+        tokio::time::timeout(DOH_PROVIDER_ATTEMPT_DELAY / 2, race.dns_lookup(request()))
+            .await
+            .expect_err("cancelled");
+
+        assert_eq!(delay_for(&race, 0), Duration::ZERO);
+        let started = Instant::now();
+        race.dns_lookup(request()).await.expect("success");
+        assert_eq!(started.elapsed(), DOH_PROVIDER_ATTEMPT_DELAY + LOOKUP_TIME);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timed_out_provider_is_tried_later() {
+        let race = DohProviderRace::from_lookups(
+            vec![("hanging", Box::new(HangingProvider) as Box<dyn DnsLookup>)],
+            &ObservableEvent::new(),
+        );
+
+        let started = Instant::now();
+        assert_matches!(race.dns_lookup(request()).await, Err(Error::Timeout));
+        assert_eq!(started.elapsed(), DOH_PROVIDER_ATTEMPT_TIMEOUT);
+
+        assert!(delay_for(&race, 0) > Duration::ZERO);
+    }
+
+    #[test]
+    fn default_providers_have_distinct_names() {
+        let providers = DohProviderConfig::defaults();
+        assert!(providers.len() > 1);
+        assert!(providers.iter().map(|p| p.name).all_unique());
+    }
+
+    #[test]
+    fn domain_fronted_routes() {
+        let config = DohProviderConfig {
+            name: "fronted",
+            ips: vec![ip_addr!("192.0.2.1"), ip_addr!("192.0.2.2")],
+            domain_front: Some(DomainFrontConfig {
+                http_host: "doh.example".into(),
+                sni_list: vec!["front-1.example".into(), "front-2.example".into()],
+                root_certs: RootCertificates::Native,
+                path_prefix: "/doh".into(),
+                front_name: "front",
+                return_routes_with_all_snis: true,
+            }),
+        };
+
+        let routes = config.routes();
+        assert_eq!(
+            routes
+                .iter()
+                .map(|route| (&route.inner.fragment.sni, route.inner.inner.address))
+                .collect_vec(),
+            [
+                (
+                    &Host::Domain("front-1.example".into()),
+                    ip_addr!("192.0.2.1")
+                ),
+                (
+                    &Host::Domain("front-1.example".into()),
+                    ip_addr!("192.0.2.2")
+                ),
+                (
+                    &Host::Domain("front-2.example".into()),
+                    ip_addr!("192.0.2.1")
+                ),
+                (
+                    &Host::Domain("front-2.example".into()),
+                    ip_addr!("192.0.2.2")
+                ),
+            ]
+        );
+        assert!(routes.iter().all(|route| {
+            *route.fragment.host_header == *"doh.example"
+                && *route.fragment.path_prefix == *"/doh"
+                && route.fragment.front_name == Some("front")
+        }));
+    }
+}
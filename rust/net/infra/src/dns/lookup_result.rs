@@ -8,6 +8,7 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::slice::Iter;
 use std::vec::IntoIter;
 
+use crate::dns::dnssec::DnssecStatus;
 use crate::DnsSource;
 
 #[derive(Debug, Clone)]
@@ -15,6 +16,7 @@ pub struct LookupResult {
     pub(crate) source: DnsSource,
     pub(crate) ipv4: Vec<Ipv4Addr>,
     pub(crate) ipv6: Vec<Ipv6Addr>,
+    pub(crate) dnssec: DnssecStatus,
 }
 
 impl IntoIterator for LookupResult {
@@ -55,17 +57,31 @@ impl<'a> IntoIterator for &'a LookupResult {
 
 impl LookupResult {
     pub fn new(source: DnsSource, ipv4: Vec<Ipv4Addr>, ipv6: Vec<Ipv6Addr>) -> Self {
-        Self { source, ipv4, ipv6 }
+        Self {
+            source,
+            ipv4,
+            ipv6,
+            dnssec: DnssecStatus::NotValidated,
+        }
+    }
+
+    pub(crate) fn with_dnssec_status(self, dnssec: DnssecStatus) -> Self {
+        Self { dnssec, ..self }
     }
 
     pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
         self.into_iter()
     }
 
-    pub(crate) fn source(&self) -> DnsSource {
+    pub fn source(&self) -> DnsSource {
         self.source
     }
 
+    /// How the addresses were authenticated by the resolver that produced them.
+    pub fn dnssec_status(&self) -> DnssecStatus {
+        self.dnssec
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.ipv4.is_empty() && self.ipv6.is_empty()
     }
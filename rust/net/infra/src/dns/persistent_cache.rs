@@ -0,0 +1,434 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! DNS results that survive app restarts.
+//!
+//! [`CustomDnsResolver`](crate::dns::custom_resolver::CustomDnsResolver)
+//! caches results in memory only, so every cold start pays for a full lookup.
+//! A [`PersistentDnsCache`] with a [`DnsCacheStore`] keeps unexpired results
+//! across restarts. Since some networks return addresses that only work within
+//! that network, entries are saved and loaded per [`NetworkIdentity`].
+//! [`FileDnsCacheStore`] is a store that keeps them in a directory.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use itertools::Itertools as _;
+
+use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::lookup_result::LookupResult;
+use crate::DnsSource;
+
+/// App-provided identifier for the network the device is connected to.
+///
+/// This should distinguish networks that might resolve names differently,
+/// e.g. a hash of the Wi-Fi network name. It is never logged.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct NetworkIdentity(pub String);
+
+impl Debug for NetworkIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NetworkIdentity(REDACTED)")
+    }
+}
+
+/// A lookup result as saved to a [`DnsCacheStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PersistedDnsEntry {
+    pub hostname: String,
+    /// Where the result originally came from.
+    pub source: DnsSource,
+    pub ipv4: Vec<Ipv4Addr>,
+    pub ipv6: Vec<Ipv6Addr>,
+    /// When the shortest record TTL runs out.
+    pub expires_at: SystemTime,
+}
+
+/// Persistent storage for a [`PersistentDnsCache`].
+pub trait DnsCacheStore: Debug + Send + Sync {
+    /// Returns the entries most recently saved for `network`.
+    fn load(&self, network: &NetworkIdentity) -> Vec<PersistedDnsEntry>;
+
+    /// Replaces the entries saved for `network`.
+    ///
+    /// Called after every lookup that updated the cache, on a thread where
+    /// blocking is allowed.
+    fn save(&self, network: &NetworkIdentity, entries: Vec<PersistedDnsEntry>);
+}
+
+/// DNS lookup results for the current network, backed by a [`DnsCacheStore`].
+///
+/// The cache is inactive until both a store and the current network are set.
+///
+/// The store is never called with the cache's state locked, so a slow store
+/// only holds up saving, not lookups.
+#[derive(Debug, Default)]
+pub struct PersistentDnsCache {
+    state: Mutex<PersistentDnsCacheState>,
+    /// Held while loading or saving, so that snapshots reach the store in the
+    /// order they were taken, and aren't taken while a load is in progress.
+    save_lock: Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct PersistentDnsCacheState {
+    store: Option<Arc<dyn DnsCacheStore>>,
+    network: Option<NetworkIdentity>,
+    /// Bumped whenever the store or network changes, to discard loads that
+    /// were overtaken by another change.
+    generation: u64,
+    entries: HashMap<String, PersistedDnsEntry>,
+}
+
+impl PersistentDnsCache {
+    /// Sets the store, loading any entries saved for the current network.
+    pub fn set_store(&self, store: Option<Arc<dyn DnsCacheStore>>) {
+        let mut guard = self.state.lock().expect("not poisoned");
+        guard.store = store;
+        guard.generation = guard.generation.wrapping_add(1);
+        drop(guard);
+        self.reload(SystemTime::now());
+    }
+
+    /// Sets the current network, replacing any entries saved for the previous
+    /// one with those saved for the new one.
+    ///
+    /// Passing `None` disables the cache until a network is set again.
+    pub fn set_network(&self, network: Option<NetworkIdentity>) {
+        let mut guard = self.state.lock().expect("not poisoned");
+        if guard.network == network {
+            return;
+        }
+        guard.network = network;
+        guard.generation = guard.generation.wrapping_add(1);
+        drop(guard);
+        self.reload(SystemTime::now());
+    }
+
+    pub(crate) fn current_network(&self) -> Option<NetworkIdentity> {
+        let guard = self.state.lock().expect("not poisoned");
+        guard.store.as_ref().and(guard.network.clone())
+    }
+
+    /// Returns the unexpired result for `hostname`, if there is one.
+    pub(crate) fn get(&self, hostname: &str, now: SystemTime) -> Option<LookupResult> {
+        let mut guard = self.state.lock().expect("not poisoned");
+        match guard.entries.get(hostname) {
+            Some(entry) if entry.expires_at <= now => {
+                guard.entries.remove(hostname);
+                None
+            }
+            Some(entry) => {
+                log::info!(
+                    "DNS record for {} found in persistent cache (from {})",
+                    log_safe_domain(hostname),
+                    entry.source,
+                );
+                Some(LookupResult::new(
+                    DnsSource::Cache,
+                    entry.ipv4.clone(),
+                    entry.ipv6.clone(),
+                ))
+            }
+            None => None,
+        }
+    }
+
+    /// Saves a result that was looked up on `network`.
+    ///
+    /// The result is dropped if the current network has changed since.
+    pub(crate) fn insert(
+        &self,
+        network: &NetworkIdentity,
+        entry: PersistedDnsEntry,
+        now: SystemTime,
+    ) {
+        let _saving = self.save_lock.lock().expect("not poisoned");
+        let (store, entries) = {
+            let mut guard = self.state.lock().expect("not poisoned");
+            if guard.network.as_ref() != Some(network) {
+                return;
+            }
+            let Some(store) = guard.store.clone() else {
+                return;
+            };
+            guard
+                .entries
+                .retain(|_, existing| existing.expires_at > now);
+            if entry.expires_at > now {
+                guard.entries.insert(entry.hostname.clone(), entry);
+            }
+            (store, guard.entries.values().cloned().collect())
+        };
+        store.save(network, entries);
+    }
+
+    /// Replaces the entries with those saved for the current store and
+    /// network, unless either changes again while loading.
+    fn reload(&self, now: SystemTime) {
+        let _loading = self.save_lock.lock().expect("not poisoned");
+        let (store, network, generation) = {
+            let mut guard = self.state.lock().expect("not poisoned");
+            guard.entries.clear();
+            (guard.store.clone(), guard.network.clone(), guard.generation)
+        };
+        let (Some(store), Some(network)) = (store, network) else {
+            return;
+        };
+        let entries = store
+            .load(&network)
+            .into_iter()
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| (entry.hostname.clone(), entry))
+            .collect();
+
+        let mut guard = self.state.lock().expect("not poisoned");
+        if guard.generation == generation {
+            guard.entries = entries;
+        }
+    }
+}
+
+/// A [`DnsCacheStore`] that keeps each network's entries in a file in
+/// `directory`.
+///
+/// Failures to read or write are logged and otherwise treated as an empty
+/// cache.
+#[derive(Debug)]
+pub struct FileDnsCacheStore {
+    directory: PathBuf,
+}
+
+impl FileDnsCacheStore {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    fn path(&self, network: &NetworkIdentity) -> PathBuf {
+        self.directory.join(format!(
+            "dns-{}",
+            BASE64_URL_SAFE_NO_PAD.encode(network.0.as_bytes())
+        ))
+    }
+}
+
+impl DnsCacheStore for FileDnsCacheStore {
+    fn load(&self, network: &NetworkIdentity) -> Vec<PersistedDnsEntry> {
+        let contents = match std::fs::read_to_string(self.path(network)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return vec![],
+            Err(e) => {
+                log::warn!("failed to read persistent DNS cache: {e}");
+                return vec![];
+            }
+        };
+        contents.lines().filter_map(parse_entry).collect()
+    }
+
+    fn save(&self, network: &NetworkIdentity, entries: Vec<PersistedDnsEntry>) {
+        let path = self.path(network);
+        let contents: String = entries.iter().map(format_entry).collect();
+        // Write to a temporary file first so that a crash can't leave a
+        // partially written cache behind.
+        let temp_path = path.with_extension("tmp");
+        if let Err(e) =
+            std::fs::write(&temp_path, contents).and_then(|()| std::fs::rename(&temp_path, &path))
+        {
+            log::warn!("failed to write persistent DNS cache: {e}");
+        }
+    }
+}
+
+/// Formats an entry as a line of tab-separated fields.
+fn format_entry(entry: &PersistedDnsEntry) -> String {
+    let PersistedDnsEntry {
+        hostname,
+        source,
+        ipv4,
+        ipv6,
+        expires_at,
+    } = entry;
+    let expires_at = expires_at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    format!(
+        "{hostname}\t{source}\t{expires_at}\t{}\t{}\n",
+        ipv4.iter().join(","),
+        ipv6.iter().join(","),
+    )
+}
+
+/// Parses a line written by [`format_entry`], skipping it if it's malformed.
+fn parse_entry(line: &str) -> Option<PersistedDnsEntry> {
+    let (hostname, source, expires_at, ipv4, ipv6) = line.split('\t').collect_tuple()?;
+    fn parse_list<T: std::str::FromStr>(list: &str) -> Option<Vec<T>> {
+        list.split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().ok())
+            .collect()
+    }
+    Some(PersistedDnsEntry {
+        hostname: hostname.to_owned(),
+        source: source.parse().ok()?,
+        ipv4: parse_list(ipv4)?,
+        ipv6: parse_list(ipv6)?,
+        expires_at: SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs(expires_at.parse().ok()?))?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use const_str::ip_addr;
+
+    use super::*;
+
+    const HOSTNAME: &str = "chat.signal.org";
+    const IPV4: Ipv4Addr = ip_addr!(v4, "192.0.2.1");
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[derive(Debug, Default)]
+    struct InMemoryStore(Mutex<HashMap<NetworkIdentity, Vec<PersistedDnsEntry>>>);
+
+    impl DnsCacheStore for InMemoryStore {
+        fn load(&self, network: &NetworkIdentity) -> Vec<PersistedDnsEntry> {
+            let guard = self.0.lock().expect("not poisoned");
+            guard.get(network).cloned().unwrap_or_default()
+        }
+
+        fn save(&self, network: &NetworkIdentity, entries: Vec<PersistedDnsEntry>) {
+            let mut guard = self.0.lock().expect("not poisoned");
+            guard.insert(network.clone(), entries);
+        }
+    }
+
+    fn network(name: &str) -> NetworkIdentity {
+        NetworkIdentity(name.to_owned())
+    }
+
+    fn entry(expires_at: SystemTime) -> PersistedDnsEntry {
+        PersistedDnsEntry {
+            hostname: HOSTNAME.to_owned(),
+            source: DnsSource::DnsOverHttpsLookup,
+            ipv4: vec![IPV4],
+            ipv6: vec![],
+            expires_at,
+        }
+    }
+
+    fn cache_with_store(
+        store: &Arc<InMemoryStore>,
+        network: &NetworkIdentity,
+    ) -> PersistentDnsCache {
+        let cache = PersistentDnsCache::default();
+        cache.set_store(Some(store.clone()));
+        cache.set_network(Some(network.clone()));
+        cache
+    }
+
+    #[test]
+    fn entries_survive_restart() {
+        let store = Arc::new(InMemoryStore::default());
+        let home = network("home");
+        let now = SystemTime::now();
+
+        cache_with_store(&store, &home).insert(&home, entry(now + TTL), now);
+
+        let restarted = cache_with_store(&store, &home);
+        let result = restarted.get(HOSTNAME, now).expect("cached");
+        assert_eq!(result.source(), DnsSource::Cache);
+        assert_eq!(result.ipv4, [IPV4]);
+    }
+
+    #[test]
+    fn entries_are_per_network() {
+        let store = Arc::new(InMemoryStore::default());
+        let home = network("home");
+        let now = SystemTime::now();
+
+        let cache = cache_with_store(&store, &home);
+        cache.insert(&home, entry(now + TTL), now);
+
+        cache.set_network(Some(network("cafe")));
+        assert!(cache.get(HOSTNAME, now).is_none());
+
+        cache.set_network(Some(home));
+        assert!(cache.get(HOSTNAME, now).is_some());
+    }
+
+    #[test]
+    fn results_from_previous_network_are_dropped() {
+        let store = Arc::new(InMemoryStore::default());
+        let now = SystemTime::now();
+
+        let cache = cache_with_store(&store, &network("cafe"));
+        cache.insert(&network("home"), entry(now + TTL), now);
+        assert!(cache.get(HOSTNAME, now).is_none());
+        assert!(store.load(&network("home")).is_empty());
+    }
+
+    #[test]
+    fn expired_entries_are_not_returned() {
+        let store = Arc::new(InMemoryStore::default());
+        let home = network("home");
+        let now = SystemTime::now();
+
+        let cache = cache_with_store(&store, &home);
+        cache.insert(&home, entry(now + TTL), now);
+        assert!(cache.get(HOSTNAME, now + TTL).is_none());
+        assert!(cache_with_store(&store, &home)
+            .get(HOSTNAME, now + TTL)
+            .is_none());
+    }
+
+    #[test]
+    fn network_identity_is_not_logged() {
+        assert!(!format!("{:?}", network("home")).contains("home"));
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("libsignal-dns-cache-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("can create");
+
+        let store = FileDnsCacheStore::new(directory.clone());
+        let home = network("home");
+        assert!(store.load(&home).is_empty());
+
+        let entries = vec![
+            PersistedDnsEntry {
+                ipv6: vec![ip_addr!(v6, "2001:db8::1"), ip_addr!(v6, "2001:db8::2")],
+                ..entry(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            },
+            PersistedDnsEntry {
+                hostname: "cdn.signal.org".to_owned(),
+                source: DnsSource::UdpLookup,
+                ..entry(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_060))
+            },
+        ];
+        store.save(&home, entries.clone());
+        assert_eq!(store.load(&home), entries);
+        assert!(store.load(&network("cafe")).is_empty());
+
+        std::fs::remove_dir_all(&directory).expect("can clean up");
+    }
+
+    #[test]
+    fn inactive_without_network() {
+        let store = Arc::new(InMemoryStore::default());
+        let cache = PersistentDnsCache::default();
+        cache.set_store(Some(store));
+        assert_eq!(cache.current_network(), None);
+    }
+}
@@ -0,0 +1,109 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use crate::dns::dnssec::DnssecStatus;
+use crate::route::EchConfigList;
+
+/// Connection parameters published in a DNS HTTPS record.
+///
+/// See [RFC 9460](https://datatracker.ietf.org/doc/html/rfc9460).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceBinding {
+    /// Lower values are preferred; 0 means the record is an alias.
+    pub priority: u16,
+    /// The name of the service endpoint, or empty if it is the queried name.
+    pub target: String,
+    /// ALPN protocol IDs supported by the service endpoint.
+    pub alpn: Vec<String>,
+    pub ech_config_list: Option<EchConfigList>,
+}
+
+impl ServiceBinding {
+    /// Picks the most preferred record that applies to `hostname` itself.
+    ///
+    /// Alias records and records for other service endpoints are ignored,
+    /// since following them would require additional lookups.
+    pub(crate) fn select(
+        records: impl IntoIterator<Item = ServiceBinding>,
+        hostname: &str,
+    ) -> Option<ServiceBinding> {
+        let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+        records
+            .into_iter()
+            .filter(|record| {
+                let target = record.target.strip_suffix('.').unwrap_or(&record.target);
+                record.priority != 0 && (target.is_empty() || target.eq_ignore_ascii_case(hostname))
+            })
+            .min_by_key(|record| record.priority)
+    }
+
+    /// Clears parameters that shouldn't be used from an answer with the given
+    /// DNSSEC status.
+    ///
+    /// ECH configs are only kept from [`DnssecStatus::Secure`] answers.
+    /// Nothing else vouches for them, and an on-path attacker who substituted
+    /// their own could read the real SNI.
+    pub(crate) fn clear_unauthenticated(bindings: &mut [ServiceBinding], dnssec: DnssecStatus) {
+        if dnssec == DnssecStatus::Secure {
+            return;
+        }
+        for binding in bindings {
+            binding.ech_config_list = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(priority: u16, target: &str) -> ServiceBinding {
+        ServiceBinding {
+            priority,
+            target: target.to_owned(),
+            alpn: vec![],
+            ech_config_list: None,
+        }
+    }
+
+    #[test]
+    fn select_prefers_lowest_priority_for_same_name() {
+        let selected = ServiceBinding::select(
+            [
+                record(0, "alias.example"),
+                record(2, ""),
+                record(1, "other.example"),
+                record(3, "Chat.Signal.org."),
+            ],
+            "chat.signal.org",
+        );
+        assert_eq!(selected, Some(record(2, "")));
+    }
+
+    #[test]
+    fn ech_configs_are_only_kept_from_secure_answers() {
+        let with_ech = ServiceBinding {
+            ech_config_list: Some(EchConfigList::new([0xEC; 3].as_slice())),
+            ..record(1, "")
+        };
+        for (dnssec, expected) in [
+            (DnssecStatus::NotValidated, record(1, "")),
+            (DnssecStatus::Unsigned, record(1, "")),
+            (DnssecStatus::Secure, with_ech.clone()),
+        ] {
+            let mut bindings = [with_ech.clone()];
+            ServiceBinding::clear_unauthenticated(&mut bindings, dnssec);
+            assert_eq!(bindings, [expected], "{dnssec:?}");
+        }
+    }
+
+    #[test]
+    fn select_ignores_aliases() {
+        assert_eq!(
+            ServiceBinding::select([record(0, "")], "chat.signal.org"),
+            None
+        );
+    }
+}
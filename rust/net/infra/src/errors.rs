@@ -8,6 +8,7 @@ use std::fmt::Display;
 use tokio_boring_signal::HandshakeError;
 
 use crate::certs;
+use crate::route::EchConfigList;
 
 pub trait LogSafeDisplay: Display {}
 
@@ -30,6 +31,14 @@ pub enum TransportConnectError {
     CertError,
     /// Failed to establish SSL connection: {0}
     SslFailedHandshake(FailedHandshakeReason),
+    // The chain was otherwise valid, so this likely indicates interception.
+    /// Server certificate chain doesn't include any pinned key
+    CertificatePinMismatch,
+    /// Server rejected Encrypted Client Hello
+    EchRejected {
+        /// New configs the server offered for a retry, if any.
+        retry_configs: Option<EchConfigList>,
+    },
     /// Proxy handshake failed
     ProxyProtocol,
     /// Abort due to local error
@@ -114,6 +123,8 @@ impl From<TransportConnectError> for std::io::Error {
             TransportConnectError::SslFailedHandshake(_)
             | TransportConnectError::SslError(_)
             | TransportConnectError::CertError
+            | TransportConnectError::CertificatePinMismatch
+            | TransportConnectError::EchRejected { .. }
             | TransportConnectError::ProxyProtocol => ErrorKind::InvalidData,
             TransportConnectError::DnsError => ErrorKind::NotFound,
             TransportConnectError::ClientAbort => ErrorKind::ConnectionAborted,
@@ -3,17 +3,20 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::convert::Infallible;
 use std::net::IpAddr;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
 use bytes::Bytes;
-use futures_util::FutureExt;
+use futures_util::{FutureExt, Stream, TryStreamExt as _};
 use http::response::Parts;
 use http::uri::PathAndQuery;
 use http::HeaderMap;
-use http_body_util::{BodyExt, Full, Limited};
-use hyper::client::conn::http2;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, BodyStream, Full, Limited, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::client::conn::{http1, http2};
 use hyper_util::rt::{TokioExecutor, TokioIo};
 
 use crate::errors::{LogSafeDisplay, TransportConnectError};
@@ -21,7 +24,7 @@ use crate::route::{
     ConnectError, Connector, HttpRouteFragment, HttpsTlsRoute, NoDelay, TcpRoute,
     ThrottlingConnector, TlsRoute,
 };
-use crate::{AsyncDuplexStream, Connection, TransportInfo};
+use crate::{Alpn, AsyncDuplexStream, Connection, TransportInfo};
 
 #[derive(displaydoc::Display, Debug)]
 pub enum HttpError {
@@ -39,6 +42,8 @@ pub enum HttpError {
     FailedToReadContentOfKnownSize,
     /// Failed while reading response body with the unknown size
     FailedToReadContentOfUnknownSize,
+    /// Failed while streaming response body
+    FailedToStreamContent,
     /// Content larger than max size configured for the client
     ResponseTooLarge,
 }
@@ -88,36 +93,260 @@ impl AggregatingHttp2Client {
             .map_err(|_| HttpError::SendRequestError)?;
 
         let (parts, body) = res.into_parts();
+        let content = collect_response_body(&parts, body, self.max_response_size).await?;
 
-        let content_length = parts
-            .headers
-            .get(hyper::header::CONTENT_LENGTH)
-            .map(|c| {
-                c.to_str()
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .ok_or(HttpError::ContentLengthHeaderInvalid)
-            })
-            .transpose()?;
+        Ok((parts, content))
+    }
+}
+
+/// Body type for requests sent over an [`HttpConnection`].
+pub type RequestBody = UnsyncBoxBody<Bytes, std::io::Error>;
+
+/// Body type for responses received over an [`HttpConnection`].
+///
+/// The body is streamed from the server as it is read.
+pub type ResponseBody = Incoming;
+
+/// Creates a [`RequestBody`] with contents that are already in memory.
+pub fn request_body_from_bytes(body: impl Into<Bytes>) -> RequestBody {
+    Full::new(body.into())
+        .map_err(|e: Infallible| match e {})
+        .boxed_unsync()
+}
+
+/// Creates a [`RequestBody`] that streams its contents from `chunks`.
+pub fn request_body_from_stream(
+    chunks: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+) -> RequestBody {
+    StreamBody::new(chunks.map_ok(Frame::data)).boxed_unsync()
+}
+
+/// Streams the data in a response body as it arrives from the server.
+pub fn response_body_data(
+    body: ResponseBody,
+) -> impl Stream<Item = Result<Bytes, HttpError>> + Send {
+    BodyStream::new(body)
+        .map_err(|_: hyper::Error| HttpError::FailedToStreamContent)
+        .try_filter_map(|frame| std::future::ready(Ok(frame.into_data().ok())))
+}
+
+/// Reads a response body into memory, failing if it exceeds `max_size` bytes.
+pub async fn collect_response_body(
+    parts: &Parts,
+    body: ResponseBody,
+    max_size: usize,
+) -> Result<Bytes, HttpError> {
+    let content_length = parts
+        .headers
+        .get(hyper::header::CONTENT_LENGTH)
+        .map(|c| {
+            c.to_str()
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or(HttpError::ContentLengthHeaderInvalid)
+        })
+        .transpose()?;
+
+    let content = match content_length {
+        Some(content_length) if content_length > max_size => {
+            return Err(HttpError::ResponseTooLarge)
+        }
+        Some(content_length) => Limited::new(body, content_length)
+            .collect()
+            .await
+            .map_err(|_| HttpError::FailedToReadContentOfKnownSize)?,
+        _ => Limited::new(body, max_size)
+            .collect()
+            .await
+            .map_err(|_| HttpError::FailedToReadContentOfUnknownSize)?,
+    }
+    .to_bytes();
+
+    Ok(content)
+}
+
+/// An established HTTP/1.1 or HTTP/2 connection to a single host.
+///
+/// Requests can be sent concurrently. Over HTTP/2 they are multiplexed; over
+/// HTTP/1.1 they are queued and sent one after another.
+pub struct HttpConnection {
+    sender: HttpSender,
+    host_header: Arc<str>,
+    path_prefix: Arc<str>,
+    info: TransportInfo,
+}
+
+enum HttpSender {
+    Http1(tokio::sync::Mutex<http1::SendRequest<RequestBody>>),
+    Http2(http2::SendRequest<RequestBody>),
+}
+
+/// [`Connector`] that establishes an [`HttpConnection`] over a transport
+/// provided by the wrapped connector.
+///
+/// The HTTP version is chosen by the ALPN of the route's TLS fragment: HTTP/2
+/// if the route asks for it, HTTP/1.1 otherwise.
+#[derive(Debug, Default)]
+pub struct HttpConnector<C>(pub C);
+
+#[derive(Debug, derive_more::From, displaydoc::Display)]
+pub enum HttpConnectError {
+    /// {0}
+    Transport(#[from] TransportConnectError),
+    /// HTTP handshake failed
+    HttpHandshake,
+}
 
-        let content = match content_length {
-            Some(content_length) if content_length > self.max_response_size => {
-                return Err(HttpError::ResponseTooLarge)
+impl LogSafeDisplay for HttpConnectError {}
+
+impl HttpConnection {
+    /// Sends a request and waits for the response headers.
+    ///
+    /// `path_and_query` is appended to the path prefix of the route the
+    /// connection was established over. The response body is not read.
+    pub async fn send_request(
+        &self,
+        path_and_query: PathAndQuery,
+        method: http::Method,
+        headers: HeaderMap,
+        body: RequestBody,
+    ) -> Result<http::Response<ResponseBody>, HttpError> {
+        let Self {
+            sender,
+            host_header,
+            path_prefix,
+            info: _,
+        } = self;
+
+        let request_builder = match sender {
+            HttpSender::Http1(_) => http::Request::builder()
+                .uri(format!("{path_prefix}{path_and_query}"))
+                .header(http::header::HOST, &**host_header)
+                .version(http::Version::HTTP_11),
+            HttpSender::Http2(_) => http::Request::builder()
+                .uri(format!(
+                    "https://{host_header}{path_prefix}{path_and_query}"
+                ))
+                .version(http::Version::HTTP_2),
+        };
+        let mut request_builder = request_builder.method(method);
+        request_builder
+            .headers_mut()
+            // This can fail if the builder is invalid.
+            .ok_or(HttpError::FailedToCreateRequest)?
+            .extend(headers);
+        let request = request_builder
+            .body(body)
+            .map_err(|_| HttpError::FailedToCreateRequest)?;
+
+        let response = match sender {
+            HttpSender::Http1(sender) => {
+                // HTTP/1.1 connections can only handle one request at a time,
+                // so wait for any earlier request to finish.
+                let mut sender = sender.lock().await;
+                sender
+                    .ready()
+                    .await
+                    .map_err(|_| HttpError::SendRequestError)?;
+                sender.send_request(request).await
             }
-            Some(content_length) => Limited::new(body, content_length)
-                .collect()
+            HttpSender::Http2(sender) => sender.clone().send_request(request).await,
+        };
+        response.map_err(|_| HttpError::SendRequestError)
+    }
+
+    /// The host that requests are addressed to.
+    ///
+    /// For a domain-fronted route this is the real host, not the front.
+    pub fn host(&self) -> &str {
+        &self.host_header
+    }
+}
+
+impl Connection for HttpConnection {
+    fn transport_info(&self) -> TransportInfo {
+        self.info.clone()
+    }
+}
+
+impl<C, T, Inner> Connector<HttpsTlsRoute<TlsRoute<T>>, Inner> for HttpConnector<C>
+where
+    C: Connector<
+            TlsRoute<T>,
+            Inner,
+            Connection: Connection + AsyncDuplexStream + 'static,
+            Error = TransportConnectError,
+        > + Sync,
+    Inner: Send,
+    T: Send,
+{
+    type Connection = HttpConnection;
+
+    type Error = HttpConnectError;
+
+    async fn connect_over(
+        &self,
+        over: Inner,
+        route: HttpsTlsRoute<TlsRoute<T>>,
+        log_tag: Arc<str>,
+    ) -> Result<Self::Connection, Self::Error> {
+        let HttpsTlsRoute {
+            fragment:
+                HttpRouteFragment {
+                    host_header,
+                    path_prefix,
+                    front_name: _,
+                },
+            inner: tls_route,
+        } = route;
+        let use_http2 = tls_route.fragment.alpn == Some(Alpn::Http2);
+
+        let stream = self
+            .0
+            .connect_over(over, tls_route, log_tag.clone())
+            .await?;
+        let info = stream.transport_info();
+        let io = TokioIo::new(stream);
+
+        let TransportInfo { ip_version, .. } = info;
+        let sender = if use_http2 {
+            let (sender, connection) = http2::handshake(TokioExecutor::new(), io)
                 .await
-                .map_err(|_| HttpError::FailedToReadContentOfKnownSize)?,
-            _ => Limited::new(body, self.max_response_size)
-                .collect()
+                .map_err(|_: hyper::Error| HttpConnectError::HttpHandshake)?;
+            tokio::spawn(drive_connection(connection, "HTTP2", ip_version, log_tag));
+            HttpSender::Http2(sender)
+        } else {
+            let (sender, connection) = http1::handshake(io)
                 .await
-                .map_err(|_| HttpError::FailedToReadContentOfUnknownSize)?,
-        }
-        .to_bytes();
+                .map_err(|_: hyper::Error| HttpConnectError::HttpHandshake)?;
+            tokio::spawn(drive_connection(connection, "HTTP1", ip_version, log_tag));
+            HttpSender::Http1(sender.into())
+        };
 
-        Ok((parts, content))
+        Ok(HttpConnection {
+            sender,
+            host_header,
+            path_prefix,
+            info,
+        })
+    }
+}
+
+/// Drives client connection events until the connection is closed.
+///
+/// The connection closes due to an error or when all senders are dropped.
+async fn drive_connection(
+    connection: impl std::future::Future<Output = hyper::Result<()>>,
+    protocol: &'static str,
+    ip_version: crate::IpType,
+    log_tag: Arc<str>,
+) {
+    match connection.await {
+        Ok(()) => log::info!("[{log_tag}] {protocol} connection [{ip_version}] closed"),
+        Err(err) => log::warn!("[{log_tag}] {protocol} connection [{ip_version}] failed: {err}"),
     }
 }
+
 struct StatelessHttp2Connector<C>(C);
 
 struct CompletedH2Connection {
@@ -126,12 +355,6 @@ struct CompletedH2Connection {
     path_prefix: Arc<str>,
 }
 
-#[derive(derive_more::From)]
-enum HttpConnectError {
-    Transport(#[from] TransportConnectError),
-    HttpHandshake,
-}
-
 impl<T, C, Inner> Connector<HttpsTlsRoute<T>, Inner> for StatelessHttp2Connector<C>
 where
     C: Connector<
@@ -173,18 +396,8 @@ where
             .await
             .map_err(|_: hyper::Error| HttpConnectError::HttpHandshake)?;
 
-        // Starting a thread to drive client connection events.
-        // The task will complete once the connection is closed due to an error
-        // or if all clients are dropped.
         let TransportInfo { ip_version, .. } = info;
-        tokio::spawn(async move {
-            match connection.await {
-                Ok(_) => log::info!("[{log_tag}] HTTP2 connection [{ip_version}] closed"),
-                Err(err) => {
-                    log::warn!("[{log_tag}] HTTP2 connection [{ip_version}] failed: {err}",)
-                }
-            }
-        });
+        tokio::spawn(drive_connection(connection, "HTTP2", ip_version, log_tag));
 
         Ok(CompletedH2Connection {
             sender,
@@ -251,6 +464,7 @@ mod test {
 
     use assert_matches::assert_matches;
     use http::{HeaderName, HeaderValue, Method, StatusCode};
+    use test_case::test_case;
     use warp::Filter as _;
 
     use super::*;
@@ -324,6 +538,8 @@ mod test {
                             SERVER_CERTIFICATE.cert.der(),
                         )),
                         alpn: None,
+                        ech_config_list: None,
+                        pin_set: None,
                     },
                     inner: TcpRoute {
                         address: Ipv6Addr::LOCALHOST.into(),
@@ -399,6 +615,8 @@ mod test {
                             SERVER_CERTIFICATE.cert.der(),
                         )),
                         alpn: None,
+                        ech_config_list: None,
+                        pin_set: None,
                     },
                     inner: TcpRoute {
                         address: Ipv6Addr::LOCALHOST.into(),
@@ -426,4 +644,77 @@ mod test {
 
         assert_matches!(result, Err(HttpError::FailedToCreateRequest));
     }
+
+    #[test_case(Alpn::Http1_1, warp::http::Version::HTTP_11; "http1")]
+    #[test_case(Alpn::Http2, warp::http::Version::HTTP_2; "http2")]
+    #[tokio::test]
+    async fn http_connector_streams_request_body(
+        alpn: Alpn,
+        expected_version: warp::http::Version,
+    ) {
+        let _ = env_logger::try_init();
+        let (request_info_send, request_info_recv) = std::sync::mpsc::channel();
+
+        let (server_addr, server) = localhost_https_server_with_fake_response(request_info_send);
+        tokio::spawn(server);
+
+        let connector = HttpConnector(
+            crate::route::ComposedConnector::<_, _, TransportConnectError>::new(
+                crate::tcp_ssl::StatelessDirect,
+                crate::tcp_ssl::StatelessDirect,
+            ),
+        );
+        let connection = connector
+            .connect_over(
+                (),
+                HttpsTlsRoute {
+                    fragment: HttpRouteFragment {
+                        host_header: "test-host".into(),
+                        path_prefix: "/prefix".into(),
+                        front_name: None,
+                    },
+                    inner: TlsRoute {
+                        fragment: TlsRouteFragment {
+                            sni: Host::Domain(SERVER_HOSTNAME.into()),
+                            root_certs: crate::certs::RootCertificates::FromDer(Cow::Borrowed(
+                                SERVER_CERTIFICATE.cert.der(),
+                            )),
+                            alpn: Some(alpn),
+                            ech_config_list: None,
+                            pin_set: None,
+                        },
+                        inner: TcpRoute {
+                            address: Ipv6Addr::LOCALHOST.into(),
+                            port: NonZeroU16::new(server_addr.port()).unwrap(),
+                        },
+                    },
+                },
+                "test".into(),
+            )
+            .await
+            .expect("can connect");
+
+        let chunks = futures_util::stream::iter(["first ", "second"].map(|chunk| Ok(chunk.into())));
+        let response = connection
+            .send_request(
+                "/request/path".parse().unwrap(),
+                Method::PUT,
+                HeaderMap::new(),
+                request_body_from_stream(chunks),
+            )
+            .await
+            .expect("request should succeed");
+
+        let last_request = request_info_recv.recv().unwrap();
+        assert_eq!(last_request.version, expected_version);
+        assert_eq!(last_request.method, warp::http::Method::PUT);
+        assert_eq!(last_request.path.as_str(), "/prefix/request/path");
+
+        let (parts, body) = response.into_parts();
+        assert_eq!(parts.status, StatusCode::OK);
+        let body = collect_response_body(&parts, body, MAX_RESPONSE_SIZE)
+            .await
+            .expect("can read body");
+        assert_eq!(body, FAKE_RESPONSE);
+    }
 }
@@ -16,7 +16,7 @@ use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use crate::certs::RootCertificates;
+use crate::certs::{RootCertificates, SpkiPinSet};
 use crate::connection_manager::{
     MultiRouteConnectionManager, SingleRouteThrottlingConnectionManager,
 };
@@ -164,6 +164,8 @@ pub struct TransportConnectionParams {
     pub port: NonZeroU16,
     /// Trusted certificates for this connection.
     pub certs: RootCertificates,
+    /// Public keys the server's certificate chain must include, if any.
+    pub pin_set: Option<SpkiPinSet>,
 }
 
 #[derive(Debug, Clone)]
@@ -201,7 +203,7 @@ pub trait Connection {
 }
 
 /// Source for the result of a hostname lookup.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, strum::Display)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, strum::Display, strum::EnumString)]
 #[strum(serialize_all = "lowercase")]
 pub enum DnsSource {
     /// The result was returned from the cache
@@ -334,6 +336,17 @@ pub enum Alpn {
     Http2,
 }
 
+impl Alpn {
+    /// Parses a protocol ID (without the length prefix), e.g. `h2`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "http/1.1" => Some(Alpn::Http1_1),
+            "h2" => Some(Alpn::Http2),
+            _ => None,
+        }
+    }
+}
+
 impl AsRef<[u8]> for Alpn {
     fn as_ref(&self) -> &[u8] {
         match self {
@@ -14,6 +14,8 @@ use futures_util::{Sink, SinkExt as _, StreamExt as _};
 use snow::TransportState;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
+use crate::{Connection, TransportInfo};
+
 /// Stream abstraction that encrypts/decrypts with [Noise].
 ///
 /// Implements [`AsyncRead`] and [`AsyncWrite`] over a block-based [`Transport`]
@@ -21,6 +23,7 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 /// [`snow::TransportState`] instance to encrypt and decrypt.
 ///
 /// [Noise]: https://noiseprotocol.org/noise.html
+#[derive(Debug)]
 pub struct NoiseStream<S> {
     inner: S,
     transport: ClientConnection,
@@ -62,6 +65,12 @@ impl<S> NoiseStream<S> {
     }
 }
 
+impl<S: Connection> Connection for NoiseStream<S> {
+    fn transport_info(&self) -> TransportInfo {
+        self.inner.transport_info()
+    }
+}
+
 /// Convenience alias for types that implement [`FusedStream`] and [`Sink`] for
 /// `NoiseStream` to wrap.
 ///
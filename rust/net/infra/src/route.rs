@@ -33,6 +33,9 @@ pub use crate::route::provider::RouteProviderExt;
 mod proxy;
 pub use proxy::*;
 
+mod proxy_selection;
+pub use proxy_selection::*;
+
 mod resolve;
 pub use resolve::*;
 
@@ -539,7 +542,8 @@ mod test {
     use crate::host::Host;
     use crate::route::resolve::testutils::FakeResolver;
     use crate::route::testutils::{FakeContext, FakeRoute};
-    use crate::route::{NoDelay, SocksProxy, TlsProxy};
+    use crate::route::{NoDelay, PluggableTransportProxy, SocksProxy, TlsProxy};
+    use crate::tcp_ssl::proxy::pluggable::{SharedPluggableTransport, WebSocketTunnel};
     use crate::tcp_ssl::proxy::socks;
     use crate::{Alpn, DnsSource};
 
@@ -557,6 +561,7 @@ mod test {
                 ws_config: WebSocketConfig::default(),
                 headers: HeaderMap::default(),
                 endpoint: WS_ENDPOINT.clone(),
+                deflate: None,
             },
             inner: HttpsProvider {
                 direct_host_header: "http-host".into(),
@@ -579,6 +584,7 @@ mod test {
                         dns_hostname: "target-host".into(),
                         port: TARGET_PORT,
                     },
+                    pin_set: None,
                 },
             },
         };
@@ -591,6 +597,7 @@ mod test {
                     ws_config: WebSocketConfig::default(),
                     headers: HeaderMap::default(),
                     endpoint: WS_ENDPOINT.clone(),
+                    deflate: None,
                 },
                 inner: HttpsTlsRoute {
                     fragment: HttpRouteFragment {
@@ -603,6 +610,8 @@ mod test {
                             root_certs: ROOT_CERTS.clone(),
                             sni: Host::Domain("sni-name".into()),
                             alpn: Some(Alpn::Http1_1),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("target-host".into()),
@@ -616,6 +625,7 @@ mod test {
                     ws_config: WebSocketConfig::default(),
                     headers: HeaderMap::default(),
                     endpoint: WS_ENDPOINT.clone(),
+                    deflate: None,
                 },
                 inner: HttpsTlsRoute {
                     fragment: HttpRouteFragment {
@@ -628,6 +638,8 @@ mod test {
                             root_certs: PROXY_ROOT_CERTS,
                             sni: Host::Domain("front-sni1".into()),
                             alpn: Some(Alpn::Http2),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni1".into()),
@@ -641,6 +653,7 @@ mod test {
                     ws_config: WebSocketConfig::default(),
                     headers: HeaderMap::default(),
                     endpoint: WS_ENDPOINT.clone(),
+                    deflate: None,
                 },
                 inner: HttpsTlsRoute {
                     fragment: HttpRouteFragment {
@@ -653,6 +666,8 @@ mod test {
                             root_certs: PROXY_ROOT_CERTS,
                             sni: Host::Domain("front-sni2".into()),
                             alpn: Some(Alpn::Http2),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni2".into()),
@@ -679,6 +694,7 @@ mod test {
                 dns_hostname: "direct-target".into(),
                 port: TARGET_PORT,
             },
+            pin_set: None,
         };
 
         let provider = ConnectionProxyRouteProvider {
@@ -700,6 +716,8 @@ mod test {
                     root_certs: ROOT_CERTS.clone(),
                     sni: Host::Domain("direct-sni".into()),
                     alpn: None,
+                    ech_config_list: None,
+                    pin_set: None,
                 },
                 inner: ConnectionProxyRoute::Tls {
                     proxy: TlsRoute {
@@ -711,6 +729,8 @@ mod test {
                             root_certs: PROXY_CERTS.clone(),
                             sni: Host::Domain("tls-proxy".into()),
                             alpn: None,
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                     },
                 },
@@ -733,6 +753,7 @@ mod test {
                 dns_hostname: "direct-target".into(),
                 port: TARGET_PORT,
             },
+            pin_set: None,
         };
 
         let provider = ConnectionProxyRouteProvider {
@@ -753,6 +774,8 @@ mod test {
                 root_certs: ROOT_CERTS.clone(),
                 sni: Host::Domain("direct-sni".into()),
                 alpn: None,
+                ech_config_list: None,
+                pin_set: None,
             },
             inner: ConnectionProxyRoute::Socks(SocksRoute {
                 proxy: TcpRoute {
@@ -769,6 +792,75 @@ mod test {
         assert_eq!(routes, expected_routes);
     }
 
+    #[test]
+    fn pluggable_transport_route() {
+        const TARGET_PORT: NonZeroU16 = nonzero!(7898u16);
+        const PROXY_PORT: NonZeroU16 = nonzero!(443u16);
+
+        let transport = SharedPluggableTransport::new(WebSocketTunnel {
+            host_header: "bridge-host".into(),
+            path: PathAndQuery::from_static("/tunnel"),
+            ws_config: WebSocketConfig::default(),
+        });
+
+        let direct_provider = TlsRouteProvider {
+            sni: Host::Domain("direct-sni".into()),
+            certs: ROOT_CERTS.clone(),
+            inner: DirectTcpRouteProvider {
+                dns_hostname: "direct-target".into(),
+                port: TARGET_PORT,
+            },
+            pin_set: None,
+        };
+
+        let provider = ConnectionProxyRouteProvider {
+            proxy: PluggableTransportProxy {
+                transport: transport.clone(),
+                proxy_host: Host::Domain("bridge".into()),
+                proxy_port: PROXY_PORT,
+                proxy_tls: Some(PROXY_ROOT_CERTS),
+                resolve_hostname_locally: false,
+            }
+            .into(),
+            inner: direct_provider,
+        };
+
+        let routes = provider.routes(&FakeContext::new()).collect_vec();
+
+        let expected_routes = vec![TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: ROOT_CERTS.clone(),
+                sni: Host::Domain("direct-sni".into()),
+                alpn: None,
+                ech_config_list: None,
+                pin_set: None,
+            },
+            inner: ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                fragment: PluggableTransportRouteFragment {
+                    transport,
+                    target_host: ProxyTarget::ResolvedRemotely {
+                        name: "direct-target".into(),
+                    },
+                    target_port: TARGET_PORT,
+                },
+                inner: either::Either::Left(TlsRoute {
+                    inner: TcpRoute {
+                        address: Host::Domain(UnresolvedHost("bridge".into())),
+                        port: PROXY_PORT,
+                    },
+                    fragment: TlsRouteFragment {
+                        root_certs: PROXY_ROOT_CERTS,
+                        sni: Host::Domain("bridge".into()),
+                        alpn: Some(Alpn::Http1_1),
+                        ech_config_list: None,
+                        pin_set: None,
+                    },
+                }),
+            }),
+        }];
+        assert_eq!(routes, expected_routes);
+    }
+
     #[test]
     fn connection_proxy_on_top_of_websocket_route_is_provider() {
         // Compilation-only test that makes sure we can wrap a fully-specified
@@ -1034,16 +1126,11 @@ mod test {
         let (connector, connection_responders) = FakeConnector::new();
 
         let outcomes = NoDelay;
-        let resolver = HashMap::from_iter(HOSTNAMES.iter().map(|(name, ip)| {
-            (
-                *name,
-                LookupResult {
-                    source: DnsSource::Test,
-                    ipv4: vec![*ip],
-                    ipv6: vec![],
-                },
-            )
-        }));
+        let resolver = HashMap::from_iter(
+            HOSTNAMES
+                .iter()
+                .map(|(name, ip)| (*name, LookupResult::new(DnsSource::Test, vec![*ip], vec![]))),
+        );
 
         let connect_task = tokio::spawn(async move {
             let route_resolver = RouteResolver::default();
@@ -1088,16 +1175,11 @@ mod test {
         let (connector, mut connection_responders) = FakeConnector::new();
 
         let outcomes = NoDelay;
-        let resolver = HashMap::from_iter(HOSTNAMES.iter().map(|(name, ip)| {
-            (
-                *name,
-                LookupResult {
-                    source: DnsSource::Test,
-                    ipv4: vec![*ip],
-                    ipv6: vec![],
-                },
-            )
-        }));
+        let resolver = HashMap::from_iter(
+            HOSTNAMES
+                .iter()
+                .map(|(name, ip)| (*name, LookupResult::new(DnsSource::Test, vec![*ip], vec![]))),
+        );
 
         let start = Instant::now();
         let connect_task = tokio::spawn(async move {
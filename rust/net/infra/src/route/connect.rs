@@ -22,6 +22,9 @@ use crate::route::{
 };
 use crate::ws::WebSocketConnectError;
 
+mod ech;
+pub use ech::*;
+
 mod throttle;
 pub use throttle::*;
 
@@ -105,8 +108,9 @@ pub type StatelessTransportConnector = TransportConnector;
 type TcpConnector = crate::tcp_ssl::StatelessDirect;
 type DirectProxyConnector =
     DirectOrProxy<TcpConnector, crate::tcp_ssl::proxy::StatelessProxied, TransportConnectError>;
-type TransportConnector =
-    ComposedConnector<crate::tcp_ssl::StatelessDirect, DirectProxyConnector, TransportConnectError>;
+type TransportConnector = EchFallbackConnector<
+    ComposedConnector<crate::tcp_ssl::StatelessDirect, DirectProxyConnector, TransportConnectError>,
+>;
 type WebSocketHttpConnector =
     ComposedConnector<crate::ws::Stateless, TransportConnector, WebSocketConnectError>;
 
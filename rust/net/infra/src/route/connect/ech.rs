@@ -0,0 +1,194 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::errors::TransportConnectError;
+use crate::route::connect::Connector;
+use crate::route::TlsRoute;
+
+/// [`Connector`] wrapper that retries a TLS connection once if the server
+/// rejects Encrypted Client Hello.
+///
+/// The retry uses the configs the server offered with its rejection, or sends
+/// the real SNI in the clear if it didn't offer any, as the ECH draft
+/// specifies. Either way the retry starts over from a fresh transport, since
+/// the first handshake has already failed.
+#[derive(Debug, Default)]
+pub struct EchFallbackConnector<C>(C);
+
+impl<C> EchFallbackConnector<C> {
+    pub fn new(inner: C) -> Self {
+        Self(inner)
+    }
+}
+
+impl<C, T> Connector<TlsRoute<T>, ()> for EchFallbackConnector<C>
+where
+    C: Connector<TlsRoute<T>, (), Error = TransportConnectError> + Sync,
+    T: Clone + Send,
+{
+    type Connection = C::Connection;
+
+    type Error = TransportConnectError;
+
+    fn connect_over(
+        &self,
+        (): (),
+        route: TlsRoute<T>,
+        log_tag: Arc<str>,
+    ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+        let Self(inner) = self;
+        let fallback = route
+            .fragment
+            .ech_config_list
+            .is_some()
+            .then(|| route.clone());
+        async move {
+            let result = inner.connect_over((), route, log_tag.clone()).await;
+            let Some(mut route) = fallback else {
+                return result;
+            };
+            let retry_configs = match result {
+                Err(TransportConnectError::EchRejected { retry_configs }) => retry_configs,
+                result => return result,
+            };
+            log::info!(
+                "[{log_tag}] server rejected ECH; retrying {}",
+                if retry_configs.is_some() {
+                    "with its new configs"
+                } else {
+                    "without ECH"
+                }
+            );
+            route.fragment.ech_config_list = retry_configs;
+            inner.connect_over((), route, log_tag).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use assert_matches::assert_matches;
+    use futures_util::FutureExt as _;
+
+    use super::*;
+    use crate::certs::RootCertificates;
+    use crate::host::Host;
+    use crate::route::{EchConfigList, TlsRouteFragment};
+
+    /// Records the ECH configs of each attempt and fails the ones listed in
+    /// `rejections`, in order.
+    struct FakeConnector {
+        attempts: Mutex<Vec<Option<EchConfigList>>>,
+        rejections: Mutex<Vec<Option<EchConfigList>>>,
+    }
+
+    impl FakeConnector {
+        fn rejecting(rejections: impl IntoIterator<Item = Option<EchConfigList>>) -> Self {
+            Self {
+                attempts: Default::default(),
+                rejections: Mutex::new(rejections.into_iter().collect()),
+            }
+        }
+    }
+
+    impl Connector<TlsRoute<()>, ()> for FakeConnector {
+        type Connection = ();
+
+        type Error = TransportConnectError;
+
+        fn connect_over(
+            &self,
+            (): (),
+            route: TlsRoute<()>,
+            _log_tag: Arc<str>,
+        ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+            self.attempts
+                .lock()
+                .expect("not poisoned")
+                .push(route.fragment.ech_config_list);
+            let mut rejections = self.rejections.lock().expect("not poisoned");
+            let result = if rejections.is_empty() {
+                Ok(())
+            } else {
+                Err(TransportConnectError::EchRejected {
+                    retry_configs: rejections.remove(0),
+                })
+            };
+            std::future::ready(result)
+        }
+    }
+
+    fn route(ech_config_list: Option<EchConfigList>) -> TlsRoute<()> {
+        TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::Native,
+                sni: Host::Domain("chat.signal.org".into()),
+                alpn: None,
+                ech_config_list,
+                pin_set: None,
+            },
+            inner: (),
+        }
+    }
+
+    fn connect(
+        connector: &EchFallbackConnector<FakeConnector>,
+        route: TlsRoute<()>,
+    ) -> Result<(), TransportConnectError> {
+        connector
+            .connect_over((), route, "test".into())
+            .now_or_never()
+            .expect("ready")
+    }
+
+    #[test]
+    fn retries_with_server_retry_configs() {
+        let original = EchConfigList::new([1; 4].as_slice());
+        let retry = EchConfigList::new([2; 4].as_slice());
+        let connector = EchFallbackConnector::new(FakeConnector::rejecting([Some(retry.clone())]));
+
+        assert_matches!(connect(&connector, route(Some(original.clone()))), Ok(()));
+        assert_eq!(
+            *connector.0.attempts.lock().expect("not poisoned"),
+            [Some(original), Some(retry)]
+        );
+    }
+
+    #[test]
+    fn retries_without_ech_if_server_offers_no_configs() {
+        let original = EchConfigList::new([1; 4].as_slice());
+        let connector = EchFallbackConnector::new(FakeConnector::rejecting([None]));
+
+        assert_matches!(connect(&connector, route(Some(original.clone()))), Ok(()));
+        assert_eq!(
+            *connector.0.attempts.lock().expect("not poisoned"),
+            [Some(original), None]
+        );
+    }
+
+    #[test]
+    fn retries_only_once() {
+        let original = EchConfigList::new([1; 4].as_slice());
+        let retry = EchConfigList::new([2; 4].as_slice());
+        let connector = EchFallbackConnector::new(FakeConnector::rejecting([
+            Some(retry.clone()),
+            Some(EchConfigList::new([3; 4].as_slice())),
+        ]));
+
+        assert_matches!(
+            connect(&connector, route(Some(original.clone()))),
+            Err(TransportConnectError::EchRejected { .. })
+        );
+        assert_eq!(
+            *connector.0.attempts.lock().expect("not poisoned"),
+            [Some(original), Some(retry)]
+        );
+    }
+}
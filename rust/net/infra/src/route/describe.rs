@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::collections::HashMap;
 use std::future::Future;
 use std::net::IpAddr;
 use std::num::NonZeroU16;
@@ -13,12 +14,14 @@ use nonzero_ext::nonzero;
 use tokio::time::Instant;
 
 use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::service_binding::ServiceBinding;
 use crate::errors::LogSafeDisplay;
 use crate::host::Host;
 use crate::route::{
     ConnectionProxyKind, ConnectionProxyRoute, Connector, DirectOrProxyRoute,
-    HttpProxyRouteFragment, HttpsProxyRoute, HttpsTlsRoute, ProxyTarget, ResolveHostnames,
-    ResolvedRoute, RouteDelayPolicy, SocksRoute, TcpRoute, TlsRoute, UnresolvedHost,
+    HttpProxyRouteFragment, HttpsProxyRoute, PluggableTransportRoute,
+    PluggableTransportRouteFragment, ProxyTarget, ResolveHostnames, ResolvedRoute,
+    RouteDelayPolicy, SocksRoute, TcpRoute, TlsRoute, UnresolvedHost, UnresolvedHttpsServiceRoute,
     UnresolvedWebsocketServiceRoute, DEFAULT_HTTPS_PORT,
 };
 
@@ -72,14 +75,22 @@ impl<P: RouteDelayPolicy<R>, R, D> RouteDelayPolicy<WithLoggableDescription<R, D
     }
 }
 
-/// Loggable description for a [`UnresolvedWebsocketServiceRoute`].
+/// Loggable description for a [`UnresolvedWebsocketServiceRoute`] or
+/// [`UnresolvedHttpsServiceRoute`].
 #[derive(Clone, Debug, PartialEq)]
 pub struct UnresolvedRouteDescription {
     front: Option<&'static str>,
     proxy: Option<ConnectionProxyKind>,
+    transport: Option<PluggableTransportDescription>,
     target: (Host<Arc<str>>, NonZeroU16),
 }
 
+/// Loggable description for a [`PluggableTransportRoute`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluggableTransportDescription {
+    name: &'static str,
+}
+
 impl<R: ResolveHostnames + DescribeForLog> ResolveHostnames for ResolveWithSavedDescription<R> {
     type Resolved = WithLoggableDescription<R::Resolved, R::Description>;
 
@@ -95,6 +106,10 @@ impl<R: ResolveHostnames + DescribeForLog> ResolveHostnames for ResolveWithSaved
             description,
         }
     }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        self.0.apply_service_bindings(bindings)
+    }
 }
 
 impl<R: ResolvedRoute, D> ResolvedRoute for WithLoggableDescription<R, D> {
@@ -128,6 +143,7 @@ impl std::fmt::Display for UnresolvedRouteDescription {
         let Self {
             front,
             proxy,
+            transport,
             target: (domain, port),
         } = self;
         write!(
@@ -140,18 +156,63 @@ impl std::fmt::Display for UnresolvedRouteDescription {
         } else {
             f.write_str(" (direct)")
         }?;
-        if let Some(proxy_kind) = proxy {
-            write!(f, " through {proxy_kind:?} proxy")?
+        match (transport, proxy) {
+            (Some(transport), _) => write!(f, " through {transport}")?,
+            (None, Some(proxy_kind)) => write!(f, " through {proxy_kind:?} proxy")?,
+            (None, None) => {}
         }
         Ok(())
     }
 }
 
+impl LogSafeDisplay for PluggableTransportDescription {}
+impl std::fmt::Display for PluggableTransportDescription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} pluggable transport", self.name)
+    }
+}
+
 impl UnresolvedRouteDescription {
+    /// Returns an identifier for the route that is stable across restarts.
+    ///
+    /// Unlike the [`Display`](std::fmt::Display) output this includes the
+    /// full target hostname, so it must not be logged.
+    pub fn persistent_key(&self) -> String {
+        let Self {
+            front,
+            proxy,
+            transport,
+            target: (host, port),
+        } = self;
+        let mut key = format!("{host}:{port}");
+        if let Some(front) = front {
+            key.push_str(" front=");
+            key.push_str(front);
+        }
+        if let Some(proxy_kind) = proxy {
+            // Spelled out rather than using Debug so that renaming a variant
+            // doesn't orphan saved history.
+            key.push_str(" proxy=");
+            key.push_str(match proxy_kind {
+                ConnectionProxyKind::Tls => "tls",
+                ConnectionProxyKind::Tcp => "tcp",
+                ConnectionProxyKind::Socks => "socks",
+                ConnectionProxyKind::Https => "https",
+                ConnectionProxyKind::Pluggable => "pluggable",
+            });
+        }
+        if let Some(PluggableTransportDescription { name }) = transport {
+            key.push_str(" transport=");
+            key.push_str(name);
+        }
+        key
+    }
+
     pub fn fake() -> Self {
         Self {
             front: None,
             proxy: None,
+            transport: None,
             target: (
                 Host::Domain("local-test.signal.org".into()),
                 nonzero!(443u16),
@@ -166,14 +227,22 @@ impl DescribeForLog for UnresolvedWebsocketServiceRoute {
     fn describe_for_log(&self) -> Self::Description {
         let Self {
             fragment: _ws_fragment,
+            inner: https_route,
+        } = self;
+        https_route.describe_for_log()
+    }
+}
+
+impl DescribeForLog for UnresolvedHttpsServiceRoute {
+    type Description = UnresolvedRouteDescription;
+
+    fn describe_for_log(&self) -> Self::Description {
+        let Self {
+            fragment: http_fragment,
             inner:
-                HttpsTlsRoute {
-                    fragment: http_fragment,
-                    inner:
-                        TlsRoute {
-                            fragment: tls_fragment,
-                            inner: direct_or_proxy,
-                        },
+                TlsRoute {
+                    fragment: tls_fragment,
+                    inner: direct_or_proxy,
                 },
         } = self;
 
@@ -200,6 +269,15 @@ impl DescribeForLog for UnresolvedWebsocketServiceRoute {
                         },
                     inner: _,
                 }) => (target_host.as_informational_host(), *target_port),
+                ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                    fragment:
+                        PluggableTransportRouteFragment {
+                            target_host,
+                            target_port,
+                            ..
+                        },
+                    inner: _,
+                }) => (target_host.as_informational_host(), *target_port),
             },
         };
 
@@ -207,16 +285,33 @@ impl DescribeForLog for UnresolvedWebsocketServiceRoute {
             DirectOrProxyRoute::Direct(_) => None,
             DirectOrProxyRoute::Proxy(proxy) => Some(ConnectionProxyKind::from(proxy)),
         };
+        let transport = match &direct_or_proxy {
+            DirectOrProxyRoute::Proxy(ConnectionProxyRoute::Pluggable(pluggable)) => {
+                Some(pluggable.describe_for_log())
+            }
+            DirectOrProxyRoute::Direct(_) | DirectOrProxyRoute::Proxy(_) => None,
+        };
         let front = http_fragment.front_name;
 
         UnresolvedRouteDescription {
             front,
             proxy,
+            transport,
             target,
         }
     }
 }
 
+impl<A> DescribeForLog for PluggableTransportRoute<A> {
+    type Description = PluggableTransportDescription;
+
+    fn describe_for_log(&self) -> Self::Description {
+        PluggableTransportDescription {
+            name: self.fragment.transport.name(),
+        }
+    }
+}
+
 impl ProxyTarget<Host<UnresolvedHost>> {
     /// Returns a [`Host`] suitable for informational purposes.
     ///
@@ -229,3 +324,22 @@ impl ProxyTarget<Host<UnresolvedHost>> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn persistent_key_is_stable() {
+        let description = UnresolvedRouteDescription {
+            front: Some("front"),
+            proxy: Some(ConnectionProxyKind::Socks),
+            transport: None,
+            target: (Host::Domain("chat.signal.org".into()), nonzero!(443u16)),
+        };
+        assert_eq!(
+            description.persistent_key(),
+            "chat.signal.org:443 front=front proxy=socks"
+        );
+    }
+}
@@ -135,6 +135,8 @@ impl RouteProvider for DomainFrontRouteProvider {
                             root_certs: root_certs.clone(),
                             sni: Host::Domain(Arc::clone(sni)),
                             alpn: Some((*http_version).into()),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                     },
                     fragment: HttpRouteFragment {
@@ -260,6 +262,7 @@ mod test {
                     dns_hostname: "direct-tcp-host".into(),
                     port: DIRECT_TCP_PORT,
                 },
+                pin_set: None,
             },
         };
 
@@ -278,7 +281,9 @@ mod test {
                         fragment: TlsRouteFragment {
                             root_certs: RootCertificates::Native,
                             sni: Host::Domain("direct-host".into()),
-                            alpn: Some(Alpn::Http2)
+                            alpn: Some(Alpn::Http2),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("direct-tcp-host".into()),
@@ -296,7 +301,9 @@ mod test {
                         fragment: TlsRouteFragment {
                             root_certs: RootCertificates::Native,
                             sni: Host::Domain("front-sni-1a".into()),
-                            alpn: Some(Alpn::Http1_1)
+                            alpn: Some(Alpn::Http1_1),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-1a".into()),
@@ -314,7 +321,9 @@ mod test {
                         fragment: TlsRouteFragment {
                             root_certs: RootCertificates::Native,
                             sni: Host::Domain("front-sni-1b".into()),
-                            alpn: Some(Alpn::Http1_1)
+                            alpn: Some(Alpn::Http1_1),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-1b".into()),
@@ -332,7 +341,9 @@ mod test {
                         fragment: TlsRouteFragment {
                             root_certs: RootCertificates::Native,
                             sni: Host::Domain("front-sni-2b".into()),
-                            alpn: Some(Alpn::Http1_1)
+                            alpn: Some(Alpn::Http1_1),
+                            ech_config_list: None,
+                            pin_set: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-2b".into()),
@@ -16,6 +16,7 @@ use crate::route::{
     ReplaceFragment, RouteProvider, RouteProviderContext, SimpleRoute, TcpRoute, TlsRoute,
     TlsRouteFragment, UnresolvedHost,
 };
+use crate::tcp_ssl::proxy::pluggable::SharedPluggableTransport;
 use crate::tcp_ssl::proxy::socks;
 use crate::Alpn;
 
@@ -45,6 +46,25 @@ pub struct HttpProxyRouteFragment<Addr> {
     pub authorization: Option<HttpProxyAuth>,
 }
 
+/// Route for connecting through a
+/// [`PluggableTransport`](crate::tcp_ssl::proxy::pluggable::PluggableTransport)
+/// bridge.
+pub type PluggableTransportRoute<Addr> = SimpleRoute<
+    PluggableTransportRouteFragment<Addr>,
+    Either<TlsRoute<TcpRoute<Addr>>, TcpRoute<Addr>>,
+>;
+
+/// The transport to use over the connection to a bridge, and where the bridge
+/// should relay to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PluggableTransportRouteFragment<Addr> {
+    pub transport: SharedPluggableTransport,
+    /// The address to pass to the bridge as the target.
+    pub target_host: ProxyTarget<Addr>,
+    /// The port on the target (to pass to the bridge).
+    pub target_port: NonZeroU16,
+}
+
 /// Username and password to pass to an HTTP proxy.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct HttpProxyAuth {
@@ -64,6 +84,7 @@ pub enum ConnectionProxyRoute<Addr> {
     },
     Socks(SocksRoute<Addr>),
     Https(HttpsProxyRoute<Addr>),
+    Pluggable(PluggableTransportRoute<Addr>),
 }
 
 /// Target address for proxy protocols that support remote resolution.
@@ -127,12 +148,31 @@ pub struct HttpProxy {
     pub resolve_hostname_locally: bool,
 }
 
+/// A bridge reached through a
+/// [`PluggableTransport`](crate::tcp_ssl::proxy::pluggable::PluggableTransport).
+///
+/// Pluggable transports are configured in code rather than from a URL, so
+/// there is no corresponding scheme for [`ConnectionProxyConfig::from_parts`].
+#[derive(Debug, Clone)]
+pub struct PluggableTransportProxy {
+    pub transport: SharedPluggableTransport,
+    pub proxy_host: Host<Arc<str>>,
+    pub proxy_port: NonZeroU16,
+    /// Certificates to verify the bridge with, or `None` for plain TCP.
+    ///
+    /// Must be set for transports that [require
+    /// TLS](crate::tcp_ssl::proxy::pluggable::PluggableTransport::requires_tls).
+    pub proxy_tls: Option<RootCertificates>,
+    pub resolve_hostname_locally: bool,
+}
+
 #[derive(Debug, Clone, derive_more::From)]
 pub enum ConnectionProxyConfig {
     Tls(TlsProxy),
     Tcp(TcpProxy),
     Socks(SocksProxy),
     Http(HttpProxy),
+    Pluggable(PluggableTransportProxy),
 }
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -343,19 +383,23 @@ impl AsReplacer for ConnectionProxyConfig {
                 Either::Left(Either::Left(tls_proxy.as_replacer()))
             }
             ConnectionProxyConfig::Tcp(tcp_proxy) => {
-                Either::Right(Either::Left(tcp_proxy.as_replacer()))
+                Either::Right(Either::Left(Either::Left(tcp_proxy.as_replacer())))
             }
             ConnectionProxyConfig::Socks(socks_proxy) => {
-                Either::Right(Either::Right(socks_proxy.as_replacer()))
+                Either::Right(Either::Left(Either::Right(socks_proxy.as_replacer())))
             }
             ConnectionProxyConfig::Http(http_proxy) => {
                 Either::Left(Either::Right(http_proxy.as_replacer()))
             }
+            ConnectionProxyConfig::Pluggable(pluggable_proxy) => {
+                Either::Right(Either::Right(pluggable_proxy.as_replacer()))
+            }
         };
         move |route| match &replacer {
             Either::Left(Either::Left(f)) => f(route),
             Either::Left(Either::Right(f)) => f(route),
-            Either::Right(Either::Left(f)) => f(route),
+            Either::Right(Either::Left(Either::Left(f))) => f(route),
+            Either::Right(Either::Left(Either::Right(f))) => f(route),
             Either::Right(Either::Right(f)) => f(route),
         }
     }
@@ -399,6 +443,8 @@ impl AsReplacer for TlsProxy {
             root_certs: proxy_certs.clone(),
             sni: proxy_host.clone(),
             alpn: None,
+            ech_config_list: None,
+            pin_set: None,
         };
 
         let tcp = TcpRoute {
@@ -477,6 +523,8 @@ impl AsReplacer for HttpProxy {
                     root_certs: proxy_certs.clone(),
                     sni: proxy_host.clone(),
                     alpn: Some(Alpn::Http1_1),
+                    ech_config_list: None,
+                    pin_set: None,
                 },
             }),
             None => Either::Right(proxy_tcp_route),
@@ -500,6 +548,53 @@ impl AsReplacer for HttpProxy {
     }
 }
 
+impl AsReplacer for PluggableTransportProxy {
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+        &self,
+    ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
+        let Self {
+            transport,
+            proxy_host,
+            proxy_port,
+            proxy_tls,
+            resolve_hostname_locally,
+        } = self;
+        let proxy_tcp_route = TcpRoute {
+            address: proxy_host.clone().map_domain(UnresolvedHost::from),
+            port: *proxy_port,
+        };
+        let inner_route = match proxy_tls {
+            Some(proxy_certs) => Either::Left(TlsRoute {
+                inner: proxy_tcp_route,
+                fragment: TlsRouteFragment {
+                    root_certs: proxy_certs.clone(),
+                    sni: proxy_host.clone(),
+                    alpn: transport.alpn(),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+            }),
+            None => Either::Right(proxy_tcp_route),
+        };
+        move |route| {
+            route.replace(|TcpRoute { address, port }| {
+                ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                    fragment: PluggableTransportRouteFragment {
+                        transport: transport.clone(),
+                        target_host: if *resolve_hostname_locally {
+                            ProxyTarget::ResolvedLocally(Host::Domain(address))
+                        } else {
+                            ProxyTarget::ResolvedRemotely { name: address.0 }
+                        },
+                        target_port: port,
+                    },
+                    inner: inner_route.clone(),
+                })
+            })
+        }
+    }
+}
+
 impl<R> ReplaceFragment<ConnectionProxyRoute<R>> for ConnectionProxyRoute<R> {
     type Replacement<T> = T;
 
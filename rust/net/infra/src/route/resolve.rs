@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::future::Future;
 use std::net::IpAddr;
@@ -10,16 +11,19 @@ use std::sync::Arc;
 
 use either::Either;
 use futures_util::stream::FuturesUnordered;
-use futures_util::{FutureExt as _, TryStreamExt as _};
+use futures_util::{FutureExt as _, StreamExt as _, TryStreamExt as _};
 use itertools::Itertools;
 
 use crate::dns::lookup_result::LookupResult;
+use crate::dns::service_binding::ServiceBinding;
 use crate::dns::{DnsError, DnsResolver};
 use crate::host::Host;
 use crate::route::{
     ConnectionProxyRoute, DirectOrProxyRoute, HttpProxyRouteFragment, HttpsProxyRoute,
-    HttpsTlsRoute, ProxyTarget, SocksRoute, TcpRoute, TlsRoute, UnresolvedHost, WebSocketRoute,
+    HttpsTlsRoute, PluggableTransportRoute, PluggableTransportRouteFragment, ProxyTarget,
+    SocksRoute, TcpRoute, TlsRoute, UnresolvedHost, WebSocketRoute,
 };
+use crate::timeouts::DNS_RESOLUTION_DELAY;
 
 /// A route with hostnames that can be resolved.
 ///
@@ -39,6 +43,13 @@ pub trait ResolveHostnames {
     /// The provided `lookup` callback must be able to resolve every hostname
     /// that is yielded by `self.hostnames()`.
     fn resolve(self, lookup: impl FnMut(&str) -> IpAddr) -> Self::Resolved;
+
+    /// Fills in connection parameters from the DNS service bindings for the
+    /// hostnames in `self`.
+    ///
+    /// The default implementation does nothing, which is correct for routes
+    /// that don't contain a TLS layer.
+    fn apply_service_bindings(&mut self, _bindings: &HashMap<Arc<str>, ServiceBinding>) {}
 }
 
 /// A route that has had all its hostnames resolved to IP addresses.
@@ -62,12 +73,30 @@ pub trait Resolver {
         &self,
         hostname: &str,
     ) -> impl Future<Output = Result<LookupResult, DnsError>> + Send;
+
+    /// Asynchronously looks up the [`ServiceBinding`] for a single domain
+    /// name.
+    ///
+    /// The default implementation never finds one.
+    fn lookup_service_binding(
+        &self,
+        _hostname: &str,
+    ) -> impl Future<Output = Option<ServiceBinding>> + Send {
+        std::future::ready(None)
+    }
 }
 
 impl Resolver for DnsResolver {
     fn lookup_ip(&self, hostname: &str) -> impl Future<Output = Result<LookupResult, DnsError>> {
         DnsResolver::lookup_ip(self, hostname)
     }
+
+    fn lookup_service_binding(
+        &self,
+        hostname: &str,
+    ) -> impl Future<Output = Option<ServiceBinding>> + Send {
+        DnsResolver::lookup_service_binding(self, hostname)
+    }
 }
 
 /// The output of [`resolve_route`] on successful resolution.
@@ -85,9 +114,13 @@ type ResolveRouteIter<R> = std::iter::Chain<
 /// DNS resolution for a given host can produce multiple addresses, the output
 /// is a sequence of routes in the order in which connections should be
 /// attempted.
+///
+/// Service bindings are looked up alongside the addresses and applied to the
+/// route, but resolution only waits for them for [`DNS_RESOLUTION_DELAY`]
+/// after the addresses are known.
 pub async fn resolve_route<R: ResolveHostnames + Clone + 'static>(
     dns: &impl Resolver,
-    route: R,
+    mut route: R,
 ) -> Result<ResolveRouteIter<R::Resolved>, (Arc<str>, DnsError)> {
     let to_resolve = route.hostnames().map(|UnresolvedHost(hostname)| {
         dns.lookup_ip(hostname).map(|result| match result {
@@ -95,10 +128,36 @@ pub async fn resolve_route<R: ResolveHostnames + Clone + 'static>(
             Err(e) => Err((Arc::clone(hostname), e)),
         })
     });
+    let service_bindings_to_lookup = route.hostnames().map(|UnresolvedHost(hostname)| {
+        dns.lookup_service_binding(hostname)
+            .map(|binding| binding.map(|binding| (Arc::clone(hostname), binding)))
+    });
 
-    let resolved = FuturesUnordered::from_iter(to_resolve)
-        .try_collect::<Vec<_>>()
-        .await?;
+    let resolved = FuturesUnordered::from_iter(to_resolve).try_collect::<Vec<_>>();
+    let service_bindings = FuturesUnordered::from_iter(service_bindings_to_lookup)
+        .filter_map(std::future::ready)
+        .collect::<HashMap<_, _>>();
+    let (resolved, service_bindings) = {
+        let resolved = std::pin::pin!(resolved);
+        let mut service_bindings = std::pin::pin!(service_bindings);
+        // Service binding lookups are polled first so that resolvers that
+        // don't support them never need a timer.
+        match futures_util::future::select(service_bindings.as_mut(), resolved).await {
+            futures_util::future::Either::Left((service_bindings, resolved)) => {
+                (resolved.await?, service_bindings)
+            }
+            futures_util::future::Either::Right((resolved, _)) => {
+                // The addresses are enough to connect; don't hold things up
+                // for long waiting on the optional extras.
+                let resolved = resolved?;
+                let service_bindings = tokio::time::timeout(DNS_RESOLUTION_DELAY, service_bindings)
+                    .await
+                    .unwrap_or_default();
+                (resolved, service_bindings)
+            }
+        }
+    };
+    route.apply_service_bindings(&service_bindings);
 
     let resolutions = resolved
         .into_iter()
@@ -203,6 +262,10 @@ macro_rules! impl_resolve_hostnames {
                     $($other_fields)*
                 }
             }
+
+            fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+                self.$delegate_field.apply_service_bindings(bindings)
+            }
         }
     };
     ($typ:ident, $delegate_field:ident) => {
@@ -211,10 +274,35 @@ macro_rules! impl_resolve_hostnames {
 }
 
 impl_resolve_hostnames!(TcpRoute, address, port);
-impl_resolve_hostnames!(TlsRoute, inner, fragment);
 impl_resolve_hostnames!(HttpsTlsRoute, inner, fragment);
 impl_resolve_hostnames!(WebSocketRoute, inner, fragment);
 
+impl<A: ResolveHostnames> ResolveHostnames for TlsRoute<A> {
+    type Resolved = TlsRoute<A::Resolved>;
+
+    fn hostnames(&self) -> impl Iterator<Item = &UnresolvedHost> {
+        self.inner.hostnames()
+    }
+
+    fn resolve(self, lookup: impl FnMut(&str) -> IpAddr) -> Self::Resolved {
+        let Self { inner, fragment } = self;
+        TlsRoute {
+            inner: inner.resolve(lookup),
+            fragment,
+        }
+    }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        let Self { inner, fragment } = self;
+        inner.apply_service_bindings(bindings);
+        if let Host::Domain(sni) = &fragment.sni {
+            if let Some(binding) = bindings.get(sni) {
+                fragment.apply_service_binding(binding);
+            }
+        }
+    }
+}
+
 impl<D: ResolveHostnames, P: ResolveHostnames> ResolveHostnames for DirectOrProxyRoute<D, P> {
     type Resolved = DirectOrProxyRoute<D::Resolved, P::Resolved>;
 
@@ -231,6 +319,13 @@ impl<D: ResolveHostnames, P: ResolveHostnames> ResolveHostnames for DirectOrProx
             DirectOrProxyRoute::Proxy(p) => DirectOrProxyRoute::Proxy(p.resolve(lookup)),
         }
     }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        match self {
+            DirectOrProxyRoute::Direct(d) => d.apply_service_bindings(bindings),
+            DirectOrProxyRoute::Proxy(p) => p.apply_service_bindings(bindings),
+        }
+    }
 }
 
 impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
@@ -240,8 +335,11 @@ impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
         match self {
             Self::Tls { proxy } => Either::Left(Either::Left(proxy.hostnames())),
             Self::Tcp { proxy } => Either::Left(Either::Right(proxy.hostnames())),
-            Self::Socks(socks) => Either::Right(Either::Right(socks.hostnames())),
+            Self::Socks(socks) => Either::Right(Either::Right(Either::Left(socks.hostnames()))),
             Self::Https(http) => Either::Right(Either::Left(http.hostnames())),
+            Self::Pluggable(pluggable) => {
+                Either::Right(Either::Right(Either::Right(pluggable.hostnames())))
+            }
         }
     }
 
@@ -257,6 +355,19 @@ impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
                 ConnectionProxyRoute::Socks(socks.resolve(lookup))
             }
             ConnectionProxyRoute::Https(http) => ConnectionProxyRoute::Https(http.resolve(lookup)),
+            ConnectionProxyRoute::Pluggable(pluggable) => {
+                ConnectionProxyRoute::Pluggable(pluggable.resolve(lookup))
+            }
+        }
+    }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        match self {
+            Self::Tls { proxy } => proxy.apply_service_bindings(bindings),
+            Self::Tcp { proxy } => proxy.apply_service_bindings(bindings),
+            Self::Socks(socks) => socks.apply_service_bindings(bindings),
+            Self::Https(http) => http.apply_service_bindings(bindings),
+            Self::Pluggable(pluggable) => pluggable.apply_service_bindings(bindings),
         }
     }
 }
@@ -303,6 +414,65 @@ impl<A: ResolveHostnames> ResolveHostnames for HttpsProxyRoute<A> {
             fragment,
         }
     }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        match &mut self.inner {
+            Either::Left(tls) => tls.apply_service_bindings(bindings),
+            Either::Right(tcp) => tcp.apply_service_bindings(bindings),
+        }
+    }
+}
+
+impl<A: ResolveHostnames> ResolveHostnames for PluggableTransportRoute<A> {
+    type Resolved = PluggableTransportRoute<A::Resolved>;
+
+    fn hostnames(&self) -> impl Iterator<Item = &UnresolvedHost> {
+        let Self {
+            inner,
+            fragment:
+                PluggableTransportRouteFragment {
+                    transport: _,
+                    target_host,
+                    target_port: _,
+                },
+        } = self;
+        inner
+            .as_ref()
+            .map_either(ResolveHostnames::hostnames, ResolveHostnames::hostnames)
+            .chain(target_host.locally_resolved_hostnames())
+    }
+
+    fn resolve(self, mut lookup: impl FnMut(&str) -> IpAddr) -> Self::Resolved {
+        let Self {
+            inner,
+            fragment:
+                PluggableTransportRouteFragment {
+                    transport,
+                    target_host,
+                    target_port,
+                },
+        } = self;
+        let fragment = PluggableTransportRouteFragment {
+            transport,
+            target_host: target_host.replace_locally_resolved(&mut lookup),
+            target_port,
+        };
+        Self::Resolved {
+            inner: inner.map_either_with(
+                lookup,
+                |lookup, r| r.resolve(lookup),
+                |lookup, r| r.resolve(lookup),
+            ),
+            fragment,
+        }
+    }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        match &mut self.inner {
+            Either::Left(tls) => tls.apply_service_bindings(bindings),
+            Either::Right(tcp) => tcp.apply_service_bindings(bindings),
+        }
+    }
 }
 
 impl<A: ResolveHostnames> ResolveHostnames for SocksRoute<A> {
@@ -335,6 +505,10 @@ impl<A: ResolveHostnames> ResolveHostnames for SocksRoute<A> {
             protocol,
         }
     }
+
+    fn apply_service_bindings(&mut self, bindings: &HashMap<Arc<str>, ServiceBinding>) {
+        self.proxy.apply_service_bindings(bindings)
+    }
 }
 
 impl<A: ResolveHostnames> ProxyTarget<A> {
@@ -380,6 +554,7 @@ impl_resolved_route!(TcpRoute, address);
 impl_resolved_route!(TlsRoute, inner);
 impl_resolved_route!(HttpsTlsRoute, inner);
 impl_resolved_route!(HttpsProxyRoute, inner);
+impl_resolved_route!(PluggableTransportRoute, inner);
 impl_resolved_route!(WebSocketRoute, inner);
 
 impl<D: ResolvedRoute, P: ResolvedRoute> ResolvedRoute for DirectOrProxyRoute<D, P> {
@@ -398,6 +573,7 @@ impl<A: ResolvedRoute> ResolvedRoute for ConnectionProxyRoute<A> {
             ConnectionProxyRoute::Tcp { proxy } => proxy.immediate_target(),
             ConnectionProxyRoute::Socks(proxy) => proxy.immediate_target(),
             ConnectionProxyRoute::Https(proxy) => proxy.immediate_target(),
+            ConnectionProxyRoute::Pluggable(proxy) => proxy.immediate_target(),
         }
     }
 }
@@ -499,11 +675,11 @@ mod test {
     use crate::host::Host;
     use crate::route::resolve::testutils::{FakeResolver, FakeResponder};
     use crate::route::{
-        DirectOrProxyRoute, HttpRouteFragment, SocksRoute, TlsRouteFragment,
+        DirectOrProxyRoute, EchConfigList, HttpRouteFragment, SocksRoute, TlsRouteFragment,
         UnresolvedHttpsServiceRoute,
     };
     use crate::tcp_ssl::proxy::socks;
-    use crate::DnsSource;
+    use crate::{Alpn, DnsSource};
 
     const PROXY_PORT: NonZeroU16 = nonzero!(444u16);
     const TARGET_PORT: NonZeroU16 = nonzero!(888u16);
@@ -594,19 +770,19 @@ mod test {
         responders
             .remove("host-1")
             .unwrap()
-            .respond(Ok(LookupResult {
-                source: DnsSource::Cache,
-                ipv4: vec![],
-                ipv6: vec![ip_addr!(v6, "::1111")],
-            }));
+            .respond(Ok(LookupResult::new(
+                DnsSource::Cache,
+                vec![],
+                vec![ip_addr!(v6, "::1111")],
+            )));
         responders
             .remove("host-3")
             .unwrap()
-            .respond(Ok(LookupResult {
-                source: DnsSource::Cache,
-                ipv4: vec![ip_addr!(v4, "5.5.5.5")],
-                ipv6: vec![ip_addr!(v6, "::2222")],
-            }));
+            .respond(Ok(LookupResult::new(
+                DnsSource::Cache,
+                vec![ip_addr!(v4, "5.5.5.5")],
+                vec![ip_addr!(v6, "::2222")],
+            )));
 
         let () = tokio::select! {
             biased;
@@ -617,11 +793,11 @@ mod test {
         responders
             .remove("host-2")
             .unwrap()
-            .respond(Ok(LookupResult {
-                source: DnsSource::Test,
-                ipv4: vec![],
-                ipv6: vec![ip_addr!(v6, "::3333")],
-            }));
+            .respond(Ok(LookupResult::new(
+                DnsSource::Test,
+                vec![],
+                vec![ip_addr!(v6, "::3333")],
+            )));
         let result = resolve.await.expect("finished");
 
         pretty_assertions::assert_eq!(
@@ -647,19 +823,19 @@ mod test {
         let dns = HashMap::from([
             (
                 "proxy-domain",
-                LookupResult {
-                    source: DnsSource::Static,
-                    ipv4: vec![ip_addr!(v4, "10.10.10.10")],
-                    ipv6: vec![ip_addr!(v6, "::ffff")],
-                },
+                LookupResult::new(
+                    DnsSource::Static,
+                    vec![ip_addr!(v4, "10.10.10.10")],
+                    vec![ip_addr!(v6, "::ffff")],
+                ),
             ),
             (
                 "target-domain",
-                LookupResult {
-                    source: DnsSource::Static,
-                    ipv4: vec![ip_addr!(v4, "1.2.3.4"), ip_addr!(v4, "1.2.3.5")],
-                    ipv6: vec![ip_addr!(v6, "::1234")],
-                },
+                LookupResult::new(
+                    DnsSource::Static,
+                    vec![ip_addr!(v4, "1.2.3.4"), ip_addr!(v4, "1.2.3.5")],
+                    vec![ip_addr!(v6, "::1234")],
+                ),
             ),
         ]);
 
@@ -673,6 +849,8 @@ mod test {
             root_certs: RootCertificates::Native,
             sni: Host::Domain("target-domain".into()),
             alpn: None,
+            ech_config_list: None,
+            pin_set: None,
         };
 
         fn socks_route<A>(proxy: A, target: A) -> ConnectionProxyRoute<A> {
@@ -774,4 +952,82 @@ mod test {
 
         pretty_assertions::assert_eq!(resolved, expected_routes);
     }
+
+    struct WithServiceBindings<'a> {
+        addresses: HashMap<&'a str, LookupResult>,
+        bindings: HashMap<&'a str, ServiceBinding>,
+    }
+
+    impl Resolver for WithServiceBindings<'_> {
+        fn lookup_ip(
+            &self,
+            hostname: &str,
+        ) -> impl Future<Output = Result<LookupResult, DnsError>> {
+            self.addresses.lookup_ip(hostname)
+        }
+
+        fn lookup_service_binding(
+            &self,
+            hostname: &str,
+        ) -> impl Future<Output = Option<ServiceBinding>> + Send {
+            std::future::ready(self.bindings.get(hostname).cloned())
+        }
+    }
+
+    #[test]
+    fn applies_service_binding_to_tls_fragment() {
+        let ech_config_list = EchConfigList::new([0xEC; 8].as_slice());
+        let dns = WithServiceBindings {
+            addresses: HashMap::from([(
+                "target-domain",
+                LookupResult::new(DnsSource::Static, vec![ip_addr!(v4, "1.2.3.4")], vec![]),
+            )]),
+            bindings: HashMap::from([(
+                "target-domain",
+                ServiceBinding {
+                    priority: 1,
+                    target: "".to_owned(),
+                    alpn: vec!["h3".to_owned(), "h2".to_owned()],
+                    ech_config_list: Some(ech_config_list.clone()),
+                },
+            )]),
+        };
+
+        let unresolved_route = TlsRoute {
+            inner: TcpRoute {
+                address: UnresolvedHost("target-domain".into()),
+                port: TARGET_PORT,
+            },
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::Native,
+                sni: Host::Domain("target-domain".into()),
+                alpn: None,
+                ech_config_list: None,
+                pin_set: None,
+            },
+        };
+
+        let resolved = resolve_route(&dns, unresolved_route)
+            .now_or_never()
+            .expect("all resolution is static")
+            .expect("all hostnames are resolvable")
+            .collect_vec();
+
+        pretty_assertions::assert_eq!(
+            resolved,
+            [TlsRoute {
+                inner: TcpRoute {
+                    address: ip_addr!("1.2.3.4"),
+                    port: TARGET_PORT,
+                },
+                fragment: TlsRouteFragment {
+                    root_certs: RootCertificates::Native,
+                    sni: Host::Domain("target-domain".into()),
+                    alpn: Some(Alpn::Http2),
+                    ech_config_list: Some(ech_config_list),
+                    pin_set: None,
+                },
+            }]
+        );
+    }
 }
@@ -9,6 +9,7 @@ use std::future::Future;
 use std::hash::Hash;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use derive_where::derive_where;
 use futures_util::stream::{FusedStream, FuturesUnordered};
@@ -257,6 +258,18 @@ pub struct AttemptOutcome {
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct UnsuccessfulOutcome;
 
+/// A [`ConnectionOutcomes`] entry with a wall-clock timestamp.
+///
+/// Unlike [`Instant`]s, these remain meaningful across process restarts, so
+/// they can be saved and loaded with
+/// [`ConnectionOutcomes::saved_outcomes`] and
+/// [`ConnectionOutcomes::load_saved_outcomes`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SavedOutcome {
+    pub last_failure: SystemTime,
+    pub failure_count: u8,
+}
+
 impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
     pub fn new(params: ConnectionOutcomeParams) -> Self {
         Self {
@@ -311,6 +324,80 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
         self.recent_failures
             .retain(|_route, (last_time, _failure_count)| cutoff < *last_time);
     }
+
+    /// Exports the recorded failures with wall-clock timestamps.
+    ///
+    /// `now` and `wall_now` should refer to the same moment.
+    pub fn saved_outcomes(
+        &self,
+        now: Instant,
+        wall_now: SystemTime,
+    ) -> impl Iterator<Item = (&R, SavedOutcome)> + '_ {
+        self.recent_failures
+            .iter()
+            .map(move |(route, (when, count))| {
+                let age = now.saturating_duration_since(*when);
+                let last_failure = wall_now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+                (
+                    route,
+                    SavedOutcome {
+                        last_failure,
+                        failure_count: *count,
+                    },
+                )
+            })
+    }
+
+    /// Merges in failures exported by [`Self::saved_outcomes`], possibly by a
+    /// previous run of the process.
+    ///
+    /// Entries older than the age cutoff are dropped, and existing entries are
+    /// only replaced by more recent ones. Timestamps in the future, which can
+    /// happen if the wall clock moved backwards, are treated as `wall_now`.
+    pub fn load_saved_outcomes(
+        &mut self,
+        saved: impl IntoIterator<Item = (R, SavedOutcome)>,
+        now: Instant,
+        wall_now: SystemTime,
+    ) {
+        use std::collections::hash_map::Entry;
+
+        let Self {
+            params,
+            recent_failures,
+        } = self;
+
+        for (
+            route,
+            SavedOutcome {
+                last_failure,
+                failure_count,
+            },
+        ) in saved
+        {
+            let age = wall_now
+                .duration_since(last_failure)
+                .unwrap_or(Duration::ZERO);
+            if age >= params.age_cutoff {
+                continue;
+            }
+            let Some(when) = now.checked_sub(age) else {
+                continue;
+            };
+            let loaded = (when, failure_count.min(params.max_count));
+
+            match recent_failures.entry(route) {
+                Entry::Occupied(mut entry) => {
+                    if entry.get().0 < when {
+                        entry.insert(loaded);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(loaded);
+                }
+            }
+        }
+    }
 }
 
 impl<P: RouteDelayPolicy<R>, R> RouteDelayPolicy<R> for &P {
@@ -677,11 +764,11 @@ mod test {
         let resolver = RouteResolver { allow_ipv6: true };
         let name_resolver = HashMap::from([(
             "domain-name",
-            LookupResult {
-                ipv4: vec![ip_addr!(v4, "1.2.3.4")],
-                ipv6: vec![ip_addr!(v6, "::1234")],
-                source: DnsSource::Static,
-            },
+            LookupResult::new(
+                DnsSource::Static,
+                vec![ip_addr!(v4, "1.2.3.4")],
+                vec![ip_addr!(v6, "::1234")],
+            ),
         )]);
 
         let unresolved_routes = [FakeRoute(UnresolvedHost("domain-name".into()))];
@@ -713,19 +800,19 @@ mod test {
         let name_resolver = HashMap::from([
             (
                 "name-1",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "1.2.3.4")],
-                    ipv6: vec![ip_addr!(v6, "::1234")],
-                    source: DnsSource::Static,
-                },
+                LookupResult::new(
+                    DnsSource::Static,
+                    vec![ip_addr!(v4, "1.2.3.4")],
+                    vec![ip_addr!(v6, "::1234")],
+                ),
             ),
             (
                 "name-2",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "5.6.7.8")],
-                    ipv6: vec![ip_addr!(v6, "::5678")],
-                    source: DnsSource::Static,
-                },
+                LookupResult::new(
+                    DnsSource::Static,
+                    vec![ip_addr!(v4, "5.6.7.8")],
+                    vec![ip_addr!(v6, "::5678")],
+                ),
             ),
         ]);
 
@@ -866,6 +953,68 @@ mod test {
         );
     }
 
+    #[test]
+    fn connection_outcomes_saved_and_loaded() {
+        const AGE_CUTOFF: Duration = Duration::from_secs(1000);
+        let params = ConnectionOutcomeParams {
+            age_cutoff: AGE_CUTOFF,
+            cooldown_growth_factor: 2.0,
+            count_growth_factor: 10.0,
+            max_count: 5,
+            max_delay: Duration::from_secs(100),
+        };
+        let mut outcomes = ConnectionOutcomes::new(params.clone());
+
+        const ROUTE: &str = "route";
+        const OLD_ROUTE: &str = "old route";
+        let start = Instant::now();
+        let wall_start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        outcomes.record_outcome(
+            ROUTE,
+            start,
+            Duration::from_secs(10),
+            Err(UnsuccessfulOutcome),
+        );
+        let now = start + Duration::from_secs(10);
+        let wall_now = wall_start + Duration::from_secs(10);
+        let expected_delay = outcomes.compute_delay(&ROUTE, now);
+        assert_ne!(expected_delay, Duration::ZERO);
+
+        let saved = outcomes
+            .saved_outcomes(now, wall_now)
+            .map(|(route, outcome)| (*route, outcome))
+            .collect_vec();
+        assert_eq!(
+            saved,
+            [(
+                ROUTE,
+                SavedOutcome {
+                    last_failure: wall_start,
+                    failure_count: 1
+                }
+            )]
+        );
+
+        // Load into a fresh table in a "new process" with unrelated Instants.
+        let mut loaded = ConnectionOutcomes::new(params);
+        let later = Instant::now() + Duration::from_secs(12345);
+        loaded.load_saved_outcomes(
+            saved.into_iter().chain([(
+                OLD_ROUTE,
+                SavedOutcome {
+                    last_failure: wall_now - AGE_CUTOFF,
+                    failure_count: 5,
+                },
+            )]),
+            later,
+            wall_now,
+        );
+
+        assert_eq!(loaded.compute_delay(&ROUTE, later), expected_delay);
+        assert_eq!(loaded.compute_delay(&OLD_ROUTE, later), Duration::ZERO);
+    }
+
     #[test]
     fn connection_outcomes_reset_by_cutoff() {
         const MAX_DELAY: Duration = Duration::from_secs(100);
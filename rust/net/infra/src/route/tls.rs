@@ -5,7 +5,8 @@
 
 use std::sync::Arc;
 
-use crate::certs::RootCertificates;
+use crate::certs::{RootCertificates, SpkiPinSet};
+use crate::dns::service_binding::ServiceBinding;
 use crate::host::Host;
 use crate::route::{ReplaceFragment, RouteProvider, RouteProviderContext, SimpleRoute};
 use crate::Alpn;
@@ -15,20 +16,44 @@ pub struct TlsRouteFragment {
     pub root_certs: RootCertificates,
     pub sni: Host<Arc<str>>,
     pub alpn: Option<Alpn>,
+    /// Encrypted Client Hello configurations for the server.
+    ///
+    /// If present, the real SNI is encrypted and the server's public name is
+    /// sent in its place. If absent, the SNI is sent in the clear.
+    pub ech_config_list: Option<EchConfigList>,
+    /// Public keys the server's certificate chain must include, if any.
+    pub pin_set: Option<SpkiPinSet>,
 }
 
+/// An `ECHConfigList` structure, as published in DNS HTTPS records.
+///
+/// See [draft-ietf-tls-esni](https://datatracker.ietf.org/doc/draft-ietf-tls-esni/).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct EchConfigList(Arc<[u8]>);
+
 pub type TlsRoute<T> = SimpleRoute<TlsRouteFragment, T>;
 
 #[derive(Debug)]
 pub struct TlsRouteProvider<P> {
     pub(crate) sni: Host<Arc<str>>,
     pub(crate) certs: RootCertificates,
+    pub(crate) pin_set: Option<SpkiPinSet>,
     pub(crate) inner: P,
 }
 
 impl<T> TlsRouteProvider<T> {
     pub fn new(certs: RootCertificates, sni: Host<Arc<str>>, inner: T) -> Self {
-        Self { sni, certs, inner }
+        Self {
+            sni,
+            certs,
+            pin_set: None,
+            inner,
+        }
+    }
+
+    /// Requires the server's certificate chain to include a key from `pin_set`.
+    pub fn with_pin_set(self, pin_set: Option<SpkiPinSet>) -> Self {
+        Self { pin_set, ..self }
     }
 }
 
@@ -45,13 +70,20 @@ impl<P: RouteProvider> RouteProvider for TlsRouteProvider<P> {
         &'s self,
         context: &impl RouteProviderContext,
     ) -> impl Iterator<Item = Self::Route> + 's {
-        let Self { sni, certs, inner } = self;
+        let Self {
+            sni,
+            certs,
+            pin_set,
+            inner,
+        } = self;
 
         inner.routes(context).map(|route| TlsRoute {
             fragment: TlsRouteFragment {
                 root_certs: certs.clone(),
                 sni: sni.clone(),
                 alpn: None,
+                ech_config_list: None,
+                pin_set: pin_set.clone(),
             },
             inner: route,
         })
@@ -82,6 +114,39 @@ impl SetAlpn for TlsRouteFragment {
     }
 }
 
+impl TlsRouteFragment {
+    /// Fills in connection parameters from a DNS service binding for the
+    /// route's SNI.
+    ///
+    /// Parameters that were already set are left alone.
+    pub(crate) fn apply_service_binding(&mut self, binding: &ServiceBinding) {
+        if self.ech_config_list.is_none() {
+            self.ech_config_list = binding.ech_config_list.clone();
+        }
+        if self.alpn.is_none() {
+            self.alpn = binding.alpn.iter().find_map(|id| Alpn::from_id(id));
+        }
+    }
+}
+
+impl EchConfigList {
+    pub fn new(config_list: impl Into<Arc<[u8]>>) -> Self {
+        Self(config_list.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for EchConfigList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EchConfigList")
+            .field(&format_args!("{} bytes", self.0.len()))
+            .finish()
+    }
+}
+
 impl<T, H> From<&TlsRoute<T>> for Host<H>
 where
     for<'a> &'a T: Into<Host<H>>,
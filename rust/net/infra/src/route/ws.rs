@@ -10,6 +10,7 @@ use http::HeaderMap;
 use tungstenite::protocol::WebSocketConfig;
 
 use crate::route::{ReplaceFragment, RouteProvider, RouteProviderContext, SimpleRoute};
+use crate::ws::DeflateConfig;
 
 #[derive(Clone, Debug)]
 pub struct WebSocketRouteFragment {
@@ -19,6 +20,8 @@ pub struct WebSocketRouteFragment {
     pub endpoint: PathAndQuery,
     /// Request headers to include in the HTTP request establishing the connection.
     pub headers: HeaderMap,
+    /// Compression to offer the server, or `None` to send plain frames.
+    pub deflate: Option<DeflateConfig>,
 }
 
 impl AsMut<WebSocketRouteFragment> for WebSocketRouteFragment {
@@ -75,9 +78,11 @@ impl PartialEq for WebSocketRouteFragment {
             ws_config,
             endpoint,
             headers,
+            deflate,
         } = self;
         endpoint == &other.endpoint
             && headers == &other.headers
+            && deflate == &other.deflate
             && ws_config_eq(ws_config, &other.ws_config)
     }
 }
@@ -90,9 +95,11 @@ impl std::hash::Hash for WebSocketRouteFragment {
             ws_config,
             endpoint,
             headers: _,
+            deflate,
         } = self;
         ws_config_hash(ws_config, state);
         endpoint.hash(state);
+        deflate.hash(state);
     }
 }
 
@@ -517,6 +517,7 @@ mod test {
                 tcp_host: Host::Domain(Arc::clone(&host)),
                 port: nonzero!(443u16),
                 certs: RootCertificates::Native,
+                pin_set: None,
             },
             http_host: host,
             http_request_decorator: HttpRequestDecoratorSeq::default(),
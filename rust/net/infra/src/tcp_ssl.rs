@@ -15,13 +15,13 @@ use futures_util::TryFutureExt;
 use tokio::net::TcpStream;
 use tokio_boring_signal::SslStream;
 
-use crate::certs::RootCertificates;
+use crate::certs::{PinCheck, RootCertificates, SpkiPinSet};
 use crate::dns::DnsResolver;
 use crate::errors::TransportConnectError;
 use crate::host::Host;
 use crate::route::{
-    ConnectionProxyConfig, Connector, ConnectorExt as _, TcpProxy, TcpRoute, TlsProxy,
-    TlsRouteFragment,
+    ConnectionProxyConfig, Connector, ConnectorExt as _, EchConfigList, TcpProxy, TcpRoute,
+    TlsProxy, TlsRouteFragment,
 };
 use crate::tcp_ssl::proxy::tls::TlsProxyConnector;
 use crate::timeouts::TCP_CONNECTION_ATTEMPT_DELAY;
@@ -175,22 +175,38 @@ where
             root_certs,
             sni,
             alpn,
+            ech_config_list,
+            pin_set,
         } = fragment;
         let host = sni;
 
-        let ssl_config =
-            ssl_config(&root_certs, host.as_deref(), alpn).map_err(TransportConnectError::from);
+        let ssl_config = ssl_config(
+            &root_certs,
+            host.as_deref(),
+            alpn,
+            ech_config_list.as_ref(),
+            pin_set.as_ref(),
+        )
+        .map_err(TransportConnectError::from);
 
         async move {
             let domain = match &host {
                 Host::Ip(ip_addr) => either::Either::Left(ip_addr.to_string()),
                 Host::Domain(domain) => either::Either::Right(&**domain),
             };
-            let ssl_config = ssl_config?;
+            let (ssl_config, pin_check) = ssl_config?;
 
             tokio_boring_signal::connect(ssl_config, &domain, inner)
                 .await
-                .map_err(TransportConnectError::from)
+                .map_err(|error| {
+                    if pin_check.mismatched() {
+                        TransportConnectError::CertificatePinMismatch
+                    } else if let Some(retry_configs) = ech_rejection(&error) {
+                        TransportConnectError::EchRejected { retry_configs }
+                    } else {
+                        TransportConnectError::from(error)
+                    }
+                })
         }
     }
 }
@@ -201,13 +217,41 @@ impl<S: Connection> Connection for SslStream<S> {
     }
 }
 
+/// If the handshake failed because the server rejected Encrypted Client Hello,
+/// returns the configs it offered for a retry.
+///
+/// BoringSSL only reports the rejection once the server has authenticated as
+/// the ECH public name, so the retry configs can be trusted as much as the
+/// original ones.
+fn ech_rejection<S>(
+    error: &tokio_boring_signal::HandshakeError<S>,
+) -> Option<Option<EchConfigList>> {
+    let rejected = error.as_ssl_error_stack().is_some_and(|stack| {
+        stack
+            .errors()
+            .iter()
+            .any(|error| error.reason() == Some("ECH_REJECTED"))
+    });
+    if !rejected {
+        return None;
+    }
+    Some(
+        error
+            .ssl()
+            .and_then(|ssl| ssl.get_ech_retry_configs())
+            .map(EchConfigList::new),
+    )
+}
+
 fn ssl_config(
     certs: &RootCertificates,
     host: Host<&str>,
     alpn: Option<Alpn>,
-) -> Result<ConnectConfiguration, TransportConnectError> {
+    ech_config_list: Option<&EchConfigList>,
+    pin_set: Option<&SpkiPinSet>,
+) -> Result<(ConnectConfiguration, PinCheck), TransportConnectError> {
     let mut ssl = SslConnector::builder(SslMethod::tls_client())?;
-    certs.apply_to_connector(&mut ssl, host)?;
+    let pin_check = certs.apply_to_connector(&mut ssl, host, pin_set)?;
     if let Some(alpn) = alpn {
         ssl.set_alpn_protos(alpn.as_ref())?;
     }
@@ -232,7 +276,15 @@ fn ssl_config(
     // #[cfg(feature = "dev-util")]
     // development_only_enable_nss_standard_debug_interop(&mut ssl)?;
 
-    Ok(ssl.build().configure()?)
+    let mut config = ssl.build().configure()?;
+    if let Some(ech_config_list) = ech_config_list {
+        // BoringSSL sends the public name from the config as the outer SNI and
+        // encrypts the real one. If the server rejects ECH, the handshake fails
+        // with TransportConnectError::EchRejected rather than silently falling
+        // back to a plaintext SNI; see EchFallbackConnector.
+        config.set_ech_config_list(ech_config_list.as_bytes())?;
+    }
+    Ok((config, pin_check))
 }
 
 async fn connect_tls<S: AsyncDuplexStream>(
@@ -245,6 +297,8 @@ async fn connect_tls<S: AsyncDuplexStream>(
         root_certs: connection_params.certs.clone(),
         sni: Host::Domain(Arc::clone(&connection_params.sni)),
         alpn: Some(alpn),
+        ech_config_list: None,
+        pin_set: connection_params.pin_set.clone(),
     };
 
     StatelessDirect
@@ -265,11 +319,7 @@ async fn connect_tcp(
                 std::net::IpAddr::V4(v4) => (vec![v4], vec![]),
                 std::net::IpAddr::V6(v6) => (vec![], vec![v6]),
             };
-            crate::dns::lookup_result::LookupResult {
-                source: crate::DnsSource::Static,
-                ipv4,
-                ipv6,
-            }
+            crate::dns::lookup_result::LookupResult::new(crate::DnsSource::Static, ipv4, ipv6)
         }
         Host::Domain(domain) => dns_resolver
             .lookup_ip(domain)
@@ -375,8 +425,14 @@ impl TransportConnector for TcpSslConnector {
                 let stream_and_info = connector.connect(connection_params, alpn).await?;
                 stream_and_info.map_stream(TcpSslConnectorStream::Proxy)
             }
-            Some(ConnectionProxyConfig::Socks(_) | ConnectionProxyConfig::Http(_)) => {
-                log::warn!("SOCKS and HTTP proxies are not supported by TransportConnector");
+            Some(
+                ConnectionProxyConfig::Socks(_)
+                | ConnectionProxyConfig::Http(_)
+                | ConnectionProxyConfig::Pluggable(_),
+            ) => {
+                log::warn!(
+                    "SOCKS, HTTP, and pluggable transport proxies are not supported by TransportConnector"
+                );
                 return Err(TransportConnectError::InvalidConfiguration);
             }
         };
@@ -475,6 +531,7 @@ mod test {
             },
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            pin_set: None,
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -511,6 +568,7 @@ mod test {
             tcp_host: Host::Ip(addr.ip()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            pin_set: None,
         };
 
         match connector.connect(&connection_params, Alpn::Http1_1).await {
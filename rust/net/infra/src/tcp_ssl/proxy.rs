@@ -15,6 +15,7 @@ use crate::route::{ConnectionProxyRoute, Connector, ConnectorExt as _, TlsRoute}
 use crate::{Connection, IpType};
 
 pub mod https;
+pub mod pluggable;
 pub mod socks;
 pub mod tls;
 
@@ -64,6 +65,9 @@ impl Connector<ConnectionProxyRoute<IpAddr>, ()> for StatelessProxied {
             ConnectionProxyRoute::Https(route) => {
                 self.connect(route, log_tag).map_ok(Into::into).await
             }
+            ConnectionProxyRoute::Pluggable(route) => {
+                self.connect(route, log_tag).map_ok(Into::into).await
+            }
         }
     }
 }
@@ -0,0 +1,236 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Pluggable transports for reaching a bridge that relays traffic onward.
+//!
+//! A [`PluggableTransport`] is handed a stream that is already connected to
+//! its bridge server and turns it into a byte stream to the eventual target,
+//! using whatever obfuscation it likes on the wire. New transports can be
+//! added by implementing the trait; routing, resolution, and logging are
+//! handled generically by [`PluggableTransportRoute`].
+
+use std::future::Future;
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::future::BoxFuture;
+use futures_util::TryFutureExt as _;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::either::Either;
+
+use crate::errors::TransportConnectError;
+use crate::host::Host;
+use crate::route::{
+    ComposedConnector, Connector, ConnectorExt as _, PluggableTransportRoute,
+    PluggableTransportRouteFragment, ProxyTarget,
+};
+use crate::{Alpn, AsyncDuplexStream, Connection, TransportInfo};
+
+mod websocket;
+pub use websocket::{WebSocketTunnel, TUNNEL_TARGET_HEADER};
+
+/// A transport that tunnels a byte stream through a bridge server.
+pub trait PluggableTransport: Send + Sync + 'static {
+    /// A short, log-safe name for the transport, like `"websocket-tunnel"`.
+    fn name(&self) -> &'static str;
+
+    /// The ALPN to offer if the connection to the bridge uses TLS.
+    fn alpn(&self) -> Option<Alpn> {
+        None
+    }
+
+    /// Whether the connection to the bridge must use TLS.
+    ///
+    /// Routes that reach the bridge over plain TCP are rejected for transports
+    /// that return `true`.
+    fn requires_tls(&self) -> bool {
+        false
+    }
+
+    /// Establishes a tunnel to `target` over a stream connected to the bridge.
+    fn connect_over<'a>(
+        &'a self,
+        bridge: Box<dyn AsyncDuplexStream>,
+        target: (Host<Arc<str>>, NonZeroU16),
+        log_tag: Arc<str>,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncDuplexStream>, TransportConnectError>>;
+}
+
+/// Shared handle to a [`PluggableTransport`] that can be stored in routes.
+///
+/// Handles compare and hash by the identity of the transport instance, so
+/// clones of the same handle are equal but separately constructed transports
+/// are not, even if they are configured identically.
+#[derive(Clone)]
+pub struct SharedPluggableTransport(Arc<dyn PluggableTransport>);
+
+/// An [`AsyncDuplexStream`] tunneled through a [`PluggableTransport`].
+pub struct PluggableTransportStream {
+    inner: Box<dyn AsyncDuplexStream>,
+    info: TransportInfo,
+}
+
+type StatelessTcpConnector = super::super::StatelessDirect;
+type StatelessTlsConnector = ComposedConnector<
+    super::super::StatelessDirect,
+    super::super::StatelessDirect,
+    TransportConnectError,
+>;
+
+impl Connector<PluggableTransportRoute<IpAddr>, ()> for super::StatelessProxied {
+    type Connection = PluggableTransportStream;
+
+    type Error = TransportConnectError;
+
+    fn connect_over(
+        &self,
+        (): (),
+        route: PluggableTransportRoute<IpAddr>,
+        log_tag: Arc<str>,
+    ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+        let PluggableTransportRoute { fragment, inner } = route;
+        async move {
+            if fragment.transport.requires_tls() && inner.is_right() {
+                log::warn!(
+                    "[{log_tag}] {} transport requires TLS to the bridge",
+                    fragment.transport.name()
+                );
+                return Err(TransportConnectError::InvalidConfiguration);
+            }
+
+            let tls_connector = StatelessTlsConnector::default();
+            let tcp_connector = StatelessTcpConnector::default();
+            let inner = inner
+                .map_either(
+                    |tls| {
+                        tls_connector
+                            .connect(tls, log_tag.clone())
+                            .map_ok(Either::Left)
+                    },
+                    |tcp| {
+                        tcp_connector
+                            .connect(tcp, log_tag.clone())
+                            .map_ok(Either::Right)
+                    },
+                )
+                .await?;
+            let info = inner.transport_info();
+
+            let PluggableTransportRouteFragment {
+                transport,
+                target_host,
+                target_port,
+            } = fragment;
+
+            let target_host = match target_host {
+                ProxyTarget::ResolvedLocally(addr) => Host::Ip(addr),
+                ProxyTarget::ResolvedRemotely { name } => Host::Domain(name),
+            };
+
+            match transport
+                .0
+                .connect_over(Box::new(inner), (target_host, target_port), log_tag.clone())
+                .await
+            {
+                Ok(inner) => Ok(PluggableTransportStream { inner, info }),
+                Err(e) => {
+                    log::info!(
+                        "[{log_tag}] failed to connect via {} transport: {e}",
+                        transport.name()
+                    );
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+impl SharedPluggableTransport {
+    pub fn new(transport: impl PluggableTransport) -> Self {
+        Self(Arc::new(transport))
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    pub fn alpn(&self) -> Option<Alpn> {
+        self.0.alpn()
+    }
+
+    pub fn requires_tls(&self) -> bool {
+        self.0.requires_tls()
+    }
+}
+
+impl std::fmt::Debug for SharedPluggableTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedPluggableTransport")
+            .field(&self.name())
+            .finish()
+    }
+}
+
+impl PartialEq for SharedPluggableTransport {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedPluggableTransport {}
+
+impl std::hash::Hash for SharedPluggableTransport {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.0).cast::<()>(), state)
+    }
+}
+
+impl std::fmt::Debug for PluggableTransportStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluggableTransportStream")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for PluggableTransportStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PluggableTransportStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl Connection for PluggableTransportStream {
+    fn transport_info(&self) -> TransportInfo {
+        self.info.clone()
+    }
+}
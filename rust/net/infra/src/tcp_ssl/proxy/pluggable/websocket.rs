@@ -0,0 +1,368 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::num::NonZeroU16;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf as _, Bytes};
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt as _, SinkExt as _, StreamExt as _};
+use http::uri::PathAndQuery;
+use http::HeaderName;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tungstenite::handshake::client::generate_key;
+use tungstenite::protocol::WebSocketConfig;
+
+use super::PluggableTransport;
+use crate::errors::TransportConnectError;
+use crate::host::Host;
+use crate::ws::{WebSocketServiceError, WebSocketTransport};
+use crate::{Alpn, AsyncDuplexStream};
+
+/// Header used to tell a websocket tunnel bridge where to relay traffic.
+pub const TUNNEL_TARGET_HEADER: HeaderName = HeaderName::from_static("x-signal-tunnel-target");
+
+/// [`PluggableTransport`] that carries the tunneled stream in websocket
+/// messages.
+///
+/// The client performs an ordinary websocket handshake with the bridge at
+/// `path`, naming the target in the [`TUNNEL_TARGET_HEADER`] header. Once the
+/// bridge accepts, each binary message carries the next chunk of the tunneled
+/// stream in either direction. The connection to the bridge must use TLS, so
+/// on the wire this looks like any other secure websocket connection to the
+/// bridge's domain; routes that reach the bridge over plain TCP are rejected.
+#[derive(Clone, Debug)]
+pub struct WebSocketTunnel {
+    /// The `Host` header to send to the bridge.
+    pub host_header: Arc<str>,
+    /// The path the bridge accepts tunnel connections on.
+    pub path: PathAndQuery,
+    pub ws_config: WebSocketConfig,
+}
+
+/// Adapts the message-oriented [`WebSocketTransport`] to a byte stream.
+struct WebSocketTunnelStream<S> {
+    transport: WebSocketTransport<S>,
+    /// The unread remainder of the last message received.
+    read_buffer: Bytes,
+}
+
+impl PluggableTransport for WebSocketTunnel {
+    fn name(&self) -> &'static str {
+        "websocket-tunnel"
+    }
+
+    fn alpn(&self) -> Option<Alpn> {
+        Some(Alpn::Http1_1)
+    }
+
+    fn requires_tls(&self) -> bool {
+        true
+    }
+
+    fn connect_over<'a>(
+        &'a self,
+        bridge: Box<dyn AsyncDuplexStream>,
+        (target_host, target_port): (Host<Arc<str>>, NonZeroU16),
+        log_tag: Arc<str>,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncDuplexStream>, TransportConnectError>> {
+        let Self {
+            host_header,
+            path,
+            ws_config,
+        } = self;
+
+        async move {
+            let request = http::Request::builder()
+                .method(http::Method::GET)
+                .uri(
+                    http::uri::Builder::new()
+                        .scheme("wss")
+                        .authority(&**host_header)
+                        .path_and_query(path.clone())
+                        .build()
+                        .map_err(|_| TransportConnectError::InvalidConfiguration)?,
+                )
+                .header(http::header::HOST, &**host_header)
+                .header(http::header::CONNECTION, "Upgrade")
+                .header(http::header::UPGRADE, "websocket")
+                .header(http::header::SEC_WEBSOCKET_VERSION, "13")
+                .header(http::header::SEC_WEBSOCKET_KEY, generate_key())
+                .header(TUNNEL_TARGET_HEADER, format!("{target_host}:{target_port}"))
+                .body(())
+                .map_err(|_| TransportConnectError::InvalidConfiguration)?;
+
+            let (stream, _response) =
+                tokio_tungstenite::client_async_with_config(request, bridge, Some(*ws_config))
+                    .await
+                    .map_err(|e| {
+                        log::info!(
+                            "[{log_tag}] websocket tunnel handshake failed: {}",
+                            WebSocketServiceError::from(e)
+                        );
+                        TransportConnectError::ProxyProtocol
+                    })?;
+
+            let tunnel: Box<dyn AsyncDuplexStream> =
+                Box::new(WebSocketTunnelStream::new(WebSocketTransport(stream)));
+            Ok(tunnel)
+        }
+        .boxed()
+    }
+}
+
+impl<S> WebSocketTunnelStream<S> {
+    fn new(transport: WebSocketTransport<S>) -> Self {
+        Self {
+            transport,
+            read_buffer: Bytes::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WebSocketTunnelStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let Self {
+            transport,
+            read_buffer,
+        } = self.get_mut();
+
+        while read_buffer.is_empty() {
+            match ready!(transport.poll_next_unpin(cx)) {
+                Some(Ok(message)) => *read_buffer = message,
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                // End of stream; leave `buf` unfilled.
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+
+        let count = buf.remaining().min(read_buffer.len());
+        buf.put_slice(&read_buffer[..count]);
+        read_buffer.advance(count);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WebSocketTunnelStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let transport = &mut self.get_mut().transport;
+        ready!(transport.poll_ready_unpin(cx))?;
+        transport.start_send_unpin(Bytes::copy_from_slice(buf))?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.get_mut().transport.poll_flush_unpin(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.get_mut().transport.poll_close_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::net::IpAddr;
+
+    use assert_matches::assert_matches;
+    use either::Either;
+    use http::{HeaderValue, StatusCode};
+    use tokio::net::TcpStream;
+    use tokio::sync::oneshot;
+    use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    use super::*;
+    use crate::certs::RootCertificates;
+    use crate::route::{
+        Connector as _, ConnectorExt as _, PluggableTransportRoute,
+        PluggableTransportRouteFragment, ProxyTarget, TcpRoute, TlsRoute, TlsRouteFragment,
+    };
+    use crate::tcp_ssl::proxy::pluggable::SharedPluggableTransport;
+    use crate::tcp_ssl::proxy::testutil::{
+        TcpServer, TlsServer, PROXY_CERTIFICATE, PROXY_HOSTNAME,
+    };
+    use crate::tcp_ssl::proxy::StatelessProxied;
+    use crate::tcp_ssl::testutil::{
+        localhost_http_server, make_http_request_response_over, SERVER_CERTIFICATE, SERVER_HOSTNAME,
+    };
+    use crate::tcp_ssl::StatelessDirect;
+
+    const BRIDGE_HOST: &str = "bridge.example.com";
+    const TUNNEL_PATH: &str = "/tunnel-secret";
+
+    struct TunnelRequest {
+        path: String,
+        target: Option<HeaderValue>,
+    }
+
+    type BridgeRoute = Either<TlsRoute<TcpRoute<IpAddr>>, TcpRoute<IpAddr>>;
+
+    /// Starts a stand-in TLS bridge that accepts a single tunnel connection.
+    ///
+    /// If `reject` is true, the websocket handshake is refused; otherwise the
+    /// tunnel is relayed to `upstream`.
+    fn spawn_localhost_bridge(
+        upstream: std::net::SocketAddr,
+        reject: bool,
+    ) -> (BridgeRoute, oneshot::Receiver<TunnelRequest>) {
+        let tcp_server = TcpServer::bind_localhost();
+        let bridge_addr = tcp_server.listen_addr;
+        let tls_server = TlsServer::new(tcp_server, &PROXY_CERTIFICATE);
+        let (request_tx, request_rx) = oneshot::channel();
+
+        let _task_handle = tokio::spawn(async move {
+            let (stream, _remote_addr) = tls_server.accept().await;
+            let callback = move |request: &Request, response: Response| {
+                let _ignore_error = request_tx.send(TunnelRequest {
+                    path: request.uri().path().to_owned(),
+                    target: request.headers().get(TUNNEL_TARGET_HEADER).cloned(),
+                });
+                if reject {
+                    let mut error = ErrorResponse::new(None);
+                    *error.status_mut() = StatusCode::NOT_FOUND;
+                    return Err(error);
+                }
+                Ok(response)
+            };
+            let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, callback).await else {
+                return;
+            };
+
+            let mut tunnel = WebSocketTunnelStream::new(WebSocketTransport(ws));
+            let mut upstream = TcpStream::connect(upstream)
+                .await
+                .expect("can connect to upstream");
+            let _ignore_error = tokio::io::copy_bidirectional(&mut tunnel, &mut upstream).await;
+        });
+
+        let route = TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::FromDer(Cow::Borrowed(PROXY_CERTIFICATE.cert.der())),
+                sni: Host::Domain(PROXY_HOSTNAME.into()),
+                alpn: Some(Alpn::Http1_1),
+                ech_config_list: None,
+                pin_set: None,
+            },
+            inner: bridge_addr.try_into().expect("bound to a nonzero port"),
+        };
+        (Either::Left(route), request_rx)
+    }
+
+    fn tunnel_route(
+        bridge: BridgeRoute,
+        target_port: NonZeroU16,
+    ) -> PluggableTransportRoute<IpAddr> {
+        PluggableTransportRoute {
+            fragment: PluggableTransportRouteFragment {
+                transport: SharedPluggableTransport::new(WebSocketTunnel {
+                    host_header: BRIDGE_HOST.into(),
+                    path: PathAndQuery::from_static(TUNNEL_PATH),
+                    ws_config: WebSocketConfig::default(),
+                }),
+                target_host: ProxyTarget::ResolvedRemotely {
+                    name: SERVER_HOSTNAME.into(),
+                },
+                target_port,
+            },
+            inner: bridge,
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_through_tunnel() {
+        let (addr, server) = localhost_http_server();
+        let _server_handle = tokio::spawn(server);
+
+        let (bridge, request_rx) = spawn_localhost_bridge(addr, false);
+        let target_port = addr.port().try_into().expect("bound port");
+
+        let stream = StatelessProxied
+            .connect(tunnel_route(bridge, target_port), "test".into())
+            .await
+            .expect("can connect");
+
+        let TunnelRequest { path, target } = request_rx.await.expect("bridge saw request");
+        assert_eq!(path, TUNNEL_PATH);
+        assert_eq!(
+            target.as_ref().and_then(|target| target.to_str().ok()),
+            Some(format!("{SERVER_HOSTNAME}:{target_port}").as_str())
+        );
+
+        let tls_stream = StatelessDirect
+            .connect_over(
+                stream,
+                TlsRouteFragment {
+                    root_certs: RootCertificates::FromDer(Cow::Borrowed(
+                        SERVER_CERTIFICATE.cert.der(),
+                    )),
+                    sni: Host::Domain(SERVER_HOSTNAME.into()),
+                    alpn: Some(Alpn::Http1_1),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+                "test".into(),
+            )
+            .await
+            .expect("can connect to the target over the tunnel");
+
+        make_http_request_response_over(tls_stream).await;
+    }
+
+    #[tokio::test]
+    async fn bridge_rejection_is_a_proxy_error() {
+        let (addr, server) = localhost_http_server();
+        let _server_handle = tokio::spawn(server);
+
+        let (bridge, request_rx) = spawn_localhost_bridge(addr, true);
+
+        let result = StatelessProxied
+            .connect(
+                tunnel_route(bridge, addr.port().try_into().expect("bound port")),
+                "test".into(),
+            )
+            .await;
+        assert_matches!(result, Err(TransportConnectError::ProxyProtocol));
+
+        let TunnelRequest { path, target: _ } = request_rx.await.expect("bridge saw request");
+        assert_eq!(path, TUNNEL_PATH);
+    }
+
+    #[tokio::test]
+    async fn plain_tcp_bridge_is_rejected() {
+        let tcp_server = TcpServer::bind_localhost();
+        let bridge = tcp_server
+            .listen_addr
+            .try_into()
+            .expect("bound to a nonzero port");
+
+        let result = StatelessProxied
+            .connect(
+                tunnel_route(
+                    Either::Right(bridge),
+                    NonZeroU16::new(443).expect("nonzero"),
+                ),
+                "test".into(),
+            )
+            .await;
+        assert_matches!(result, Err(TransportConnectError::InvalidConfiguration));
+    }
+}
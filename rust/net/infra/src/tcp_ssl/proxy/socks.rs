@@ -105,7 +105,9 @@ impl TransportConnector for SocksConnector {
                 DnsSource::Static,
             ),
             Host::Domain(host) if *resolve_hostname_locally => {
-                let LookupResult { source, ipv4, ipv6 } = dns_resolver
+                let LookupResult {
+                    source, ipv4, ipv6, ..
+                } = dns_resolver
                     .lookup_ip(host)
                     .await
                     .map_err(|_| TransportConnectError::DnsError)?;
@@ -490,6 +492,7 @@ mod test {
             certs: crate::certs::RootCertificates::FromDer(std::borrow::Cow::Borrowed(
                 SERVER_CERTIFICATE.cert.der(),
             )),
+            pin_set: None,
         };
         let mut connect = connector.connect(&connection_params, Alpn::Http1_1);
 
@@ -613,6 +616,7 @@ mod test {
             certs: crate::certs::RootCertificates::FromDer(std::borrow::Cow::Borrowed(
                 SERVER_CERTIFICATE.cert.der(),
             )),
+            pin_set: None,
         };
         let connect = connector.connect(&connection_params, Alpn::Http1_1);
 
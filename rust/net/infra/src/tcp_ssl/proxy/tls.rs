@@ -73,7 +73,13 @@ impl TransportConnector for TlsProxyConnector {
                 );
                 // This won't always work, but it's enough to connect to proxies
                 // by hostnames.
-                let ssl_config = ssl_config(&self.proxy_certs, self.proxy_host.as_deref(), None)?;
+                let (ssl_config, _pin_check) = ssl_config(
+                    &self.proxy_certs,
+                    self.proxy_host.as_deref(),
+                    None,
+                    None,
+                    None,
+                )?;
                 Either::Left(
                     tokio_boring_signal::connect(
                         ssl_config,
@@ -198,6 +204,7 @@ mod test {
             tcp_host: Host::Domain("localhost".into()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            pin_set: None,
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -241,6 +248,7 @@ mod test {
             tcp_host: Host::Domain("localhost".into()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            pin_set: None,
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -8,6 +8,16 @@ pub const DNS_FALLBACK_LOOKUP_TIMEOUTS: &[Duration] = &[
     Duration::from_secs(10),
     Duration::from_secs(15),
 ];
+/// When looking up a name over DNS-over-HTTPS, providers are raced against
+/// each other with each one being given an additional delay before it starts.
+pub const DOH_PROVIDER_ATTEMPT_DELAY: Duration = Duration::from_millis(500);
+/// How long one DNS-over-HTTPS provider is given to answer before it's
+/// counted as failing.
+///
+/// This is shorter than the first of [`DNS_FALLBACK_LOOKUP_TIMEOUTS`] so that
+/// a provider that doesn't answer at all is noticed before the whole race is
+/// given up on.
+pub const DOH_PROVIDER_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(3);
 /// If during a DNS resolution we've sent multiple queries (one per IP type)
 /// and one of them produced a result, we'll wait this time interval
 /// to let the other query complete before proceeding
@@ -32,6 +42,10 @@ pub const ONE_ROUTE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
 /// Timeout for a connect operation that attempts multiple routes
 pub const MULTI_ROUTE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(180);
 
+/// Timeout for connecting through a Noise tunnel before falling back to a
+/// plain connection
+pub const NOISE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(15);
+
 /// When establishing a TCP connection, connections to different IP addresses are
 /// raced between each other with each new attempt being given an additional delay
 /// before it starts.
@@ -31,6 +31,9 @@ use crate::{
     ServiceConnectionInfo, StreamAndInfo, TransportConnector,
 };
 
+mod deflate;
+pub use deflate::{DeflateConfig, DeflateStream};
+
 pub mod error;
 pub use error::{Error, WebSocketConnectError};
 
@@ -103,6 +106,7 @@ impl<T: TransportConnector, E> WebSocketClientConnector<T, E> {
                     headers: Default::default(),
                     ws_config,
                     endpoint,
+                    deflate: None,
                 },
                 max_connection_time,
             ),
@@ -174,7 +178,7 @@ impl<Inner> Connector<(WebSocketRouteFragment, HttpRouteFragment), Inner> for St
 where
     Inner: AsyncDuplexStream,
 {
-    type Connection = tokio_tungstenite::WebSocketStream<Inner>;
+    type Connection = tokio_tungstenite::WebSocketStream<DeflateStream<Inner>>;
 
     type Error = tungstenite::Error;
 
@@ -189,6 +193,7 @@ where
                 ws_config,
                 endpoint,
                 headers,
+                deflate,
             },
             HttpRouteFragment {
                 host_header,
@@ -212,6 +217,9 @@ where
 
             let mut builder = http::Request::builder();
             *builder.headers_mut().expect("no headers, so not invalid") = headers;
+            if let Some(deflate) = &deflate {
+                builder = builder.header(http::header::SEC_WEBSOCKET_EXTENSIONS, deflate.offer());
+            }
 
             let request = builder
                 .header(http::header::HOST, &*host_header)
@@ -226,6 +234,9 @@ where
                 )
                 .body(())?;
 
+            // The adapter picks up the server's answer to the offer from the
+            // handshake response.
+            let inner = DeflateStream::new(inner, deflate);
             let (stream, _response) =
                 tokio_tungstenite::client_async_with_config(request, inner, Some(ws_config))
                     .await?;
@@ -319,6 +330,8 @@ where
             ws_config,
             endpoint,
             headers,
+            // Only routes connected with `Stateless` negotiate compression.
+            deflate: _,
         } = &self.fragment;
         let connection_params = connection_params
             .clone()
@@ -640,8 +653,10 @@ pub mod testutil {
     use crate::timeouts::{WS_KEEP_ALIVE_INTERVAL, WS_MAX_IDLE_INTERVAL};
     use crate::AsyncDuplexStream;
 
-    pub async fn fake_websocket() -> (WebSocketStream<DuplexStream>, WebSocketStream<DuplexStream>)
-    {
+    pub async fn fake_websocket() -> (
+        WebSocketStream<DuplexStream>,
+        WebSocketStream<DeflateStream<DuplexStream>>,
+    ) {
         let (client, server) = tokio::io::duplex(1024);
         let client_future = Stateless.connect_over(
             client,
@@ -650,6 +665,7 @@ pub mod testutil {
                     ws_config: WebSocketConfig::default(),
                     endpoint: PathAndQuery::from_static("/"),
                     headers: Default::default(),
+                    deflate: None,
                 },
                 HttpRouteFragment {
                     host_header: "localhost".into(),
@@ -0,0 +1,1135 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Support for the permessage-deflate websocket extension ([RFC 7692]).
+//!
+//! [`tungstenite`] doesn't implement any websocket extensions, so compression
+//! is done by [`DeflateStream`], a byte-stream adapter that sits between the
+//! transport and the websocket implementation. It watches the server's
+//! handshake response to see whether the offered extension was accepted, and
+//! if so rewrites frames in both directions: outgoing messages are compressed
+//! on their way to the transport, and incoming compressed messages are
+//! inflated before `tungstenite` sees them.
+//!
+//! [RFC 7692]: https://www.rfc-editor.org/rfc/rfc7692
+
+use std::fmt::Write as _;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf as _, BufMut as _, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{Connection, TransportInfo};
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// The bytes a sync flush ends with.
+///
+/// These are left off of compressed messages on the wire, and have to be added
+/// back before decompressing.
+const SYNC_FLUSH_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// The smallest window size that can be negotiated.
+const MIN_WINDOW_BITS: u8 = 8;
+/// The largest (and default) window size.
+const MAX_WINDOW_BITS: u8 = 15;
+/// The smallest window size zlib can use for raw deflate streams.
+const MIN_ZLIB_WINDOW_BITS: u8 = 9;
+
+/// Limit on the size of the server's handshake response headers.
+const MAX_RESPONSE_HEAD_LEN: usize = 16 * 1024;
+/// How much to read from the transport at a time.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+/// How much output to buffer before applying backpressure to writers.
+const WRITE_BUFFER_LIMIT: usize = 64 * 1024;
+
+const FIN_BIT: u8 = 0x80;
+const RSV1_BIT: u8 = 0x40;
+const OPCODE_MASK: u8 = 0x0f;
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_FIRST_CONTROL: u8 = 0x8;
+const MASK_BIT: u8 = 0x80;
+
+/// Parameters to offer when negotiating permessage-deflate compression.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeflateConfig {
+    /// Ask the server to compress each message independently.
+    ///
+    /// This lets the client discard its decompression state between messages,
+    /// at the cost of worse compression of the server's messages.
+    pub server_no_context_takeover: bool,
+    /// Compress each outgoing message independently.
+    pub client_no_context_takeover: bool,
+    /// The largest LZ77 window the server may use, as a base-2 logarithm
+    /// between 8 and 15.
+    ///
+    /// Smaller windows reduce the memory needed to decompress the server's
+    /// messages.
+    pub server_max_window_bits: u8,
+    /// The largest LZ77 window to use when compressing, as a base-2 logarithm
+    /// between 9 and 15.
+    pub client_max_window_bits: u8,
+    /// The largest incoming message to accept, after decompression.
+    ///
+    /// This bounds how much memory a small compressed message can expand to.
+    pub max_message_size: usize,
+    /// Outgoing messages shorter than this are sent uncompressed.
+    pub min_compressed_message_size: usize,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: MAX_WINDOW_BITS,
+            client_max_window_bits: MAX_WINDOW_BITS,
+            // Matches tungstenite's default limit for uncompressed messages.
+            max_message_size: 64 << 20,
+            min_compressed_message_size: 64,
+        }
+    }
+}
+
+impl DeflateConfig {
+    fn server_window_bits(&self) -> u8 {
+        self.server_max_window_bits
+            .clamp(MIN_WINDOW_BITS, MAX_WINDOW_BITS)
+    }
+
+    fn client_window_bits(&self) -> u8 {
+        self.client_max_window_bits
+            .clamp(MIN_ZLIB_WINDOW_BITS, MAX_WINDOW_BITS)
+    }
+
+    /// The value to send in the `Sec-WebSocket-Extensions` request header.
+    pub(crate) fn offer(&self) -> String {
+        let mut offer = EXTENSION_NAME.to_owned();
+        if self.server_no_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+        let server_bits = self.server_window_bits();
+        if server_bits < MAX_WINDOW_BITS {
+            write!(offer, "; server_max_window_bits={server_bits}").expect("can format");
+        }
+        write!(
+            offer,
+            "; client_max_window_bits={}",
+            self.client_window_bits()
+        )
+        .expect("can format");
+        offer
+    }
+}
+
+/// Compression parameters agreed on with the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Negotiated {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_window_bits: u8,
+    client_window_bits: u8,
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Checks the server's response to an offer made with `config`.
+///
+/// Returns `Ok(None)` if the server declined compression, and an error if it
+/// accepted with parameters that weren't offered.
+fn negotiate(config: &DeflateConfig, response: &str) -> io::Result<Option<Negotiated>> {
+    let response = response.trim();
+    if response.is_empty() {
+        return Ok(None);
+    }
+    // Only one extension was offered, so only one can be accepted.
+    if response.contains(',') {
+        return Err(invalid_data(
+            "server accepted unrequested websocket extensions",
+        ));
+    }
+
+    let mut params = response.split(';').map(str::trim);
+    if !params
+        .next()
+        .is_some_and(|name| name.eq_ignore_ascii_case(EXTENSION_NAME))
+    {
+        return Err(invalid_data(
+            "server accepted an unrequested websocket extension",
+        ));
+    }
+
+    let mut server_no_context_takeover = false;
+    let mut client_no_context_takeover = false;
+    let mut server_max_window_bits = None;
+    let mut client_max_window_bits = None;
+    let mut seen = Vec::new();
+    for param in params {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        let name = name.to_ascii_lowercase();
+        if seen.contains(&name) {
+            return Err(invalid_data("repeated permessage-deflate parameter"));
+        }
+        match (name.as_str(), value) {
+            ("server_no_context_takeover", None) => server_no_context_takeover = true,
+            ("client_no_context_takeover", None) => client_no_context_takeover = true,
+            ("server_max_window_bits", Some(value)) => {
+                server_max_window_bits = Some(parse_window_bits(value)?)
+            }
+            ("client_max_window_bits", Some(value)) => {
+                client_max_window_bits = Some(parse_window_bits(value)?)
+            }
+            _ => return Err(invalid_data("unsupported permessage-deflate parameter")),
+        }
+        seen.push(name);
+    }
+
+    let offered_server_bits = config.server_window_bits();
+    let server_window_bits = server_max_window_bits.unwrap_or(MAX_WINDOW_BITS);
+    if offered_server_bits < MAX_WINDOW_BITS && server_window_bits > offered_server_bits {
+        return Err(invalid_data(
+            "server chose a larger permessage-deflate window than offered",
+        ));
+    }
+    let client_window_bits = client_max_window_bits
+        .unwrap_or(MAX_WINDOW_BITS)
+        .min(config.client_window_bits());
+
+    Ok(Some(Negotiated {
+        server_no_context_takeover,
+        client_no_context_takeover: config.client_no_context_takeover || client_no_context_takeover,
+        server_window_bits,
+        client_window_bits,
+    }))
+}
+
+fn parse_window_bits(value: &str) -> io::Result<u8> {
+    value
+        .parse()
+        .ok()
+        .filter(|bits| (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(bits))
+        .ok_or_else(|| invalid_data("invalid permessage-deflate window size"))
+}
+
+/// Finds the `Sec-WebSocket-Extensions` value in a complete response head.
+///
+/// Returns `None` if the response isn't a successful websocket upgrade, in
+/// which case `tungstenite` will report the failure.
+fn extensions_header(head: &[u8]) -> Option<String> {
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?;
+    if status.split_ascii_whitespace().nth(1) != Some("101") {
+        return None;
+    }
+    let values = lines
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| {
+            name.trim()
+                .eq_ignore_ascii_case(http::header::SEC_WEBSOCKET_EXTENSIONS.as_str())
+        })
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+    Some(values.join(", "))
+}
+
+/// A single websocket frame.
+struct Frame {
+    /// The first byte of the header: the FIN and RSV bits, and the opcode.
+    head: u8,
+    mask: Option<[u8; 4]>,
+    /// The unmasked payload.
+    payload: BytesMut,
+}
+
+impl Frame {
+    /// Removes a complete frame from the front of `buffer`, if there is one.
+    fn parse(buffer: &mut BytesMut, max_payload_len: usize) -> io::Result<Option<Self>> {
+        let Some(&[head, second]) = buffer.get(..2) else {
+            return Ok(None);
+        };
+        let (payload_len, len_end) = match second & !MASK_BIT {
+            126 => match buffer.get(2..4) {
+                Some(len) => (
+                    u64::from(u16::from_be_bytes(len.try_into().expect("right size"))),
+                    4,
+                ),
+                None => return Ok(None),
+            },
+            127 => match buffer.get(2..10) {
+                Some(len) => (u64::from_be_bytes(len.try_into().expect("right size")), 10),
+                None => return Ok(None),
+            },
+            len => (u64::from(len), 2),
+        };
+        let header_len = if second & MASK_BIT != 0 {
+            len_end + 4
+        } else {
+            len_end
+        };
+        let payload_len = usize::try_from(payload_len)
+            .ok()
+            .filter(|len| *len <= max_payload_len)
+            .ok_or_else(|| invalid_data("websocket frame too large"))?;
+
+        let Some(frame_len) = header_len.checked_add(payload_len) else {
+            return Err(invalid_data("websocket frame too large"));
+        };
+        if buffer.len() < frame_len {
+            return Ok(None);
+        }
+
+        let mask = (header_len > len_end)
+            .then(|| buffer[len_end..header_len].try_into().expect("right size"));
+        buffer.advance(header_len);
+        let mut payload = buffer.split_to(payload_len);
+        if let Some(mask) = mask {
+            apply_mask(&mut payload, mask);
+        }
+        Ok(Some(Self {
+            head,
+            mask,
+            payload,
+        }))
+    }
+
+    fn is_final(&self) -> bool {
+        self.head & FIN_BIT != 0
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.head & RSV1_BIT != 0
+    }
+
+    fn opcode(&self) -> u8 {
+        self.head & OPCODE_MASK
+    }
+
+    fn is_control(&self) -> bool {
+        self.opcode() >= OPCODE_FIRST_CONTROL
+    }
+
+    /// Appends the frame to `out` in its wire format.
+    fn encode(&self, out: &mut BytesMut) {
+        let Self {
+            head,
+            mask,
+            payload,
+        } = self;
+        let mask_bit = if mask.is_some() { MASK_BIT } else { 0 };
+
+        out.reserve(14 + payload.len());
+        out.put_u8(*head);
+        match u8::try_from(payload.len()) {
+            Ok(len) if len < 126 => out.put_u8(mask_bit | len),
+            _ => match u16::try_from(payload.len()) {
+                Ok(len) => {
+                    out.put_u8(mask_bit | 126);
+                    out.put_u16(len);
+                }
+                Err(_) => {
+                    out.put_u8(mask_bit | 127);
+                    out.put_u64(payload.len().try_into().expect("usize fits in u64"));
+                }
+            },
+        }
+
+        if let Some(mask) = mask {
+            out.put_slice(mask);
+        }
+        let payload_start = out.len();
+        out.put_slice(payload);
+        if let Some(mask) = mask {
+            apply_mask(&mut out[payload_start..], *mask);
+        }
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (byte, mask) in payload.iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= mask;
+    }
+}
+
+/// Compression state for a connection where permessage-deflate was negotiated.
+struct Codec {
+    negotiated: Negotiated,
+    max_message_size: usize,
+    min_compressed_message_size: usize,
+    decompressor: Decompress,
+    /// `None` if the server limited the window to a size zlib can't produce.
+    compressor: Option<Compress>,
+    /// Whether the message currently being received is compressed.
+    receiving_compressed: bool,
+    /// The decompressed size so far of the message being received.
+    received_len: usize,
+    /// Whether the message currently being sent is compressed.
+    sending_compressed: bool,
+}
+
+impl Codec {
+    fn new(config: &DeflateConfig, negotiated: Negotiated) -> Self {
+        let compressor = (negotiated.client_window_bits >= MIN_ZLIB_WINDOW_BITS).then(|| {
+            Compress::new_with_window_bits(
+                Compression::default(),
+                false,
+                negotiated.client_window_bits,
+            )
+        });
+        // A decompressor with a larger window can read anything produced with
+        // a smaller one.
+        let decompressor = Decompress::new_with_window_bits(
+            false,
+            negotiated.server_window_bits.max(MIN_ZLIB_WINDOW_BITS),
+        );
+        Self {
+            negotiated,
+            max_message_size: config.max_message_size,
+            min_compressed_message_size: config.min_compressed_message_size,
+            decompressor,
+            compressor,
+            receiving_compressed: false,
+            received_len: 0,
+            sending_compressed: false,
+        }
+    }
+
+    /// Decompresses an incoming frame if it's part of a compressed message.
+    fn decode_incoming(&mut self, mut frame: Frame) -> io::Result<Frame> {
+        // Control frames are never compressed, and interleave with fragments
+        // of data messages without interrupting them.
+        if frame.is_control() {
+            return Ok(frame);
+        }
+        if frame.opcode() != OPCODE_CONTINUATION {
+            self.receiving_compressed = frame.is_compressed();
+            self.received_len = 0;
+        } else if frame.is_compressed() {
+            // Only the first frame of a message is marked; leave it for
+            // tungstenite to reject.
+            return Ok(frame);
+        }
+        if !self.receiving_compressed {
+            return Ok(frame);
+        }
+
+        let is_final = frame.is_final();
+        if is_final {
+            frame.payload.extend_from_slice(&SYNC_FLUSH_TRAILER);
+        }
+        let limit = self.max_message_size - self.received_len;
+        let payload = inflate(&mut self.decompressor, &frame.payload, limit)?;
+        self.received_len += payload.len();
+        if is_final && self.negotiated.server_no_context_takeover {
+            self.decompressor.reset(false);
+        }
+
+        Ok(Frame {
+            head: frame.head & !RSV1_BIT,
+            mask: frame.mask,
+            payload: payload[..].into(),
+        })
+    }
+
+    /// Compresses an outgoing frame if it's part of a message worth
+    /// compressing.
+    fn encode_outgoing(&mut self, frame: Frame) -> io::Result<Frame> {
+        if frame.is_control() {
+            return Ok(frame);
+        }
+        if frame.opcode() != OPCODE_CONTINUATION {
+            self.sending_compressed = self.compressor.is_some()
+                && frame.payload.len() >= self.min_compressed_message_size;
+        }
+        if !self.sending_compressed {
+            return Ok(frame);
+        }
+
+        let compressor = self
+            .compressor
+            .as_mut()
+            .expect("checked at the start of the message");
+        let mut payload = deflate(compressor, &frame.payload)?;
+        let mut head = frame.head;
+        if frame.opcode() != OPCODE_CONTINUATION {
+            head |= RSV1_BIT;
+        }
+        if frame.is_final() {
+            if payload.ends_with(&SYNC_FLUSH_TRAILER) {
+                payload.truncate(payload.len() - SYNC_FLUSH_TRAILER.len());
+            }
+            if self.negotiated.client_no_context_takeover {
+                compressor.reset();
+            }
+        }
+
+        Ok(Frame {
+            head,
+            mask: frame.mask,
+            payload: payload[..].into(),
+        })
+    }
+}
+
+/// Decompresses `input`, failing if the output would exceed `limit` bytes.
+fn inflate(decompressor: &mut Decompress, mut input: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len().saturating_mul(2).min(limit));
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(READ_CHUNK_LEN);
+        }
+        let (in_before, out_before) = (decompressor.total_in(), output.len());
+        let status = decompressor
+            .decompress_vec(input, &mut output, FlushDecompress::Sync)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let consumed =
+            usize::try_from(decompressor.total_in() - in_before).expect("bounded by input length");
+        input = &input[consumed..];
+
+        if output.len() > limit {
+            return Err(invalid_data("decompressed websocket message too large"));
+        }
+        if status == Status::StreamEnd {
+            // The sender ended the deflate stream; the next message starts a
+            // new one.
+            decompressor.reset(false);
+            return Ok(output);
+        }
+        let made_progress = consumed != 0 || output.len() != out_before;
+        if !made_progress || (input.is_empty() && output.len() < output.capacity()) {
+            return Ok(output);
+        }
+    }
+}
+
+/// Compresses `input` and flushes the result to a byte boundary.
+fn deflate(compressor: &mut Compress, mut input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(READ_CHUNK_LEN);
+        }
+        let in_before = compressor.total_in();
+        compressor
+            .compress_vec(input, &mut output, FlushCompress::Sync)
+            .map_err(io::Error::other)?;
+        let consumed =
+            usize::try_from(compressor.total_in() - in_before).expect("bounded by input length");
+        input = &input[consumed..];
+        if input.is_empty() && output.len() < output.capacity() {
+            return Ok(output);
+        }
+    }
+}
+
+/// Client-side stream adapter that implements permessage-deflate.
+///
+/// Wraps the transport underneath a websocket client. If created with a
+/// [`DeflateConfig`], the caller is expected to have offered it in the
+/// handshake request (see [`DeflateConfig::offer`]); the adapter picks up the
+/// server's answer from the handshake response and compresses or decompresses
+/// messages accordingly. Without a config, or if the server declines, bytes
+/// pass through unchanged.
+pub struct DeflateStream<S> {
+    inner: S,
+    state: State,
+    /// Bytes read from `inner` that haven't been processed yet.
+    read_pending: BytesMut,
+    /// Processed bytes waiting to be read.
+    read_ready: BytesMut,
+    /// Written bytes that don't yet make up a complete frame.
+    write_pending: BytesMut,
+    /// Processed bytes waiting to be written to `inner`.
+    write_ready: BytesMut,
+}
+
+enum State {
+    /// Waiting for the end of the server's handshake response.
+    Handshake {
+        config: DeflateConfig,
+        response: Vec<u8>,
+    },
+    /// Compression wasn't negotiated.
+    Passthrough,
+    Deflate(Box<Codec>),
+}
+
+impl<S> DeflateStream<S> {
+    pub fn new(inner: S, config: Option<DeflateConfig>) -> Self {
+        let state = match config {
+            Some(config) => State::Handshake {
+                config,
+                response: Vec::new(),
+            },
+            None => State::Passthrough,
+        };
+        Self {
+            inner,
+            state,
+            read_pending: BytesMut::new(),
+            read_ready: BytesMut::new(),
+            write_pending: BytesMut::new(),
+            write_ready: BytesMut::new(),
+        }
+    }
+
+    /// Whether the server agreed to use compression.
+    ///
+    /// Only meaningful once the websocket handshake has completed.
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.state, State::Deflate(_))
+    }
+
+    /// Processes buffered input, returning `false` if more is needed.
+    fn process_read(&mut self) -> io::Result<bool> {
+        let Self {
+            state,
+            read_pending,
+            read_ready,
+            ..
+        } = self;
+        if read_pending.is_empty() {
+            return Ok(false);
+        }
+
+        match state {
+            State::Passthrough => {
+                *read_ready = read_pending.split();
+                Ok(true)
+            }
+            State::Handshake { config, response } => {
+                let config = *config;
+                // The terminator might straddle the previous read.
+                let search_start = response.len().saturating_sub(3);
+                response.extend_from_slice(read_pending);
+                let Some(terminator) = response[search_start..]
+                    .windows(4)
+                    .position(|window| window == b"\r\n\r\n")
+                else {
+                    if response.len() > MAX_RESPONSE_HEAD_LEN {
+                        return Err(invalid_data("websocket handshake response too large"));
+                    }
+                    *read_ready = read_pending.split();
+                    return Ok(true);
+                };
+
+                let head_len = search_start + terminator + 4;
+                let already_seen = response.len() - read_pending.len();
+                *read_ready = read_pending.split_to(head_len - already_seen);
+                response.truncate(head_len);
+
+                let negotiated = match extensions_header(response) {
+                    Some(extensions) => negotiate(&config, &extensions)?,
+                    None => None,
+                };
+                *state = match negotiated {
+                    Some(negotiated) => State::Deflate(Box::new(Codec::new(&config, negotiated))),
+                    None => State::Passthrough,
+                };
+                Ok(true)
+            }
+            State::Deflate(codec) => {
+                let Some(frame) = Frame::parse(read_pending, codec.max_message_size)? else {
+                    return Ok(false);
+                };
+                codec.decode_incoming(frame)?.encode(read_ready);
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for DeflateStream<S>
+where
+    S: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let negotiated = match &self.state {
+            State::Deflate(codec) => Some(&codec.negotiated),
+            State::Handshake { .. } | State::Passthrough => None,
+        };
+        f.debug_struct("DeflateStream")
+            .field("inner", &self.inner)
+            .field("negotiated", &negotiated)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Connection> Connection for DeflateStream<S> {
+    fn transport_info(&self) -> TransportInfo {
+        self.inner.transport_info()
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.read_ready.is_empty() {
+                let count = buf.remaining().min(this.read_ready.len());
+                buf.put_slice(&this.read_ready[..count]);
+                this.read_ready.advance(count);
+                return Poll::Ready(Ok(()));
+            }
+            if matches!(this.state, State::Passthrough) && this.read_pending.is_empty() {
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            }
+            if this.process_read()? {
+                continue;
+            }
+
+            let mut chunk = [0; READ_CHUNK_LEN];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            this.read_pending.extend_from_slice(chunk.filled());
+            if chunk.filled().is_empty() {
+                // Pass along anything left over so the websocket can report
+                // the truncated frame.
+                this.read_ready = this.read_pending.split();
+                if this.read_ready.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+}
+
+/// Writes all of `buffer` to `inner`.
+fn poll_write_all<S: AsyncWrite + Unpin>(
+    inner: &mut S,
+    cx: &mut Context<'_>,
+    buffer: &mut BytesMut,
+) -> Poll<io::Result<()>> {
+    while !buffer.is_empty() {
+        let written = ready!(Pin::new(&mut *inner).poll_write(cx, buffer))?;
+        if written == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        buffer.advance(written);
+    }
+    Poll::Ready(Ok(()))
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let Self {
+            inner,
+            state,
+            write_pending,
+            write_ready,
+            ..
+        } = self.get_mut();
+
+        // The handshake request and any uncompressed connection pass through.
+        let State::Deflate(codec) = state else {
+            return Pin::new(inner).poll_write(cx, buf);
+        };
+
+        if write_ready.len() >= WRITE_BUFFER_LIMIT {
+            ready!(poll_write_all(inner, cx, write_ready))?;
+        }
+
+        write_pending.extend_from_slice(buf);
+        while let Some(frame) = Frame::parse(write_pending, usize::MAX)? {
+            codec.encode_outgoing(frame)?.encode(write_ready);
+        }
+
+        // Get started on writing, but don't wait; that's what flushing is for.
+        if let Poll::Ready(Err(e)) = poll_write_all(inner, cx, write_ready) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_write_all(&mut this.inner, cx, &mut this.write_ready))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_write_all(&mut this.inner, cx, &mut this.write_ready))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use http::uri::PathAndQuery;
+    use test_case::test_case;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _, DuplexStream};
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::Message;
+
+    use super::*;
+    use crate::route::{Connector as _, HttpRouteFragment, WebSocketRouteFragment};
+    use crate::ws::Stateless;
+
+    const TEXT_FRAME: u8 = FIN_BIT | 0x1;
+    const LONG_MESSAGE: &str = "the quick brown fox jumps over the lazy dog. \
+        the quick brown fox jumps over the lazy dog. \
+        the quick brown fox jumps over the lazy dog.";
+
+    /// The server end of a websocket connection, handled a frame at a time.
+    struct RawServer {
+        stream: DuplexStream,
+        buffer: BytesMut,
+    }
+
+    impl RawServer {
+        /// Completes the handshake, accepting `extensions` if present.
+        ///
+        /// Returns the extensions offered by the client.
+        async fn accept(mut stream: DuplexStream, extensions: Option<&str>) -> (Self, String) {
+            let mut buffer = BytesMut::new();
+            let head_len = loop {
+                if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                assert_ne!(stream.read_buf(&mut buffer).await.unwrap(), 0);
+            };
+            let request = buffer.split_to(head_len);
+            let request = std::str::from_utf8(&request).unwrap();
+            let header = |name: &str| {
+                request
+                    .split("\r\n")
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.trim().to_owned())
+            };
+
+            let key = header("sec-websocket-key").expect("has key");
+            let mut response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                Upgrade: websocket\r\n\
+                Connection: Upgrade\r\n\
+                Sec-WebSocket-Accept: {}\r\n",
+                tungstenite::handshake::derive_accept_key(key.as_bytes())
+            );
+            if let Some(extensions) = extensions {
+                response.push_str(&format!("Sec-WebSocket-Extensions: {extensions}\r\n"));
+            }
+            response.push_str("\r\n");
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            let offered = header("sec-websocket-extensions").unwrap_or_default();
+            (Self { stream, buffer }, offered)
+        }
+
+        async fn read_frame(&mut self) -> Frame {
+            loop {
+                if let Some(frame) = Frame::parse(&mut self.buffer, usize::MAX).unwrap() {
+                    return frame;
+                }
+                assert_ne!(self.stream.read_buf(&mut self.buffer).await.unwrap(), 0);
+            }
+        }
+
+        async fn write_frame(&mut self, head: u8, payload: &[u8]) {
+            let mut out = BytesMut::new();
+            Frame {
+                head,
+                mask: None,
+                payload: payload.into(),
+            }
+            .encode(&mut out);
+            self.stream.write_all(&out).await.unwrap();
+        }
+    }
+
+    async fn connect(
+        config: Option<DeflateConfig>,
+        accepted_extensions: Option<&str>,
+    ) -> (
+        Result<WebSocketStream<DeflateStream<DuplexStream>>, tungstenite::Error>,
+        RawServer,
+        String,
+    ) {
+        let (client, server) = tokio::io::duplex(1024);
+        let client_future = Stateless.connect_over(
+            client,
+            (
+                WebSocketRouteFragment {
+                    ws_config: Default::default(),
+                    endpoint: PathAndQuery::from_static("/"),
+                    headers: Default::default(),
+                    deflate: config,
+                },
+                HttpRouteFragment {
+                    host_header: "localhost".into(),
+                    path_prefix: "".into(),
+                    front_name: None,
+                },
+            ),
+            "test".into(),
+        );
+        let server_future = RawServer::accept(server, accepted_extensions);
+        let (client, (server, offered)) = tokio::join!(client_future, server_future);
+        (client, server, offered)
+    }
+
+    /// Compresses a whole message the way a server would.
+    fn compress_message(compressor: &mut Compress, message: &[u8]) -> Vec<u8> {
+        let mut compressed = deflate(compressor, message).unwrap();
+        assert!(compressed.ends_with(&SYNC_FLUSH_TRAILER));
+        compressed.truncate(compressed.len() - SYNC_FLUSH_TRAILER.len());
+        compressed
+    }
+
+    fn decompress_message(decompressor: &mut Decompress, compressed: &[u8]) -> Vec<u8> {
+        let input = [compressed, &SYNC_FLUSH_TRAILER].concat();
+        inflate(decompressor, &input, usize::MAX).unwrap()
+    }
+
+    #[test]
+    fn offer_format() {
+        assert_eq!(
+            DeflateConfig::default().offer(),
+            "permessage-deflate; client_max_window_bits=15"
+        );
+        assert_eq!(
+            DeflateConfig {
+                server_no_context_takeover: true,
+                client_no_context_takeover: true,
+                server_max_window_bits: 10,
+                client_max_window_bits: 8,
+                ..Default::default()
+            }
+            .offer(),
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover; \
+            server_max_window_bits=10; client_max_window_bits=9"
+        );
+    }
+
+    #[test_case("" => None; "declined")]
+    #[test_case("permessage-deflate" => Some((false, false, 15, 15)); "no parameters")]
+    #[test_case(
+        "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+        => Some((true, false, 15, 10)); "with parameters")]
+    #[test_case(
+        "Permessage-Deflate; Client_No_Context_Takeover; server_max_window_bits=\"12\""
+        => Some((false, true, 12, 15)); "case and quoting")]
+    fn negotiate_accepted(response: &str) -> Option<(bool, bool, u8, u8)> {
+        negotiate(&DeflateConfig::default(), response)
+            .expect("valid")
+            .map(
+                |Negotiated {
+                     server_no_context_takeover,
+                     client_no_context_takeover,
+                     server_window_bits,
+                     client_window_bits,
+                 }| {
+                    (
+                        server_no_context_takeover,
+                        client_no_context_takeover,
+                        server_window_bits,
+                        client_window_bits,
+                    )
+                },
+            )
+    }
+
+    #[test_case("x-webkit-deflate-frame"; "other extension")]
+    #[test_case("permessage-deflate, permessage-deflate"; "multiple extensions")]
+    #[test_case("permessage-deflate; unknown_parameter"; "unknown parameter")]
+    #[test_case("permessage-deflate; server_max_window_bits=16"; "window too large")]
+    #[test_case("permessage-deflate; server_max_window_bits"; "window missing value")]
+    #[test_case("permessage-deflate; server_max_window_bits=12"; "window larger than offered")]
+    #[test_case("permessage-deflate; server_no_context_takeover=1"; "flag with value")]
+    #[test_case(
+        "permessage-deflate; client_no_context_takeover; client_no_context_takeover";
+        "repeated parameter")]
+    fn negotiate_rejected(response: &str) {
+        let config = DeflateConfig {
+            server_max_window_bits: 11,
+            ..Default::default()
+        };
+        assert_matches!(negotiate(&config, response), Err(e) if e.kind() == io::ErrorKind::InvalidData);
+    }
+
+    #[test_case(false; "with context takeover")]
+    #[test_case(true; "without context takeover")]
+    #[tokio::test]
+    async fn compresses_in_both_directions(no_context_takeover: bool) {
+        let config = DeflateConfig {
+            client_no_context_takeover: no_context_takeover,
+            server_no_context_takeover: no_context_takeover,
+            ..Default::default()
+        };
+        let accepted = if no_context_takeover {
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover"
+        } else {
+            "permessage-deflate"
+        };
+        let (client, mut server, offered) = connect(Some(config), Some(accepted)).await;
+        let mut client = client.expect("can connect");
+        assert_eq!(offered, config.offer());
+        assert!(client.get_ref().is_compressed());
+
+        let mut server_compressor = Compress::new(Compression::default(), false);
+        for _ in 0..2 {
+            let compressed = compress_message(&mut server_compressor, LONG_MESSAGE.as_bytes());
+            server.write_frame(TEXT_FRAME | RSV1_BIT, &compressed).await;
+            if no_context_takeover {
+                server_compressor.reset();
+            }
+            assert_matches!(
+                client.next().await,
+                Some(Ok(Message::Text(text))) if text == LONG_MESSAGE
+            );
+        }
+
+        let mut server_decompressor = Decompress::new(false);
+        let mut compressed_lengths = vec![];
+        for _ in 0..2 {
+            client
+                .send(Message::Text(LONG_MESSAGE.to_owned()))
+                .await
+                .unwrap();
+            let frame = server.read_frame().await;
+            assert_eq!(frame.head, TEXT_FRAME | RSV1_BIT);
+            assert!(frame.mask.is_some());
+            assert_eq!(
+                decompress_message(&mut server_decompressor, &frame.payload),
+                LONG_MESSAGE.as_bytes()
+            );
+            if no_context_takeover {
+                server_decompressor.reset(false);
+            }
+            compressed_lengths.push(frame.payload.len());
+        }
+
+        assert!(compressed_lengths[0] < LONG_MESSAGE.len());
+        // Later messages can refer back to earlier ones unless the context is
+        // reset in between.
+        if no_context_takeover {
+            assert_eq!(compressed_lengths[1], compressed_lengths[0]);
+        } else {
+            assert!(compressed_lengths[1] < compressed_lengths[0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn decompresses_fragmented_message() {
+        let (client, mut server, _offered) =
+            connect(Some(DeflateConfig::default()), Some("permessage-deflate")).await;
+        let mut client = client.expect("can connect");
+
+        let mut server_compressor = Compress::new(Compression::default(), false);
+        let compressed = compress_message(&mut server_compressor, LONG_MESSAGE.as_bytes());
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+
+        // The first frame is marked as compressed, and a control frame can
+        // appear before the message is finished.
+        server.write_frame(RSV1_BIT | 0x1, first).await;
+        server.write_frame(FIN_BIT | 0x9, b"ping").await;
+        server.write_frame(FIN_BIT, second).await;
+
+        assert_matches!(client.next().await, Some(Ok(Message::Ping(ping))) if ping == b"ping");
+        assert_matches!(
+            client.next().await,
+            Some(Ok(Message::Text(text))) if text == LONG_MESSAGE
+        );
+    }
+
+    #[tokio::test]
+    async fn short_messages_are_sent_uncompressed() {
+        let (client, mut server, _offered) =
+            connect(Some(DeflateConfig::default()), Some("permessage-deflate")).await;
+        let mut client = client.expect("can connect");
+
+        client.send(Message::Text("hi".to_owned())).await.unwrap();
+        let frame = server.read_frame().await;
+        assert_eq!(frame.head, TEXT_FRAME);
+        assert_eq!(&frame.payload[..], b"hi");
+
+        // Uncompressed messages from the server are fine too.
+        server.write_frame(TEXT_FRAME, b"hello").await;
+        assert_matches!(client.next().await, Some(Ok(Message::Text(text))) if text == "hello");
+    }
+
+    #[test_case(Some(DeflateConfig::default()), None; "declined")]
+    #[test_case(None, None; "not offered")]
+    #[tokio::test]
+    async fn uncompressed_connection(
+        config: Option<DeflateConfig>,
+        accepted_extensions: Option<&str>,
+    ) {
+        let (client, mut server, offered) = connect(config, accepted_extensions).await;
+        let mut client = client.expect("can connect");
+        assert_eq!(offered, config.map(|c| c.offer()).unwrap_or_default());
+        assert!(!client.get_ref().is_compressed());
+
+        client
+            .send(Message::Text(LONG_MESSAGE.to_owned()))
+            .await
+            .unwrap();
+        let frame = server.read_frame().await;
+        assert_eq!(frame.head, TEXT_FRAME);
+        assert_eq!(&frame.payload[..], LONG_MESSAGE.as_bytes());
+
+        server.write_frame(TEXT_FRAME, b"hello").await;
+        assert_matches!(client.next().await, Some(Ok(Message::Text(text))) if text == "hello");
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_message() {
+        let config = DeflateConfig {
+            max_message_size: 1000,
+            ..Default::default()
+        };
+        let (client, mut server, _offered) =
+            connect(Some(config), Some("permessage-deflate")).await;
+        let mut client = client.expect("can connect");
+
+        let mut server_compressor = Compress::new(Compression::default(), false);
+        let compressed = compress_message(&mut server_compressor, &[b'a'; 1001]);
+        assert!(compressed.len() < 100);
+        server
+            .write_frame(FIN_BIT | RSV1_BIT | 0x2, &compressed)
+            .await;
+
+        assert_matches!(
+            client.next().await,
+            Some(Err(tungstenite::Error::Io(e))) if e.kind() == io::ErrorKind::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_response() {
+        let (client, _server, _offered) = connect(
+            Some(DeflateConfig::default()),
+            Some("permessage-deflate; unknown_parameter"),
+        )
+        .await;
+        assert_matches!(
+            client,
+            Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -8,12 +8,16 @@ use std::pin::Pin;
 use std::task::{ready, Poll};
 
 use bytes::Bytes;
+use futures_util::stream::FusedStream;
 use futures_util::{Sink, SinkExt as _, Stream, StreamExt as _};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_tungstenite::WebSocketStream;
 use tungstenite::protocol::frame::coding::CloseCode;
 use tungstenite::Message;
 
+use crate::{Connection, TransportInfo};
+
+#[derive(Debug)]
 pub struct WebSocketTransport<S>(pub WebSocketStream<S>);
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -89,6 +93,18 @@ impl<S: AsyncRead + AsyncWrite + Unpin> Stream for WebSocketTransport<S> {
     }
 }
 
+impl<S: AsyncRead + AsyncWrite + Unpin> FusedStream for WebSocketTransport<S> {
+    fn is_terminated(&self) -> bool {
+        self.0.is_terminated()
+    }
+}
+
+impl<S: Connection + AsyncRead + AsyncWrite + Unpin> Connection for WebSocketTransport<S> {
+    fn transport_info(&self) -> TransportInfo {
+        self.0.transport_info()
+    }
+}
+
 impl From<TransportError> for IoError {
     fn from(value: TransportError) -> Self {
         let kind = match &value {
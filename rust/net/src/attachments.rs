@@ -0,0 +1,886 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Resumable attachment transfers to and from CDNs.
+//!
+//! Uploads use the [tus] resumable upload protocol; downloads use HTTP range
+//! requests. Both run over an [`HttpsClient`], so they use the same routes,
+//! proxies, and domain fronting as other connections to Signal services.
+//!
+//! Transfers can be cancelled by dropping their futures; this is what happens
+//! when an app cancels an in-progress bridged call. The transfer can later be
+//! resumed from where it stopped, over the same or a new connection.
+//!
+//! [tus]: https://tus.io/protocols/resumable-upload
+
+use std::io::SeekFrom;
+use std::time::Duration;
+
+use bytes::{Buf as _, Bytes};
+use futures_util::StreamExt as _;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use libsignal_net_infra::errors::LogSafeDisplay;
+use libsignal_net_infra::http_client::{
+    request_body_from_bytes, request_body_from_stream, response_body_data,
+};
+use tokio::io::{
+    AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt as _, AsyncWrite, AsyncWriteExt as _,
+};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::https_client::{HttpsClient, HttpsClientError, HttpsRequest};
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod fake_cdn;
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const OFFSET_OCTET_STREAM: HeaderValue =
+    HeaderValue::from_static("application/offset+octet-stream");
+
+/// How much of an upload source to read at a time.
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Receives updates on how much of an attachment has been transferred.
+pub trait TransferProgress: Send + Sync {
+    /// Called as data is transferred.
+    ///
+    /// `transferred` counts from the start of the attachment, so it includes
+    /// data transferred before the transfer was resumed. `total` is `None` if
+    /// the length of a download isn't known yet.
+    fn on_progress(&self, transferred: u64, total: Option<u64>);
+}
+
+impl<F: Fn(u64, Option<u64>) + Send + Sync> TransferProgress for F {
+    fn on_progress(&self, transferred: u64, total: Option<u64>) {
+        self(transferred, total)
+    }
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum AttachmentTransferError {
+    /// {0}
+    Https(#[from] HttpsClientError),
+    /// CDN responded with unexpected status {0}
+    UnexpectedStatus(StatusCode),
+    /// CDN response had a missing or invalid {0} header
+    InvalidResponseHeader(HeaderName),
+    /// The upload no longer exists on the CDN
+    UploadNotFound,
+    /// The CDN stopped accepting data for the upload
+    UploadStalled,
+    /// The response ended before the whole attachment was received
+    IncompleteDownload,
+    /// The attachment changed on the CDN since the download started
+    AttachmentChanged,
+    /// IO error: {0}
+    Io(#[from] std::io::Error),
+}
+
+impl LogSafeDisplay for AttachmentTransferError {}
+
+impl From<AttachmentTransferError> for std::io::Error {
+    fn from(value: AttachmentTransferError) -> Self {
+        use std::io::ErrorKind;
+        let kind = match value {
+            AttachmentTransferError::Io(e) => return e,
+            AttachmentTransferError::Https(
+                HttpsClientError::TimeoutEstablishingConnection | HttpsClientError::RequestTimedOut,
+            ) => ErrorKind::TimedOut,
+            AttachmentTransferError::Https(_) => ErrorKind::ConnectionAborted,
+            AttachmentTransferError::UploadNotFound => ErrorKind::NotFound,
+            AttachmentTransferError::IncompleteDownload => ErrorKind::UnexpectedEof,
+            AttachmentTransferError::AttachmentChanged => ErrorKind::InvalidData,
+            AttachmentTransferError::UnexpectedStatus(_)
+            | AttachmentTransferError::InvalidResponseHeader(_)
+            | AttachmentTransferError::UploadStalled => ErrorKind::InvalidData,
+        };
+        Self::new(kind, value.to_string())
+    }
+}
+
+/// An upload that has been created on the CDN and can be appended to.
+///
+/// The location, headers, and length are all that's needed to resume the
+/// upload later, so they can be persisted if the upload should survive a
+/// restart.
+#[derive(Clone, Debug)]
+pub struct ResumableUpload {
+    location: PathAndQuery,
+    headers: HeaderMap,
+    length: u64,
+}
+
+/// A download that keeps track of how much has been received so it can be
+/// resumed with a range request.
+///
+/// Resumed requests carry an `If-Range` header with the attachment's validator
+/// (its `ETag`, or failing that its `Last-Modified` date) from the first
+/// response, so data from two different versions of an attachment is never
+/// combined.
+#[derive(Clone, Debug)]
+pub struct ResumableDownload {
+    path: PathAndQuery,
+    headers: HeaderMap,
+    offset: u64,
+    total_length: Option<u64>,
+    validator: Option<HeaderValue>,
+}
+
+impl ResumableUpload {
+    /// Creates a new upload of `length` bytes by posting to `create_path`.
+    ///
+    /// `headers` are sent with this and all subsequent requests for the
+    /// upload; they typically carry the authorization from an upload form.
+    pub async fn create(
+        client: &HttpsClient,
+        create_path: PathAndQuery,
+        headers: HeaderMap,
+        length: u64,
+        timeout: Duration,
+    ) -> Result<Self, AttachmentTransferError> {
+        let mut request_headers = tus_headers(&headers);
+        request_headers.insert(UPLOAD_LENGTH, length.into());
+
+        let (parts, _body) = client
+            .send(
+                HttpsRequest {
+                    method: Method::POST,
+                    path: create_path,
+                    headers: request_headers,
+                    body: request_body_from_bytes(Bytes::new()),
+                },
+                timeout,
+            )
+            .await?
+            .into_parts();
+        if parts.status != StatusCode::CREATED {
+            return Err(AttachmentTransferError::UnexpectedStatus(parts.status));
+        }
+
+        let location = parts
+            .headers
+            .get(http::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| location_path(location, client.host()))
+            .ok_or(AttachmentTransferError::InvalidResponseHeader(
+                http::header::LOCATION,
+            ))?;
+
+        Ok(Self {
+            location,
+            headers,
+            length,
+        })
+    }
+
+    /// Refers to an upload previously created with [`Self::create`].
+    pub fn resume(location: PathAndQuery, headers: HeaderMap, length: u64) -> Self {
+        Self {
+            location,
+            headers,
+            length,
+        }
+    }
+
+    /// The path of the upload on the CDN.
+    pub fn location(&self) -> &PathAndQuery {
+        &self.location
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Asks the CDN how many bytes of the upload it has received.
+    pub async fn offset(
+        &self,
+        client: &HttpsClient,
+        timeout: Duration,
+    ) -> Result<u64, AttachmentTransferError> {
+        let (parts, _body) = client
+            .send(
+                HttpsRequest {
+                    method: Method::HEAD,
+                    path: self.location.clone(),
+                    headers: tus_headers(&self.headers),
+                    body: request_body_from_bytes(Bytes::new()),
+                },
+                timeout,
+            )
+            .await?
+            .into_parts();
+        match parts.status {
+            StatusCode::OK | StatusCode::NO_CONTENT => upload_offset(&parts.headers),
+            StatusCode::NOT_FOUND | StatusCode::GONE => {
+                Err(AttachmentTransferError::UploadNotFound)
+            }
+            status => Err(AttachmentTransferError::UnexpectedStatus(status)),
+        }
+    }
+
+    /// Sends the rest of the upload from `source`.
+    ///
+    /// The CDN is asked how much it already has, and `source` is read from
+    /// that point on. `source` must produce the same contents every time the
+    /// upload is resumed.
+    ///
+    /// `timeout` applies to each request, including the one that carries the
+    /// data, so it should allow for the whole upload at the slowest expected
+    /// rate.
+    pub async fn upload_from(
+        &self,
+        client: &HttpsClient,
+        source: &mut (impl AsyncRead + AsyncSeek + Unpin + Send),
+        progress: &dyn TransferProgress,
+        timeout: Duration,
+    ) -> Result<(), AttachmentTransferError> {
+        let mut offset = self.offset(client, timeout).await?;
+        progress.on_progress(offset, Some(self.length));
+
+        while offset < self.length {
+            let new_offset = self
+                .append_from(client, source, offset, progress, timeout)
+                .await?;
+            if new_offset <= offset {
+                return Err(AttachmentTransferError::UploadStalled);
+            }
+            offset = new_offset;
+        }
+        Ok(())
+    }
+
+    async fn append_from(
+        &self,
+        client: &HttpsClient,
+        source: &mut (impl AsyncRead + AsyncSeek + Unpin + Send),
+        offset: u64,
+        progress: &dyn TransferProgress,
+        timeout: Duration,
+    ) -> Result<u64, AttachmentTransferError> {
+        source.seek(SeekFrom::Start(offset)).await?;
+
+        let mut headers = tus_headers(&self.headers);
+        headers.insert(UPLOAD_OFFSET, offset.into());
+        headers.insert(http::header::CONTENT_TYPE, OFFSET_OCTET_STREAM);
+        headers.insert(http::header::CONTENT_LENGTH, (self.length - offset).into());
+
+        // The request body is fed through a channel so that `source` can be
+        // borrowed rather than moved into the body.
+        let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel(1);
+        // The connection only asks for the next chunk once it has written the
+        // previous one, so that's when progress is reported, rather than when
+        // a chunk is queued.
+        let (written_tx, mut written_rx) = tokio::sync::watch::channel(offset);
+        let body = futures_util::stream::unfold(
+            (ReceiverStream::new(chunk_rx), offset, written_tx),
+            |(mut chunks, written, written_tx)| async move {
+                written_tx.send_if_modified(|last| std::mem::replace(last, written) != written);
+                let chunk: Result<Bytes, std::io::Error> = chunks.next().await?;
+                let queued = chunk
+                    .as_ref()
+                    .map_or(0, |chunk| u64::try_from(chunk.len()).expect("fits"));
+                Some((chunk, (chunks, written + queued, written_tx)))
+            },
+        );
+        let send = client.send(
+            HttpsRequest {
+                method: Method::PATCH,
+                path: self.location.clone(),
+                headers,
+                body: request_body_from_stream(body),
+            },
+            timeout,
+        );
+        let pump = async move {
+            let mut queued = offset;
+            let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+            while queued < self.length {
+                let remaining = usize::try_from(self.length - queued).unwrap_or(usize::MAX);
+                let read = source
+                    .read(&mut buffer[..remaining.min(UPLOAD_CHUNK_SIZE)])
+                    .await?;
+                if read == 0 {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                let chunk = Bytes::copy_from_slice(&buffer[..read]);
+                if chunk_tx.send(Ok(chunk)).await.is_err() {
+                    // The request ended early; its result explains why.
+                    break;
+                }
+                queued += u64::try_from(read).expect("fits");
+            }
+            Ok::<_, std::io::Error>(())
+        };
+        let report_progress = async {
+            while written_rx.changed().await.is_ok() {
+                progress.on_progress(*written_rx.borrow_and_update(), Some(self.length));
+            }
+            std::future::pending::<std::convert::Infallible>().await
+        };
+
+        let (response, pumped) = tokio::select! {
+            result = async { tokio::join!(send, pump) } => result,
+            never = report_progress => match never {},
+        };
+        pumped?;
+        let (parts, _body) = response?.into_parts();
+        match parts.status {
+            StatusCode::OK | StatusCode::NO_CONTENT => {
+                let new_offset = upload_offset(&parts.headers)?;
+                progress.on_progress(new_offset, Some(self.length));
+                Ok(new_offset)
+            }
+            StatusCode::NOT_FOUND | StatusCode::GONE => {
+                Err(AttachmentTransferError::UploadNotFound)
+            }
+            status => Err(AttachmentTransferError::UnexpectedStatus(status)),
+        }
+    }
+}
+
+impl ResumableDownload {
+    /// Starts a download of the attachment at `path`.
+    ///
+    /// `headers` are sent with each request for the attachment.
+    pub fn new(path: PathAndQuery, headers: HeaderMap) -> Self {
+        Self {
+            path,
+            headers,
+            offset: 0,
+            total_length: None,
+            validator: None,
+        }
+    }
+
+    /// Continues a download that has already received `offset` bytes.
+    ///
+    /// Without [`Self::with_validator`], the received bytes are assumed to
+    /// belong to the attachment currently on the CDN.
+    pub fn with_offset(self, offset: u64) -> Self {
+        Self { offset, ..self }
+    }
+
+    /// Sets the validator saved from an earlier attempt (see
+    /// [`Self::validator`]), to be checked when resuming.
+    pub fn with_validator(self, validator: HeaderValue) -> Self {
+        Self {
+            validator: Some(validator),
+            ..self
+        }
+    }
+
+    /// The validator the CDN reported for the attachment, if any.
+    ///
+    /// This should be saved along with the received bytes if the download
+    /// might be resumed by a new `ResumableDownload`.
+    pub fn validator(&self) -> Option<&HeaderValue> {
+        self.validator.as_ref()
+    }
+
+    /// The number of bytes received so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The length of the whole attachment, if the CDN has reported it.
+    pub fn total_length(&self) -> Option<u64> {
+        self.total_length
+    }
+
+    /// Downloads the rest of the attachment, appending it to `sink`.
+    ///
+    /// The offset is updated after each chunk is written, so if this fails or
+    /// its future is dropped, calling it again resumes after the last complete
+    /// write. A write that is interrupted partway through may leave extra
+    /// bytes in `sink`; callers that can observe the sink's length (like a
+    /// file) should use [`Self::with_offset`] to resume from that instead.
+    ///
+    /// If the attachment no longer matches the validator from an earlier
+    /// response, this fails with [`AttachmentTransferError::AttachmentChanged`]
+    /// without writing anything; the received data has to be discarded and the
+    /// download restarted.
+    ///
+    /// `timeout` applies to receiving the response headers and to each
+    /// subsequent chunk of the body.
+    pub async fn download_into(
+        &mut self,
+        client: &HttpsClient,
+        sink: &mut (impl AsyncWrite + Unpin + Send),
+        progress: &dyn TransferProgress,
+        timeout: Duration,
+    ) -> Result<(), AttachmentTransferError> {
+        if self.total_length.is_some_and(|total| self.offset >= total) {
+            return Ok(());
+        }
+
+        let mut headers = self.headers.clone();
+        if self.offset > 0 {
+            let range = HeaderValue::try_from(format!("bytes={}-", self.offset))
+                .expect("valid header value");
+            headers.insert(http::header::RANGE, range);
+            if let Some(validator) = &self.validator {
+                headers.insert(http::header::IF_RANGE, validator.clone());
+            }
+        }
+
+        let response = client
+            .send(
+                HttpsRequest {
+                    method: Method::GET,
+                    path: self.path.clone(),
+                    headers,
+                    body: request_body_from_bytes(Bytes::new()),
+                },
+                timeout,
+            )
+            .await?;
+        let (parts, body) = response.into_parts();
+
+        let response_validator = validator(&parts.headers);
+        if matches!(parts.status, StatusCode::OK | StatusCode::PARTIAL_CONTENT) {
+            match (&self.validator, response_validator) {
+                (Some(validator), Some(response_validator)) if *validator != response_validator => {
+                    return Err(AttachmentTransferError::AttachmentChanged);
+                }
+                // A CDN that stops sending a validator can't be trusted to
+                // have honored If-Range.
+                (Some(_), None) if self.offset > 0 => {
+                    return Err(AttachmentTransferError::AttachmentChanged);
+                }
+                (None, Some(response_validator)) => self.validator = Some(response_validator),
+                _ => {}
+            }
+        }
+
+        // If the CDN ignored the range (but the attachment hasn't changed), the
+        // start of the body has to be skipped.
+        let mut to_skip = match parts.status {
+            StatusCode::OK => {
+                self.total_length = content_length(&parts.headers);
+                self.offset
+            }
+            StatusCode::PARTIAL_CONTENT => {
+                let (start, total) = content_range(&parts.headers)?;
+                if start != self.offset {
+                    return Err(AttachmentTransferError::InvalidResponseHeader(
+                        http::header::CONTENT_RANGE,
+                    ));
+                }
+                self.total_length = total;
+                0
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                // This is expected if everything was received before the
+                // download was interrupted.
+                let total = unsatisfied_content_range(&parts.headers);
+                if total != Some(self.offset) {
+                    return Err(AttachmentTransferError::UnexpectedStatus(parts.status));
+                }
+                self.total_length = total;
+                progress.on_progress(self.offset, self.total_length);
+                return Ok(());
+            }
+            status => return Err(AttachmentTransferError::UnexpectedStatus(status)),
+        };
+        progress.on_progress(self.offset, self.total_length);
+
+        let mut body = std::pin::pin!(response_body_data(body));
+        while let Some(chunk) = tokio::time::timeout(timeout, body.next())
+            .await
+            .map_err(|_: tokio::time::error::Elapsed| HttpsClientError::RequestTimedOut)?
+        {
+            let mut chunk = chunk.map_err(HttpsClientError::Http)?;
+            if to_skip > 0 {
+                let skipped = usize::try_from(to_skip)
+                    .unwrap_or(usize::MAX)
+                    .min(chunk.len());
+                chunk.advance(skipped);
+                to_skip -= u64::try_from(skipped).expect("fits");
+            }
+            if chunk.is_empty() {
+                continue;
+            }
+
+            sink.write_all(&chunk).await?;
+            self.offset += u64::try_from(chunk.len()).expect("fits");
+            progress.on_progress(self.offset, self.total_length);
+        }
+        sink.flush().await?;
+
+        match self.total_length {
+            Some(total) if total != self.offset => Err(AttachmentTransferError::IncompleteDownload),
+            Some(_) => Ok(()),
+            None => {
+                self.total_length = Some(self.offset);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn tus_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    headers
+}
+
+/// Extracts the path from a `Location` header, which may be an absolute URL.
+///
+/// Later requests for the upload go over the same connection, so an absolute
+/// URL is only accepted if it refers to `host` over HTTPS on the default port.
+fn location_path(location: &str, host: &str) -> Option<PathAndQuery> {
+    let uri: http::Uri = location.parse().ok()?;
+    if let Some(authority) = uri.authority() {
+        let same_origin = uri.scheme() == Some(&http::uri::Scheme::HTTPS)
+            && authority.host().eq_ignore_ascii_case(host)
+            && authority.port_u16().unwrap_or(443) == 443;
+        if !same_origin {
+            return None;
+        }
+    } else if uri.scheme().is_some() {
+        return None;
+    }
+    uri.path_and_query()
+        .filter(|path| path.path().starts_with('/'))
+        .cloned()
+}
+
+fn upload_offset(headers: &HeaderMap) -> Result<u64, AttachmentTransferError> {
+    headers
+        .get(UPLOAD_OFFSET)
+        .and_then(|offset| offset.to_str().ok()?.parse().ok())
+        .ok_or(AttachmentTransferError::InvalidResponseHeader(
+            UPLOAD_OFFSET,
+        ))
+}
+
+/// Returns the strong `ETag`, or failing that the `Last-Modified` date, for
+/// use in an `If-Range` header.
+///
+/// Weak `ETag`s can't be used with `If-Range`.
+fn validator(headers: &HeaderMap) -> Option<HeaderValue> {
+    headers
+        .get(http::header::ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(http::header::LAST_MODIFIED))
+        .cloned()
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Parses a `Content-Range` header of the form `bytes start-end/total`.
+///
+/// Returns the start of the range and the total length, if known.
+fn content_range(headers: &HeaderMap) -> Result<(u64, Option<u64>), AttachmentTransferError> {
+    let parse = || {
+        let value = headers.get(http::header::CONTENT_RANGE)?.to_str().ok()?;
+        let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+        let (start, _end) = range.split_once('-')?;
+        let total = match total {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        };
+        Some((start.parse().ok()?, total))
+    };
+    parse().ok_or(AttachmentTransferError::InvalidResponseHeader(
+        http::header::CONTENT_RANGE,
+    ))
+}
+
+/// Parses the `Content-Range` header of a 416 response, `bytes */total`.
+fn unsatisfied_content_range(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes */")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    use assert_matches::assert_matches;
+    use libsignal_net_infra::Alpn;
+    use test_case::test_case;
+
+    use super::fake_cdn::{FakeCdn, FAKE_CDN_UPLOAD_PATH};
+    use super::*;
+    use crate::connect_state::{ConnectState, SUGGESTED_CONNECT_CONFIG};
+    use crate::env::UserAgent;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn attachment(len: usize) -> Vec<u8> {
+        (0..len).map(|i| u8::try_from(i % 251).unwrap()).collect()
+    }
+
+    async fn connect(cdn: &FakeCdn, alpn: Alpn) -> HttpsClient {
+        HttpsClient::connect(
+            &ConnectState::new(SUGGESTED_CONNECT_CONFIG),
+            &cdn.dns_resolver(),
+            vec![cdn.route(alpn)],
+            None,
+            &UserAgent::with_libsignal_version("test"),
+            "test",
+        )
+        .await
+        .expect("can connect")
+    }
+
+    fn no_progress(_transferred: u64, _total: Option<u64>) {}
+
+    /// Records the last progress update.
+    #[derive(Default)]
+    struct LastProgress(Mutex<Option<(u64, Option<u64>)>>);
+
+    impl TransferProgress for LastProgress {
+        fn on_progress(&self, transferred: u64, total: Option<u64>) {
+            *self.0.lock().expect("not poisoned") = Some((transferred, total));
+        }
+    }
+
+    #[test_case(Alpn::Http1_1; "http1")]
+    #[test_case(Alpn::Http2; "http2")]
+    #[tokio::test]
+    async fn upload_then_download(alpn: Alpn) {
+        let _ = env_logger::try_init();
+        let cdn = FakeCdn::start();
+        let client = connect(&cdn, alpn).await;
+        let contents = attachment(200_000);
+        let length = u64::try_from(contents.len()).unwrap();
+
+        let upload = ResumableUpload::create(
+            &client,
+            PathAndQuery::from_static(FAKE_CDN_UPLOAD_PATH),
+            HeaderMap::new(),
+            length,
+            TIMEOUT,
+        )
+        .await
+        .expect("can create");
+        let progress = LastProgress::default();
+        upload
+            .upload_from(&client, &mut Cursor::new(&contents), &progress, TIMEOUT)
+            .await
+            .expect("can upload");
+        assert_eq!(*progress.0.lock().unwrap(), Some((length, Some(length))));
+        assert_eq!(
+            cdn.object(upload.location().as_str()).as_deref(),
+            Some(&contents[..])
+        );
+
+        let mut download = ResumableDownload::new(upload.location().clone(), HeaderMap::new());
+        let mut received = Vec::new();
+        download
+            .download_into(&client, &mut received, &progress, TIMEOUT)
+            .await
+            .expect("can download");
+        assert_eq!(received, contents);
+        assert_eq!(download.total_length(), Some(length));
+        assert_eq!(*progress.0.lock().unwrap(), Some((length, Some(length))));
+    }
+
+    #[tokio::test]
+    async fn upload_resumes_after_failure() {
+        let _ = env_logger::try_init();
+        let cdn = FakeCdn::start();
+        let client = connect(&cdn, Alpn::Http2).await;
+        let contents = attachment(100_000);
+        let length = u64::try_from(contents.len()).unwrap();
+
+        let upload = ResumableUpload::create(
+            &client,
+            PathAndQuery::from_static(FAKE_CDN_UPLOAD_PATH),
+            HeaderMap::new(),
+            length,
+            TIMEOUT,
+        )
+        .await
+        .expect("can create");
+
+        const RECEIVED_BEFORE_FAILURE: u64 = 30_000;
+        cdn.fail_next_append_after(RECEIVED_BEFORE_FAILURE.try_into().unwrap());
+        let result = upload
+            .upload_from(&client, &mut Cursor::new(&contents), &no_progress, TIMEOUT)
+            .await;
+        assert_matches!(
+            result,
+            Err(AttachmentTransferError::UnexpectedStatus(
+                StatusCode::INTERNAL_SERVER_ERROR
+            ))
+        );
+        assert_eq!(
+            upload.offset(&client, TIMEOUT).await.expect("can check"),
+            RECEIVED_BEFORE_FAILURE
+        );
+
+        // Resume with a fresh handle, as if after a restart.
+        let upload = ResumableUpload::resume(upload.location().clone(), HeaderMap::new(), length);
+        let progress = LastProgress::default();
+        upload
+            .upload_from(&client, &mut Cursor::new(&contents), &progress, TIMEOUT)
+            .await
+            .expect("can finish");
+        assert_eq!(
+            cdn.object(upload.location().as_str()).as_deref(),
+            Some(&contents[..])
+        );
+    }
+
+    #[test_case(Alpn::Http1_1; "http1")]
+    #[test_case(Alpn::Http2; "http2")]
+    #[tokio::test]
+    async fn download_resumes_after_cancellation(alpn: Alpn) {
+        let _ = env_logger::try_init();
+        let cdn = FakeCdn::start();
+        let contents = attachment(50_000);
+        cdn.put_object("/attachments/abc", contents.clone());
+
+        const RECEIVED_BEFORE_STALL: u64 = 20_000;
+        cdn.stall_next_download_after(RECEIVED_BEFORE_STALL.try_into().unwrap());
+
+        let client = connect(&cdn, alpn).await;
+        let mut download = ResumableDownload::new(
+            PathAndQuery::from_static("/attachments/abc"),
+            HeaderMap::new(),
+        );
+        let mut received = Vec::new();
+
+        // Cancel once the download stalls.
+        let (stalled_tx, stalled_rx) = tokio::sync::watch::channel(false);
+        let progress = move |transferred: u64, _total: Option<u64>| {
+            if transferred == RECEIVED_BEFORE_STALL {
+                stalled_tx.send_replace(true);
+            }
+        };
+        tokio::select! {
+            result = download.download_into(&client, &mut received, &progress, TIMEOUT) => {
+                panic!("download finished: {result:?}")
+            }
+            _ = async {
+                let mut stalled_rx = stalled_rx;
+                stalled_rx.wait_for(|stalled| *stalled).await.map(|_| ())
+            } => {}
+        }
+        assert_eq!(download.offset(), RECEIVED_BEFORE_STALL);
+        assert_eq!(
+            download.total_length(),
+            Some(contents.len().try_into().unwrap())
+        );
+
+        // The stalled response may still be occupying the old connection.
+        let client = connect(&cdn, alpn).await;
+        download
+            .download_into(&client, &mut received, &no_progress, TIMEOUT)
+            .await
+            .expect("can finish");
+        assert_eq!(received, contents);
+
+        // Downloading again after completion is a no-op.
+        download
+            .download_into(&client, &mut received, &no_progress, TIMEOUT)
+            .await
+            .expect("no-op");
+        assert_eq!(received, contents);
+    }
+
+    #[tokio::test]
+    async fn resumed_download_detects_changed_attachment() {
+        let _ = env_logger::try_init();
+        let cdn = FakeCdn::start();
+        let contents = attachment(10_000);
+        cdn.put_object("/attachments/abc", contents.clone());
+
+        const RECEIVED_BEFORE_STALL: u64 = 4_000;
+        cdn.stall_next_download_after(RECEIVED_BEFORE_STALL.try_into().unwrap());
+
+        let client = connect(&cdn, Alpn::Http2).await;
+        let mut download = ResumableDownload::new(
+            PathAndQuery::from_static("/attachments/abc"),
+            HeaderMap::new(),
+        );
+        let mut received = Vec::new();
+        let (stalled_tx, stalled_rx) = tokio::sync::watch::channel(false);
+        let progress = move |transferred: u64, _total: Option<u64>| {
+            if transferred == RECEIVED_BEFORE_STALL {
+                stalled_tx.send_replace(true);
+            }
+        };
+        tokio::select! {
+            result = download.download_into(&client, &mut received, &progress, TIMEOUT) => {
+                panic!("download finished: {result:?}")
+            }
+            _ = async {
+                let mut stalled_rx = stalled_rx;
+                stalled_rx.wait_for(|stalled| *stalled).await.map(|_| ())
+            } => {}
+        }
+        let validator = download.validator().expect("fake CDN sends ETags").clone();
+
+        // Replace the attachment, then resume with a fresh handle, as if after
+        // a restart.
+        cdn.put_object("/attachments/abc", attachment(12_000));
+        let client = connect(&cdn, Alpn::Http2).await;
+        let mut download = ResumableDownload::new(
+            PathAndQuery::from_static("/attachments/abc"),
+            HeaderMap::new(),
+        )
+        .with_offset(RECEIVED_BEFORE_STALL)
+        .with_validator(validator);
+        let result = download
+            .download_into(&client, &mut received, &no_progress, TIMEOUT)
+            .await;
+        assert_matches!(result, Err(AttachmentTransferError::AttachmentChanged));
+        assert_eq!(
+            received,
+            &contents[..RECEIVED_BEFORE_STALL.try_into().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn download_range_past_end() {
+        let cdn = FakeCdn::start();
+        let contents = attachment(1_000);
+        cdn.put_object("/attachments/abc", contents.clone());
+
+        let client = connect(&cdn, Alpn::Http2).await;
+        let mut download = ResumableDownload::new(
+            PathAndQuery::from_static("/attachments/abc"),
+            HeaderMap::new(),
+        )
+        .with_offset(contents.len().try_into().unwrap());
+        download
+            .download_into(&client, &mut Vec::new(), &no_progress, TIMEOUT)
+            .await
+            .expect("nothing left to download");
+        assert_eq!(download.total_length(), Some(1_000));
+    }
+
+    #[test_case("/upload/abc" => Some("/upload/abc".to_owned()); "path")]
+    #[test_case("https://cdn.example.com/upload/abc?x=1" => Some("/upload/abc?x=1".to_owned()); "same host")]
+    #[test_case("https://CDN.example.com:443/upload/abc" => Some("/upload/abc".to_owned()); "same host explicit port")]
+    #[test_case("https://other.example.com/upload/abc" => None; "other host")]
+    #[test_case("http://cdn.example.com/upload/abc" => None; "plain http")]
+    #[test_case("https://cdn.example.com:8443/upload/abc" => None; "other port")]
+    #[test_case("not a url" => None; "invalid")]
+    fn location_header_forms(location: &str) -> Option<String> {
+        location_path(location, "cdn.example.com").map(|path| path.as_str().to_owned())
+    }
+}
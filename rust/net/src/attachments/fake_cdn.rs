@@ -0,0 +1,375 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::num::NonZeroU16;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use bytes::Bytes;
+use futures_util::StreamExt as _;
+use libsignal_net_infra::certs::RootCertificates;
+use libsignal_net_infra::dns::lookup_result::LookupResult;
+use libsignal_net_infra::dns::DnsResolver;
+use libsignal_net_infra::host::Host;
+use libsignal_net_infra::route::{
+    DirectOrProxyRoute, HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment,
+    UnresolvedHost, UnresolvedHttpsServiceRoute,
+};
+use libsignal_net_infra::{Alpn, DnsSource};
+use rcgen::CertifiedKey;
+use warp::filters::path::FullPath;
+use warp::http::StatusCode;
+use warp::Filter as _;
+
+use super::TUS_VERSION;
+
+/// The hostname [`FakeCdn`]'s certificate is issued for.
+pub const FAKE_CDN_HOSTNAME: &str = "cdn.test-server.signal.org.local";
+
+/// The path [`FakeCdn`] accepts upload creation requests on.
+pub const FAKE_CDN_UPLOAD_PATH: &str = "/upload";
+
+static CERTIFICATE: LazyLock<CertifiedKey> = LazyLock::new(|| {
+    rcgen::generate_simple_self_signed([FAKE_CDN_HOSTNAME.to_string()]).expect("can generate")
+});
+
+/// A CDN listening on localhost for the lifetime of the value.
+///
+/// Supports creating and appending to resumable uploads with the tus protocol
+/// at [`FAKE_CDN_UPLOAD_PATH`], and ranged downloads of any stored object,
+/// including `If-Range` checks against each object's `ETag`. A completed upload
+/// can be downloaded from its upload location.
+pub struct FakeCdn {
+    address: SocketAddr,
+    state: Arc<Mutex<CdnState>>,
+    server_task: tokio::task::AbortHandle,
+}
+
+#[derive(Default)]
+struct CdnState {
+    uploads: HashMap<String, PendingUpload>,
+    objects: HashMap<String, StoredObject>,
+    next_upload_id: u64,
+    next_etag: u64,
+    /// If set, the next append stores only this many bytes and then fails.
+    fail_next_append_after: Option<usize>,
+    /// If set, the next download sends only this many bytes and then stalls.
+    stall_next_download_after: Option<usize>,
+}
+
+struct PendingUpload {
+    length: u64,
+    contents: Vec<u8>,
+}
+
+#[derive(Clone)]
+struct StoredObject {
+    contents: Bytes,
+    etag: String,
+}
+
+impl CdnState {
+    /// Stores `contents` at `path` with a new `ETag`.
+    fn store_object(&mut self, path: &str, contents: Bytes) {
+        self.next_etag += 1;
+        let etag = format!("\"{}\"", self.next_etag);
+        self.objects
+            .insert(path.to_owned(), StoredObject { contents, etag });
+    }
+}
+
+impl FakeCdn {
+    /// Starts listening on an ephemeral localhost port.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(CdnState::default()));
+
+        let create = {
+            let state = state.clone();
+            warp::post()
+                .and(warp::path::full())
+                .and(warp::header::optional::<u64>("upload-length"))
+                .map(move |path: FullPath, length: Option<u64>| {
+                    create_upload(&state, path.as_str(), length)
+                })
+        };
+        let offset = {
+            let state = state.clone();
+            warp::head()
+                .and(warp::path::full())
+                .map(move |path: FullPath| upload_offset(&state, path.as_str()))
+        };
+        let append = {
+            let state = state.clone();
+            warp::patch()
+                .and(warp::path::full())
+                .and(warp::header::optional::<u64>("upload-offset"))
+                .and(warp::body::bytes())
+                .map(move |path: FullPath, offset: Option<u64>, body: Bytes| {
+                    append_to_upload(&state, path.as_str(), offset, body)
+                })
+        };
+        let download = {
+            let state = state.clone();
+            warp::get()
+                .and(warp::path::full())
+                .and(warp::header::optional::<String>("range"))
+                .and(warp::header::optional::<String>("if-range"))
+                .map(
+                    move |path: FullPath, range: Option<String>, if_range: Option<String>| {
+                        download(&state, path.as_str(), range.as_deref(), if_range.as_deref())
+                    },
+                )
+        };
+
+        let (address, server) = warp::serve(create.or(offset).or(append).or(download))
+            .tls()
+            .cert(CERTIFICATE.cert.pem())
+            .key(CERTIFICATE.key_pair.serialize_pem())
+            .bind_ephemeral((Ipv6Addr::LOCALHOST, 0));
+        let server_task = tokio::spawn(server).abort_handle();
+
+        Self {
+            address,
+            state,
+            server_task,
+        }
+    }
+
+    /// A resolver that maps [`FAKE_CDN_HOSTNAME`] to the server.
+    pub fn dns_resolver(&self) -> DnsResolver {
+        DnsResolver::new_from_static_map(HashMap::from([(
+            FAKE_CDN_HOSTNAME,
+            LookupResult::new(DnsSource::Static, vec![], vec![Ipv6Addr::LOCALHOST]),
+        )]))
+    }
+
+    /// A direct route to the server that negotiates HTTP with `alpn`.
+    pub fn route(&self, alpn: Alpn) -> UnresolvedHttpsServiceRoute {
+        HttpsTlsRoute {
+            fragment: HttpRouteFragment {
+                host_header: FAKE_CDN_HOSTNAME.into(),
+                path_prefix: "".into(),
+                front_name: None,
+            },
+            inner: TlsRoute {
+                fragment: TlsRouteFragment {
+                    root_certs: RootCertificates::FromDer(Cow::Borrowed(CERTIFICATE.cert.der())),
+                    sni: Host::Domain(FAKE_CDN_HOSTNAME.into()),
+                    alpn: Some(alpn),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+                inner: DirectOrProxyRoute::Direct(TcpRoute {
+                    address: UnresolvedHost(FAKE_CDN_HOSTNAME.into()),
+                    port: NonZeroU16::new(self.address.port()).expect("bound port"),
+                }),
+            },
+        }
+    }
+
+    /// Stores `contents` so it can be downloaded from `path`.
+    ///
+    /// Replacing an existing object gives it a new `ETag`.
+    pub fn put_object(&self, path: &str, contents: impl Into<Bytes>) {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.store_object(path, contents.into());
+    }
+
+    /// Returns the contents stored at `path`, including completed uploads.
+    pub fn object(&self, path: &str) -> Option<Bytes> {
+        let state = self.state.lock().expect("not poisoned");
+        state
+            .objects
+            .get(path)
+            .map(|object| object.contents.clone())
+    }
+
+    /// Makes the next append to an upload keep only its first `bytes` bytes
+    /// and then fail, as if the connection dropped.
+    pub fn fail_next_append_after(&self, bytes: usize) {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.fail_next_append_after = Some(bytes);
+    }
+
+    /// Makes the next download send only its first `bytes` bytes and then
+    /// stop sending without closing the connection.
+    pub fn stall_next_download_after(&self, bytes: usize) {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.stall_next_download_after = Some(bytes);
+    }
+}
+
+impl Drop for FakeCdn {
+    fn drop(&mut self) {
+        self.server_task.abort();
+    }
+}
+
+type Response = warp::http::Response<warp::hyper::Body>;
+
+fn response(status: StatusCode) -> warp::http::response::Builder {
+    warp::http::Response::builder()
+        .status(status)
+        .header("tus-resumable", TUS_VERSION)
+}
+
+fn empty_response(status: StatusCode) -> Response {
+    response(status)
+        .body(warp::hyper::Body::empty())
+        .expect("valid response")
+}
+
+fn create_upload(state: &Mutex<CdnState>, path: &str, length: Option<u64>) -> Response {
+    if path != FAKE_CDN_UPLOAD_PATH {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
+    let Some(length) = length else {
+        return empty_response(StatusCode::BAD_REQUEST);
+    };
+
+    let mut state = state.lock().expect("not poisoned");
+    let location = format!("{FAKE_CDN_UPLOAD_PATH}/{}", state.next_upload_id);
+    state.next_upload_id += 1;
+    state.uploads.insert(
+        location.clone(),
+        PendingUpload {
+            length,
+            contents: Vec::new(),
+        },
+    );
+
+    response(StatusCode::CREATED)
+        .header("location", location)
+        .body(warp::hyper::Body::empty())
+        .expect("valid response")
+}
+
+fn upload_offset(state: &Mutex<CdnState>, path: &str) -> Response {
+    let state = state.lock().expect("not poisoned");
+    let offset = match (state.uploads.get(path), state.objects.get(path)) {
+        (Some(upload), _) => upload.contents.len(),
+        (None, Some(object)) => object.contents.len(),
+        (None, None) => return empty_response(StatusCode::NOT_FOUND),
+    };
+    response(StatusCode::OK)
+        .header("upload-offset", offset)
+        .header("cache-control", "no-store")
+        .body(warp::hyper::Body::empty())
+        .expect("valid response")
+}
+
+fn append_to_upload(
+    state: &Mutex<CdnState>,
+    path: &str,
+    offset: Option<u64>,
+    mut body: Bytes,
+) -> Response {
+    let mut guard = state.lock().expect("not poisoned");
+    let state = &mut *guard;
+    let fail_after = state.fail_next_append_after.take();
+    let Some(upload) = state.uploads.get_mut(path) else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+
+    let current_offset = u64::try_from(upload.contents.len()).expect("fits");
+    if offset != Some(current_offset) {
+        return empty_response(StatusCode::CONFLICT);
+    }
+    let remaining = usize::try_from(upload.length - current_offset).expect("fits");
+    if body.len() > remaining {
+        return empty_response(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    if let Some(fail_after) = fail_after {
+        body.truncate(fail_after);
+        upload.contents.extend_from_slice(&body);
+        return empty_response(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    upload.contents.extend_from_slice(&body);
+    let new_offset = upload.contents.len();
+    if u64::try_from(new_offset).expect("fits") == upload.length {
+        let upload = state.uploads.remove(path).expect("present");
+        state.store_object(path, upload.contents.into());
+    }
+
+    response(StatusCode::NO_CONTENT)
+        .header("upload-offset", new_offset)
+        .body(warp::hyper::Body::empty())
+        .expect("valid response")
+}
+
+fn download(
+    state: &Mutex<CdnState>,
+    path: &str,
+    range: Option<&str>,
+    if_range: Option<&str>,
+) -> Response {
+    let mut state = state.lock().expect("not poisoned");
+    let stall_after = state.stall_next_download_after.take();
+    let Some(object) = state.objects.get(path).cloned() else {
+        return empty_response(StatusCode::NOT_FOUND);
+    };
+    drop(state);
+    let StoredObject {
+        contents: object,
+        etag,
+    } = object;
+
+    // A range whose validator doesn't match is ignored, so the whole object
+    // is sent.
+    let range = range.filter(|_| if_range.map_or(true, |if_range| if_range == etag));
+
+    let total = object.len();
+    let (status, start) = match range {
+        None => (StatusCode::OK, 0),
+        Some(range) => {
+            // Only open-ended ranges ("bytes=N-") are supported.
+            let Some(start) = range
+                .strip_prefix("bytes=")
+                .and_then(|r| r.strip_suffix('-'))
+                .and_then(|start| start.parse::<usize>().ok())
+            else {
+                return empty_response(StatusCode::BAD_REQUEST);
+            };
+            if start >= total {
+                return warp::http::Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header("content-range", format!("bytes */{total}"))
+                    .body(warp::hyper::Body::empty())
+                    .expect("valid response");
+            }
+            (StatusCode::PARTIAL_CONTENT, start)
+        }
+    };
+
+    let contents = object.slice(start..);
+    let mut builder = warp::http::Response::builder()
+        .status(status)
+        .header("content-length", contents.len())
+        .header("etag", etag);
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header(
+            "content-range",
+            format!("bytes {start}-{}/{total}", total - 1),
+        );
+    }
+
+    let body = match stall_after {
+        None => warp::hyper::Body::from(contents),
+        Some(stall_after) => {
+            let first_part = contents.slice(..stall_after.min(contents.len()));
+            warp::hyper::Body::wrap_stream(
+                futures_util::stream::iter([Ok::<_, std::io::Error>(first_part)])
+                    .chain(futures_util::stream::pending()),
+            )
+        }
+    };
+    builder.body(body).expect("valid response")
+}
@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-pub use libsignal_net_infra::certs::RootCertificates;
+use std::time::Duration;
+
+use hex_literal::hex;
+pub use libsignal_net_infra::certs::{RootCertificates, SpkiPinSet};
 
 pub const SIGNAL_ROOT_CERTIFICATES: RootCertificates =
     RootCertificates::FromStaticDers(&[include_bytes!("../res/signal.cer")]);
@@ -18,3 +21,108 @@ pub const PROXY_G_ROOT_CERTIFICATES: RootCertificates = RootCertificates::FromSt
     include_bytes!("../res/GTSR3.cer"),
     include_bytes!("../res/GTSR4.cer"),
 ]);
+
+/// Pins for the servers behind [`SIGNAL_ROOT_CERTIFICATES`].
+///
+/// Those servers chain only to Signal's own root, so moving to a new key means
+/// shipping a new root there as well; there is no backup to list until then.
+/// The pins expire with the root certificate.
+pub const SIGNAL_SPKI_PINS: SpkiPinSet = SpkiPinSet {
+    primary: &[hex!(
+        "c7ff606d9e4cdeb5eb5ad8e49ce4b499299b3dff2fe6c3e7dbd36a6f6a0aefb9"
+    )],
+    backup: &[],
+    anchors: &[],
+    // 2032-01-24T00:45:50Z, when signal.cer expires.
+    expires_after: Duration::from_secs(1_958_517_950),
+};
+
+/// Pins for the attachment CDNs, which are verified against the platform's
+/// roots.
+///
+/// The primary pins are the roots the CDNs currently chain to; the backups are
+/// other roots from the same CAs and from the CA the CDNs would move to next.
+/// The servers don't send these roots, so they are also the anchors for the
+/// check.
+pub const CDN_SPKI_PINS: SpkiPinSet = SpkiPinSet {
+    primary: &[
+        // Amazon Root CA 1
+        hex!("fbe3018031f9586bcbf41727e417b7d1c45c2f47f93be372a17b96b50757d5a2"),
+        // GTS Root R1
+        hex!("871a9194f4eed5b312ff40c84c1d524aed2f778bbff25f138cf81f680a7adc67"),
+        // GTS Root R4
+        hex!("9847e5653e5e9e847516e5cb818606aa7544a19be67fd7366d506988e8d84347"),
+        // ISRG Root X1
+        hex!("0b9fa5a59eed715c26c1020c711b4f6ec42d58b0015e14337a39dad301c5afc3"),
+    ],
+    backup: &[
+        // Amazon Root CA 2
+        hex!("7f4296fc5b6a4e3b35d3c369623e364ab1af381d8fa7121533c9d6c633ea2461"),
+        // Amazon Root CA 3
+        hex!("36abc32656acfc645c61b71613c4bf21c787f5cabbee48348d58597803d7abc9"),
+        // Amazon Root CA 4
+        hex!("f7ecded5c66047d28ed6466b543c40e0743abe81d109254dcf845d4c2c7853c5"),
+        // GTS Root R2
+        hex!("55f77de41c03792428f8d518c55104225be43a5598d926a528ad653e1ccec7bf"),
+        // GTS Root R3
+        hex!("4179edd981ef747477b49626408af43daa2ca7ab7f9e082c1060f84096774348"),
+        // ISRG Root X2
+        hex!("762195c225586ee6c0237456e2107dc54f1efc21f61a792ebd515913cce68332"),
+        // SSL.com Root Certification Authority RSA
+        hex!("d1c45377ebdcd618cd1651dc2e02c21d751e5aa9fcd1b3431ff6ecf6a31348fa"),
+        // SSL.com Root Certification Authority ECC
+        hex!("a320f4d534d7be97c1ae8dd0499735bc895c323add2d388bfccf662c23d7f99a"),
+    ],
+    anchors: &[
+        include_bytes!("../res/AmazonRootCA1.cer"),
+        include_bytes!("../res/AmazonRootCA2.cer"),
+        include_bytes!("../res/AmazonRootCA3.cer"),
+        include_bytes!("../res/AmazonRootCA4.cer"),
+        include_bytes!("../res/GTSR1.cer"),
+        include_bytes!("../res/GTSR2.cer"),
+        include_bytes!("../res/GTSR3.cer"),
+        include_bytes!("../res/GTSR4.cer"),
+        include_bytes!("../res/ISRGRootX1.cer"),
+        include_bytes!("../res/ISRGRootX2.cer"),
+        include_bytes!("../res/SSLcomRootRSA.cer"),
+        include_bytes!("../res/SSLcomRootECC.cer"),
+    ],
+    // 2028-01-01T00:00:00Z, so that the CA choices get revisited.
+    expires_after: Duration::from_secs(1_830_297_600),
+};
+
+#[cfg(test)]
+mod test {
+    use libsignal_net_infra::certs::spki_hash_of_der;
+
+    use super::*;
+
+    #[test]
+    fn signal_pins_match_root() {
+        let RootCertificates::FromStaticDers([root]) = SIGNAL_ROOT_CERTIFICATES else {
+            panic!("expected a single root");
+        };
+        assert_eq!(
+            SIGNAL_SPKI_PINS.primary,
+            [spki_hash_of_der(root).expect("valid")]
+        );
+    }
+
+    #[test]
+    fn cdn_pins_match_anchors() {
+        let mut pins = CDN_SPKI_PINS
+            .primary
+            .iter()
+            .chain(CDN_SPKI_PINS.backup)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut anchor_hashes = CDN_SPKI_PINS
+            .anchors
+            .iter()
+            .map(|der| spki_hash_of_der(der).expect("valid"))
+            .collect::<Vec<_>>();
+        pins.sort();
+        anchor_hashes.sort();
+        assert_eq!(pins, anchor_hashes);
+    }
+}
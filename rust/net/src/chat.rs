@@ -17,14 +17,19 @@ use libsignal_net_infra::route::{
     TransportRoute, UnresolvedHttpsServiceRoute, UnresolvedWebsocketServiceRoute, WebSocketRoute,
     WebSocketRouteFragment,
 };
-use libsignal_net_infra::timeouts::ONE_ROUTE_CONNECTION_TIMEOUT;
+use libsignal_net_infra::timeouts::{
+    TimeoutOr, NOISE_CONNECTION_TIMEOUT, ONE_ROUTE_CONNECTION_TIMEOUT,
+};
 use libsignal_net_infra::utils::ObservableEvent;
+use libsignal_net_infra::ws::DeflateStream;
 use libsignal_net_infra::{
-    make_ws_config, AsHttpHeader, Connection, EndpointConnection, IpType, TransportInfo,
+    make_ws_config, AsHttpHeader, AsyncDuplexStream, Connection, EndpointConnection, IpType,
+    TransportInfo,
 };
 use tokio_tungstenite::WebSocketStream;
 
 use crate::auth::Auth;
+use crate::chat::noise::{NoiseRoute, NoiseTunnel};
 use crate::connect_state::{
     ConnectState, DefaultTransportConnector, RouteInfo, WebSocketTransportConnectorFactory,
 };
@@ -34,9 +39,12 @@ use crate::proto;
 mod error;
 pub use error::ChatServiceError;
 
+pub mod api;
 pub mod fake;
 pub mod noise;
+pub mod outbox;
 pub mod server_requests;
+pub mod supervisor;
 pub mod ws;
 pub mod ws2;
 
@@ -164,9 +172,15 @@ pub struct ChatConnection {
     connection_info: ConnectionInfo,
 }
 
-/// The type of the websocket connection over a given transport-level connection
-/// used by [`ChatConnection`].
-type ChatWebSocketConnection<TC> = ThrottledConnection<WebSocketStream<TC>>;
+/// The websocket connection over a given transport-level connection used by
+/// [`ChatConnection`].
+#[derive(Debug)]
+enum ChatWebSocketConnection<TC> {
+    /// Connected directly over the transport.
+    Direct(ThrottledConnection<WebSocketStream<DeflateStream<TC>>>),
+    /// Connected inside a Noise tunnel over the transport.
+    Noise(ThrottledConnection<WebSocketStream<DeflateStream<NoiseTunnel<TC>>>>),
+}
 
 type ChatTransportConnection =
     <DefaultTransportConnector as Connector<TransportRoute, ()>>::Connection;
@@ -182,6 +196,7 @@ pub struct PendingChatConnection<T = ChatTransportConnection> {
     log_tag: Arc<str>,
 }
 
+#[derive(Clone)]
 pub struct AuthenticatedChatHeaders {
     pub auth: Auth,
     pub receive_stories: ReceiveStories,
@@ -198,6 +213,7 @@ impl ChatConnection {
         user_agent: &UserAgent,
         ws_config: self::ws2::Config,
         auth: Option<AuthenticatedChatHeaders>,
+        noise_route: Option<NoiseRoute>,
         log_tag: &str,
     ) -> Result<PendingChatConnection, ChatServiceError>
     where
@@ -211,11 +227,19 @@ impl ChatConnection {
             user_agent,
             ws_config,
             auth,
+            noise_route,
             log_tag,
         )
         .await
     }
 
+    /// Connects the chat websocket over one of the provided routes.
+    ///
+    /// If `noise_route` is provided, the connection is first attempted
+    /// through a Noise tunnel on each route, for up to
+    /// [`NOISE_CONNECTION_TIMEOUT`]. If none of those attempts succeed, the
+    /// websocket is connected directly over TLS instead, unless the server
+    /// rejected the connection in a way that TLS wouldn't change.
     #[cfg_attr(feature = "test-util", visibility::make(pub))]
     async fn start_connect_with_transport<TC>(
         connect: &tokio::sync::RwLock<ConnectState<TC>>,
@@ -225,6 +249,7 @@ impl ChatConnection {
         user_agent: &UserAgent,
         ws_config: self::ws2::Config,
         auth: Option<AuthenticatedChatHeaders>,
+        noise_route: Option<NoiseRoute>,
         log_tag: &str,
     ) -> Result<PendingChatConnection<TC::Connection>, ChatServiceError>
     where
@@ -243,23 +268,67 @@ impl ChatConnection {
             ws_config: Default::default(),
             endpoint: PathAndQuery::from_static(crate::env::constants::WEB_SOCKET_PATH),
             headers: HeaderMap::from_iter(headers),
+            deflate: ws_config.compression,
         };
+        let log_tag: Arc<str> = log_tag.into();
+
+        // If we create multiple authenticated chat websocket connections at
+        // the same time, the server will terminate earlier ones as later ones
+        // complete. Throttling at the websocket connection level lets us get
+        // connection parallelism at the transport level (which is useful)
+        // while limiting us to one fully established connection at a time.
+        if let Some(noise_route) = noise_route {
+            let (tunnel_fragment, noise_connector) = noise_route.into_tunnel(ws_fragment.clone());
+            let tunnel_routes = (&http_route_provider).map_routes(|http| WebSocketRoute {
+                inner: http,
+                fragment: tunnel_fragment.clone(),
+            });
+            let result = tokio::time::timeout(
+                NOISE_CONNECTION_TIMEOUT,
+                ConnectState::connect_tunneled_ws(
+                    connect,
+                    tunnel_routes,
+                    (),
+                    ThrottlingConnector::new(noise_connector, 1),
+                    resolver,
+                    confirmation_header_name.as_ref(),
+                    log_tag.clone(),
+                ),
+            )
+            .await
+            .unwrap_or(Err(TimeoutOr::Timeout {
+                attempt_duration: NOISE_CONNECTION_TIMEOUT,
+            }));
+            match result.map_err(ChatServiceError::from_single_connect_error) {
+                Ok((ws_connection, route_info)) => {
+                    return Ok(PendingChatConnection {
+                        connection: ChatWebSocketConnection::Noise(ws_connection),
+                        route_info,
+                        ws_config,
+                        log_tag,
+                    })
+                }
+                // The server answered, so connecting over TLS would get the
+                // same answer.
+                Err(
+                    e @ (ChatServiceError::AppExpired
+                    | ChatServiceError::DeviceDeregistered
+                    | ChatServiceError::RetryLater { .. }),
+                ) => return Err(e),
+                Err(e) => {
+                    log::warn!("[{log_tag}] Noise connection failed, falling back to TLS: {e}")
+                }
+            }
+        }
+
         let ws_routes = http_route_provider.map_routes(|http| WebSocketRoute {
             inner: http,
             fragment: ws_fragment.clone(),
         });
-
-        let log_tag: Arc<str> = log_tag.into();
         let (ws_connection, route_info) = ConnectState::connect_ws(
             connect,
             ws_routes,
             (),
-            // If we create multiple authenticated chat websocket connections at
-            // the same time, the server will terminate earlier ones as later
-            // ones complete. Throttling at the websocket connection level
-            // lets us get connection parallelism at the transport level (which
-            // is useful) while limiting us to one fully established connection
-            // at a time.
             ThrottlingConnector::new(crate::infra::ws::Stateless, 1),
             resolver,
             confirmation_header_name.as_ref(),
@@ -269,7 +338,7 @@ impl ChatConnection {
         .map_err(ChatServiceError::from_single_connect_error)?;
 
         Ok(PendingChatConnection {
-            connection: ws_connection,
+            connection: ChatWebSocketConnection::Direct(ws_connection),
             route_info,
             ws_config,
             log_tag,
@@ -287,18 +356,21 @@ impl ChatConnection {
             route_info,
             log_tag,
         } = pending;
+        let connection_info = ConnectionInfo {
+            route_info,
+            transport_info: connection.transport_info(),
+        };
+        let inner = match connection {
+            ChatWebSocketConnection::Direct(connection) => {
+                ws2::Chat::new(tokio_runtime, connection, ws_config, log_tag, listener)
+            }
+            ChatWebSocketConnection::Noise(connection) => {
+                ws2::Chat::new(tokio_runtime, connection, ws_config, log_tag, listener)
+            }
+        };
         Self {
-            connection_info: ConnectionInfo {
-                route_info,
-                transport_info: connection.transport_info(),
-            },
-            inner: crate::chat::ws2::Chat::new(
-                tokio_runtime,
-                connection,
-                ws_config,
-                log_tag,
-                listener,
-            ),
+            connection_info,
+            inner,
         }
     }
 
@@ -340,6 +412,24 @@ impl PendingChatConnection {
     }
 }
 
+impl<TC: AsyncDuplexStream> ChatWebSocketConnection<TC> {
+    async fn close(&mut self) -> Result<(), tungstenite::Error> {
+        match self {
+            Self::Direct(connection) => connection.close().await,
+            Self::Noise(connection) => connection.close().await,
+        }
+    }
+}
+
+impl<TC: AsyncDuplexStream + Connection> Connection for ChatWebSocketConnection<TC> {
+    fn transport_info(&self) -> TransportInfo {
+        match self {
+            Self::Direct(connection) => connection.transport_info(),
+            Self::Noise(connection) => connection.transport_info(),
+        }
+    }
+}
+
 impl Display for ConnectionInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
@@ -375,6 +465,7 @@ pub mod test_support {
         let network_change_event = ObservableEvent::new();
         let dns_resolver =
             DnsResolver::new_with_static_fallback(env.static_fallback(), &network_change_event);
+        dns_resolver.set_service_binding_lookup_enabled(env.service_binding_lookup);
 
         let route_provider = DirectOrProxyProvider::maybe_proxied(
             env.chat_domain_config
@@ -391,6 +482,7 @@ pub mod test_support {
             initial_request_id: 0,
             local_idle_timeout: Duration::from_secs(60),
             remote_idle_timeout: Duration::from_secs(60),
+            compression: Some(Default::default()),
         };
 
         let pending = ChatConnection::start_connect_with(
@@ -404,6 +496,7 @@ pub mod test_support {
             &user_agent,
             ws_config,
             None,
+            None,
             "test",
         )
         .await?;
@@ -420,9 +513,10 @@ pub mod test_support {
 
 #[cfg(test)]
 pub(crate) mod test {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, VecDeque};
 
     use assert_matches::assert_matches;
+    use futures_util::StreamExt as _;
     use http::{HeaderName, HeaderValue};
     use libsignal_net_infra::certs::RootCertificates;
     use libsignal_net_infra::dns::lookup_result::LookupResult;
@@ -585,6 +679,38 @@ pub(crate) mod test {
     // It's easier to use this with test_case in string form.
     const CONFIRMATION_HEADER: &str = "x-really-signal";
 
+    const CHAT_DOMAIN: &str = "test.signal.org";
+
+    fn chat_dns_resolver() -> DnsResolver {
+        DnsResolver::new_from_static_map(HashMap::from_iter([(
+            CHAT_DOMAIN,
+            LookupResult::localhost(),
+        )]))
+    }
+
+    fn chat_route() -> UnresolvedHttpsServiceRoute {
+        HttpsTlsRoute {
+            fragment: HttpRouteFragment {
+                host_header: CHAT_DOMAIN.into(),
+                path_prefix: "".into(),
+                front_name: None,
+            },
+            inner: TlsRoute {
+                fragment: TlsRouteFragment {
+                    root_certs: RootCertificates::Native,
+                    sni: Host::Domain(CHAT_DOMAIN.into()),
+                    alpn: Some(Alpn::Http1_1),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+                inner: DirectOrProxyRoute::Direct(TcpRoute {
+                    address: UnresolvedHost(CHAT_DOMAIN.into()),
+                    port: DEFAULT_HTTPS_PORT,
+                }),
+            },
+        }
+    }
+
     #[test_case(403, &[] => matches ChatServiceError::AllConnectionRoutesFailed)]
     #[test_case(403, &[(CONFIRMATION_HEADER, "1")] => matches ChatServiceError::DeviceDeregistered)]
     #[test_case(499, &[(CONFIRMATION_HEADER, "1")] => matches ChatServiceError::AppExpired)]
@@ -631,32 +757,10 @@ pub(crate) mod test {
             }),
         );
 
-        const CHAT_DOMAIN: &str = "test.signal.org";
-
         let err = ChatConnection::start_connect_with_transport(
             &connect_state,
-            &DnsResolver::new_from_static_map(HashMap::from_iter([(
-                CHAT_DOMAIN,
-                LookupResult::localhost(),
-            )])),
-            vec![HttpsTlsRoute {
-                fragment: HttpRouteFragment {
-                    host_header: CHAT_DOMAIN.into(),
-                    path_prefix: "".into(),
-                    front_name: None,
-                },
-                inner: TlsRoute {
-                    fragment: TlsRouteFragment {
-                        root_certs: RootCertificates::Native,
-                        sni: Host::Domain(CHAT_DOMAIN.into()),
-                        alpn: Some(Alpn::Http1_1),
-                    },
-                    inner: DirectOrProxyRoute::Direct(TcpRoute {
-                        address: UnresolvedHost(CHAT_DOMAIN.into()),
-                        port: DEFAULT_HTTPS_PORT,
-                    }),
-                },
-            }],
+            &chat_dns_resolver(),
+            vec![chat_route()],
             Some(HeaderName::from_static(CONFIRMATION_HEADER)),
             &UserAgent::with_libsignal_version("test"),
             ws2::Config {
@@ -664,8 +768,10 @@ pub(crate) mod test {
                 local_idle_timeout: Duration::ZERO,
                 remote_idle_timeout: Duration::ZERO,
                 initial_request_id: 0,
+                compression: None,
             },
             None,
+            None,
             "fake chat",
         )
         .await
@@ -675,4 +781,190 @@ pub(crate) mod test {
 
         err
     }
+
+    /// Connects chat over [`chat_route`], handing out `transports` in order
+    /// for each attempt.
+    async fn connect_over_transports(
+        transports: impl IntoIterator<Item = tokio::io::DuplexStream>,
+        noise_route: Option<NoiseRoute>,
+    ) -> Result<PendingChatConnection<tokio::io::DuplexStream>, ChatServiceError> {
+        let transports = std::sync::Mutex::new(VecDeque::from_iter(transports));
+        let connect_state = ConnectState::new_with_transport_connector(
+            SUGGESTED_CONNECT_CONFIG,
+            ConnectFn(|_inner, _route, _log_tag| {
+                std::future::ready(transports.lock().expect("unpoisoned").pop_front().ok_or(
+                    WebSocketConnectError::Transport(TransportConnectError::TcpConnectionFailed),
+                ))
+            }),
+        );
+
+        ChatConnection::start_connect_with_transport(
+            &connect_state,
+            &chat_dns_resolver(),
+            vec![chat_route()],
+            None,
+            &UserAgent::with_libsignal_version("test"),
+            ws2::Config {
+                local_idle_timeout: Duration::from_secs(60),
+                remote_idle_timeout: Duration::from_secs(60),
+                initial_request_id: 0,
+                compression: Some(Default::default()),
+            },
+            None,
+            noise_route,
+            "fake chat",
+        )
+        .await
+    }
+
+    const NOISE_ENDPOINT: &str = "/v1/noise";
+
+    #[tokio::test]
+    async fn connects_through_noise_tunnel() {
+        let (server_private_key, server_public_key) = noise::testutil::generate_keypair();
+
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(noise::testutil::noise_echo_server(
+            server,
+            server_private_key,
+            None,
+        ));
+
+        let pending = connect_over_transports(
+            [client],
+            Some(NoiseRoute {
+                endpoint: PathAndQuery::from_static(NOISE_ENDPOINT),
+                authorization: noise::Authorization::Anonymous { server_public_key },
+            }),
+        )
+        .await
+        .expect("can connect");
+        assert_matches!(pending.connection, ChatWebSocketConnection::Noise(_));
+        drop(pending);
+
+        let received = server.await.expect("server exited cleanly");
+        assert_eq!(
+            received.tunnel.expect("tunnel requested").uri().path(),
+            NOISE_ENDPOINT
+        );
+        assert_eq!(
+            received.inner.expect("chat requested").uri().path(),
+            crate::env::constants::WEB_SOCKET_PATH
+        );
+    }
+
+    /// Accepts a plain websocket on `stream`, reporting the requested path.
+    fn spawn_websocket_server(
+        stream: tokio::io::DuplexStream,
+    ) -> tokio::sync::oneshot::Receiver<String> {
+        let (path_tx, path_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let mut websocket = tokio_tungstenite::accept_hdr_async(
+                stream,
+                |request: &tungstenite::handshake::server::Request,
+                 response: tungstenite::handshake::server::Response|
+                 -> Result<_, tungstenite::handshake::server::ErrorResponse> {
+                    let _ignore_error = path_tx.send(request.uri().path().to_owned());
+                    Ok(response)
+                },
+            )
+            .await
+            .expect("handshake succeeds");
+            // Hold the connection open until the client goes away.
+            while let Some(Ok(_)) = websocket.next().await {}
+        });
+        path_rx
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_tls_if_noise_handshake_fails() {
+        let (server_private_key, _server_public_key) = noise::testutil::generate_keypair();
+        let (_other_private_key, other_public_key) = noise::testutil::generate_keypair();
+
+        let (noise_client, noise_server) = tokio::io::duplex(4096);
+        let noise_server = tokio::spawn(noise::testutil::noise_echo_server(
+            noise_server,
+            server_private_key,
+            None,
+        ));
+
+        let (tls_client, tls_server) = tokio::io::duplex(4096);
+        let path_rx = spawn_websocket_server(tls_server);
+
+        let pending = connect_over_transports(
+            [noise_client, tls_client],
+            Some(NoiseRoute {
+                endpoint: PathAndQuery::from_static(NOISE_ENDPOINT),
+                authorization: noise::Authorization::Anonymous {
+                    server_public_key: other_public_key,
+                },
+            }),
+        )
+        .await
+        .expect("can connect");
+        assert_matches!(pending.connection, ChatWebSocketConnection::Direct(_));
+
+        let received = noise_server.await.expect("server exited cleanly");
+        assert!(received.tunnel.is_some());
+        assert_eq!(received.handshake_payload, None);
+        assert_eq!(
+            path_rx.await.expect("TLS websocket requested"),
+            crate::env::constants::WEB_SOCKET_PATH
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_fall_back_to_tls_if_server_rejects_noise_connection() {
+        let (client, server) = tokio::io::duplex(4096);
+        let _server = tokio::spawn(tokio_tungstenite::accept_hdr_async(
+            server,
+            |_request: &tungstenite::handshake::server::Request,
+             _response: tungstenite::handshake::server::Response|
+             -> Result<_, tungstenite::handshake::server::ErrorResponse> {
+                Err(::http::Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body(None)
+                    .expect("valid"))
+            },
+        ));
+
+        // A TLS attempt would fail for lack of a transport instead.
+        let (_other_private_key, server_public_key) = noise::testutil::generate_keypair();
+        let err = connect_over_transports(
+            [client],
+            Some(NoiseRoute {
+                endpoint: PathAndQuery::from_static(NOISE_ENDPOINT),
+                authorization: noise::Authorization::Anonymous { server_public_key },
+            }),
+        )
+        .await
+        .expect_err("rejected");
+        assert_matches!(err, ChatServiceError::DeviceDeregistered);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn falls_back_to_tls_if_noise_connection_stalls() {
+        // Nothing ever answers on this one.
+        let (noise_client, _noise_server) = tokio::io::duplex(4096);
+        let (tls_client, tls_server) = tokio::io::duplex(4096);
+        let path_rx = spawn_websocket_server(tls_server);
+
+        let (_server_private_key, server_public_key) = noise::testutil::generate_keypair();
+        let started = tokio::time::Instant::now();
+        let pending = connect_over_transports(
+            [noise_client, tls_client],
+            Some(NoiseRoute {
+                endpoint: PathAndQuery::from_static(NOISE_ENDPOINT),
+                authorization: noise::Authorization::Anonymous { server_public_key },
+            }),
+        )
+        .await
+        .expect("can connect");
+        assert_matches!(pending.connection, ChatWebSocketConnection::Direct(_));
+        assert!(started.elapsed() >= NOISE_CONNECTION_TIMEOUT);
+        assert_eq!(
+            path_rx.await.expect("TLS websocket requested"),
+            crate::env::constants::WEB_SOCKET_PATH
+        );
+    }
 }
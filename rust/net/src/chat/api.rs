@@ -0,0 +1,320 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Typed wrappers around the chat server's REST-over-websocket API.
+//!
+//! Each submodule covers one area of the API and exposes free functions that
+//! build a [`chat::Request`], send it through a [`ChatSender`], and decode the
+//! [`chat::Response`], mapping well-known status codes to [`ApiError`]
+//! variants.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt as _;
+use http::header::{ACCEPT, CONTENT_TYPE};
+use http::uri::PathAndQuery;
+use http::{HeaderName, HeaderValue, StatusCode};
+use libsignal_core::{DeviceId, ServiceId};
+use libsignal_net_infra::errors::LogSafeDisplay;
+use libsignal_net_infra::extract_retry_after_seconds;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, ChatConnection, ChatServiceError};
+
+pub mod accounts;
+pub mod keys;
+pub mod messages;
+pub mod multi_recipient;
+pub mod profiles;
+
+const JSON_MIME_TYPE: &str = "application/json";
+const UNIDENTIFIED_ACCESS_KEY_HEADER_NAME: HeaderName =
+    HeaderName::from_static("unidentified-access-key");
+
+/// Something that can deliver requests to the chat server.
+///
+/// Implemented for [`ChatConnection`]; other implementations can be used to
+/// layer additional behavior (or fakes) underneath the typed API.
+pub trait ChatSender: Sync {
+    fn send_request(
+        &self,
+        request: chat::Request,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<chat::Response, ChatServiceError>>;
+}
+
+impl ChatSender for ChatConnection {
+    fn send_request(
+        &self,
+        request: chat::Request,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<chat::Response, ChatServiceError>> {
+        self.send(request, timeout).boxed()
+    }
+}
+
+/// The device lists returned by the server when a send's device list doesn't
+/// match the recipient's registered devices (HTTP 409).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MismatchedDevices {
+    pub account: ServiceId,
+    /// Devices the recipient has that were not included in the request.
+    pub missing_devices: Vec<DeviceId>,
+    /// Devices included in the request that the recipient no longer has.
+    pub extra_devices: Vec<DeviceId>,
+}
+
+/// The devices whose sessions are out of date, as returned by the server with
+/// HTTP 410.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaleDevices {
+    pub account: ServiceId,
+    pub stale_devices: Vec<DeviceId>,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ApiError {
+    /// chat request failed: {0}
+    Chat(#[from] ChatServiceError),
+    /// request was not authorized
+    Unauthorized,
+    /// requested resource was not found
+    NotFound,
+    /// device list did not match the recipient's devices
+    MismatchedDevices(Vec<MismatchedDevices>),
+    /// sessions for some of the recipient's devices are stale
+    StaleDevices(Vec<StaleDevices>),
+    /// rate limited by the server
+    RateLimited { retry_after_seconds: Option<u32> },
+    /// unexpected response status {0}
+    UnexpectedStatus(StatusCode),
+    /// invalid request: {0}
+    InvalidRequest(&'static str),
+    /// invalid response: {0}
+    InvalidResponse(&'static str),
+}
+
+impl LogSafeDisplay for ApiError {}
+
+impl ApiError {
+    /// Maps the status codes shared by all endpoints to errors.
+    ///
+    /// Endpoints with their own interpretation of a status (like 409 when
+    /// sending messages) should check for it before calling this.
+    fn from_status(response: &chat::Response) -> Self {
+        match response.status.as_u16() {
+            401 | 403 => Self::Unauthorized,
+            404 => Self::NotFound,
+            413 | 429 => Self::RateLimited {
+                retry_after_seconds: extract_retry_after_seconds(&response.headers),
+            },
+            _ => Self::UnexpectedStatus(response.status),
+        }
+    }
+}
+
+/// Sends `request` and returns the response if it was successful.
+async fn send(
+    chat: &(impl ChatSender + ?Sized),
+    request: chat::Request,
+    timeout: Duration,
+) -> Result<chat::Response, ApiError> {
+    let response = chat.send_request(request, timeout).await?;
+    if !response.status.is_success() {
+        return Err(ApiError::from_status(&response));
+    }
+    Ok(response)
+}
+
+fn parse_json_body<T: DeserializeOwned>(response: &chat::Response) -> Result<T, ApiError> {
+    let body = response
+        .body
+        .as_deref()
+        .ok_or(ApiError::InvalidResponse("missing body"))?;
+    serde_json::from_slice(body).map_err(|_| ApiError::InvalidResponse("invalid JSON"))
+}
+
+fn path_and_query(path: String) -> Result<PathAndQuery, ApiError> {
+    PathAndQuery::try_from(path).map_err(|_| ApiError::InvalidRequest("invalid path"))
+}
+
+fn get_request(path: PathAndQuery) -> chat::Request {
+    chat::Request {
+        method: http::Method::GET,
+        body: None,
+        headers: http::HeaderMap::from_iter([(ACCEPT, HeaderValue::from_static(JSON_MIME_TYPE))]),
+        path,
+    }
+}
+
+fn json_request(method: http::Method, path: PathAndQuery, body: &impl Serialize) -> chat::Request {
+    chat::Request {
+        method,
+        body: Some(
+            serde_json::to_vec(body)
+                .expect("request bodies are always serializable")
+                .into_boxed_slice(),
+        ),
+        headers: http::HeaderMap::from_iter([
+            (CONTENT_TYPE, HeaderValue::from_static(JSON_MIME_TYPE)),
+            (ACCEPT, HeaderValue::from_static(JSON_MIME_TYPE)),
+        ]),
+        path,
+    }
+}
+
+/// Adds an `Unidentified-Access-Key` header for sealed sender requests.
+fn add_access_key(request: &mut chat::Request, access_key: &[u8]) {
+    request.headers.insert(
+        UNIDENTIFIED_ACCESS_KEY_HEADER_NAME,
+        HeaderValue::try_from(BASE64_STANDARD.encode(access_key))
+            .expect("base64 is a valid header"),
+    );
+}
+
+fn parse_service_id(value: &str) -> Result<ServiceId, ApiError> {
+    ServiceId::parse_from_service_id_string(value)
+        .ok_or(ApiError::InvalidResponse("invalid service ID"))
+}
+
+fn device_ids(ids: Vec<u32>) -> Vec<DeviceId> {
+    ids.into_iter().map(DeviceId::from).collect()
+}
+
+/// The JSON form of a 409 response body for a single recipient.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct RawMismatchedDevices {
+    missing_devices: Vec<u32>,
+    extra_devices: Vec<u32>,
+}
+
+impl RawMismatchedDevices {
+    fn into_typed(self, account: ServiceId) -> MismatchedDevices {
+        MismatchedDevices {
+            account,
+            missing_devices: device_ids(self.missing_devices),
+            extra_devices: device_ids(self.extra_devices),
+        }
+    }
+}
+
+/// The JSON form of a 410 response body for a single recipient.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct RawStaleDevices {
+    stale_devices: Vec<u32>,
+}
+
+impl RawStaleDevices {
+    fn into_typed(self, account: ServiceId) -> StaleDevices {
+        StaleDevices {
+            account,
+            stale_devices: device_ids(self.stale_devices),
+        }
+    }
+}
+
+#[cfg(test)]
+mod testutil {
+    use http::StatusCode;
+
+    use crate::chat::fake::FakeChatRemote;
+    use crate::chat::{ws2, ChatConnection, RequestProto, ResponseProto};
+
+    pub(super) fn fake_chat() -> (ChatConnection, FakeChatRemote) {
+        let listener: ws2::EventListener = Box::new(|_event| {});
+        ChatConnection::new_fake(tokio::runtime::Handle::current(), listener)
+    }
+
+    /// Waits for the next request from the client and answers it.
+    pub(super) async fn respond(
+        remote: &FakeChatRemote,
+        status: StatusCode,
+        headers: &[&str],
+        body: Option<&str>,
+    ) -> RequestProto {
+        let request = remote
+            .receive_request()
+            .await
+            .expect("valid request")
+            .expect("client still connected");
+        remote
+            .send_response(ResponseProto {
+                id: request.id,
+                status: Some(status.as_u16().into()),
+                message: status.canonical_reason().map(String::from),
+                headers: headers.iter().map(|h| h.to_string()).collect(),
+                body: body.map(|b| b.as_bytes().to_vec()),
+            })
+            .expect("client still connected");
+        request
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use test_case::test_case;
+
+    use super::testutil::{fake_chat, respond};
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test_case(401, &[] => matches ApiError::Unauthorized)]
+    #[test_case(403, &[] => matches ApiError::Unauthorized)]
+    #[test_case(404, &[] => matches ApiError::NotFound)]
+    #[test_case(413, &["Retry-After: 30"] => matches ApiError::RateLimited { retry_after_seconds: Some(30) })]
+    #[test_case(429, &[] => matches ApiError::RateLimited { retry_after_seconds: None })]
+    #[test_case(500, &[] => matches ApiError::UnexpectedStatus(StatusCode::INTERNAL_SERVER_ERROR))]
+    #[tokio::test]
+    async fn status_mapping(status: u16, headers: &'static [&'static str]) -> ApiError {
+        let (chat, remote) = fake_chat();
+        let status = StatusCode::from_u16(status).expect("valid");
+        let (result, _request) = tokio::join!(
+            send(
+                &chat,
+                get_request(PathAndQuery::from_static("/v1/test")),
+                TIMEOUT
+            ),
+            respond(&remote, status, headers, None),
+        );
+        result.expect_err("should fail")
+    }
+
+    #[tokio::test]
+    async fn success_passes_response_through() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            send(
+                &chat,
+                get_request(PathAndQuery::from_static("/v1/test")),
+                TIMEOUT
+            ),
+            respond(&remote, StatusCode::NO_CONTENT, &[], None),
+        );
+        assert_eq!(request.verb(), "GET");
+        assert_eq!(request.path(), "/v1/test");
+        assert_matches!(result, Ok(chat::Response { status, .. }) if status == StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn parse_json_body_requires_body() {
+        let response = chat::Response {
+            status: StatusCode::OK,
+            message: None,
+            body: None,
+            headers: Default::default(),
+        };
+        assert_matches!(
+            parse_json_body::<RawStaleDevices>(&response),
+            Err(ApiError::InvalidResponse("missing body"))
+        );
+    }
+}
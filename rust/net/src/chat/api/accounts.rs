@@ -0,0 +1,251 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Account- and device-level endpoints.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use http::uri::PathAndQuery;
+use libsignal_core::{Aci, DeviceId, Pni, E164};
+use serde::Deserialize;
+
+use super::{get_request, parse_json_body, send, ApiError, ChatSender};
+
+const WHOAMI_PATH: &str = "/v1/accounts/whoami";
+const DEVICES_PATH: &str = "/v1/devices/";
+const KEEPALIVE_PATH: &str = "/v1/keepalive";
+
+/// The identifiers of the authenticated account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WhoAmI {
+    pub aci: Aci,
+    pub pni: Pni,
+    pub number: E164,
+    pub username_hash: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawWhoAmI {
+    uuid: String,
+    pni: String,
+    number: String,
+    #[serde(default)]
+    username_hash: Option<String>,
+}
+
+impl TryFrom<RawWhoAmI> for WhoAmI {
+    type Error = ApiError;
+
+    fn try_from(raw: RawWhoAmI) -> Result<Self, Self::Error> {
+        let RawWhoAmI {
+            uuid,
+            pni,
+            number,
+            username_hash,
+        } = raw;
+        Ok(Self {
+            aci: Aci::parse_from_service_id_string(&uuid)
+                .ok_or(ApiError::InvalidResponse("invalid ACI"))?,
+            pni: Pni::parse_from_service_id_string(&pni)
+                .ok_or(ApiError::InvalidResponse("invalid PNI"))?,
+            number: number
+                .parse()
+                .map_err(|_| ApiError::InvalidResponse("invalid phone number"))?,
+            username_hash: username_hash
+                .map(|hash| BASE64_STANDARD.decode(hash))
+                .transpose()
+                .map_err(|_| ApiError::InvalidResponse("invalid username hash"))?,
+        })
+    }
+}
+
+/// A device linked to the authenticated account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub id: DeviceId,
+    /// The device name, encrypted by the client that registered it.
+    pub encrypted_name: Option<Vec<u8>>,
+    /// Milliseconds since the epoch, truncated to the day.
+    pub last_seen: u64,
+    /// Milliseconds since the epoch.
+    pub created: u64,
+}
+
+#[derive(Deserialize)]
+struct RawDeviceList {
+    devices: Vec<RawDeviceInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawDeviceInfo {
+    id: u32,
+    #[serde(default)]
+    name: Option<String>,
+    last_seen: u64,
+    created: u64,
+}
+
+impl TryFrom<RawDeviceInfo> for DeviceInfo {
+    type Error = ApiError;
+
+    fn try_from(raw: RawDeviceInfo) -> Result<Self, Self::Error> {
+        let RawDeviceInfo {
+            id,
+            name,
+            last_seen,
+            created,
+        } = raw;
+        Ok(Self {
+            id: id.into(),
+            encrypted_name: name
+                .map(|name| BASE64_STANDARD.decode(name))
+                .transpose()
+                .map_err(|_| ApiError::InvalidResponse("invalid device name"))?,
+            last_seen,
+            created,
+        })
+    }
+}
+
+/// Fetches the identifiers of the account the connection is authenticated as.
+pub async fn whoami(
+    chat: &(impl ChatSender + ?Sized),
+    timeout: Duration,
+) -> Result<WhoAmI, ApiError> {
+    let request = get_request(PathAndQuery::from_static(WHOAMI_PATH));
+    let response = send(chat, request, timeout).await?;
+    parse_json_body::<RawWhoAmI>(&response)?.try_into()
+}
+
+/// Lists the devices linked to the authenticated account.
+pub async fn devices(
+    chat: &(impl ChatSender + ?Sized),
+    timeout: Duration,
+) -> Result<Vec<DeviceInfo>, ApiError> {
+    let request = get_request(PathAndQuery::from_static(DEVICES_PATH));
+    let response = send(chat, request, timeout).await?;
+    parse_json_body::<RawDeviceList>(&response)?
+        .devices
+        .into_iter()
+        .map(DeviceInfo::try_from)
+        .collect()
+}
+
+/// Checks that the connection is still usable.
+///
+/// The server also uses keepalives to check whether a device has messages
+/// it hasn't yet acknowledged.
+pub async fn keepalive(
+    chat: &(impl ChatSender + ?Sized),
+    timeout: Duration,
+) -> Result<(), ApiError> {
+    let request = get_request(PathAndQuery::from_static(KEEPALIVE_PATH));
+    send(chat, request, timeout).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use http::StatusCode;
+    use nonzero_ext::nonzero;
+    use uuid::uuid;
+
+    use super::super::testutil::{fake_chat, respond};
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn whoami_success() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            whoami(&chat, TIMEOUT),
+            respond(
+                &remote,
+                StatusCode::OK,
+                &[],
+                Some(
+                    r#"{
+                        "uuid": "e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c",
+                        "pni": "PNI:a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c",
+                        "number": "+18005550100",
+                        "usernameHash": "AAEC"
+                    }"#
+                ),
+            ),
+        );
+        assert_eq!(request.path(), WHOAMI_PATH);
+        assert_eq!(
+            result.expect("success"),
+            WhoAmI {
+                aci: Aci::from(uuid!("e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c")),
+                pni: Pni::from(uuid!("a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c")),
+                number: E164::new(nonzero!(18005550100u64)),
+                username_hash: Some(vec![0, 1, 2]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn whoami_rejects_bad_aci() {
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            whoami(&chat, TIMEOUT),
+            respond(
+                &remote,
+                StatusCode::OK,
+                &[],
+                Some(r#"{"uuid": "nope", "pni": "nope", "number": "+18005550100"}"#),
+            ),
+        );
+        assert_matches!(result, Err(ApiError::InvalidResponse("invalid ACI")));
+    }
+
+    #[tokio::test]
+    async fn devices_success() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            devices(&chat, TIMEOUT),
+            respond(
+                &remote,
+                StatusCode::OK,
+                &[],
+                Some(
+                    r#"{"devices": [
+                        {"id": 1, "lastSeen": 1700000000000, "created": 1600000000000},
+                        {"id": 2, "name": "AQID", "lastSeen": 1700000000000, "created": 1650000000000}
+                    ]}"#
+                ),
+            ),
+        );
+        assert_eq!(request.path(), DEVICES_PATH);
+        let devices = result.expect("success");
+        assert_eq!(
+            devices
+                .iter()
+                .map(|d| (d.id, d.encrypted_name.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DeviceId::from(1), None),
+                (DeviceId::from(2), Some(vec![1, 2, 3]))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keepalive_unauthorized() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            keepalive(&chat, TIMEOUT),
+            respond(&remote, StatusCode::UNAUTHORIZED, &[], None),
+        );
+        assert_eq!(request.path(), KEEPALIVE_PATH);
+        assert_matches!(result, Err(ApiError::Unauthorized));
+    }
+}
@@ -0,0 +1,500 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Prekey management endpoints.
+
+use std::time::{Duration, SystemTime};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use libsignal_core::{DeviceId, ProtocolAddress, ServiceId, ServiceIdKind};
+use libsignal_protocol::{
+    kem, process_prekey_bundle, IdentityKey, IdentityKeyStore, KyberPreKeyId, PreKeyBundle,
+    PreKeyId, PublicKey, SessionStore, SignalProtocolError, SignedPreKeyId,
+};
+use rand::{CryptoRng, Rng};
+use serde::Deserialize;
+
+use super::{
+    add_access_key, get_request, parse_json_body, path_and_query, send, ApiError, ChatSender,
+};
+
+const KEYS_PATH: &str = "/v2/keys";
+
+/// How many one-time prekeys the server has left for the authenticated
+/// account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreKeyCounts {
+    /// Remaining EC one-time prekeys.
+    pub count: u32,
+    /// Remaining Kyber one-time prekeys.
+    pub pq_count: u32,
+}
+
+fn identity_query_value(kind: ServiceIdKind) -> &'static str {
+    match kind {
+        ServiceIdKind::Aci => "aci",
+        ServiceIdKind::Pni => "pni",
+    }
+}
+
+/// Fetches the number of one-time prekeys remaining for the given identity
+/// of the authenticated account.
+pub async fn prekey_counts(
+    chat: &(impl ChatSender + ?Sized),
+    identity: ServiceIdKind,
+    timeout: Duration,
+) -> Result<PreKeyCounts, ApiError> {
+    let path = path_and_query(format!(
+        "{KEYS_PATH}?identity={}",
+        identity_query_value(identity)
+    ))?;
+    let response = send(chat, get_request(path), timeout).await?;
+    parse_json_body(&response)
+}
+
+/// Which of an account's devices to fetch prekey bundles for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    AllDevices,
+    Device(DeviceId),
+}
+
+impl DeviceSelector {
+    fn path_component(&self) -> String {
+        match self {
+            Self::AllDevices => "*".to_owned(),
+            Self::Device(device_id) => device_id.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPreKeyBundles {
+    identity_key: String,
+    devices: Vec<RawDevicePreKeys>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawDevicePreKeys {
+    device_id: u32,
+    registration_id: u32,
+    #[serde(default)]
+    pre_key: Option<RawPreKey>,
+    signed_pre_key: RawSignedPreKey,
+    #[serde(default)]
+    pq_pre_key: Option<RawSignedPreKey>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPreKey {
+    key_id: u32,
+    public_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSignedPreKey {
+    key_id: u32,
+    public_key: String,
+    signature: String,
+}
+
+fn decode_base64(value: &str, error: &'static str) -> Result<Vec<u8>, ApiError> {
+    BASE64_STANDARD
+        .decode(value)
+        .map_err(|_| ApiError::InvalidResponse(error))
+}
+
+fn decode_ec_key(value: &str) -> Result<PublicKey, ApiError> {
+    let bytes = decode_base64(value, "invalid prekey")?;
+    PublicKey::deserialize(&bytes).map_err(|_| ApiError::InvalidResponse("invalid prekey"))
+}
+
+fn decode_kem_key(value: &str) -> Result<kem::PublicKey, ApiError> {
+    let bytes = decode_base64(value, "invalid Kyber prekey")?;
+    kem::PublicKey::deserialize(&bytes)
+        .map_err(|_| ApiError::InvalidResponse("invalid Kyber prekey"))
+}
+
+/// Decodes a signature and checks it against the identity key.
+///
+/// [`process_prekey_bundle`] checks these too, but checking them here means
+/// a bad bundle from the server is reported as a bad response rather than
+/// surfacing later as a protocol error.
+fn verified_signature(
+    identity_key: &IdentityKey,
+    signed_key: &[u8],
+    signature: &str,
+) -> Result<Vec<u8>, ApiError> {
+    let signature = decode_base64(signature, "invalid prekey signature")?;
+    if !identity_key
+        .public_key()
+        .verify_signature(signed_key, &signature)
+    {
+        return Err(ApiError::InvalidResponse("invalid prekey signature"));
+    }
+    Ok(signature)
+}
+
+impl RawDevicePreKeys {
+    fn into_bundle(self, identity_key: IdentityKey) -> Result<PreKeyBundle, ApiError> {
+        let Self {
+            device_id,
+            registration_id,
+            pre_key,
+            signed_pre_key,
+            pq_pre_key,
+        } = self;
+
+        let pre_key = pre_key
+            .map(|RawPreKey { key_id, public_key }| {
+                Ok::<_, ApiError>((PreKeyId::from(key_id), decode_ec_key(&public_key)?))
+            })
+            .transpose()?;
+
+        let signed_pre_key_public = decode_ec_key(&signed_pre_key.public_key)?;
+        let signed_pre_key_signature = verified_signature(
+            &identity_key,
+            &signed_pre_key_public.serialize(),
+            &signed_pre_key.signature,
+        )?;
+
+        let bundle = PreKeyBundle::new(
+            registration_id,
+            device_id.into(),
+            pre_key,
+            SignedPreKeyId::from(signed_pre_key.key_id),
+            signed_pre_key_public,
+            signed_pre_key_signature,
+            identity_key,
+        )
+        .map_err(|_| ApiError::InvalidResponse("invalid prekey bundle"))?;
+
+        let Some(pq_pre_key) = pq_pre_key else {
+            return Ok(bundle);
+        };
+        let pq_public = decode_kem_key(&pq_pre_key.public_key)?;
+        let pq_signature =
+            verified_signature(&identity_key, &pq_public.serialize(), &pq_pre_key.signature)?;
+        Ok(bundle.with_kyber_pre_key(
+            KyberPreKeyId::from(pq_pre_key.key_id),
+            pq_public,
+            pq_signature,
+        ))
+    }
+}
+
+/// Fetches prekey bundles for one or all of an account's devices.
+///
+/// Over an unauthenticated connection, `access_key` must be the recipient's
+/// unidentified access key. Every signature in the response is checked
+/// against the account's identity key before the bundles are returned.
+pub async fn get_prekey_bundles(
+    chat: &(impl ChatSender + ?Sized),
+    service_id: ServiceId,
+    devices: DeviceSelector,
+    access_key: Option<&[u8; 16]>,
+    timeout: Duration,
+) -> Result<Vec<PreKeyBundle>, ApiError> {
+    let path = path_and_query(format!(
+        "{KEYS_PATH}/{}/{}",
+        service_id.service_id_string(),
+        devices.path_component()
+    ))?;
+    let mut request = get_request(path);
+    if let Some(access_key) = access_key {
+        add_access_key(&mut request, access_key);
+    }
+    let response = send(chat, request, timeout).await?;
+    let RawPreKeyBundles {
+        identity_key,
+        devices,
+    } = parse_json_body(&response)?;
+
+    let identity_key = IdentityKey::decode(&decode_base64(&identity_key, "invalid identity key")?)
+        .map_err(|_| ApiError::InvalidResponse("invalid identity key"))?;
+    devices
+        .into_iter()
+        .map(|device| device.into_bundle(identity_key))
+        .collect()
+}
+
+/// Starts a session with each device in `bundles`, as if by calling
+/// [`process_prekey_bundle`] for each one in turn.
+///
+/// Stops at the first device that fails, leaving sessions for any earlier
+/// devices in place.
+pub async fn process_prekey_bundles<R: Rng + CryptoRng>(
+    service_id: ServiceId,
+    bundles: &[PreKeyBundle],
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    now: SystemTime,
+    csprng: &mut R,
+) -> Result<(), SignalProtocolError> {
+    for bundle in bundles {
+        let address = ProtocolAddress::new(service_id.service_id_string(), bundle.device_id()?);
+        process_prekey_bundle(&address, session_store, identity_store, bundle, now, csprng).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use http::StatusCode;
+    use libsignal_core::Aci;
+    use libsignal_protocol::{IdentityKeyPair, InMemSignalProtocolStore, KeyPair};
+    use rand::rngs::OsRng;
+    use serde_json::json;
+    use uuid::uuid;
+
+    use super::super::testutil::{fake_chat, respond};
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const ACI: uuid::Uuid = uuid!("e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+
+    /// Builds a `/v2/keys` response body for devices 1 (with all prekeys)
+    /// and 2 (with only a signed prekey).
+    fn bundles_body(identity: &IdentityKeyPair, corrupt_kyber_signature: bool) -> String {
+        let sign = |key: &[u8]| {
+            identity
+                .private_key()
+                .calculate_signature(key, &mut OsRng)
+                .expect("can sign")
+        };
+        let signed_pre_key = KeyPair::generate(&mut OsRng).public_key.serialize();
+        let kyber_pre_key = kem::KeyPair::generate(kem::KeyType::Kyber1024)
+            .public_key
+            .serialize();
+        let mut kyber_signature = sign(&kyber_pre_key);
+        if corrupt_kyber_signature {
+            kyber_signature[0] ^= 1;
+        }
+        json!({
+            "identityKey": BASE64_STANDARD.encode(identity.identity_key().serialize()),
+            "devices": [
+                {
+                    "deviceId": 1,
+                    "registrationId": 111,
+                    "preKey": {
+                        "keyId": 10,
+                        "publicKey": BASE64_STANDARD.encode(
+                            KeyPair::generate(&mut OsRng).public_key.serialize()
+                        ),
+                    },
+                    "signedPreKey": {
+                        "keyId": 20,
+                        "publicKey": BASE64_STANDARD.encode(&signed_pre_key),
+                        "signature": BASE64_STANDARD.encode(sign(&signed_pre_key)),
+                    },
+                    "pqPreKey": {
+                        "keyId": 30,
+                        "publicKey": BASE64_STANDARD.encode(&kyber_pre_key),
+                        "signature": BASE64_STANDARD.encode(kyber_signature),
+                    },
+                },
+                {
+                    "deviceId": 2,
+                    "registrationId": 222,
+                    "signedPreKey": {
+                        "keyId": 21,
+                        "publicKey": BASE64_STANDARD.encode(&signed_pre_key),
+                        "signature": BASE64_STANDARD.encode(sign(&signed_pre_key)),
+                    },
+                },
+            ],
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn prekey_counts_success() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            prekey_counts(&chat, ServiceIdKind::Pni, TIMEOUT),
+            respond(
+                &remote,
+                StatusCode::OK,
+                &[],
+                Some(r#"{"count": 12, "pqCount": 34}"#),
+            ),
+        );
+        assert_eq!(request.verb(), "GET");
+        assert_eq!(request.path(), "/v2/keys?identity=pni");
+        assert_eq!(
+            result.expect("success"),
+            PreKeyCounts {
+                count: 12,
+                pq_count: 34
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn prekey_counts_rate_limited() {
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            prekey_counts(&chat, ServiceIdKind::Aci, TIMEOUT),
+            respond(
+                &remote,
+                StatusCode::TOO_MANY_REQUESTS,
+                &["retry-after: 7"],
+                None
+            ),
+        );
+        assert_matches!(
+            result,
+            Err(ApiError::RateLimited {
+                retry_after_seconds: Some(7)
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn prekey_bundles_success() {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let body = bundles_body(&identity, false);
+
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            get_prekey_bundles(
+                &chat,
+                Aci::from(ACI).into(),
+                DeviceSelector::AllDevices,
+                Some(&[0xAA; 16]),
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::OK, &[], Some(&body)),
+        );
+        assert_eq!(request.verb(), "GET");
+        assert_eq!(
+            request.path(),
+            "/v2/keys/e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c/*"
+        );
+        let expected_header = format!(
+            "unidentified-access-key: {}",
+            BASE64_STANDARD.encode([0xAA; 16])
+        );
+        assert!(request.headers.contains(&expected_header));
+
+        let bundles = result.expect("success");
+        assert_eq!(bundles.len(), 2);
+        for bundle in &bundles {
+            assert_eq!(
+                bundle.identity_key().expect("present"),
+                identity.identity_key()
+            );
+        }
+
+        let first = &bundles[0];
+        assert_eq!(first.device_id().expect("present"), DeviceId::from(1));
+        assert_eq!(first.registration_id().expect("present"), 111);
+        assert_eq!(first.pre_key_id().expect("valid"), Some(PreKeyId::from(10)));
+        assert_eq!(
+            first.kyber_pre_key_id().expect("valid"),
+            Some(KyberPreKeyId::from(30))
+        );
+
+        let second = &bundles[1];
+        assert_eq!(second.device_id().expect("present"), DeviceId::from(2));
+        assert_eq!(second.pre_key_id().expect("valid"), None);
+        assert_eq!(second.kyber_pre_key_id().expect("valid"), None);
+    }
+
+    #[tokio::test]
+    async fn prekey_bundles_single_device_path() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            get_prekey_bundles(
+                &chat,
+                Aci::from(ACI).into(),
+                DeviceSelector::Device(DeviceId::from(3)),
+                None,
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::NOT_FOUND, &[], None),
+        );
+        assert_eq!(
+            request.path(),
+            "/v2/keys/e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c/3"
+        );
+        assert!(!request
+            .headers
+            .iter()
+            .any(|h| h.starts_with("unidentified-access-key")));
+        assert_matches!(result, Err(ApiError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn prekey_bundles_rejects_bad_signature() {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let body = bundles_body(&identity, true);
+
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            get_prekey_bundles(
+                &chat,
+                Aci::from(ACI).into(),
+                DeviceSelector::AllDevices,
+                None,
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::OK, &[], Some(&body)),
+        );
+        assert_matches!(
+            result,
+            Err(ApiError::InvalidResponse("invalid prekey signature"))
+        );
+    }
+
+    #[tokio::test]
+    async fn fetched_bundles_start_sessions() {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let body = bundles_body(&identity, false);
+
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            get_prekey_bundles(
+                &chat,
+                Aci::from(ACI).into(),
+                DeviceSelector::AllDevices,
+                None,
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::OK, &[], Some(&body)),
+        );
+        let bundles = result.expect("success");
+
+        let mut store = InMemSignalProtocolStore::new(IdentityKeyPair::generate(&mut OsRng), 5)
+            .expect("can create store");
+        process_prekey_bundles(
+            Aci::from(ACI).into(),
+            &bundles,
+            &mut store.session_store,
+            &mut store.identity_store,
+            SystemTime::now(),
+            &mut OsRng,
+        )
+        .await
+        .expect("can process bundles");
+
+        for device_id in [1, 2] {
+            let address =
+                ProtocolAddress::new(Aci::from(ACI).service_id_string(), device_id.into());
+            assert!(store
+                .session_store
+                .load_session(&address)
+                .await
+                .expect("can load")
+                .is_some());
+        }
+    }
+}
@@ -0,0 +1,488 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Message sending endpoints.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use http::header::{ACCEPT, CONTENT_TYPE};
+use http::{HeaderName, HeaderValue, StatusCode};
+use libsignal_core::{DeviceId, ServiceId};
+use libsignal_protocol::{CiphertextMessageType, Timestamp};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    add_access_key, json_request, parse_json_body, parse_service_id, path_and_query, ApiError,
+    ChatSender, MismatchedDevices, RawMismatchedDevices, RawStaleDevices, StaleDevices,
+    JSON_MIME_TYPE,
+};
+use crate::chat;
+
+const MESSAGES_PATH_PREFIX: &str = "/v1/messages";
+const MULTI_RECIPIENT_PATH: &str = "/v1/messages/multi_recipient";
+const MULTI_RECIPIENT_MIME_TYPE: &str = "application/vnd.signal-messenger.mrm";
+const GROUP_SEND_TOKEN_HEADER_NAME: HeaderName = HeaderName::from_static("group-send-token");
+
+/// The envelope type the server should use when delivering a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EnvelopeType {
+    Ciphertext = 1,
+    PreKeyBundle = 3,
+    PlaintextContent = 8,
+}
+
+impl TryFrom<CiphertextMessageType> for EnvelopeType {
+    type Error = ApiError;
+
+    fn try_from(value: CiphertextMessageType) -> Result<Self, Self::Error> {
+        match value {
+            CiphertextMessageType::Whisper => Ok(Self::Ciphertext),
+            CiphertextMessageType::PreKey => Ok(Self::PreKeyBundle),
+            CiphertextMessageType::Plaintext => Ok(Self::PlaintextContent),
+            CiphertextMessageType::SenderKey => Err(ApiError::InvalidRequest(
+                "sender key messages must be sent as multi-recipient messages",
+            )),
+        }
+    }
+}
+
+/// An encrypted message for one of the destination's devices.
+#[derive(Clone, Debug)]
+pub struct OutgoingDeviceMessage {
+    pub envelope_type: EnvelopeType,
+    pub device_id: DeviceId,
+    pub registration_id: u32,
+    pub content: Vec<u8>,
+}
+
+/// Options shared by single- and multi-recipient sends.
+#[derive(Clone, Copy, Debug)]
+pub struct SendOptions {
+    pub timestamp: Timestamp,
+    /// Only deliver to devices that are currently connected.
+    pub online: bool,
+    /// Whether the recipient should be woken up to process the message.
+    pub urgent: bool,
+}
+
+/// How a sealed sender multi-recipient message is authorized.
+#[derive(Clone, Copy, Debug)]
+pub enum MultiRecipientAccess<'a> {
+    /// The XOR of all the recipients' unidentified access keys.
+    CombinedAccessKey(&'a [u8; 16]),
+    /// A serialized group send full token.
+    GroupSendToken(&'a [u8]),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOutgoingMessage {
+    r#type: u8,
+    destination_device_id: u32,
+    destination_registration_id: u32,
+    content: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOutgoingMessageList {
+    messages: Vec<RawOutgoingMessage>,
+    online: bool,
+    urgent: bool,
+    timestamp: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSendMessageResponse {
+    #[serde(default)]
+    needs_sync: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMultiRecipientResponse {
+    #[serde(default)]
+    uuids404: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawAccountMismatchedDevices {
+    uuid: String,
+    devices: RawMismatchedDevices,
+}
+
+#[derive(Deserialize)]
+struct RawAccountStaleDevices {
+    uuid: String,
+    devices: RawStaleDevices,
+}
+
+/// The result of a successful single-recipient send.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendMessageResponse {
+    /// Whether the sender's other devices need a sync message for this send.
+    pub needs_sync: bool,
+}
+
+/// Sends a message to all of `destination`'s devices.
+///
+/// If `access_key` is provided the message is sent with sealed sender
+/// authorization; otherwise the connection must be authenticated.
+///
+/// Device list mismatches are reported as [`ApiError::MismatchedDevices`]
+/// or [`ApiError::StaleDevices`] with a single entry for `destination`.
+pub async fn send_message(
+    chat: &(impl ChatSender + ?Sized),
+    destination: ServiceId,
+    messages: &[OutgoingDeviceMessage],
+    options: SendOptions,
+    access_key: Option<&[u8; 16]>,
+    timeout: Duration,
+) -> Result<SendMessageResponse, ApiError> {
+    let SendOptions {
+        timestamp,
+        online,
+        urgent,
+    } = options;
+    let body = RawOutgoingMessageList {
+        messages: messages
+            .iter()
+            .map(|message| RawOutgoingMessage {
+                r#type: message.envelope_type as u8,
+                destination_device_id: message.device_id.into(),
+                destination_registration_id: message.registration_id,
+                content: BASE64_STANDARD.encode(&message.content),
+            })
+            .collect(),
+        online,
+        urgent,
+        timestamp: timestamp.epoch_millis(),
+    };
+    let path = path_and_query(format!(
+        "{MESSAGES_PATH_PREFIX}/{}",
+        destination.service_id_string()
+    ))?;
+    let mut request = json_request(http::Method::PUT, path, &body);
+    if let Some(access_key) = access_key {
+        add_access_key(&mut request, access_key);
+    }
+
+    let response = chat.send_request(request, timeout).await?;
+    match response.status {
+        StatusCode::CONFLICT => {
+            let raw: RawMismatchedDevices = parse_json_body(&response)?;
+            Err(ApiError::MismatchedDevices(vec![
+                raw.into_typed(destination)
+            ]))
+        }
+        StatusCode::GONE => {
+            let raw: RawStaleDevices = parse_json_body(&response)?;
+            Err(ApiError::StaleDevices(vec![raw.into_typed(destination)]))
+        }
+        status if status.is_success() => {
+            let RawSendMessageResponse { needs_sync } = parse_json_body(&response)?;
+            Ok(SendMessageResponse { needs_sync })
+        }
+        _ => Err(ApiError::from_status(&response)),
+    }
+}
+
+/// Sends a serialized [`SealedSenderV2SentMessage`] to every recipient it
+/// contains.
+///
+/// On success, returns the recipients the server didn't recognize, which
+/// have not received the message.
+///
+/// [`SealedSenderV2SentMessage`]: libsignal_protocol::SealedSenderV2SentMessage
+pub async fn send_multi_recipient_message(
+    chat: &(impl ChatSender + ?Sized),
+    payload: &[u8],
+    options: SendOptions,
+    access: MultiRecipientAccess<'_>,
+    timeout: Duration,
+) -> Result<Vec<ServiceId>, ApiError> {
+    let SendOptions {
+        timestamp,
+        online,
+        urgent,
+    } = options;
+    let path = path_and_query(format!(
+        "{MULTI_RECIPIENT_PATH}?ts={}&online={online}&urgent={urgent}",
+        timestamp.epoch_millis()
+    ))?;
+    let mut request = chat::Request {
+        method: http::Method::PUT,
+        body: Some(payload.into()),
+        headers: http::HeaderMap::from_iter([
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static(MULTI_RECIPIENT_MIME_TYPE),
+            ),
+            (ACCEPT, HeaderValue::from_static(JSON_MIME_TYPE)),
+        ]),
+        path,
+    };
+    match access {
+        MultiRecipientAccess::CombinedAccessKey(key) => add_access_key(&mut request, key),
+        MultiRecipientAccess::GroupSendToken(token) => {
+            request.headers.insert(
+                GROUP_SEND_TOKEN_HEADER_NAME,
+                HeaderValue::try_from(BASE64_STANDARD.encode(token))
+                    .expect("base64 is a valid header"),
+            );
+        }
+    }
+
+    let response = chat.send_request(request, timeout).await?;
+    match response.status {
+        StatusCode::CONFLICT => {
+            let raw: Vec<RawAccountMismatchedDevices> = parse_json_body(&response)?;
+            let entries = raw
+                .into_iter()
+                .map(|entry| Ok(entry.devices.into_typed(parse_service_id(&entry.uuid)?)))
+                .collect::<Result<_, ApiError>>()?;
+            Err(ApiError::MismatchedDevices(entries))
+        }
+        StatusCode::GONE => {
+            let raw: Vec<RawAccountStaleDevices> = parse_json_body(&response)?;
+            let entries = raw
+                .into_iter()
+                .map(|entry| Ok(entry.devices.into_typed(parse_service_id(&entry.uuid)?)))
+                .collect::<Result<_, ApiError>>()?;
+            Err(ApiError::StaleDevices(entries))
+        }
+        status if status.is_success() => {
+            // The server may omit the body entirely when every recipient was found.
+            let RawMultiRecipientResponse { uuids404 } = if response.body.is_some() {
+                parse_json_body(&response)?
+            } else {
+                Default::default()
+            };
+            uuids404.iter().map(|uuid| parse_service_id(uuid)).collect()
+        }
+        _ => Err(ApiError::from_status(&response)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use libsignal_core::{Aci, Pni};
+    use uuid::uuid;
+
+    use super::super::testutil::{fake_chat, respond};
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const ACI: uuid::Uuid = uuid!("e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+    const PNI: uuid::Uuid = uuid!("a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+
+    const OPTIONS: SendOptions = SendOptions {
+        timestamp: Timestamp::from_epoch_millis(1700000000000),
+        online: false,
+        urgent: true,
+    };
+
+    fn device_message(device_id: u32) -> OutgoingDeviceMessage {
+        OutgoingDeviceMessage {
+            envelope_type: EnvelopeType::Ciphertext,
+            device_id: device_id.into(),
+            registration_id: 1000 + device_id,
+            content: vec![1, 2, 3],
+        }
+    }
+
+    #[tokio::test]
+    async fn send_message_success() {
+        let (chat, remote) = fake_chat();
+        let destination = ServiceId::from(Aci::from(ACI));
+        let (result, request) = tokio::join!(
+            send_message(
+                &chat,
+                destination,
+                &[device_message(1), device_message(2)],
+                OPTIONS,
+                None,
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::OK, &[], Some(r#"{"needsSync": true}"#)),
+        );
+        assert_eq!(request.verb(), "PUT");
+        assert_eq!(
+            request.path(),
+            "/v1/messages/e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c"
+        );
+        let body: serde_json::Value = serde_json::from_slice(request.body()).expect("valid JSON");
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "messages": [
+                    {"type": 1, "destinationDeviceId": 1, "destinationRegistrationId": 1001, "content": "AQID"},
+                    {"type": 1, "destinationDeviceId": 2, "destinationRegistrationId": 1002, "content": "AQID"},
+                ],
+                "online": false,
+                "urgent": true,
+                "timestamp": 1700000000000u64,
+            })
+        );
+        assert_eq!(
+            result.expect("success"),
+            SendMessageResponse { needs_sync: true }
+        );
+    }
+
+    #[tokio::test]
+    async fn send_message_mismatched_devices() {
+        let (chat, remote) = fake_chat();
+        let destination = ServiceId::from(Aci::from(ACI));
+        let (result, _request) = tokio::join!(
+            send_message(
+                &chat,
+                destination,
+                &[device_message(1), device_message(3)],
+                OPTIONS,
+                None,
+                TIMEOUT,
+            ),
+            respond(
+                &remote,
+                StatusCode::CONFLICT,
+                &[],
+                Some(r#"{"missingDevices": [2], "extraDevices": [3]}"#),
+            ),
+        );
+        assert_matches!(result, Err(ApiError::MismatchedDevices(entries)) => {
+            assert_eq!(entries, [MismatchedDevices {
+                account: destination,
+                missing_devices: vec![2.into()],
+                extra_devices: vec![3.into()],
+            }]);
+        });
+    }
+
+    #[tokio::test]
+    async fn send_message_stale_devices() {
+        let (chat, remote) = fake_chat();
+        let destination = ServiceId::from(Pni::from(PNI));
+        let (result, _request) = tokio::join!(
+            send_message(
+                &chat,
+                destination,
+                &[device_message(1)],
+                OPTIONS,
+                Some(&[0; 16]),
+                TIMEOUT,
+            ),
+            respond(
+                &remote,
+                StatusCode::GONE,
+                &[],
+                Some(r#"{"staleDevices": [1]}"#)
+            ),
+        );
+        assert_matches!(result, Err(ApiError::StaleDevices(entries)) => {
+            assert_eq!(entries, [StaleDevices {
+                account: destination,
+                stale_devices: vec![1.into()],
+            }]);
+        });
+    }
+
+    #[tokio::test]
+    async fn send_multi_recipient_success() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            send_multi_recipient_message(
+                &chat,
+                b"payload",
+                OPTIONS,
+                MultiRecipientAccess::GroupSendToken(b"token"),
+                TIMEOUT,
+            ),
+            respond(
+                &remote,
+                StatusCode::OK,
+                &[],
+                Some(r#"{"uuids404": ["PNI:a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c"]}"#),
+            ),
+        );
+        assert_eq!(
+            request.path(),
+            "/v1/messages/multi_recipient?ts=1700000000000&online=false&urgent=true"
+        );
+        assert_eq!(request.body(), b"payload");
+        assert!(request.headers.contains(&format!(
+            "group-send-token: {}",
+            BASE64_STANDARD.encode("token")
+        )));
+        assert_eq!(result.expect("success"), [ServiceId::from(Pni::from(PNI))]);
+    }
+
+    #[tokio::test]
+    async fn send_multi_recipient_mismatched_devices() {
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            send_multi_recipient_message(
+                &chat,
+                b"payload",
+                OPTIONS,
+                MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                TIMEOUT,
+            ),
+            respond(
+                &remote,
+                StatusCode::CONFLICT,
+                &[],
+                Some(
+                    r#"[
+                        {"uuid": "e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c", "devices": {"missingDevices": [4]}},
+                        {"uuid": "PNI:a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c", "devices": {"extraDevices": [5]}}
+                    ]"#
+                ),
+            ),
+        );
+        assert_matches!(result, Err(ApiError::MismatchedDevices(entries)) => {
+            assert_eq!(entries, [
+                MismatchedDevices {
+                    account: Aci::from(ACI).into(),
+                    missing_devices: vec![4.into()],
+                    extra_devices: vec![],
+                },
+                MismatchedDevices {
+                    account: Pni::from(PNI).into(),
+                    missing_devices: vec![],
+                    extra_devices: vec![5.into()],
+                },
+            ]);
+        });
+    }
+
+    #[tokio::test]
+    async fn send_multi_recipient_unauthorized() {
+        let (chat, remote) = fake_chat();
+        let (result, _request) = tokio::join!(
+            send_multi_recipient_message(
+                &chat,
+                b"payload",
+                OPTIONS,
+                MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::UNAUTHORIZED, &[], None),
+        );
+        assert_matches!(result, Err(ApiError::Unauthorized));
+    }
+
+    #[test]
+    fn sender_key_messages_are_not_single_recipient() {
+        assert_matches!(
+            EnvelopeType::try_from(CiphertextMessageType::SenderKey),
+            Err(ApiError::InvalidRequest(_))
+        );
+    }
+}
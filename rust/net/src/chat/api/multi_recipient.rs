@@ -0,0 +1,890 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Sealed sender multi-recipient sends that recover from device list
+//! mismatches.
+//!
+//! [`send_multi_recipient_message`] reports 409 and 410 responses as errors
+//! and leaves it to the caller to fix up its sessions. [`send_with_recovery`]
+//! does that fixing up itself: it archives sessions for devices the server
+//! says are gone or stale, fetches bundles for devices that are missing, and
+//! re-encrypts and resends until the server accepts the message or the
+//! attempt limit is reached.
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::time::{Duration, SystemTime};
+
+use libsignal_core::{DeviceId, ProtocolAddress, ServiceId};
+use libsignal_protocol::{
+    sealed_sender_multi_recipient_encrypt, IdentityKeyStore, SessionStore, SignalProtocolError,
+    UnidentifiedSenderMessageContent,
+};
+use rand::{CryptoRng, Rng};
+
+use super::keys::{get_prekey_bundles, process_prekey_bundles, DeviceSelector};
+use super::messages::{send_multi_recipient_message, MultiRecipientAccess, SendOptions};
+use super::{ApiError, ChatSender, MismatchedDevices, StaleDevices};
+
+/// A recipient of a multi-recipient message.
+#[derive(Clone, Debug)]
+pub struct Recipient {
+    pub service_id: ServiceId,
+    /// The devices the sender believes the recipient has.
+    ///
+    /// If empty, the recipient's full device list is fetched from the server
+    /// along with their prekey bundles.
+    pub devices: Vec<DeviceId>,
+    /// The recipient's unidentified access key, used to fetch prekey bundles
+    /// over an unauthenticated connection.
+    pub access_key: Option<[u8; 16]>,
+}
+
+/// What happened to one recipient of a [`send_with_recovery`] call.
+#[derive(Debug)]
+pub enum RecipientOutcome {
+    /// The message was accepted for these devices.
+    ///
+    /// The device list may differ from the one passed in; callers should
+    /// store it for future sends.
+    Sent { devices: Vec<DeviceId> },
+    /// The server doesn't know this account, or it has no devices.
+    NotFound,
+    /// The recipient's identity key has changed and the new key is not
+    /// trusted by the identity store.
+    UntrustedIdentity,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum MultiRecipientSendError {
+    /// {0}
+    Api(#[from] ApiError),
+    /// {0}
+    Protocol(#[from] SignalProtocolError),
+    /// device lists still did not match after {0} attempts
+    TooManyAttempts(NonZeroUsize),
+}
+
+/// The message to send and how to send it.
+#[derive(Clone, Copy)]
+pub struct MultiRecipientMessage<'a> {
+    pub recipients: &'a [Recipient],
+    pub content: &'a UnidentifiedSenderMessageContent,
+    pub options: SendOptions,
+    pub access: MultiRecipientAccess<'a>,
+}
+
+/// The state of one recipient across attempts.
+struct PendingRecipient {
+    service_id: ServiceId,
+    devices: Vec<DeviceId>,
+    access_key: Option<[u8; 16]>,
+    /// Set once the recipient has dropped out of the send.
+    outcome: Option<RecipientOutcome>,
+}
+
+impl PendingRecipient {
+    fn address(&self, device_id: DeviceId) -> ProtocolAddress {
+        ProtocolAddress::new(self.service_id.service_id_string(), device_id)
+    }
+}
+
+/// Encrypts `message.content` for every device of every recipient and sends
+/// it, recovering from device list mismatches.
+///
+/// Each attempt first makes sure there is a usable session for every device,
+/// fetching and processing prekey bundles where there isn't. A 409 or 410
+/// response archives the sessions it names and updates the device lists
+/// before the next attempt. Any other failure ends the send.
+///
+/// Recipients that turn out not to exist, or whose new identity key is not
+/// trusted, are dropped from later attempts rather than failing the whole
+/// send. On success, returns an outcome for each recipient, in order.
+pub async fn send_with_recovery<R: Rng + CryptoRng>(
+    chat: &(impl ChatSender + ?Sized),
+    message: MultiRecipientMessage<'_>,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    csprng: &mut R,
+    max_attempts: NonZeroUsize,
+    timeout: Duration,
+) -> Result<Vec<(ServiceId, RecipientOutcome)>, MultiRecipientSendError> {
+    let MultiRecipientMessage {
+        recipients,
+        content,
+        options,
+        access,
+    } = message;
+
+    let mut pending: Vec<PendingRecipient> = recipients
+        .iter()
+        .map(|recipient| PendingRecipient {
+            service_id: recipient.service_id,
+            devices: recipient.devices.clone(),
+            access_key: recipient.access_key,
+            outcome: None,
+        })
+        .collect();
+
+    for _ in 0..max_attempts.get() {
+        for recipient in pending.iter_mut().filter(|r| r.outcome.is_none()) {
+            ensure_sessions(
+                chat,
+                recipient,
+                session_store,
+                identity_store,
+                csprng,
+                timeout,
+            )
+            .await?;
+        }
+
+        let mut addresses = vec![];
+        let mut sessions = vec![];
+        for recipient in pending.iter().filter(|r| r.outcome.is_none()) {
+            for &device_id in &recipient.devices {
+                let address = recipient.address(device_id);
+                // The store is shared with the rest of the app, so the
+                // session ensure_sessions set up may have gone away since.
+                let session = session_store
+                    .load_session(&address)
+                    .await?
+                    .ok_or_else(|| SignalProtocolError::SessionNotFound(address.clone()))?;
+                addresses.push(address);
+                sessions.push(session);
+            }
+        }
+
+        // Everyone dropped out; there's nothing left to send.
+        if addresses.is_empty() {
+            return Ok(finish(pending, &[]));
+        }
+
+        let payload = sealed_sender_multi_recipient_encrypt(
+            &addresses.iter().collect::<Vec<_>>(),
+            &sessions.iter().collect::<Vec<_>>(),
+            std::iter::empty(),
+            content,
+            &*identity_store,
+            csprng,
+        )
+        .await?;
+
+        match send_multi_recipient_message(chat, &payload, options, access, timeout).await {
+            Ok(not_found) => return Ok(finish(pending, &not_found)),
+            Err(ApiError::MismatchedDevices(entries)) => {
+                for entry in entries {
+                    apply_mismatched_devices(&mut pending, entry, session_store).await?;
+                }
+            }
+            Err(ApiError::StaleDevices(entries)) => {
+                for entry in entries {
+                    apply_stale_devices(&pending, entry, session_store).await?;
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(MultiRecipientSendError::TooManyAttempts(max_attempts))
+}
+
+fn finish(
+    pending: Vec<PendingRecipient>,
+    not_found: &[ServiceId],
+) -> Vec<(ServiceId, RecipientOutcome)> {
+    pending
+        .into_iter()
+        .map(|recipient| {
+            let PendingRecipient {
+                service_id,
+                devices,
+                access_key: _,
+                outcome,
+            } = recipient;
+            let outcome = match outcome {
+                Some(outcome) => outcome,
+                None if not_found.contains(&service_id) => RecipientOutcome::NotFound,
+                None => RecipientOutcome::Sent { devices },
+            };
+            (service_id, outcome)
+        })
+        .collect()
+}
+
+/// Fetches and processes prekey bundles for any of `recipient`'s devices that
+/// lack a usable session.
+///
+/// A device the server doesn't know is dropped from the recipient's device
+/// list; if that leaves none, the full list is fetched instead. Marks the
+/// recipient as finished if the server doesn't know the account or their
+/// identity key isn't trusted.
+async fn ensure_sessions<R: Rng + CryptoRng>(
+    chat: &(impl ChatSender + ?Sized),
+    recipient: &mut PendingRecipient,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    csprng: &mut R,
+    timeout: Duration,
+) -> Result<(), MultiRecipientSendError> {
+    let now = SystemTime::now();
+    let mut selectors = if recipient.devices.is_empty() {
+        VecDeque::from([DeviceSelector::AllDevices])
+    } else {
+        let mut selectors = VecDeque::new();
+        for &device_id in &recipient.devices {
+            let usable = match session_store
+                .load_session(&recipient.address(device_id))
+                .await?
+            {
+                Some(session) => session.has_usable_sender_chain(now)?,
+                None => false,
+            };
+            if !usable {
+                selectors.push_back(DeviceSelector::Device(device_id));
+            }
+        }
+        selectors
+    };
+
+    while let Some(selector) = selectors.pop_front() {
+        let bundles = match get_prekey_bundles(
+            chat,
+            recipient.service_id,
+            selector,
+            recipient.access_key.as_ref(),
+            timeout,
+        )
+        .await
+        {
+            Ok(bundles) => bundles,
+            Err(ApiError::NotFound) => match selector {
+                DeviceSelector::AllDevices => {
+                    recipient.outcome = Some(RecipientOutcome::NotFound);
+                    return Ok(());
+                }
+                DeviceSelector::Device(device_id) => {
+                    recipient.devices.retain(|&d| d != device_id);
+                    if recipient.devices.is_empty() {
+                        selectors.push_back(DeviceSelector::AllDevices);
+                    }
+                    continue;
+                }
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        match process_prekey_bundles(
+            recipient.service_id,
+            &bundles,
+            session_store,
+            identity_store,
+            now,
+            csprng,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(SignalProtocolError::UntrustedIdentity(_)) => {
+                recipient.outcome = Some(RecipientOutcome::UntrustedIdentity);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if selector == DeviceSelector::AllDevices {
+            recipient.devices = bundles
+                .iter()
+                .map(|bundle| bundle.device_id())
+                .collect::<Result<_, _>>()?;
+        }
+    }
+
+    if recipient.devices.is_empty() {
+        recipient.outcome = Some(RecipientOutcome::NotFound);
+    }
+    Ok(())
+}
+
+async fn archive_session(
+    session_store: &mut dyn SessionStore,
+    address: &ProtocolAddress,
+) -> Result<(), SignalProtocolError> {
+    let Some(mut session) = session_store.load_session(address).await? else {
+        return Ok(());
+    };
+    session.archive_current_state()?;
+    session_store.store_session(address, &session).await
+}
+
+fn find_recipient(
+    pending: &[PendingRecipient],
+    account: ServiceId,
+) -> Result<usize, MultiRecipientSendError> {
+    pending
+        .iter()
+        .position(|r| r.service_id == account && r.outcome.is_none())
+        .ok_or(MultiRecipientSendError::Api(ApiError::InvalidResponse(
+            "device list error for an unknown recipient",
+        )))
+}
+
+async fn apply_mismatched_devices(
+    pending: &mut [PendingRecipient],
+    entry: MismatchedDevices,
+    session_store: &mut dyn SessionStore,
+) -> Result<(), MultiRecipientSendError> {
+    let MismatchedDevices {
+        account,
+        missing_devices,
+        extra_devices,
+    } = entry;
+    let index = find_recipient(pending, account)?;
+    let recipient = &mut pending[index];
+
+    for device_id in extra_devices {
+        archive_session(session_store, &recipient.address(device_id)).await?;
+        recipient.devices.retain(|&d| d != device_id);
+    }
+    for device_id in missing_devices {
+        if !recipient.devices.contains(&device_id) {
+            recipient.devices.push(device_id);
+        }
+    }
+    Ok(())
+}
+
+async fn apply_stale_devices(
+    pending: &[PendingRecipient],
+    entry: StaleDevices,
+    session_store: &mut dyn SessionStore,
+) -> Result<(), MultiRecipientSendError> {
+    let StaleDevices {
+        account,
+        stale_devices,
+    } = entry;
+    let recipient = &pending[find_recipient(pending, account)?];
+
+    // The next attempt will fetch new bundles for these devices, since
+    // their sessions are no longer usable.
+    for device_id in stale_devices {
+        archive_session(session_store, &recipient.address(device_id)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use base64::prelude::{Engine as _, BASE64_STANDARD};
+    use http::StatusCode;
+    use libsignal_core::Aci;
+    use libsignal_protocol::{
+        kem, CiphertextMessageType, ContentHint, IdentityKeyPair, InMemSignalProtocolStore,
+        KeyPair, SenderCertificate, ServerCertificate, Timestamp,
+    };
+    use nonzero_ext::nonzero;
+    use rand::rngs::OsRng;
+    use serde_json::json;
+    use uuid::uuid;
+
+    use super::super::testutil::{fake_chat, respond};
+    use super::*;
+    use crate::chat::fake::FakeChatRemote;
+    use crate::chat::RequestProto;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const ALICE: uuid::Uuid = uuid!("e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+    const BOB: uuid::Uuid = uuid!("a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+    const BOB_PATH: &str = "/v2/keys/a3f4b2c1-3aa7-4bd1-8a3b-9a3c4ab6cf0c";
+
+    const OPTIONS: SendOptions = SendOptions {
+        timestamp: Timestamp::from_epoch_millis(1700000000000),
+        online: false,
+        urgent: true,
+    };
+
+    fn bob() -> ServiceId {
+        Aci::from(BOB).into()
+    }
+
+    fn alice_store_and_content() -> (InMemSignalProtocolStore, UnidentifiedSenderMessageContent) {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let trust_root = KeyPair::generate(&mut OsRng);
+        let server_key = KeyPair::generate(&mut OsRng);
+        let server_cert = ServerCertificate::new(
+            1,
+            server_key.public_key,
+            &trust_root.private_key,
+            &mut OsRng,
+        )
+        .expect("valid");
+        let sender_cert = SenderCertificate::new(
+            ALICE.to_string(),
+            None,
+            *identity.public_key(),
+            DeviceId::from(1),
+            Timestamp::from_epoch_millis(1700000000000),
+            server_cert,
+            &server_key.private_key,
+            &mut OsRng,
+        )
+        .expect("valid");
+        let content = UnidentifiedSenderMessageContent::new(
+            CiphertextMessageType::Plaintext,
+            sender_cert,
+            b"hello".to_vec(),
+            ContentHint::Default,
+            None,
+        )
+        .expect("valid");
+        let store = InMemSignalProtocolStore::new(identity, 1).expect("valid");
+        (store, content)
+    }
+
+    /// Builds a `/v2/keys` response body with fresh prekeys for each device.
+    fn bundles_body(identity: &IdentityKeyPair, device_ids: &[u32]) -> String {
+        let sign = |key: &[u8]| {
+            BASE64_STANDARD.encode(
+                identity
+                    .private_key()
+                    .calculate_signature(key, &mut OsRng)
+                    .expect("can sign"),
+            )
+        };
+        let devices: Vec<_> = device_ids
+            .iter()
+            .map(|&device_id| {
+                let signed_pre_key = KeyPair::generate(&mut OsRng).public_key.serialize();
+                let kyber_pre_key = kem::KeyPair::generate(kem::KeyType::Kyber1024)
+                    .public_key
+                    .serialize();
+                json!({
+                    "deviceId": device_id,
+                    "registrationId": 100 + device_id,
+                    "signedPreKey": {
+                        "keyId": 1,
+                        "publicKey": BASE64_STANDARD.encode(&signed_pre_key),
+                        "signature": sign(&signed_pre_key),
+                    },
+                    "pqPreKey": {
+                        "keyId": 2,
+                        "publicKey": BASE64_STANDARD.encode(&kyber_pre_key),
+                        "signature": sign(&kyber_pre_key),
+                    },
+                })
+            })
+            .collect();
+        json!({
+            "identityKey": BASE64_STANDARD.encode(identity.identity_key().serialize()),
+            "devices": devices,
+        })
+        .to_string()
+    }
+
+    async fn expect_request(
+        remote: &FakeChatRemote,
+        verb: &str,
+        path: &str,
+        status: StatusCode,
+        body: Option<&str>,
+    ) -> RequestProto {
+        let request = respond(remote, status, &[], body).await;
+        assert_eq!(request.verb(), verb);
+        assert_eq!(request.path(), path);
+        request
+    }
+
+    fn multi_recipient_path() -> String {
+        "/v1/messages/multi_recipient?ts=1700000000000&online=false&urgent=true".to_owned()
+    }
+
+    #[tokio::test]
+    async fn fetches_missing_devices_and_retries() {
+        let (mut alice, content) = alice_store_and_content();
+        let bob_identity = IdentityKeyPair::generate(&mut OsRng);
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![],
+            access_key: None,
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(3usize),
+                TIMEOUT,
+            ),
+            async {
+                let body = bundles_body(&bob_identity, &[1]);
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/*"),
+                    StatusCode::OK,
+                    Some(&body),
+                )
+                .await;
+                let conflict = format!(
+                    r#"[{{"uuid": "{BOB}", "devices": {{"missingDevices": [2], "extraDevices": []}}}}]"#
+                );
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::CONFLICT,
+                    Some(&conflict),
+                )
+                .await;
+                let body = bundles_body(&bob_identity, &[2]);
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/2"),
+                    StatusCode::OK,
+                    Some(&body),
+                )
+                .await;
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::OK,
+                    Some("{}"),
+                )
+                .await;
+            },
+        );
+
+        let outcomes = result.expect("success");
+        assert_matches!(&outcomes[..], [(service_id, RecipientOutcome::Sent { devices })] => {
+            assert_eq!(*service_id, bob());
+            assert_eq!(devices, &[DeviceId::from(1), DeviceId::from(2)]);
+        });
+    }
+
+    #[tokio::test]
+    async fn refreshes_stale_and_drops_extra_devices() {
+        let (mut alice, content) = alice_store_and_content();
+        let bob_identity = IdentityKeyPair::generate(&mut OsRng);
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![1.into(), 2.into()],
+            access_key: Some([1; 16]),
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::CombinedAccessKey(&[1; 16]),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(3usize),
+                TIMEOUT,
+            ),
+            async {
+                for device_id in [1, 2] {
+                    let body = bundles_body(&bob_identity, &[device_id]);
+                    expect_request(
+                        &remote,
+                        "GET",
+                        &format!("{BOB_PATH}/{device_id}"),
+                        StatusCode::OK,
+                        Some(&body),
+                    )
+                    .await;
+                }
+                let gone = format!(r#"[{{"uuid": "{BOB}", "devices": {{"staleDevices": [1]}}}}]"#);
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::GONE,
+                    Some(&gone),
+                )
+                .await;
+                let body = bundles_body(&bob_identity, &[1]);
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/1"),
+                    StatusCode::OK,
+                    Some(&body),
+                )
+                .await;
+                let conflict = format!(
+                    r#"[{{"uuid": "{BOB}", "devices": {{"missingDevices": [], "extraDevices": [2]}}}}]"#
+                );
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::CONFLICT,
+                    Some(&conflict),
+                )
+                .await;
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::OK,
+                    None,
+                )
+                .await;
+            },
+        );
+
+        let outcomes = result.expect("success");
+        assert_matches!(&outcomes[..], [(_, RecipientOutcome::Sent { devices })] => {
+            assert_eq!(devices, &[DeviceId::from(1)]);
+        });
+        let device_2 = ProtocolAddress::new(bob().service_id_string(), 2.into());
+        let session = alice
+            .session_store
+            .load_session(&device_2)
+            .await
+            .expect("can load")
+            .expect("archived session is kept");
+        assert!(!session
+            .has_usable_sender_chain(SystemTime::now())
+            .expect("valid"));
+    }
+
+    #[tokio::test]
+    async fn unknown_recipient_is_not_found() {
+        let (mut alice, content) = alice_store_and_content();
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![],
+            access_key: None,
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::GroupSendToken(b"token"),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(3usize),
+                TIMEOUT,
+            ),
+            async {
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/*"),
+                    StatusCode::NOT_FOUND,
+                    None,
+                )
+                .await;
+            },
+        );
+
+        let outcomes = result.expect("success");
+        assert_matches!(&outcomes[..], [(_, RecipientOutcome::NotFound)]);
+    }
+
+    #[tokio::test]
+    async fn missing_device_is_dropped_without_dropping_recipient() {
+        let (mut alice, content) = alice_store_and_content();
+        let bob_identity = IdentityKeyPair::generate(&mut OsRng);
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![1.into(), 2.into()],
+            access_key: None,
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(3usize),
+                TIMEOUT,
+            ),
+            async {
+                let body = bundles_body(&bob_identity, &[1]);
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/1"),
+                    StatusCode::OK,
+                    Some(&body),
+                )
+                .await;
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/2"),
+                    StatusCode::NOT_FOUND,
+                    None,
+                )
+                .await;
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::OK,
+                    Some("{}"),
+                )
+                .await;
+            },
+        );
+
+        let outcomes = result.expect("success");
+        assert_matches!(&outcomes[..], [(_, RecipientOutcome::Sent { devices })] => {
+            assert_eq!(devices, &[DeviceId::from(1)]);
+        });
+    }
+
+    #[tokio::test]
+    async fn all_devices_missing_falls_back_to_full_device_list() {
+        let (mut alice, content) = alice_store_and_content();
+        let bob_identity = IdentityKeyPair::generate(&mut OsRng);
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![2.into()],
+            access_key: None,
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(3usize),
+                TIMEOUT,
+            ),
+            async {
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/2"),
+                    StatusCode::NOT_FOUND,
+                    None,
+                )
+                .await;
+                let body = bundles_body(&bob_identity, &[3]);
+                expect_request(
+                    &remote,
+                    "GET",
+                    &format!("{BOB_PATH}/*"),
+                    StatusCode::OK,
+                    Some(&body),
+                )
+                .await;
+                expect_request(
+                    &remote,
+                    "PUT",
+                    &multi_recipient_path(),
+                    StatusCode::OK,
+                    Some("{}"),
+                )
+                .await;
+            },
+        );
+
+        let outcomes = result.expect("success");
+        assert_matches!(&outcomes[..], [(_, RecipientOutcome::Sent { devices })] => {
+            assert_eq!(devices, &[DeviceId::from(3)]);
+        });
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (mut alice, content) = alice_store_and_content();
+        let bob_identity = IdentityKeyPair::generate(&mut OsRng);
+        let recipients = [Recipient {
+            service_id: bob(),
+            devices: vec![1.into()],
+            access_key: None,
+        }];
+
+        let (chat, remote) = fake_chat();
+        let (result, ()) = tokio::join!(
+            send_with_recovery(
+                &chat,
+                MultiRecipientMessage {
+                    recipients: &recipients,
+                    content: &content,
+                    options: OPTIONS,
+                    access: MultiRecipientAccess::CombinedAccessKey(&[0; 16]),
+                },
+                &mut alice.session_store,
+                &mut alice.identity_store,
+                &mut OsRng,
+                nonzero!(2usize),
+                TIMEOUT,
+            ),
+            async {
+                let gone = format!(r#"[{{"uuid": "{BOB}", "devices": {{"staleDevices": [1]}}}}]"#);
+                for _ in 0..2 {
+                    let body = bundles_body(&bob_identity, &[1]);
+                    expect_request(
+                        &remote,
+                        "GET",
+                        &format!("{BOB_PATH}/1"),
+                        StatusCode::OK,
+                        Some(&body),
+                    )
+                    .await;
+                    expect_request(
+                        &remote,
+                        "PUT",
+                        &multi_recipient_path(),
+                        StatusCode::GONE,
+                        Some(&gone),
+                    )
+                    .await;
+                }
+            },
+        );
+
+        assert_matches!(
+            result,
+            Err(MultiRecipientSendError::TooManyAttempts(attempts)) if attempts.get() == 2
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Profile endpoints.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use libsignal_core::{Aci, ServiceId};
+use libsignal_protocol::IdentityKey;
+use serde::Deserialize;
+
+use super::{
+    add_access_key, get_request, parse_json_body, path_and_query, send, ApiError, ChatSender,
+};
+
+const PROFILE_PATH_PREFIX: &str = "/v1/profile";
+
+/// Which profile to fetch.
+#[derive(Clone, Copy, Debug)]
+pub enum ProfileRequest<'a> {
+    /// The unversioned profile of an account, which includes only
+    /// its identity key and sealed sender information.
+    Unversioned(ServiceId),
+    /// A particular version of an account's profile, identified by the hex
+    /// string derived from the profile key.
+    Versioned { aci: Aci, version: &'a str },
+}
+
+/// A profile as returned by the server.
+///
+/// Fields other than `identity_key` are encrypted with the profile key and
+/// are only present for versioned requests.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub identity_key: IdentityKey,
+    pub encrypted_name: Option<Vec<u8>>,
+    pub encrypted_about: Option<Vec<u8>>,
+    pub encrypted_about_emoji: Option<Vec<u8>>,
+    pub encrypted_payment_address: Option<Vec<u8>>,
+    /// The path of the avatar on the CDN.
+    pub avatar: Option<String>,
+    /// A verifier for the account's unidentified access key, if it has one.
+    pub unidentified_access: Option<Vec<u8>>,
+    pub unrestricted_unidentified_access: bool,
+    pub capabilities: HashMap<String, bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawProfile {
+    identity_key: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    about: Option<String>,
+    #[serde(default)]
+    about_emoji: Option<String>,
+    #[serde(default)]
+    payment_address: Option<String>,
+    #[serde(default)]
+    avatar: Option<String>,
+    #[serde(default)]
+    unidentified_access: Option<String>,
+    #[serde(default)]
+    unrestricted_unidentified_access: bool,
+    #[serde(default)]
+    capabilities: HashMap<String, bool>,
+}
+
+fn decode_optional(
+    value: Option<String>,
+    error: &'static str,
+) -> Result<Option<Vec<u8>>, ApiError> {
+    value
+        .map(|v| BASE64_STANDARD.decode(v))
+        .transpose()
+        .map_err(|_| ApiError::InvalidResponse(error))
+}
+
+impl TryFrom<RawProfile> for Profile {
+    type Error = ApiError;
+
+    fn try_from(raw: RawProfile) -> Result<Self, Self::Error> {
+        let RawProfile {
+            identity_key,
+            name,
+            about,
+            about_emoji,
+            payment_address,
+            avatar,
+            unidentified_access,
+            unrestricted_unidentified_access,
+            capabilities,
+        } = raw;
+        let identity_key = BASE64_STANDARD
+            .decode(identity_key)
+            .ok()
+            .and_then(|key| IdentityKey::decode(&key).ok())
+            .ok_or(ApiError::InvalidResponse("invalid identity key"))?;
+        Ok(Self {
+            identity_key,
+            encrypted_name: decode_optional(name, "invalid name")?,
+            encrypted_about: decode_optional(about, "invalid about")?,
+            encrypted_about_emoji: decode_optional(about_emoji, "invalid about emoji")?,
+            encrypted_payment_address: decode_optional(payment_address, "invalid payment address")?,
+            avatar,
+            unidentified_access: decode_optional(
+                unidentified_access,
+                "invalid unidentified access verifier",
+            )?,
+            unrestricted_unidentified_access,
+            capabilities,
+        })
+    }
+}
+
+/// Fetches a profile.
+///
+/// If `access_key` is provided, it is sent as the recipient's unidentified
+/// access key, which allows fetching profiles over an unauthenticated
+/// connection.
+pub async fn get_profile(
+    chat: &(impl ChatSender + ?Sized),
+    request: ProfileRequest<'_>,
+    access_key: Option<&[u8; 16]>,
+    timeout: Duration,
+) -> Result<Profile, ApiError> {
+    let path = match request {
+        ProfileRequest::Unversioned(service_id) => {
+            format!("{PROFILE_PATH_PREFIX}/{}", service_id.service_id_string())
+        }
+        ProfileRequest::Versioned { aci, version } => {
+            if version.is_empty() || !version.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ApiError::InvalidRequest("profile version must be hex"));
+            }
+            format!(
+                "{PROFILE_PATH_PREFIX}/{}/{version}",
+                aci.service_id_string()
+            )
+        }
+    };
+    let mut http_request = get_request(path_and_query(path)?);
+    if let Some(access_key) = access_key {
+        add_access_key(&mut http_request, access_key);
+    }
+    let response = send(chat, http_request, timeout).await?;
+    parse_json_body::<RawProfile>(&response)?.try_into()
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use http::StatusCode;
+    use libsignal_protocol::KeyPair;
+    use uuid::uuid;
+
+    use super::super::testutil::{fake_chat, respond};
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const ACI: uuid::Uuid = uuid!("e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c");
+
+    #[tokio::test]
+    async fn versioned_profile_success() {
+        let identity_key = IdentityKey::new(KeyPair::generate(&mut rand::rngs::OsRng).public_key);
+        let body = format!(
+            r#"{{
+                "identityKey": "{}",
+                "name": "AQID",
+                "avatar": "profiles/abc",
+                "unrestrictedUnidentifiedAccess": false,
+                "capabilities": {{"deleteSync": true}},
+                "badges": []
+            }}"#,
+            BASE64_STANDARD.encode(identity_key.serialize())
+        );
+
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            get_profile(
+                &chat,
+                ProfileRequest::Versioned {
+                    aci: Aci::from(ACI),
+                    version: "0123abcd",
+                },
+                Some(&[0xAA; 16]),
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::OK, &[], Some(&body)),
+        );
+        assert_eq!(
+            request.path(),
+            "/v1/profile/e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c/0123abcd"
+        );
+        let expected_header = format!(
+            "unidentified-access-key: {}",
+            BASE64_STANDARD.encode([0xAA; 16])
+        );
+        assert!(request.headers.contains(&expected_header));
+
+        let profile = result.expect("success");
+        assert_eq!(profile.identity_key, identity_key);
+        assert_eq!(profile.encrypted_name, Some(vec![1, 2, 3]));
+        assert_eq!(profile.encrypted_about, None);
+        assert_eq!(profile.avatar.as_deref(), Some("profiles/abc"));
+        assert_eq!(profile.capabilities.get("deleteSync"), Some(&true));
+    }
+
+    #[tokio::test]
+    async fn unversioned_profile_not_found() {
+        let (chat, remote) = fake_chat();
+        let (result, request) = tokio::join!(
+            get_profile(
+                &chat,
+                ProfileRequest::Unversioned(Aci::from(ACI).into()),
+                None,
+                TIMEOUT,
+            ),
+            respond(&remote, StatusCode::NOT_FOUND, &[], None),
+        );
+        assert_eq!(
+            request.path(),
+            "/v1/profile/e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c"
+        );
+        assert_matches!(result, Err(ApiError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn versioned_profile_rejects_bad_version() {
+        let (chat, _remote) = fake_chat();
+        let result = get_profile(
+            &chat,
+            ProfileRequest::Versioned {
+                aci: Aci::from(ACI),
+                version: "../keys",
+            },
+            None,
+            TIMEOUT,
+        )
+        .await;
+        assert_matches!(result, Err(ApiError::InvalidRequest(_)));
+    }
+}
@@ -19,6 +19,8 @@ pub enum ChatServiceError {
     AppExpired,
     /// Device deregistered or delinked
     DeviceDeregistered,
+    /// Server certificate chain doesn't include any pinned key
+    CertificatePinMismatch,
     /// Unexpected text frame received
     UnexpectedFrameReceived,
     /// Request message from the server is missing the `id` field
@@ -67,29 +69,22 @@ impl From<WebSocketServiceConnectError> for ChatServiceError {
     fn from(e: WebSocketServiceConnectError) -> Self {
         match e {
             WebSocketServiceConnectError::Connect(e, _) => match e {
-                WebSocketConnectError::Transport(e) => match e {
-                    TransportConnectError::InvalidConfiguration => {
-                        WebSocketServiceError::Other("invalid configuration")
-                    }
-                    TransportConnectError::TcpConnectionFailed => {
-                        WebSocketServiceError::Other("TCP connection failed")
-                    }
-                    TransportConnectError::DnsError => WebSocketServiceError::Other("DNS error"),
-                    TransportConnectError::SslError(_)
-                    | TransportConnectError::SslFailedHandshake(_) => {
-                        WebSocketServiceError::Other("TLS failure")
-                    }
-                    TransportConnectError::CertError => {
-                        WebSocketServiceError::Other("failed to load certificates")
-                    }
-                    TransportConnectError::ProxyProtocol => {
-                        WebSocketServiceError::Other("proxy protocol error")
-                    }
-                    TransportConnectError::ClientAbort => {
-                        WebSocketServiceError::Other("client abort error")
-                    }
+                WebSocketConnectError::Transport(e) => {
+                    Self::WebSocket(WebSocketServiceError::Other(match e {
+                        TransportConnectError::CertificatePinMismatch => {
+                            return Self::CertificatePinMismatch
+                        }
+                        TransportConnectError::InvalidConfiguration => "invalid configuration",
+                        TransportConnectError::TcpConnectionFailed => "TCP connection failed",
+                        TransportConnectError::DnsError => "DNS error",
+                        TransportConnectError::SslError(_)
+                        | TransportConnectError::SslFailedHandshake(_)
+                        | TransportConnectError::EchRejected { .. } => "TLS failure",
+                        TransportConnectError::CertError => "failed to load certificates",
+                        TransportConnectError::ProxyProtocol => "proxy protocol error",
+                        TransportConnectError::ClientAbort => "client abort error",
+                    }))
                 }
-                .into(),
                 WebSocketConnectError::Timeout => Self::TimeoutEstablishingConnection,
                 WebSocketConnectError::WebSocketError(e) => Self::WebSocket(e.into()),
             },
@@ -15,6 +15,9 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use crate::chat::{ws2, ChatConnection, ConnectionInfo, MessageProto, RequestProto, ResponseProto};
 use crate::connect_state::RouteInfo;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod server;
+
 /// The remote end of a fake connection to the chat server.
 #[derive(Debug)]
 pub struct FakeChatRemote {
@@ -32,6 +35,7 @@ pub enum ReceiveRequestError {
     InvalidProto(String),
     InvalidWebsocketMessageType,
     GotResponse,
+    GotRequest,
 }
 
 impl ChatConnection {
@@ -69,6 +73,7 @@ impl ChatConnection {
             local_idle_timeout: Duration::from_secs(86400),
             remote_idle_timeout: Duration::from_secs(86400),
             initial_request_id: 0,
+            compression: None,
         };
         let chat = Self {
             inner: crate::chat::ws2::Chat::new(tokio_runtime, local, config, log_tag, listener),
@@ -120,6 +125,23 @@ impl FakeChatRemote {
         }
     }
 
+    /// Waits for the client's response to a request sent with
+    /// [`send_request`](Self::send_request).
+    pub async fn receive_response(&self) -> Result<Option<ResponseProto>, ReceiveRequestError> {
+        log::debug!("waiting for next response");
+        let Some(message) = self.rx.lock().await.recv().await else {
+            return Ok(None);
+        };
+        let proto = match message {
+            tungstenite::Message::Binary(message) => ws2::decode_and_validate(&message)?,
+            _ => return Err(ReceiveRequestError::InvalidWebsocketMessageType),
+        };
+        match proto {
+            ws2::ChatMessageProto::Response(response) => Ok(Some(response)),
+            ws2::ChatMessageProto::Request(_) => Err(ReceiveRequestError::GotRequest),
+        }
+    }
+
     /// Send a close frame to the client.
     pub fn send_close(&self, code: Option<u16>) -> Result<(), Disconnected> {
         self.tx
@@ -0,0 +1,938 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! An in-process chat server for integration tests.
+//!
+//! Unlike [`ChatConnection::new_fake`], [`FakeChatServer`] listens on a real
+//! localhost socket, so clients go through the same TLS and websocket
+//! handshake they would against the production service. The server stores
+//! envelopes sent with `PUT /v1/messages/{destination}` and delivers them to
+//! the destination's authenticated connections, and tests can script
+//! handshake rejections, close codes, slow responses, and rate limiting.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{Ipv6Addr, SocketAddr};
+use std::num::NonZeroU16;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use futures_util::{SinkExt as _, StreamExt as _};
+use http::{HeaderName, StatusCode};
+use libsignal_net_infra::certs::RootCertificates;
+use libsignal_net_infra::dns::lookup_result::LookupResult;
+use libsignal_net_infra::dns::DnsResolver;
+use libsignal_net_infra::host::Host;
+use libsignal_net_infra::route::{
+    DirectOrProxyRoute, HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment,
+    UnresolvedHost, UnresolvedHttpsServiceRoute,
+};
+use libsignal_net_infra::{Alpn, DnsSource};
+use prost::Message as _;
+use rcgen::CertifiedKey;
+use serde::Deserialize;
+use tokio::sync::{mpsc, watch};
+use warp::filters::path::FullPath;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter as _, Reply};
+
+use crate::chat::{
+    ws2, AuthenticatedChatHeaders, ChatConnection, ChatMessageType, ChatServiceError, MessageProto,
+    RequestProto, ResponseProto,
+};
+use crate::connect_state::{ConnectState, SUGGESTED_CONNECT_CONFIG};
+use crate::env::constants::WEB_SOCKET_PATH;
+use crate::env::{UserAgent, TIMESTAMP_HEADER_NAME};
+
+/// The hostname [`FakeChatServer`]'s certificate is issued for.
+pub const FAKE_CHAT_SERVER_HOSTNAME: &str = "chat.test-server.signal.org.local";
+
+static CERTIFICATE: LazyLock<CertifiedKey> = LazyLock::new(|| {
+    rcgen::generate_simple_self_signed([FAKE_CHAT_SERVER_HOSTNAME.to_string()])
+        .expect("can generate")
+});
+
+/// A failure to inject into the next websocket connection attempt.
+#[derive(Clone, Debug)]
+pub enum ConnectionFailure {
+    /// Reject the websocket handshake with an HTTP error.
+    RejectHandshake {
+        status: StatusCode,
+        retry_after_seconds: Option<u32>,
+    },
+    /// Complete the handshake, then immediately close with the given code.
+    CloseAfterHandshake(u16),
+}
+
+/// A failure to inject into the next request received from a client.
+#[derive(Clone, Debug)]
+pub enum RequestFailure {
+    /// Handle the request normally, but wait before responding.
+    Delay(Duration),
+    /// Respond with 429 and a `Retry-After` header.
+    RateLimited { retry_after_seconds: u32 },
+    /// Respond with the given status and an empty body.
+    Status(StatusCode),
+}
+
+/// A chat server listening on localhost for the lifetime of the value.
+///
+/// Mailboxes are keyed by the destination path component of
+/// `PUT /v1/messages/{destination}`, and a connection receives the mailbox
+/// named by its basic auth username. Envelopes are opaque to the server: the
+/// `content` of each sent device message is delivered as-is.
+pub struct FakeChatServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    server_task: tokio::task::AbortHandle,
+}
+
+struct Shared {
+    state: Mutex<ServerState>,
+    envelopes_changed: watch::Sender<()>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    accounts: HashMap<String, String>,
+    mailboxes: HashMap<String, VecDeque<StoredEnvelope>>,
+    next_envelope_id: u64,
+    connection_failures: VecDeque<ConnectionFailure>,
+    request_failures: VecDeque<RequestFailure>,
+    received_requests: Vec<RequestProto>,
+}
+
+#[derive(Clone)]
+struct StoredEnvelope {
+    id: u64,
+    server_timestamp: u64,
+    body: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct RawIncomingMessageList {
+    messages: Vec<RawIncomingMessage>,
+}
+
+#[derive(Deserialize)]
+struct RawIncomingMessage {
+    content: String,
+}
+
+impl FakeChatServer {
+    /// Starts listening on an ephemeral localhost port.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn start() -> Self {
+        let (envelopes_changed, _) = watch::channel(());
+        let shared = Arc::new(Shared {
+            state: Default::default(),
+            envelopes_changed,
+        });
+
+        let (address, server) = {
+            let shared = shared.clone();
+            let routes = warp::path::full()
+                .and(warp::header::optional::<String>("authorization"))
+                .and(warp::ws())
+                .map(
+                    move |path: FullPath, authorization: Option<String>, ws: Ws| {
+                        handle_upgrade(&shared, path.as_str(), authorization.as_deref(), ws)
+                    },
+                );
+            warp::serve(routes)
+                .tls()
+                .cert(CERTIFICATE.cert.pem())
+                .key(CERTIFICATE.key_pair.serialize_pem())
+                .bind_ephemeral((Ipv6Addr::LOCALHOST, 0))
+        };
+        let server_task = tokio::spawn(server).abort_handle();
+
+        Self {
+            address,
+            shared,
+            server_task,
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The root certificate clients need to trust to connect to the server.
+    pub fn root_certificates(&self) -> RootCertificates {
+        RootCertificates::FromDer(Cow::Borrowed(CERTIFICATE.cert.der()))
+    }
+
+    /// A resolver that maps [`FAKE_CHAT_SERVER_HOSTNAME`] to the server.
+    pub fn dns_resolver(&self) -> DnsResolver {
+        DnsResolver::new_from_static_map(HashMap::from([(
+            FAKE_CHAT_SERVER_HOSTNAME,
+            LookupResult::new(DnsSource::Static, vec![], vec![Ipv6Addr::LOCALHOST]),
+        )]))
+    }
+
+    /// A direct route to the server, suitable for
+    /// [`ChatConnection::start_connect_with`].
+    pub fn route(&self) -> UnresolvedHttpsServiceRoute {
+        HttpsTlsRoute {
+            fragment: HttpRouteFragment {
+                host_header: FAKE_CHAT_SERVER_HOSTNAME.into(),
+                path_prefix: "".into(),
+                front_name: None,
+            },
+            inner: TlsRoute {
+                fragment: TlsRouteFragment {
+                    root_certs: self.root_certificates(),
+                    sni: Host::Domain(FAKE_CHAT_SERVER_HOSTNAME.into()),
+                    alpn: Some(Alpn::Http1_1),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+                inner: DirectOrProxyRoute::Direct(TcpRoute {
+                    address: UnresolvedHost(FAKE_CHAT_SERVER_HOSTNAME.into()),
+                    port: NonZeroU16::new(self.address.port()).expect("bound port"),
+                }),
+            },
+        }
+    }
+
+    /// Connects to the server the same way a production client would.
+    pub async fn connect(
+        &self,
+        auth: Option<AuthenticatedChatHeaders>,
+        listener: ws2::EventListener,
+    ) -> Result<ChatConnection, ChatServiceError> {
+        let connect = ConnectState::new(SUGGESTED_CONNECT_CONFIG);
+        let pending = ChatConnection::start_connect_with(
+            &connect,
+            &self.dns_resolver(),
+            vec![self.route()],
+            Some(HeaderName::from_static(TIMESTAMP_HEADER_NAME)),
+            &UserAgent::with_libsignal_version("fake chat server"),
+            ws2::Config {
+                initial_request_id: 0,
+                local_idle_timeout: Duration::from_secs(60),
+                remote_idle_timeout: Duration::from_secs(60),
+                compression: Some(Default::default()),
+            },
+            auth,
+            None,
+            "fake chat server",
+        )
+        .await?;
+        Ok(ChatConnection::finish_connect(
+            tokio::runtime::Handle::current(),
+            pending,
+            listener,
+        ))
+    }
+
+    /// Allows connections authenticating as `username` with `password`.
+    ///
+    /// Unauthenticated connections are always accepted, but requests with
+    /// credentials that don't match a registered account are rejected with a
+    /// 401.
+    pub fn add_account(&self, username: &str, password: &str) {
+        self.lock()
+            .accounts
+            .insert(username.to_owned(), password.to_owned());
+    }
+
+    /// Stores an envelope for delivery to `account`'s connections.
+    pub fn enqueue_envelope(&self, account: &str, envelope: Vec<u8>) {
+        self.lock().store_envelope(account, envelope);
+        self.shared.envelopes_changed.send_replace(());
+    }
+
+    /// Envelopes for `account` that haven't been acknowledged yet.
+    pub fn pending_envelopes(&self, account: &str) -> Vec<Vec<u8>> {
+        self.lock()
+            .mailboxes
+            .get(account)
+            .into_iter()
+            .flatten()
+            .map(|envelope| envelope.body.clone())
+            .collect()
+    }
+
+    /// Every request received from a client so far, in order.
+    pub fn received_requests(&self) -> Vec<RequestProto> {
+        self.lock().received_requests.clone()
+    }
+
+    /// Applies `failure` to the next connection attempt.
+    ///
+    /// Multiple failures are applied to successive attempts in order.
+    pub fn fail_next_connection(&self, failure: ConnectionFailure) {
+        self.lock().connection_failures.push_back(failure);
+    }
+
+    /// Applies `failure` to the next request from any client.
+    ///
+    /// Multiple failures are applied to successive requests in order.
+    pub fn fail_next_request(&self, failure: RequestFailure) {
+        self.lock().request_failures.push_back(failure);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ServerState> {
+        self.shared.lock()
+    }
+}
+
+impl Drop for FakeChatServer {
+    fn drop(&mut self) {
+        self.server_task.abort();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, ServerState> {
+        self.state.lock().expect("not poisoned")
+    }
+}
+
+impl ServerState {
+    fn store_envelope(&mut self, account: &str, body: Vec<u8>) {
+        let id = self.next_envelope_id;
+        self.next_envelope_id += 1;
+        self.mailboxes
+            .entry(account.to_owned())
+            .or_default()
+            .push_back(StoredEnvelope {
+                id,
+                server_timestamp: now_millis(),
+                body,
+            });
+    }
+
+    /// Returns the authenticated account, `Ok(None)` for an anonymous
+    /// connection, or `Err(())` if the credentials are wrong.
+    fn authenticate(&self, authorization: Option<&str>) -> Result<Option<String>, ()> {
+        let Some(authorization) = authorization else {
+            return Ok(None);
+        };
+        let credentials = authorization
+            .strip_prefix("Basic ")
+            .and_then(|encoded| BASE64_STANDARD.decode(encoded).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .ok_or(())?;
+        let (username, password) = credentials.split_once(':').ok_or(())?;
+        match self.accounts.get(username) {
+            Some(expected) if expected == password => Ok(Some(username.to_owned())),
+            _ => Err(()),
+        }
+    }
+}
+
+fn handle_upgrade(
+    shared: &Arc<Shared>,
+    path: &str,
+    authorization: Option<&str>,
+    ws: Ws,
+) -> Box<dyn Reply> {
+    if path != WEB_SOCKET_PATH {
+        return http_error(StatusCode::NOT_FOUND, None);
+    }
+
+    let (failure, account) = {
+        let mut state = shared.lock();
+        (
+            state.connection_failures.pop_front(),
+            state.authenticate(authorization),
+        )
+    };
+    let close_code = match failure {
+        Some(ConnectionFailure::RejectHandshake {
+            status,
+            retry_after_seconds,
+        }) => return http_error(status, retry_after_seconds),
+        Some(ConnectionFailure::CloseAfterHandshake(code)) => Some(code),
+        None => None,
+    };
+    let Ok(account) = account else {
+        return http_error(StatusCode::UNAUTHORIZED, None);
+    };
+
+    let shared = shared.clone();
+    Box::new(ws.on_upgrade(move |socket| serve_connection(shared, socket, account, close_code)))
+}
+
+fn http_error(status: StatusCode, retry_after_seconds: Option<u32>) -> Box<dyn Reply> {
+    let mut response = warp::http::Response::builder()
+        .status(status.as_u16())
+        .header(TIMESTAMP_HEADER_NAME, now_millis().to_string());
+    if let Some(retry_after_seconds) = retry_after_seconds {
+        response = response.header("retry-after", retry_after_seconds.to_string());
+    }
+    Box::new(response.body(Vec::new()).expect("valid response"))
+}
+
+async fn serve_connection(
+    shared: Arc<Shared>,
+    socket: WebSocket,
+    account: Option<String>,
+    close_code: Option<u16>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    if let Some(code) = close_code {
+        let _ignore_send_failure = sink.send(Message::close_with(code, "scripted close")).await;
+        return;
+    }
+
+    // Responses may be delayed, so everything goes through a channel to a
+    // single writer.
+    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded_channel::<MessageProto>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing_rx.recv().await {
+            if sink
+                .send(Message::binary(message.encode_to_vec()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    let mut envelopes_changed = shared.envelopes_changed.subscribe();
+    let mut delivery = account.map(|account| Delivery {
+        account,
+        next_request_id: 1,
+        in_flight: HashMap::new(),
+        sent_queue_empty: false,
+    });
+
+    loop {
+        if let Some(delivery) = &mut delivery {
+            delivery.deliver_new(&shared, &outgoing_tx);
+        }
+
+        tokio::select! {
+            changed = envelopes_changed.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+            incoming = stream.next() => {
+                let Some(Ok(message)) = incoming else {
+                    break;
+                };
+                if message.is_close() {
+                    break;
+                }
+                if !message.is_binary() {
+                    continue;
+                }
+                match ws2::decode_and_validate(message.as_bytes()) {
+                    Ok(ws2::ChatMessageProto::Request(request)) => {
+                        handle_request(&shared, request, &outgoing_tx);
+                    }
+                    Ok(ws2::ChatMessageProto::Response(response)) => {
+                        if let Some(delivery) = &mut delivery {
+                            delivery.handle_ack(&shared, response);
+                        }
+                    }
+                    Err(e) => log::warn!("fake chat server ignoring invalid message: {e}"),
+                }
+            }
+        }
+    }
+
+    drop(outgoing_tx);
+    let _ignore_join_error = writer.await;
+}
+
+/// Per-connection envelope delivery state.
+struct Delivery {
+    account: String,
+    next_request_id: u64,
+    /// Maps outgoing request IDs to the envelopes they carry.
+    in_flight: HashMap<u64, u64>,
+    sent_queue_empty: bool,
+}
+
+impl Delivery {
+    fn deliver_new(&mut self, shared: &Shared, outgoing: &mpsc::UnboundedSender<MessageProto>) {
+        let envelopes: Vec<StoredEnvelope> = {
+            let state = shared.lock();
+            let sent: HashSet<u64> = self.in_flight.values().copied().collect();
+            state
+                .mailboxes
+                .get(&self.account)
+                .into_iter()
+                .flatten()
+                .filter(|envelope| !sent.contains(&envelope.id))
+                .cloned()
+                .collect()
+        };
+
+        for StoredEnvelope {
+            id,
+            server_timestamp,
+            body,
+        } in envelopes
+        {
+            let request_id = self.next_request_id();
+            self.in_flight.insert(request_id, id);
+            send_request(
+                outgoing,
+                RequestProto {
+                    verb: Some("PUT".to_owned()),
+                    path: Some("/api/v1/message".to_owned()),
+                    body: Some(body),
+                    headers: vec![format!("{TIMESTAMP_HEADER_NAME}: {server_timestamp}")],
+                    id: Some(request_id),
+                },
+            );
+        }
+
+        if !self.sent_queue_empty {
+            self.sent_queue_empty = true;
+            let request_id = self.next_request_id();
+            send_request(
+                outgoing,
+                RequestProto {
+                    verb: Some("PUT".to_owned()),
+                    path: Some("/api/v1/queue/empty".to_owned()),
+                    body: None,
+                    headers: vec![],
+                    id: Some(request_id),
+                },
+            );
+        }
+    }
+
+    fn handle_ack(&mut self, shared: &Shared, response: ResponseProto) {
+        if response.status != Some(StatusCode::OK.as_u16().into()) {
+            // Like the real server, keep treating the envelope as in flight,
+            // so it's only redelivered on the next connection.
+            return;
+        }
+        let Some(envelope_id) = response.id.and_then(|id| self.in_flight.remove(&id)) else {
+            return;
+        };
+        if let Some(mailbox) = shared.lock().mailboxes.get_mut(&self.account) {
+            mailbox.retain(|envelope| envelope.id != envelope_id);
+        }
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        id
+    }
+}
+
+fn handle_request(
+    shared: &Arc<Shared>,
+    request: RequestProto,
+    outgoing: &mpsc::UnboundedSender<MessageProto>,
+) {
+    let id = request.id.unwrap_or_default();
+    let failure = {
+        let mut state = shared.lock();
+        state.received_requests.push(request.clone());
+        state.request_failures.pop_front()
+    };
+
+    let (response, delay) = match failure {
+        Some(RequestFailure::RateLimited {
+            retry_after_seconds,
+        }) => {
+            let mut response = empty_response(id, StatusCode::TOO_MANY_REQUESTS);
+            response
+                .headers
+                .push(format!("retry-after: {retry_after_seconds}"));
+            (response, None)
+        }
+        Some(RequestFailure::Status(status)) => (empty_response(id, status), None),
+        Some(RequestFailure::Delay(delay)) => (route_request(shared, request), Some(delay)),
+        None => (route_request(shared, request), None),
+    };
+
+    let outgoing = outgoing.clone();
+    let message = MessageProto {
+        r#type: Some(ChatMessageType::Response.into()),
+        request: None,
+        response: Some(response),
+    };
+    match delay {
+        None => {
+            let _ignore_closed = outgoing.send(message);
+        }
+        Some(delay) => {
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ignore_closed = outgoing.send(message);
+            });
+        }
+    }
+}
+
+fn route_request(shared: &Shared, request: RequestProto) -> ResponseProto {
+    let id = request.id.unwrap_or_default();
+    let verb = request.verb.as_deref().unwrap_or_default();
+    let path = request.path.as_deref().unwrap_or_default();
+    let path = path.split_once('?').map_or(path, |(path, _query)| path);
+
+    match (verb, path.strip_prefix("/v1/messages/")) {
+        ("PUT", Some(destination)) => {
+            let Some(messages) = request
+                .body
+                .as_deref()
+                .and_then(|body| serde_json::from_slice::<RawIncomingMessageList>(body).ok())
+                .and_then(|list| {
+                    list.messages
+                        .into_iter()
+                        .map(|message| BASE64_STANDARD.decode(message.content).ok())
+                        .collect::<Option<Vec<_>>>()
+                })
+            else {
+                return empty_response(id, StatusCode::BAD_REQUEST);
+            };
+
+            {
+                let mut state = shared.lock();
+                if !state.accounts.contains_key(destination) {
+                    return empty_response(id, StatusCode::NOT_FOUND);
+                }
+                for message in messages {
+                    state.store_envelope(destination, message);
+                }
+            }
+            shared.envelopes_changed.send_replace(());
+
+            ResponseProto {
+                id: Some(id),
+                status: Some(StatusCode::OK.as_u16().into()),
+                message: Some("OK".to_owned()),
+                headers: vec!["content-type: application/json".to_owned()],
+                body: Some(br#"{"needsSync":false}"#.to_vec()),
+            }
+        }
+        _ if verb == "GET" && path == "/v1/keepalive" => empty_response(id, StatusCode::OK),
+        _ => empty_response(id, StatusCode::NOT_FOUND),
+    }
+}
+
+fn send_request(outgoing: &mpsc::UnboundedSender<MessageProto>, request: RequestProto) {
+    let _ignore_closed = outgoing.send(MessageProto {
+        r#type: Some(ChatMessageType::Request.into()),
+        request: Some(request),
+        response: None,
+    });
+}
+
+fn empty_response(id: u64, status: StatusCode) -> ResponseProto {
+    ResponseProto {
+        id: Some(id),
+        status: Some(status.as_u16().into()),
+        message: status.canonical_reason().map(ToOwned::to_owned),
+        headers: vec![],
+        body: None,
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("after epoch")
+        .as_millis()
+        .try_into()
+        .expect("fits in u64")
+}
+
+/// Waits until `condition` holds, polling the server state.
+///
+/// Acknowledgements are processed asynchronously, so tests that check
+/// [`FakeChatServer::pending_envelopes`] after acking need to wait.
+pub async fn eventually(mut condition: impl FnMut() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition became true")
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+
+    use assert_matches::assert_matches;
+    use libsignal_core::ServiceId;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::auth::Auth;
+    use crate::chat::api::messages::{
+        send_message, EnvelopeType, OutgoingDeviceMessage, SendOptions,
+    };
+    use crate::chat::server_requests::{ServerEvent, ServerEventDispatcher};
+    use crate::chat::{ReceiveStories, Request};
+
+    const ALICE: &str = "9d0652a3-dcc3-4d11-975f-74d61598733f";
+    const BOB: &str = "e5f5ad4b-3aa7-4bd1-8a3b-9a3c4ab6cf0c";
+    const PASSWORD: &str = "hunter2";
+
+    fn headers_for(username: &str) -> AuthenticatedChatHeaders {
+        AuthenticatedChatHeaders {
+            auth: Auth {
+                username: username.to_owned(),
+                password: PASSWORD.to_owned(),
+            },
+            receive_stories: ReceiveStories::from(false),
+        }
+    }
+
+    fn event_listener() -> (ws2::EventListener, mpsc::UnboundedReceiver<ServerEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let listener = ServerEventDispatcher::new().into_listener(move |event| {
+            let _ = tx.send(event);
+        });
+        (listener, rx)
+    }
+
+    /// Asserts that `future` is still pending after `duration`.
+    async fn assert_pending_for<T>(duration: Duration, future: impl Future<Output = T>) {
+        assert!(tokio::time::timeout(duration, future).await.is_err());
+    }
+
+    fn keepalive() -> Request {
+        Request {
+            method: http::Method::GET,
+            body: None,
+            headers: Default::default(),
+            path: http::uri::PathAndQuery::from_static("/v1/keepalive"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_stored_envelopes_until_acked() {
+        let server = FakeChatServer::start();
+        server.add_account(ALICE, PASSWORD);
+        server.enqueue_envelope(ALICE, b"hello".to_vec());
+
+        let (listener, mut events) = event_listener();
+        let _chat = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+
+        let send_ack = assert_matches!(
+            events.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, send_ack, .. }) if envelope == b"hello" => send_ack
+        );
+        assert_matches!(events.recv().await, Some(ServerEvent::QueueEmpty));
+        assert_eq!(server.pending_envelopes(ALICE), [b"hello".to_vec()]);
+
+        send_ack(StatusCode::OK).expect("can ack");
+        eventually(|| server.pending_envelopes(ALICE).is_empty()).await;
+    }
+
+    #[tokio::test]
+    async fn unacked_envelopes_are_redelivered() {
+        let server = FakeChatServer::start();
+        server.add_account(ALICE, PASSWORD);
+        server.enqueue_envelope(ALICE, b"hello".to_vec());
+
+        let (listener, mut events) = event_listener();
+        let chat = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+        assert_matches!(
+            events.recv().await,
+            Some(ServerEvent::IncomingMessage { .. })
+        );
+        chat.disconect().await;
+
+        let (listener, mut events) = event_listener();
+        let _chat = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+        assert_matches!(
+            events.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, .. }) if envelope == b"hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_envelopes_are_redelivered_only_after_reconnecting() {
+        let server = FakeChatServer::start();
+        server.add_account(ALICE, PASSWORD);
+        server.enqueue_envelope(ALICE, b"first".to_vec());
+
+        let (listener, mut events) = event_listener();
+        let chat = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+        let send_ack = assert_matches!(
+            events.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, send_ack, .. }) if envelope == b"first" => send_ack
+        );
+        assert_matches!(events.recv().await, Some(ServerEvent::QueueEmpty));
+        send_ack(StatusCode::INTERNAL_SERVER_ERROR).expect("can nack");
+
+        // A new envelope triggers another delivery pass, which must skip the
+        // rejected one.
+        server.enqueue_envelope(ALICE, b"second".to_vec());
+        assert_matches!(
+            events.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, .. }) if envelope == b"second"
+        );
+        assert_pending_for(Duration::from_millis(100), events.recv()).await;
+        assert_eq!(
+            server.pending_envelopes(ALICE),
+            [b"first".to_vec(), b"second".to_vec()]
+        );
+        chat.disconect().await;
+
+        let (listener, mut events) = event_listener();
+        let _chat = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+        for expected in [b"first".as_slice(), b"second"] {
+            assert_matches!(
+                events.recv().await,
+                Some(ServerEvent::IncomingMessage { envelope, .. }) if envelope == expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn sent_messages_reach_connected_recipient() {
+        let server = FakeChatServer::start();
+        server.add_account(ALICE, PASSWORD);
+        server.add_account(BOB, PASSWORD);
+
+        let (listener, mut bob_events) = event_listener();
+        let _bob = server
+            .connect(Some(headers_for(BOB)), listener)
+            .await
+            .expect("can connect");
+        assert_matches!(bob_events.recv().await, Some(ServerEvent::QueueEmpty));
+
+        let (listener, _alice_events) = event_listener();
+        let alice = server
+            .connect(Some(headers_for(ALICE)), listener)
+            .await
+            .expect("can connect");
+        let response = send_message(
+            &alice,
+            ServiceId::parse_from_service_id_string(BOB).expect("valid service ID"),
+            &[OutgoingDeviceMessage {
+                envelope_type: EnvelopeType::Ciphertext,
+                device_id: 1_u32.into(),
+                registration_id: 1001,
+                content: vec![1, 2, 3],
+            }],
+            SendOptions {
+                timestamp: libsignal_protocol::Timestamp::from_epoch_millis(1700000000000),
+                online: false,
+                urgent: true,
+            },
+            None,
+            Duration::from_secs(5),
+        )
+        .await
+        .expect("sent");
+        assert!(!response.needs_sync);
+
+        assert_matches!(
+            bob_events.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, .. }) if envelope == [1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_rejected() {
+        let server = FakeChatServer::start();
+        server.add_account(ALICE, "something else");
+
+        let (listener, _events) = event_listener();
+        let result = server.connect(Some(headers_for(ALICE)), listener).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn scripted_handshake_rejections() {
+        let server = FakeChatServer::start();
+        server.fail_next_connection(ConnectionFailure::RejectHandshake {
+            status: StatusCode::from_u16(499).expect("valid"),
+            retry_after_seconds: None,
+        });
+        server.fail_next_connection(ConnectionFailure::RejectHandshake {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after_seconds: Some(20),
+        });
+
+        let (listener, _events) = event_listener();
+        assert_matches!(
+            server.connect(None, listener).await.err(),
+            Some(ChatServiceError::AppExpired)
+        );
+        let (listener, _events) = event_listener();
+        assert_matches!(
+            server.connect(None, listener).await.err(),
+            Some(ChatServiceError::RetryLater {
+                retry_after_seconds: 20
+            })
+        );
+        let (listener, _events) = event_listener();
+        server.connect(None, listener).await.expect("can connect");
+    }
+
+    #[tokio::test]
+    async fn scripted_close_code_ends_connection() {
+        let server = FakeChatServer::start();
+        server.fail_next_connection(ConnectionFailure::CloseAfterHandshake(4401));
+
+        let (listener, mut events) = event_listener();
+        let _chat = server.connect(None, listener).await.expect("can connect");
+        assert_matches!(events.recv().await, Some(ServerEvent::Stopped(_)));
+    }
+
+    #[tokio::test]
+    async fn scripted_request_failures() {
+        let server = FakeChatServer::start();
+        server.fail_next_request(RequestFailure::RateLimited {
+            retry_after_seconds: 30,
+        });
+        server.fail_next_request(RequestFailure::Delay(Duration::from_secs(60)));
+
+        let (listener, _events) = event_listener();
+        let chat = server.connect(None, listener).await.expect("can connect");
+
+        let response = chat
+            .send(keepalive(), Duration::from_secs(5))
+            .await
+            .expect("got response");
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            response.headers.get("retry-after").map(|v| v.as_bytes()),
+            Some(&b"30"[..])
+        );
+
+        assert_pending_for(
+            Duration::from_millis(200),
+            chat.send(keepalive(), Duration::from_secs(5)),
+        )
+        .await;
+
+        let response = chat
+            .send(keepalive(), Duration::from_secs(5))
+            .await
+            .expect("got response");
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(server.received_requests().len(), 3);
+    }
+}
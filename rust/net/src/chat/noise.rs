@@ -3,9 +3,14 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+mod connector;
+#[cfg(test)]
+pub(crate) use connector::testutil;
+pub use connector::{NoiseRoute, NoiseTunnel, NoiseWebSocketConnector};
+
 mod encrypted_stream;
 mod waker;
-pub use encrypted_stream::{Authorization, EncryptedStream};
+pub use encrypted_stream::{handshake, Authorization, EncryptedStream};
 
 mod handshake;
 
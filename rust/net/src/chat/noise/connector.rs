@@ -0,0 +1,405 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::future::Future;
+use std::sync::Arc;
+
+use http::uri::PathAndQuery;
+use http::{header, HeaderMap};
+use libsignal_net_infra::noise::NoiseStream;
+use libsignal_net_infra::route::{Connector, HttpRouteFragment, WebSocketRouteFragment};
+use libsignal_net_infra::ws::{DeflateStream, WebSocketTransport};
+use libsignal_net_infra::AsyncDuplexStream;
+use tokio_tungstenite::WebSocketStream;
+
+use super::{handshake, Authorization};
+use crate::infra::ws::Stateless;
+
+/// Noise-encrypted tunnel carried in the binary messages of a websocket.
+pub type NoiseTunnel<S> = NoiseStream<WebSocketTransport<DeflateStream<S>>>;
+
+/// Where and how to establish a [`NoiseTunnel`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseRoute {
+    /// The path of the websocket that carries the tunnel.
+    pub endpoint: PathAndQuery,
+    /// How to identify the client, including the server static key to pin.
+    ///
+    /// [`Authorization::Authenticated`] uses the IK handshake pattern, and
+    /// [`Authorization::Anonymous`] uses NK.
+    pub authorization: Authorization,
+}
+
+impl NoiseRoute {
+    /// Produces the route fragment for the tunnel and a connector that
+    /// establishes the websocket described by `ws_fragment` inside it.
+    ///
+    /// The tunnel only carries over the `User-Agent` header from
+    /// `ws_fragment`.
+    pub fn into_tunnel(
+        self,
+        ws_fragment: WebSocketRouteFragment,
+    ) -> (WebSocketRouteFragment, NoiseWebSocketConnector) {
+        let Self {
+            endpoint,
+            authorization,
+        } = self;
+
+        let tunnel_fragment = WebSocketRouteFragment {
+            ws_config: ws_fragment.ws_config,
+            endpoint,
+            headers: HeaderMap::from_iter(
+                ws_fragment
+                    .headers
+                    .get(header::USER_AGENT)
+                    .map(|user_agent| (header::USER_AGENT, user_agent.clone())),
+            ),
+            // The tunnel carries encrypted data, which won't compress.
+            deflate: None,
+        };
+
+        (
+            tunnel_fragment,
+            NoiseWebSocketConnector::new(authorization, ws_fragment),
+        )
+    }
+}
+
+/// [`Connector`] that establishes a websocket inside a [`NoiseTunnel`].
+///
+/// The route's websocket fragment is used to connect the tunnel; its endpoint
+/// is normally a [`NoiseRoute::endpoint`]. Once the Noise handshake has
+/// succeeded, the websocket described by the connector's own fragment is
+/// established inside the tunnel on the same host. Headers for the inner
+/// websocket, including any credentials, are never sent outside the tunnel.
+pub struct NoiseWebSocketConnector {
+    authorization: Authorization,
+    inner: WebSocketRouteFragment,
+}
+
+impl NoiseWebSocketConnector {
+    pub fn new(authorization: Authorization, inner: WebSocketRouteFragment) -> Self {
+        Self {
+            authorization,
+            inner,
+        }
+    }
+}
+
+impl<Inner> Connector<(WebSocketRouteFragment, HttpRouteFragment), Inner>
+    for NoiseWebSocketConnector
+where
+    Inner: AsyncDuplexStream,
+{
+    type Connection = WebSocketStream<DeflateStream<NoiseTunnel<Inner>>>;
+
+    type Error = tungstenite::Error;
+
+    fn connect_over(
+        &self,
+        inner: Inner,
+        (tunnel_fragment, http_fragment): (WebSocketRouteFragment, HttpRouteFragment),
+        log_tag: Arc<str>,
+    ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+        let Self {
+            authorization,
+            inner: inner_fragment,
+        } = self;
+
+        async move {
+            let tunnel = Stateless
+                .connect_over(
+                    inner,
+                    (tunnel_fragment, http_fragment.clone()),
+                    log_tag.clone(),
+                )
+                .await?;
+
+            let stream = handshake(authorization, WebSocketTransport(tunnel))
+                .await
+                .map_err(|e| {
+                    log::info!("[{log_tag}] Noise handshake failed: {e}");
+                    tungstenite::Error::Io(e.into())
+                })?;
+
+            Stateless
+                .connect_over(stream, (inner_fragment.clone(), http_fragment), log_tag)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testutil {
+    use std::future::Future;
+
+    use bytes::Bytes;
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use libsignal_net_infra::noise::NoiseStream;
+    use libsignal_net_infra::ws::WebSocketTransport;
+    use libsignal_net_infra::AsyncDuplexStream;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    use crate::chat::noise::handshake::{IK_NOISE_PATTERN, NK_NOISE_PATTERN};
+
+    /// Generates a static keypair for either side of a handshake, returning
+    /// the private key and then the public key.
+    pub(crate) fn generate_keypair() -> ([u8; 32], [u8; 32]) {
+        let keypair = snow::Builder::new(NK_NOISE_PATTERN.parse().expect("valid"))
+            .generate_keypair()
+            .expect("can generate");
+        (
+            keypair.private.try_into().expect("correct length"),
+            keypair.public.try_into().expect("correct length"),
+        )
+    }
+
+    /// The requests a [`noise_echo_server`] received.
+    #[derive(Debug, Default)]
+    pub(crate) struct ReceivedRequests {
+        /// The request that established the tunnel.
+        pub(crate) tunnel: Option<http::Request<()>>,
+        /// The Noise handshake payload sent by the client.
+        pub(crate) handshake_payload: Option<Vec<u8>>,
+        /// The request for the websocket inside the tunnel.
+        pub(crate) inner: Option<http::Request<()>>,
+    }
+
+    /// Serves a single websocket inside a Noise tunnel over `stream`, echoing
+    /// back every message it receives.
+    ///
+    /// The server identifies itself with `server_private_key`. If
+    /// `client_public_key` is provided, the client must use the IK pattern with
+    /// the matching private key; otherwise it must use NK. The returned future
+    /// resolves once the inner websocket is closed, or as soon as any step
+    /// fails.
+    pub(crate) fn noise_echo_server(
+        stream: impl AsyncDuplexStream + 'static,
+        server_private_key: [u8; 32],
+        client_public_key: Option<[u8; 32]>,
+    ) -> impl Future<Output = ReceivedRequests> + Send + 'static {
+        async move {
+            let mut received = ReceivedRequests::default();
+
+            let Ok(tunnel) = tokio_tungstenite::accept_hdr_async(
+                stream,
+                |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+                    received.tunnel = Some(copy_request(request));
+                    Ok(response)
+                },
+            )
+            .await
+            else {
+                return received;
+            };
+            let mut transport = WebSocketTransport(tunnel);
+
+            let builder = match &client_public_key {
+                Some(client_public_key) => {
+                    snow::Builder::new(IK_NOISE_PATTERN.parse().expect("valid"))
+                        .local_private_key(&server_private_key)
+                        .remote_public_key(client_public_key)
+                }
+                None => snow::Builder::new(NK_NOISE_PATTERN.parse().expect("valid"))
+                    .local_private_key(&server_private_key),
+            };
+            let mut handshake = builder.build_responder().expect("valid keys");
+
+            let Some(Ok(first)) = transport.next().await else {
+                return received;
+            };
+            let mut payload = vec![0; first.len()];
+            let Ok(payload_len) = handshake.read_message(&first, &mut payload) else {
+                return received;
+            };
+            payload.truncate(payload_len);
+            received.handshake_payload = Some(payload);
+
+            let mut reply = [0; 128];
+            let reply_len = handshake.write_message(&[], &mut reply).expect("can write");
+            if transport
+                .send(Bytes::copy_from_slice(&reply[..reply_len]))
+                .await
+                .is_err()
+            {
+                return received;
+            }
+
+            let handshake_hash = handshake.get_handshake_hash().to_vec();
+            let stream = NoiseStream::new(
+                transport,
+                handshake.into_transport_mode().expect("finished"),
+                handshake_hash,
+            );
+
+            let Ok(websocket) = tokio_tungstenite::accept_hdr_async(
+                stream,
+                |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+                    received.inner = Some(copy_request(request));
+                    Ok(response)
+                },
+            )
+            .await
+            else {
+                return received;
+            };
+
+            echo_messages(websocket).await;
+            received
+        }
+    }
+
+    async fn echo_messages<S: AsyncDuplexStream>(mut websocket: WebSocketStream<S>) {
+        while let Some(Ok(message)) = websocket.next().await {
+            if message.is_close() {
+                break;
+            }
+            if (message.is_binary() || message.is_text()) && websocket.send(message).await.is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    fn copy_request(request: &Request) -> http::Request<()> {
+        let mut copy = http::Request::new(());
+        *copy.uri_mut() = request.uri().clone();
+        *copy.headers_mut() = request.headers().clone();
+        copy
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use hex_literal::hex;
+    use http::HeaderValue;
+    use libsignal_core::Aci;
+    use test_case::test_case;
+    use tungstenite::Message;
+
+    use super::testutil::{generate_keypair, noise_echo_server};
+    use super::*;
+
+    const ACI: Aci = Aci::from_uuid_bytes(hex!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
+    const DEVICE_ID: u8 = 42;
+    const TUNNEL_PATH: &str = "/v1/noise";
+    const CHAT_PATH: &str = "/v1/websocket/";
+
+    /// Returns the fragments for the tunnel and a connector for the chat
+    /// websocket inside it.
+    fn tunnel_to_chat(
+        authorization: Authorization,
+    ) -> (
+        (WebSocketRouteFragment, HttpRouteFragment),
+        NoiseWebSocketConnector,
+    ) {
+        let route = NoiseRoute {
+            endpoint: PathAndQuery::from_static(TUNNEL_PATH),
+            authorization,
+        };
+        let (tunnel_fragment, connector) = route.into_tunnel(WebSocketRouteFragment {
+            ws_config: Default::default(),
+            endpoint: PathAndQuery::from_static(CHAT_PATH),
+            headers: HeaderMap::from_iter([
+                (header::USER_AGENT, HeaderValue::from_static("test agent")),
+                (header::AUTHORIZATION, HeaderValue::from_static("secret")),
+            ]),
+            deflate: Some(Default::default()),
+        });
+        let http_fragment = HttpRouteFragment {
+            host_header: "chat.test.signal.org".into(),
+            path_prefix: "".into(),
+            front_name: None,
+        };
+        ((tunnel_fragment, http_fragment), connector)
+    }
+
+    #[test_case(false; "anonymous")]
+    #[test_case(true; "authenticated")]
+    #[tokio::test]
+    async fn connects_through_tunnel(authenticated: bool) {
+        let (server_private_key, server_public_key) = generate_keypair();
+        let (client_private_key, client_public_key) = generate_keypair();
+
+        let authorization = if authenticated {
+            Authorization::Authenticated {
+                aci: ACI,
+                device_id: DEVICE_ID,
+                server_public_key,
+                client_private_key,
+            }
+        } else {
+            Authorization::Anonymous { server_public_key }
+        };
+
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(noise_echo_server(
+            server,
+            server_private_key,
+            authenticated.then_some(client_public_key),
+        ));
+
+        let (fragments, connector) = tunnel_to_chat(authorization);
+        let mut websocket = connector
+            .connect_over(client, fragments, "test".into())
+            .await
+            .expect("can connect");
+
+        websocket
+            .send(Message::Binary(b"hello"[..].into()))
+            .await
+            .expect("can send");
+        assert_matches!(
+            websocket.next().await,
+            Some(Ok(Message::Binary(message))) if message == b"hello"
+        );
+        websocket.close(None).await.expect("can close");
+
+        let received = server.await.expect("server exited cleanly");
+
+        let tunnel = received.tunnel.expect("tunnel requested");
+        assert_eq!(tunnel.uri().path(), TUNNEL_PATH);
+        assert_eq!(
+            tunnel.headers().get(header::USER_AGENT),
+            Some(&HeaderValue::from_static("test agent"))
+        );
+        assert_eq!(tunnel.headers().get(header::AUTHORIZATION), None);
+
+        let handshake_payload = received.handshake_payload.expect("handshake completed");
+        assert_eq!(handshake_payload.is_empty(), !authenticated);
+
+        let inner = received.inner.expect("websocket requested in tunnel");
+        assert_eq!(inner.uri().path(), CHAT_PATH);
+        assert_eq!(
+            inner.headers().get(header::AUTHORIZATION),
+            Some(&HeaderValue::from_static("secret"))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unpinned_server_key() {
+        let (server_private_key, _server_public_key) = generate_keypair();
+        let (_other_private_key, other_public_key) = generate_keypair();
+
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(noise_echo_server(server, server_private_key, None));
+
+        let (fragments, connector) = tunnel_to_chat(Authorization::Anonymous {
+            server_public_key: other_public_key,
+        });
+        let result = connector
+            .connect_over(client, fragments, "test".into())
+            .await;
+        assert_matches!(result, Err(tungstenite::Error::Io(_)));
+
+        let received = server.await.expect("server exited cleanly");
+        assert!(received.tunnel.is_some());
+        assert_eq!(received.handshake_payload, None);
+        assert!(received.inner.is_none());
+    }
+}
@@ -239,6 +239,18 @@ impl<S: Transport + Unpin> AsyncWrite for EncryptedStream<S> {
     }
 }
 
+/// Performs the Noise handshake identified by `authorization` over `transport`.
+///
+/// Unlike [`EncryptedStream`], which waits for the first read or write, this
+/// completes the handshake before returning, so a server that doesn't hold the
+/// pinned static key is detected right away.
+pub async fn handshake<S: Transport + Unpin>(
+    authorization: &Authorization,
+    transport: S,
+) -> Result<NoiseStream<S>, SendError> {
+    start_handshake(authorization, &mut Some(transport))?.await
+}
+
 fn start_handshake<S>(
     auth: &Authorization,
     transport: &mut Option<S>,
@@ -0,0 +1,810 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A persistent queue of chat requests that survives disconnects.
+//!
+//! Requests handed to an [`Outbox`] are written to an [`OutboxStore`] before
+//! anything is sent, then delivered in order whenever the chat connection is
+//! up. Each request carries an `Idempotency-Key` header that stays the same
+//! across retries, so the server can recognize a request it already handled
+//! if a response was lost along with the connection.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, watch, Notify};
+
+use crate::chat::api::ChatSender;
+use crate::chat::supervisor::{ChatSupervisor, ConnectionState};
+use crate::chat::{self, ChatServiceError};
+
+const IDEMPOTENCY_KEY_HEADER_NAME: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// A queued request in the form it is persisted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredRequest {
+    /// Assigned by the outbox; increases with each enqueued request.
+    pub id: u64,
+    pub method: String,
+    pub path: String,
+    /// Includes the idempotency key.
+    ///
+    /// Values are kept as bytes, since header values don't have to be UTF-8.
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<Vec<u8>>,
+    /// The request fails with [`OutboxError::DeadlineExceeded`] if it hasn't
+    /// been delivered by this time.
+    pub deadline: SystemTime,
+}
+
+impl StoredRequest {
+    fn to_request(&self) -> Result<chat::Request, OutboxError> {
+        let Self {
+            id: _,
+            method,
+            path,
+            headers,
+            body,
+            deadline: _,
+        } = self;
+        let headers = headers
+            .iter()
+            .map(|(name, value)| {
+                Some((
+                    HeaderName::try_from(name.as_str()).ok()?,
+                    HeaderValue::from_bytes(value).ok()?,
+                ))
+            })
+            .collect::<Option<HeaderMap>>()
+            .ok_or(OutboxError::InvalidStoredRequest)?;
+        Ok(chat::Request {
+            method: method
+                .parse()
+                .map_err(|_| OutboxError::InvalidStoredRequest)?,
+            body: body.clone().map(Vec::into_boxed_slice),
+            headers,
+            path: PathAndQuery::try_from(path.as_str())
+                .map_err(|_| OutboxError::InvalidStoredRequest)?,
+        })
+    }
+}
+
+/// outbox storage failed: {0}
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub struct OutboxStoreError(pub &'static str);
+
+/// Where an [`Outbox`] keeps requests until they are delivered.
+///
+/// Each method should take effect durably before it returns.
+pub trait OutboxStore: Send + Sync {
+    /// Returns every request that has been inserted but not removed.
+    fn load(&self) -> Result<Vec<StoredRequest>, OutboxStoreError>;
+    fn insert(&self, request: &StoredRequest) -> Result<(), OutboxStoreError>;
+    fn remove(&self, id: u64) -> Result<(), OutboxStoreError>;
+}
+
+/// An [`OutboxStore`] that doesn't persist anything.
+///
+/// Useful for tests, or for getting ordered replay without persistence.
+#[derive(Debug, Default)]
+pub struct InMemoryOutboxStore(Mutex<Vec<StoredRequest>>);
+
+impl OutboxStore for InMemoryOutboxStore {
+    fn load(&self) -> Result<Vec<StoredRequest>, OutboxStoreError> {
+        Ok(self.0.lock().expect("not poisoned").clone())
+    }
+
+    fn insert(&self, request: &StoredRequest) -> Result<(), OutboxStoreError> {
+        self.0.lock().expect("not poisoned").push(request.clone());
+        Ok(())
+    }
+
+    fn remove(&self, id: u64) -> Result<(), OutboxStoreError> {
+        self.0
+            .lock()
+            .expect("not poisoned")
+            .retain(|request| request.id != id);
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum OutboxError {
+    /// request was not delivered before its deadline
+    DeadlineExceeded,
+    /// request failed: {0}
+    Chat(ChatServiceError),
+    /// stored request could not be decoded
+    InvalidStoredRequest,
+    /// outbox was dropped before the request completed
+    Abandoned,
+}
+
+/// Timing parameters for an [`Outbox`].
+#[derive(Clone, Debug)]
+pub struct OutboxConfig {
+    /// The longest to wait for any one response.
+    pub request_timeout: Duration,
+    /// How long to wait before retrying after a failure that didn't end the
+    /// connection.
+    pub retry_delay: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            retry_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The eventual result of a request added to an [`Outbox`].
+#[derive(Debug)]
+pub struct OutboxReceipt {
+    pub id: u64,
+    result: oneshot::Receiver<Result<chat::Response, OutboxError>>,
+}
+
+impl OutboxReceipt {
+    /// Waits for the request to be delivered or to fail.
+    ///
+    /// Any response from the server counts as delivery, whatever its status.
+    pub async fn result(self) -> Result<chat::Response, OutboxError> {
+        self.result.await.unwrap_or(Err(OutboxError::Abandoned))
+    }
+}
+
+struct Pending {
+    stored: StoredRequest,
+    completion: oneshot::Sender<Result<chat::Response, OutboxError>>,
+}
+
+struct OutboxState {
+    queue: VecDeque<Pending>,
+    next_id: u64,
+}
+
+/// A persistent, ordered queue of chat requests.
+///
+/// Requests are added with [`enqueue`](Self::enqueue) and delivered by
+/// [`run`](Self::run), which must be polled for anything to be sent.
+pub struct Outbox<S> {
+    store: S,
+    config: OutboxConfig,
+    state: Mutex<OutboxState>,
+    enqueued: Notify,
+}
+
+impl<S: OutboxStore> Outbox<S> {
+    /// Creates an outbox, restoring any requests left in `store`.
+    ///
+    /// Returns receipts for the restored requests, in delivery order.
+    pub fn open(
+        store: S,
+        config: OutboxConfig,
+    ) -> Result<(Self, Vec<OutboxReceipt>), OutboxStoreError> {
+        let mut stored = store.load()?;
+        stored.sort_by_key(|request| request.id);
+        let next_id = stored.last().map_or(0, |request| request.id + 1);
+
+        let mut receipts = Vec::with_capacity(stored.len());
+        let queue = stored
+            .into_iter()
+            .map(|stored| {
+                let (completion, result) = oneshot::channel();
+                receipts.push(OutboxReceipt {
+                    id: stored.id,
+                    result,
+                });
+                Pending { stored, completion }
+            })
+            .collect();
+
+        let outbox = Self {
+            store,
+            config,
+            state: Mutex::new(OutboxState { queue, next_id }),
+            enqueued: Notify::new(),
+        };
+        Ok((outbox, receipts))
+    }
+
+    /// Persists `request` and queues it for delivery.
+    ///
+    /// An `Idempotency-Key` header is added unless the request already has
+    /// one.
+    pub fn enqueue(
+        &self,
+        request: chat::Request,
+        deadline: SystemTime,
+    ) -> Result<OutboxReceipt, OutboxStoreError> {
+        let chat::Request {
+            method,
+            body,
+            mut headers,
+            path,
+        } = request;
+        if !headers.contains_key(IDEMPOTENCY_KEY_HEADER_NAME) {
+            headers.insert(
+                IDEMPOTENCY_KEY_HEADER_NAME,
+                HeaderValue::try_from(hex::encode(rand::random::<[u8; 16]>()))
+                    .expect("hex is a valid header"),
+            );
+        }
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.as_str().to_owned(), value.as_bytes().to_vec()))
+            .collect();
+
+        let id = {
+            let mut state = self.state.lock().expect("not poisoned");
+            let id = state.next_id;
+            state.next_id += 1;
+            id
+        };
+        let stored = StoredRequest {
+            id,
+            method: method.as_str().to_owned(),
+            path: path.as_str().to_owned(),
+            headers,
+            body: body.map(Vec::from),
+            deadline,
+        };
+        // Persist before queueing, so nothing is sent that a restart would
+        // forget. The state lock isn't held during the write, so a slow store
+        // holds up only this call, not delivery of requests already queued. If
+        // the write fails, nothing is queued and the ID is just skipped.
+        self.store.insert(&stored)?;
+
+        let (completion, result) = oneshot::channel();
+        {
+            let mut state = self.state.lock().expect("not poisoned");
+            // A concurrent enqueue with a later ID may have gotten here first.
+            let position = state
+                .queue
+                .partition_point(|pending| pending.stored.id < id);
+            state.queue.insert(position, Pending { stored, completion });
+        }
+        self.enqueued.notify_one();
+        Ok(OutboxReceipt { id, result })
+    }
+
+    /// The number of requests waiting to be delivered.
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("not poisoned").queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Delivers queued requests through `supervisor` whenever it is
+    /// connected.
+    ///
+    /// Returns when the supervisor stops. Undelivered requests stay in the
+    /// store for the next time the outbox is opened.
+    pub async fn run_with_supervisor(&self, supervisor: &ChatSupervisor) {
+        self.run(supervisor, supervisor.state()).await
+    }
+
+    /// Delivers queued requests through `chat` while `connection` reports
+    /// [`ConnectionState::Connected`].
+    ///
+    /// Returns when `connection` reports [`ConnectionState::Stopped`] or its
+    /// sender is dropped.
+    pub async fn run(
+        &self,
+        chat: &(impl ChatSender + ?Sized),
+        mut connection: watch::Receiver<ConnectionState>,
+    ) {
+        loop {
+            self.expire_overdue();
+            let connected = match &*connection.borrow_and_update() {
+                ConnectionState::Connected(_) => true,
+                ConnectionState::Stopped(_) => return,
+                _ => false,
+            };
+
+            let wait_for_change = if connected {
+                match self.deliver_front(chat).await {
+                    Delivery::Delivered => continue,
+                    Delivery::Empty => None,
+                    Delivery::Retry => Some(self.config.retry_delay),
+                }
+            } else {
+                None
+            };
+
+            // Wait for something to change: the connection, the queue, or
+            // the next deadline (or retry).
+            let wake_at = self
+                .next_deadline_delay()
+                .into_iter()
+                .chain(wait_for_change)
+                .min();
+            tokio::select! {
+                changed = connection.changed() => if changed.is_err() {
+                    return;
+                },
+                () = self.enqueued.notified() => {},
+                () = sleep_or_forever(wake_at) => {},
+            }
+        }
+    }
+
+    /// Sends the request at the front of the queue and completes it unless
+    /// it should be retried.
+    async fn deliver_front(&self, chat: &(impl ChatSender + ?Sized)) -> Delivery {
+        let Some((stored, timeout)) = ({
+            let state = self.state.lock().expect("not poisoned");
+            state.queue.front().map(|pending| {
+                let remaining = pending
+                    .stored
+                    .deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                (
+                    pending.stored.clone(),
+                    remaining.min(self.config.request_timeout),
+                )
+            })
+        }) else {
+            return Delivery::Empty;
+        };
+
+        let request = match stored.to_request() {
+            Ok(request) => request,
+            Err(e) => {
+                self.complete(stored.id, Err(e));
+                return Delivery::Delivered;
+            }
+        };
+        match chat.send_request(request, timeout).await {
+            Ok(response) => {
+                self.complete(stored.id, Ok(response));
+                Delivery::Delivered
+            }
+            Err(e) if is_retryable(&e) => {
+                log::info!("outbox request {} will be retried: {e}", stored.id);
+                Delivery::Retry
+            }
+            Err(e) => {
+                self.complete(stored.id, Err(OutboxError::Chat(e)));
+                Delivery::Delivered
+            }
+        }
+    }
+
+    /// Removes request `id` from the queue and reports its result.
+    ///
+    /// The request is usually at the front, but an enqueue that was still
+    /// writing to the store may have put an earlier one ahead of it.
+    fn complete(&self, id: u64, result: Result<chat::Response, OutboxError>) {
+        let mut state = self.state.lock().expect("not poisoned");
+        let Some(position) = state
+            .queue
+            .iter()
+            .position(|pending| pending.stored.id == id)
+        else {
+            // Already expired while the request was in flight.
+            return;
+        };
+        let pending = state.queue.remove(position).expect("just found");
+        drop(state);
+        self.finish(pending, result);
+    }
+
+    fn finish(&self, pending: Pending, result: Result<chat::Response, OutboxError>) {
+        if let Err(e) = self.store.remove(pending.stored.id) {
+            log::warn!(
+                "failed to remove outbox request {} from storage: {e}",
+                pending.stored.id
+            );
+        }
+        // The caller may have stopped waiting.
+        let _ = pending.completion.send(result);
+    }
+
+    /// Fails every request whose deadline has passed.
+    fn expire_overdue(&self) {
+        let now = SystemTime::now();
+        let expired: Vec<_> = {
+            let mut state = self.state.lock().expect("not poisoned");
+            let (expired, remaining) = std::mem::take(&mut state.queue)
+                .into_iter()
+                .partition(|pending| pending.stored.deadline <= now);
+            state.queue = remaining;
+            expired
+        };
+        for pending in expired {
+            log::info!("outbox request {} expired", pending.stored.id);
+            self.finish(pending, Err(OutboxError::DeadlineExceeded));
+        }
+    }
+
+    fn next_deadline_delay(&self) -> Option<Duration> {
+        let now = SystemTime::now();
+        self.state
+            .lock()
+            .expect("not poisoned")
+            .queue
+            .iter()
+            .map(|pending| {
+                pending
+                    .stored
+                    .deadline
+                    .duration_since(now)
+                    .unwrap_or_default()
+            })
+            .min()
+    }
+}
+
+enum Delivery {
+    Delivered,
+    Empty,
+    Retry,
+}
+
+/// Whether a request that failed this way may not have reached the server.
+fn is_retryable(error: &ChatServiceError) -> bool {
+    match error {
+        ChatServiceError::WebSocket(_)
+        | ChatServiceError::TimeoutEstablishingConnection
+        | ChatServiceError::RequestSendTimedOut
+        | ChatServiceError::AllConnectionRoutesFailed
+        | ChatServiceError::CertificatePinMismatch
+        | ChatServiceError::Disconnected
+        | ChatServiceError::RetryLater { .. } => true,
+        ChatServiceError::AppExpired
+        | ChatServiceError::DeviceDeregistered
+        | ChatServiceError::UnexpectedFrameReceived
+        | ChatServiceError::ServerRequestMissingId
+        | ChatServiceError::IncomingDataInvalid
+        | ChatServiceError::RequestHasInvalidHeader
+        | ChatServiceError::InvalidConnectionConfiguration => false,
+    }
+}
+
+async fn sleep_or_forever(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures_util::future::BoxFuture;
+    use futures_util::FutureExt as _;
+    use http::StatusCode;
+    use libsignal_net_infra::{IpType, TransportInfo};
+
+    use super::*;
+    use crate::chat::supervisor::StopReason;
+    use crate::chat::ConnectionInfo;
+    use crate::connect_state::RouteInfo;
+
+    const CONFIG: OutboxConfig = OutboxConfig {
+        request_timeout: Duration::from_secs(5),
+        retry_delay: Duration::from_millis(10),
+    };
+
+    /// Answers requests from a script, recording each one.
+    #[derive(Default)]
+    struct ScriptedSender {
+        results: Mutex<VecDeque<Result<chat::Response, ChatServiceError>>>,
+        requests: Mutex<Vec<chat::Request>>,
+    }
+
+    impl ScriptedSender {
+        fn new(
+            results: impl IntoIterator<Item = Result<chat::Response, ChatServiceError>>,
+        ) -> Self {
+            Self {
+                results: Mutex::new(results.into_iter().collect()),
+                requests: Default::default(),
+            }
+        }
+
+        fn idempotency_keys(&self) -> Vec<HeaderValue> {
+            self.requests
+                .lock()
+                .expect("not poisoned")
+                .iter()
+                .map(|request| request.headers[IDEMPOTENCY_KEY_HEADER_NAME].clone())
+                .collect()
+        }
+    }
+
+    impl ChatSender for ScriptedSender {
+        fn send_request(
+            &self,
+            request: chat::Request,
+            _timeout: Duration,
+        ) -> BoxFuture<'_, Result<chat::Response, ChatServiceError>> {
+            self.requests.lock().expect("not poisoned").push(request);
+            let result = self
+                .results
+                .lock()
+                .expect("not poisoned")
+                .pop_front()
+                .expect("script has another entry");
+            std::future::ready(result).boxed()
+        }
+    }
+
+    fn ok_response() -> Result<chat::Response, ChatServiceError> {
+        Ok(chat::Response {
+            status: StatusCode::OK,
+            message: None,
+            body: None,
+            headers: Default::default(),
+        })
+    }
+
+    fn connected() -> ConnectionState {
+        ConnectionState::Connected(ConnectionInfo {
+            route_info: RouteInfo::fake(),
+            transport_info: TransportInfo {
+                ip_version: IpType::V4,
+                local_port: 0,
+            },
+        })
+    }
+
+    fn request(path: &'static str) -> chat::Request {
+        chat::Request {
+            method: http::Method::PUT,
+            body: Some(b"body".as_slice().into()),
+            headers: Default::default(),
+            path: PathAndQuery::from_static(path),
+        }
+    }
+
+    fn far_deadline() -> SystemTime {
+        SystemTime::now() + Duration::from_secs(3600)
+    }
+
+    /// An [`InMemoryOutboxStore`] whose inserts can be made to fail.
+    #[derive(Default)]
+    struct FlakyStore {
+        inner: InMemoryOutboxStore,
+        fail_inserts: std::sync::atomic::AtomicBool,
+    }
+
+    impl OutboxStore for FlakyStore {
+        fn load(&self) -> Result<Vec<StoredRequest>, OutboxStoreError> {
+            self.inner.load()
+        }
+
+        fn insert(&self, request: &StoredRequest) -> Result<(), OutboxStoreError> {
+            if self.fail_inserts.load(std::sync::atomic::Ordering::Relaxed) {
+                return Err(OutboxStoreError("disk full"));
+            }
+            self.inner.insert(request)
+        }
+
+        fn remove(&self, id: u64) -> Result<(), OutboxStoreError> {
+            self.inner.remove(id)
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_in_order() {
+        let sender = ScriptedSender::new([ok_response(), ok_response()]);
+        let (outbox, restored) =
+            Outbox::open(InMemoryOutboxStore::default(), CONFIG).expect("can open");
+        assert!(restored.is_empty());
+
+        let first = outbox
+            .enqueue(request("/v1/first"), far_deadline())
+            .expect("can enqueue");
+        let second = outbox
+            .enqueue(request("/v1/second"), far_deadline())
+            .expect("can enqueue");
+        assert_eq!(outbox.store.load().expect("can load").len(), 2);
+
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(first.result().await, Ok(_));
+            assert_matches!(second.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+
+        let paths: Vec<_> = sender
+            .requests
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|request| request.path.to_string())
+            .collect();
+        assert_eq!(paths, ["/v1/first", "/v1/second"]);
+        let keys = sender.idempotency_keys();
+        assert_ne!(keys[0], keys[1]);
+        assert!(outbox.is_empty());
+        assert!(outbox.store.load().expect("can load").is_empty());
+    }
+
+    #[tokio::test]
+    async fn retries_with_the_same_idempotency_key() {
+        let sender = ScriptedSender::new([
+            Err(ChatServiceError::Disconnected),
+            Err(ChatServiceError::RequestSendTimedOut),
+            ok_response(),
+        ]);
+        let (outbox, _) = Outbox::open(InMemoryOutboxStore::default(), CONFIG).expect("can open");
+        let receipt = outbox
+            .enqueue(request("/v1/retry"), far_deadline())
+            .expect("can enqueue");
+
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(receipt.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+
+        let keys = sender.idempotency_keys();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.iter().all(|key| *key == keys[0]));
+    }
+
+    #[tokio::test]
+    async fn waits_for_connection() {
+        let sender = ScriptedSender::new([ok_response()]);
+        let (outbox, _) = Outbox::open(InMemoryOutboxStore::default(), CONFIG).expect("can open");
+
+        let (state_tx, state_rx) = watch::channel(ConnectionState::Connecting);
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            let receipt = outbox
+                .enqueue(request("/v1/later"), far_deadline())
+                .expect("can enqueue");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(sender.requests.lock().expect("not poisoned").is_empty());
+
+            state_tx.send_replace(connected());
+            assert_matches!(receipt.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+    }
+
+    #[tokio::test]
+    async fn fails_expired_and_rejected_requests() {
+        let sender = ScriptedSender::new([Err(ChatServiceError::RequestHasInvalidHeader)]);
+        let (outbox, _) = Outbox::open(InMemoryOutboxStore::default(), CONFIG).expect("can open");
+        let expired = outbox
+            .enqueue(request("/v1/expired"), SystemTime::now())
+            .expect("can enqueue");
+        let rejected = outbox
+            .enqueue(request("/v1/rejected"), far_deadline())
+            .expect("can enqueue");
+
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(expired.result().await, Err(OutboxError::DeadlineExceeded));
+            assert_matches!(
+                rejected.result().await,
+                Err(OutboxError::Chat(ChatServiceError::RequestHasInvalidHeader))
+            );
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+        assert_eq!(sender.requests.lock().expect("not poisoned").len(), 1);
+        assert!(outbox.store.load().expect("can load").is_empty());
+    }
+
+    #[tokio::test]
+    async fn store_failure_queues_nothing() {
+        let store = FlakyStore::default();
+        store
+            .fail_inserts
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        let (outbox, _) = Outbox::open(store, CONFIG).expect("can open");
+
+        assert_matches!(
+            outbox.enqueue(request("/v1/unstored"), far_deadline()),
+            Err(OutboxStoreError("disk full"))
+        );
+        assert!(outbox.is_empty());
+
+        outbox
+            .store
+            .fail_inserts
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        let receipt = outbox
+            .enqueue(request("/v1/stored"), far_deadline())
+            .expect("can enqueue");
+        // The failed request's ID isn't reused.
+        assert_eq!(receipt.id, 1);
+
+        let sender = ScriptedSender::new([ok_response()]);
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(receipt.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+        let paths: Vec<_> = sender
+            .requests
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|request| request.path.to_string())
+            .collect();
+        assert_eq!(paths, ["/v1/stored"]);
+    }
+
+    #[tokio::test]
+    async fn restores_from_store() {
+        let store = InMemoryOutboxStore::default();
+        store
+            .insert(&StoredRequest {
+                id: 41,
+                method: "PUT".to_owned(),
+                path: "/v1/restored".to_owned(),
+                headers: vec![("idempotency-key".to_owned(), b"abc".to_vec())],
+                body: None,
+                deadline: far_deadline(),
+            })
+            .expect("can insert");
+
+        let (outbox, restored) = Outbox::open(store, CONFIG).expect("can open");
+        let [receipt] = <[_; 1]>::try_from(restored).expect("one restored request");
+        assert_eq!(receipt.id, 41);
+        let next = outbox
+            .enqueue(request("/v1/next"), far_deadline())
+            .expect("can enqueue");
+        assert_eq!(next.id, 42);
+
+        let sender = ScriptedSender::new([ok_response(), ok_response()]);
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(receipt.result().await, Ok(_));
+            assert_matches!(next.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+        assert_eq!(sender.idempotency_keys()[0], "abc");
+    }
+
+    #[tokio::test]
+    async fn keeps_non_utf8_header_values() {
+        let store = InMemoryOutboxStore::default();
+        let (outbox, _) = Outbox::open(store, CONFIG).expect("can open");
+        let value = HeaderValue::from_bytes(b"caf\xe9").expect("valid header");
+        let receipt = outbox
+            .enqueue(
+                chat::Request {
+                    headers: HeaderMap::from_iter([(
+                        HeaderName::from_static("x-test"),
+                        value.clone(),
+                    )]),
+                    ..request("/v1/latin1")
+                },
+                far_deadline(),
+            )
+            .expect("can enqueue");
+
+        let [stored] =
+            <[_; 1]>::try_from(outbox.store.load().expect("can load")).expect("one stored request");
+        assert!(stored
+            .headers
+            .contains(&("x-test".to_owned(), b"caf\xe9".to_vec())));
+
+        let sender = ScriptedSender::new([ok_response()]);
+        let (state_tx, state_rx) = watch::channel(connected());
+        tokio::join!(outbox.run(&sender, state_rx), async {
+            assert_matches!(receipt.result().await, Ok(_));
+            state_tx.send_replace(ConnectionState::Stopped(StopReason::Requested));
+        });
+        assert_eq!(
+            sender.requests.lock().expect("not poisoned")[0].headers["x-test"],
+            value
+        );
+    }
+}
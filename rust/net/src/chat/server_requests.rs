@@ -3,6 +3,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::collections::HashMap;
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
 use libsignal_net_infra::ws::WebSocketServiceError;
 use libsignal_protocol::Timestamp;
 
@@ -60,6 +63,8 @@ pub enum ServerEventError {
     MissingPath,
     /// server sent an unknown request: {0}
     UnrecognizedPath(String),
+    /// server request was malformed: {0}
+    InvalidRequest(&'static str),
 }
 
 impl TryFrom<ws2::ListenerEvent> for ServerEvent {
@@ -139,3 +144,399 @@ fn convert_received_message(
         _unknown_path => Err(ServerEventError::UnrecognizedPath(path)),
     }
 }
+
+/// A request pushed by the server, before it has been matched to a handler.
+#[derive(Clone, Debug)]
+pub struct ServerRequest {
+    pub id: u64,
+    pub verb: String,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+impl From<RequestProto> for ServerRequest {
+    fn from(proto: RequestProto) -> Self {
+        let RequestProto {
+            verb,
+            path,
+            body,
+            headers,
+            id,
+        } = proto;
+        let headers = headers
+            .iter()
+            .filter_map(|header| {
+                let (name, value) = header.split_once(':')?;
+                Some((
+                    HeaderName::try_from(name.trim()).ok()?,
+                    HeaderValue::try_from(value.trim()).ok()?,
+                ))
+            })
+            .collect();
+        Self {
+            id: id.unwrap_or(0),
+            verb: verb.unwrap_or_default(),
+            path: path.unwrap_or_default(),
+            headers,
+            body,
+        }
+    }
+}
+
+/// A kind of server-initiated request with a typed representation.
+///
+/// Register handlers for these with [`ServerEventDispatcher::on`].
+pub trait ServerPushEvent: Sized {
+    const VERB: http::Method;
+    const PATH: &'static str;
+
+    /// Extracts the event from a request whose verb and path matched.
+    fn parse(request: ServerRequest) -> Result<Self, ServerEventError>;
+}
+
+const ALERT_HEADER_NAME: &str = "x-signal-alert";
+
+/// Alerts the server wants shown to the user, such as a pending account
+/// deletion.
+///
+/// These arrive as `X-Signal-Alert` headers on the server's responses, such as
+/// the response to the chat websocket handshake. Each header can carry several
+/// comma-separated alerts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServerAlerts(pub Vec<String>);
+
+impl ServerAlerts {
+    /// Collects the alerts from a response's headers.
+    ///
+    /// Headers that aren't valid ASCII are skipped. Only the number skipped is
+    /// logged, since alerts may be specific to the account.
+    pub fn from_response_headers(headers: &HeaderMap) -> Self {
+        let mut alerts = vec![];
+        let mut malformed_count = 0;
+        for value in headers.get_all(ALERT_HEADER_NAME) {
+            let Ok(value) = value.to_str() else {
+                malformed_count += 1;
+                continue;
+            };
+            alerts.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|alert| !alert.is_empty())
+                    .map(String::from),
+            );
+        }
+        if malformed_count != 0 {
+            log::warn!("ignoring {malformed_count} malformed {ALERT_HEADER_NAME} header(s)");
+        }
+        Self(alerts)
+    }
+}
+
+/// The address a device waiting to be linked has been assigned.
+///
+/// This is the first request on a provisioning connection; the primary device
+/// sends its [`ProvisioningEnvelope`] to this address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvisioningAddress(pub String);
+
+impl ServerPushEvent for ProvisioningAddress {
+    const VERB: http::Method = http::Method::PUT;
+    const PATH: &'static str = "/v1/address";
+
+    fn parse(request: ServerRequest) -> Result<Self, ServerEventError> {
+        let body = request.body.ok_or(ServerEventError::InvalidRequest(
+            "missing provisioning address",
+        ))?;
+        let crate::proto::provisioning::ProvisioningUuid { uuid } = prost::Message::decode(&*body)
+            .map_err(|_| ServerEventError::InvalidRequest("invalid provisioning address"))?;
+        match uuid {
+            Some(address) if !address.is_empty() => Ok(Self(address)),
+            _ => Err(ServerEventError::InvalidRequest(
+                "missing provisioning address",
+            )),
+        }
+    }
+}
+
+/// The encrypted provisioning message sent by the primary device, delivered
+/// on a provisioning connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvisioningEnvelope(pub Vec<u8>);
+
+impl ServerPushEvent for ProvisioningEnvelope {
+    const VERB: http::Method = http::Method::PUT;
+    const PATH: &'static str = "/v1/message";
+
+    fn parse(request: ServerRequest) -> Result<Self, ServerEventError> {
+        match request.body {
+            Some(envelope) if !envelope.is_empty() => Ok(Self(envelope)),
+            _ => Err(ServerEventError::InvalidRequest(
+                "missing provisioning envelope",
+            )),
+        }
+    }
+}
+
+type RequestHandler = Box<dyn FnMut(ServerRequest, ws2::Responder) + Send>;
+
+/// Routes server-initiated requests to handlers by verb and path.
+///
+/// Registered handlers take priority over the built-in [`ServerEvent`]s,
+/// which are passed to the fallback given to
+/// [`into_listener`](Self::into_listener). Requests that match neither are
+/// answered with 404 so the server doesn't wait on them.
+#[derive(Default)]
+pub struct ServerEventDispatcher {
+    handlers: HashMap<(http::Method, String), RequestHandler>,
+}
+
+impl ServerEventDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles `E` with `handler`, which is responsible for responding.
+    ///
+    /// If the request can't be parsed as `E`, it is answered with 400 and
+    /// `handler` is not called.
+    pub fn on<E: ServerPushEvent>(
+        self,
+        mut handler: impl FnMut(E, ws2::Responder) + Send + 'static,
+    ) -> Self {
+        self.on_request(E::VERB, E::PATH, move |request, responder| {
+            match E::parse(request) {
+                Ok(event) => handler(event, responder),
+                Err(e) => {
+                    log::warn!("failed to parse {} {}: {e}", E::VERB, E::PATH);
+                    respond_or_log(responder, StatusCode::BAD_REQUEST);
+                }
+            }
+        })
+    }
+
+    /// Handles requests for `verb` and `path` without any parsing.
+    ///
+    /// Replaces any handler previously registered for the same request.
+    pub fn on_request(
+        mut self,
+        verb: http::Method,
+        path: &str,
+        handler: impl FnMut(ServerRequest, ws2::Responder) + Send + 'static,
+    ) -> Self {
+        self.handlers
+            .insert((verb, path.to_owned()), Box::new(handler));
+        self
+    }
+
+    /// Produces a listener for a chat connection.
+    ///
+    /// Requests without a registered handler are converted to
+    /// [`ServerEvent`]s and passed to `fallback`, as is the end of the
+    /// connection.
+    pub fn into_listener(
+        mut self,
+        mut fallback: impl FnMut(ServerEvent) + Send + 'static,
+    ) -> ws2::EventListener {
+        Box::new(move |event| match event {
+            ws2::ListenerEvent::ReceivedMessage(proto, responder) => {
+                let handler = proto
+                    .verb
+                    .as_deref()
+                    .and_then(|verb| http::Method::from_bytes(verb.as_bytes()).ok())
+                    .zip(proto.path.clone())
+                    .and_then(|key| self.handlers.get_mut(&key));
+                if let Some(handler) = handler {
+                    handler(proto.into(), responder);
+                    return;
+                }
+
+                // Keep hold of the responder in case the request isn't
+                // recognized, so it can be answered with a 404.
+                let mut responder = Some(responder);
+                let event = convert_received_message(proto, || {
+                    let responder = responder.take().expect("only taken once");
+                    Box::new(move |status| Ok(responder.send_response(status)?))
+                });
+                match event {
+                    Ok(event) => fallback(event),
+                    Err(e) => {
+                        log::warn!("unhandled server request: {e}");
+                        if let Some(responder) = responder {
+                            respond_or_log(responder, StatusCode::NOT_FOUND);
+                        }
+                    }
+                }
+            }
+            finished @ ws2::ListenerEvent::Finished(_) => match ServerEvent::try_from(finished) {
+                Ok(event) => fallback(event),
+                Err(e) => log::warn!("{e}"),
+            },
+        })
+    }
+}
+
+fn respond_or_log(responder: ws2::Responder, status: StatusCode) {
+    if let Err(e) = responder.send_response(status) {
+        log::warn!("failed to respond to server request: {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use test_case::test_case;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::chat::fake::FakeChatRemote;
+    use crate::chat::ChatConnection;
+
+    /// Connects a fake chat whose listener routes provisioning envelopes and
+    /// fallback events to the returned receivers.
+    fn connect() -> (
+        ChatConnection,
+        FakeChatRemote,
+        mpsc::UnboundedReceiver<ProvisioningEnvelope>,
+        mpsc::UnboundedReceiver<ServerEvent>,
+    ) {
+        let (envelopes_tx, envelopes_rx) = mpsc::unbounded_channel();
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let listener = ServerEventDispatcher::new()
+            .on(move |envelope: ProvisioningEnvelope, responder| {
+                envelopes_tx.send(envelope).expect("test is running");
+                respond_or_log(responder, StatusCode::OK);
+            })
+            .into_listener(move |event| {
+                // Ignore the end of the connection when the test finishes.
+                let _ = events_tx.send(event);
+            });
+        let (chat, remote) = ChatConnection::new_fake(tokio::runtime::Handle::current(), listener);
+        (chat, remote, envelopes_rx, events_rx)
+    }
+
+    fn request(id: u64, path: &str, body: &[u8]) -> RequestProto {
+        RequestProto {
+            verb: Some("PUT".to_owned()),
+            path: Some(path.to_owned()),
+            body: Some(body.to_vec()),
+            headers: vec![],
+            id: Some(id),
+        }
+    }
+
+    async fn response_status(remote: &FakeChatRemote, id: u64) -> u32 {
+        let response = remote
+            .receive_response()
+            .await
+            .expect("valid response")
+            .expect("still connected");
+        assert_eq!(response.id, Some(id));
+        response.status.expect("has status")
+    }
+
+    #[tokio::test]
+    async fn registered_handler_receives_typed_event() {
+        let (_chat, remote, mut envelopes, _events) = connect();
+        remote
+            .send_request(request(1, ProvisioningEnvelope::PATH, b"envelope"))
+            .expect("connected");
+        assert_eq!(
+            envelopes.recv().await,
+            Some(ProvisioningEnvelope(b"envelope".to_vec()))
+        );
+        assert_eq!(response_status(&remote, 1).await, 200);
+    }
+
+    #[tokio::test]
+    async fn unparseable_request_is_rejected() {
+        let (_chat, remote, mut envelopes, _events) = connect();
+        remote
+            .send_request(request(2, ProvisioningEnvelope::PATH, b""))
+            .expect("connected");
+        assert_eq!(response_status(&remote, 2).await, 400);
+        assert_matches!(envelopes.try_recv(), Err(mpsc::error::TryRecvError::Empty));
+    }
+
+    #[tokio::test]
+    async fn unknown_request_gets_404() {
+        let (_chat, remote, _envelopes, mut events) = connect();
+        remote
+            .send_request(request(3, "/v1/test/unknown", b""))
+            .expect("connected");
+        assert_eq!(response_status(&remote, 3).await, 404);
+        assert_matches!(events.try_recv(), Err(mpsc::error::TryRecvError::Empty));
+    }
+
+    #[tokio::test]
+    async fn builtin_events_go_to_fallback() {
+        let (_chat, remote, _envelopes, mut events) = connect();
+        remote
+            .send_request(request(4, "/api/v1/queue/empty", b""))
+            .expect("connected");
+        assert_matches!(events.recv().await, Some(ServerEvent::QueueEmpty));
+    }
+
+    fn server_request(body: Option<Vec<u8>>) -> ServerRequest {
+        ServerRequest {
+            id: 1,
+            verb: "PUT".to_owned(),
+            path: String::new(),
+            headers: HeaderMap::new(),
+            body,
+        }
+    }
+
+    #[test_case(&[] => Vec::<String>::new(); "none")]
+    #[test_case(&[b"deleting"] => vec!["deleting"]; "one")]
+    #[test_case(&[b"a, b", b"c"] => vec!["a", "b", "c"]; "split and repeated")]
+    #[test_case(&[b" , a ,"] => vec!["a"]; "empty entries")]
+    #[test_case(&[b"caf\xe9", b"deleting"] => vec!["deleting"]; "malformed skipped")]
+    fn parse_alerts(values: &[&[u8]]) -> Vec<String> {
+        let headers = values
+            .iter()
+            .map(|value| {
+                (
+                    HeaderName::from_static(ALERT_HEADER_NAME),
+                    HeaderValue::from_bytes(value).expect("valid header value"),
+                )
+            })
+            .collect();
+        let ServerAlerts(alerts) = ServerAlerts::from_response_headers(&headers);
+        alerts
+    }
+
+    #[test]
+    fn parse_provisioning_address() {
+        let body = prost::Message::encode_to_vec(&crate::proto::provisioning::ProvisioningUuid {
+            uuid: Some("abc".to_owned()),
+        });
+        assert_eq!(
+            ProvisioningAddress::parse(server_request(Some(body))).expect("valid"),
+            ProvisioningAddress("abc".to_owned())
+        );
+    }
+
+    #[test_case(None; "missing body")]
+    #[test_case(Some(vec![]); "missing address")]
+    #[test_case(Some(vec![0xff]); "not a protobuf")]
+    fn parse_invalid_provisioning_address(body: Option<Vec<u8>>) {
+        assert_matches!(
+            ProvisioningAddress::parse(server_request(body)),
+            Err(ServerEventError::InvalidRequest(_))
+        );
+    }
+
+    #[test]
+    fn parse_provisioning_envelope() {
+        assert_eq!(
+            ProvisioningEnvelope::parse(server_request(Some(b"envelope".to_vec()))).expect("valid"),
+            ProvisioningEnvelope(b"envelope".to_vec())
+        );
+        assert_matches!(
+            ProvisioningEnvelope::parse(server_request(None)),
+            Err(ServerEventError::InvalidRequest(_))
+        );
+    }
+}
@@ -0,0 +1,755 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Keeps an authenticated chat connection alive across disconnects.
+//!
+//! A [`ChatSupervisor`] owns a [`ChatConnector`] and the headers needed to
+//! authenticate, and runs a task that reconnects with jittered exponential
+//! backoff whenever the connection ends unexpectedly. Network changes cut
+//! short any backoff in progress, since the failure that caused it may no
+//! longer apply, and replace any connection or attempt made over the old
+//! network.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt as _, Stream};
+use libsignal_net_infra::utils::{EventSubscription, ObservableEvent};
+use rand::Rng;
+use tokio::sync::{oneshot, watch};
+
+use crate::chat::api::ChatSender;
+use crate::chat::ws2::{self, FinishReason};
+use crate::chat::{
+    self, AuthenticatedChatHeaders, ChatConnection, ChatServiceError, ConnectionInfo,
+};
+
+/// Establishes the connections managed by a [`ChatSupervisor`].
+///
+/// Implemented for closures, so that callers can capture whatever they use
+/// to connect (typically a route provider and connect state).
+pub trait ChatConnector: Send + Sync + 'static {
+    fn connect(
+        &self,
+        auth: AuthenticatedChatHeaders,
+        listener: ws2::EventListener,
+    ) -> BoxFuture<'_, Result<ChatConnection, ChatServiceError>>;
+}
+
+impl<F, Fut> ChatConnector for F
+where
+    F: Fn(AuthenticatedChatHeaders, ws2::EventListener) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<ChatConnection, ChatServiceError>> + Send + 'static,
+{
+    fn connect(
+        &self,
+        auth: AuthenticatedChatHeaders,
+        listener: ws2::EventListener,
+    ) -> BoxFuture<'_, Result<ChatConnection, ChatServiceError>> {
+        self(auth, listener).boxed()
+    }
+}
+
+/// Timing parameters for reconnecting.
+#[derive(Clone, Debug)]
+pub struct ReconnectConfig {
+    /// The delay before the first retry after a failure.
+    pub initial_delay: Duration,
+    /// The upper bound for the delay between attempts.
+    pub max_delay: Duration,
+    /// How long to wait for the network to settle after it changes before
+    /// trying again.
+    pub network_change_delay: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            network_change_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// The delay before the given (1-based) retry.
+    ///
+    /// Doubles with each attempt up to `max_delay`, then picks uniformly
+    /// from the upper half of that range so that clients that lost their
+    /// connections at the same time don't all come back at once.
+    fn backoff_delay(&self, attempt: u32, rng: &mut impl Rng) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .initial_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        rng.gen_range(delay / 2..=delay)
+    }
+}
+
+/// Why a [`ChatSupervisor`] stopped reconnecting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// [`ChatSupervisor::stop`] was called.
+    Requested,
+    /// The server rejected this version of the app.
+    AppExpired,
+    /// The server rejected the credentials for this device.
+    DeviceDeregistered,
+}
+
+/// The state of a supervised connection.
+#[derive(Clone, Debug)]
+pub enum ConnectionState {
+    Connecting,
+    Connected(ConnectionInfo),
+    /// Waiting to make the `attempt`th retry.
+    WaitingToReconnect {
+        attempt: u32,
+        delay: Duration,
+    },
+    /// Waiting for the network to settle after a change.
+    PausedForNetworkChange,
+    /// The supervisor won't connect again.
+    Stopped(StopReason),
+}
+
+/// An authenticated chat connection that reconnects itself.
+///
+/// Requests sent while there is no connection fail with
+/// [`ChatServiceError::Disconnected`]; callers that want to wait can watch
+/// [`state`](Self::state) for [`ConnectionState::Connected`].
+pub struct ChatSupervisor {
+    current: Arc<Mutex<Option<Arc<ChatConnection>>>>,
+    state: watch::Receiver<ConnectionState>,
+    stop: watch::Sender<bool>,
+    _network_change_subscription: EventSubscription,
+}
+
+impl ChatSupervisor {
+    /// Starts connecting on `tokio_runtime`.
+    ///
+    /// `auth` is sent with every connection attempt, so the same credentials
+    /// and story preference apply after each reconnect. Requests from the
+    /// server on any of the connections are passed to `listener`; the end
+    /// of each connection is reported through [`state`](Self::state) instead.
+    pub fn start(
+        tokio_runtime: &tokio::runtime::Handle,
+        connector: impl ChatConnector,
+        auth: AuthenticatedChatHeaders,
+        config: ReconnectConfig,
+        network_change_event: &ObservableEvent,
+        listener: ws2::EventListener,
+    ) -> Self {
+        let current = Arc::new(Mutex::new(None));
+        let (state_tx, state) = watch::channel(ConnectionState::Connecting);
+        let (stop, stop_rx) = watch::channel(false);
+        // A generation counter rather than a Notify, so that changes that
+        // happen while the task is busy elsewhere are still seen later.
+        let (network_generation, network_changed) = watch::channel(0u64);
+        let subscription = network_change_event.subscribe(Box::new(move || {
+            network_generation.send_modify(|generation| *generation = generation.wrapping_add(1))
+        }));
+
+        tokio_runtime.spawn(
+            SupervisorTask {
+                connector,
+                auth,
+                config,
+                listener: Arc::new(Mutex::new(listener)),
+                current: current.clone(),
+                state: state_tx,
+                stop: stop_rx,
+                network_changed,
+            }
+            .run(),
+        );
+
+        Self {
+            current,
+            state,
+            stop,
+            _network_change_subscription: subscription,
+        }
+    }
+
+    /// Observes the state of the connection.
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Yields the current state and then every subsequent change.
+    pub fn state_stream(&self) -> impl Stream<Item = ConnectionState> + Send + 'static {
+        let mut receiver = self.state.clone();
+        receiver.mark_changed();
+        futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.changed().await.ok()?;
+            let state = receiver.borrow_and_update().clone();
+            Some((state, receiver))
+        })
+    }
+
+    /// Disconnects and stops reconnecting.
+    ///
+    /// Returns once the current connection, if any, has been closed.
+    pub async fn stop(&self) {
+        // If the task has already exited, there's nothing to stop.
+        let _ = self.stop.send(true);
+        let mut state = self.state.clone();
+        let _ = state
+            .wait_for(|state| matches!(state, ConnectionState::Stopped(_)))
+            .await;
+    }
+
+    pub async fn send(
+        &self,
+        request: chat::Request,
+        timeout: Duration,
+    ) -> Result<chat::Response, ChatServiceError> {
+        let connection = self
+            .current
+            .lock()
+            .expect("not poisoned")
+            .clone()
+            .ok_or(ChatServiceError::Disconnected)?;
+        connection.send(request, timeout).await
+    }
+}
+
+impl ChatSender for ChatSupervisor {
+    fn send_request(
+        &self,
+        request: chat::Request,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<chat::Response, ChatServiceError>> {
+        self.send(request, timeout).boxed()
+    }
+}
+
+struct SupervisorTask<C> {
+    connector: C,
+    auth: AuthenticatedChatHeaders,
+    config: ReconnectConfig,
+    listener: Arc<Mutex<ws2::EventListener>>,
+    current: Arc<Mutex<Option<Arc<ChatConnection>>>>,
+    state: watch::Sender<ConnectionState>,
+    stop: watch::Receiver<bool>,
+    network_changed: watch::Receiver<u64>,
+}
+
+/// How a connection or a wait ended.
+enum Outcome {
+    Continue,
+    /// The network changed, so past failures say little about the next
+    /// attempt.
+    ResetBackoff,
+    Stop(StopReason),
+}
+
+/// Resolves once the supervisor asks the task to stop (or is dropped).
+async fn stop_requested(stop: &mut watch::Receiver<bool>) {
+    // An error means the sender was dropped, which is also a request to stop.
+    let _ = stop.wait_for(|stop| *stop).await;
+}
+
+/// Resolves once the network has changed since the last time this resolved.
+async fn next_network_change(network_changed: &mut watch::Receiver<u64>) {
+    if network_changed.changed().await.is_err() {
+        // The supervisor was dropped, which `stop_requested` handles.
+        std::future::pending().await
+    }
+}
+
+impl<C: ChatConnector> SupervisorTask<C> {
+    async fn run(mut self) {
+        let mut attempt = 0;
+        let reason = loop {
+            if *self.stop.borrow() {
+                break StopReason::Requested;
+            }
+            self.state.send_replace(ConnectionState::Connecting);
+
+            let (finished_tx, finished_rx) = oneshot::channel();
+            let connect = self
+                .connector
+                .connect(self.auth.clone(), self.forwarding_listener(finished_tx));
+            let result = tokio::select! {
+                result = connect => Some(result),
+                () = next_network_change(&mut self.network_changed) => None,
+                () = stop_requested(&mut self.stop) => break StopReason::Requested,
+            };
+
+            let mut retry_after = None;
+            let mut network_changed = false;
+            match result {
+                None => {
+                    log::info!("network changed while connecting supervised chat");
+                    network_changed = true;
+                }
+                Some(Ok(connection)) => {
+                    attempt = 0;
+                    match self.supervise(connection, finished_rx).await {
+                        Outcome::Continue => {}
+                        Outcome::ResetBackoff => network_changed = true,
+                        Outcome::Stop(reason) => break reason,
+                    }
+                }
+                Some(Err(ChatServiceError::AppExpired)) => break StopReason::AppExpired,
+                Some(Err(ChatServiceError::DeviceDeregistered)) => {
+                    break StopReason::DeviceDeregistered
+                }
+                Some(Err(e)) => {
+                    log::warn!("supervised chat failed to connect: {e}");
+                    if let ChatServiceError::RetryLater {
+                        retry_after_seconds,
+                    } = e
+                    {
+                        retry_after = Some(Duration::from_secs(retry_after_seconds.into()));
+                    }
+                }
+            }
+
+            let outcome = if network_changed {
+                self.pause_for_network_change().await
+            } else {
+                attempt += 1;
+                let delay = self
+                    .config
+                    .backoff_delay(attempt, &mut rand::thread_rng())
+                    .max(retry_after.unwrap_or_default());
+                self.wait(attempt, delay).await
+            };
+            match outcome {
+                Outcome::Continue => {}
+                Outcome::ResetBackoff => attempt = 0,
+                Outcome::Stop(reason) => break reason,
+            }
+        };
+        log::info!("supervised chat stopped: {reason:?}");
+        self.state.send_replace(ConnectionState::Stopped(reason));
+    }
+
+    /// Wraps the caller's listener so that the end of a connection is
+    /// reported to the supervisor rather than the caller.
+    fn forwarding_listener(
+        &self,
+        finished_tx: oneshot::Sender<Result<FinishReason, ws2::FinishError>>,
+    ) -> ws2::EventListener {
+        let listener = self.listener.clone();
+        let mut finished_tx = Some(finished_tx);
+        Box::new(move |event| match event {
+            ws2::ListenerEvent::Finished(reason) => {
+                if let Some(finished_tx) = finished_tx.take() {
+                    // The supervisor may have stopped already.
+                    let _ = finished_tx.send(reason);
+                }
+            }
+            event @ ws2::ListenerEvent::ReceivedMessage(..) => {
+                (listener.lock().expect("not poisoned"))(event)
+            }
+        })
+    }
+
+    /// Publishes `connection` and waits for it to end.
+    async fn supervise(
+        &mut self,
+        connection: ChatConnection,
+        finished_rx: oneshot::Receiver<Result<FinishReason, ws2::FinishError>>,
+    ) -> Outcome {
+        let connection = Arc::new(connection);
+        *self.current.lock().expect("not poisoned") = Some(connection.clone());
+        self.state.send_replace(ConnectionState::Connected(
+            connection.connection_info().clone(),
+        ));
+        log::info!("supervised chat connected {}", connection.connection_info());
+
+        let outcome = tokio::select! {
+            finished = finished_rx => match finished {
+                Ok(Ok(FinishReason::LocalDisconnect)) => Outcome::Stop(StopReason::Requested),
+                Ok(Ok(FinishReason::RemoteDisconnect)) => {
+                    log::info!("supervised chat disconnected by the server");
+                    Outcome::Continue
+                }
+                Ok(Err(e)) => {
+                    log::warn!("supervised chat disconnected: {e:?}");
+                    Outcome::Continue
+                }
+                Err(_) => Outcome::Continue,
+            },
+            () = next_network_change(&mut self.network_changed) => {
+                // The connection may be stuck on the old network.
+                log::info!("network changed; replacing supervised chat connection");
+                connection.disconect().await;
+                Outcome::ResetBackoff
+            }
+            () = stop_requested(&mut self.stop) => {
+                connection.disconect().await;
+                Outcome::Stop(StopReason::Requested)
+            }
+        };
+        *self.current.lock().expect("not poisoned") = None;
+        outcome
+    }
+
+    /// Waits `delay` before the next attempt.
+    ///
+    /// A network change replaces the rest of the wait with a short pause.
+    async fn wait(&mut self, attempt: u32, delay: Duration) -> Outcome {
+        self.state
+            .send_replace(ConnectionState::WaitingToReconnect { attempt, delay });
+        tokio::select! {
+            () = tokio::time::sleep(delay) => Outcome::Continue,
+            () = next_network_change(&mut self.network_changed) => {
+                self.pause_for_network_change().await
+            }
+            () = stop_requested(&mut self.stop) => Outcome::Stop(StopReason::Requested),
+        }
+    }
+
+    /// Pauses briefly after a network change, to let the new network settle,
+    /// starting over if it changes again.
+    async fn pause_for_network_change(&mut self) -> Outcome {
+        log::info!("network changed; reconnecting supervised chat soon");
+        self.state
+            .send_replace(ConnectionState::PausedForNetworkChange);
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep(self.config.network_change_delay) => {
+                    return Outcome::ResetBackoff
+                }
+                () = next_network_change(&mut self.network_changed) => {}
+                () = stop_requested(&mut self.stop) => {
+                    return Outcome::Stop(StopReason::Requested)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use assert_matches::assert_matches;
+    use futures_util::StreamExt as _;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::auth::Auth;
+    use crate::chat::fake::FakeChatRemote;
+    use crate::chat::RequestProto;
+
+    const CONFIG: ReconnectConfig = ReconnectConfig {
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+        network_change_delay: Duration::from_millis(100),
+    };
+
+    /// A connector that plays back a script of results.
+    ///
+    /// `None` entries connect to a fake remote, which is handed to the test
+    /// through the returned receiver.
+    fn scripted_connector(
+        script: impl IntoIterator<Item = Option<ChatServiceError>>,
+    ) -> (
+        impl ChatConnector,
+        tokio::sync::mpsc::UnboundedReceiver<(FakeChatRemote, String)>,
+    ) {
+        let script = Mutex::new(script.into_iter().collect::<VecDeque<_>>());
+        let (remotes_tx, remotes_rx) = tokio::sync::mpsc::unbounded_channel();
+        let connector = move |auth: AuthenticatedChatHeaders, listener| {
+            let next = script
+                .lock()
+                .expect("not poisoned")
+                .pop_front()
+                .expect("script has another entry");
+            let result = match next {
+                Some(error) => Err(error),
+                None => {
+                    let (chat, remote) =
+                        ChatConnection::new_fake(tokio::runtime::Handle::current(), listener);
+                    remotes_tx
+                        .send((remote, auth.auth.username))
+                        .expect("test is running");
+                    Ok(chat)
+                }
+            };
+            std::future::ready(result)
+        };
+        (connector, remotes_rx)
+    }
+
+    fn auth() -> AuthenticatedChatHeaders {
+        AuthenticatedChatHeaders {
+            auth: Auth {
+                username: "user".to_owned(),
+                password: "pass".to_owned(),
+            },
+            receive_stories: true.into(),
+        }
+    }
+
+    fn start(
+        connector: impl ChatConnector,
+        network_change_event: &ObservableEvent,
+    ) -> ChatSupervisor {
+        ChatSupervisor::start(
+            &tokio::runtime::Handle::current(),
+            connector,
+            auth(),
+            CONFIG,
+            network_change_event,
+            Box::new(|_event| {}),
+        )
+    }
+
+    async fn wait_until_connected(supervisor: &ChatSupervisor) {
+        supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::Connected(_)))
+            .await
+            .expect("still running");
+    }
+
+    #[test_case(1, Duration::from_secs(1))]
+    #[test_case(3, Duration::from_secs(4))]
+    #[test_case(10, Duration::from_secs(30))]
+    #[test_case(u32::MAX, Duration::from_secs(30))]
+    fn backoff_delay_is_jittered_below_cap(attempt: u32, cap: Duration) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let delay = CONFIG.backoff_delay(attempt, &mut rng);
+            assert!(cap / 2 <= delay && delay <= cap, "{delay:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnects_after_remote_disconnect() {
+        let (connector, mut remotes) = scripted_connector([None, None]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let (first, username) = remotes.recv().await.expect("connected");
+        assert_eq!(username, "user");
+        wait_until_connected(&supervisor).await;
+
+        first.send_close(None).expect("still connected");
+        let (_second, username) = remotes.recv().await.expect("reconnected");
+        assert_eq!(username, "user", "auth is replayed on reconnect");
+        wait_until_connected(&supervisor).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_between_failed_attempts() {
+        let (connector, mut remotes) = scripted_connector([
+            Some(ChatServiceError::AllConnectionRoutesFailed),
+            Some(ChatServiceError::TimeoutEstablishingConnection),
+            None,
+        ]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let started = tokio::time::Instant::now();
+        let states: Vec<_> = supervisor
+            .state_stream()
+            .take_while(|state| std::future::ready(!matches!(state, ConnectionState::Connected(_))))
+            .collect()
+            .await;
+        let _remote = remotes.recv().await.expect("connected");
+
+        let attempts: Vec<_> = states
+            .iter()
+            .filter_map(|state| match state {
+                ConnectionState::WaitingToReconnect { attempt, .. } => Some(*attempt),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(attempts, [1, 2]);
+        // At least half of 1s, then half of 2s.
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_later_extends_backoff() {
+        let (connector, mut remotes) = scripted_connector([
+            Some(ChatServiceError::RetryLater {
+                retry_after_seconds: 20,
+            }),
+            None,
+        ]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let started = tokio::time::Instant::now();
+        let _remote = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+        assert!(started.elapsed() >= Duration::from_secs(20));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn network_change_cuts_backoff_short() {
+        let (connector, mut remotes) = scripted_connector([
+            Some(ChatServiceError::RetryLater {
+                retry_after_seconds: 600,
+            }),
+            None,
+        ]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let started = tokio::time::Instant::now();
+        supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::WaitingToReconnect { .. }))
+            .await
+            .expect("still running");
+        event.fire();
+        supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::PausedForNetworkChange))
+            .await
+            .expect("still running");
+
+        let _remote = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn network_change_while_connecting_starts_over() {
+        let (remotes_tx, mut remotes) = tokio::sync::mpsc::unbounded_channel();
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        // The first attempt never finishes, as if stuck on the old network.
+        let connector = move |_auth: AuthenticatedChatHeaders, listener: ws2::EventListener| {
+            let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let remotes_tx = remotes_tx.clone();
+            async move {
+                if attempt == 0 {
+                    std::future::pending::<()>().await;
+                }
+                let (chat, remote) =
+                    ChatConnection::new_fake(tokio::runtime::Handle::current(), listener);
+                remotes_tx.send(remote).expect("test is running");
+                Ok::<_, ChatServiceError>(chat)
+            }
+        };
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        // Fire before the attempt has even been polled; the change must not be
+        // lost just because the task isn't waiting to reconnect.
+        event.fire();
+        supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::PausedForNetworkChange))
+            .await
+            .expect("still running");
+        let _remote = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn network_change_while_connected_reconnects() {
+        let (connector, mut remotes) = scripted_connector([None, None]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let _first = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+
+        event.fire();
+        supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::PausedForNetworkChange))
+            .await
+            .expect("still running");
+
+        let _second = remotes.recv().await.expect("reconnected");
+        wait_until_connected(&supervisor).await;
+    }
+
+    #[test_case(ChatServiceError::DeviceDeregistered => StopReason::DeviceDeregistered)]
+    #[test_case(ChatServiceError::AppExpired => StopReason::AppExpired)]
+    #[tokio::test(start_paused = true)]
+    async fn fatal_errors_stop_reconnecting(error: ChatServiceError) -> StopReason {
+        let (connector, _remotes) = scripted_connector([Some(error)]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let state = supervisor
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::Stopped(_)))
+            .await
+            .expect("still running")
+            .clone();
+        assert_matches!(state, ConnectionState::Stopped(reason) => reason)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_disconnects() {
+        let (connector, mut remotes) = scripted_connector([None]);
+        let event = ObservableEvent::new();
+        let supervisor = start(connector, &event);
+
+        let _remote = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+
+        supervisor.stop().await;
+        assert_matches!(
+            &*supervisor.state().borrow(),
+            ConnectionState::Stopped(StopReason::Requested)
+        );
+        assert_matches!(
+            supervisor
+                .send(
+                    chat::Request {
+                        method: http::Method::GET,
+                        body: None,
+                        headers: Default::default(),
+                        path: http::uri::PathAndQuery::from_static("/v1/keepalive"),
+                    },
+                    Duration::from_secs(5),
+                )
+                .await,
+            Err(ChatServiceError::Disconnected)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn forwards_server_requests() {
+        let (connector, mut remotes) = scripted_connector([None]);
+        let event = ObservableEvent::new();
+        let (requests_tx, mut requests_rx) = tokio::sync::mpsc::unbounded_channel();
+        let supervisor = ChatSupervisor::start(
+            &tokio::runtime::Handle::current(),
+            connector,
+            auth(),
+            CONFIG,
+            &event,
+            Box::new(move |event| {
+                if let ws2::ListenerEvent::ReceivedMessage(request, _responder) = event {
+                    requests_tx.send(request).expect("test is running");
+                }
+            }),
+        );
+
+        let (remote, _) = remotes.recv().await.expect("connected");
+        wait_until_connected(&supervisor).await;
+        remote
+            .send_request(RequestProto {
+                verb: Some("PUT".to_owned()),
+                path: Some("/api/v1/queue/empty".to_owned()),
+                body: None,
+                headers: vec![],
+                id: Some(7),
+            })
+            .expect("still connected");
+        let request = requests_rx.recv().await.expect("forwarded");
+        assert_eq!(request.path(), "/api/v1/queue/empty");
+    }
+}
@@ -15,7 +15,7 @@ use futures_util::{pin_mut, Stream, StreamExt as _};
 use http::uri::PathAndQuery;
 use http::{Method, StatusCode};
 use itertools::Itertools as _;
-use libsignal_net_infra::ws::{WebSocketServiceError, WebSocketStreamLike};
+use libsignal_net_infra::ws::{DeflateConfig, WebSocketServiceError, WebSocketStreamLike};
 pub use libsignal_net_infra::ws2::FinishReason;
 use libsignal_net_infra::ws2::Outcome;
 use pin_project::pin_project;
@@ -68,6 +68,12 @@ pub struct Config {
 
     /// The value to use as the ID for the first outgoing request.
     pub initial_request_id: u64,
+
+    /// The permessage-deflate compression to offer when connecting, or `None`
+    /// to send uncompressed frames.
+    ///
+    /// Only used when establishing a connection; [`Chat::new`] ignores it.
+    pub compression: Option<DeflateConfig>,
 }
 
 #[derive(Debug)]
@@ -160,6 +166,7 @@ impl Chat {
             initial_request_id,
             local_idle_timeout,
             remote_idle_timeout,
+            compression: _,
         } = config;
 
         // Enable access to tokio types like Sleep, but only for the duration of this call.
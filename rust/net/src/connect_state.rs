@@ -14,11 +14,13 @@ use itertools::Itertools as _;
 use libsignal_net_infra::connection_manager::{ErrorClass, ErrorClassifier as _};
 use libsignal_net_infra::dns::DnsResolver;
 use libsignal_net_infra::errors::{LogSafeDisplay, TransportConnectError};
+use libsignal_net_infra::http_client::{HttpConnectError, HttpConnection, HttpConnector};
 use libsignal_net_infra::route::{
     ComposedConnector, ConnectError, ConnectionOutcomeParams, ConnectionOutcomes, Connector,
-    ConnectorFactory, DescribedRouteConnector, HttpRouteFragment, ResolveWithSavedDescription,
-    RouteProvider, RouteProviderContext, RouteProviderExt as _, RouteResolver, ThrottlingConnector,
-    TransportRoute, UnresolvedRouteDescription, UnresolvedWebsocketServiceRoute,
+    ConnectorFactory, DescribedRouteConnector, EchFallbackConnector, HttpRouteFragment,
+    HttpsServiceRoute, ResolveWithSavedDescription, RouteProvider, RouteProviderContext,
+    RouteProviderExt as _, RouteResolver, ThrottlingConnector, TransportRoute,
+    UnresolvedHttpsServiceRoute, UnresolvedRouteDescription, UnresolvedWebsocketServiceRoute,
     WebSocketRouteFragment, WebSocketServiceRoute, WithLoggableDescription,
     WithoutLoggableDescription,
 };
@@ -35,6 +37,11 @@ use crate::auth::Auth;
 use crate::enclave::{EndpointParams, NewHandshake};
 use crate::ws::WebSocketServiceConnectError;
 
+pub mod route_history;
+pub mod telemetry;
+use route_history::{RouteHistory, RouteHistoryStore, WithRouteHistory};
+use telemetry::{InstrumentedConnector, RecordingResolver, TelemetryCallback};
+
 /// Suggested values for [`ConnectionOutcomeParams`].
 pub const SUGGESTED_CONNECT_PARAMS: ConnectionOutcomeParams = ConnectionOutcomeParams {
     age_cutoff: Duration::from_secs(5 * 60),
@@ -44,9 +51,20 @@ pub const SUGGESTED_CONNECT_PARAMS: ConnectionOutcomeParams = ConnectionOutcomeP
     count_growth_factor: 10.0,
 };
 
+/// Suggested values for [`Config::route_history_params`].
+///
+/// Routes that are blocked on a network tend to stay that way, so the history
+/// that survives restarts remembers failures for much longer than the
+/// in-memory record does.
+pub const SUGGESTED_ROUTE_HISTORY_PARAMS: ConnectionOutcomeParams = ConnectionOutcomeParams {
+    age_cutoff: Duration::from_secs(24 * 60 * 60),
+    ..SUGGESTED_CONNECT_PARAMS
+};
+
 /// Suggested values for [`Config`].
 pub const SUGGESTED_CONNECT_CONFIG: Config = Config {
     connect_params: SUGGESTED_CONNECT_PARAMS,
+    route_history_params: SUGGESTED_ROUTE_HISTORY_PARAMS,
     connect_timeout: ONE_ROUTE_CONNECTION_TIMEOUT,
 };
 
@@ -87,23 +105,37 @@ pub struct ConnectState<ConnectorFactory = DefaultConnectorFactory> {
     make_transport_connector: ConnectorFactory,
     /// Record of connection outcomes.
     attempts_record: ConnectionOutcomes<WebSocketServiceRoute>,
+    /// Record of connection outcomes for plain HTTPS connections.
+    https_attempts_record: ConnectionOutcomes<HttpsServiceRoute>,
+    /// Record of connection outcomes by route description, which can be
+    /// persisted across restarts.
+    route_history: RouteHistory,
+    /// Like `route_history`, but for websockets tunneled over the same routes
+    /// (see [`Self::connect_tunneled_ws`]). Kept only in memory.
+    tunneled_route_history: RouteHistory,
     /// [`RouteProviderContext`] passed to route providers.
     route_provider_context: RouteProviderContextImpl,
+    /// Where to report individual connection attempts, if anywhere.
+    telemetry: Option<TelemetryCallback>,
 }
 
-pub type DefaultTransportConnector = ComposedConnector<
-    ThrottlingConnector<crate::infra::tcp_ssl::StatelessDirect>,
-    crate::infra::route::DirectOrProxy<
-        crate::infra::tcp_ssl::StatelessDirect,
-        crate::infra::tcp_ssl::proxy::StatelessProxied,
+pub type DefaultTransportConnector = EchFallbackConnector<
+    ComposedConnector<
+        ThrottlingConnector<crate::infra::tcp_ssl::StatelessDirect>,
+        crate::infra::route::DirectOrProxy<
+            crate::infra::tcp_ssl::StatelessDirect,
+            crate::infra::tcp_ssl::proxy::StatelessProxied,
+            TransportConnectError,
+        >,
         TransportConnectError,
     >,
-    TransportConnectError,
 >;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub connect_params: ConnectionOutcomeParams,
+    /// Parameters for the route history that is saved across restarts.
+    pub route_history_params: ConnectionOutcomeParams,
     pub connect_timeout: Duration,
 }
 
@@ -115,7 +147,10 @@ impl ConnectorFactory<TransportRoute, ()> for DefaultConnectorFactory {
     fn make(&self) -> Self::Connector {
         let throttle_tls_connections = ThrottlingConnector::new(Default::default(), 1);
         let proxy_or_direct_connector = Default::default();
-        ComposedConnector::new(throttle_tls_connections, proxy_or_direct_connector)
+        EchFallbackConnector::new(ComposedConnector::new(
+            throttle_tls_connections,
+            proxy_or_direct_connector,
+        ))
     }
 }
 
@@ -133,20 +168,51 @@ impl<ConnectorFactory> ConnectState<ConnectorFactory> {
     ) -> tokio::sync::RwLock<Self> {
         let Config {
             connect_params,
+            route_history_params,
             connect_timeout,
         } = config;
         Self {
             route_resolver: RouteResolver::default(),
             connect_timeout,
             make_transport_connector,
-            attempts_record: ConnectionOutcomes::new(connect_params),
+            attempts_record: ConnectionOutcomes::new(connect_params.clone()),
+            https_attempts_record: ConnectionOutcomes::new(connect_params),
+            route_history: RouteHistory::new(ConnectionOutcomes::new(route_history_params.clone())),
+            tunneled_route_history: RouteHistory::new(ConnectionOutcomes::new(
+                route_history_params,
+            )),
             route_provider_context: RouteProviderContextImpl::default(),
+            telemetry: None,
         }
         .into()
     }
 
     pub fn network_changed(&mut self, network_change_time: Instant) {
         self.attempts_record.reset(network_change_time);
+        self.https_attempts_record.reset(network_change_time);
+        self.route_history.network_changed(network_change_time);
+        self.tunneled_route_history
+            .network_changed(network_change_time);
+    }
+
+    /// Loads route outcome history from the stores and saves future updates
+    /// to them.
+    ///
+    /// Tunneled connections keep a separate history (see
+    /// [`Self::connect_tunneled_ws`]), so they need a store of their own.
+    pub fn set_route_history_stores(
+        &mut self,
+        direct: Option<Arc<dyn RouteHistoryStore>>,
+        tunneled: Option<Arc<dyn RouteHistoryStore>>,
+    ) {
+        self.route_history.set_store(direct);
+        self.tunneled_route_history.set_store(tunneled);
+    }
+
+    /// Sets the callback that receives a [`telemetry::ConnectionAttemptRecord`]
+    /// for every subsequent connection attempt.
+    pub fn set_telemetry(&mut self, telemetry: Option<TelemetryCallback>) {
+        self.telemetry = telemetry;
     }
 }
 
@@ -171,6 +237,13 @@ impl RouteInfo {
     }
 }
 
+/// Which history a websocket connection's outcomes are recorded in.
+#[derive(Clone, Copy, Debug)]
+enum RouteHistoryKind {
+    Direct,
+    Tunneled,
+}
+
 impl<TC> ConnectState<TC> {
     pub async fn connect_ws<WC, Inner>(
         this: &tokio::sync::RwLock<Self>,
@@ -181,6 +254,90 @@ impl<TC> ConnectState<TC> {
         confirmation_header_name: Option<&HeaderName>,
         log_tag: Arc<str>,
     ) -> Result<(WC::Connection, RouteInfo), TimeoutOr<ConnectError<WebSocketServiceConnectError>>>
+    where
+        Inner: Clone + Send,
+        // Note that we're not using WebSocketTransportConnectorFactory here to make `connect_ws`
+        // easier to test; specifically, the output is not guaranteed to be an AsyncDuplexStream.
+        TC: ConnectorFactory<
+            TransportRoute,
+            Inner,
+            Connector: Sync + Connector<TransportRoute, Inner, Error: Into<WebSocketConnectError>>,
+        >,
+        WC: Connector<
+                (WebSocketRouteFragment, HttpRouteFragment),
+                TC::Connection,
+                Error = tungstenite::Error,
+            > + Send
+            + Sync,
+    {
+        Self::connect_ws_with_history(
+            this,
+            RouteHistoryKind::Direct,
+            routes,
+            inner,
+            ws_connector,
+            resolver,
+            confirmation_header_name,
+            log_tag,
+        )
+        .await
+    }
+
+    /// Like [`Self::connect_ws`], but for a websocket that tunnels another
+    /// protocol over the same routes.
+    ///
+    /// A tunnel can be blocked even where plain connections over the same
+    /// route work, so its outcomes are kept out of the history used for
+    /// [`Self::connect_ws`] (and vice versa).
+    pub async fn connect_tunneled_ws<WC, Inner>(
+        this: &tokio::sync::RwLock<Self>,
+        routes: impl RouteProvider<Route = UnresolvedWebsocketServiceRoute>,
+        inner: Inner,
+        ws_connector: WC,
+        resolver: &DnsResolver,
+        confirmation_header_name: Option<&HeaderName>,
+        log_tag: Arc<str>,
+    ) -> Result<(WC::Connection, RouteInfo), TimeoutOr<ConnectError<WebSocketServiceConnectError>>>
+    where
+        Inner: Clone + Send,
+        // Note that we're not using WebSocketTransportConnectorFactory here to make `connect_ws`
+        // easier to test; specifically, the output is not guaranteed to be an AsyncDuplexStream.
+        TC: ConnectorFactory<
+            TransportRoute,
+            Inner,
+            Connector: Sync + Connector<TransportRoute, Inner, Error: Into<WebSocketConnectError>>,
+        >,
+        WC: Connector<
+                (WebSocketRouteFragment, HttpRouteFragment),
+                TC::Connection,
+                Error = tungstenite::Error,
+            > + Send
+            + Sync,
+    {
+        Self::connect_ws_with_history(
+            this,
+            RouteHistoryKind::Tunneled,
+            routes,
+            inner,
+            ws_connector,
+            resolver,
+            confirmation_header_name,
+            log_tag,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn connect_ws_with_history<WC, Inner>(
+        this: &tokio::sync::RwLock<Self>,
+        history_kind: RouteHistoryKind,
+        routes: impl RouteProvider<Route = UnresolvedWebsocketServiceRoute>,
+        inner: Inner,
+        ws_connector: WC,
+        resolver: &DnsResolver,
+        confirmation_header_name: Option<&HeaderName>,
+        log_tag: Arc<str>,
+    ) -> Result<(WC::Connection, RouteInfo), TimeoutOr<ConnectError<WebSocketServiceConnectError>>>
     where
         Inner: Clone + Send,
         // Note that we're not using WebSocketTransportConnectorFactory here to make `connect_ws`
@@ -204,8 +361,16 @@ impl<TC> ConnectState<TC> {
             connect_timeout,
             make_transport_connector,
             attempts_record,
+            https_attempts_record: _,
+            route_history,
+            tunneled_route_history,
             route_provider_context,
+            telemetry,
         } = &*connect_read;
+        let route_history = match history_kind {
+            RouteHistoryKind::Direct => route_history,
+            RouteHistoryKind::Tunneled => tunneled_route_history,
+        };
 
         let routes = routes.routes(route_provider_context).collect_vec();
 
@@ -216,26 +381,37 @@ impl<TC> ConnectState<TC> {
 
         let transport_connector = make_transport_connector.make();
         let route_provider = routes.into_iter().map(ResolveWithSavedDescription);
-        let connector =
-            DescribedRouteConnector(ComposedConnector::new(ws_connector, &transport_connector));
-        let delay_policy = WithoutLoggableDescription(&attempts_record);
-
+        let resolver = RecordingResolver::new(resolver);
+        let connector = InstrumentedConnector {
+            ws_connector,
+            transport_connector: &transport_connector,
+            confirmation_header_name,
+            resolver: &resolver,
+            telemetry: telemetry.as_ref(),
+        };
+        let delay_policy = WithRouteHistory {
+            live: WithoutLoggableDescription(&attempts_record),
+            history: route_history,
+        };
+
+        // A pin mismatch on one route doesn't stop the others from being tried,
+        // but if none of them works, it's reported instead of a generic failure.
+        let mut pin_mismatch = None;
         let start = Instant::now();
         let connect = crate::infra::route::connect(
             route_resolver,
             delay_policy,
             route_provider,
-            resolver,
+            &resolver,
             connector,
             inner,
             log_tag.clone(),
             |error| {
-                let error = WebSocketServiceConnectError::from_websocket_error(
-                    error,
-                    confirmation_header_name,
-                    Instant::now(),
-                );
                 log::debug!("[{log_tag}] connection attempt failed with {error}");
+                if error.is_certificate_pin_mismatch() {
+                    pin_mismatch.get_or_insert(error);
+                    return ControlFlow::Continue(());
+                }
                 match error.classify() {
                     ErrorClass::Intermittent => ControlFlow::Continue(()),
                     ErrorClass::Fatal | ErrorClass::RetryAt(_) => ControlFlow::Break(error),
@@ -243,11 +419,24 @@ impl<TC> ConnectState<TC> {
             },
         );
 
-        let (result, updates) = tokio::time::timeout(*connect_timeout, connect)
-            .await
-            .map_err(|_: tokio::time::error::Elapsed| TimeoutOr::Timeout {
-                attempt_duration: *connect_timeout,
-            })?;
+        let finished = tokio::time::timeout(*connect_timeout, connect).await;
+        let (result, updates) = match finished {
+            Ok(finished) => finished,
+            Err(_elapsed) => {
+                return Err(match pin_mismatch {
+                    Some(error) => TimeoutOr::Other(ConnectError::FatalConnect(error)),
+                    None => TimeoutOr::Timeout {
+                        attempt_duration: *connect_timeout,
+                    },
+                })
+            }
+        };
+        let result = match (result, pin_mismatch) {
+            (Err(ConnectError::AllAttemptsFailed), Some(error)) => {
+                Err(ConnectError::FatalConnect(error))
+            }
+            (result, _) => result,
+        };
 
         // Drop our read lock so we can re-acquire as a writer. It's okay if we
         // race with other writers since the order in which updates are applied
@@ -262,19 +451,146 @@ impl<TC> ConnectState<TC> {
             Err(e) => log::info!("[{log_tag}] connection failed with {e}"),
         }
 
-        this.write().await.attempts_record.apply_outcome_updates(
-            updates.outcomes.into_iter().map(
-                |(
-                    WithLoggableDescription {
-                        route,
-                        description: _,
-                    },
-                    outcome,
-                )| (route, outcome),
-            ),
-            updates.finished_at,
+        let (route_updates, description_updates): (Vec<_>, Vec<_>) = updates
+            .outcomes
+            .into_iter()
+            .map(
+                |(WithLoggableDescription { route, description }, outcome)| {
+                    ((route, outcome), (description, outcome))
+                },
+            )
+            .unzip();
+        let mut connect_write = this.write().await;
+        connect_write
+            .attempts_record
+            .apply_outcome_updates(route_updates, updates.finished_at);
+        let history_snapshot = match history_kind {
+            RouteHistoryKind::Direct => &mut connect_write.route_history,
+            RouteHistoryKind::Tunneled => &mut connect_write.tunneled_route_history,
+        }
+        .apply_outcome_updates(description_updates, updates.finished_at);
+        drop(connect_write);
+        if let Some(snapshot) = history_snapshot {
+            // Stores may write to disk, so keep them off the runtime's worker
+            // threads.
+            tokio::task::spawn_blocking(move || snapshot.save());
+        }
+
+        let (connection, description) = result?;
+        Ok((
+            connection,
+            RouteInfo {
+                unresolved: description,
+            },
+        ))
+    }
+
+    /// Establishes an HTTP connection over one of `routes`.
+    ///
+    /// Outcomes are recorded just like for [`Self::connect_ws`], so routes that
+    /// have recently failed are deprioritized. Transport-level failures move on
+    /// to the next route; a failed HTTP handshake ends the attempt.
+    pub async fn connect_https(
+        this: &tokio::sync::RwLock<Self>,
+        routes: impl RouteProvider<Route = UnresolvedHttpsServiceRoute>,
+        resolver: &DnsResolver,
+        log_tag: Arc<str>,
+    ) -> Result<(HttpConnection, RouteInfo), TimeoutOr<ConnectError<HttpConnectError>>>
+    where
+        TC: ConnectorFactory<
+            TransportRoute,
+            (),
+            Connector: Sync + Connector<TransportRoute, (), Error = TransportConnectError>,
+            Connection: AsyncDuplexStream + libsignal_net_infra::Connection + 'static,
+        >,
+    {
+        let connect_read = this.read().await;
+
+        let Self {
+            route_resolver,
+            connect_timeout,
+            make_transport_connector,
+            attempts_record: _,
+            https_attempts_record,
+            route_history,
+            tunneled_route_history: _,
+            route_provider_context,
+            telemetry: _,
+        } = &*connect_read;
+
+        let routes = routes.routes(route_provider_context).collect_vec();
+
+        log::info!(
+            "[{log_tag}] starting HTTPS connection attempt with {} routes",
+            routes.len()
+        );
+
+        let transport_connector = make_transport_connector.make();
+        let route_provider = routes.into_iter().map(ResolveWithSavedDescription);
+        let connector = DescribedRouteConnector(HttpConnector(&transport_connector));
+        let delay_policy = WithRouteHistory {
+            live: WithoutLoggableDescription(&https_attempts_record),
+            history: route_history,
+        };
+
+        let start = Instant::now();
+        let connect = crate::infra::route::connect(
+            route_resolver,
+            delay_policy,
+            route_provider,
+            resolver,
+            connector,
+            (),
+            log_tag.clone(),
+            |error| {
+                log::debug!("[{log_tag}] HTTPS connection attempt failed with {error}");
+                match error {
+                    HttpConnectError::Transport(_) => ControlFlow::Continue(()),
+                    HttpConnectError::HttpHandshake => ControlFlow::Break(error),
+                }
+            },
         );
 
+        let (result, updates) = tokio::time::timeout(*connect_timeout, connect)
+            .await
+            .map_err(|_: tokio::time::error::Elapsed| TimeoutOr::Timeout {
+                attempt_duration: *connect_timeout,
+            })?;
+
+        // As in `connect_ws`, drop the read lock before re-acquiring as a writer.
+        drop(connect_read);
+
+        match &result {
+            Ok((_connection, route)) => log::info!(
+                "[{log_tag}] HTTPS connection through {route} succeeded after {:.3?}",
+                start.elapsed()
+            ),
+            Err(e) => log::info!("[{log_tag}] HTTPS connection failed with {e}"),
+        }
+
+        let (route_updates, description_updates): (Vec<_>, Vec<_>) = updates
+            .outcomes
+            .into_iter()
+            .map(
+                |(WithLoggableDescription { route, description }, outcome)| {
+                    ((route, outcome), (description, outcome))
+                },
+            )
+            .unzip();
+        let mut connect_write = this.write().await;
+        connect_write
+            .https_attempts_record
+            .apply_outcome_updates(route_updates, updates.finished_at);
+        let history_snapshot = connect_write
+            .route_history
+            .apply_outcome_updates(description_updates, updates.finished_at);
+        drop(connect_write);
+        if let Some(snapshot) = history_snapshot {
+            // Stores may write to disk, so keep them off the runtime's worker
+            // threads.
+            tokio::task::spawn_blocking(move || snapshot.save());
+        }
+
         let (connection, description) = result?;
         Ok((
             connection,
@@ -369,7 +685,7 @@ mod test {
         DirectOrProxyRoute, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment, UnresolvedHost,
         UnresolvedTransportRoute, WebSocketRoute,
     };
-    use libsignal_net_infra::{Alpn, DnsSource, RouteType};
+    use libsignal_net_infra::{Alpn, DnsSource, IpType, RouteType};
     use nonzero_ext::nonzero;
 
     use super::*;
@@ -380,6 +696,8 @@ mod test {
             root_certs: RootCertificates::Native,
             sni: Host::Domain("fake-sni".into()),
             alpn: Some(Alpn::Http1_1),
+            ech_config_list: None,
+            pin_set: None,
         },
         inner: DirectOrProxyRoute::Direct(TcpRoute {
             address: UnresolvedHost::from(Arc::from(FAKE_HOST_NAME)),
@@ -394,6 +712,7 @@ mod test {
                         ws_config: Default::default(),
                         endpoint: PathAndQuery::from_static("/first"),
                         headers: HeaderMap::new(),
+                        deflate: None,
                     },
                     inner: HttpsTlsRoute {
                         fragment: HttpRouteFragment {
@@ -409,6 +728,7 @@ mod test {
                         ws_config: Default::default(),
                         endpoint: PathAndQuery::from_static("/second"),
                         headers: HeaderMap::new(),
+                        deflate: None,
                     },
                     inner: HttpsTlsRoute {
                         fragment: HttpRouteFragment {
@@ -451,8 +771,14 @@ mod test {
             connect_timeout: Duration::MAX,
             route_resolver: RouteResolver::default(),
             attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            https_attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            route_history: RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS)),
+            tunneled_route_history: RouteHistory::new(ConnectionOutcomes::new(
+                SUGGESTED_CONNECT_PARAMS,
+            )),
             make_transport_connector: fake_transport_connector,
             route_provider_context: Default::default(),
+            telemetry: None,
         }
         .into();
 
@@ -478,6 +804,150 @@ mod test {
         assert_eq!(unresolved.to_string(), "REDACTED:1234 fronted by proxyf");
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn connect_ws_reports_attempts() {
+        let [failing_route, succeeding_route] = (*FAKE_WEBSOCKET_ROUTES).clone();
+
+        const TRANSPORT_TIME: Duration = Duration::from_millis(300);
+        const UPGRADE_TIME: Duration = Duration::from_millis(200);
+
+        let ws_connector = ConnectFn(|(), route, _log_tag| {
+            let (ws, http) = &route;
+            let result = if (ws, http) == (&failing_route.fragment, &failing_route.inner.fragment) {
+                Err(tungstenite::Error::ConnectionClosed)
+            } else {
+                Ok(route)
+            };
+            async move {
+                tokio::time::sleep(UPGRADE_TIME).await;
+                result
+            }
+        });
+        let resolver = DnsResolver::new_from_static_map(HashMap::from([(
+            FAKE_HOST_NAME,
+            LookupResult::new(DnsSource::Static, vec![ip_addr!(v4, "1.1.1.1")], vec![]),
+        )]));
+
+        let fake_transport_connector = ConnectFn(move |(), _, _| async {
+            tokio::time::sleep(TRANSPORT_TIME).await;
+            Ok::<_, WebSocketConnectError>(())
+        });
+
+        let (telemetry, records) = telemetry::telemetry_stream();
+        let state = ConnectState {
+            connect_timeout: Duration::MAX,
+            route_resolver: RouteResolver::default(),
+            attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            https_attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            route_history: RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS)),
+            tunneled_route_history: RouteHistory::new(ConnectionOutcomes::new(
+                SUGGESTED_CONNECT_PARAMS,
+            )),
+            make_transport_connector: fake_transport_connector,
+            route_provider_context: Default::default(),
+            telemetry: Some(telemetry),
+        }
+        .into();
+
+        let _connection = ConnectState::connect_ws(
+            &state,
+            vec![failing_route.clone(), succeeding_route.clone()],
+            (),
+            ws_connector,
+            &resolver,
+            None,
+            "test".into(),
+        )
+        .await
+        .expect("succeeded");
+
+        // Dropping the state drops the callback, which ends the stream.
+        drop(state);
+        let records: Vec<_> = futures_util::StreamExt::collect(records).await;
+
+        let [failed, succeeded] = &records[..] else {
+            panic!("expected two records, got {records:?}");
+        };
+        for record in &records {
+            assert_matches!(record.ip_type, IpType::V4);
+            assert_eq!(record.dns_source, Some(DnsSource::Static));
+            assert_eq!(record.tls_handshake_completed_after, Some(TRANSPORT_TIME));
+            assert_eq!(record.websocket_upgrade_duration, Some(UPGRADE_TIME));
+            assert_eq!(record.duration, TRANSPORT_TIME + UPGRADE_TIME);
+        }
+        assert_eq!(
+            failed.result,
+            telemetry::AttemptResult::Failed {
+                error: telemetry::AttemptError::WebSocket
+            }
+        );
+        assert_eq!(failed.route.to_string(), "REDACTED:1234 (direct)");
+        assert_eq!(succeeded.result, telemetry::AttemptResult::Succeeded);
+        assert_eq!(
+            succeeded.route.to_string(),
+            "REDACTED:1234 fronted by proxyf"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_ws_reports_pin_mismatch_if_no_route_works() {
+        let [first_route, second_route] = (*FAKE_WEBSOCKET_ROUTES).clone();
+
+        let ws_connector = ConnectFn(|(), _route, _log_tag| {
+            std::future::ready(Err::<(), _>(tungstenite::Error::ConnectionClosed))
+        });
+        let resolver = DnsResolver::new_from_static_map(HashMap::from([(
+            FAKE_HOST_NAME,
+            LookupResult::new(DnsSource::Static, vec![ip_addr!(v4, "1.1.1.1")], vec![]),
+        )]));
+
+        // Only the first attempt hits the mismatch; the other gets through TLS
+        // and fails afterwards.
+        let mismatched = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let fake_transport_connector = ConnectFn(move |(), _, _| {
+            std::future::ready(
+                if mismatched.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                    Ok(())
+                } else {
+                    Err(WebSocketConnectError::Transport(
+                        TransportConnectError::CertificatePinMismatch,
+                    ))
+                },
+            )
+        });
+
+        let state = ConnectState {
+            connect_timeout: Duration::MAX,
+            route_resolver: RouteResolver::default(),
+            attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            https_attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            route_history: RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS)),
+            tunneled_route_history: RouteHistory::new(ConnectionOutcomes::new(
+                SUGGESTED_CONNECT_PARAMS,
+            )),
+            make_transport_connector: fake_transport_connector,
+            route_provider_context: Default::default(),
+            telemetry: None,
+        }
+        .into();
+
+        let result = ConnectState::connect_ws(
+            &state,
+            vec![first_route, second_route],
+            (),
+            ws_connector,
+            &resolver,
+            None,
+            "test".into(),
+        )
+        .await;
+
+        assert_matches!(
+            result,
+            Err(TimeoutOr::Other(ConnectError::FatalConnect(e))) if e.is_certificate_pin_mismatch()
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn connect_ws_timeout() {
         let ws_connector = crate::infra::ws::Stateless;
@@ -496,8 +966,14 @@ mod test {
             connect_timeout: CONNECT_TIMEOUT,
             route_resolver: RouteResolver::default(),
             attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            https_attempts_record: ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS),
+            route_history: RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_CONNECT_PARAMS)),
+            tunneled_route_history: RouteHistory::new(ConnectionOutcomes::new(
+                SUGGESTED_CONNECT_PARAMS,
+            )),
             make_transport_connector: always_hangs_connector,
             route_provider_context: Default::default(),
+            telemetry: None,
         }
         .into();
 
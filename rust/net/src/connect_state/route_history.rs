@@ -0,0 +1,415 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Route outcome history that survives app restarts.
+//!
+//! [`ConnectState`] delays routes that recently failed, but that record is
+//! keyed by resolved route and lives only in memory. This module keeps a
+//! second record keyed by [`UnresolvedRouteDescription`] that can be saved to
+//! and loaded from a [`RouteHistoryStore`], so a freshly started app doesn't
+//! re-probe every route that was blocked last time.
+//!
+//! [`ConnectState`]: super::ConnectState
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use libsignal_net_infra::route::{
+    ConnectionOutcomes, RouteDelayPolicy, SavedOutcome, UnresolvedRouteDescription,
+    WithLoggableDescription,
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+/// Persistent storage for route outcome history.
+///
+/// The history is an opaque blob; implementations only need to store the
+/// most recently saved value.
+pub trait RouteHistoryStore: Send + Sync {
+    /// Returns the most recently saved history, if any.
+    fn load(&self) -> Option<Vec<u8>>;
+
+    /// Replaces the saved history.
+    ///
+    /// Called after every connection attempt that changed the history, on a
+    /// thread where blocking is allowed.
+    fn save(&self, history: Vec<u8>);
+}
+
+/// A [`RouteHistoryStore`] that keeps the history in a file.
+///
+/// Failures to read or write are logged and otherwise treated as having no
+/// history.
+#[derive(Debug)]
+pub struct FileRouteHistoryStore {
+    path: PathBuf,
+}
+
+impl FileRouteHistoryStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl RouteHistoryStore for FileRouteHistoryStore {
+    fn load(&self) -> Option<Vec<u8>> {
+        match std::fs::read(&self.path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("failed to read saved route history: {e}");
+                None
+            }
+        }
+    }
+
+    fn save(&self, history: Vec<u8>) {
+        // Write to a temporary file first so that a crash can't leave a
+        // partially written history behind.
+        let temp_path = self.path.with_extension("tmp");
+        if let Err(e) = std::fs::write(&temp_path, history)
+            .and_then(|()| std::fs::rename(&temp_path, &self.path))
+        {
+            log::warn!("failed to write route history: {e}");
+        }
+    }
+}
+
+/// Route failures keyed by [`UnresolvedRouteDescription::persistent_key`].
+pub(super) struct RouteHistory {
+    outcomes: ConnectionOutcomes<String>,
+    store: Option<Arc<OrderedStore>>,
+    /// Sequence number for the next [`RouteHistorySnapshot`].
+    next_snapshot: u64,
+}
+
+/// A [`RouteHistoryStore`] that ignores snapshots older than the last one
+/// saved.
+struct OrderedStore {
+    store: Arc<dyn RouteHistoryStore>,
+    /// The sequence number of the last snapshot saved, held while saving.
+    last_saved: Mutex<Option<u64>>,
+}
+
+/// A serialized [`RouteHistory`] waiting to be saved.
+///
+/// Saving is left to the caller so that it can happen after releasing any
+/// locks guarding the history.
+#[must_use]
+pub(super) struct RouteHistorySnapshot {
+    store: Arc<OrderedStore>,
+    sequence: u64,
+    history: Vec<u8>,
+}
+
+impl RouteHistorySnapshot {
+    /// Saves the snapshot, unless a later one has already been saved.
+    pub(super) fn save(self) {
+        let Self {
+            store,
+            sequence,
+            history,
+        } = self;
+        let mut last_saved = store.last_saved.lock().expect("not poisoned");
+        if last_saved.is_some_and(|last_saved| last_saved >= sequence) {
+            return;
+        }
+        store.store.save(history);
+        *last_saved = Some(sequence);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRouteHistory {
+    routes: Vec<SavedRoute>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRoute {
+    route: String,
+    last_failure_unix_millis: u64,
+    failure_count: u8,
+}
+
+impl RouteHistory {
+    pub(super) fn new(outcomes: ConnectionOutcomes<String>) -> Self {
+        Self {
+            outcomes,
+            store: None,
+            next_snapshot: 0,
+        }
+    }
+
+    /// Loads any history from `store`, which will also receive all future
+    /// updates.
+    pub(super) fn set_store(&mut self, store: Option<Arc<dyn RouteHistoryStore>>) {
+        if let Some(saved) = store.as_ref().and_then(|store| store.load()) {
+            match serde_json::from_slice::<SavedRouteHistory>(&saved) {
+                Ok(SavedRouteHistory { routes }) => self.outcomes.load_saved_outcomes(
+                    routes.into_iter().map(
+                        |SavedRoute {
+                             route,
+                             last_failure_unix_millis,
+                             failure_count,
+                         }| {
+                            (
+                                route,
+                                SavedOutcome {
+                                    last_failure: SystemTime::UNIX_EPOCH
+                                        + Duration::from_millis(last_failure_unix_millis),
+                                    failure_count,
+                                },
+                            )
+                        },
+                    ),
+                    Instant::now(),
+                    SystemTime::now(),
+                ),
+                Err(_) => log::warn!("ignoring unreadable saved route history"),
+            }
+        }
+        self.store = store.map(|store| {
+            Arc::new(OrderedStore {
+                store,
+                last_saved: Mutex::new(None),
+            })
+        });
+    }
+
+    /// Records `updates`, returning a snapshot to save if there's a store.
+    pub(super) fn apply_outcome_updates(
+        &mut self,
+        updates: impl IntoIterator<
+            Item = (
+                UnresolvedRouteDescription,
+                libsignal_net_infra::route::AttemptOutcome,
+            ),
+        >,
+        finished_at: Instant,
+    ) -> Option<RouteHistorySnapshot> {
+        self.outcomes.apply_outcome_updates(
+            updates
+                .into_iter()
+                .map(|(description, outcome)| (description.persistent_key(), outcome)),
+            finished_at,
+        );
+
+        let store = self.store.clone()?;
+        let routes = self
+            .outcomes
+            .saved_outcomes(Instant::now(), SystemTime::now())
+            .map(
+                |(
+                    route,
+                    SavedOutcome {
+                        last_failure,
+                        failure_count,
+                    },
+                )| SavedRoute {
+                    route: route.clone(),
+                    last_failure_unix_millis: last_failure
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis()
+                        .try_into()
+                        .unwrap_or(u64::MAX),
+                    failure_count,
+                },
+            )
+            .collect();
+        let history =
+            serde_json::to_vec(&SavedRouteHistory { routes }).expect("can serialize to JSON");
+        let sequence = self.next_snapshot;
+        self.next_snapshot += 1;
+        Some(RouteHistorySnapshot {
+            store,
+            sequence,
+            history,
+        })
+    }
+
+    pub(super) fn network_changed(&mut self, network_change_time: Instant) {
+        self.outcomes.reset(network_change_time);
+    }
+}
+
+/// Delays a route by the longer of the delays from the in-memory record for
+/// the resolved route and the history for its description.
+pub(super) struct WithRouteHistory<'a, P> {
+    pub(super) live: P,
+    pub(super) history: &'a RouteHistory,
+}
+
+impl<P, R> RouteDelayPolicy<WithLoggableDescription<R, UnresolvedRouteDescription>>
+    for WithRouteHistory<'_, P>
+where
+    P: RouteDelayPolicy<WithLoggableDescription<R, UnresolvedRouteDescription>>,
+{
+    fn compute_delay(
+        &self,
+        route: &WithLoggableDescription<R, UnresolvedRouteDescription>,
+        now: Instant,
+    ) -> Duration {
+        let live_delay = self.live.compute_delay(route, now);
+        let history_delay = self
+            .history
+            .outcomes
+            .compute_delay(&route.description.persistent_key(), now);
+        live_delay.max(history_delay)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use libsignal_net_infra::route::{AttemptOutcome, UnsuccessfulOutcome};
+
+    use super::*;
+    use crate::connect_state::{SUGGESTED_CONNECT_PARAMS, SUGGESTED_ROUTE_HISTORY_PARAMS};
+
+    #[derive(Default)]
+    struct InMemoryStore(Mutex<Option<Vec<u8>>>);
+
+    impl RouteHistoryStore for InMemoryStore {
+        fn load(&self) -> Option<Vec<u8>> {
+            self.0.lock().expect("not poisoned").clone()
+        }
+
+        fn save(&self, history: Vec<u8>) {
+            *self.0.lock().expect("not poisoned") = Some(history);
+        }
+    }
+
+    #[test]
+    fn history_survives_restart() {
+        let store = Arc::new(InMemoryStore::default());
+        let route = UnresolvedRouteDescription::fake();
+        let key = route.persistent_key();
+
+        let mut history =
+            RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_ROUTE_HISTORY_PARAMS));
+        history.set_store(Some(store.clone()));
+        let now = Instant::now();
+        let snapshot = history
+            .apply_outcome_updates(
+                [(
+                    route.clone(),
+                    AttemptOutcome {
+                        started: now,
+                        result: Err(UnsuccessfulOutcome),
+                    },
+                )],
+                now,
+            )
+            .expect("has store");
+        assert!(store.load().is_none(), "not saved until asked");
+        snapshot.save();
+        assert!(store.load().is_some());
+
+        let mut restarted =
+            RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_ROUTE_HISTORY_PARAMS));
+        assert_eq!(
+            restarted.outcomes.compute_delay(&key, Instant::now()),
+            Duration::ZERO
+        );
+        restarted.set_store(Some(store));
+        assert_ne!(
+            restarted.outcomes.compute_delay(&key, Instant::now()),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn older_snapshots_are_not_saved_over_newer_ones() {
+        let store = Arc::new(InMemoryStore::default());
+        let mut history =
+            RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_ROUTE_HISTORY_PARAMS));
+        history.set_store(Some(store.clone()));
+
+        let now = Instant::now();
+        let mut snapshot = |result| {
+            history
+                .apply_outcome_updates(
+                    [(
+                        UnresolvedRouteDescription::fake(),
+                        AttemptOutcome {
+                            started: now,
+                            result,
+                        },
+                    )],
+                    now,
+                )
+                .expect("has store")
+        };
+        let failed = snapshot(Err(UnsuccessfulOutcome));
+        let succeeded = snapshot(Ok(()));
+
+        succeeded.save();
+        let saved = store.load();
+        failed.save();
+        assert_eq!(store.load(), saved);
+    }
+
+    #[test]
+    fn unreadable_history_is_ignored() {
+        let store = Arc::new(InMemoryStore(Mutex::new(Some(b"not json".to_vec()))));
+        let mut history =
+            RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_ROUTE_HISTORY_PARAMS));
+        history.set_store(Some(store));
+        assert_eq!(
+            history.outcomes.compute_delay(
+                &UnresolvedRouteDescription::fake().persistent_key(),
+                Instant::now()
+            ),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn saved_failures_outlive_the_live_cutoff() {
+        let key = UnresolvedRouteDescription::fake().persistent_key();
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(60 * 60);
+        assert!(Duration::from_secs(60 * 60) > SUGGESTED_CONNECT_PARAMS.age_cutoff);
+        let saved = serde_json::to_vec(&SavedRouteHistory {
+            routes: vec![SavedRoute {
+                route: key.clone(),
+                last_failure_unix_millis: an_hour_ago
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .expect("after epoch")
+                    .as_millis()
+                    .try_into()
+                    .expect("fits"),
+                failure_count: 3,
+            }],
+        })
+        .expect("can serialize");
+        let store = Arc::new(InMemoryStore(Mutex::new(Some(saved))));
+
+        let mut history =
+            RouteHistory::new(ConnectionOutcomes::new(SUGGESTED_ROUTE_HISTORY_PARAMS));
+        history.set_store(Some(store));
+        assert_ne!(
+            history.outcomes.compute_delay(&key, Instant::now()),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "libsignal-route-history-test-{}",
+            std::process::id()
+        ));
+        let store = FileRouteHistoryStore::new(path.clone());
+        assert_eq!(store.load(), None);
+
+        store.save(b"history".to_vec());
+        assert_eq!(store.load(), Some(b"history".to_vec()));
+
+        std::fs::remove_file(path).expect("was saved");
+    }
+}
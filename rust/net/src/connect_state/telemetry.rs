@@ -0,0 +1,321 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Structured records of the connection attempts made by [`ConnectState`].
+//!
+//! [`ConnectState::connect_ws`] already logs which routes it tried and how
+//! they failed; this module exposes the same information as data so that apps
+//! can aggregate it, e.g. to detect censorship or track route reliability.
+//!
+//! [`ConnectState`]: super::ConnectState
+//! [`ConnectState::connect_ws`]: super::ConnectState::connect_ws
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use futures_util::{FutureExt as _, Stream};
+use http::HeaderName;
+use libsignal_net_infra::connection_manager::{ErrorClass, ErrorClassifier as _};
+use libsignal_net_infra::dns::lookup_result::LookupResult;
+use libsignal_net_infra::dns::service_binding::ServiceBinding;
+use libsignal_net_infra::dns::DnsError;
+use libsignal_net_infra::errors::TransportConnectError;
+use libsignal_net_infra::route::{
+    Connector, HttpRouteFragment, HttpsTlsRoute, ResolvedRoute as _, Resolver, TransportRoute,
+    UnresolvedRouteDescription, WebSocketRoute, WebSocketRouteFragment, WebSocketServiceRoute,
+    WithLoggableDescription,
+};
+use libsignal_net_infra::ws::WebSocketConnectError;
+use libsignal_net_infra::{DnsSource, IpType};
+use tokio::time::Instant;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use super::RouteInfo;
+use crate::ws::WebSocketServiceConnectError;
+
+/// Receives a record for every connection attempt once it finishes.
+pub type TelemetryCallback = Arc<dyn Fn(ConnectionAttemptRecord) + Send + Sync>;
+
+/// A single attempt to connect over one resolved route.
+#[derive(Clone, Debug)]
+pub struct ConnectionAttemptRecord {
+    /// Log-safe description of the route.
+    pub route: RouteInfo,
+    /// IP version of the address that was connected to directly.
+    ///
+    /// For proxied routes this is the address of the proxy.
+    pub ip_type: IpType,
+    /// Where the address came from, or `None` if the route used an IP literal.
+    pub dns_source: Option<DnsSource>,
+    pub started_at: SystemTime,
+    /// Time from the start of the attempt until the TLS handshake finished, if
+    /// it did.
+    ///
+    /// This includes connecting over TCP and through any proxy, which the
+    /// transport connector does as part of the same step.
+    pub tls_handshake_completed_after: Option<Duration>,
+    /// Time taken by the websocket upgrade request, if the TLS handshake
+    /// finished and the server responded.
+    pub websocket_upgrade_duration: Option<Duration>,
+    /// Time from the start of the attempt until it finished or was abandoned.
+    pub duration: Duration,
+    pub result: AttemptResult,
+}
+
+/// How a connection attempt ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttemptResult {
+    Succeeded,
+    /// The attempt failed, but other routes might still work.
+    Failed {
+        error: AttemptError,
+    },
+    /// The server rejected the connection, so no other routes will be tried.
+    Rejected {
+        error: AttemptError,
+    },
+    /// The attempt was still in progress when the overall connection attempt
+    /// finished, either because another route succeeded or because it timed
+    /// out.
+    Abandoned,
+}
+
+/// The stage at which a connection attempt failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttemptError {
+    /// A hostname couldn't be resolved.
+    Dns,
+    /// The TCP connection couldn't be established.
+    Tcp,
+    /// The TLS handshake failed.
+    Tls,
+    /// The server's certificate chain didn't include any pinned key.
+    CertificatePinMismatch,
+    /// The proxy couldn't be connected through.
+    Proxy,
+    /// The attempt took too long.
+    Timeout,
+    /// The websocket upgrade request got this HTTP status in response.
+    HttpStatus(u16),
+    /// The websocket upgrade failed without an HTTP response.
+    WebSocket,
+    /// The route couldn't be attempted, e.g. because it was misconfigured.
+    Other,
+}
+
+impl From<&WebSocketServiceConnectError> for AttemptError {
+    fn from(error: &WebSocketServiceConnectError) -> Self {
+        match error {
+            WebSocketServiceConnectError::RejectedByServer { response, .. } => {
+                Self::HttpStatus(response.status().as_u16())
+            }
+            WebSocketServiceConnectError::Connect(error, _) => match error {
+                WebSocketConnectError::Transport(error) => match error {
+                    TransportConnectError::DnsError => Self::Dns,
+                    TransportConnectError::TcpConnectionFailed => Self::Tcp,
+                    TransportConnectError::SslError(_)
+                    | TransportConnectError::SslFailedHandshake(_)
+                    | TransportConnectError::EchRejected { .. }
+                    | TransportConnectError::CertError => Self::Tls,
+                    TransportConnectError::CertificatePinMismatch => Self::CertificatePinMismatch,
+                    TransportConnectError::ProxyProtocol => Self::Proxy,
+                    TransportConnectError::InvalidConfiguration
+                    | TransportConnectError::ClientAbort => Self::Other,
+                },
+                WebSocketConnectError::Timeout => Self::Timeout,
+                WebSocketConnectError::WebSocketError(tungstenite::Error::Http(response)) => {
+                    Self::HttpStatus(response.status().as_u16())
+                }
+                WebSocketConnectError::WebSocketError(_) => Self::WebSocket,
+            },
+        }
+    }
+}
+
+/// Returns a [`TelemetryCallback`] that forwards records to a [`Stream`].
+pub fn telemetry_stream() -> (
+    TelemetryCallback,
+    impl Stream<Item = ConnectionAttemptRecord>,
+) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let callback: TelemetryCallback = Arc::new(move |record| {
+        // If the stream was dropped, nobody's interested anymore.
+        let _ignore_closed = tx.send(record);
+    });
+    (callback, UnboundedReceiverStream::new(rx))
+}
+
+/// A [`Resolver`] that remembers where each returned address came from.
+pub(super) struct RecordingResolver<'a, R> {
+    inner: &'a R,
+    sources: Mutex<HashMap<IpAddr, DnsSource>>,
+}
+
+impl<'a, R> RecordingResolver<'a, R> {
+    pub(super) fn new(inner: &'a R) -> Self {
+        Self {
+            inner,
+            sources: Default::default(),
+        }
+    }
+
+    fn source_for(&self, addr: &IpAddr) -> Option<DnsSource> {
+        self.sources
+            .lock()
+            .expect("not poisoned")
+            .get(addr)
+            .copied()
+    }
+}
+
+impl<R: Resolver + Sync> Resolver for RecordingResolver<'_, R> {
+    fn lookup_ip(
+        &self,
+        hostname: &str,
+    ) -> impl Future<Output = Result<LookupResult, DnsError>> + Send {
+        self.inner.lookup_ip(hostname).map(|result| {
+            if let Ok(lookup) = &result {
+                let mut sources = self.sources.lock().expect("not poisoned");
+                sources.extend(lookup.into_iter().map(|addr| (addr, lookup.source())));
+            }
+            result
+        })
+    }
+
+    fn lookup_service_binding(
+        &self,
+        hostname: &str,
+    ) -> impl Future<Output = Option<ServiceBinding>> + Send {
+        self.inner.lookup_service_binding(hostname)
+    }
+}
+
+/// Connects websocket routes like [`ComposedConnector`] does, timing each
+/// stage and reporting the attempt to a [`TelemetryCallback`].
+///
+/// Errors are converted to [`WebSocketServiceConnectError`] here rather than in
+/// the caller so that the record can include how the failure was classified.
+///
+/// [`ComposedConnector`]: libsignal_net_infra::route::ComposedConnector
+pub(super) struct InstrumentedConnector<'a, WC, TC, R> {
+    pub(super) ws_connector: WC,
+    pub(super) transport_connector: TC,
+    pub(super) confirmation_header_name: Option<&'a HeaderName>,
+    pub(super) resolver: &'a RecordingResolver<'a, R>,
+    pub(super) telemetry: Option<&'a TelemetryCallback>,
+}
+
+impl<WC, TC, R, Inner>
+    Connector<WithLoggableDescription<WebSocketServiceRoute, UnresolvedRouteDescription>, Inner>
+    for InstrumentedConnector<'_, WC, TC, R>
+where
+    WC: Connector<
+            (WebSocketRouteFragment, HttpRouteFragment),
+            TC::Connection,
+            Error = tungstenite::Error,
+        > + Sync,
+    TC: Connector<TransportRoute, Inner, Error: Into<WebSocketConnectError>> + Sync,
+    R: Sync,
+    Inner: Send,
+{
+    type Connection = (WC::Connection, UnresolvedRouteDescription);
+    type Error = WebSocketServiceConnectError;
+
+    fn connect_over(
+        &self,
+        over: Inner,
+        route: WithLoggableDescription<WebSocketServiceRoute, UnresolvedRouteDescription>,
+        log_tag: Arc<str>,
+    ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+        let Self {
+            ws_connector,
+            transport_connector,
+            confirmation_header_name,
+            resolver,
+            telemetry,
+        } = self;
+        let WithLoggableDescription {
+            route:
+                WebSocketRoute {
+                    fragment: ws_fragment,
+                    inner:
+                        HttpsTlsRoute {
+                            fragment: http_fragment,
+                            inner: transport_route,
+                        },
+                },
+            description,
+        } = route;
+
+        let target = *transport_route.immediate_target();
+        let record = ConnectionAttemptRecord {
+            route: RouteInfo {
+                unresolved: description.clone(),
+            },
+            ip_type: IpType::from(&target),
+            dns_source: resolver.source_for(&target),
+            started_at: SystemTime::now(),
+            tls_handshake_completed_after: None,
+            websocket_upgrade_duration: None,
+            duration: Duration::ZERO,
+            result: AttemptResult::Abandoned,
+        };
+
+        async move {
+            let start = Instant::now();
+            // Report the attempt even if this future is dropped before it
+            // completes.
+            let mut record = scopeguard::guard(record, |mut record| {
+                record.duration = start.elapsed();
+                if let Some(telemetry) = telemetry {
+                    telemetry(record)
+                }
+            });
+
+            let result = async {
+                let transport = transport_connector
+                    .connect_over(over, transport_route, log_tag.clone())
+                    .await
+                    .map_err(Into::into)?;
+                let tls_handshake_completed_after = start.elapsed();
+                record.tls_handshake_completed_after = Some(tls_handshake_completed_after);
+                let result = ws_connector
+                    .connect_over(transport, (ws_fragment, http_fragment), log_tag)
+                    .await;
+                record.websocket_upgrade_duration = Some(
+                    start
+                        .elapsed()
+                        .saturating_sub(tls_handshake_completed_after),
+                );
+                result.map_err(WebSocketConnectError::from)
+            }
+            .await
+            .map_err(|error| {
+                WebSocketServiceConnectError::from_websocket_error(
+                    error,
+                    *confirmation_header_name,
+                    Instant::now(),
+                )
+            });
+
+            record.result = match &result {
+                Ok(_) => AttemptResult::Succeeded,
+                Err(error) => match error.classify() {
+                    ErrorClass::Intermittent => AttemptResult::Failed {
+                        error: error.into(),
+                    },
+                    ErrorClass::Fatal | ErrorClass::RetryAt(_) => AttemptResult::Rejected {
+                        error: error.into(),
+                    },
+                },
+            };
+
+            result.map(|connection| (connection, description))
+        }
+    }
+}
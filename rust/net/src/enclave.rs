@@ -347,6 +347,7 @@ impl<E: EnclaveKind> EnclaveEndpoint<'_, E> {
             ws_config: Default::default(),
             endpoint: E::url_path(params.mr_enclave.as_ref()),
             headers: Default::default(),
+            deflate: None,
         };
 
         WebSocketProvider::new(ws_fragment, http_provider)
@@ -370,6 +371,7 @@ async fn connect_attested<C: ConnectionManager, T: TransportConnector>(
             ws_config: endpoint_connection.config.ws_config,
             endpoint: endpoint_connection.config.endpoint.clone(),
             headers: HeaderMap::from_iter([auth.as_header()]),
+            deflate: None,
         },
         endpoint_connection.config.max_connection_time,
     );
@@ -599,6 +601,7 @@ mod test {
                 tcp_host: Host::Domain("fake".into()),
                 port: nonzero!(1234u16),
                 certs: libsignal_net_infra::certs::RootCertificates::Native,
+                pin_set: None,
             },
             http_request_decorator: HttpRequestDecoratorSeq::default(),
             http_host: Arc::from("fake-http"),
@@ -11,9 +11,11 @@ use std::sync::Arc;
 
 use const_str::ip_addr;
 use hex_literal::hex;
+use http::uri::PathAndQuery;
 use http::HeaderValue;
+use libsignal_core::Aci;
 use libsignal_keytrans::{DeploymentMode, PublicConfig, VerifyingKey, VrfPublicKey};
-use libsignal_net_infra::certs::RootCertificates;
+use libsignal_net_infra::certs::{RootCertificates, SpkiPinSet};
 use libsignal_net_infra::dns::lookup_result::LookupResult;
 use libsignal_net_infra::host::Host;
 use libsignal_net_infra::route::{
@@ -28,7 +30,10 @@ use nonzero_ext::nonzero;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
 
-use crate::certs::{PROXY_G_ROOT_CERTIFICATES, SIGNAL_ROOT_CERTIFICATES};
+use crate::certs::{
+    CDN_SPKI_PINS, PROXY_G_ROOT_CERTIFICATES, SIGNAL_ROOT_CERTIFICATES, SIGNAL_SPKI_PINS,
+};
+use crate::chat::noise::{Authorization, NoiseRoute};
 use crate::enclave::{
     Cdsi, EnclaveEndpoint, EndpointParams, MrEnclave, Nitro, Sgx, SgxPreQuantum, Tpm2Snp,
 };
@@ -49,11 +54,13 @@ const DOMAIN_CONFIG_CHAT: DomainConfig = DomainConfig {
         hostname: "chat.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: Some(TIMESTAMP_HEADER_NAME),
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/service",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
 };
 
@@ -70,11 +77,13 @@ const DOMAIN_CONFIG_CHAT_STAGING: DomainConfig = DomainConfig {
         hostname: "chat.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: Some(TIMESTAMP_HEADER_NAME),
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/service-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
 };
 
@@ -83,11 +92,13 @@ const DOMAIN_CONFIG_CDSI: DomainConfig = DomainConfig {
         hostname: "cdsi.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/cdsi",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "40.122.45.194")],
     ip_v6: &[ip_addr!(v6, "2603:1030:7::1")],
@@ -98,11 +109,13 @@ const DOMAIN_CONFIG_CDSI_STAGING: DomainConfig = DomainConfig {
         hostname: "cdsi.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/cdsi-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "104.43.162.137")],
     ip_v6: &[ip_addr!(v6, "2603:1030:7::732")],
@@ -113,11 +126,13 @@ const DOMAIN_CONFIG_SVR2: DomainConfig = DomainConfig {
         hostname: "svr2.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr2",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "20.66.40.69")],
     ip_v6: &[],
@@ -128,11 +143,13 @@ const DOMAIN_CONFIG_SVR2_STAGING: DomainConfig = DomainConfig {
         hostname: "svr2.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr2-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "20.253.229.239")],
     ip_v6: &[],
@@ -143,11 +160,13 @@ const DOMAIN_CONFIG_SVR3_SGX: DomainConfig = DomainConfig {
         hostname: "backend1.svr3.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-sgx",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "40.112.138.96")],
     ip_v6: &[],
@@ -158,11 +177,13 @@ const DOMAIN_CONFIG_SVR3_SGX_STAGING: DomainConfig = DomainConfig {
         hostname: "backend1.svr3.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-sgx-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "13.88.63.29")],
     ip_v6: &[],
@@ -173,11 +194,13 @@ const DOMAIN_CONFIG_SVR3_NITRO: DomainConfig = DomainConfig {
         hostname: "backend2.svr3.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-nitro",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "75.2.91.98")],
     ip_v6: &[],
@@ -188,11 +211,13 @@ const DOMAIN_CONFIG_SVR3_NITRO_STAGING: DomainConfig = DomainConfig {
         hostname: "backend2.svr3.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-nitro-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "75.2.86.85"), ip_addr!(v4, "99.83.239.137")],
     ip_v6: &[],
@@ -203,11 +228,13 @@ pub const DOMAIN_CONFIG_SVR3_TPM2SNP: DomainConfig = DomainConfig {
         hostname: "backend3.svr3.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-tpm2snp",
             configs: [PROXY_CONFIG_F_PROD, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "34.144.241.251")],
     ip_v6: &[],
@@ -218,16 +245,34 @@ pub const DOMAIN_CONFIG_SVR3_TPM2SNP_STAGING: DomainConfig = DomainConfig {
         hostname: "backend3.svr3.staging.signal.org",
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
+        pin_set: Some(SIGNAL_SPKI_PINS),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
             path_prefix: "/svr3-tpm2snp-staging",
             configs: [PROXY_CONFIG_F_STAGING, PROXY_CONFIG_G],
         }),
+        noise: None,
     },
     ip_v4: &[ip_addr!(v4, "13.88.30.76")],
     ip_v6: &[],
 };
 
+/// The attachment CDN hosts, which are reached with [`RootCertificates::Native`].
+pub const CDN_HOSTNAMES: &[&str] = &[
+    "cdn.signal.org",
+    "cdn2.signal.org",
+    "cdn3.signal.org",
+    "cdn-staging.signal.org",
+    "cdn2-staging.signal.org",
+    "cdn3-staging.signal.org",
+];
+
+/// The pins to require when connecting to `hostname`, if it is one of the
+/// [`CDN_HOSTNAMES`].
+pub fn cdn_pin_set(hostname: &str) -> Option<SpkiPinSet> {
+    CDN_HOSTNAMES.contains(&hostname).then_some(CDN_SPKI_PINS)
+}
+
 pub const PROXY_CONFIG_F_PROD: ProxyConfig = ProxyConfig {
     route_type: RouteType::ProxyF,
     http_host: "reflector-signal.global.ssl.fastly.net",
@@ -337,6 +382,17 @@ pub struct ConnectionConfig {
     pub port: NonZeroU16,
     /// Which certificates to use when connecting to the resource.
     pub cert: RootCertificates,
+    /// Public keys that the resource's certificate chain must include.
+    ///
+    /// This is enforced on the TLS session with the resource itself, including
+    /// when it is tunneled through a SOCKS, HTTP, or TLS proxy. Domain-fronted
+    /// routes end TLS at the front instead, so they aren't pinned.
+    ///
+    /// A pin on a root that is already the only one in [`Self::cert`] only
+    /// matters if that list grows. With [`RootCertificates::Native`], pinned
+    /// roots have to be listed as [`SpkiPinSet::anchors`] too, since servers
+    /// don't send them.
+    pub pin_set: Option<SpkiPinSet>,
     /// A header to look for that indicates that the resource was reached.
     ///
     /// If this is `Some()`, then the presence of the header in an HTTP response
@@ -347,6 +403,61 @@ pub struct ConnectionConfig {
     /// Additional configuration for connecting to the resource through a proxy
     /// if a direct connection fails.
     pub proxy: Option<ConnectionProxyConfig>,
+
+    /// A Noise-encrypted tunnel to try before the plain TLS websocket routes.
+    ///
+    /// If this is `None`, or the tunnel can't be established, websocket
+    /// connections are made directly over TLS.
+    pub noise: Option<NoiseConnectionConfig>,
+}
+
+/// Configuration for reaching a resource's websocket through a Noise tunnel.
+#[derive(Clone, Debug)]
+pub struct NoiseConnectionConfig {
+    /// The path of the websocket that carries the Noise handshake and the
+    /// encrypted traffic.
+    pub endpoint: &'static str,
+    /// The server's static public key, which the handshake is pinned to.
+    pub server_public_key: [u8; 32],
+}
+
+impl NoiseConnectionConfig {
+    /// A route that connects to the server as an anonymous client.
+    pub fn anonymous_route(&self) -> NoiseRoute {
+        let Self {
+            endpoint,
+            server_public_key,
+        } = self;
+        NoiseRoute {
+            endpoint: PathAndQuery::from_static(endpoint),
+            authorization: Authorization::Anonymous {
+                server_public_key: *server_public_key,
+            },
+        }
+    }
+
+    /// A route that identifies the client to the server as `aci`'s device
+    /// `device_id`.
+    pub fn authenticated_route(
+        &self,
+        aci: Aci,
+        device_id: u8,
+        client_private_key: [u8; 32],
+    ) -> NoiseRoute {
+        let Self {
+            endpoint,
+            server_public_key,
+        } = self;
+        NoiseRoute {
+            endpoint: PathAndQuery::from_static(endpoint),
+            authorization: Authorization::Authenticated {
+                aci,
+                device_id,
+                server_public_key: *server_public_key,
+                client_private_key,
+            },
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -383,6 +494,7 @@ impl ConnectionConfig {
                     tcp_host: Host::Domain(Arc::clone(&hostname)),
                     port: self.port,
                     certs: self.cert.clone(),
+                    pin_set: self.pin_set.clone(),
                 },
                 http_host: hostname,
                 http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -422,8 +534,10 @@ impl ConnectionConfig {
             hostname,
             port,
             cert,
+            pin_set,
             confirmation_header_name: _,
             proxy,
+            noise: _,
         } = self;
         let domain_front_configs = proxy
             .as_ref()
@@ -467,7 +581,8 @@ impl ConnectionConfig {
                 cert.clone(),
                 Host::Domain(Arc::clone(&hostname)),
                 DirectTcpRouteProvider::new(hostname, *port),
-            ),
+            )
+            .with_pin_set(pin_set.clone()),
         )
     }
 }
@@ -539,6 +654,7 @@ impl ProxyConfig {
                     tcp_host: Host::Domain(sni_and_dns_host),
                     port: nonzero!(443u16),
                     certs: certs.clone(),
+                    pin_set: None,
                 },
                 http_host: Arc::clone(&http_host),
                 http_request_decorator: HttpRequestDecorator::PathPrefix(proxy_path).into(),
@@ -581,6 +697,13 @@ pub struct Env<'a, Svr3> {
     pub chat_domain_config: DomainConfig,
     // TODO: make non-optional when the public endpoints are up
     pub keytrans_config: Option<KeyTransConfig>,
+    /// Whether to look up DNS HTTPS records for these servers, which can
+    /// provide Encrypted Client Hello configs.
+    ///
+    /// See [`DnsResolver::set_service_binding_lookup_enabled`].
+    ///
+    /// [`DnsResolver::set_service_binding_lookup_enabled`]: libsignal_net_infra::dns::DnsResolver::set_service_binding_lookup_enabled
+    pub service_binding_lookup: bool,
 }
 
 impl<'a> Env<'a, Svr3Env<'a>> {
@@ -664,6 +787,7 @@ pub const STAGING: Env<'static, Svr3Env> = Env {
         vrf_key_material: KEYTRANS_VRF_KEY_MATERIAL_STAGING,
         auditor_key_material: KEYTRANS_AUDITOR_KEY_MATERIAL_STAGING,
     }),
+    service_binding_lookup: true,
 };
 
 pub const PROD: Env<'static, Svr3Env> = Env {
@@ -691,6 +815,7 @@ pub const PROD: Env<'static, Svr3Env> = Env {
         },
     ),
     keytrans_config: None,
+    service_binding_lookup: false,
 };
 
 pub mod constants {
@@ -763,6 +888,38 @@ mod test {
         }
     }
 
+    #[test]
+    fn every_production_domain_is_pinned() {
+        let now = std::time::SystemTime::now();
+        for env in [&PROD, &STAGING] {
+            let configs = [
+                &env.chat_domain_config,
+                &env.cdsi.domain_config,
+                &env.svr2.domain_config,
+                &env.svr3.sgx().domain_config,
+                &env.svr3.nitro().domain_config,
+                &env.svr3.tpm2snp().domain_config,
+            ];
+            for config in configs {
+                let hostname = config.connect.hostname;
+                let pin_set = config
+                    .connect
+                    .pin_set
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("{hostname} has no pins"));
+                assert!(!pin_set.primary.is_empty(), "{hostname}");
+                assert!(!pin_set.is_expired(now), "{hostname}");
+            }
+        }
+        for hostname in CDN_HOSTNAMES {
+            let pin_set = cdn_pin_set(hostname).unwrap_or_else(|| panic!("{hostname} has no pins"));
+            assert!(!pin_set.primary.is_empty(), "{hostname}");
+            assert!(!pin_set.backup.is_empty(), "{hostname}");
+            assert!(!pin_set.is_expired(now), "{hostname}");
+        }
+        assert_eq!(cdn_pin_set("example.com"), None);
+    }
+
     #[test_matrix([true, false])]
     fn connect_config_routes_enable_domain_fronting(enable_domain_fronting: bool) {
         const PORT: NonZeroU16 = nonzero!(123u16);
@@ -770,6 +927,7 @@ mod test {
             hostname: "host",
             port: PORT,
             cert: RootCertificates::Native,
+            pin_set: None,
             confirmation_header_name: None,
             proxy: Some(ConnectionProxyConfig {
                 path_prefix: "proxy-prefix",
@@ -788,6 +946,7 @@ mod test {
                     },
                 ],
             }),
+            noise: None,
         };
         let route_provider =
             CONNECT_CONFIG.route_provider(EnableDomainFronting(enable_domain_fronting));
@@ -804,6 +963,8 @@ mod test {
                     root_certs: RootCertificates::Native,
                     sni: Host::Domain("host".into()),
                     alpn: Some(Alpn::Http1_1),
+                    ech_config_list: None,
+                    pin_set: None,
                 },
                 inner: TcpRoute {
                     address: UnresolvedHost::from(Arc::from("host")),
@@ -0,0 +1,406 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for plain HTTPS requests to Signal services.
+//!
+//! Connections are made with [`ConnectState::connect_https`], so they go
+//! through the same route selection, proxy, and outcome tracking as chat
+//! websocket connections.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::response::Parts;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, Method};
+use libsignal_net_infra::dns::DnsResolver;
+use libsignal_net_infra::errors::{LogSafeDisplay, TransportConnectError};
+use libsignal_net_infra::http_client::{
+    collect_response_body, request_body_from_bytes, HttpConnectError, HttpConnection, HttpError,
+    RequestBody, ResponseBody,
+};
+use libsignal_net_infra::route::{
+    ConnectError, Connector, ConnectorFactory, RouteProvider, TransportRoute,
+    UnresolvedHttpsServiceRoute,
+};
+use libsignal_net_infra::timeouts::TimeoutOr;
+use libsignal_net_infra::{AsHttpHeader as _, AsyncDuplexStream, Connection};
+
+use crate::auth::Auth;
+use crate::chat::ConnectionInfo;
+use crate::connect_state::ConnectState;
+use crate::env::UserAgent;
+
+/// A connection to a Signal service for making HTTPS requests.
+///
+/// Whether requests are sent with HTTP/1.1 or HTTP/2 is decided by the ALPN
+/// of the route the connection was made over. Either way, requests can be made
+/// concurrently from a shared reference.
+pub struct HttpsClient {
+    connection: HttpConnection,
+    default_headers: HeaderMap,
+    connection_info: ConnectionInfo,
+    log_tag: Arc<str>,
+}
+
+/// A request to send with [`HttpsClient::send`].
+#[derive(Debug)]
+pub struct HttpsRequest {
+    pub method: Method,
+    /// The path and query, relative to the path prefix of the route.
+    pub path: PathAndQuery,
+    /// Headers to send in addition to the client's default headers.
+    ///
+    /// A header here replaces a default header with the same name.
+    pub headers: HeaderMap,
+    pub body: RequestBody,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum HttpsClientError {
+    /// Timed out while establishing connection
+    TimeoutEstablishingConnection,
+    /// All connection routes failed or timed out
+    AllConnectionRoutesFailed,
+    /// Invalid connection configuration
+    InvalidConnectionConfiguration,
+    /// HTTP handshake failed
+    HttpHandshake,
+    /// Timed out while waiting for a response
+    RequestTimedOut,
+    /// HTTP error: {0}
+    Http(HttpError),
+}
+
+impl LogSafeDisplay for HttpsClientError {}
+
+impl HttpsClient {
+    /// Connects over one of the routes from `route_provider`.
+    ///
+    /// If provided, `auth` is sent as the `Authorization` header on every
+    /// request, along with `user_agent`.
+    pub async fn connect<TC>(
+        connect: &tokio::sync::RwLock<ConnectState<TC>>,
+        resolver: &DnsResolver,
+        route_provider: impl RouteProvider<Route = UnresolvedHttpsServiceRoute>,
+        auth: Option<Auth>,
+        user_agent: &UserAgent,
+        log_tag: &str,
+    ) -> Result<Self, HttpsClientError>
+    where
+        TC: ConnectorFactory<
+            TransportRoute,
+            (),
+            Connector: Sync + Connector<TransportRoute, (), Error = TransportConnectError>,
+            Connection: AsyncDuplexStream + Connection + 'static,
+        >,
+    {
+        let log_tag: Arc<str> = log_tag.into();
+        let (connection, route_info) =
+            ConnectState::connect_https(connect, route_provider, resolver, log_tag.clone())
+                .await
+                .map_err(HttpsClientError::from_connect_error)?;
+
+        let default_headers = HeaderMap::from_iter(
+            auth.iter()
+                .map(|auth| auth.as_header())
+                .chain([user_agent.as_header()]),
+        );
+        let connection_info = ConnectionInfo {
+            route_info,
+            transport_info: connection.transport_info(),
+        };
+
+        Ok(Self {
+            connection,
+            default_headers,
+            connection_info,
+            log_tag,
+        })
+    }
+
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.connection_info
+    }
+
+    /// The host that requests are addressed to.
+    pub fn host(&self) -> &str {
+        self.connection.host()
+    }
+
+    /// Sends a request and returns the response once its headers arrive.
+    ///
+    /// `timeout` limits how long to wait for the response headers; the body
+    /// is streamed afterwards and can be read at the caller's pace.
+    pub async fn send(
+        &self,
+        request: HttpsRequest,
+        timeout: Duration,
+    ) -> Result<http::Response<ResponseBody>, HttpsClientError> {
+        tokio::time::timeout(timeout, self.send_inner(request))
+            .await
+            .map_err(|_: tokio::time::error::Elapsed| {
+                log::info!("[{}] HTTPS request timed out", self.log_tag);
+                HttpsClientError::RequestTimedOut
+            })?
+    }
+
+    /// Sends a request and reads the entire response into memory.
+    ///
+    /// Unlike [`Self::send`], `timeout` covers reading the response body too.
+    pub async fn send_and_collect(
+        &self,
+        request: HttpsRequest,
+        timeout: Duration,
+        max_response_size: usize,
+    ) -> Result<(Parts, Bytes), HttpsClientError> {
+        let send_and_collect = async {
+            let (parts, body) = self.send_inner(request).await?.into_parts();
+            let content = collect_response_body(&parts, body, max_response_size)
+                .await
+                .map_err(HttpsClientError::Http)?;
+            Ok((parts, content))
+        };
+        tokio::time::timeout(timeout, send_and_collect)
+            .await
+            .map_err(|_: tokio::time::error::Elapsed| {
+                log::info!("[{}] HTTPS request timed out", self.log_tag);
+                HttpsClientError::RequestTimedOut
+            })?
+    }
+
+    async fn send_inner(
+        &self,
+        request: HttpsRequest,
+    ) -> Result<http::Response<ResponseBody>, HttpsClientError> {
+        let HttpsRequest {
+            method,
+            path,
+            headers,
+            body,
+        } = request;
+        let mut all_headers = self.default_headers.clone();
+        all_headers.extend(headers);
+
+        self.connection
+            .send_request(path, method, all_headers, body)
+            .await
+            .map_err(|e| {
+                log::info!("[{}] HTTPS request failed: {e}", self.log_tag);
+                HttpsClientError::Http(e)
+            })
+    }
+}
+
+impl HttpsRequest {
+    /// A request with no extra headers and an in-memory body.
+    pub fn new(method: Method, path: PathAndQuery, body: impl Into<Bytes>) -> Self {
+        Self {
+            method,
+            path,
+            headers: HeaderMap::new(),
+            body: request_body_from_bytes(body),
+        }
+    }
+}
+
+impl HttpsClientError {
+    fn from_connect_error(e: TimeoutOr<ConnectError<HttpConnectError>>) -> Self {
+        match e {
+            TimeoutOr::Other(ConnectError::NoResolvedRoutes) => {
+                Self::InvalidConnectionConfiguration
+            }
+            TimeoutOr::Other(ConnectError::AllAttemptsFailed) => Self::AllConnectionRoutesFailed,
+            TimeoutOr::Other(ConnectError::FatalConnect(e)) => match e {
+                // Transport errors aren't fatal, so they shouldn't end up here.
+                HttpConnectError::Transport(_) => Self::AllConnectionRoutesFailed,
+                HttpConnectError::HttpHandshake => Self::HttpHandshake,
+            },
+            TimeoutOr::Timeout {
+                attempt_duration: _,
+            } => Self::TimeoutEstablishingConnection,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::net::{Ipv6Addr, SocketAddr};
+    use std::num::NonZeroU16;
+    use std::sync::LazyLock;
+
+    use assert_matches::assert_matches;
+    use futures_util::StreamExt as _;
+    use libsignal_net_infra::certs::RootCertificates;
+    use libsignal_net_infra::dns::lookup_result::LookupResult;
+    use libsignal_net_infra::host::Host;
+    use libsignal_net_infra::http_client::request_body_from_stream;
+    use libsignal_net_infra::route::{
+        DirectOrProxyRoute, HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment,
+        UnresolvedHost,
+    };
+    use libsignal_net_infra::{Alpn, DnsSource};
+    use rcgen::CertifiedKey;
+    use test_case::test_case;
+    use warp::Filter as _;
+
+    use super::*;
+    use crate::connect_state::SUGGESTED_CONNECT_CONFIG;
+
+    const SERVER_HOSTNAME: &str = "rest.test-server.signal.org.local";
+    const SLOW_PATH: &str = "/slow";
+
+    static CERTIFICATE: LazyLock<CertifiedKey> = LazyLock::new(|| {
+        rcgen::generate_simple_self_signed([SERVER_HOSTNAME.to_string()]).expect("can generate")
+    });
+
+    /// Starts a server that echoes the request body, and reports the request's
+    /// path and `User-Agent` in response headers.
+    fn localhost_echo_server() -> SocketAddr {
+        let slow = warp::path("slow").then(|| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            "too late"
+        });
+        let echo = warp::body::bytes()
+            .and(warp::header::optional::<String>("user-agent"))
+            .and(warp::path::full())
+            .map(
+                |body: Bytes, user_agent: Option<String>, path: warp::path::FullPath| {
+                    warp::reply::with_header(
+                        warp::reply::with_header(
+                            body.to_vec(),
+                            "echo-user-agent",
+                            user_agent.unwrap_or_default(),
+                        ),
+                        "echo-path",
+                        path.as_str(),
+                    )
+                },
+            );
+        let (address, server) = warp::serve(slow.or(echo))
+            .tls()
+            .cert(CERTIFICATE.cert.pem())
+            .key(CERTIFICATE.key_pair.serialize_pem())
+            .bind_ephemeral((Ipv6Addr::LOCALHOST, 0));
+        tokio::spawn(server);
+        address
+    }
+
+    fn route_to(address: SocketAddr, alpn: Alpn) -> UnresolvedHttpsServiceRoute {
+        HttpsTlsRoute {
+            fragment: HttpRouteFragment {
+                host_header: SERVER_HOSTNAME.into(),
+                path_prefix: "".into(),
+                front_name: None,
+            },
+            inner: TlsRoute {
+                fragment: TlsRouteFragment {
+                    root_certs: RootCertificates::FromDer(Cow::Borrowed(CERTIFICATE.cert.der())),
+                    sni: Host::Domain(SERVER_HOSTNAME.into()),
+                    alpn: Some(alpn),
+                    ech_config_list: None,
+                    pin_set: None,
+                },
+                inner: DirectOrProxyRoute::Direct(TcpRoute {
+                    address: UnresolvedHost(SERVER_HOSTNAME.into()),
+                    port: NonZeroU16::new(address.port()).expect("bound port"),
+                }),
+            },
+        }
+    }
+
+    async fn connect_to(address: SocketAddr, alpn: Alpn) -> HttpsClient {
+        let connect_state = ConnectState::new(SUGGESTED_CONNECT_CONFIG);
+        let resolver = DnsResolver::new_from_static_map(HashMap::from([(
+            SERVER_HOSTNAME,
+            LookupResult::new(DnsSource::Static, vec![], vec![Ipv6Addr::LOCALHOST]),
+        )]));
+
+        HttpsClient::connect(
+            &connect_state,
+            &resolver,
+            vec![route_to(address, alpn)],
+            None,
+            &UserAgent::with_libsignal_version("test"),
+            "test",
+        )
+        .await
+        .expect("can connect")
+    }
+
+    #[test_case(Alpn::Http1_1; "http1")]
+    #[test_case(Alpn::Http2; "http2")]
+    #[tokio::test]
+    async fn send_streaming_request(alpn: Alpn) {
+        let _ = env_logger::try_init();
+        let address = localhost_echo_server();
+        let client = connect_to(address, alpn).await;
+
+        let chunks = futures_util::stream::iter(["streamed ", "request ", "body"])
+            .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())));
+        let (parts, body) = client
+            .send_and_collect(
+                HttpsRequest {
+                    method: Method::PUT,
+                    path: PathAndQuery::from_static("/v1/echo"),
+                    headers: HeaderMap::new(),
+                    body: request_body_from_stream(chunks),
+                },
+                Duration::from_secs(10),
+                1024,
+            )
+            .await
+            .expect("request succeeds");
+
+        assert_eq!(parts.status, http::StatusCode::OK);
+        assert_eq!(body, "streamed request body");
+        assert_eq!(
+            parts.headers.get("echo-path").map(|v| v.as_bytes()),
+            Some(&b"/v1/echo"[..])
+        );
+        let user_agent = parts
+            .headers
+            .get("echo-user-agent")
+            .and_then(|v| v.to_str().ok())
+            .expect("has user agent");
+        assert!(user_agent.starts_with("test libsignal/"), "{user_agent}");
+    }
+
+    #[tokio::test]
+    async fn concurrent_http1_requests_are_serialized() {
+        let _ = env_logger::try_init();
+        let address = localhost_echo_server();
+        let client = connect_to(address, Alpn::Http1_1).await;
+
+        let request = |body: &'static str| {
+            client.send_and_collect(
+                HttpsRequest::new(Method::POST, PathAndQuery::from_static("/"), body),
+                Duration::from_secs(10),
+                1024,
+            )
+        };
+        let (first, second) = tokio::join!(request("first"), request("second"));
+        assert_eq!(first.expect("succeeds").1, "first");
+        assert_eq!(second.expect("succeeds").1, "second");
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        let _ = env_logger::try_init();
+        let address = localhost_echo_server();
+        let client = connect_to(address, Alpn::Http2).await;
+
+        let result = client
+            .send(
+                HttpsRequest::new(Method::GET, PathAndQuery::from_static(SLOW_PATH), ""),
+                Duration::from_millis(100),
+            )
+            .await;
+        assert_matches!(result, Err(HttpsClientError::RequestTimedOut));
+    }
+}
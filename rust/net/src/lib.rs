@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+pub mod attachments;
 pub mod auth;
 pub mod cdsi;
 pub mod certs;
@@ -10,6 +11,7 @@ pub mod chat;
 pub mod connect_state;
 pub mod enclave;
 pub mod env;
+pub mod https_client;
 pub mod keytrans;
 pub mod proto;
 pub mod svr;
@@ -5,3 +5,4 @@
 
 pub(crate) mod cds2;
 pub mod chat_websocket;
+pub(crate) mod provisioning;
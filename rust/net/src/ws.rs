@@ -71,6 +71,17 @@ impl WebSocketServiceConnectError {
         )
     }
 
+    /// Whether the server's certificate chain didn't include any pinned key.
+    pub fn is_certificate_pin_mismatch(&self) -> bool {
+        matches!(
+            self,
+            Self::Connect(
+                WebSocketConnectError::Transport(TransportConnectError::CertificatePinMismatch),
+                _
+            )
+        )
+    }
+
     pub fn invalid_proxy_configuration() -> Self {
         Self::Connect(
             WebSocketConnectError::Transport(TransportConnectError::InvalidConfiguration),
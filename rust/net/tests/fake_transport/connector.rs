@@ -14,7 +14,8 @@ use libsignal_net::infra::errors::TransportConnectError;
 use libsignal_net_infra::host::Host;
 use libsignal_net_infra::route::{
     ConnectionProxyRoute, Connector, ConnectorFactory, DirectOrProxyRoute, HttpProxyRouteFragment,
-    HttpsProxyRoute, ProxyTarget, SocksRoute, TcpRoute, TlsRoute, TransportRoute,
+    HttpsProxyRoute, PluggableTransportRoute, PluggableTransportRouteFragment, ProxyTarget,
+    SocksRoute, TcpRoute, TlsRoute, TransportRoute,
 };
 use tokio::io::DuplexStream;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -188,6 +189,15 @@ fn target_host_port(route: &TransportRoute) -> TcpRoute<Host<Arc<str>>> {
                 target_port,
                 target_addr: target_host,
                 ..
+            })
+            | ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                fragment:
+                    PluggableTransportRouteFragment {
+                        target_port,
+                        target_host,
+                        ..
+                    },
+                ..
             }) => (
                 *target_port,
                 match target_host {
@@ -56,8 +56,10 @@ pub fn allow_all_routes(
                 port,
                 hostname,
                 cert: _,
+                pin_set: _,
                 confirmation_header_name: _,
                 proxy: _,
+                noise: _,
             },
     } = domain_config;
     let direct_ips = &resolved_names[hostname];
@@ -92,8 +94,10 @@ pub fn allow_domain_fronting(
                 port: _,
                 hostname: _,
                 cert: _,
+                pin_set: _,
                 confirmation_header_name: _,
                 proxy,
+                noise: _,
             },
     } = domain_config;
     let allow_targets = proxy
@@ -217,8 +221,10 @@ impl FakeDeps {
                 local_idle_timeout,
                 remote_idle_timeout: remote_idle_ping_timeout,
                 initial_request_id: 0,
+                compression: Some(Default::default()),
             },
             None,
+            None,
             "fake chat",
         )
         .await
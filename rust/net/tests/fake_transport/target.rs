@@ -10,8 +10,9 @@ use std::sync::Arc;
 
 use libsignal_net_infra::host::Host;
 use libsignal_net_infra::route::{
-    ConnectionProxyRoute, DirectOrProxyRoute, HttpProxyRouteFragment, HttpsProxyRoute, ProxyTarget,
-    SocksRoute, TcpRoute, TransportRoute, DEFAULT_HTTPS_PORT,
+    ConnectionProxyRoute, DirectOrProxyRoute, HttpProxyRouteFragment, HttpsProxyRoute,
+    PluggableTransportRoute, PluggableTransportRouteFragment, ProxyTarget, SocksRoute, TcpRoute,
+    TransportRoute, DEFAULT_HTTPS_PORT,
 };
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -54,6 +55,15 @@ impl FakeTransportTarget {
                             ..
                         },
                     ..
+                })
+                | ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                    fragment:
+                        PluggableTransportRouteFragment {
+                            target_host,
+                            target_port,
+                            ..
+                        },
+                    ..
                 }) => Self::TcpThroughProxy {
                     host: match target_host {
                         ProxyTarget::ResolvedLocally(ip) => (*ip).into(),
@@ -0,0 +1,83 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Dumps protocol state from a particular version of libsignal, or checks that the current
+//! version can still use a previous dump.
+//!
+//! ```text
+//! cross_version_state dump <v12|v21|current> <dir>
+//! cross_version_state check <dir>
+//! ```
+//!
+//! A dump contains two directories, `alice` and `bob`, each holding the serialized records of
+//! one side of a conversation plus some messages that haven't been decrypted yet. Keeping dumps
+//! from old versions around lets us check that databases written by old apps will still work
+//! after upgrading.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use libsignal_protocol_cross_version_testing::*;
+
+const ALICE_DIR: &str = "alice";
+const BOB_DIR: &str = "bob";
+
+fn make_store(version: &str) -> Option<Box<dyn LibSignalProtocolStore>> {
+    Some(match version {
+        "v12" => Box::new(LibSignalProtocolV12::new()),
+        "v21" => Box::new(LibSignalProtocolV21::new()),
+        "current" => Box::new(LibSignalProtocolCurrent::new()),
+        _ => return None,
+    })
+}
+
+fn dump(version: &str, dir: &Path) -> Result<(), String> {
+    let (Some(mut alice_store), Some(mut bob_store)) = (make_store(version), make_store(version))
+    else {
+        return Err(format!("unknown version '{version}'"));
+    };
+    let [alice_snapshot, bob_snapshot] = create_snapshots(&mut *alice_store, &mut *bob_store);
+    alice_snapshot
+        .write_to_dir(&dir.join(ALICE_DIR))
+        .and_then(|()| bob_snapshot.write_to_dir(&dir.join(BOB_DIR)))
+        .map_err(|e| format!("failed to write snapshot: {e}"))
+}
+
+fn check(dir: &Path) -> Result<(), String> {
+    let read = |name| {
+        StateSnapshot::read_from_dir(&dir.join(name))
+            .map_err(|e| format!("failed to read snapshot '{name}': {e}"))
+    };
+    let alice_snapshot = read(ALICE_DIR)?;
+    let bob_snapshot = read(BOB_DIR)?;
+    check_snapshots(&alice_snapshot, &bob_snapshot);
+    println!(
+        "snapshot from {} is usable with the current version",
+        alice_snapshot.version
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["dump", version, dir] => dump(version, &PathBuf::from(dir)),
+        ["check", dir] => check(&PathBuf::from(dir)),
+        _ => Err(
+            "usage: cross_version_state dump <v12|v21|current> <dir>\n       cross_version_state check <dir>"
+                .to_owned(),
+        ),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -9,11 +9,13 @@ use futures_util::FutureExt;
 use libsignal_protocol_current::*;
 use rand::{thread_rng, Rng};
 
+use crate::{KnownRecords, LibSignalProtocolStore as _, StateSnapshot, DISTRIBUTION_ID};
+
 fn address(id: &str) -> ProtocolAddress {
     ProtocolAddress::new(id.into(), 1.into())
 }
 
-pub struct LibSignalProtocolCurrent(InMemSignalProtocolStore);
+pub struct LibSignalProtocolCurrent(InMemSignalProtocolStore, KnownRecords);
 
 impl LibSignalProtocolCurrent {
     pub fn new() -> Self {
@@ -25,8 +27,89 @@ impl LibSignalProtocolCurrent {
         Self(
             InMemSignalProtocolStore::new(identity_key, registration_id as u32)
                 .expect("can initialize"),
+            KnownRecords::default(),
         )
     }
+
+    /// Loads records produced by any version's [`snapshot`](super::LibSignalProtocolStore::snapshot).
+    ///
+    /// Pending messages are ignored; the caller is expected to decrypt them.
+    pub fn from_snapshot(snapshot: &StateSnapshot) -> Self {
+        let identity_key_pair = IdentityKeyPair::try_from(&snapshot.identity_key_pair[..])
+            .expect("valid identity key pair");
+        let mut store = InMemSignalProtocolStore::new(identity_key_pair, snapshot.registration_id)
+            .expect("can initialize");
+        let mut known_records = KnownRecords::default();
+
+        for (remote, identity) in &snapshot.identities {
+            store
+                .save_identity(
+                    &address(remote),
+                    &IdentityKey::decode(identity).expect("valid identity key"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save identities");
+        }
+        for (remote, record) in &snapshot.sessions {
+            store
+                .store_session(
+                    &address(remote),
+                    &SessionRecord::deserialize(record).expect("valid session record"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save sessions");
+            known_records.remotes.insert(remote.clone());
+        }
+        for (id, record) in &snapshot.pre_keys {
+            store
+                .save_pre_key(
+                    (*id).into(),
+                    &PreKeyRecord::deserialize(record).expect("valid pre-key record"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save pre-keys");
+            known_records.pre_key_ids.push(*id);
+        }
+        for (id, record) in &snapshot.signed_pre_keys {
+            store
+                .save_signed_pre_key(
+                    (*id).into(),
+                    &SignedPreKeyRecord::deserialize(record).expect("valid signed pre-key record"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save pre-keys");
+            known_records.signed_pre_key_ids.push(*id);
+        }
+        for (id, record) in &snapshot.kyber_pre_keys {
+            store
+                .save_kyber_pre_key(
+                    (*id).into(),
+                    &KyberPreKeyRecord::deserialize(record).expect("valid Kyber pre-key record"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save pre-keys");
+            known_records.kyber_pre_key_ids.push(*id);
+        }
+        for (sender, record) in &snapshot.sender_keys {
+            store
+                .store_sender_key(
+                    &address(sender),
+                    DISTRIBUTION_ID.parse().expect("valid"),
+                    &SenderKeyRecord::deserialize(record).expect("valid sender key record"),
+                )
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can save sender keys");
+            known_records.sender_key_senders.insert(sender.clone());
+        }
+
+        Self(store, known_records)
+    }
 }
 
 impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
@@ -53,6 +136,19 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
         let device_id: u32 = csprng.gen();
         let pre_key_id: u32 = csprng.gen();
         let signed_pre_key_id: u32 = csprng.gen();
+        let kyber_pre_key_id: u32 = csprng.gen();
+
+        let kyber_pre_key_record = KyberPreKeyRecord::generate(
+            kem::KeyType::Kyber1024,
+            kyber_pre_key_id.into(),
+            self.0
+                .get_identity_key_pair()
+                .now_or_never()
+                .expect("synchronous")
+                .expect("can fetch identity key")
+                .private_key(),
+        )
+        .expect("can generate Kyber pre-keys");
 
         let pre_key_bundle = PreKeyBundle::new(
             self.0
@@ -73,7 +169,16 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
                 .expect("can fetch identity key")
                 .identity_key(),
         )
-        .expect("can create pre-key bundles");
+        .expect("can create pre-key bundles")
+        .with_kyber_pre_key(
+            kyber_pre_key_id.into(),
+            kyber_pre_key_record
+                .public_key()
+                .expect("can fetch Kyber public key"),
+            kyber_pre_key_record
+                .signature()
+                .expect("can fetch Kyber signature"),
+        );
 
         self.0
             .save_pre_key(
@@ -100,10 +205,21 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
             .expect("synchronous")
             .expect("can save pre-keys");
 
+        self.0
+            .save_kyber_pre_key(kyber_pre_key_id.into(), &kyber_pre_key_record)
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can save pre-keys");
+
+        self.1.pre_key_ids.push(pre_key_id);
+        self.1.signed_pre_key_ids.push(signed_pre_key_id);
+        self.1.kyber_pre_key_ids.push(kyber_pre_key_id);
+
         pre_key_bundle
     }
 
     fn process_pre_key_bundle(&mut self, remote: &str, pre_key_bundle: PreKeyBundle) {
+        self.1.remotes.insert(remote.to_owned());
         process_prekey_bundle(
             &address(remote),
             &mut self.0.session_store,
@@ -118,6 +234,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
     }
 
     fn encrypt(&mut self, remote: &str, msg: &[u8]) -> (Vec<u8>, CiphertextMessageType) {
+        self.1.remotes.insert(remote.to_owned());
         let encrypted = message_encrypt(
             msg,
             &address(remote),
@@ -132,6 +249,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
     }
 
     fn decrypt(&mut self, remote: &str, msg: &[u8], msg_type: CiphertextMessageType) -> Vec<u8> {
+        self.1.remotes.insert(remote.to_owned());
         match msg_type {
             CiphertextMessageType::Whisper => message_decrypt_signal(
                 &SignalMessage::try_from(msg).expect("valid"),
@@ -159,4 +277,174 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
             _ => panic!("unexpected 1:1 message type"),
         }
     }
+
+    fn create_sender_key_distribution_message(&mut self, local: &str) -> Vec<u8> {
+        self.1.sender_key_senders.insert(local.to_owned());
+        create_sender_key_distribution_message(
+            &address(local),
+            DISTRIBUTION_ID.parse().expect("valid"),
+            &mut self.0.sender_key_store,
+            &mut thread_rng(),
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can create sender keys")
+        .serialized()
+        .to_vec()
+    }
+
+    fn process_sender_key_distribution_message(&mut self, sender: &str, skdm: &[u8]) {
+        self.1.sender_key_senders.insert(sender.to_owned());
+        process_sender_key_distribution_message(
+            &address(sender),
+            &SenderKeyDistributionMessage::try_from(skdm).expect("valid"),
+            &mut self.0.sender_key_store,
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can process sender keys")
+    }
+
+    fn group_encrypt(&mut self, local: &str, msg: &[u8]) -> Vec<u8> {
+        group_encrypt(
+            &mut self.0.sender_key_store,
+            &address(local),
+            DISTRIBUTION_ID.parse().expect("valid"),
+            msg,
+            &mut thread_rng(),
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can encrypt group messages")
+        .serialized()
+        .to_vec()
+    }
+
+    fn group_decrypt(&mut self, sender: &str, msg: &[u8]) -> Vec<u8> {
+        group_decrypt(msg, &mut self.0.sender_key_store, &address(sender))
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can decrypt group messages")
+    }
+
+    fn snapshot(&mut self) -> StateSnapshot {
+        let identity_key_pair = self
+            .0
+            .get_identity_key_pair()
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can fetch identity key");
+        let registration_id = self
+            .0
+            .get_local_registration_id()
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can fetch registration id");
+
+        let sessions = self
+            .1
+            .remotes
+            .iter()
+            .filter_map(|remote| {
+                let record = self
+                    .0
+                    .load_session(&address(remote))
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load sessions")?;
+                Some((
+                    remote.clone(),
+                    record.serialize().expect("can serialize sessions"),
+                ))
+            })
+            .collect();
+        let identities = self
+            .1
+            .remotes
+            .iter()
+            .filter_map(|remote| {
+                let identity = self
+                    .0
+                    .get_identity(&address(remote))
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load identities")?;
+                Some((remote.clone(), identity.serialize().to_vec()))
+            })
+            .collect();
+        let pre_keys = self
+            .1
+            .pre_key_ids
+            .iter()
+            .filter_map(|&id| {
+                // One-time pre-keys are removed once they've been used.
+                let record = self
+                    .0
+                    .get_pre_key(id.into())
+                    .now_or_never()
+                    .expect("synchronous")
+                    .ok()?;
+                Some((id, record.serialize().expect("can serialize pre-keys")))
+            })
+            .collect();
+        let signed_pre_keys = self
+            .1
+            .signed_pre_key_ids
+            .iter()
+            .map(|&id| {
+                let record = self
+                    .0
+                    .get_signed_pre_key(id.into())
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can fetch signed pre-keys");
+                (id, record.serialize().expect("can serialize pre-keys"))
+            })
+            .collect();
+        let kyber_pre_keys = self
+            .1
+            .kyber_pre_key_ids
+            .iter()
+            .map(|&id| {
+                let record = self
+                    .0
+                    .get_kyber_pre_key(id.into())
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can fetch Kyber pre-keys");
+                (id, record.serialize().expect("can serialize pre-keys"))
+            })
+            .collect();
+        let sender_keys = self
+            .1
+            .sender_key_senders
+            .iter()
+            .map(|sender| {
+                let record = self
+                    .0
+                    .load_sender_key(&address(sender), DISTRIBUTION_ID.parse().expect("valid"))
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load sender keys")
+                    .expect("has sender key");
+                (
+                    sender.clone(),
+                    record.serialize().expect("can serialize sender keys"),
+                )
+            })
+            .collect();
+
+        StateSnapshot {
+            version: self.version().to_owned(),
+            identity_key_pair: identity_key_pair.serialize().to_vec(),
+            registration_id,
+            identities,
+            sessions,
+            pre_keys,
+            signed_pre_keys,
+            kyber_pre_keys,
+            sender_keys,
+            pending_messages: vec![],
+        }
+    }
 }
@@ -5,6 +5,8 @@
 
 #![allow(clippy::new_without_default)]
 
+use std::collections::BTreeSet;
+
 pub use libsignal_protocol_current::{CiphertextMessageType, PreKeyBundle};
 
 pub trait LibSignalProtocolStore {
@@ -13,8 +15,37 @@ pub trait LibSignalProtocolStore {
     fn process_pre_key_bundle(&mut self, remote: &str, pre_key_bundle: PreKeyBundle);
     fn encrypt(&mut self, remote: &str, msg: &[u8]) -> (Vec<u8>, CiphertextMessageType);
     fn decrypt(&mut self, remote: &str, msg: &[u8], msg_type: CiphertextMessageType) -> Vec<u8>;
+    /// Creates a sender key for `local` in [`DISTRIBUTION_ID`], returning the serialized
+    /// distribution message.
+    fn create_sender_key_distribution_message(&mut self, local: &str) -> Vec<u8>;
+    fn process_sender_key_distribution_message(&mut self, sender: &str, skdm: &[u8]);
+    fn group_encrypt(&mut self, local: &str, msg: &[u8]) -> Vec<u8>;
+    fn group_decrypt(&mut self, sender: &str, msg: &[u8]) -> Vec<u8>;
+    /// Serializes every record this store has created or received.
+    ///
+    /// The returned snapshot never has any pending messages; it's up to the caller to add them.
+    fn snapshot(&mut self) -> StateSnapshot;
 }
 
+/// The only distribution used for sender key messages in this harness.
+const DISTRIBUTION_ID: &str = "d1d1d1d1-7000-11eb-b32a-33b8a8a487a6";
+
+/// Records created by a store, so that they can be found again when taking a snapshot.
+///
+/// The in-memory stores don't support enumerating their contents.
+#[derive(Default)]
+struct KnownRecords {
+    remotes: BTreeSet<String>,
+    pre_key_ids: Vec<u32>,
+    signed_pre_key_ids: Vec<u32>,
+    kyber_pre_key_ids: Vec<u32>,
+    /// Senders with a sender key in [`DISTRIBUTION_ID`], including the local user.
+    sender_key_senders: BTreeSet<String>,
+}
+
+mod snapshot;
+pub use snapshot::{check_snapshots, create_snapshots, PendingMessage, StateSnapshot};
+
 mod current;
 pub use current::LibSignalProtocolCurrent;
 
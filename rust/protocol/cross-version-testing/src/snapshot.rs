@@ -0,0 +1,330 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Serialized protocol state, as an app would keep it in its database.
+//!
+//! A [`StateSnapshot`] holds the raw serialized records from one store, plus any messages that
+//! were sent to that store but not yet decrypted. Snapshots produced by an old version of
+//! libsignal can be written to disk with [`StateSnapshot::write_to_dir`], and later loaded into
+//! the current version with [`LibSignalProtocolCurrent::from_snapshot`] to check that the records
+//! are still usable after an upgrade.
+
+use std::path::Path;
+use std::{fs, io};
+
+use crate::{CiphertextMessageType, LibSignalProtocolCurrent, LibSignalProtocolStore};
+
+/// A message that was encrypted for a snapshotted store but not yet delivered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingMessage {
+    pub sender: String,
+    pub message_type: CiphertextMessageType,
+    pub ciphertext: Vec<u8>,
+    pub plaintext: Vec<u8>,
+}
+
+/// Serialized records from a single [`LibSignalProtocolStore`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// The version of libsignal that produced the snapshot, for diagnostics.
+    pub version: String,
+    pub identity_key_pair: Vec<u8>,
+    pub registration_id: u32,
+    /// Identity keys of remote parties, keyed by remote name (always device 1).
+    pub identities: Vec<(String, Vec<u8>)>,
+    /// Session records, keyed by remote name (always device 1).
+    pub sessions: Vec<(String, Vec<u8>)>,
+    pub pre_keys: Vec<(u32, Vec<u8>)>,
+    pub signed_pre_keys: Vec<(u32, Vec<u8>)>,
+    pub kyber_pre_keys: Vec<(u32, Vec<u8>)>,
+    /// Sender key records for the harness's single distribution, keyed by sender name (always
+    /// device 1).
+    pub sender_keys: Vec<(String, Vec<u8>)>,
+    pub pending_messages: Vec<PendingMessage>,
+}
+
+const VERSION_FILE: &str = "version";
+const IDENTITY_KEY_PAIR_FILE: &str = "identity_key_pair";
+const REGISTRATION_ID_FILE: &str = "registration_id";
+const IDENTITIES_DIR: &str = "identities";
+const SESSIONS_DIR: &str = "sessions";
+const PRE_KEYS_DIR: &str = "pre_keys";
+const SIGNED_PRE_KEYS_DIR: &str = "signed_pre_keys";
+const KYBER_PRE_KEYS_DIR: &str = "kyber_pre_keys";
+const SENDER_KEYS_DIR: &str = "sender_keys";
+const PENDING_MESSAGES_DIR: &str = "pending_messages";
+
+impl StateSnapshot {
+    /// Writes the snapshot to `dir`, which will be created if necessary.
+    ///
+    /// Each record gets its own file containing exactly the bytes produced by its `serialize`
+    /// method, so the snapshot can also be inspected or edited by hand.
+    pub fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(VERSION_FILE), &self.version)?;
+        fs::write(dir.join(IDENTITY_KEY_PAIR_FILE), &self.identity_key_pair)?;
+        fs::write(
+            dir.join(REGISTRATION_ID_FILE),
+            self.registration_id.to_string(),
+        )?;
+        write_records(&dir.join(IDENTITIES_DIR), &self.identities)?;
+        write_records(&dir.join(SESSIONS_DIR), &self.sessions)?;
+        write_records(&dir.join(PRE_KEYS_DIR), &self.pre_keys)?;
+        write_records(&dir.join(SIGNED_PRE_KEYS_DIR), &self.signed_pre_keys)?;
+        write_records(&dir.join(KYBER_PRE_KEYS_DIR), &self.kyber_pre_keys)?;
+        write_records(&dir.join(SENDER_KEYS_DIR), &self.sender_keys)?;
+
+        let pending_dir = dir.join(PENDING_MESSAGES_DIR);
+        fs::create_dir_all(&pending_dir)?;
+        for (i, message) in self.pending_messages.iter().enumerate() {
+            // Zero-padded so that directory order matches delivery order.
+            let message_dir = pending_dir.join(format!("{i:04}"));
+            fs::create_dir_all(&message_dir)?;
+            fs::write(message_dir.join("sender"), &message.sender)?;
+            fs::write(
+                message_dir.join("type"),
+                (message.message_type as u8).to_string(),
+            )?;
+            fs::write(message_dir.join("ciphertext"), &message.ciphertext)?;
+            fs::write(message_dir.join("plaintext"), &message.plaintext)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot previously written by [`Self::write_to_dir`].
+    pub fn read_from_dir(dir: &Path) -> io::Result<Self> {
+        let version = fs::read_to_string(dir.join(VERSION_FILE))?;
+        let identity_key_pair = fs::read(dir.join(IDENTITY_KEY_PAIR_FILE))?;
+        let registration_id = parse(&fs::read_to_string(dir.join(REGISTRATION_ID_FILE))?)?;
+        let identities = read_records(&dir.join(IDENTITIES_DIR))?;
+        let sessions = read_records(&dir.join(SESSIONS_DIR))?;
+        let pre_keys = read_records(&dir.join(PRE_KEYS_DIR))?
+            .into_iter()
+            .map(|(id, record)| Ok((parse(&id)?, record)))
+            .collect::<io::Result<_>>()?;
+        let signed_pre_keys = read_records(&dir.join(SIGNED_PRE_KEYS_DIR))?
+            .into_iter()
+            .map(|(id, record)| Ok((parse(&id)?, record)))
+            .collect::<io::Result<_>>()?;
+        let kyber_pre_keys = read_records(&dir.join(KYBER_PRE_KEYS_DIR))?
+            .into_iter()
+            .map(|(id, record)| Ok((parse(&id)?, record)))
+            .collect::<io::Result<_>>()?;
+        let sender_keys = read_records(&dir.join(SENDER_KEYS_DIR))?;
+
+        let mut pending_messages = vec![];
+        for message_dir in sorted_entries(&dir.join(PENDING_MESSAGES_DIR))? {
+            let message_dir = dir.join(PENDING_MESSAGES_DIR).join(message_dir);
+            let message_type: u8 = parse(&fs::read_to_string(message_dir.join("type"))?)?;
+            pending_messages.push(PendingMessage {
+                sender: fs::read_to_string(message_dir.join("sender"))?,
+                message_type: CiphertextMessageType::try_from(message_type)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?,
+                ciphertext: fs::read(message_dir.join("ciphertext"))?,
+                plaintext: fs::read(message_dir.join("plaintext"))?,
+            });
+        }
+
+        Ok(Self {
+            version,
+            identity_key_pair,
+            registration_id,
+            identities,
+            sessions,
+            pre_keys,
+            signed_pre_keys,
+            kyber_pre_keys,
+            sender_keys,
+            pending_messages,
+        })
+    }
+}
+
+fn write_records<K: ToString>(dir: &Path, records: &[(K, Vec<u8>)]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (key, record) in records {
+        fs::write(dir.join(key.to_string()), record)?;
+    }
+    Ok(())
+}
+
+fn read_records(dir: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    sorted_entries(dir)?
+        .into_iter()
+        .map(|name| {
+            let record = fs::read(dir.join(&name))?;
+            Ok((name, record))
+        })
+        .collect()
+}
+
+fn sorted_entries(dir: &Path) -> io::Result<Vec<String>> {
+    let mut names = fs::read_dir(dir)?
+        .map(|entry| {
+            entry?
+                .file_name()
+                .into_string()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "non-UTF-8 file name"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> io::Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    s.trim()
+        .parse()
+        .map_err(|e: T::Err| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+const ALICE_NAME: &str = "alice";
+const BOB_NAME: &str = "bob";
+
+/// Runs a conversation between two stores, including group messages, and snapshots both of them,
+/// leaving a few messages in each direction undelivered.
+///
+/// Returns the snapshots for Alice and Bob, in that order.
+pub fn create_snapshots(
+    alice_store: &mut dyn LibSignalProtocolStore,
+    bob_store: &mut dyn LibSignalProtocolStore,
+) -> [StateSnapshot; 2] {
+    let bob_pre_key_bundle = bob_store.create_pre_key_bundle();
+    alice_store.process_pre_key_bundle(BOB_NAME, bob_pre_key_bundle);
+
+    let (message, message_type) = alice_store.encrypt(BOB_NAME, b"hello");
+    assert_eq!(message_type, CiphertextMessageType::PreKey);
+    assert_eq!(
+        bob_store.decrypt(ALICE_NAME, &message, message_type),
+        b"hello"
+    );
+
+    let (message, message_type) = bob_store.encrypt(ALICE_NAME, b"hi");
+    assert_eq!(alice_store.decrypt(BOB_NAME, &message, message_type), b"hi");
+
+    let mut pending_for_bob = vec![];
+    for i in 0..3 {
+        let plaintext = format!("A->B pending message {i}").into_bytes();
+        let (ciphertext, message_type) = alice_store.encrypt(BOB_NAME, &plaintext);
+        pending_for_bob.push(PendingMessage {
+            sender: ALICE_NAME.to_owned(),
+            message_type,
+            ciphertext,
+            plaintext,
+        });
+    }
+    // Deliver the last one, so that the others are decrypted out of order.
+    let delivered = pending_for_bob.pop().expect("just added");
+    assert_eq!(
+        bob_store.decrypt(ALICE_NAME, &delivered.ciphertext, delivered.message_type),
+        delivered.plaintext
+    );
+
+    let mut pending_for_alice = vec![];
+    for i in 0..2 {
+        let plaintext = format!("B->A pending message {i}").into_bytes();
+        let (ciphertext, message_type) = bob_store.encrypt(ALICE_NAME, &plaintext);
+        pending_for_alice.push(PendingMessage {
+            sender: BOB_NAME.to_owned(),
+            message_type,
+            ciphertext,
+            plaintext,
+        });
+    }
+
+    // Both parties join a group, so that each has its own sender key and the other's.
+    join_group(ALICE_NAME, alice_store, bob_store);
+    join_group(BOB_NAME, bob_store, alice_store);
+    for (sender_name, sender_store, pending) in [
+        (ALICE_NAME, &mut *alice_store, &mut pending_for_bob),
+        (BOB_NAME, &mut *bob_store, &mut pending_for_alice),
+    ] {
+        let plaintext = format!("{sender_name} pending group message").into_bytes();
+        pending.push(PendingMessage {
+            sender: sender_name.to_owned(),
+            message_type: CiphertextMessageType::SenderKey,
+            ciphertext: sender_store.group_encrypt(sender_name, &plaintext),
+            plaintext,
+        });
+    }
+
+    let mut alice_snapshot = alice_store.snapshot();
+    alice_snapshot.pending_messages = pending_for_alice;
+    let mut bob_snapshot = bob_store.snapshot();
+    bob_snapshot.pending_messages = pending_for_bob;
+    [alice_snapshot, bob_snapshot]
+}
+
+/// Sends `sender_name`'s sender key to the other party and checks that a group message gets
+/// through.
+fn join_group(
+    sender_name: &str,
+    sender_store: &mut dyn LibSignalProtocolStore,
+    recipient_store: &mut dyn LibSignalProtocolStore,
+) {
+    let skdm = sender_store.create_sender_key_distribution_message(sender_name);
+    recipient_store.process_sender_key_distribution_message(sender_name, &skdm);
+    let message = sender_store.group_encrypt(sender_name, b"hello, group");
+    assert_eq!(
+        recipient_store.group_decrypt(sender_name, &message),
+        b"hello, group"
+    );
+}
+
+/// Loads snapshots produced by [`create_snapshots`] into the current version, decrypts the
+/// pending messages, and checks that the two parties can keep talking.
+///
+/// Panics if anything fails, like the rest of this harness.
+pub fn check_snapshots(alice_snapshot: &StateSnapshot, bob_snapshot: &StateSnapshot) {
+    let mut alice_store = LibSignalProtocolCurrent::from_snapshot(alice_snapshot);
+    let mut bob_store = LibSignalProtocolCurrent::from_snapshot(bob_snapshot);
+
+    for (store, snapshot) in [
+        (&mut alice_store, alice_snapshot),
+        (&mut bob_store, bob_snapshot),
+    ] {
+        for message in &snapshot.pending_messages {
+            let plaintext = match message.message_type {
+                CiphertextMessageType::SenderKey => {
+                    store.group_decrypt(&message.sender, &message.ciphertext)
+                }
+                message_type => store.decrypt(&message.sender, &message.ciphertext, message_type),
+            };
+            assert_eq!(
+                plaintext, message.plaintext,
+                "failed to decrypt pending message from {} (snapshot from {})",
+                message.sender, snapshot.version,
+            );
+        }
+    }
+
+    for i in 0..3 {
+        let plaintext = format!("A->B after upgrade {i}").into_bytes();
+        let (ciphertext, message_type) = alice_store.encrypt(BOB_NAME, &plaintext);
+        assert_eq!(message_type, CiphertextMessageType::Whisper);
+        assert_eq!(
+            bob_store.decrypt(ALICE_NAME, &ciphertext, message_type),
+            plaintext
+        );
+
+        let plaintext = format!("B->A after upgrade {i}").into_bytes();
+        let (ciphertext, message_type) = bob_store.encrypt(ALICE_NAME, &plaintext);
+        assert_eq!(message_type, CiphertextMessageType::Whisper);
+        assert_eq!(
+            alice_store.decrypt(BOB_NAME, &ciphertext, message_type),
+            plaintext
+        );
+
+        let plaintext = format!("A->group after upgrade {i}").into_bytes();
+        let ciphertext = alice_store.group_encrypt(ALICE_NAME, &plaintext);
+        assert_eq!(bob_store.group_decrypt(ALICE_NAME, &ciphertext), plaintext);
+
+        let plaintext = format!("B->group after upgrade {i}").into_bytes();
+        let ciphertext = bob_store.group_encrypt(BOB_NAME, &plaintext);
+        assert_eq!(alice_store.group_decrypt(BOB_NAME, &ciphertext), plaintext);
+    }
+}
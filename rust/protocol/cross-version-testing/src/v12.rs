@@ -7,11 +7,13 @@ use futures_util::FutureExt;
 use libsignal_protocol_v12::*;
 use rand_v7::{thread_rng, Rng};
 
+use crate::{KnownRecords, LibSignalProtocolStore as _, StateSnapshot, DISTRIBUTION_ID};
+
 fn address(id: &str) -> ProtocolAddress {
     ProtocolAddress::new(id.into(), 1)
 }
 
-pub struct LibSignalProtocolV12(InMemSignalProtocolStore);
+pub struct LibSignalProtocolV12(InMemSignalProtocolStore, KnownRecords);
 
 impl LibSignalProtocolV12 {
     pub fn new() -> Self {
@@ -23,6 +25,7 @@ impl LibSignalProtocolV12 {
         Self(
             InMemSignalProtocolStore::new(identity_key, registration_id as u32)
                 .expect("can initialize"),
+            KnownRecords::default(),
         )
     }
 }
@@ -109,10 +112,14 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV12 {
             .expect("synchronous")
             .expect("can save pre-keys");
 
+        self.1.pre_key_ids.push(pre_key_id);
+        self.1.signed_pre_key_ids.push(signed_pre_key_id);
+
         pre_key_bundle
     }
 
     fn process_pre_key_bundle(&mut self, remote: &str, pre_key_bundle: super::PreKeyBundle) {
+        self.1.remotes.insert(remote.to_owned());
         let pre_key_bundle = PreKeyBundle::new(
             pre_key_bundle
                 .registration_id()
@@ -170,6 +177,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV12 {
     }
 
     fn encrypt(&mut self, remote: &str, msg: &[u8]) -> (Vec<u8>, super::CiphertextMessageType) {
+        self.1.remotes.insert(remote.to_owned());
         let encrypted = message_encrypt(
             msg,
             &address(remote),
@@ -195,6 +203,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV12 {
         msg: &[u8],
         msg_type: super::CiphertextMessageType,
     ) -> Vec<u8> {
+        self.1.remotes.insert(remote.to_owned());
         match msg_type {
             super::CiphertextMessageType::Whisper => message_decrypt_signal(
                 &SignalMessage::try_from(msg).expect("valid"),
@@ -223,4 +232,168 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV12 {
             _ => panic!("unexpected 1:1 message type"),
         }
     }
+
+    fn create_sender_key_distribution_message(&mut self, local: &str) -> Vec<u8> {
+        self.1.sender_key_senders.insert(local.to_owned());
+        create_sender_key_distribution_message(
+            &address(local),
+            DISTRIBUTION_ID.parse().expect("valid"),
+            &mut self.0.sender_key_store,
+            &mut thread_rng(),
+            None,
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can create sender keys")
+        .serialized()
+        .to_vec()
+    }
+
+    fn process_sender_key_distribution_message(&mut self, sender: &str, skdm: &[u8]) {
+        self.1.sender_key_senders.insert(sender.to_owned());
+        process_sender_key_distribution_message(
+            &address(sender),
+            &SenderKeyDistributionMessage::try_from(skdm).expect("valid"),
+            &mut self.0.sender_key_store,
+            None,
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can process sender keys")
+    }
+
+    fn group_encrypt(&mut self, local: &str, msg: &[u8]) -> Vec<u8> {
+        group_encrypt(
+            &mut self.0.sender_key_store,
+            &address(local),
+            DISTRIBUTION_ID.parse().expect("valid"),
+            msg,
+            &mut thread_rng(),
+            None,
+        )
+        .now_or_never()
+        .expect("synchronous")
+        .expect("can encrypt group messages")
+        .serialized()
+        .to_vec()
+    }
+
+    fn group_decrypt(&mut self, sender: &str, msg: &[u8]) -> Vec<u8> {
+        group_decrypt(msg, &mut self.0.sender_key_store, &address(sender), None)
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can decrypt group messages")
+    }
+
+    fn snapshot(&mut self) -> StateSnapshot {
+        let identity_key_pair = self
+            .0
+            .get_identity_key_pair(None)
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can fetch identity key");
+        let registration_id = self
+            .0
+            .get_local_registration_id(None)
+            .now_or_never()
+            .expect("synchronous")
+            .expect("can fetch registration id");
+
+        let sessions = self
+            .1
+            .remotes
+            .iter()
+            .filter_map(|remote| {
+                let record = self
+                    .0
+                    .load_session(&address(remote), None)
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load sessions")?;
+                Some((
+                    remote.clone(),
+                    record.serialize().expect("can serialize sessions"),
+                ))
+            })
+            .collect();
+        let identities = self
+            .1
+            .remotes
+            .iter()
+            .filter_map(|remote| {
+                let identity = self
+                    .0
+                    .get_identity(&address(remote), None)
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load identities")?;
+                Some((remote.clone(), identity.serialize().to_vec()))
+            })
+            .collect();
+        let pre_keys = self
+            .1
+            .pre_key_ids
+            .iter()
+            .filter_map(|&id| {
+                // One-time pre-keys are removed once they've been used.
+                let record = self
+                    .0
+                    .get_pre_key(id.into(), None)
+                    .now_or_never()
+                    .expect("synchronous")
+                    .ok()?;
+                Some((id, record.serialize().expect("can serialize pre-keys")))
+            })
+            .collect();
+        let signed_pre_keys = self
+            .1
+            .signed_pre_key_ids
+            .iter()
+            .map(|&id| {
+                let record = self
+                    .0
+                    .get_signed_pre_key(id.into(), None)
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can fetch signed pre-keys");
+                (id, record.serialize().expect("can serialize pre-keys"))
+            })
+            .collect();
+        let sender_keys = self
+            .1
+            .sender_key_senders
+            .iter()
+            .map(|sender| {
+                let record = self
+                    .0
+                    .load_sender_key(
+                        &address(sender),
+                        DISTRIBUTION_ID.parse().expect("valid"),
+                        None,
+                    )
+                    .now_or_never()
+                    .expect("synchronous")
+                    .expect("can load sender keys")
+                    .expect("has sender key");
+                (
+                    sender.clone(),
+                    record.serialize().expect("can serialize sender keys"),
+                )
+            })
+            .collect();
+
+        StateSnapshot {
+            version: self.version().to_owned(),
+            identity_key_pair: identity_key_pair.serialize().to_vec(),
+            registration_id,
+            identities,
+            sessions,
+            pre_keys,
+            signed_pre_keys,
+            // Kyber pre-keys didn't exist yet.
+            kyber_pre_keys: vec![],
+            sender_keys,
+            pending_messages: vec![],
+        }
+    }
 }
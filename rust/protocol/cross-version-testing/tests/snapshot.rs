@@ -0,0 +1,51 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use libsignal_protocol_cross_version_testing::*;
+
+fn round_trip_through_disk(snapshot: &StateSnapshot, name: &str) -> StateSnapshot {
+    let dir = std::env::temp_dir().join(format!(
+        "libsignal-cross-version-{}-{}-{name}",
+        std::process::id(),
+        snapshot.version
+    ));
+    snapshot.write_to_dir(&dir).expect("can write");
+    let result = StateSnapshot::read_from_dir(&dir).expect("can read");
+    std::fs::remove_dir_all(&dir).expect("can clean up");
+    result
+}
+
+#[test]
+fn test_snapshots_load_in_current() {
+    let makers: [fn() -> Box<dyn LibSignalProtocolStore>; 3] = [
+        || Box::new(LibSignalProtocolCurrent::new()),
+        || Box::new(LibSignalProtocolV21::new()),
+        || Box::new(LibSignalProtocolV12::new()),
+    ];
+
+    for make_store in makers {
+        let mut alice_store = make_store();
+        let mut bob_store = make_store();
+        let [alice_snapshot, bob_snapshot] = create_snapshots(&mut *alice_store, &mut *bob_store);
+        assert_eq!(alice_snapshot.pending_messages.len(), 3);
+        assert_eq!(bob_snapshot.pending_messages.len(), 3);
+        for snapshot in [&alice_snapshot, &bob_snapshot] {
+            // One sender key for each party.
+            assert_eq!(snapshot.sender_keys.len(), 2);
+        }
+        // Only the current version has Kyber pre-keys, and only Bob published a bundle.
+        if alice_store.version() == "current" {
+            assert!(alice_snapshot.kyber_pre_keys.is_empty());
+            assert_eq!(bob_snapshot.kyber_pre_keys.len(), 1);
+        }
+
+        let alice_snapshot_from_disk = round_trip_through_disk(&alice_snapshot, "alice");
+        assert_eq!(alice_snapshot_from_disk, alice_snapshot);
+        let bob_snapshot_from_disk = round_trip_through_disk(&bob_snapshot, "bob");
+        assert_eq!(bob_snapshot_from_disk, bob_snapshot);
+
+        check_snapshots(&alice_snapshot_from_disk, &bob_snapshot_from_disk);
+    }
+}
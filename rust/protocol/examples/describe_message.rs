@@ -0,0 +1,73 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Prints a JSON description of a serialized protocol message.
+//!
+//! The message is read from stdin as raw bytes, or passed as a hex string with `--hex`.
+
+use std::io::Read;
+
+use clap::Parser;
+use libsignal_protocol::inspect::{describe, describe_as};
+use libsignal_protocol::CiphertextMessageType;
+
+#[derive(clap::Parser)]
+struct Cli {
+    /// The message as a hex string, instead of reading raw bytes from stdin.
+    #[arg(long)]
+    hex: Option<String>,
+    /// Skip auto-detection and parse the message as this type.
+    #[arg(long = "type", value_enum)]
+    message_type: Option<MessageType>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum MessageType {
+    Whisper,
+    PreKey,
+    SenderKey,
+    Plaintext,
+}
+
+impl From<MessageType> for CiphertextMessageType {
+    fn from(value: MessageType) -> Self {
+        match value {
+            MessageType::Whisper => Self::Whisper,
+            MessageType::PreKey => Self::PreKey,
+            MessageType::SenderKey => Self::SenderKey,
+            MessageType::Plaintext => Self::Plaintext,
+        }
+    }
+}
+
+fn main() {
+    let Cli { hex, message_type } = Cli::parse();
+
+    let bytes = match hex {
+        Some(hex) => hex::decode(hex.trim()).expect("valid hex"),
+        None => {
+            let mut bytes = vec![];
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .expect("can read stdin");
+            bytes
+        }
+    };
+
+    let description = match message_type {
+        Some(message_type) => describe_as(&bytes, message_type.into()),
+        None => describe(&bytes),
+    };
+    match description {
+        Ok(description) => println!(
+            "{}",
+            serde_json::to_string_pretty(&description).expect("can serialize")
+        ),
+        Err(e) => {
+            eprintln!("could not parse message: {e}");
+            std::process::exit(1);
+        }
+    }
+}
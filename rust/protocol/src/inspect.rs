@@ -0,0 +1,511 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Describes serialized protocol messages without decrypting them.
+//!
+//! This is a debugging aid: given the raw bytes of an envelope's content, [`describe`] works out
+//! which kind of message it is and reports the fields that are visible without any keys.
+//!
+//! Descriptions can be serialized with serde when the `json` feature is enabled.
+
+use uuid::Uuid;
+
+use crate::sealed_sender::{
+    UnidentifiedSenderMessage, SEALED_SENDER_V1_FULL_VERSION,
+    SEALED_SENDER_V2_SERVICE_ID_FULL_VERSION, SEALED_SENDER_V2_UUID_FULL_VERSION,
+};
+use crate::{
+    CiphertextMessageType, PlaintextContent, PreKeySignalMessage, Result,
+    SealedSenderV2SentMessage, SenderKeyDistributionMessage, SenderKeyMessage, SignalMessage,
+    SignalProtocolError, UnidentifiedSenderMessageContent,
+};
+
+/// The visible parts of a 1:1 [`SignalMessage`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct SignalMessageDescription {
+    pub message_version: u8,
+    #[cfg_attr(feature = "json", serde(with = "hex"))]
+    pub sender_ratchet_key: Box<[u8]>,
+    pub counter: u32,
+    pub previous_counter: u32,
+    pub ciphertext_len: usize,
+}
+
+/// A single recipient of a [`SealedSenderV2SentMessage`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct SealedSenderV2RecipientDescription {
+    pub service_id: String,
+    /// Device IDs and their registration IDs.
+    pub devices: Vec<(u32, u16)>,
+}
+
+/// A structured description of a serialized message, as produced by [`describe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum MessageDescription {
+    Signal(SignalMessageDescription),
+    PreKeySignal {
+        message_version: u8,
+        registration_id: u32,
+        pre_key_id: Option<u32>,
+        signed_pre_key_id: u32,
+        kyber_pre_key_id: Option<u32>,
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        base_key: Box<[u8]>,
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        identity_key: Box<[u8]>,
+        message: SignalMessageDescription,
+    },
+    SenderKey {
+        message_version: u8,
+        distribution_id: Uuid,
+        chain_id: u32,
+        iteration: u32,
+        ciphertext_len: usize,
+    },
+    SenderKeyDistribution {
+        message_version: u8,
+        distribution_id: Uuid,
+        chain_id: u32,
+        iteration: u32,
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        signing_key: Box<[u8]>,
+    },
+    Plaintext {
+        body_len: usize,
+    },
+    SealedSenderV1 {
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        ephemeral_public: Box<[u8]>,
+        encrypted_message_len: usize,
+    },
+    SealedSenderV2Received {
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        ephemeral_public: Box<[u8]>,
+        encrypted_message_len: usize,
+    },
+    SealedSenderV2Sent {
+        version: u8,
+        recipients: Vec<SealedSenderV2RecipientDescription>,
+    },
+    /// The decrypted outer layer of a sealed sender message.
+    UnidentifiedSenderMessageContent {
+        sender_uuid: String,
+        sender_device_id: u32,
+        content_hint: u32,
+        #[cfg_attr(feature = "json", serde(with = "hex"))]
+        group_id: Box<[u8]>,
+        contents: Box<MessageDescription>,
+    },
+}
+
+/// Works out what kind of message `bytes` holds and describes its unencrypted fields.
+///
+/// Detection is based on the leading version byte, falling back to trying each plausible format
+/// in turn. Sealed sender v2 messages with version `0x22` are assumed to be in the "received"
+/// format delivered to clients, since the older multi-recipient "sent" format used the same
+/// version byte.
+///
+/// Serialized [`UnidentifiedSenderMessageContent`]s, which have no version byte, are recognized
+/// only if nothing else matches.
+pub fn describe(bytes: &[u8]) -> Result<MessageDescription> {
+    let Some(&first_byte) = bytes.first() else {
+        return Err(SignalProtocolError::CiphertextMessageTooShort(0));
+    };
+
+    match first_byte {
+        PlaintextContent::PLAINTEXT_CONTEXT_IDENTIFIER_BYTE => {
+            let message = PlaintextContent::try_from(bytes)?;
+            Ok(MessageDescription::Plaintext {
+                body_len: message.body().len(),
+            })
+        }
+        SEALED_SENDER_V1_FULL_VERSION | SEALED_SENDER_V2_UUID_FULL_VERSION => {
+            describe_unidentified_sender_message(bytes)
+        }
+        SEALED_SENDER_V2_SERVICE_ID_FULL_VERSION => describe_sealed_sender_v2_sent_message(bytes),
+        _ => describe_versioned_message(bytes).or_else(|e| {
+            describe_unidentified_sender_message_content(
+                &UnidentifiedSenderMessageContent::deserialize(bytes).map_err(|_| e)?,
+            )
+        }),
+    }
+}
+
+/// Describes `bytes` as the given message type, without any auto-detection.
+pub fn describe_as(
+    bytes: &[u8],
+    message_type: CiphertextMessageType,
+) -> Result<MessageDescription> {
+    Ok(match message_type {
+        CiphertextMessageType::Whisper => {
+            MessageDescription::Signal(describe_signal_message(&SignalMessage::try_from(bytes)?))
+        }
+        CiphertextMessageType::PreKey => {
+            describe_pre_key_signal_message(&PreKeySignalMessage::try_from(bytes)?)
+        }
+        CiphertextMessageType::SenderKey => {
+            describe_sender_key_message(&SenderKeyMessage::try_from(bytes)?)
+        }
+        CiphertextMessageType::Plaintext => MessageDescription::Plaintext {
+            body_len: PlaintextContent::try_from(bytes)?.body().len(),
+        },
+    })
+}
+
+/// Describes the outer layer of a sealed sender message after it has been decrypted, along with
+/// the message it contains.
+pub fn describe_unidentified_sender_message_content(
+    usmc: &UnidentifiedSenderMessageContent,
+) -> Result<MessageDescription> {
+    let sender = usmc.sender()?;
+    Ok(MessageDescription::UnidentifiedSenderMessageContent {
+        sender_uuid: sender.sender_uuid()?.to_owned(),
+        sender_device_id: sender.sender_device_id()?.into(),
+        content_hint: usmc.content_hint()?.into(),
+        group_id: usmc.group_id()?.unwrap_or_default().into(),
+        contents: Box::new(describe_as(usmc.contents()?, usmc.msg_type()?)?),
+    })
+}
+
+/// Tries each of the formats that share the protocol message version byte.
+fn describe_versioned_message(bytes: &[u8]) -> Result<MessageDescription> {
+    // The formats are distinguishable because their protobuf fields have different types, and
+    // because SignalMessage and SenderKeyMessage have different-length trailers. Try the most
+    // common first, and report that failure if nothing matches.
+    let error = match PreKeySignalMessage::try_from(bytes) {
+        Ok(message) => return Ok(describe_pre_key_signal_message(&message)),
+        Err(e) => e,
+    };
+    if let Ok(message) = SignalMessage::try_from(bytes) {
+        return Ok(MessageDescription::Signal(describe_signal_message(
+            &message,
+        )));
+    }
+    if let Ok(message) = SenderKeyMessage::try_from(bytes) {
+        return Ok(describe_sender_key_message(&message));
+    }
+    if let Ok(message) = SenderKeyDistributionMessage::try_from(bytes) {
+        return Ok(MessageDescription::SenderKeyDistribution {
+            message_version: message.message_version(),
+            distribution_id: message.distribution_id()?,
+            chain_id: message.chain_id()?,
+            iteration: message.iteration()?,
+            signing_key: message.signing_key()?.serialize(),
+        });
+    }
+    Err(error)
+}
+
+fn describe_signal_message(message: &SignalMessage) -> SignalMessageDescription {
+    SignalMessageDescription {
+        message_version: message.message_version(),
+        sender_ratchet_key: message.sender_ratchet_key().serialize(),
+        counter: message.counter(),
+        previous_counter: message.previous_counter(),
+        ciphertext_len: message.body().len(),
+    }
+}
+
+fn describe_pre_key_signal_message(message: &PreKeySignalMessage) -> MessageDescription {
+    MessageDescription::PreKeySignal {
+        message_version: message.message_version(),
+        registration_id: message.registration_id(),
+        pre_key_id: message.pre_key_id().map(Into::into),
+        signed_pre_key_id: message.signed_pre_key_id().into(),
+        kyber_pre_key_id: message.kyber_pre_key_id().map(Into::into),
+        base_key: message.base_key().serialize(),
+        identity_key: message.identity_key().serialize(),
+        message: describe_signal_message(message.message()),
+    }
+}
+
+fn describe_sender_key_message(message: &SenderKeyMessage) -> MessageDescription {
+    MessageDescription::SenderKey {
+        message_version: message.message_version(),
+        distribution_id: message.distribution_id(),
+        chain_id: message.chain_id(),
+        iteration: message.iteration(),
+        ciphertext_len: message.ciphertext().len(),
+    }
+}
+
+fn describe_unidentified_sender_message(bytes: &[u8]) -> Result<MessageDescription> {
+    Ok(match UnidentifiedSenderMessage::deserialize(bytes)? {
+        UnidentifiedSenderMessage::V1 {
+            ephemeral_public,
+            encrypted_static: _,
+            encrypted_message,
+        } => MessageDescription::SealedSenderV1 {
+            ephemeral_public: ephemeral_public.serialize(),
+            encrypted_message_len: encrypted_message.len(),
+        },
+        UnidentifiedSenderMessage::V2 {
+            ephemeral_public,
+            encrypted_message_key: _,
+            authentication_tag: _,
+            encrypted_message,
+        } => MessageDescription::SealedSenderV2Received {
+            ephemeral_public: ephemeral_public.serialize(),
+            encrypted_message_len: encrypted_message.len(),
+        },
+    })
+}
+
+fn describe_sealed_sender_v2_sent_message(bytes: &[u8]) -> Result<MessageDescription> {
+    let message = SealedSenderV2SentMessage::parse(bytes)?;
+    Ok(MessageDescription::SealedSenderV2Sent {
+        version: message.version,
+        recipients: message
+            .recipients
+            .iter()
+            .map(
+                |(service_id, recipient)| SealedSenderV2RecipientDescription {
+                    service_id: service_id.service_id_string(),
+                    devices: recipient
+                        .devices
+                        .iter()
+                        .map(|&(device_id, registration_id)| (device_id.into(), registration_id))
+                        .collect(),
+                },
+            )
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use hex_literal::hex;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{
+        ContentHint, IdentityKeyPair, KeyPair, SenderCertificate, ServerCertificate, Timestamp,
+    };
+
+    fn signal_message() -> SignalMessage {
+        let mut csprng = OsRng;
+        let sender_identity = IdentityKeyPair::generate(&mut csprng);
+        let receiver_identity = IdentityKeyPair::generate(&mut csprng);
+        SignalMessage::new(
+            4,
+            &[0x42; 32],
+            KeyPair::generate(&mut csprng).public_key,
+            7,
+            6,
+            b"ciphertext",
+            sender_identity.identity_key(),
+            receiver_identity.identity_key(),
+        )
+        .expect("valid")
+    }
+
+    #[test]
+    fn describes_signal_message() {
+        let message = signal_message();
+        let description = describe(message.serialized()).expect("valid");
+        assert_matches!(description, MessageDescription::Signal(SignalMessageDescription {
+            message_version: 4,
+            counter: 7,
+            previous_counter: 6,
+            ciphertext_len: 10,
+            sender_ratchet_key,
+        }) => assert_eq!(sender_ratchet_key, message.sender_ratchet_key().serialize()));
+    }
+
+    #[test]
+    fn describes_pre_key_signal_message() {
+        let mut csprng = OsRng;
+        let identity = IdentityKeyPair::generate(&mut csprng);
+        let message = PreKeySignalMessage::new(
+            4,
+            1234,
+            Some(5.into()),
+            6.into(),
+            None,
+            KeyPair::generate(&mut csprng).public_key,
+            *identity.identity_key(),
+            signal_message(),
+        )
+        .expect("valid");
+
+        let description = describe(message.serialized()).expect("valid");
+        assert_matches!(
+            description,
+            MessageDescription::PreKeySignal {
+                registration_id: 1234,
+                pre_key_id: Some(5),
+                signed_pre_key_id: 6,
+                kyber_pre_key_id: None,
+                message: SignalMessageDescription { counter: 7, .. },
+                ..
+            }
+        );
+        assert_eq!(
+            describe_as(message.serialized(), CiphertextMessageType::PreKey).expect("valid"),
+            description
+        );
+    }
+
+    #[test]
+    fn describes_sender_key_messages() {
+        let mut csprng = OsRng;
+        let signing_key = KeyPair::generate(&mut csprng);
+        let distribution_id = Uuid::from_u128(0x1234);
+
+        let message = SenderKeyMessage::new(
+            3,
+            distribution_id,
+            1,
+            2,
+            b"group ciphertext".to_vec().into_boxed_slice(),
+            &mut csprng,
+            &signing_key.private_key,
+        )
+        .expect("valid");
+        assert_eq!(
+            describe(message.serialized()).expect("valid"),
+            MessageDescription::SenderKey {
+                message_version: 3,
+                distribution_id,
+                chain_id: 1,
+                iteration: 2,
+                ciphertext_len: 16,
+            }
+        );
+
+        let message = SenderKeyDistributionMessage::new(
+            3,
+            distribution_id,
+            1,
+            2,
+            vec![0; 32],
+            signing_key.public_key,
+        )
+        .expect("valid");
+        assert_eq!(
+            describe(message.serialized()).expect("valid"),
+            MessageDescription::SenderKeyDistribution {
+                message_version: 3,
+                distribution_id,
+                chain_id: 1,
+                iteration: 2,
+                signing_key: signing_key.public_key.serialize(),
+            }
+        );
+    }
+
+    #[test]
+    fn describes_sealed_sender_fixtures() {
+        let v1 = hex!(
+            "11"
+            "0a21 05 0101010101010101010101010101010101010101010101010101010101010101"
+            "1204 aabbccdd"
+            "1a05 0102030405"
+        );
+        assert_eq!(
+            describe(&v1).expect("valid"),
+            MessageDescription::SealedSenderV1 {
+                ephemeral_public: [[0x05].as_slice(), &[0x01; 32]].concat().into(),
+                encrypted_message_len: 5,
+            }
+        );
+
+        let v2 = hex!(
+            "22"
+            "0202020202020202020202020202020202020202020202020202020202020202"
+            "03030303030303030303030303030303"
+            "0404040404040404040404040404040404040404040404040404040404040404"
+            "7365616c6564"
+        );
+        assert_eq!(
+            describe(&v2).expect("valid"),
+            MessageDescription::SealedSenderV2Received {
+                ephemeral_public: [[0x05].as_slice(), &[0x04; 32]].concat().into(),
+                encrypted_message_len: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn describes_unidentified_sender_message_content() {
+        let mut csprng = OsRng;
+        let trust_root = KeyPair::generate(&mut csprng);
+        let server_key = KeyPair::generate(&mut csprng);
+        let server_certificate = ServerCertificate::new(
+            1,
+            server_key.public_key,
+            &trust_root.private_key,
+            &mut csprng,
+        )
+        .expect("valid");
+        let sender_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f";
+        let sender_certificate = SenderCertificate::new(
+            sender_uuid.to_owned(),
+            None,
+            KeyPair::generate(&mut csprng).public_key,
+            23.into(),
+            Timestamp::from_epoch_millis(1605722925),
+            server_certificate,
+            &server_key.private_key,
+            &mut csprng,
+        )
+        .expect("valid");
+
+        let message = signal_message();
+        let usmc = UnidentifiedSenderMessageContent::new(
+            CiphertextMessageType::Whisper,
+            sender_certificate,
+            message.serialized().to_vec(),
+            ContentHint::Resendable,
+            Some(b"group".to_vec()),
+        )
+        .expect("valid");
+
+        let description = describe(usmc.serialized().expect("valid")).expect("valid");
+        assert_eq!(
+            description,
+            MessageDescription::UnidentifiedSenderMessageContent {
+                sender_uuid: sender_uuid.to_owned(),
+                sender_device_id: 23,
+                content_hint: ContentHint::Resendable.into(),
+                group_id: b"group".as_slice().into(),
+                contents: Box::new(MessageDescription::Signal(describe_signal_message(
+                    &message
+                ))),
+            }
+        );
+        assert_eq!(
+            describe_unidentified_sender_message_content(&usmc).expect("valid"),
+            description
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn serializes_to_json() {
+        let description = describe(signal_message().serialized()).expect("valid");
+        let json = serde_json::to_value(&description).expect("can serialize");
+        assert_eq!(json["type"], "signal");
+        assert_eq!(json["counter"], 7);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_matches!(
+            describe(&[]),
+            Err(SignalProtocolError::CiphertextMessageTooShort(0))
+        );
+        assert!(describe(&[0x44; 20]).is_err());
+        assert!(describe(&[0x11, 0x00]).is_err());
+    }
+}
@@ -29,6 +29,7 @@ mod fingerprint;
 mod group_cipher;
 mod identity_key;
 pub mod incremental_mac;
+pub mod inspect;
 pub mod kem;
 mod proto;
 mod protocol;
@@ -62,7 +62,6 @@ pub struct SignalMessage {
     message_version: u8,
     sender_ratchet_key: PublicKey,
     counter: u32,
-    #[allow(dead_code)]
     previous_counter: u32,
     ciphertext: Box<[u8]>,
     serialized: Box<[u8]>,
@@ -125,6 +124,11 @@ impl SignalMessage {
         self.counter
     }
 
+    #[inline]
+    pub fn previous_counter(&self) -> u32 {
+        self.previous_counter
+    }
+
     #[inline]
     pub fn serialized(&self) -> &[u8] {
         &self.serialized
@@ -729,7 +733,7 @@ impl PlaintextContent {
     ///
     /// This ensures someone doesn't try to serialize an arbitrary Content message as
     /// PlaintextContent; only messages that are okay to send as plaintext should be allowed.
-    const PLAINTEXT_CONTEXT_IDENTIFIER_BYTE: u8 = 0xC0;
+    pub(crate) const PLAINTEXT_CONTEXT_IDENTIFIER_BYTE: u8 = 0xC0;
 
     /// Marks the end of a message and the start of any padding.
     ///
@@ -515,7 +515,7 @@ impl UnidentifiedSenderMessageContent {
     }
 }
 
-enum UnidentifiedSenderMessage<'a> {
+pub(crate) enum UnidentifiedSenderMessage<'a> {
     V1 {
         ephemeral_public: PublicKey,
         encrypted_static: Vec<u8>,
@@ -530,13 +530,13 @@ enum UnidentifiedSenderMessage<'a> {
 }
 
 const SEALED_SENDER_V1_MAJOR_VERSION: u8 = 1;
-const SEALED_SENDER_V1_FULL_VERSION: u8 = 0x11;
+pub(crate) const SEALED_SENDER_V1_FULL_VERSION: u8 = 0x11;
 const SEALED_SENDER_V2_MAJOR_VERSION: u8 = 2;
-const SEALED_SENDER_V2_UUID_FULL_VERSION: u8 = 0x22;
-const SEALED_SENDER_V2_SERVICE_ID_FULL_VERSION: u8 = 0x23;
+pub(crate) const SEALED_SENDER_V2_UUID_FULL_VERSION: u8 = 0x22;
+pub(crate) const SEALED_SENDER_V2_SERVICE_ID_FULL_VERSION: u8 = 0x23;
 
 impl<'a> UnidentifiedSenderMessage<'a> {
-    fn deserialize(data: &'a [u8]) -> Result<Self> {
+    pub(crate) fn deserialize(data: &'a [u8]) -> Result<Self> {
         let (version_byte, remaining) = data.split_first().ok_or_else(|| {
             SignalProtocolError::InvalidSealedSenderMessage("Message was empty".to_owned())
         })?;
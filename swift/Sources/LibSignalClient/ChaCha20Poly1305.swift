@@ -0,0 +1,145 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// Streamed ChaCha20-Poly1305 encryption, as specified in RFC 8439.
+public class ChaCha20Poly1305Encryption: NativeHandleOwner<SignalMutPointerChaCha20Poly1305Encryption> {
+    public convenience init(
+        key: some ContiguousBytes,
+        nonce: some ContiguousBytes,
+        associatedData: some ContiguousBytes
+    ) throws {
+        let handle = try key.withUnsafeBorrowedBuffer { keyBuffer in
+            try nonce.withUnsafeBorrowedBuffer { nonceBuffer in
+                try associatedData.withUnsafeBorrowedBuffer { adBuffer in
+                    var result = SignalMutPointerChaCha20Poly1305Encryption()
+                    try checkError(signal_chacha20_poly1305_encryption_new(
+                        &result,
+                        keyBuffer,
+                        nonceBuffer,
+                        adBuffer
+                    ))
+                    return result
+                }
+            }
+        }
+        self.init(owned: NonNull(handle)!)
+    }
+
+    override internal class func destroyNativeHandle(_ handle: NonNull<SignalMutPointerChaCha20Poly1305Encryption>) -> SignalFfiErrorRef? {
+        return signal_chacha20_poly1305_encryption_destroy(handle.pointer)
+    }
+
+    public func encrypt(_ message: inout Data) throws {
+        try withNativeHandle { nativeHandle in
+            try message.withUnsafeMutableBytes { messageBytes in
+                try checkError(signal_chacha20_poly1305_encryption_update(
+                    nativeHandle,
+                    SignalBorrowedMutableBuffer(messageBytes),
+                    0,
+                    UInt32(messageBytes.count)
+                ))
+            }
+        }
+    }
+
+    public func computeTag() throws -> Data {
+        return try withNativeHandle { nativeHandle in
+            try invokeFnReturningData {
+                signal_chacha20_poly1305_encryption_compute_tag($0, nativeHandle)
+            }
+        }
+    }
+}
+
+extension SignalMutPointerChaCha20Poly1305Encryption: SignalMutPointer {
+    public typealias ConstPointer = OpaquePointer?
+
+    public init(untyped: OpaquePointer?) {
+        self.init(raw: untyped)
+    }
+
+    public func toOpaque() -> OpaquePointer? {
+        self.raw
+    }
+
+    public func const() -> Self.ConstPointer {
+        nil
+    }
+}
+
+/// Streamed ChaCha20-Poly1305 decryption, as specified in RFC 8439.
+public class ChaCha20Poly1305Decryption: NativeHandleOwner<SignalMutPointerChaCha20Poly1305Decryption> {
+    public convenience init(
+        key: some ContiguousBytes,
+        nonce: some ContiguousBytes,
+        associatedData: some ContiguousBytes
+    ) throws {
+        let handle = try key.withUnsafeBorrowedBuffer { keyBuffer in
+            try nonce.withUnsafeBorrowedBuffer { nonceBuffer in
+                try associatedData.withUnsafeBorrowedBuffer { adBuffer in
+                    var result = SignalMutPointerChaCha20Poly1305Decryption()
+                    try checkError(signal_chacha20_poly1305_decryption_new(
+                        &result,
+                        keyBuffer,
+                        nonceBuffer,
+                        adBuffer
+                    ))
+                    return result
+                }
+            }
+        }
+        self.init(owned: NonNull(handle)!)
+    }
+
+    override internal class func destroyNativeHandle(_ handle: NonNull<SignalMutPointerChaCha20Poly1305Decryption>) -> SignalFfiErrorRef? {
+        return signal_chacha20_poly1305_decryption_destroy(handle.pointer)
+    }
+
+    public func decrypt(_ message: inout Data) throws {
+        try withNativeHandle { nativeHandle in
+            try message.withUnsafeMutableBytes { messageBytes in
+                try checkError(signal_chacha20_poly1305_decryption_update(
+                    nativeHandle,
+                    SignalBorrowedMutableBuffer(messageBytes),
+                    0,
+                    UInt32(messageBytes.count)
+                ))
+            }
+        }
+    }
+
+    public func verifyTag(_ tag: some ContiguousBytes) throws -> Bool {
+        return try withNativeHandle { nativeHandle in
+            try tag.withUnsafeBorrowedBuffer { tagBuffer in
+                var result = false
+                try checkError(signal_chacha20_poly1305_decryption_verify_tag(
+                    &result,
+                    nativeHandle,
+                    tagBuffer
+                ))
+                return result
+            }
+        }
+    }
+}
+
+extension SignalMutPointerChaCha20Poly1305Decryption: SignalMutPointer {
+    public typealias ConstPointer = OpaquePointer?
+
+    public init(untyped: OpaquePointer?) {
+        self.init(raw: untyped)
+    }
+
+    public func toOpaque() -> OpaquePointer? {
+        self.raw
+    }
+
+    public func const() -> Self.ConstPointer {
+        nil
+    }
+}
@@ -103,14 +103,20 @@ public class AuthenticatedChatConnection: NativeHandleOwner<
     /// after ``AuthenticatedChatConnection/start(listener:)`` is called.
     internal init(
         tokioAsyncContext: TokioAsyncContext, connectionManager: ConnectionManager,
-        username: String, password: String, receiveStories: Bool
+        username: String, password: String, receiveStories: Bool, noisePrivateKey: PrivateKey?
     ) async throws {
         let nativeHandle = try await tokioAsyncContext.invokeAsyncFunction { promise, tokioAsyncContext in
             connectionManager.withNativeHandle { connectionManager in
-                signal_authenticated_chat_connection_connect(
-                    promise, tokioAsyncContext.const(), connectionManager.const(), username,
-                    password, receiveStories
-                )
+                let connect = { (noisePrivateKey: SignalConstPointerPrivateKey) in
+                    signal_authenticated_chat_connection_connect(
+                        promise, tokioAsyncContext.const(), connectionManager.const(), username,
+                        password, receiveStories, noisePrivateKey
+                    )
+                }
+                guard let noisePrivateKey else {
+                    return connect(SignalConstPointerPrivateKey(raw: nil))
+                }
+                return noisePrivateKey.withNativeHandle { connect($0.const()) }
             }
         }
         self.tokioAsyncContext = tokioAsyncContext
@@ -0,0 +1,146 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// A single attempt to connect over one route, as reported to
+/// ``Net/setConnectionAttemptListener(_:)``.
+public struct ConnectionAttempt: Sendable {
+    public enum Result: UInt8, Sendable {
+        case succeeded = 0
+        /// The attempt failed, but other routes might still work.
+        case failed = 1
+        /// The server rejected the connection, so no other routes were tried.
+        case rejected = 2
+        /// The attempt was still in progress when another route succeeded or the overall
+        /// connection attempt timed out.
+        case abandoned = 3
+    }
+
+    /// The stage at which an attempt failed.
+    public enum Failure: UInt8, Sendable {
+        case dns = 1
+        case tcp = 2
+        case tls = 3
+        case certificatePinMismatch = 4
+        case proxy = 5
+        case timeout = 6
+        /// See ``ConnectionAttempt/httpStatus``.
+        case httpStatus = 7
+        case webSocket = 8
+        case other = 9
+    }
+
+    /// Log-safe description of the route.
+    public var route: String
+    /// The IP version of the address connected to directly (for proxied routes, the proxy's).
+    public var ipType: IpType
+    public var startedAt: Date
+    public var durationMillis: UInt32
+    /// Time from the start of the attempt until the TLS handshake finished, including connecting
+    /// over TCP and through any proxy.
+    public var tlsHandshakeCompletedAfterMillis: UInt32?
+    /// Time taken by the websocket upgrade request, if the server responded to it.
+    public var websocketUpgradeMillis: UInt32?
+    public var result: Result
+    /// Set if ``result`` is ``Result/failed`` or ``Result/rejected``.
+    public var failure: Failure?
+    /// Set if ``failure`` is ``Failure/httpStatus``.
+    public var httpStatus: UInt16?
+}
+
+private class ConnectionAttemptHandle: NativeHandleOwner<SignalMutPointerConnectionAttempt> {
+    override class func destroyNativeHandle(_ handle: NonNull<SignalMutPointerConnectionAttempt>) -> SignalFfiErrorRef? {
+        signal_connection_attempt_destroy(handle.pointer)
+    }
+
+    func read() throws -> ConnectionAttempt {
+        try withNativeHandle { attempt in
+            let attempt = attempt.const()
+            let optionalMillis = { (value: UInt32) in value == UInt32.max ? nil : value }
+            let httpStatus = try invokeFnReturningInteger {
+                signal_connection_attempt_http_status($0, attempt)
+            }
+            return ConnectionAttempt(
+                route: try invokeFnReturningString {
+                    signal_connection_attempt_route($0, attempt)
+                },
+                ipType: IpType(rawValue: try invokeFnReturningInteger {
+                    signal_connection_attempt_ip_version($0, attempt)
+                }) ?? .unknown,
+                startedAt: Date(timeIntervalSince1970: Double(try invokeFnReturningInteger {
+                    signal_connection_attempt_started_at($0, attempt)
+                }) / 1000),
+                durationMillis: try invokeFnReturningInteger {
+                    signal_connection_attempt_duration_millis($0, attempt)
+                },
+                tlsHandshakeCompletedAfterMillis: optionalMillis(try invokeFnReturningInteger {
+                    signal_connection_attempt_tls_handshake_completed_after_millis($0, attempt)
+                }),
+                websocketUpgradeMillis: optionalMillis(try invokeFnReturningInteger {
+                    signal_connection_attempt_websocket_upgrade_millis($0, attempt)
+                }),
+                result: ConnectionAttempt.Result(rawValue: try invokeFnReturningInteger {
+                    signal_connection_attempt_result($0, attempt)
+                }) ?? .failed,
+                failure: ConnectionAttempt.Failure(rawValue: try invokeFnReturningInteger {
+                    signal_connection_attempt_error($0, attempt)
+                }),
+                httpStatus: httpStatus == 0 ? nil : httpStatus
+            )
+        }
+    }
+}
+
+internal class ConnectionAttemptListenerBridge {
+    private let listener: (ConnectionAttempt) -> Void
+
+    internal init(_ listener: @escaping (ConnectionAttempt) -> Void) {
+        self.listener = listener
+    }
+
+    /// Creates an **owned** callback struct from this object.
+    ///
+    /// The resulting struct must eventually have its `destroy` callback invoked with its `ctx` as argument,
+    /// or the ConnectionAttemptListenerBridge object used to construct it (`self`) will be leaked.
+    func makeListenerStruct() -> SignalFfiConnectionAttemptListenerStruct {
+        let connectionAttempted: SignalConnectionAttempted = { rawCtx, attemptHandle in
+            let bridge = Unmanaged<ConnectionAttemptListenerBridge>.fromOpaque(rawCtx!).takeUnretainedValue()
+            let handle = ConnectionAttemptHandle(owned: NonNull(attemptHandle)!)
+            let attempt = failOnError { try handle.read() }
+            bridge.listener(attempt)
+        }
+        return .init(
+            ctx: Unmanaged.passRetained(self).toOpaque(),
+            connection_attempted: connectionAttempted,
+            destroy: { rawCtx in
+                _ = Unmanaged<AnyObject>.fromOpaque(rawCtx!).takeRetainedValue()
+            }
+        )
+    }
+}
+
+extension SignalMutPointerConnectionAttempt: SignalMutPointer {
+    public typealias ConstPointer = SignalConstPointerConnectionAttempt
+
+    public init(untyped: OpaquePointer?) {
+        self.init(raw: untyped)
+    }
+
+    public func toOpaque() -> OpaquePointer? {
+        self.raw
+    }
+
+    public func const() -> SignalConstPointerConnectionAttempt {
+        SignalConstPointerConnectionAttempt(raw: self.raw)
+    }
+}
+
+extension SignalConstPointerConnectionAttempt: SignalConstPointer {
+    public func toOpaque() -> OpaquePointer? {
+        self.raw
+    }
+}
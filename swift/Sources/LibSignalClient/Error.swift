@@ -63,6 +63,7 @@ public enum SignalError: Error {
     case chatServiceInactive(String)
     case appExpired(String)
     case deviceDeregistered(String)
+    case certificatePinMismatch(String)
 
     case unknown(UInt32, String)
 }
@@ -220,6 +221,8 @@ internal func checkError(_ error: SignalFfiErrorRef?) throws {
         throw SignalError.appExpired(errStr)
     case SignalErrorCodeDeviceDeregistered:
         throw SignalError.deviceDeregistered(errStr)
+    case SignalErrorCodeCertificatePinMismatch:
+        throw SignalError.certificatePinMismatch(errStr)
     case SignalErrorCodeBackupValidation:
         let unknownFields = try invokeFnReturningStringArray {
             signal_error_get_unknown_fields(error, $0)
@@ -32,6 +32,43 @@ public func hkdf(
     return output
 }
 
+/// The hash functions supported by ``hkdf(digest:outputLength:inputKeyMaterial:salt:info:)``.
+public enum HkdfDigest: UInt8, Sendable {
+    // These must be kept in sync with DigestAlgorithm in signal-crypto.
+    case sha1 = 0
+    case sha256 = 1
+    case sha512 = 2
+}
+
+/// Derives `outputLength` bytes with HKDF, using `digest` as the underlying hash.
+///
+/// Throws ``SignalError/invalidArgument(_:)`` if `outputLength` is more than 255 times the digest
+/// size.
+public func hkdf(
+    digest: HkdfDigest,
+    outputLength: Int,
+    inputKeyMaterial: some ContiguousBytes,
+    salt: some ContiguousBytes,
+    info: some ContiguousBytes
+) throws -> [UInt8] {
+    try inputKeyMaterial.withUnsafeBorrowedBuffer { inputBuffer in
+        try salt.withUnsafeBorrowedBuffer { saltBuffer in
+            try info.withUnsafeBorrowedBuffer { infoBuffer in
+                try invokeFnReturningArray {
+                    signal_hkdf_derive_with_digest(
+                        $0,
+                        digest.rawValue,
+                        UInt32(outputLength),
+                        inputBuffer,
+                        infoBuffer,
+                        saltBuffer
+                    )
+                }
+            }
+        }
+    }
+}
+
 @available(*, deprecated, message: "Remove the 'version' parameter for standard HKDF behavior")
 public func hkdf(
     outputLength: Int,
@@ -72,6 +72,26 @@ public class Net {
         try self.connectionManager.setProxy(scheme: Net.signalTlsProxyScheme, host: host, port: port, username: username, password: nil)
     }
 
+    /// Chooses a proxy (or none) per destination for all new connections, from a PAC-like list of
+    /// rules, one per line.
+    ///
+    /// Each line is a host pattern (`*`, a domain, `*.suffix`, an IP address, or a CIDR network)
+    /// followed by `DIRECT`, `PROXY host:port`, `HTTPS host:port`, `SOCKS host:port` (SOCKS4),
+    /// `SOCKS4 host:port`, or `SOCKS5 host:port`. The first matching rule wins; destinations that
+    /// match no rule are connected to directly. `#` starts a comment.
+    ///
+    /// This replaces any proxy set with ``Net/setProxy(host:port:)``, and is in turn replaced by
+    /// setting a proxy, ``Net/setInvalidProxy()``, or ``Net/clearProxy()``.
+    ///
+    /// Existing connections and services will continue with the setting they were created with.
+    /// (In particular, changing this setting will not affect any existing ``ChatConnection``s.)
+    ///
+    /// - Throws: ``SignalError/ioError(_:)`` if the rules can't be parsed. In that case, all new
+    ///   connections will fail until ``Net/clearProxy()`` is called.
+    public func setProxyRules(_ rules: String) throws {
+        try self.connectionManager.setProxyRules(rules)
+    }
+
     /// Refuses to make any new connections until a new proxy configuration is set or
     /// ``Net/clearProxy()`` is called.
     ///
@@ -102,6 +122,50 @@ public class Net {
         self.connectionManager.setCensorshipCircumventionEnabled(enabled)
     }
 
+    /// Enables or disables compression for all new chat connections (until changed).
+    ///
+    /// Existing connections will continue with the setting they were created with. When enabled,
+    /// each message is compressed independently.
+    ///
+    /// Compression is off by default.
+    public func setChatCompressionEnabled(_ enabled: Bool) {
+        self.connectionManager.setChatCompressionEnabled(enabled)
+    }
+
+    /// Saves DNS results in files in `directory`, so that they can be reused after a restart.
+    ///
+    /// The directory must already exist. Passing `nil` stops saving results. Results are only
+    /// saved and used while a network identity is set with ``Net/setNetworkIdentity(_:)``.
+    public func setDnsCacheDirectory(_ directory: String?) {
+        self.connectionManager.setDnsCacheDirectory(directory)
+    }
+
+    /// Saves which connection routes recently failed in files in `directory`, so that they are
+    /// still tried last after a restart.
+    ///
+    /// The directory must already exist. Passing `nil` stops saving route history.
+    public func setRouteHistoryDirectory(_ directory: String?) {
+        self.connectionManager.setRouteHistoryDirectory(directory)
+    }
+
+    /// Calls `listener` with details of every subsequent attempt to connect over a single route,
+    /// e.g. to track which routes work on the current network.
+    ///
+    /// Calls are serialized but may happen on any thread, and should return promptly. Passing
+    /// `nil` stops reporting attempts.
+    public func setConnectionAttemptListener(_ listener: ((ConnectionAttempt) -> Void)?) {
+        self.connectionManager.setConnectionAttemptListener(listener)
+    }
+
+    /// Sets an identifier for the current network, which determines which saved DNS results can
+    /// be used.
+    ///
+    /// This should distinguish networks that might resolve names differently, e.g. a hash of the
+    /// Wi-Fi network name. Passing `nil` stops using saved results until an identity is set again.
+    public func setNetworkIdentity(_ identity: String?) {
+        self.connectionManager.setNetworkIdentity(identity)
+    }
+
     /// Notifies libsignal that the network has changed.
     ///
     /// This will lead to, e.g. caches being cleared and cooldowns being reset.
@@ -210,6 +274,8 @@ public class Net {
     ///   - username: The username to provide; this is typically of the form `{aci}.{deviceId}`.
     ///   - password: The password to provide to the server.
     ///   - receiveStories: Indicates to the server whether it should send story updates on this connection.
+    ///   - noisePrivateKey: The account's identity key, used to identify this device to the server
+    ///     if the connection goes through a Noise tunnel. Without it, the tunnel is anonymous.
     ///
     /// - Throws: ``SignalError/appExpired(_:)`` if the current app version is too old (as judged by
     ///   the server).
@@ -221,8 +287,8 @@ public class Net {
     ///
     /// - Returns:
     ///   An object representing the established, but not yet active, connection.
-    public func connectAuthenticatedChat(username: String, password: String, receiveStories: Bool) async throws -> AuthenticatedChatConnection {
-        return try await AuthenticatedChatConnection(tokioAsyncContext: self.asyncContext, connectionManager: self.connectionManager, username: username, password: password, receiveStories: receiveStories)
+    public func connectAuthenticatedChat(username: String, password: String, receiveStories: Bool, noisePrivateKey: PrivateKey? = nil) async throws -> AuthenticatedChatConnection {
+        return try await AuthenticatedChatConnection(tokioAsyncContext: self.asyncContext, connectionManager: self.connectionManager, username: username, password: password, receiveStories: receiveStories, noisePrivateKey: noisePrivateKey)
     }
 
     /// Asynchronously establishes an unauthenticated connection to the remote
@@ -311,6 +377,12 @@ internal class ConnectionManager: NativeHandleOwner<SignalMutPointerConnectionMa
         }
     }
 
+    internal func setProxyRules(_ rules: String) throws {
+        try self.withNativeHandle {
+            try checkError(signal_connection_manager_set_proxy_rules($0.const(), rules))
+        }
+    }
+
     internal func setInvalidProxy() {
         self.withNativeHandle {
             failOnError(signal_connection_manager_set_invalid_proxy($0.const()))
@@ -329,6 +401,49 @@ internal class ConnectionManager: NativeHandleOwner<SignalMutPointerConnectionMa
         }
     }
 
+    internal func setChatCompressionEnabled(_ enabled: Bool) {
+        self.withNativeHandle {
+            failOnError(signal_connection_manager_set_chat_compression_enabled($0.const(), enabled))
+        }
+    }
+
+    internal func setDnsCacheDirectory(_ directory: String?) {
+        self.withNativeHandle { connectionManager in
+            directory.withCString { directory in
+                failOnError(signal_connection_manager_set_dns_cache_directory(connectionManager.const(), directory))
+            }
+        }
+    }
+
+    internal func setRouteHistoryDirectory(_ directory: String?) {
+        self.withNativeHandle { connectionManager in
+            directory.withCString { directory in
+                failOnError(signal_connection_manager_set_route_history_directory(connectionManager.const(), directory))
+            }
+        }
+    }
+
+    internal func setConnectionAttemptListener(_ listener: ((ConnectionAttempt) -> Void)?) {
+        self.withNativeHandle { connectionManager in
+            guard let listener else {
+                failOnError(signal_connection_manager_set_connection_attempt_listener(connectionManager.const(), .init()))
+                return
+            }
+            var listenerStruct = ConnectionAttemptListenerBridge(listener).makeListenerStruct()
+            withUnsafePointer(to: &listenerStruct) { listenerStruct in
+                failOnError(signal_connection_manager_set_connection_attempt_listener(connectionManager.const(), SignalConstPointerFfiConnectionAttemptListenerStruct(raw: listenerStruct)))
+            }
+        }
+    }
+
+    internal func setNetworkIdentity(_ identity: String?) {
+        self.withNativeHandle { connectionManager in
+            identity.withCString { identity in
+                failOnError(signal_connection_manager_set_network_identity(connectionManager.const(), identity))
+            }
+        }
+    }
+
     override internal class func destroyNativeHandle(_ handle: NonNull<SignalMutPointerConnectionManager>) -> SignalFfiErrorRef? {
         signal_connection_manager_destroy(handle.pointer)
     }
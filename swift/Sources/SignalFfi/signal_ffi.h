@@ -205,6 +205,7 @@ typedef enum {
   SignalErrorCodeConnectionFailed = 148,
   SignalErrorCodeChatServiceInactive = 149,
   SignalErrorCodeRequestTimedOut = 150,
+  SignalErrorCodeCertificatePinMismatch = 151,
   SignalErrorCodeSvrDataMissing = 160,
   SignalErrorCodeSvrRestoreFailed = 161,
   SignalErrorCodeSvrRotationMachineTooManySteps = 162,
@@ -226,10 +227,18 @@ typedef struct SignalAes256GcmSiv SignalAes256GcmSiv;
 
 typedef struct SignalAuthenticatedChatConnection SignalAuthenticatedChatConnection;
 
+typedef struct SignalCdnConnection SignalCdnConnection;
+
 typedef struct SignalCdsiLookup SignalCdsiLookup;
 
+typedef struct SignalChaCha20Poly1305Decryption SignalChaCha20Poly1305Decryption;
+
+typedef struct SignalChaCha20Poly1305Encryption SignalChaCha20Poly1305Encryption;
+
 typedef struct SignalCiphertextMessage SignalCiphertextMessage;
 
+typedef struct SignalConnectionAttempt SignalConnectionAttempt;
+
 /**
  * Information about an established connection.
  */
@@ -282,6 +291,8 @@ typedef struct SignalProtocolAddress SignalProtocolAddress;
 
 typedef struct SignalPublicKey SignalPublicKey;
 
+typedef struct SignalResumableUpload SignalResumableUpload;
+
 typedef struct SignalSanitizedMetadata SignalSanitizedMetadata;
 
 typedef struct SignalSenderCertificate SignalSenderCertificate;
@@ -538,6 +549,14 @@ typedef struct {
   SignalAes256GcmDecryption *raw;
 } SignalMutPointerAes256GcmDecryption;
 
+typedef struct {
+  SignalChaCha20Poly1305Encryption *raw;
+} SignalMutPointerChaCha20Poly1305Encryption;
+
+typedef struct {
+  SignalChaCha20Poly1305Decryption *raw;
+} SignalMutPointerChaCha20Poly1305Decryption;
+
 typedef struct {
   unsigned char *base;
   size_t length;
@@ -803,6 +822,43 @@ typedef struct {
   const SignalConnectionManager *raw;
 } SignalConstPointerConnectionManager;
 
+typedef void (*SignalConnectionAttempted)(void *ctx, SignalConnectionAttempt *attempt);
+
+typedef void (*SignalDestroyConnectionAttemptListener)(void *ctx);
+
+/**
+ * Callbacks for [`ConnectionAttemptListener`].
+ *
+ * Callbacks will be serialized (i.e. two calls will not come in at the same time), but may not
+ * always happen on the same thread. Calls should be responded to promptly to avoid holding up
+ * connection attempts.
+ *
+ * # Safety
+ *
+ * This type contains raw pointers. Code that constructs an instance of this type must ensure
+ * memory safety assuming that
+ * - the callback function pointer fields are called with `ctx` as an argument;
+ * - the `destroy` function pointer field is called with `ctx` as an argument;
+ * - no function pointer fields are called after `destroy` is called.
+ */
+typedef struct {
+  void *ctx;
+  SignalConnectionAttempted connection_attempted;
+  SignalDestroyConnectionAttemptListener destroy;
+} SignalFfiConnectionAttemptListenerStruct;
+
+typedef struct {
+  const SignalFfiConnectionAttemptListenerStruct *raw;
+} SignalConstPointerFfiConnectionAttemptListenerStruct;
+
+typedef struct {
+  SignalConnectionAttempt *raw;
+} SignalMutPointerConnectionAttempt;
+
+typedef struct {
+  const SignalConnectionAttempt *raw;
+} SignalConstPointerConnectionAttempt;
+
 typedef struct {
   SignalLookupRequest *raw;
 } SignalMutPointerLookupRequest;
@@ -840,6 +896,84 @@ typedef struct {
   const SignalCdsiLookup *raw;
 } SignalConstPointerCdsiLookup;
 
+typedef struct {
+  SignalCdnConnection *raw;
+} SignalMutPointerCdnConnection;
+
+/**
+ * A C callback used to report the results of Rust futures.
+ *
+ * cbindgen will produce independent C types like `SignalCPromisei32` and
+ * `SignalCPromiseProtocolAddress`.
+ *
+ * This derives Copy because it behaves like a C type; nevertheless, a promise should still only be
+ * completed once.
+ */
+typedef struct {
+  void (*complete)(SignalFfiError *error, const SignalMutPointerCdnConnection *result, const void *context);
+  const void *context;
+  SignalCancellationId cancellation_id;
+} SignalCPromiseMutPointerCdnConnection;
+
+typedef struct {
+  const SignalCdnConnection *raw;
+} SignalConstPointerCdnConnection;
+
+typedef struct {
+  SignalResumableUpload *raw;
+} SignalMutPointerResumableUpload;
+
+/**
+ * A C callback used to report the results of Rust futures.
+ *
+ * cbindgen will produce independent C types like `SignalCPromisei32` and
+ * `SignalCPromiseProtocolAddress`.
+ *
+ * This derives Copy because it behaves like a C type; nevertheless, a promise should still only be
+ * completed once.
+ */
+typedef struct {
+  void (*complete)(SignalFfiError *error, const SignalMutPointerResumableUpload *result, const void *context);
+  const void *context;
+  SignalCancellationId cancellation_id;
+} SignalCPromiseMutPointerResumableUpload;
+
+typedef struct {
+  const SignalResumableUpload *raw;
+} SignalConstPointerResumableUpload;
+
+/**
+ * `total` is `u64::MAX` if the length isn't known yet.
+ */
+typedef void (*SignalTransferProgressed)(void *ctx, uint64_t transferred, uint64_t total);
+
+typedef void (*SignalDestroyTransferProgressListener)(void *ctx);
+
+/**
+ * Callbacks for [`TransferProgressListener`].
+ *
+ * Callbacks will be serialized (i.e. two calls will not come in at the same time), but may not
+ * always happen on the same thread. Calls should be responded to promptly to avoid holding up
+ * the transfer.
+ *
+ * # Safety
+ *
+ * This type contains raw pointers. Code that constructs an instance of this type must ensure
+ * memory safety assuming that
+ * - the callback function pointer fields are called with `ctx` as an argument;
+ * - the `destroy` function pointer field is called with `ctx` as an argument;
+ * - no function pointer fields are called after `destroy` is called.
+ */
+typedef struct {
+  void *ctx;
+  SignalTransferProgressed on_progress;
+  SignalDestroyTransferProgressListener destroy;
+} SignalFfiTransferProgressListenerStruct;
+
+typedef struct {
+  const SignalFfiTransferProgressListenerStruct *raw;
+} SignalConstPointerFfiTransferProgressListenerStruct;
+
 typedef struct {
   SignalOwnedBufferOfFfiCdsiLookupResponseEntry entries;
   int32_t debug_permits_used;
@@ -1116,6 +1250,10 @@ SignalFfiError *signal_aes256_gcm_encryption_destroy(SignalMutPointerAes256GcmEn
 
 SignalFfiError *signal_aes256_gcm_decryption_destroy(SignalMutPointerAes256GcmDecryption p);
 
+SignalFfiError *signal_chacha20_poly1305_encryption_destroy(SignalMutPointerChaCha20Poly1305Encryption p);
+
+SignalFfiError *signal_chacha20_poly1305_decryption_destroy(SignalMutPointerChaCha20Poly1305Decryption p);
+
 SignalFfiError *signal_aes256_ctr32_new(SignalMutPointerAes256Ctr32 *out, SignalBorrowedBuffer key, SignalBorrowedBuffer nonce, uint32_t initial_ctr);
 
 SignalFfiError *signal_aes256_ctr32_process(SignalMutPointerAes256Ctr32 ctr, SignalBorrowedMutableBuffer data, uint32_t offset, uint32_t length);
@@ -1132,12 +1270,26 @@ SignalFfiError *signal_aes256_gcm_decryption_update(SignalMutPointerAes256GcmDec
 
 SignalFfiError *signal_aes256_gcm_decryption_verify_tag(bool *out, SignalMutPointerAes256GcmDecryption gcm, SignalBorrowedBuffer tag);
 
+SignalFfiError *signal_chacha20_poly1305_encryption_new(SignalMutPointerChaCha20Poly1305Encryption *out, SignalBorrowedBuffer key, SignalBorrowedBuffer nonce, SignalBorrowedBuffer associated_data);
+
+SignalFfiError *signal_chacha20_poly1305_encryption_update(SignalMutPointerChaCha20Poly1305Encryption cipher, SignalBorrowedMutableBuffer data, uint32_t offset, uint32_t length);
+
+SignalFfiError *signal_chacha20_poly1305_encryption_compute_tag(SignalOwnedBuffer *out, SignalMutPointerChaCha20Poly1305Encryption cipher);
+
+SignalFfiError *signal_chacha20_poly1305_decryption_new(SignalMutPointerChaCha20Poly1305Decryption *out, SignalBorrowedBuffer key, SignalBorrowedBuffer nonce, SignalBorrowedBuffer associated_data);
+
+SignalFfiError *signal_chacha20_poly1305_decryption_update(SignalMutPointerChaCha20Poly1305Decryption cipher, SignalBorrowedMutableBuffer data, uint32_t offset, uint32_t length);
+
+SignalFfiError *signal_chacha20_poly1305_decryption_verify_tag(bool *out, SignalMutPointerChaCha20Poly1305Decryption cipher, SignalBorrowedBuffer tag);
+
 SignalFfiError *signal_aes256_gcm_siv_new(SignalMutPointerAes256GcmSiv *out, SignalBorrowedBuffer key);
 
 SignalFfiError *signal_aes256_gcm_siv_encrypt(SignalOwnedBuffer *out, SignalConstPointerAes256GcmSiv aes_gcm_siv_obj, SignalBorrowedBuffer ptext, SignalBorrowedBuffer nonce, SignalBorrowedBuffer associated_data);
 
 SignalFfiError *signal_aes256_gcm_siv_decrypt(SignalOwnedBuffer *out, SignalConstPointerAes256GcmSiv aes_gcm_siv, SignalBorrowedBuffer ctext, SignalBorrowedBuffer nonce, SignalBorrowedBuffer associated_data);
 
+SignalFfiError *signal_hkdf_derive_with_digest(SignalOwnedBuffer *out, uint8_t digest, uint32_t output_length, SignalBorrowedBuffer ikm, SignalBorrowedBuffer info, SignalBorrowedBuffer salt);
+
 SignalFfiError *signal_ciphertext_message_destroy(SignalMutPointerCiphertextMessage p);
 
 SignalFfiError *signal_decryption_error_message_destroy(SignalMutPointerDecryptionErrorMessage p);
@@ -1866,14 +2018,42 @@ SignalFfiError *signal_connection_manager_set_invalid_proxy(SignalConstPointerCo
 
 SignalFfiError *signal_connection_manager_clear_proxy(SignalConstPointerConnectionManager connection_manager);
 
+SignalFfiError *signal_connection_manager_set_proxy_rules(SignalConstPointerConnectionManager connection_manager, const char *rules);
+
 SignalFfiError *signal_connection_manager_set_censorship_circumvention_enabled(SignalConstPointerConnectionManager connection_manager, bool enabled);
 
+SignalFfiError *signal_connection_manager_set_chat_compression_enabled(SignalConstPointerConnectionManager connection_manager, bool enabled);
+
+SignalFfiError *signal_connection_manager_set_dns_cache_directory(SignalConstPointerConnectionManager connection_manager, const char *directory);
+
+SignalFfiError *signal_connection_manager_set_route_history_directory(SignalConstPointerConnectionManager connection_manager, const char *directory);
+
+SignalFfiError *signal_connection_manager_set_connection_attempt_listener(SignalConstPointerConnectionManager connection_manager, SignalConstPointerFfiConnectionAttemptListenerStruct listener);
+
+SignalFfiError *signal_connection_manager_set_network_identity(SignalConstPointerConnectionManager connection_manager, const char *identity);
+
 SignalFfiError *signal_connection_manager_on_network_change(SignalConstPointerConnectionManager connection_manager);
 
 SignalFfiError *signal_create_otp(const char **out, const char *username, SignalBorrowedBuffer secret);
 
 SignalFfiError *signal_create_otp_from_base64(const char **out, const char *username, const char *secret);
 
+SignalFfiError *signal_cdn_connection_destroy(SignalMutPointerCdnConnection p);
+
+SignalFfiError *signal_resumable_upload_destroy(SignalMutPointerResumableUpload p);
+
+SignalFfiError *signal_cdn_connection_connect(SignalCPromiseMutPointerCdnConnection *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerConnectionManager connection_manager, const char *hostname);
+
+SignalFfiError *signal_cdn_connection_create_upload(SignalCPromiseMutPointerResumableUpload *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerCdnConnection connection, SignalConstPointerHttpRequest request, uint64_t length, uint32_t timeout_millis);
+
+SignalFfiError *signal_cdn_connection_upload_file(SignalCPromisebool *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerCdnConnection connection, SignalConstPointerResumableUpload upload, const char *file_path, SignalConstPointerFfiTransferProgressListenerStruct progress, uint32_t timeout_millis);
+
+SignalFfiError *signal_cdn_connection_download_to_file(SignalCPromisebool *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerCdnConnection connection, SignalConstPointerHttpRequest request, const char *file_path, SignalConstPointerFfiTransferProgressListenerStruct progress, uint32_t timeout_millis);
+
+SignalFfiError *signal_resumable_upload_resume(SignalMutPointerResumableUpload *out, const char *location, SignalConstPointerHttpRequest request, uint64_t length);
+
+SignalFfiError *signal_resumable_upload_get_location(const char **out, SignalConstPointerResumableUpload upload);
+
 SignalFfiError *signal_lookup_request_destroy(SignalMutPointerLookupRequest p);
 
 SignalFfiError *signal_lookup_request_new(SignalMutPointerLookupRequest *out);
@@ -1924,7 +2104,7 @@ SignalFfiError *signal_unauthenticated_chat_connection_disconnect(SignalCPromise
 
 SignalFfiError *signal_unauthenticated_chat_connection_info(SignalMutPointerChatConnectionInfo *out, SignalConstPointerUnauthenticatedChatConnection chat);
 
-SignalFfiError *signal_authenticated_chat_connection_connect(SignalCPromiseMutPointerAuthenticatedChatConnection *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerConnectionManager connection_manager, const char *username, const char *password, bool receive_stories);
+SignalFfiError *signal_authenticated_chat_connection_connect(SignalCPromiseMutPointerAuthenticatedChatConnection *promise, SignalConstPointerTokioAsyncContext async_runtime, SignalConstPointerConnectionManager connection_manager, const char *username, const char *password, bool receive_stories, SignalConstPointerPrivateKey noise_private_key);
 
 SignalFfiError *signal_authenticated_chat_connection_init_listener(SignalConstPointerAuthenticatedChatConnection chat, SignalConstPointerFfiChatListenerStruct listener);
 
@@ -1938,6 +2118,26 @@ SignalFfiError *signal_server_message_ack_destroy(SignalMutPointerServerMessageA
 
 SignalFfiError *signal_server_message_ack_send(SignalConstPointerServerMessageAck ack);
 
+SignalFfiError *signal_connection_attempt_destroy(SignalMutPointerConnectionAttempt p);
+
+SignalFfiError *signal_connection_attempt_route(const char **out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_ip_version(uint8_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_started_at(uint64_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_duration_millis(uint32_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_tls_handshake_completed_after_millis(uint32_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_websocket_upgrade_millis(uint32_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_result(uint8_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_error(uint8_t *out, SignalConstPointerConnectionAttempt attempt);
+
+SignalFfiError *signal_connection_attempt_http_status(uint16_t *out, SignalConstPointerConnectionAttempt attempt);
+
 SignalFfiError *signal_tokio_async_context_destroy(SignalMutPointerTokioAsyncContext p);
 
 SignalFfiError *signal_tokio_async_context_new(SignalMutPointerTokioAsyncContext *out);
@@ -72,6 +72,9 @@ final class ChatServiceTests: TestCaseBase {
         do {
             try failWithError("DeviceDeregistered")
         } catch SignalError.deviceDeregistered(_) {}
+        do {
+            try failWithError("CertificatePinMismatch")
+        } catch SignalError.certificatePinMismatch(_) {}
         do {
             try failWithError("Disconnected")
         } catch SignalError.chatServiceInactive(_) {}
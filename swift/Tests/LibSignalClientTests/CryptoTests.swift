@@ -157,4 +157,59 @@ class CryptoTests: TestCaseBase {
         try! Aes256Ctr32.process(&ciphertext, key: key, nonce: nonce)
         XCTAssertEqual(ciphertext, expectedCiphertext)
     }
+
+    func testChaCha20Poly1305Kat() {
+        // RFC 8439 section 2.8.2
+        let key: [UInt8] = [0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8A, 0x8B, 0x8C, 0x8D, 0x8E, 0x8F, 0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0x9B, 0x9C, 0x9D, 0x9E, 0x9F]
+        let nonce: [UInt8] = [0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47]
+        let associatedData: [UInt8] = [0x50, 0x51, 0x52, 0x53, 0xC0, 0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7]
+        let plaintext = Data("Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".utf8)
+        let expectedCiphertext = Data([0xD3, 0x1A, 0x8D, 0x34, 0x64, 0x8E, 0x60, 0xDB, 0x7B, 0x86, 0xAF, 0xBC, 0x53, 0xEF, 0x7E, 0xC2, 0xA4, 0xAD, 0xED, 0x51, 0x29, 0x6E, 0x08, 0xFE, 0xA9, 0xE2, 0xB5, 0xA7, 0x36, 0xEE, 0x62, 0xD6, 0x3D, 0xBE, 0xA4, 0x5E, 0x8C, 0xA9, 0x67, 0x12, 0x82, 0xFA, 0xFB, 0x69, 0xDA, 0x92, 0x72, 0x8B, 0x1A, 0x71, 0xDE, 0x0A, 0x9E, 0x06, 0x0B, 0x29, 0x05, 0xD6, 0xA5, 0xB6, 0x7E, 0xCD, 0x3B, 0x36, 0x92, 0xDD, 0xBD, 0x7F, 0x2D, 0x77, 0x8B, 0x8C, 0x98, 0x03, 0xAE, 0xE3, 0x28, 0x09, 0x1B, 0x58, 0xFA, 0xB3, 0x24, 0xE4, 0xFA, 0xD6, 0x75, 0x94, 0x55, 0x85, 0x80, 0x8B, 0x48, 0x31, 0xD7, 0xBC, 0x3F, 0xF4, 0xDE, 0xF0, 0x8E, 0x4B, 0x7A, 0x9D, 0xE5, 0x76, 0xD2, 0x65, 0x86, 0xCE, 0xC6, 0x4B, 0x61, 0x16])
+        let expectedTag = Data([0x1A, 0xE1, 0x0B, 0x59, 0x4F, 0x09, 0xE2, 0x6A, 0x7E, 0x90, 0x2E, 0xCB, 0xD0, 0x60, 0x06, 0x91])
+
+        let encryptor = try! ChaCha20Poly1305Encryption(key: key, nonce: nonce, associatedData: associatedData)
+        var ciphertext = plaintext
+        try! encryptor.encrypt(&ciphertext)
+        XCTAssertEqual(ciphertext, expectedCiphertext)
+        XCTAssertEqual(try! encryptor.computeTag(), expectedTag)
+
+        let decryptor = try! ChaCha20Poly1305Decryption(key: key, nonce: nonce, associatedData: associatedData)
+        var decrypted = ciphertext
+        try! decryptor.decrypt(&decrypted)
+        XCTAssertEqual(decrypted, plaintext)
+        XCTAssert(try! decryptor.verifyTag(expectedTag))
+
+        var badTag = expectedTag
+        badTag[0] ^= 1
+        let badDecryptor = try! ChaCha20Poly1305Decryption(key: key, nonce: nonce, associatedData: associatedData)
+        XCTAssertFalse(try! badDecryptor.verifyTag(badTag))
+    }
+
+    func testHkdfWithDigest() {
+        // From RFC 5869, test cases 1 and 4, plus test case 1's inputs with SHA-512.
+        let salt: [UInt8] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C]
+        let info: [UInt8] = [0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9]
+
+        XCTAssertEqual(
+            try! hkdf(digest: .sha1, outputLength: 42, inputKeyMaterial: [UInt8](repeating: 0x0B, count: 11), salt: salt, info: info),
+            [0x08, 0x5A, 0x01, 0xEA, 0x1B, 0x10, 0xF3, 0x69, 0x33, 0x06, 0x8B, 0x56, 0xEF, 0xA5, 0xAD, 0x81, 0xA4, 0xF1, 0x4B, 0x82, 0x2F, 0x5B, 0x09, 0x15, 0x68, 0xA9, 0xCD, 0xD4, 0xF1, 0x55, 0xFD, 0xA2, 0xC2, 0x2E, 0x42, 0x24, 0x78, 0xD3, 0x05, 0xF3, 0xF8, 0x96]
+        )
+
+        let ikm = [UInt8](repeating: 0x0B, count: 22)
+        XCTAssertEqual(
+            try! hkdf(digest: .sha256, outputLength: 42, inputKeyMaterial: ikm, salt: salt, info: info),
+            [0x3C, 0xB2, 0x5F, 0x25, 0xFA, 0xAC, 0xD5, 0x7A, 0x90, 0x43, 0x4F, 0x64, 0xD0, 0x36, 0x2F, 0x2A, 0x2D, 0x2D, 0x0A, 0x90, 0xCF, 0x1A, 0x5A, 0x4C, 0x5D, 0xB0, 0x2D, 0x56, 0xEC, 0xC4, 0xC5, 0xBF, 0x34, 0x00, 0x72, 0x08, 0xD5, 0xB8, 0x87, 0x18, 0x58, 0x65]
+        )
+        XCTAssertEqual(
+            try! hkdf(digest: .sha512, outputLength: 42, inputKeyMaterial: ikm, salt: salt, info: info),
+            [0x83, 0x23, 0x90, 0x08, 0x6C, 0xDA, 0x71, 0xFB, 0x47, 0x62, 0x5B, 0xB5, 0xCE, 0xB1, 0x68, 0xE4, 0xC8, 0xE2, 0x6A, 0x1A, 0x16, 0xED, 0x34, 0xD9, 0xFC, 0x7F, 0xE9, 0x2C, 0x14, 0x81, 0x57, 0x93, 0x38, 0xDA, 0x36, 0x2C, 0xB8, 0xD9, 0xF9, 0x25, 0xD7, 0xCB]
+        )
+
+        XCTAssertThrowsError(try hkdf(digest: .sha1, outputLength: 255 * 20 + 1, inputKeyMaterial: ikm, salt: [UInt8](), info: [UInt8]())) {
+            guard case SignalError.invalidArgument(_) = $0 else {
+                XCTFail("wrong error: \($0)")
+                return
+            }
+        }
+    }
 }